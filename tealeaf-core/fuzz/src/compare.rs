@@ -0,0 +1,94 @@
+//! Value comparisons shared by the round-trip targets.
+
+use tealeaf::Value;
+
+/// Deep equality for Values. Handles NaN (via to_bits) and compares
+/// objects by key set (not insertion order, which schemas may reorder).
+pub fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Null, Value::Null) => true,
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::Int(a), Value::Int(b)) => a == b,
+        (Value::UInt(a), Value::UInt(b)) => a == b,
+        (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+        (Value::String(a), Value::String(b)) => a == b,
+        (Value::Bytes(a), Value::Bytes(b)) => a == b,
+        (Value::Timestamp(a, a_tz), Value::Timestamp(b, b_tz)) => a == b && a_tz == b_tz,
+        (Value::Ref(a), Value::Ref(b)) => a == b,
+        (Value::Tagged(ta, va), Value::Tagged(tb, vb)) => ta == tb && values_equal(va, vb),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| values_equal(x, y))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter().all(|(k, v)| b.get(k).is_some_and(|bv| values_equal(v, bv)))
+        }
+        (Value::Map(a), Value::Map(b)) => {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|((ak, av), (bk, bv))| {
+                    values_equal(ak, bk) && values_equal(av, bv)
+                })
+        }
+        // Int/UInt coercion: writer may encode a positive Int as UInt or vice versa
+        (Value::Int(a), Value::UInt(b)) => *a >= 0 && *a as u64 == *b,
+        (Value::UInt(a), Value::Int(b)) => *b >= 0 && *a == *b as u64,
+        // JsonNumber coercion: may roundtrip as Int/UInt if it fits
+        (Value::JsonNumber(a), Value::JsonNumber(b)) => a == b,
+        (Value::JsonNumber(s), Value::Int(i)) => s.parse::<i64>().ok() == Some(*i),
+        (Value::Int(i), Value::JsonNumber(s)) => s.parse::<i64>().ok() == Some(*i),
+        (Value::JsonNumber(s), Value::UInt(u)) => s.parse::<u64>().ok() == Some(*u),
+        (Value::UInt(u), Value::JsonNumber(s)) => s.parse::<u64>().ok() == Some(*u),
+        _ => false,
+    }
+}
+
+/// Like values_equal but also accepts Float ↔ Int/UInt coercion for whole-number floats.
+/// Used to validate compact_floats roundtrips where re-parsing produces Int for values like 42.0.
+pub fn values_numeric_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Null, Value::Null) => true,
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::Int(a), Value::Int(b)) => a == b,
+        (Value::UInt(a), Value::UInt(b)) => a == b,
+        (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+        (Value::String(a), Value::String(b)) => a == b,
+        (Value::Bytes(a), Value::Bytes(b)) => a == b,
+        (Value::Timestamp(a, a_tz), Value::Timestamp(b, b_tz)) => a == b && a_tz == b_tz,
+        (Value::Ref(a), Value::Ref(b)) => a == b,
+        (Value::Tagged(ta, va), Value::Tagged(tb, vb)) => ta == tb && values_numeric_equal(va, vb),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(x, y)| values_numeric_equal(x, y))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter().all(|(k, v)| b.get(k).is_some_and(|bv| values_numeric_equal(v, bv)))
+        }
+        (Value::Map(a), Value::Map(b)) => {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|((ak, av), (bk, bv))| {
+                    values_numeric_equal(ak, bk) && values_numeric_equal(av, bv)
+                })
+        }
+        (Value::Int(a), Value::UInt(b)) => *a >= 0 && *a as u64 == *b,
+        (Value::UInt(a), Value::Int(b)) => *b >= 0 && *a == *b as u64,
+        // Float ↔ Int/UInt coercion: compact_floats strips .0, re-parsing produces Int
+        (Value::Float(f), Value::Int(i)) => {
+            f.is_finite() && *f == f.trunc() && *f == *i as f64
+        }
+        (Value::Int(i), Value::Float(f)) => {
+            f.is_finite() && *f == f.trunc() && *f == *i as f64
+        }
+        (Value::Float(f), Value::UInt(u)) => {
+            f.is_finite() && *f >= 0.0 && *f == f.trunc() && *f == *u as f64
+        }
+        (Value::UInt(u), Value::Float(f)) => {
+            f.is_finite() && *f >= 0.0 && *f == f.trunc() && *f == *u as f64
+        }
+        (Value::JsonNumber(a), Value::JsonNumber(b)) => a == b,
+        (Value::JsonNumber(s), Value::Int(i)) => s.parse::<i64>().ok() == Some(*i),
+        (Value::Int(i), Value::JsonNumber(s)) => s.parse::<i64>().ok() == Some(*i),
+        (Value::JsonNumber(s), Value::UInt(u)) => s.parse::<u64>().ok() == Some(*u),
+        (Value::UInt(u), Value::JsonNumber(s)) => s.parse::<u64>().ok() == Some(*u),
+        _ => false,
+    }
+}
@@ -0,0 +1,70 @@
+//! JSON bridge: import the input as JSON and check that exporting and
+//! re-importing it keeps the values.
+
+use crate::compare::values_equal;
+
+pub fn run(data: &[u8]) {
+    let Ok(data) = std::str::from_utf8(data) else { return };
+
+    // Import JSON
+    let tl = match tealeaf::TeaLeaf::from_json(data) {
+        Ok(tl) => tl,
+        Err(_) => return,
+    };
+
+    // Export back to JSON
+    let json_out = match tl.to_json() {
+        Ok(j) => j,
+        Err(_) => {
+            panic!("to_json() failed on successfully imported JSON");
+        }
+    };
+
+    // Re-import the exported JSON
+    let reparsed = match tealeaf::TeaLeaf::from_json(&json_out) {
+        Ok(r) => r,
+        Err(_) => {
+            panic!("Re-import of to_json() output failed");
+        }
+    };
+
+    // Invariant: key count must match
+    assert_eq!(
+        tl.data.len(), reparsed.data.len(),
+        "json roundtrip key count mismatch: {} vs {}",
+        tl.data.len(), reparsed.data.len(),
+    );
+
+    // Invariant: every original value must survive the roundtrip
+    for (key, orig_val) in &tl.data {
+        match reparsed.data.get(key) {
+            Some(re_val) => {
+                assert!(
+                    values_equal(orig_val, re_val),
+                    "json roundtrip value mismatch for key '{}'", key,
+                );
+            }
+            None => {
+                panic!("json roundtrip lost key '{}'", key);
+            }
+        }
+    }
+
+    // Invariant: is_root_array must survive the roundtrip.
+    // Compare against the original input to catch from_json misclassifying a root array.
+    let input_is_array = data.trim_start().starts_with('[');
+    let export_is_array = json_out.trim_start().starts_with('[');
+    assert_eq!(
+        input_is_array, export_is_array,
+        "is_root_array mismatch: input={}, export={}",
+        input_is_array, export_is_array,
+    );
+
+    // Re-export must also be identical (catches drift in the second roundtrip)
+    let json_out_2 = reparsed.to_json()
+        .expect("second to_json() failed on roundtripped data");
+    assert_eq!(
+        json_out, json_out_2,
+        "json roundtrip serialization mismatch",
+    );
+}
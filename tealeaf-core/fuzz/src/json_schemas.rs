@@ -0,0 +1,138 @@
+//! Schema inference: import the input as JSON with inferred schemas and
+//! check that the text written with them parses back to the same values,
+//! schemas, unions and root-array flag.
+
+use tealeaf::FormatOptions;
+use crate::compare::{values_equal, values_numeric_equal};
+
+pub fn run(data: &[u8]) {
+    let Ok(data) = std::str::from_utf8(data) else { return };
+
+    // Fuzz JSON import with schema inference
+    let tl = match tealeaf::TeaLeaf::from_json_with_schemas(data) {
+        Ok(tl) => tl,
+        Err(_) => return,
+    };
+
+    // Exercise JSON serialization — must not panic
+    let _ = tl.to_json();
+
+    // Serialize to TL text with inferred schemas
+    let tl_text = tl.to_tl_with_schemas();
+
+    // Re-parse the emitted TL text — failure here means the serializer
+    // produced output that the parser rejects, which is a bug.
+    let reparsed = match tealeaf::TeaLeaf::parse(&tl_text) {
+        Ok(r) => r,
+        Err(_) => {
+            panic!("Re-parse of to_tl_with_schemas output failed (from_json_with_schemas path)");
+        }
+    };
+
+    // Invariant: roundtrip must preserve all keys and values.
+    // Schemas may reorder fields within objects, so compare by key set.
+    assert_eq!(
+        tl.data.len(),
+        reparsed.data.len(),
+        "json-schema roundtrip key count mismatch: {} vs {}",
+        tl.data.len(),
+        reparsed.data.len(),
+    );
+    for (key, orig_val) in &tl.data {
+        match reparsed.data.get(key) {
+            Some(re_val) => {
+                assert!(
+                    values_equal(orig_val, re_val),
+                    "json-schema roundtrip value mismatch for key '{}'",
+                    key,
+                );
+            }
+            None => {
+                panic!("json-schema roundtrip lost key '{}'", key);
+            }
+        }
+    }
+
+    // ---- Compact text with schemas roundtrip ----
+    let compact_text = tl.to_tl_with_options(&FormatOptions::compact());
+    let compact_reparsed = match tealeaf::TeaLeaf::parse(&compact_text) {
+        Ok(r) => r,
+        Err(_) => {
+            panic!("Re-parse of compact to_tl_with_options output failed (from_json_with_schemas path)");
+        }
+    };
+    assert_eq!(
+        tl.data.len(),
+        compact_reparsed.data.len(),
+        "compact json-schema roundtrip key count mismatch",
+    );
+    for (key, orig_val) in &tl.data {
+        match compact_reparsed.data.get(key) {
+            Some(re_val) => {
+                assert!(
+                    values_equal(orig_val, re_val),
+                    "compact json-schema roundtrip value mismatch for key '{}'", key,
+                );
+            }
+            None => {
+                panic!("compact json-schema roundtrip lost key '{}'", key);
+            }
+        }
+    }
+
+    // ---- Compact floats text with schemas roundtrip ----
+    let cf_text = tl.to_tl_with_options(&FormatOptions::compact().with_compact_floats());
+    let cf_reparsed = match tealeaf::TeaLeaf::parse(&cf_text) {
+        Ok(r) => r,
+        Err(_) => {
+            panic!("Re-parse of compact_floats to_tl_with_options output failed (from_json_with_schemas path)");
+        }
+    };
+    assert_eq!(
+        tl.data.len(),
+        cf_reparsed.data.len(),
+        "compact_floats json-schema roundtrip key count mismatch",
+    );
+    for (key, orig_val) in &tl.data {
+        match cf_reparsed.data.get(key) {
+            Some(re_val) => {
+                assert!(
+                    values_numeric_equal(orig_val, re_val),
+                    "compact_floats json-schema roundtrip value mismatch for key '{}'", key,
+                );
+            }
+            None => {
+                panic!("compact_floats json-schema roundtrip lost key '{}'", key);
+            }
+        }
+    }
+
+    // Invariant: schema count must survive TL text roundtrip.
+    // If the serializer emits @struct definitions, the parser must re-parse them.
+    assert_eq!(
+        tl.schemas.len(),
+        reparsed.schemas.len(),
+        "json-schema roundtrip schema count mismatch: {} vs {}",
+        tl.schemas.len(),
+        reparsed.schemas.len(),
+    );
+
+    // Invariant: union count must survive TL text roundtrip.
+    assert_eq!(
+        tl.unions.len(),
+        reparsed.unions.len(),
+        "json-schema roundtrip union count mismatch: {} vs {}",
+        tl.unions.len(),
+        reparsed.unions.len(),
+    );
+
+    // Invariant: @root-array directive must survive TL text roundtrip.
+    // Check by re-emitting to TL text and comparing the directive presence.
+    let reemitted = reparsed.to_tl_with_schemas();
+    let has_root_array_orig = tl_text.contains("@root-array");
+    let has_root_array_rt = reemitted.contains("@root-array");
+    assert_eq!(
+        has_root_array_orig, has_root_array_rt,
+        "json-schema roundtrip @root-array directive mismatch",
+    );
+}
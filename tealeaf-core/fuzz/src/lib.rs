@@ -0,0 +1,200 @@
+//! Fuzz targets for tealeaf, as a library.
+//!
+//! Each target module has a `run(data)` function that returns normally for
+//! any input the library handles correctly, and panics when an input breaks
+//! one of the target's invariants. The `cargo fuzz` binaries in
+//! `fuzz_targets/` are thin wrappers around them.
+//!
+//! [`replay`] runs stored inputs through the targets outside libFuzzer: crash
+//! artifacts, a corpus, or the regression inputs in `regressions/`. CI can
+//! use it to check that fixed crashes stay fixed without a nightly toolchain.
+//!
+//! ```no_run
+//! let replay = tealeaf_fuzz::replay("fuzz/artifacts").unwrap();
+//! for failure in &replay.failures {
+//!     eprintln!("{}: {}: {}", failure.target.name(), failure.path.display(), failure.message);
+//! }
+//! assert!(replay.passed());
+//! ```
+
+use std::fmt;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+mod compare;
+
+pub mod get_path;
+pub mod json;
+pub mod json_schemas;
+pub mod parse;
+pub mod reader;
+pub mod roundtrip;
+pub mod serialize;
+pub mod structured;
+
+/// A fuzz target, named after its `cargo fuzz` binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    Parse,
+    Reader,
+    Json,
+    Roundtrip,
+    Serialize,
+    JsonSchemas,
+    Structured,
+    GetPath,
+}
+
+impl Target {
+    pub const ALL: [Target; 8] = [
+        Target::Parse,
+        Target::Reader,
+        Target::Json,
+        Target::Roundtrip,
+        Target::Serialize,
+        Target::JsonSchemas,
+        Target::Structured,
+        Target::GetPath,
+    ];
+
+    /// The binary name, such as `fuzz_parse`. It is also the name of the
+    /// target's directory under `corpus/` and `artifacts/`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Target::Parse => "fuzz_parse",
+            Target::Reader => "fuzz_reader",
+            Target::Json => "fuzz_json",
+            Target::Roundtrip => "fuzz_roundtrip",
+            Target::Serialize => "fuzz_serialize",
+            Target::JsonSchemas => "fuzz_json_schemas",
+            Target::Structured => "fuzz_structured",
+            Target::GetPath => "fuzz_get_path",
+        }
+    }
+
+    /// The target named `name`, with or without the `fuzz_` prefix.
+    pub fn from_name(name: &str) -> Option<Target> {
+        let name = name.strip_prefix("fuzz_").unwrap_or(name);
+        Target::ALL.into_iter().find(|t| &t.name()["fuzz_".len()..] == name)
+    }
+
+    /// Run the target on one input. Panics if the input breaks one of the
+    /// target's invariants.
+    pub fn run(self, data: &[u8]) {
+        match self {
+            Target::Parse => parse::run(data),
+            Target::Reader => reader::run(data),
+            Target::Json => json::run(data),
+            Target::Roundtrip => roundtrip::run(data),
+            Target::Serialize => serialize::run(data),
+            Target::JsonSchemas => json_schemas::run(data),
+            Target::Structured => structured::run(data),
+            Target::GetPath => get_path::run(data),
+        }
+    }
+
+    /// Run the target on one input, returning the panic message if it
+    /// panics.
+    pub fn check(self, data: &[u8]) -> Result<(), String> {
+        panic::catch_unwind(AssertUnwindSafe(|| self.run(data))).map_err(|payload| {
+            match payload.downcast::<String>() {
+                Ok(message) => *message,
+                Err(payload) => match payload.downcast::<&str>() {
+                    Ok(message) => message.to_string(),
+                    Err(_) => "panicked".to_string(),
+                },
+            }
+        })
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An input a target panicked on.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub target: Target,
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// The outcome of [`replay`].
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    /// Inputs run
+    pub inputs: usize,
+    pub failures: Vec<Failure>,
+}
+
+impl Replay {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Run the stored inputs at `path` through the targets they belong to.
+///
+/// `path` is a file or a directory of them, searched recursively. Each
+/// file is run by the target named by the nearest enclosing directory with
+/// a target's name, as in `artifacts/fuzz_parse/crash-…` or
+/// `corpus/fuzz_reader/…`. Hidden files, such as `.gitkeep`, are skipped.
+/// Files are run in name order.
+///
+/// Fails if a file cannot be read or no directory names its target.
+pub fn replay<P: AsRef<Path>>(path: P) -> io::Result<Replay> {
+    let mut replay = Replay::default();
+    replay_into(path.as_ref(), None, &mut replay)?;
+    Ok(replay)
+}
+
+/// Like [`replay`], but run every file at `path` through `target`,
+/// whatever the directories are named.
+pub fn replay_target<P: AsRef<Path>>(target: Target, path: P) -> io::Result<Replay> {
+    let mut replay = Replay::default();
+    replay_into(path.as_ref(), Some(target), &mut replay)?;
+    Ok(replay)
+}
+
+fn replay_into(path: &Path, target: Option<Target>, replay: &mut Replay) -> io::Result<()> {
+    if path.is_dir() {
+        let mut entries = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        for entry in entries {
+            let hidden = entry.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('.'));
+            if !hidden {
+                replay_into(&entry, target, replay)?;
+            }
+        }
+        return Ok(());
+    }
+    let target = match target.or_else(|| target_of(path)) {
+        Some(target) => target,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no directory above '{}' names a fuzz target", path.display()),
+            ));
+        }
+    };
+    let data = std::fs::read(path)?;
+    replay.inputs += 1;
+    if let Err(message) = target.check(&data) {
+        replay.failures.push(Failure { target, path: path.to_path_buf(), message });
+    }
+    Ok(())
+}
+
+/// The target named by the nearest directory above `path`.
+fn target_of(path: &Path) -> Option<Target> {
+    path.ancestors()
+        .skip(1)
+        .filter_map(|dir| dir.file_name()?.to_str())
+        .find_map(|name| Target::ALL.into_iter().find(|t| t.name() == name))
+}
@@ -0,0 +1,99 @@
+//! Text parsing: parse the input, then check that `dumps` writes text that
+//! parses back to the same values, in the pretty, compact and compact-floats
+//! layouts.
+
+use tealeaf::FormatOptions;
+use crate::compare::{values_equal, values_numeric_equal};
+
+pub fn run(data: &[u8]) {
+    let Ok(data) = std::str::from_utf8(data) else { return };
+
+    // Parse fuzzer input
+    let tl = match tealeaf::TeaLeaf::parse(data) {
+        Ok(tl) => tl,
+        Err(_) => return,
+    };
+
+    // Serialize back to text and re-parse
+    let serialized = tealeaf::dumps(&tl.data);
+    let reparsed = match tealeaf::TeaLeaf::parse(&serialized) {
+        Ok(r) => r,
+        Err(_) => {
+            panic!("Re-parse of dumps() output failed");
+        }
+    };
+
+    // Invariant: key count must match
+    assert_eq!(
+        tl.data.len(), reparsed.data.len(),
+        "parse roundtrip key count mismatch: {} vs {}",
+        tl.data.len(), reparsed.data.len(),
+    );
+
+    // Invariant: every original value must survive the roundtrip
+    for (key, orig_val) in &tl.data {
+        match reparsed.data.get(key) {
+            Some(re_val) => {
+                assert!(
+                    values_equal(orig_val, re_val),
+                    "parse roundtrip value mismatch for key '{}'", key,
+                );
+            }
+            None => {
+                panic!("parse roundtrip lost key '{}'", key);
+            }
+        }
+    }
+
+    // ---- Compact text roundtrip ----
+    let compact = tealeaf::dumps_with_options(&tl.data, &FormatOptions::compact());
+    let compact_reparsed = match tealeaf::TeaLeaf::parse(&compact) {
+        Ok(r) => r,
+        Err(_) => {
+            panic!("Re-parse of compact dumps output failed");
+        }
+    };
+    assert_eq!(
+        tl.data.len(), compact_reparsed.data.len(),
+        "compact parse roundtrip key count mismatch",
+    );
+    for (key, orig_val) in &tl.data {
+        match compact_reparsed.data.get(key) {
+            Some(re_val) => {
+                assert!(
+                    values_equal(orig_val, re_val),
+                    "compact parse roundtrip value mismatch for key '{}'", key,
+                );
+            }
+            None => {
+                panic!("compact parse roundtrip lost key '{}'", key);
+            }
+        }
+    }
+
+    // ---- Compact floats text roundtrip ----
+    let cf = tealeaf::dumps_with_options(&tl.data, &FormatOptions::compact().with_compact_floats());
+    let cf_reparsed = match tealeaf::TeaLeaf::parse(&cf) {
+        Ok(r) => r,
+        Err(_) => {
+            panic!("Re-parse of compact_floats dumps output failed");
+        }
+    };
+    assert_eq!(
+        tl.data.len(), cf_reparsed.data.len(),
+        "compact_floats parse roundtrip key count mismatch",
+    );
+    for (key, orig_val) in &tl.data {
+        match cf_reparsed.data.get(key) {
+            Some(re_val) => {
+                assert!(
+                    values_numeric_equal(orig_val, re_val),
+                    "compact_floats parse roundtrip value mismatch for key '{}'", key,
+                );
+            }
+            None => {
+                panic!("compact_floats parse roundtrip lost key '{}'", key);
+            }
+        }
+    }
+}
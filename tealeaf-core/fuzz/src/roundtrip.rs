@@ -0,0 +1,72 @@
+//! Binary round-trip: parse the input as text, compile it with and without
+//! compression, and check the reader returns the same values.
+
+use crate::compare::values_equal;
+
+fn compile_read_and_check(tl: &tealeaf::TeaLeaf, path: &std::path::Path, compress: bool) {
+    if tl.compile(path, compress).is_err() {
+        return;
+    }
+    let bytes = match std::fs::read(path) {
+        Ok(b) => b,
+        Err(_) => return,
+    };
+    let reader = match tealeaf::Reader::from_bytes(bytes) {
+        Ok(r) => r,
+        Err(_) => {
+            // Writer produced output that Reader rejects — that's a bug
+            panic!("Reader failed to load writer-produced binary (compress={})", compress);
+        }
+    };
+
+    // Invariant: every key the reader returns must match the original value
+    let reader_keys = reader.keys();
+    for key in &reader_keys {
+        let reader_val = match reader.get(key) {
+            Ok(v) => v,
+            Err(_) => {
+                panic!("Reader.get failed for key '{}' in writer-produced binary", key);
+            }
+        };
+        if let Some(original) = tl.get(key) {
+            assert!(
+                values_equal(original, &reader_val),
+                "binary roundtrip value mismatch for key '{}' (compress={})",
+                key, compress,
+            );
+        } else {
+            panic!(
+                "reader returned key '{}' not in original (compress={})",
+                key, compress,
+            );
+        }
+    }
+
+    // Reverse invariant: every original key must exist in reader output
+    // (catches writer bugs that silently drop sections)
+    for key in tl.data.keys() {
+        if !reader_keys.iter().any(|k| k == key) {
+            panic!(
+                "binary roundtrip dropped key '{}' (compress={})",
+                key, compress,
+            );
+        }
+    }
+}
+
+pub fn run(data: &[u8]) {
+    let Ok(data) = std::str::from_utf8(data) else { return };
+    let tl = match tealeaf::TeaLeaf::parse(data) {
+        Ok(tl) => tl,
+        Err(_) => return,
+    };
+
+    let tmp = match tempfile::NamedTempFile::new() {
+        Ok(t) => t,
+        Err(_) => return,
+    };
+    let path = tmp.path().to_path_buf();
+
+    compile_read_and_check(&tl, &path, false);
+    compile_read_and_check(&tl, &path, true);
+}
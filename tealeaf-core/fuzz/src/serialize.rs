@@ -0,0 +1,114 @@
+//! Serialization: parse the input as text, write it as JSON, and check that
+//! the text written with schemas parses back to the same values in each
+//! layout.
+
+use tealeaf::FormatOptions;
+use crate::compare::{values_equal, values_numeric_equal};
+
+pub fn run(data: &[u8]) {
+    let Ok(data) = std::str::from_utf8(data) else { return };
+
+    // Parse text format
+    let tl = match tealeaf::TeaLeaf::parse(data) {
+        Ok(tl) => tl,
+        Err(_) => return,
+    };
+
+    // Serialize to JSON — must not panic
+    let _ = tl.to_json();
+    let _ = tl.to_json_compact();
+
+    // Serialize to TL text and re-parse
+    let tl_text = tl.to_tl_with_schemas();
+    let original = match tealeaf::loads(data) {
+        Ok(d) => d,
+        Err(_) => return,
+    };
+    let reparsed = match tealeaf::loads(&tl_text) {
+        Ok(d) => d,
+        Err(_) => {
+            panic!("Re-parse of to_tl_with_schemas output failed");
+        }
+    };
+
+    // Invariant: text roundtrip must preserve all keys and values
+    assert_eq!(
+        original.len(),
+        reparsed.len(),
+        "text roundtrip key count mismatch: {} vs {}",
+        original.len(),
+        reparsed.len(),
+    );
+    for (key, orig_val) in &original {
+        match reparsed.get(key) {
+            Some(re_val) => {
+                assert!(
+                    values_equal(orig_val, re_val),
+                    "text roundtrip value mismatch for key '{}'",
+                    key,
+                );
+            }
+            None => {
+                panic!("text roundtrip lost key '{}'", key);
+            }
+        }
+    }
+
+    // ---- Compact text roundtrip ----
+    let compact_text = tl.to_tl_with_options(&FormatOptions::compact());
+    let compact_reparsed = match tealeaf::loads(&compact_text) {
+        Ok(d) => d,
+        Err(_) => {
+            panic!("Re-parse of compact to_tl_with_options output failed");
+        }
+    };
+    assert_eq!(
+        original.len(),
+        compact_reparsed.len(),
+        "compact text roundtrip key count mismatch",
+    );
+    for (key, orig_val) in &original {
+        match compact_reparsed.get(key) {
+            Some(re_val) => {
+                assert!(
+                    values_equal(orig_val, re_val),
+                    "compact text roundtrip value mismatch for key '{}'",
+                    key,
+                );
+            }
+            None => {
+                panic!("compact text roundtrip lost key '{}'", key);
+            }
+        }
+    }
+
+    // ---- Compact floats text roundtrip ----
+    // compact_floats strips .0 from whole-number floats, so re-parsing may
+    // produce Int instead of Float. Use values_numeric_equal for comparison.
+    let cf_text = tl.to_tl_with_options(&FormatOptions::compact().with_compact_floats());
+    let cf_reparsed = match tealeaf::loads(&cf_text) {
+        Ok(d) => d,
+        Err(_) => {
+            panic!("Re-parse of compact_floats to_tl_with_options output failed");
+        }
+    };
+    assert_eq!(
+        original.len(),
+        cf_reparsed.len(),
+        "compact_floats text roundtrip key count mismatch",
+    );
+    for (key, orig_val) in &original {
+        match cf_reparsed.get(key) {
+            Some(re_val) => {
+                assert!(
+                    values_numeric_equal(orig_val, re_val),
+                    "compact_floats text roundtrip value mismatch for key '{}'",
+                    key,
+                );
+            }
+            None => {
+                panic!("compact_floats text roundtrip lost key '{}'", key);
+            }
+        }
+    }
+}
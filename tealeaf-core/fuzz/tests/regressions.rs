@@ -0,0 +1,51 @@
+//! Replays the stored regression inputs
+//!
+//! Each file in `regressions/<target>/` is an input that once crashed the
+//! target. They must all pass now.
+
+use std::path::PathBuf;
+
+use tealeaf_fuzz::{replay, replay_target, Target};
+
+fn regressions_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("regressions")
+}
+
+#[test]
+fn test_regressions_pass() {
+    let replay = replay(regressions_dir()).unwrap();
+    assert!(replay.inputs >= 13, "only {} inputs found", replay.inputs);
+    for failure in &replay.failures {
+        eprintln!("{}: {}: {}", failure.target, failure.path.display(), failure.message);
+    }
+    assert!(replay.passed());
+}
+
+#[test]
+fn test_replay_targets() {
+    let dir = tempfile::tempdir().unwrap();
+    let inputs = dir.path().join("artifacts").join("fuzz_parse");
+    std::fs::create_dir_all(&inputs).unwrap();
+    std::fs::write(inputs.join("crash-1"), "a: [1, 2, {b: 3}]").unwrap();
+    std::fs::write(inputs.join(".gitkeep"), "").unwrap();
+
+    // Target from the directory name, for a file or the directories above it
+    assert_eq!(replay(inputs.join("crash-1")).unwrap().inputs, 1);
+    assert_eq!(replay(dir.path()).unwrap().inputs, 1);
+
+    // No directory names a target
+    let stray = dir.path().join("stray");
+    std::fs::write(&stray, "a: 1").unwrap();
+    assert!(replay(&stray).is_err());
+    let all = replay_target(Target::Serialize, dir.path()).unwrap();
+    assert_eq!(all.inputs, 2);
+    assert!(all.passed());
+
+    assert_eq!(Target::from_name("fuzz_json_schemas"), Some(Target::JsonSchemas));
+    assert_eq!(Target::from_name("get_path"), Some(Target::GetPath));
+    assert_eq!(Target::from_name("fuzz_nothing"), None);
+    for target in Target::ALL {
+        assert_eq!(Target::from_name(target.name()), Some(target));
+        assert_eq!(target.check(b""), Ok(()));
+    }
+}
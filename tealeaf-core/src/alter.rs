@@ -0,0 +1,189 @@
+//! Column-level alterations of schema-bound tables.
+
+use std::collections::HashSet;
+
+use crate::{BoundObjects, Error, Field, FieldType, ObjectMap, Result, TeaLeaf, Value, object_matches_schema, resolve_schema};
+
+/// One change to the columns of a table, applied by [`TeaLeaf::alter_table`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterOp {
+    /// Append a column, set to `default` in existing rows.
+    AddColumn { name: String, field_type: FieldType, default: Value },
+    DropColumn(String),
+    RenameColumn { old: String, new: String },
+    /// Put the columns in this order, which must name each exactly once.
+    Reorder(Vec<String>),
+}
+
+/// Where a column of the altered schema takes its values from.
+enum Source {
+    Column(String),
+    Default(Value),
+}
+
+impl TeaLeaf {
+    /// Apply `ops` in order to the columns of the table under `key`.
+    ///
+    /// The table's schema is the one the text writer binds it to: its
+    /// explicit binding, its singularized key name, or the schema matching the
+    /// first row's fields. The schema changes, and so does every object bound
+    /// to it: the table's rows and any other value of that type in the
+    /// document. Each object is rebuilt once, however many ops there are.
+    ///
+    /// Returns an error, leaving the document unchanged, if `key` is not a
+    /// table with a schema, or an op names a missing column or adds or
+    /// renames to an existing one.
+    pub fn alter_table(&mut self, key: &str, ops: &[AlterOp]) -> Result<()> {
+        let rows = match self.data.get(key) {
+            Some(Value::Array(rows)) => rows,
+            Some(_) => return Err(Error::ParseError(format!("'{}' is not a table", key))),
+            None => return Err(Error::MissingField(key.to_string())),
+        };
+        let schema = resolve_schema(&self.schemas, self.binding(key), Some(key)).or_else(|| match rows.first() {
+            Some(Value::Object(first)) => {
+                let keys: HashSet<&str> = first.keys().map(|k| k.as_str()).collect();
+                self.schemas.values().find(|s| object_matches_schema(&keys, s))
+            }
+            _ => None,
+        }).ok_or_else(|| Error::UnknownStruct(format!("no schema for table '{}'", key)))?;
+
+        let mut columns: Vec<(Field, Source)> = schema.fields.iter()
+            .map(|f| (f.clone(), Source::Column(f.name.clone())))
+            .collect();
+        let position = |columns: &[(Field, Source)], name: &str| {
+            columns.iter().position(|(f, _)| f.name == name)
+                .ok_or_else(|| Error::MissingField(format!("{}.{}", schema.name, name)))
+        };
+        let taken = |columns: &[(Field, Source)], name: &str| {
+            if columns.iter().any(|(f, _)| f.name == name) {
+                Err(Error::ParseError(format!("column '{}' already exists in '{}'", name, schema.name)))
+            } else {
+                Ok(())
+            }
+        };
+        for op in ops {
+            match op {
+                AlterOp::AddColumn { name, field_type, default } => {
+                    taken(&columns, name)?;
+                    columns.push((Field::new(name.clone(), field_type.clone()), Source::Default(default.clone())));
+                }
+                AlterOp::DropColumn(name) => {
+                    let i = position(&columns, name)?;
+                    columns.remove(i);
+                }
+                AlterOp::RenameColumn { old, new } => {
+                    let i = position(&columns, old)?;
+                    if old != new {
+                        taken(&columns, new)?;
+                    }
+                    columns[i].0.name = new.clone();
+                }
+                AlterOp::Reorder(order) => {
+                    let distinct: HashSet<&str> = order.iter().map(|s| s.as_str()).collect();
+                    if distinct.len() != order.len() || order.len() != columns.len() {
+                        return Err(Error::ParseError(format!(
+                            "reorder of '{}' must name each of its {} columns once", schema.name, columns.len()
+                        )));
+                    }
+                    let mut reordered = Vec::with_capacity(columns.len());
+                    for name in order {
+                        let i = position(&columns, name)?;
+                        reordered.push(columns.swap_remove(i));
+                    }
+                    columns = reordered;
+                }
+            }
+        }
+
+        let declared: HashSet<&str> = schema.fields.iter().map(|f| f.name.as_str()).collect();
+        let edit = |obj: &mut ObjectMap<String, Value>| {
+            let mut old = std::mem::take(obj);
+            let mut new = ObjectMap::with_capacity(columns.len());
+            for (field, source) in &columns {
+                let value = match source {
+                    Source::Column(name) => old.shift_remove(name),
+                    Source::Default(value) => Some(value.clone()),
+                };
+                if let Some(value) = value {
+                    new.insert(field.name.clone(), value);
+                }
+            }
+            // Keep keys the schema never declared; the rest were dropped
+            for (k, v) in old {
+                if !declared.contains(k.as_str()) && !new.contains_key(&k) {
+                    new.insert(k, v);
+                }
+            }
+            *obj = new;
+        };
+        let name = schema.name.clone();
+        let alter = BoundObjects { schemas: &self.schemas, target: &name, edit: &edit };
+        for (k, value) in self.data.iter_mut() {
+            alter.value(value, self.bindings.get(k).map(String::as_str), Some(k));
+        }
+        self.schemas[&name].fields = columns.into_iter().map(|(f, _)| f).collect();
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"
+        @struct user (id: int, name: string, email: string?)
+        users: @table user [
+            (1, alice, "a@x.io"),
+            (2, bob, ~),
+        ]
+        owner: {id: 3, name: carol}
+    "#;
+
+    fn keys(value: &Value) -> Vec<&str> {
+        value.as_object().unwrap().keys().map(|k| k.as_str()).collect()
+    }
+
+    #[test]
+    fn test_alter_table_columns() {
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        doc.alter_table("users", &[
+            AlterOp::AddColumn { name: "active".into(), field_type: FieldType::new("bool"), default: Value::Bool(true) },
+            AlterOp::DropColumn("email".into()),
+            AlterOp::RenameColumn { old: "name".into(), new: "login".into() },
+            AlterOp::Reorder(vec!["login".into(), "id".into(), "active".into()]),
+        ]).unwrap();
+
+        let fields: Vec<&str> = doc.schema("user").unwrap().fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(fields, vec!["login", "id", "active"]);
+        let users = doc.get("users").unwrap().as_array().unwrap();
+        assert_eq!(keys(&users[1]), vec!["login", "id", "active"]);
+        assert_eq!(users[1].get("login").unwrap().as_str(), Some("bob"));
+        assert_eq!(users[0].get("active").unwrap().as_bool(), Some(true));
+        // Other objects of the same type follow the schema
+        assert_eq!(keys(doc.get("owner").unwrap()), vec!["login", "id", "active"]);
+
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("(alice, 1, true)"), "{}", text);
+        assert_eq!(TeaLeaf::parse(&text).unwrap().data, doc.data);
+    }
+
+    #[test]
+    fn test_alter_table_errors_leave_document_unchanged() {
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        let before = doc.data.clone();
+        let err = |doc: &mut TeaLeaf, ops: &[AlterOp]| doc.alter_table("users", ops).unwrap_err();
+
+        assert!(matches!(err(&mut doc, &[AlterOp::DropColumn("age".into())]), Error::MissingField(_)));
+        err(&mut doc, &[
+            AlterOp::DropColumn("email".into()),
+            AlterOp::RenameColumn { old: "id".into(), new: "name".into() },
+        ]);
+        err(&mut doc, &[AlterOp::Reorder(vec!["id".into(), "id".into(), "name".into()])]);
+        err(&mut doc, &[AlterOp::AddColumn { name: "id".into(), field_type: FieldType::new("int"), default: Value::Int(0) }]);
+        assert_eq!(doc.data, before);
+        assert_eq!(doc.schema("user").unwrap().fields.len(), 3);
+
+        assert!(matches!(doc.alter_table("owner", &[]), Err(Error::ParseError(_))));
+        assert!(matches!(doc.alter_table("nope", &[]), Err(Error::MissingField(_))));
+    }
+}
@@ -0,0 +1,681 @@
+//! Archives of parsed documents that reopen without decoding.
+//!
+//! The binary format (`.tlbx`) is for interchange: it is compact, but
+//! reading a section decodes it into [`Value`]s. An archive is a cache: the
+//! value tree is laid out as linked nodes that [`ArchivedValue`] reads in
+//! place, so [`Archive::open`] only maps the file and checks its header, and
+//! looking up `users[4000].name` touches a handful of nodes however large
+//! the document is. Archives are not meant to be exchanged between versions
+//! of this crate; rebuild them from the source document instead.
+//!
+//! ```no_run
+//! use tealeaf::{archive::Archive, TeaLeaf};
+//!
+//! TeaLeaf::load("big.tl")?.save_archive("big.tla")?;
+//!
+//! // Later runs
+//! let archive = Archive::open("big.tla")?;
+//! let name = archive.get("users").and_then(|u| u.index(4000)).and_then(|u| u.get("name"));
+//! println!("{:?}", name.and_then(|n| n.as_str()));
+//! # Ok::<(), tealeaf::Error>(())
+//! ```
+//!
+//! An archive is a 32-byte header followed by nodes, each written before
+//! any node that refers to it:
+//!
+//! ```text
+//! "TLAR" | major: u16 | minor: u16 | flags: u32 | data: u64 | meta: u64 | reserved: u32
+//! node = kind: u8 | payload
+//! ```
+//!
+//! `data` is the offset of the object holding the document's sections and
+//! `meta` that of a node describing its schemas, unions and bindings.
+//! Strings, arrays and objects hold the offsets (u64) of their elements, so
+//! any element is reached without reading its siblings. Equal strings are
+//! stored once. Secrets are stored with their contents, like in binary
+//! files. All integers are little-endian.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::{Error, Field, FieldType, IndexMap, ObjectMap, Result, Schema, TeaLeaf, TimePrecision, Union, Value, Variant};
+
+/// Magic bytes at the start of an archive.
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"TLAR";
+/// Archive layout version written by [`TeaLeaf::to_archive_bytes`].
+pub const ARCHIVE_VERSION: (u16, u16) = (1, 1);
+
+const HEADER_SIZE: usize = 32;
+/// Nesting limit when materializing, matching the binary reader
+const MAX_DEPTH: usize = 256;
+
+const FLAG_ROOT_ARRAY: u32 = 1;
+const FLAG_ROOT_PRIMITIVE: u32 = 1 << 1;
+const FLAG_CRLF: u32 = 1 << 2;
+
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const INT: u8 = 3;
+const UINT: u8 = 4;
+const FLOAT: u8 = 5;
+const STRING: u8 = 6;
+const BYTES: u8 = 7;
+const ARRAY: u8 = 8;
+const OBJECT: u8 = 9;
+const MAP: u8 = 10;
+const REF: u8 = 11;
+const TAGGED: u8 = 12;
+const TIMESTAMP: u8 = 13;
+const JSON_NUMBER: u8 = 14;
+const TIMESTAMP_NANOS: u8 = 15;
+const SECRET_STRING: u8 = 16;
+const SECRET_BYTES: u8 = 17;
+
+impl TeaLeaf {
+    /// The document laid out as an archive. See the [module docs](self).
+    pub fn to_archive_bytes(&self) -> Vec<u8> {
+        let mut out = ArchiveWriter { out: vec![0; HEADER_SIZE], strings: HashMap::new() };
+        let data = out.object(&self.data);
+        let meta = out.meta(self);
+
+        let mut flags = 0;
+        if self.is_root_array { flags |= FLAG_ROOT_ARRAY; }
+        if self.is_root_primitive { flags |= FLAG_ROOT_PRIMITIVE; }
+        if self.crlf { flags |= FLAG_CRLF; }
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(&ARCHIVE_MAGIC);
+        header.extend_from_slice(&ARCHIVE_VERSION.0.to_le_bytes());
+        header.extend_from_slice(&ARCHIVE_VERSION.1.to_le_bytes());
+        header.extend_from_slice(&flags.to_le_bytes());
+        header.extend_from_slice(&data.to_le_bytes());
+        header.extend_from_slice(&meta.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        out.out[..HEADER_SIZE].copy_from_slice(&header);
+        out.out
+    }
+
+    /// Write the document as an archive, to be reopened with [`Archive::open`].
+    pub fn save_archive<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_archive_bytes())?;
+        Ok(())
+    }
+}
+
+struct ArchiveWriter {
+    out: Vec<u8>,
+    strings: HashMap<String, u64>,
+}
+
+impl ArchiveWriter {
+    fn start(&mut self, kind: u8) -> u64 {
+        let offset = self.out.len() as u64;
+        self.out.push(kind);
+        offset
+    }
+
+    fn text(&mut self, kind: u8, s: &[u8]) -> u64 {
+        let offset = self.start(kind);
+        self.out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        self.out.extend_from_slice(s);
+        offset
+    }
+
+    fn string(&mut self, s: &str) -> u64 {
+        if let Some(&offset) = self.strings.get(s) {
+            return offset;
+        }
+        let offset = self.text(STRING, s.as_bytes());
+        self.strings.insert(s.to_string(), offset);
+        offset
+    }
+
+    /// Write `entries` as `kind` with `count` followed by the offsets.
+    fn list(&mut self, kind: u8, count: usize, offsets: &[u64]) -> u64 {
+        let offset = self.start(kind);
+        self.out.extend_from_slice(&(count as u32).to_le_bytes());
+        for child in offsets {
+            self.out.extend_from_slice(&child.to_le_bytes());
+        }
+        offset
+    }
+
+    fn object(&mut self, obj: &ObjectMap<String, Value>) -> u64 {
+        let offsets: Vec<u64> = obj.iter().flat_map(|(k, v)| [self.string(k), self.value(v)]).collect();
+        self.list(OBJECT, obj.len(), &offsets)
+    }
+
+    fn value(&mut self, value: &Value) -> u64 {
+        match value {
+            Value::Null => self.start(NULL),
+            Value::Bool(b) => self.start(if *b { TRUE } else { FALSE }),
+            Value::Int(i) => {
+                let offset = self.start(INT);
+                self.out.extend_from_slice(&i.to_le_bytes());
+                offset
+            }
+            Value::UInt(u) => {
+                let offset = self.start(UINT);
+                self.out.extend_from_slice(&u.to_le_bytes());
+                offset
+            }
+            Value::Float(f) => {
+                let offset = self.start(FLOAT);
+                self.out.extend_from_slice(&f.to_le_bytes());
+                offset
+            }
+            Value::String(s) => self.string(s),
+            Value::Bytes(b) => self.text(BYTES, b),
+            Value::Array(items) => {
+                let offsets: Vec<u64> = items.iter().map(|v| self.value(v)).collect();
+                self.list(ARRAY, items.len(), &offsets)
+            }
+            Value::Object(obj) => self.object(obj),
+            Value::Map(pairs) => {
+                let offsets: Vec<u64> = pairs.iter().flat_map(|(k, v)| [self.value(k), self.value(v)]).collect();
+                self.list(MAP, pairs.len(), &offsets)
+            }
+            Value::Ref(r) => self.text(REF, r.as_bytes()),
+            Value::Tagged(tag, inner) => {
+                let offsets = [self.string(tag), self.value(inner)];
+                let offset = self.start(TAGGED);
+                self.out.extend_from_slice(&offsets[0].to_le_bytes());
+                self.out.extend_from_slice(&offsets[1].to_le_bytes());
+                offset
+            }
+            Value::Timestamp(ts, tz) => {
+                let offset = self.start(TIMESTAMP);
+                self.out.extend_from_slice(&ts.to_le_bytes());
+                self.out.extend_from_slice(&tz.to_le_bytes());
+                offset
+            }
+            Value::JsonNumber(n) => self.text(JSON_NUMBER, n.as_bytes()),
+            Value::TimestampNanos(ns, tz, precision) => {
+                let offset = self.start(TIMESTAMP_NANOS);
+                self.out.extend_from_slice(&ns.to_le_bytes());
+                self.out.extend_from_slice(&tz.to_le_bytes());
+                self.out.push(precision.fraction_digits() as u8);
+                offset
+            }
+            // Stored as they are, never shared with equal strings
+            Value::Secret(s) => match s.expose_str() {
+                Some(text) => self.text(SECRET_STRING, text.as_bytes()),
+                None => self.text(SECRET_BYTES, s.expose_bytes()),
+            },
+        }
+    }
+
+    /// Schemas, unions and bindings as
+    /// `[[name, [field, type, ...]]...], [[name, [[variant, [field, type, ...]]...]]...], {key: schema}`.
+    fn meta(&mut self, doc: &TeaLeaf) -> u64 {
+        let fields = |fields: &[Field]| Value::Array(fields.iter()
+            .flat_map(|f| [Value::String(f.name.clone()), Value::String(f.field_type.to_string())])
+            .collect());
+        let schemas = doc.schemas.values()
+            .map(|s| Value::Array(vec![Value::String(s.name.clone()), fields(&s.fields)]))
+            .collect();
+        let unions = doc.unions.values()
+            .map(|u| Value::Array(vec![
+                Value::String(u.name.clone()),
+                Value::Array(u.variants.iter()
+                    .map(|v| Value::Array(vec![Value::String(v.name.clone()), fields(&v.fields)]))
+                    .collect()),
+            ]))
+            .collect();
+        let bindings = doc.bindings.iter().map(|(k, v)| (k.clone(), Value::String(v.clone()))).collect();
+        self.value(&Value::Array(vec![Value::Array(schemas), Value::Array(unions), Value::Object(bindings)]))
+    }
+}
+
+enum Storage {
+    Owned(Vec<u8>),
+    Mapped(Mmap),
+}
+
+/// An archived document, read in place. See the [module docs](self).
+pub struct Archive {
+    data: Storage,
+    flags: u32,
+    root: usize,
+    meta: usize,
+}
+
+impl Archive {
+    /// Map the archive at `path` into memory. Only the header is read.
+    ///
+    /// # Safety
+    /// The file must not be modified while the archive is open, as with
+    /// [`Reader::open_mmap`](crate::Reader::open_mmap).
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        Self::new(Storage::Mapped(mmap))
+    }
+
+    /// Read an archive from its bytes.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        Self::new(Storage::Owned(data))
+    }
+
+    fn new(data: Storage) -> Result<Self> {
+        let bytes = match &data {
+            Storage::Owned(v) => v.as_slice(),
+            Storage::Mapped(m) => m.as_ref(),
+        };
+        if bytes.len() < HEADER_SIZE || bytes[0..4] != ARCHIVE_MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+        let major = u16::from_le_bytes([bytes[4], bytes[5]]);
+        let minor = u16::from_le_bytes([bytes[6], bytes[7]]);
+        if (major, minor) != ARCHIVE_VERSION {
+            return Err(Error::InvalidVersion { major, minor });
+        }
+        let flags = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+        let offset = |at: usize| {
+            let offset = u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+            usize::try_from(offset).ok().filter(|&o| o >= HEADER_SIZE && o < bytes.len()).ok_or_else(corrupt)
+        };
+        let (root, meta) = (offset(12)?, offset(20)?);
+        Ok(Self { data, flags, root, meta })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match &self.data {
+            Storage::Owned(v) => v.as_slice(),
+            Storage::Mapped(m) => m.as_ref(),
+        }
+    }
+
+    /// The object holding the document's sections.
+    pub fn root(&self) -> ArchivedValue<'_> {
+        ArchivedValue { bytes: self.bytes(), pos: self.root }
+    }
+
+    /// Top-level section `key`.
+    pub fn get(&self, key: &str) -> Option<ArchivedValue<'_>> {
+        self.root().get(key)
+    }
+
+    /// Rebuild the whole document, schemas included.
+    pub fn document(&self) -> Result<TeaLeaf> {
+        let data = match self.root().to_value()? {
+            Value::Object(data) => data,
+            _ => return Err(corrupt()),
+        };
+        let meta = ArchivedValue { bytes: self.bytes(), pos: self.meta }.to_value()?;
+        let (schemas, unions, bindings) = decode_meta(meta).ok_or_else(corrupt)?;
+        Ok(TeaLeaf {
+            schemas,
+            unions,
+            data,
+            is_root_array: self.flags & FLAG_ROOT_ARRAY != 0,
+            is_root_primitive: self.flags & FLAG_ROOT_PRIMITIVE != 0,
+            crlf: self.flags & FLAG_CRLF != 0,
+            bindings,
+        })
+    }
+}
+
+type Meta = (IndexMap<String, Schema>, IndexMap<String, Union>, IndexMap<String, String>);
+
+fn decode_meta(meta: Value) -> Option<Meta> {
+    fn text(v: &Value) -> Option<String> {
+        v.as_str().map(str::to_string)
+    }
+    fn fields(v: &Value) -> Option<Vec<Field>> {
+        v.as_array()?.chunks(2)
+            .map(|pair| match pair {
+                [name, ty] => Some(Field::new(text(name)?, FieldType::parse(ty.as_str()?))),
+                _ => None,
+            })
+            .collect()
+    }
+    fn named(v: &Value) -> Option<(String, &Value)> {
+        match v.as_array()? {
+            [name, body] => Some((text(name)?, body)),
+            _ => None,
+        }
+    }
+    let parts = match meta {
+        Value::Array(parts) => parts,
+        _ => return None,
+    };
+    let (schemas, unions, bindings) = match &parts[..] {
+        [s, u, b] => (s, u, b),
+        _ => return None,
+    };
+
+    let mut schema_map = IndexMap::new();
+    for entry in schemas.as_array()? {
+        let (name, body) = named(entry)?;
+        schema_map.insert(name.clone(), Schema { name, fields: fields(body)? });
+    }
+    let mut union_map = IndexMap::new();
+    for entry in unions.as_array()? {
+        let (name, body) = named(entry)?;
+        let variants = body.as_array()?.iter()
+            .map(|v| named(v).and_then(|(name, body)| Some(Variant { name, fields: fields(body)? })))
+            .collect::<Option<Vec<_>>>()?;
+        union_map.insert(name.clone(), Union { name, variants });
+    }
+    let binding_map = bindings.as_object()?.iter()
+        .map(|(k, v)| Some((k.clone(), text(v)?)))
+        .collect::<Option<_>>()?;
+    Some((schema_map, union_map, binding_map))
+}
+
+/// A value inside an [`Archive`], read on demand.
+///
+/// Accessors return `None` for the wrong kind of value, and also if the
+/// archive is damaged; [`to_value`](Self::to_value) reports damage as an
+/// error.
+#[derive(Clone, Copy)]
+pub struct ArchivedValue<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ArchivedValue<'a> {
+    fn kind(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn array<const N: usize>(&self, at: usize) -> Option<[u8; N]> {
+        let start = self.pos.checked_add(1 + at)?;
+        self.bytes.get(start..start.checked_add(N)?)?.try_into().ok()
+    }
+
+    fn u32_at(&self, at: usize) -> Option<usize> {
+        self.array::<4>(at).map(|b| u32::from_le_bytes(b) as usize)
+    }
+
+    fn u64_at(&self, at: usize) -> Option<u64> {
+        self.array::<8>(at).map(u64::from_le_bytes)
+    }
+
+    /// The node at the offset stored at `at`. Nodes only refer back to
+    /// earlier nodes, so a damaged archive cannot form a cycle.
+    fn child(&self, at: usize) -> Option<ArchivedValue<'a>> {
+        let pos = usize::try_from(self.u64_at(at)?).ok().filter(|&p| p >= HEADER_SIZE && p < self.pos)?;
+        Some(ArchivedValue { bytes: self.bytes, pos })
+    }
+
+    /// Payload of a length-prefixed node.
+    fn payload(&self) -> Option<&'a [u8]> {
+        let len = self.u32_at(0)?;
+        let start = self.pos + 5;
+        self.bytes.get(start..start.checked_add(len)?)
+    }
+
+    fn payload_str(&self, kind: u8) -> Option<&'a str> {
+        if self.kind()? != kind {
+            return None;
+        }
+        std::str::from_utf8(self.payload()?).ok()
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.kind() == Some(NULL)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.kind()? {
+            TRUE => Some(true),
+            FALSE => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self.kind()? {
+            INT => self.array(0).map(i64::from_le_bytes),
+            UINT => i64::try_from(u64::from_le_bytes(self.array(0)?)).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_uint(&self) -> Option<u64> {
+        match self.kind()? {
+            UINT => self.array(0).map(u64::from_le_bytes),
+            INT => u64::try_from(i64::from_le_bytes(self.array(0)?)).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self.kind()? {
+            FLOAT => self.array(0).map(f64::from_le_bytes),
+            INT => self.as_int().map(|i| i as f64),
+            UINT => self.as_uint().map(|u| u as f64),
+            _ => None,
+        }
+    }
+
+    /// A string, borrowed from the archive.
+    pub fn as_str(&self) -> Option<&'a str> {
+        self.payload_str(STRING)
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        if self.kind()? != BYTES {
+            return None;
+        }
+        self.payload()
+    }
+
+    /// Number of elements of an array, or entries of an object or map.
+    pub fn len(&self) -> Option<usize> {
+        match self.kind()? {
+            ARRAY | OBJECT | MAP => self.u32_at(0),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|n| n == 0)
+    }
+
+    /// Array element `index`.
+    pub fn index(&self, index: usize) -> Option<ArchivedValue<'a>> {
+        if self.kind()? != ARRAY || index >= self.u32_at(0)? {
+            return None;
+        }
+        self.child(4 + index.checked_mul(8)?)
+    }
+
+    /// Object field `key`, found by comparing keys in order.
+    pub fn get(&self, key: &str) -> Option<ArchivedValue<'a>> {
+        if self.kind()? != OBJECT {
+            return None;
+        }
+        self.entries().find(|(k, _)| k.payload() == Some(key.as_bytes())).map(|(_, v)| v)
+    }
+
+    /// Elements of an array, in order; empty for other values.
+    pub fn iter(&self) -> impl Iterator<Item = ArchivedValue<'a>> + 'a {
+        let this = *self;
+        let count = if self.kind() == Some(ARRAY) { self.len().unwrap_or(0) } else { 0 };
+        (0..count).map_while(move |i| this.child(4 + i * 8))
+    }
+
+    /// Key-value pairs of an object or map, in order; empty for other
+    /// values. Object keys are strings.
+    pub fn entries(&self) -> impl Iterator<Item = (ArchivedValue<'a>, ArchivedValue<'a>)> + 'a {
+        let this = *self;
+        let count = if matches!(self.kind(), Some(OBJECT | MAP)) { self.len().unwrap_or(0) } else { 0 };
+        (0..count).map_while(move |i| Some((this.child(4 + i * 16)?, this.child(12 + i * 16)?)))
+    }
+
+    /// Copy this value and everything under it out of the archive.
+    pub fn to_value(&self) -> Result<Value> {
+        self.decode(0).ok_or_else(corrupt)
+    }
+
+    fn decode(&self, depth: usize) -> Option<Value> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let timestamp = || Some((i64::from_le_bytes(self.array(0)?), i16::from_le_bytes(self.array(8)?)));
+        Some(match self.kind()? {
+            NULL => Value::Null,
+            FALSE => Value::Bool(false),
+            TRUE => Value::Bool(true),
+            INT => Value::Int(i64::from_le_bytes(self.array(0)?)),
+            UINT => Value::UInt(u64::from_le_bytes(self.array(0)?)),
+            FLOAT => Value::Float(f64::from_le_bytes(self.array(0)?)),
+            STRING => Value::String(self.as_str()?.to_string()),
+            BYTES => Value::Bytes(self.as_bytes()?.to_vec()),
+            SECRET_STRING => Value::Secret(self.payload_str(SECRET_STRING)?.into()),
+            SECRET_BYTES => Value::Secret(self.payload()?.to_vec().into()),
+            ARRAY => {
+                let count = self.len()?;
+                let items: Vec<Value> = self.iter().map(|v| v.decode(depth + 1)).collect::<Option<_>>()?;
+                if items.len() != count {
+                    return None;
+                }
+                Value::Array(items)
+            }
+            OBJECT => {
+                let mut obj = ObjectMap::with_capacity(self.len()?.min(self.bytes.len() / 16));
+                for (k, v) in self.entries() {
+                    obj.insert(k.as_str()?.to_string(), v.decode(depth + 1)?);
+                }
+                if obj.len() != self.len()? {
+                    return None;
+                }
+                Value::Object(obj)
+            }
+            MAP => {
+                let pairs: Vec<(Value, Value)> = self.entries()
+                    .map(|(k, v)| Some((k.decode(depth + 1)?, v.decode(depth + 1)?)))
+                    .collect::<Option<_>>()?;
+                if pairs.len() != self.len()? {
+                    return None;
+                }
+                Value::Map(pairs)
+            }
+            REF => Value::Ref(self.payload_str(REF)?.to_string()),
+            TAGGED => {
+                let tag = self.child(0)?.as_str()?.to_string();
+                Value::Tagged(tag, Box::new(self.child(8)?.decode(depth + 1)?))
+            }
+            TIMESTAMP => {
+                let (ts, tz) = timestamp()?;
+                Value::Timestamp(ts, tz)
+            }
+            JSON_NUMBER => Value::JsonNumber(self.payload_str(JSON_NUMBER)?.to_string()),
+            TIMESTAMP_NANOS => {
+                let (ns, tz) = timestamp()?;
+                let digits = self.array::<1>(10)?[0] as usize;
+                let precision = TimePrecision::from_fraction_digits(digits);
+                if precision.fraction_digits() as usize != digits {
+                    return None;
+                }
+                Value::TimestampNanos(ns, tz, precision)
+            }
+            _ => return None,
+        })
+    }
+}
+
+impl std::fmt::Debug for ArchivedValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.to_value() {
+            Ok(value) => value.fmt(f),
+            Err(_) => write!(f, "<damaged archive node at {}>", self.pos),
+        }
+    }
+}
+
+fn corrupt() -> Error {
+    Error::ParseError("Damaged archive".to_string())
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+@struct user (id: int, name: string, tags: []string?)
+@union shape { circle (r: float), none () }
+@struct login (user: string, password: secret, key: secret)
+vault: @table login [(root, "hunter2", b"c0ffee")]
+users: @table user [(1, alice, [a, b]), (2, bob, ~)]
+config: {port: 8080, big: 18446744073709551615, ratio: 0.5, on: true, off: ~}
+misc: [b"cafe", !users, :ok 1, 2024-01-15T10:30:00Z, 2024-01-15T10:30:00.123456789+02:00]
+lookup: @map {1: one, "k": two}
+"#;
+
+    #[test]
+    fn test_round_trip_and_lookups() {
+        let doc = TeaLeaf::parse(SOURCE).unwrap();
+        let archive = Archive::from_bytes(doc.to_archive_bytes()).unwrap();
+
+        let user = archive.get("users").and_then(|u| u.index(1)).unwrap();
+        assert_eq!(user.get("name").and_then(|n| n.as_str()), Some("bob"));
+        assert_eq!(user.get("id").and_then(|n| n.as_int()), Some(2));
+        assert!(user.get("tags").is_none());
+        assert_eq!(archive.get("users").and_then(|u| u.len()), Some(2));
+        assert!(archive.get("users").unwrap().index(2).is_none());
+
+        let config = archive.get("config").unwrap();
+        assert_eq!(config.get("big").and_then(|v| v.as_uint()), Some(u64::MAX));
+        assert_eq!(config.get("big").and_then(|v| v.as_int()), None);
+        assert_eq!(config.get("ratio").and_then(|v| v.as_float()), Some(0.5));
+        assert_eq!(config.get("on").and_then(|v| v.as_bool()), Some(true));
+        assert!(config.get("off").unwrap().is_null());
+        let keys: Vec<&str> = config.entries().filter_map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["port", "big", "ratio", "on", "off"]);
+        assert_eq!(archive.get("misc").unwrap().iter().next().and_then(|b| b.as_bytes()), Some(&[0xca, 0xfe][..]));
+
+        // Secrets keep their contents
+        let vault = archive.get("vault").and_then(|v| v.index(0)).unwrap();
+        assert!(vault.get("password").unwrap().as_str().is_none());
+        assert_eq!(vault.get("password").unwrap().to_value().unwrap(), doc.get_path("vault[0].password").unwrap().clone());
+
+        let rebuilt = archive.document().unwrap();
+        assert_eq!(rebuilt.data, doc.data);
+        assert_eq!(rebuilt.schemas, doc.schemas);
+        assert_eq!(rebuilt.unions, doc.unions);
+        assert_eq!(rebuilt.to_tl_with_schemas(), doc.to_tl_with_schemas());
+    }
+
+    #[test]
+    fn test_open_file_and_reject_damage() {
+        let doc = TeaLeaf::parse(SOURCE).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.tla");
+        doc.save_archive(&path).unwrap();
+        assert_eq!(Archive::open(&path).unwrap().document().unwrap().data, doc.data);
+
+        let bytes = doc.to_archive_bytes();
+        // Repeated keys and strings are stored once
+        let text = String::from_utf8_lossy(&bytes);
+        assert_eq!(text.matches("alice").count(), 1);
+        assert_eq!(text.matches("name").count(), 1);
+
+        assert!(matches!(Archive::from_bytes(b"TLBX".to_vec()), Err(Error::InvalidMagic)));
+        let mut future = bytes.clone();
+        future[4] = 2;
+        assert!(matches!(Archive::from_bytes(future), Err(Error::InvalidVersion { major: 2, .. })));
+
+        // Any truncation is caught, either at open or on access
+        for len in [HEADER_SIZE, bytes.len() / 2, bytes.len() - 1] {
+            if let Ok(archive) = Archive::from_bytes(bytes[..len].to_vec()) {
+                assert!(archive.document().is_err(), "truncated to {}", len);
+            }
+        }
+        // A node pointing at itself does not loop
+        let mut cyclic = TeaLeaf::parse("a: [1]").unwrap().to_archive_bytes();
+        let root = u64::from_le_bytes(cyclic[12..20].try_into().unwrap());
+        let array = u64::from_le_bytes(cyclic[root as usize + 13..root as usize + 21].try_into().unwrap()) as usize;
+        let own = (array as u64).to_le_bytes();
+        cyclic[array + 5..array + 13].copy_from_slice(&own);
+        let archive = Archive::from_bytes(cyclic).unwrap();
+        assert!(archive.get("a").unwrap().index(0).is_none());
+        assert!(archive.document().is_err());
+    }
+}
@@ -1,9 +1,14 @@
 //! Builder for constructing TeaLeaf documents from multiple DTOs.
 
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+
 use indexmap::IndexMap;
 
 use crate::convert::ToTeaLeaf;
-use crate::{Schema, Union, TeaLeaf, Value};
+use crate::{Error, Result, Schema, Union, TeaLeaf, Value};
 
 /// Builder for constructing TeaLeaf documents from multiple DTOs.
 ///
@@ -21,6 +26,7 @@ pub struct TeaLeafBuilder {
     schemas: IndexMap<String, Schema>,
     unions: IndexMap<String, Union>,
     data: IndexMap<String, Value>,
+    bindings: IndexMap<String, String>,
     is_root_array: bool,
 }
 
@@ -31,22 +37,25 @@ impl TeaLeafBuilder {
             schemas: IndexMap::new(),
             unions: IndexMap::new(),
             data: IndexMap::new(),
+            bindings: IndexMap::new(),
             is_root_array: false,
         }
     }
 
-    /// Add a single DTO under the given key.
+    /// Add a single DTO under the given key, bound to its schema.
     pub fn add<T: ToTeaLeaf>(mut self, key: &str, dto: &T) -> Self {
         self.schemas.extend(T::collect_schemas());
         self.unions.extend(T::collect_unions());
         self.data
             .insert(key.to_string(), dto.to_tealeaf_value());
+        self.bind::<T>(key);
         self
     }
 
     /// Add a raw Value under the given key (no schema collection).
     pub fn add_value(mut self, key: &str, value: Value) -> Self {
         self.data.insert(key.to_string(), value);
+        self.bindings.shift_remove(key);
         self
     }
 
@@ -62,15 +71,28 @@ impl TeaLeafBuilder {
         self
     }
 
-    /// Add a Vec of DTOs as an array under the given key.
+    /// Add a Vec of DTOs as an array under the given key, bound to the
+    /// element schema.
     pub fn add_vec<T: ToTeaLeaf>(mut self, key: &str, items: &[T]) -> Self {
         self.schemas.extend(T::collect_schemas());
         self.unions.extend(T::collect_unions());
         let arr = Value::Array(items.iter().map(|i| i.to_tealeaf_value()).collect());
         self.data.insert(key.to_string(), arr);
+        self.bind::<T>(key);
         self
     }
 
+    /// Bind `key` to the schema of `T`, if it has one, so the text writer
+    /// need not guess it from the key name.
+    fn bind<T: ToTeaLeaf>(&mut self, key: &str) {
+        let base = T::tealeaf_field_type().base;
+        if self.schemas.contains_key(&base) {
+            self.bindings.insert(key.to_string(), base);
+        } else {
+            self.bindings.shift_remove(key);
+        }
+    }
+
     /// Mark the document as a root array (for JSON round-trip fidelity).
     pub fn root_array(mut self) -> Self {
         self.is_root_array = true;
@@ -81,7 +103,8 @@ impl TeaLeafBuilder {
     pub fn build(self) -> TeaLeaf {
         let mut doc = TeaLeaf::new(self.schemas, self.data);
         doc.unions = self.unions;
-        doc.set_root_array(self.is_root_array);
+        doc.bindings = self.bindings;
+        doc.is_root_array = self.is_root_array;
         doc
     }
 }
@@ -92,6 +115,176 @@ impl Default for TeaLeafBuilder {
     }
 }
 
+/// Builder that many threads can add to at once.
+///
+/// Additions go to one of several internally locked shards, picked by the
+/// calling thread, so producers rarely wait on each other. Building merges
+/// the shards deterministically, whatever order the threads ran in:
+///
+/// - sections are ordered by key;
+/// - rows of a table (see [`push_row`](Self::push_row)) are ordered by the
+///   sequence number each was pushed with, which must be unique per table;
+/// - schemas and unions are ordered by name.
+///
+/// A key added more than once, or a schema or union registered with two
+/// different definitions, makes the build fail rather than pick a winner.
+///
+/// # Example
+///
+/// ```ignore
+/// use tealeaf::ConcurrentTeaLeafBuilder;
+///
+/// let builder = ConcurrentTeaLeafBuilder::new();
+/// std::thread::scope(|s| {
+///     for (i, batch) in batches.iter().enumerate() {
+///         let builder = &builder;
+///         s.spawn(move || {
+///             for (j, user) in batch.iter().enumerate() {
+///                 builder.push_row("users", (i * BATCH + j) as u64, user);
+///             }
+///         });
+///     }
+/// });
+/// let doc = builder.build()?;
+/// ```
+pub struct ConcurrentTeaLeafBuilder {
+    shards: Vec<Mutex<Shard>>,
+}
+
+#[derive(Default)]
+struct Shard {
+    schemas: Vec<Schema>,
+    unions: Vec<Union>,
+    sections: Vec<(String, Value)>,
+    rows: HashMap<String, Vec<(u64, Value)>>,
+}
+
+impl ConcurrentTeaLeafBuilder {
+    /// Create a builder with one shard per available CPU.
+    pub fn new() -> Self {
+        Self::with_shards(std::thread::available_parallelism().map_or(4, |n| n.get()))
+    }
+
+    /// Create a builder with `shards` shards (at least one).
+    pub fn with_shards(shards: usize) -> Self {
+        Self { shards: (0..shards.max(1)).map(|_| Mutex::new(Shard::default())).collect() }
+    }
+
+    fn shard(&self) -> MutexGuard<'_, Shard> {
+        let mut hasher = DefaultHasher::new();
+        std::thread::current().id().hash(&mut hasher);
+        let shard = &self.shards[hasher.finish() as usize % self.shards.len()];
+        // A producer that panicked leaves its shard consistent: every addition is a single push
+        shard.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Add a single DTO under the given key.
+    pub fn add<T: ToTeaLeaf>(&self, key: &str, dto: &T) {
+        let value = dto.to_tealeaf_value();
+        let mut shard = self.shard();
+        shard.schemas.extend(T::collect_schemas().into_values());
+        shard.unions.extend(T::collect_unions().into_values());
+        shard.sections.push((key.to_string(), value));
+    }
+
+    /// Add a raw Value under the given key (no schema collection).
+    pub fn add_value(&self, key: &str, value: Value) {
+        self.shard().sections.push((key.to_string(), value));
+    }
+
+    /// Add a schema definition.
+    pub fn add_schema(&self, schema: Schema) {
+        self.shard().schemas.push(schema);
+    }
+
+    /// Add a union definition.
+    pub fn add_union(&self, union: Union) {
+        self.shard().unions.push(union);
+    }
+
+    /// Append a DTO to the table under `table`, at position `seq`.
+    pub fn push_row<T: ToTeaLeaf>(&self, table: &str, seq: u64, row: &T) {
+        let value = row.to_tealeaf_value();
+        let mut shard = self.shard();
+        if !shard.rows.contains_key(table) {
+            shard.schemas.extend(T::collect_schemas().into_values());
+            shard.unions.extend(T::collect_unions().into_values());
+        }
+        shard.rows.entry(table.to_string()).or_default().push((seq, value));
+    }
+
+    /// Append a raw Value to the table under `table`, at position `seq`.
+    pub fn push_value(&self, table: &str, seq: u64, value: Value) {
+        self.shard().rows.entry(table.to_string()).or_default().push((seq, value));
+    }
+
+    /// Merge the shards into a document.
+    pub fn build(self) -> Result<TeaLeaf> {
+        let mut schemas: Vec<Schema> = Vec::new();
+        let mut unions: Vec<Union> = Vec::new();
+        let mut sections: Vec<(String, Value)> = Vec::new();
+        let mut tables: HashMap<String, Vec<(u64, Value)>> = HashMap::new();
+        for shard in self.shards {
+            let shard = shard.into_inner().unwrap_or_else(|e| e.into_inner());
+            schemas.extend(shard.schemas);
+            unions.extend(shard.unions);
+            sections.extend(shard.sections);
+            for (table, rows) in shard.rows {
+                tables.entry(table).or_default().extend(rows);
+            }
+        }
+
+        for (table, mut rows) in tables {
+            rows.sort_by_key(|&(seq, _)| seq);
+            if let Some(pair) = rows.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                return Err(Error::ParseError(format!(
+                    "row sequence number {} pushed twice to '{}'", pair[0].0, table
+                )));
+            }
+            sections.push((table, Value::Array(rows.into_iter().map(|(_, row)| row).collect())));
+        }
+        sections.sort_by(|a, b| a.0.cmp(&b.0));
+        if let Some(pair) = sections.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(Error::ParseError(format!("section '{}' added more than once", pair[0].0)));
+        }
+
+        let mut doc = TeaLeaf::new(merge_definitions(schemas, |s| &s.name)?, sections.into_iter().collect());
+        doc.unions = merge_definitions(unions, |u| &u.name)?;
+        Ok(doc)
+    }
+
+    /// Merge the shards into a binary [`Writer`](crate::Writer), ready to
+    /// write.
+    #[cfg(feature = "binary")]
+    pub fn into_writer(self) -> Result<crate::Writer> {
+        self.build()?.to_writer(&mut crate::Progress::new())
+    }
+}
+
+impl Default for ConcurrentTeaLeafBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Key definitions by name in name order, dropping identical duplicates.
+fn merge_definitions<T: PartialEq>(mut defs: Vec<T>, name: impl Fn(&T) -> &String) -> Result<IndexMap<String, T>> {
+    defs.sort_by(|a, b| name(a).cmp(name(b)));
+    let mut merged: IndexMap<String, T> = IndexMap::new();
+    for def in defs {
+        match merged.get(name(&def)) {
+            Some(existing) if *existing != def => {
+                return Err(Error::ParseError(format!("conflicting definitions of '{}'", name(&def))));
+            }
+            Some(_) => {}
+            None => {
+                merged.insert(name(&def).clone(), def);
+            }
+        }
+    }
+    Ok(merged)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,4 +348,55 @@ mod tests {
         let doc = TeaLeafBuilder::default().build();
         assert!(doc.get("anything").is_none());
     }
+
+    #[test]
+    fn test_concurrent_builder_is_deterministic() {
+        let build = || {
+            let builder = ConcurrentTeaLeafBuilder::with_shards(3);
+            std::thread::scope(|s| {
+                for t in 0..4u64 {
+                    let builder = &builder;
+                    s.spawn(move || {
+                        for i in 0..50u64 {
+                            builder.push_row("numbers", i * 4 + t, &((i * 4 + t) as i64));
+                        }
+                        builder.add_value(&format!("producer{}", t), Value::Int(t as i64));
+                        builder.add_schema(crate::Schema::new("point").field("x", FieldType::new("int")));
+                    });
+                }
+            });
+            builder.add("name", &"run".to_string());
+            builder.build().unwrap()
+        };
+
+        let doc = build();
+        let numbers: Vec<i64> = doc.get("numbers").unwrap().as_array().unwrap()
+            .iter().map(|v| v.as_int().unwrap()).collect();
+        assert_eq!(numbers, (0..200).collect::<Vec<_>>());
+        let keys: Vec<&str> = doc.data.keys().map(|k| k.as_str()).collect();
+        assert_eq!(keys, vec!["name", "numbers", "producer0", "producer1", "producer2", "producer3"]);
+        assert_eq!(doc.schemas.len(), 1);
+        let again = build();
+        assert!(again.data.keys().eq(doc.data.keys()));
+        assert_eq!(again.data, doc.data);
+        assert_eq!(again.schemas, doc.schemas);
+    }
+
+    #[test]
+    fn test_concurrent_builder_conflicts() {
+        let builder = ConcurrentTeaLeafBuilder::with_shards(2);
+        builder.push_value("t", 1, Value::Int(1));
+        builder.push_value("t", 1, Value::Int(2));
+        assert!(builder.build().is_err());
+
+        let builder = ConcurrentTeaLeafBuilder::new();
+        builder.add_value("k", Value::Int(1));
+        builder.push_value("k", 0, Value::Int(2));
+        assert!(builder.build().is_err());
+
+        let builder = ConcurrentTeaLeafBuilder::new();
+        builder.add_schema(crate::Schema::new("p").field("x", FieldType::new("int")));
+        builder.add_schema(crate::Schema::new("p").field("x", FieldType::new("string")));
+        assert!(builder.build().is_err());
+    }
 }
@@ -0,0 +1,314 @@
+//! Bundles of many TeaLeaf files in one archive.
+//!
+//! [`pack`] gathers the `.tl` and `.tlbx` files under a directory into a
+//! [`Bundle`], which saves as a single `.tlb` file. [`Bundle::open`] reads
+//! one back; [`Bundle::file`] opens a bundled binary file as a [`Reader`]
+//! without unpacking anything to disk, and [`unpack`] restores the files.
+//!
+//! A bundle is a header, an index and the file contents, in index order:
+//!
+//! ```text
+//! "TLBN" | major: u16 | minor: u16 | count: u32
+//! count x ( name_len: u32 | name: UTF-8 | size: u64 )
+//! count x contents
+//! ```
+//!
+//! All integers are little-endian. Names are relative paths with `/`
+//! separators, such as `config/service.tlbx`.
+
+use std::io;
+use std::path::{Component, Path};
+
+use crate::{Error, IndexMap, Reader, Result, TeaLeaf, MAGIC};
+
+/// Magic bytes at the start of a bundle.
+pub const BUNDLE_MAGIC: [u8; 4] = *b"TLBN";
+/// Bundle format version written by [`Bundle::to_bytes`].
+pub const BUNDLE_VERSION: (u16, u16) = (1, 0);
+
+const HEADER_SIZE: usize = 12;
+
+/// Named files held together in one archive.
+///
+/// ```no_run
+/// use tealeaf::bundle;
+///
+/// bundle::pack("deploy/")?.save("deploy.tlb")?;
+///
+/// let bundle = tealeaf::Bundle::open("deploy.tlb")?;
+/// let service = bundle.file("config/service.tlbx")?;
+/// let port = service.get("port")?;
+/// # Ok::<(), tealeaf::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bundle {
+    files: IndexMap<String, Vec<u8>>,
+}
+
+impl Bundle {
+    /// An empty bundle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file, replacing any file of the same name. Fails if `name` is
+    /// not a relative path that stays inside the bundle.
+    pub fn insert(&mut self, name: impl Into<String>, contents: Vec<u8>) -> Result<()> {
+        let name = name.into();
+        check_name(&name)?;
+        self.files.insert(name, contents);
+        Ok(())
+    }
+
+    /// Names of the bundled files, in bundle order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Raw contents of a bundled file.
+    pub fn bytes(&self, name: &str) -> Option<&[u8]> {
+        self.files.get(name).map(Vec::as_slice)
+    }
+
+    /// Open a bundled binary file.
+    pub fn file(&self, name: &str) -> Result<Reader> {
+        Reader::from_bytes(self.contents(name)?.to_vec())
+    }
+
+    /// Load a bundled file as a document, whether binary or text.
+    ///
+    /// Text files are parsed on their own, so `@include` directives in them
+    /// cannot be resolved.
+    pub fn document(&self, name: &str) -> Result<TeaLeaf> {
+        let contents = self.contents(name)?;
+        if contents.starts_with(&MAGIC) {
+            return TeaLeaf::from_reader(&Reader::from_bytes(contents.to_vec())?);
+        }
+        #[cfg(feature = "text")]
+        {
+            TeaLeaf::parse(std::str::from_utf8(contents).map_err(|_| Error::InvalidUtf8)?)
+        }
+        #[cfg(not(feature = "text"))]
+        {
+            Err(Error::InvalidMagic)
+        }
+    }
+
+    /// Read a bundle from a `.tlb` file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Read a bundle from its encoded bytes.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE || data[0..4] != BUNDLE_MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+        let major = u16::from_le_bytes([data[4], data[5]]);
+        let minor = u16::from_le_bytes([data[6], data[7]]);
+        if major != BUNDLE_VERSION.0 {
+            return Err(Error::InvalidVersion { major, minor });
+        }
+        let count = u32::from_le_bytes(data[8..12].try_into().unwrap()) as usize;
+
+        let mut cursor = Cursor { data, pos: HEADER_SIZE };
+        let mut index = Vec::new();
+        for _ in 0..count {
+            let name_len = u32::from_le_bytes(cursor.take(4)?.try_into().unwrap()) as usize;
+            let name = std::str::from_utf8(cursor.take(name_len)?).map_err(|_| Error::InvalidUtf8)?;
+            let size = u64::from_le_bytes(cursor.take(8)?.try_into().unwrap());
+            index.push((name, size));
+        }
+
+        let mut bundle = Bundle::new();
+        for (name, size) in index {
+            let size = usize::try_from(size).map_err(|_| truncated())?;
+            let contents = cursor.take(size)?.to_vec();
+            if bundle.files.contains_key(name) {
+                return Err(Error::ParseError(format!("Duplicate file in bundle: {}", name)));
+            }
+            bundle.insert(name, contents)?;
+        }
+        Ok(bundle)
+    }
+
+    /// The bundle's encoded bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&BUNDLE_MAGIC);
+        out.extend_from_slice(&BUNDLE_VERSION.0.to_le_bytes());
+        out.extend_from_slice(&BUNDLE_VERSION.1.to_le_bytes());
+        out.extend_from_slice(&(self.files.len() as u32).to_le_bytes());
+        for (name, contents) in &self.files {
+            out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+        }
+        for contents in self.files.values() {
+            out.extend_from_slice(contents);
+        }
+        out
+    }
+
+    /// Write the bundle to a `.tlb` file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    fn contents(&self, name: &str) -> Result<&[u8]> {
+        self.bytes(name).ok_or_else(|| {
+            Error::Io(io::Error::new(io::ErrorKind::NotFound, format!("No file '{}' in bundle", name)))
+        })
+    }
+}
+
+/// Bundle every `.tl` and `.tlbx` file under `dir`, sorted by name.
+/// Other files are left out.
+pub fn pack<P: AsRef<Path>>(dir: P) -> Result<Bundle> {
+    let dir = dir.as_ref();
+    let mut found = Vec::new();
+    collect(dir, dir, &mut found)?;
+    found.sort();
+    let mut bundle = Bundle::new();
+    for name in found {
+        let contents = std::fs::read(dir.join(&name))?;
+        bundle.insert(name, contents)?;
+    }
+    Ok(bundle)
+}
+
+/// Write every file in the bundle at `path` under `dir`, creating
+/// directories as needed. Returns how many files were written.
+pub fn unpack<P: AsRef<Path>, Q: AsRef<Path>>(path: P, dir: Q) -> Result<usize> {
+    let bundle = Bundle::open(path)?;
+    let dir = dir.as_ref();
+    for (name, contents) in &bundle.files {
+        let target = dir.join(name);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(target, contents)?;
+    }
+    Ok(bundle.len())
+}
+
+fn collect(root: &Path, dir: &Path, found: &mut Vec<String>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect(root, &path, found)?;
+            continue;
+        }
+        let bundled = matches!(path.extension().and_then(|e| e.to_str()), Some("tl" | "tlbx"));
+        if !bundled {
+            continue;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let parts: Option<Vec<&str>> = relative.components().map(|c| c.as_os_str().to_str()).collect();
+        let parts = parts.ok_or(Error::InvalidUtf8)?;
+        found.push(parts.join("/"));
+    }
+    Ok(())
+}
+
+/// Names must be relative and stay inside the directory they unpack to.
+fn check_name(name: &str) -> Result<()> {
+    let escapes = name.is_empty()
+        || name.contains('\\')
+        || name.split('/').any(|part| part.is_empty() || part == "." || part == "..")
+        || Path::new(name).components().any(|c| !matches!(c, Component::Normal(_)));
+    if escapes {
+        return Err(Error::ParseError(format!("Invalid file name in bundle: {:?}", name)));
+    }
+    Ok(())
+}
+
+fn truncated() -> Error {
+    Error::ParseError("Truncated bundle".to_string())
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len()).ok_or_else(truncated)?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    fn sample_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("config")).unwrap();
+        TeaLeaf::parse("port: 8080\nhost: localhost").unwrap()
+            .compile(dir.path().join("config/service.tlbx"), true).unwrap();
+        std::fs::write(dir.path().join("users.tl"), "users: [alice, bob]").unwrap();
+        std::fs::write(dir.path().join("README.md"), "not bundled").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_pack_open_and_unpack() {
+        let dir = sample_dir();
+        let bundle = pack(dir.path()).unwrap();
+        assert_eq!(bundle.names().collect::<Vec<_>>(), ["config/service.tlbx", "users.tl"]);
+
+        let out = tempfile::tempdir().unwrap();
+        let path = out.path().join("deploy.tlb");
+        bundle.save(&path).unwrap();
+        let opened = Bundle::open(&path).unwrap();
+        assert_eq!(opened, bundle);
+
+        let service = opened.file("config/service.tlbx").unwrap();
+        assert_eq!(service.get("port").unwrap().as_int(), Some(8080));
+        let users = opened.document("users.tl").unwrap();
+        assert_eq!(users.get("users").and_then(|v| v.as_array()).map(|a| a.len()), Some(2));
+        assert_eq!(opened.document("config/service.tlbx").unwrap().get("host").and_then(|v| v.as_str()), Some("localhost"));
+        assert!(matches!(opened.file("missing.tlbx"), Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound));
+
+        let restored = out.path().join("restored");
+        assert_eq!(unpack(&path, &restored).unwrap(), 2);
+        for name in ["config/service.tlbx", "users.tl"] {
+            assert_eq!(std::fs::read(restored.join(name)).unwrap(), std::fs::read(dir.path().join(name)).unwrap());
+        }
+        assert!(!restored.join("README.md").exists());
+    }
+
+    #[test]
+    fn test_rejects_bad_bundles() {
+        let mut bundle = Bundle::new();
+        for name in ["../escape.tl", "/etc/passwd", "a//b.tl", "a\\b.tl", ""] {
+            assert!(bundle.insert(name, Vec::new()).is_err(), "{:?}", name);
+        }
+        bundle.insert("a.tl", b"x: 1".to_vec()).unwrap();
+        let bytes = bundle.to_bytes();
+
+        assert!(matches!(Bundle::from_bytes(b"TLBX\x01\0\0\0\0\0\0\0"), Err(Error::InvalidMagic)));
+        assert!(matches!(Bundle::from_bytes(&bytes[..bytes.len() - 1]), Err(Error::ParseError(_))));
+        let mut future = bytes.clone();
+        future[4] = 2;
+        assert!(matches!(Bundle::from_bytes(&future), Err(Error::InvalidVersion { major: 2, .. })));
+
+        // A crafted index cannot smuggle in a path outside the unpack directory
+        let mut evil = Bundle::new();
+        evil.files.insert("../a.tl".into(), Vec::new());
+        assert!(Bundle::from_bytes(&evil.to_bytes()).is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! Custom encodings for binary section data.
+//!
+//! A [`SectionCodec`] transforms the encoded bytes of a section before they
+//! are written and restores them when read, for example to encrypt a section
+//! or to compress a data type better than zlib does. Codecs are registered by
+//! ID on both sides: the writer stores the ID in front of each section it
+//! encodes, and a reader without a codec for that ID fails with
+//! [`Error::MissingCodec`](crate::Error::MissingCodec) when the section is
+//! read. Other sections of the file stay readable.
+//!
+//! A codec runs before compression on write and after decompression on read.
+//!
+//! A codec that encrypts reports the key it uses with
+//! [`SectionCodec::key_id`] and its cipher with [`SectionCodec::algorithm`].
+//! Schema fields set to it with
+//! [`Writer::set_field_key`](crate::Writer::set_field_key) are encrypted
+//! value by value. Each value is encoded on its own, so none of its strings
+//! reach the file's string table, and is stored in the file's encrypted
+//! value table after a header giving the codec ID, key ID and algorithm;
+//! rows hold its position in that table. [`Reader::key_ids`](crate::Reader::key_ids)
+//! lists the keys a file needs without decrypting anything, and
+//! [`rotate_keys`] moves values from one key to another by rewriting that
+//! table alone.
+
+use std::io::Write;
+use std::path::Path;
+
+use crate::{Error, Reader, Result};
+
+/// A reversible transformation of section bytes.
+///
+/// ```
+/// use tealeaf::{Reader, Result, SectionCodec, Value, Writer};
+///
+/// struct Reverse;
+///
+/// impl SectionCodec for Reverse {
+///     fn id(&self) -> u32 { 0x5245_5600 }
+///     fn encode(&self, data: &[u8]) -> Result<Vec<u8>> { Ok(data.iter().rev().copied().collect()) }
+///     fn decode(&self, data: &[u8]) -> Result<Vec<u8>> { Ok(data.iter().rev().copied().collect()) }
+/// }
+///
+/// let dir = tempfile::tempdir()?;
+/// let path = dir.path().join("data.tlbx");
+/// let mut writer = Writer::new();
+/// writer.register_codec(Reverse);
+/// writer.set_section_codec("secret", Reverse.id());
+/// writer.add_section("secret", &Value::String("hidden".into()), None)?;
+/// writer.write(&path, false)?;
+///
+/// let mut reader = Reader::open(&path)?;
+/// assert!(reader.get("secret").is_err());
+/// reader.register_codec(Reverse);
+/// assert_eq!(reader.get("secret")?.as_str(), Some("hidden"));
+/// # Ok::<(), tealeaf::Error>(())
+/// ```
+pub trait SectionCodec: Send + Sync {
+    /// Identifier stored with every section this codec encodes. It must stay
+    /// the same for as long as files written with the codec are read.
+    fn id(&self) -> u32;
+
+    /// Transform the encoded bytes of a section for storage.
+    fn encode(&self, data: &[u8]) -> Result<Vec<u8>>;
+
+    /// Restore bytes produced by [`encode`](Self::encode).
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>>;
+
+    /// The key this codec encrypts with, for codecs that encrypt; `None`
+    /// (the default) for other codecs. Codecs with the same ID and different
+    /// key IDs can be registered side by side. A key ID is at most 255
+    /// bytes and names the key without revealing it.
+    fn key_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// The cipher a keyed codec encrypts with, such as `"AES-256-GCM"`,
+    /// recorded with every value it encrypts; `None` (the default) for
+    /// other codecs. Keyed codecs must name one, in at most 255 bytes.
+    fn algorithm(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Encrypt `data` with the keyed `codec`, after a header naming it: the
+/// codec ID, then the length and bytes of the key ID, then the length and
+/// bytes of the algorithm.
+pub(crate) fn seal(codec: &dyn SectionCodec, data: &[u8]) -> Result<Vec<u8>> {
+    let key_id = codec.key_id()
+        .ok_or_else(|| Error::ParseError(format!("codec {:#x} encrypts fields but has no key ID", codec.id())))?;
+    let algorithm = codec.algorithm()
+        .ok_or_else(|| Error::ParseError(format!("codec {:#x} for key '{}' does not name its algorithm", codec.id(), key_id)))?;
+    let mut sealed = codec.id().to_le_bytes().to_vec();
+    for (what, s) in [("key ID", key_id), ("algorithm", algorithm)] {
+        if s.is_empty() || s.len() > u8::MAX as usize {
+            return Err(Error::ParseError(format!("{} '{}' must be 1 to 255 bytes", what, s)));
+        }
+        sealed.push(s.len() as u8);
+        sealed.extend_from_slice(s.as_bytes());
+    }
+    sealed.extend(codec.encode(data)?);
+    Ok(sealed)
+}
+
+/// The header [`seal`] put in front of an encrypted value.
+pub(crate) struct KeyHeader<'a> {
+    pub(crate) codec: u32,
+    pub(crate) key_id: &'a str,
+    pub(crate) algorithm: &'a str,
+    /// Length of the header; the codec's output follows it
+    pub(crate) len: usize,
+}
+
+/// Read the [`KeyHeader`] starting `data`.
+pub(crate) fn read_key_header(data: &[u8]) -> Result<KeyHeader<'_>> {
+    let truncated = || Error::ParseError("encrypted value header is truncated".into());
+    let codec = u32::from_le_bytes(data.get(..4).ok_or_else(truncated)?.try_into().unwrap());
+    let mut len = 4;
+    let mut text = || -> Result<&str> {
+        let n = *data.get(len).ok_or_else(truncated)? as usize;
+        let bytes = data.get(len + 1..len + 1 + n).ok_or_else(truncated)?;
+        len += 1 + n;
+        std::str::from_utf8(bytes).map_err(|_| Error::ParseError("encrypted value header is not UTF-8".into()))
+    };
+    let key_id = text()?;
+    let algorithm = text()?;
+    Ok(KeyHeader { codec, key_id, algorithm, len })
+}
+
+/// Re-encrypt every field value of the binary file at `path` that `old`
+/// encrypted, with `new`, and return how many values changed.
+///
+/// Only the file's encrypted value table is rewritten: the affected values
+/// are decrypted and encrypted again without being decoded, and the rest
+/// of the file is copied byte for byte. The file is replaced the way
+/// [`Writer::write`](crate::Writer::write) replaces it, holding the same
+/// lock on `<path>.lock` that writers with
+/// [`set_locking`](crate::Writer::set_locking) take, and left untouched if
+/// no value uses `old`. Both codecs must have a key ID, and `new` an
+/// algorithm.
+pub fn rotate_keys<P: AsRef<Path>>(path: P, old: &dyn SectionCodec, new: &dyn SectionCodec) -> Result<usize> {
+    let path = path.as_ref();
+    let _lock = crate::writer::lock_file(path)?;
+    let (bytes, rotated) = Reader::open(path)?.rotate_keys(old, new)?;
+    if rotated > 0 {
+        crate::writer::replace_file(path, |mut file| {
+            file.write_all(&bytes)?;
+            Ok(file)
+        })?;
+    }
+    Ok(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Error, FieldType, ObjectMap, Reader, Schema, Value, Writer};
+
+    struct Xor(u8);
+
+    impl SectionCodec for Xor {
+        fn id(&self) -> u32 {
+            0x584f_5200 | self.0 as u32
+        }
+        fn encode(&self, data: &[u8]) -> Result<Vec<u8>> {
+            Ok(data.iter().map(|b| b ^ self.0).collect())
+        }
+        fn decode(&self, data: &[u8]) -> Result<Vec<u8>> {
+            self.encode(data)
+        }
+    }
+
+    fn table() -> (Schema, Value) {
+        let schema = Schema::new("row").field("id", FieldType::new("int")).field("name", FieldType::new("string"));
+        let rows = (0..40).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("id".to_string(), Value::Int(i));
+            obj.insert("name".to_string(), Value::String(format!("row{}", i % 3)));
+            Value::Object(obj)
+        }).collect();
+        (schema, Value::Array(rows))
+    }
+
+    #[test]
+    fn test_codec_roundtrip() {
+        let (schema, rows) = table();
+        let dir = tempfile::tempdir().unwrap();
+        for compress in [false, true] {
+            let mut w = Writer::new().with_max_section_bytes(128);
+            w.register_codec(Xor(0x5a));
+            w.set_section_codec("rows", Xor(0x5a).id());
+            w.add_section("rows", &rows, Some(&schema)).unwrap();
+            w.add_section("plain", &Value::Int(7), None).unwrap();
+            let path = dir.path().join(format!("codec-{}.tlbx", compress));
+            w.write(&path, compress).unwrap();
+
+            let mut r = Reader::open(&path).unwrap();
+            assert_eq!(r.get("plain").unwrap(), Value::Int(7));
+            match r.get("rows") {
+                Err(Error::MissingCodec { section, codec }) => assert_eq!((section.as_str(), codec), ("rows", 0x584f_525a)),
+                other => panic!("expected MissingCodec, got {:?}", other),
+            }
+            // A codec with another ID does not help
+            r.register_codec(Xor(1));
+            assert!(r.get("rows").is_err());
+            r.register_codec(Xor(0x5a));
+            assert_eq!(r.get("rows").unwrap(), rows);
+        }
+    }
+
+    #[test]
+    fn test_codec_sections_need_version_3() {
+        let mut w = Writer::new();
+        w.register_codec(Xor(7));
+        w.set_section_codec("v", Xor(7).id());
+        w.add_section("v", &Value::Int(7), None).unwrap();
+        let mut bytes = w.to_bytes(false).unwrap();
+        bytes[4..8].copy_from_slice(&[2, 0, 0, 0]);
+        let err = Reader::from_bytes(bytes).err().unwrap();
+        assert!(err.to_string().contains("section 'v' is codec-encoded, which version 2.0 files cannot hold"), "{}", err);
+    }
+
+    /// A keyed XOR "cipher"
+    struct Key(u8, &'static str);
+
+    impl SectionCodec for Key {
+        fn id(&self) -> u32 {
+            0x4b45_5900
+        }
+        fn encode(&self, data: &[u8]) -> Result<Vec<u8>> {
+            Ok(data.iter().map(|b| b ^ self.0).collect())
+        }
+        fn decode(&self, data: &[u8]) -> Result<Vec<u8>> {
+            self.encode(data)
+        }
+        fn key_id(&self) -> Option<&str> {
+            Some(self.1)
+        }
+        fn algorithm(&self) -> Option<&str> {
+            Some("XOR-8")
+        }
+    }
+
+    fn people() -> (Schema, Value) {
+        let schema = Schema::new("person").field("id", FieldType::new("int"))
+            .field("name", FieldType::new("string")).field("ssn", FieldType::new("string").nullable());
+        let rows = (0..40).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("id".to_string(), Value::Int(i));
+            obj.insert("name".to_string(), Value::String(format!("name-{}", i)));
+            obj.insert("ssn".to_string(), if i % 10 == 0 { Value::Null } else { Value::String(format!("ssn-{}", i)) });
+            Value::Object(obj)
+        }).collect();
+        (schema, Value::Array(rows))
+    }
+
+    #[test]
+    fn test_keyed_fields_and_rotation() {
+        let (schema, rows) = people();
+        let dir = tempfile::tempdir().unwrap();
+        for compress in [false, true] {
+            let mut w = Writer::new().with_max_section_bytes(256);
+            w.set_column_stats(true);
+            w.register_codec(Key(1, "2024-q1"));
+            w.register_codec(Key(2, "2024-q2"));
+            w.set_field_key("person", "name", 0x4b45_5900, "2024-q2");
+            w.set_field_key("person", "ssn", 0x4b45_5900, "2024-q1");
+            w.add_section("people", &rows, Some(&schema)).unwrap();
+            w.add_section("plain", &Value::Int(7), None).unwrap();
+            let path = dir.path().join(format!("keyed-{}.tlbx", compress));
+            w.write(&path, compress).unwrap();
+
+            let mut r = Reader::open(&path).unwrap();
+            assert_eq!(r.key_ids(), ["2024-q2", "2024-q1"]);
+            assert_eq!(r.key_algorithm("2024-q1"), Some((0x4b45_5900, "XOR-8")));
+            assert_eq!(r.key_algorithm("2024-q3"), None);
+            assert_eq!(r.encrypted_fields(), [("person", "name"), ("person", "ssn")]);
+            // Encrypted strings stay out of the string table, and their columns get no statistics
+            assert!((0..r.string_count()).all(|i| !r.get_string(i).unwrap().contains("-")));
+            let stats = r.column_stats("people").unwrap().unwrap();
+            assert_eq!((stats[0].distinct, stats[1].distinct, stats[2].distinct, stats[2].null_count), (40, 0, 0, 4));
+            assert_eq!(r.get("plain").unwrap(), Value::Int(7));
+            match r.get("people") {
+                Err(Error::MissingKey { field, key_id, .. }) => assert_eq!((field.as_str(), key_id.as_str()), ("person.name", "2024-q2")),
+                other => panic!("expected MissingKey, got {:?}", other),
+            }
+            r.register_codec(Key(1, "2024-q1"));
+            r.register_codec(Key(2, "2024-q2"));
+            assert_eq!(r.get("people").unwrap(), rows);
+            let plain_before = r.raw_section("plain").unwrap().0;
+
+            // Only the 36 non-null values of the old key
+            assert_eq!(rotate_keys(&path, &Key(1, "2024-q1"), &Key(3, "2024-q3")).unwrap(), 36);
+            assert_eq!(rotate_keys(&path, &Key(1, "2024-q1"), &Key(3, "2024-q3")).unwrap(), 0);
+            let mut r = Reader::open(&path).unwrap();
+            assert_eq!(r.key_ids(), ["2024-q2", "2024-q3"]);
+            r.register_codec(Key(1, "2024-q1"));
+            r.register_codec(Key(2, "2024-q2"));
+            assert!(matches!(r.get("people"), Err(Error::MissingKey { .. })));
+            r.register_codec(Key(3, "2024-q3"));
+            assert_eq!(r.get("people").unwrap(), rows);
+            assert_eq!(r.raw_section("plain").unwrap().0, plain_before);
+
+            // Copied raw, encrypted values stay as stored; new values need a key
+            let mut copy = Writer::from_reader(&r).unwrap();
+            let (raw, raw_schema) = r.raw_section("people").unwrap();
+            copy.add_raw_section("people", raw_schema.unwrap(), &raw).unwrap();
+            let err = copy.add_section("more", &rows, Some(&schema)).unwrap_err();
+            assert!(matches!(err, Error::Unencodable { .. }), "{}", err);
+            let mut r = Reader::from_bytes(copy.to_bytes(compress).unwrap()).unwrap();
+            r.register_codec(Key(2, "2024-q2"));
+            r.register_codec(Key(3, "2024-q3"));
+            assert_eq!(r.get("people").unwrap(), rows);
+        }
+
+        let path = dir.path().join("keyed-false.tlbx");
+        assert!(rotate_keys(&path, &Key(3, "2024-q3"), &Xor(1)).is_err());
+        let mut w = Writer::new();
+        w.register_codec(Key(1, ""));
+        w.set_field_key("person", "ssn", 0x4b45_5900, "");
+        w.add_section("x", &rows, Some(&schema)).unwrap();
+        assert!(w.to_bytes(false).is_err());
+
+        // A keyed codec must name its algorithm
+        struct Unnamed;
+        impl SectionCodec for Unnamed {
+            fn id(&self) -> u32 { 1 }
+            fn encode(&self, data: &[u8]) -> Result<Vec<u8>> { Ok(data.to_vec()) }
+            fn decode(&self, data: &[u8]) -> Result<Vec<u8>> { Ok(data.to_vec()) }
+            fn key_id(&self) -> Option<&str> { Some("k") }
+        }
+        let mut w = Writer::new();
+        w.register_codec(Unnamed);
+        w.set_field_key("person", "ssn", 1, "k");
+        w.add_section("x", &rows, Some(&schema)).unwrap();
+        let err = w.to_bytes(false).unwrap_err();
+        assert!(err.to_string().contains("does not name its algorithm"), "{}", err);
+
+        // Encrypted fields need version 3
+        let mut w = Writer::new();
+        w.register_codec(Key(1, "k"));
+        w.set_field_key("person", "ssn", 0x4b45_5900, "k");
+        w.add_section("x", &rows, Some(&schema)).unwrap();
+        let mut bytes = w.to_bytes(false).unwrap();
+        bytes[4..8].copy_from_slice(&[2, 0, 0, 0]);
+        let err = Reader::from_bytes(bytes).err().unwrap();
+        assert!(err.to_string().contains("is encrypted, which version 2.0"), "{}", err);
+    }
+
+    #[test]
+    fn test_unregistered_codec_fails_write() {
+        let mut w = Writer::new();
+        w.set_section_codec("x", 9);
+        w.add_section("x", &Value::Int(1), None).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("x.tlbx");
+        let err = w.write(&path, false).unwrap_err();
+        assert!(matches!(err, Error::MissingCodec { codec: 9, .. }), "{}", err);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,223 @@
+//! Reading scalars out of loosely typed data.
+//!
+//! Data converted from CSV, spreadsheets or hand-written JSON often holds
+//! `"42"` where an int is meant, or `"yes"` for a bool. The `as_*` accessors
+//! only read a value of the matching kind; the `coerce_*` methods also read
+//! the text and number forms of it, by the rules of a [`Coercion`] mode.
+//! Parsing never depends on the locale: the decimal separator is always `.`
+//! and there are no thousands separators.
+
+use crate::types::canonical_float;
+use crate::Value;
+
+/// How much [`Value::coerce_bool`] and its siblings accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Coercion {
+    /// Only values of the target kind, and strings holding exactly the text
+    /// TeaLeaf writes for one
+    #[default]
+    Strict,
+    /// Also strings with surrounding whitespace and common alternative
+    /// spellings, and numbers that stand for the target without loss
+    Lenient,
+}
+
+impl Value {
+    /// The value as a bool.
+    ///
+    /// Strict reads `Bool` and the strings `true` and `false`. Lenient also
+    /// reads `yes`/`no`, `y`/`n`, `on`/`off`, `t`/`f` and `1`/`0` in any
+    /// case, and the numbers 1 and 0.
+    pub fn coerce_bool(&self, mode: Coercion) -> Option<bool> {
+        match (self, mode) {
+            (Value::Bool(b), _) => Some(*b),
+            (Value::String(s), Coercion::Strict) => match s.as_str() {
+                "true" => Some(true),
+                "false" => Some(false),
+                _ => None,
+            },
+            (Value::String(s), Coercion::Lenient) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "yes" | "y" | "on" | "t" | "1" => Some(true),
+                "false" | "no" | "n" | "off" | "f" | "0" => Some(false),
+                _ => None,
+            },
+            (Value::Int(_) | Value::UInt(_) | Value::JsonNumber(_), Coercion::Lenient) => match self.as_int() {
+                Some(1) => Some(true),
+                Some(0) => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The value as an `i64`.
+    ///
+    /// Strict reads integers that fit, and strings of decimal digits with an
+    /// optional sign. Lenient also reads whole floats in range, floats
+    /// written as strings (`"3.0"`, `"1e3"`), `_` between digits, and bools
+    /// as 1 and 0.
+    pub fn coerce_int(&self, mode: Coercion) -> Option<i64> {
+        match (self, mode) {
+            (Value::Int(_) | Value::UInt(_) | Value::JsonNumber(_), Coercion::Strict) => self.as_int(),
+            (Value::String(s), Coercion::Strict) => s.parse().ok(),
+            (Value::String(s) | Value::JsonNumber(s), Coercion::Lenient) => {
+                let s = lenient_number(s)?;
+                s.parse().ok().or_else(|| whole(s.parse().ok()?))
+            }
+            (Value::Int(_) | Value::UInt(_), Coercion::Lenient) => self.as_int(),
+            (Value::Float(f), Coercion::Lenient) => whole(*f),
+            (Value::Bool(b), Coercion::Lenient) => Some(*b as i64),
+            _ => None,
+        }
+    }
+
+    /// The value as an `f64`.
+    ///
+    /// Strict reads floats, integers that convert exactly, and strings in
+    /// decimal or exponent notation. Lenient also reads integers that round,
+    /// `_` between digits, `nan`, `inf` and `infinity` in any case, and
+    /// bools as 1.0 and 0.0.
+    pub fn coerce_float(&self, mode: Coercion) -> Option<f64> {
+        match (self, mode) {
+            (Value::Float(f), _) => Some(*f),
+            (Value::Int(i), Coercion::Strict) => Some(*i as f64).filter(|f| *f as i128 == *i as i128),
+            (Value::UInt(u), Coercion::Strict) => Some(*u as f64).filter(|f| *f as u128 == *u as u128),
+            (Value::String(s) | Value::JsonNumber(s), Coercion::Strict) => {
+                Some(s).filter(|s| is_decimal(s)).and_then(|s| s.parse().ok())
+            }
+            (Value::String(s) | Value::JsonNumber(s), Coercion::Lenient) => lenient_number(s)?.parse().ok(),
+            (Value::Int(_) | Value::UInt(_), Coercion::Lenient) => self.as_float(),
+            (Value::Bool(b), Coercion::Lenient) => Some(*b as u8 as f64),
+            _ => None,
+        }
+    }
+
+    /// The value as a string.
+    ///
+    /// Both modes read strings, and write numbers and bools as TeaLeaf
+    /// text does. Lenient also trims surrounding whitespace from strings.
+    pub fn coerce_string(&self, mode: Coercion) -> Option<String> {
+        match self {
+            Value::String(s) if mode == Coercion::Lenient => Some(s.trim().to_string()),
+            Value::String(s) | Value::JsonNumber(s) => Some(s.clone()),
+            Value::Int(i) => Some(i.to_string()),
+            Value::UInt(u) => Some(u.to_string()),
+            Value::Float(f) => Some(canonical_float(*f)),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `s` is a number in decimal or exponent notation, rather than one
+/// of the words for infinity and NaN that `str::parse` also reads.
+fn is_decimal(s: &str) -> bool {
+    s.bytes().any(|b| b.is_ascii_digit()) && s.bytes().all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+}
+
+/// `s` trimmed, without `_` between digits. Underscores anywhere else make
+/// it unreadable.
+fn lenient_number(s: &str) -> Option<std::borrow::Cow<'_, str>> {
+    let s = s.trim();
+    if !s.contains('_') {
+        return Some(s.into());
+    }
+    let bytes = s.as_bytes();
+    let separates = |i: usize| i > 0 && i + 1 < bytes.len() && bytes[i - 1].is_ascii_digit() && bytes[i + 1].is_ascii_digit();
+    if (0..bytes.len()).all(|i| bytes[i] != b'_' || separates(i)) {
+        Some(s.replace('_', "").into())
+    } else {
+        None
+    }
+}
+
+/// `f` as an `i64` if it is whole and in range.
+fn whole(f: f64) -> Option<i64> {
+    Some(f).filter(|f| f.fract() == 0.0 && *f >= -9.2e18 && *f <= 9.2e18).map(|f| f as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Coercion::{Lenient, Strict};
+
+    fn s(text: &str) -> Value {
+        Value::String(text.to_string())
+    }
+
+    #[test]
+    fn test_coerce_bool() {
+        for (value, strict, lenient) in [
+            (Value::Bool(true), Some(true), Some(true)),
+            (s("false"), Some(false), Some(false)),
+            (s(" Yes "), None, Some(true)),
+            (s("OFF"), None, Some(false)),
+            (s("0"), None, Some(false)),
+            (Value::Int(1), None, Some(true)),
+            (Value::UInt(2), None, None),
+            (s("maybe"), None, None),
+            (Value::Null, None, None),
+        ] {
+            assert_eq!(value.coerce_bool(Strict), strict, "{:?}", value);
+            assert_eq!(value.coerce_bool(Lenient), lenient, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_coerce_int() {
+        for (value, strict, lenient) in [
+            (Value::Int(-7), Some(-7), Some(-7)),
+            (Value::UInt(u64::MAX), None, None),
+            (s("+42"), Some(42), Some(42)),
+            (s(" 42 "), None, Some(42)),
+            (s("1_000_000"), None, Some(1_000_000)),
+            (s("1__0"), None, None),
+            (s("_1"), None, None),
+            (s("3.0"), None, Some(3)),
+            (s("1e3"), None, Some(1000)),
+            (s("3.5"), None, None),
+            (s("1,000"), None, None),
+            (Value::Float(4.0), None, Some(4)),
+            (Value::Float(f64::NAN), None, None),
+            (Value::Float(1e300), None, None),
+            (Value::Bool(true), None, Some(1)),
+            (Value::JsonNumber("12".into()), Some(12), Some(12)),
+        ] {
+            assert_eq!(value.coerce_int(Strict), strict, "{:?}", value);
+            assert_eq!(value.coerce_int(Lenient), lenient, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_coerce_float() {
+        for (value, strict, lenient) in [
+            (Value::Float(2.5), Some(2.5), Some(2.5)),
+            (Value::Int(3), Some(3.0), Some(3.0)),
+            (Value::Int(i64::MAX), None, Some(i64::MAX as f64)),
+            (Value::UInt(u64::MAX), None, Some(u64::MAX as f64)),
+            (s("-1.5e-3"), Some(-1.5e-3), Some(-1.5e-3)),
+            (s("1,5"), None, None),
+            (s(" 0.25\n"), None, Some(0.25)),
+            (s("1_000.5"), None, Some(1000.5)),
+            (s("Infinity"), None, Some(f64::INFINITY)),
+            (Value::Bool(false), None, Some(0.0)),
+            (s(""), None, None),
+        ] {
+            assert_eq!(value.coerce_float(Strict), strict, "{:?}", value);
+            assert_eq!(value.coerce_float(Lenient), lenient, "{:?}", value);
+        }
+        assert!(s("nan").coerce_float(Lenient).unwrap().is_nan());
+        assert_eq!(s("nan").coerce_float(Strict), None);
+    }
+
+    #[test]
+    fn test_coerce_string() {
+        assert_eq!(s(" a ").coerce_string(Strict).as_deref(), Some(" a "));
+        assert_eq!(s(" a ").coerce_string(Lenient).as_deref(), Some("a"));
+        assert_eq!(Value::Float(1.0).coerce_string(Strict).as_deref(), Some("1.0"));
+        assert_eq!(Value::UInt(9).coerce_string(Strict).as_deref(), Some("9"));
+        assert_eq!(Value::Bool(true).coerce_string(Lenient).as_deref(), Some("true"));
+        assert_eq!(Value::Null.coerce_string(Lenient), None);
+        assert_eq!(Value::Array(vec![]).coerce_string(Lenient), None);
+    }
+}
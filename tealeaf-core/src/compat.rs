@@ -0,0 +1,325 @@
+//! Schema evolution compatibility checks.
+//!
+//! [`Schema::compatible_with`] compares a new version of a schema against an
+//! old one and classifies every difference, using the registry levels familiar
+//! from Kafka/Avro:
+//!
+//! - **Backward**: readers using the new schema can read data written with the old one.
+//! - **Forward**: readers using the old schema can read data written with the new one.
+//! - **Full**: both.
+//!
+//! Absent nullable fields read as null, so adding or removing a nullable field
+//! is always safe; numeric types may widen (`int8` → `int` → `int64`,
+//! `uint8` → `int16`, `float32` → `float`). Integers widen to a float type
+//! only if its mantissa holds them exactly: 32-bit and smaller to `float`,
+//! 16-bit and smaller to `float32`.
+
+use std::fmt;
+
+use crate::{FieldType, Schema, SchemaRegistry};
+
+/// Compatibility level required between consecutive schema versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatibilityLevel {
+    /// New readers can read old data.
+    Backward,
+    /// Old readers can read new data.
+    Forward,
+    /// Both directions.
+    Full,
+    /// Any change is accepted.
+    None,
+}
+
+/// One difference between two versions of a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaChange {
+    FieldAdded { name: String, field_type: FieldType },
+    FieldRemoved { name: String, field_type: FieldType },
+    /// The new type can hold every value of the old type.
+    TypeWidened { name: String, from: FieldType, to: FieldType },
+    /// The old type can hold every value of the new type.
+    TypeNarrowed { name: String, from: FieldType, to: FieldType },
+    /// Neither type can hold the other's values.
+    TypeChanged { name: String, from: FieldType, to: FieldType },
+    BecameNullable { name: String },
+    BecameRequired { name: String },
+}
+
+impl SchemaChange {
+    /// Whether new readers can still read data written before this change.
+    pub fn is_backward_compatible(&self) -> bool {
+        match self {
+            SchemaChange::FieldAdded { field_type, .. } => field_type.nullable,
+            SchemaChange::FieldRemoved { .. } => true,
+            SchemaChange::TypeWidened { .. } => true,
+            SchemaChange::TypeNarrowed { .. } | SchemaChange::TypeChanged { .. } => false,
+            SchemaChange::BecameNullable { .. } => true,
+            SchemaChange::BecameRequired { .. } => false,
+        }
+    }
+
+    /// Whether old readers can still read data written after this change.
+    pub fn is_forward_compatible(&self) -> bool {
+        match self {
+            SchemaChange::FieldAdded { .. } => true,
+            SchemaChange::FieldRemoved { field_type, .. } => field_type.nullable,
+            SchemaChange::TypeNarrowed { .. } => true,
+            SchemaChange::TypeWidened { .. } | SchemaChange::TypeChanged { .. } => false,
+            SchemaChange::BecameRequired { .. } => true,
+            SchemaChange::BecameNullable { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaChange::FieldAdded { name, field_type } => write!(f, "added field '{}: {}'", name, field_type),
+            SchemaChange::FieldRemoved { name, field_type } => write!(f, "removed field '{}: {}'", name, field_type),
+            SchemaChange::TypeWidened { name, from, to } => write!(f, "widened '{}' from {} to {}", name, from, to),
+            SchemaChange::TypeNarrowed { name, from, to } => write!(f, "narrowed '{}' from {} to {}", name, from, to),
+            SchemaChange::TypeChanged { name, from, to } => write!(f, "changed '{}' from {} to {}", name, from, to),
+            SchemaChange::BecameNullable { name } => write!(f, "made '{}' nullable", name),
+            SchemaChange::BecameRequired { name } => write!(f, "made '{}' required", name),
+        }
+    }
+}
+
+/// The differences between two versions of a schema.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CompatibilityReport {
+    pub changes: Vec<SchemaChange>,
+}
+
+impl CompatibilityReport {
+    pub fn is_backward(&self) -> bool {
+        self.changes.iter().all(SchemaChange::is_backward_compatible)
+    }
+
+    pub fn is_forward(&self) -> bool {
+        self.changes.iter().all(SchemaChange::is_forward_compatible)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.is_backward() && self.is_forward()
+    }
+
+    /// Whether the changes are allowed under `level`.
+    pub fn satisfies(&self, level: CompatibilityLevel) -> bool {
+        match level {
+            CompatibilityLevel::Backward => self.is_backward(),
+            CompatibilityLevel::Forward => self.is_forward(),
+            CompatibilityLevel::Full => self.is_full(),
+            CompatibilityLevel::None => true,
+        }
+    }
+
+    /// Changes that break `level`.
+    pub fn violations(&self, level: CompatibilityLevel) -> Vec<&SchemaChange> {
+        self.changes.iter().filter(|c| match level {
+            CompatibilityLevel::Backward => !c.is_backward_compatible(),
+            CompatibilityLevel::Forward => !c.is_forward_compatible(),
+            CompatibilityLevel::Full => !c.is_backward_compatible() || !c.is_forward_compatible(),
+            CompatibilityLevel::None => false,
+        }).collect()
+    }
+}
+
+impl Schema {
+    /// Compare this (new) schema against an `old` version of it.
+    ///
+    /// Fields are matched by name; reordering fields is not a change.
+    pub fn compatible_with(&self, old: &Schema) -> CompatibilityReport {
+        let mut changes = Vec::new();
+        for field in &self.fields {
+            let name = field.name.clone();
+            let prev = match old.fields.iter().find(|f| f.name == field.name) {
+                Some(prev) => prev,
+                None => {
+                    changes.push(SchemaChange::FieldAdded { name, field_type: field.field_type.clone() });
+                    continue;
+                }
+            };
+            let (from, to) = (&prev.field_type, &field.field_type);
+            if !same_base(&from.base, &to.base) || from.is_array != to.is_array {
+                let (from, to) = (from.clone(), to.clone());
+                changes.push(if from.is_array != to.is_array {
+                    SchemaChange::TypeChanged { name: name.clone(), from, to }
+                } else if widens(&from.base, &to.base) {
+                    SchemaChange::TypeWidened { name: name.clone(), from, to }
+                } else if widens(&to.base, &from.base) {
+                    SchemaChange::TypeNarrowed { name: name.clone(), from, to }
+                } else {
+                    SchemaChange::TypeChanged { name: name.clone(), from, to }
+                });
+            }
+            match (from.nullable, to.nullable) {
+                (false, true) => changes.push(SchemaChange::BecameNullable { name }),
+                (true, false) => changes.push(SchemaChange::BecameRequired { name }),
+                _ => {}
+            }
+        }
+        for prev in &old.fields {
+            if !self.fields.iter().any(|f| f.name == prev.name) {
+                changes.push(SchemaChange::FieldRemoved { name: prev.name.clone(), field_type: prev.field_type.clone() });
+            }
+        }
+        CompatibilityReport { changes }
+    }
+}
+
+impl SchemaRegistry {
+    /// Add `schema`, or replace the registered schema of the same name if the
+    /// new version satisfies `level` against it.
+    ///
+    /// On rejection the registry is unchanged and the report is returned.
+    pub fn add_schema_checked(&mut self, schema: Schema, level: CompatibilityLevel) -> Result<(), CompatibilityReport> {
+        if let Some(old) = self.schemas.get(&schema.name) {
+            let report = schema.compatible_with(old);
+            if !report.satisfies(level) {
+                return Err(report);
+            }
+        }
+        self.add_schema(schema);
+        Ok(())
+    }
+}
+
+/// Numeric rank within a family, with type aliases resolved.
+fn numeric_rank(base: &str) -> Option<(char, u8)> {
+    Some(match base {
+        "int8" => ('i', 8),
+        "int16" => ('i', 16),
+        "int" | "int32" => ('i', 32),
+        "int64" => ('i', 64),
+        "uint8" => ('u', 8),
+        "uint16" => ('u', 16),
+        "uint" | "uint32" => ('u', 32),
+        "uint64" => ('u', 64),
+        "float32" => ('f', 32),
+        "float" | "float64" => ('f', 64),
+        _ => return None,
+    })
+}
+
+/// Whether two type names denote the same type (`int` and `int32`, ...).
+fn same_base(a: &str, b: &str) -> bool {
+    a == b || numeric_rank(a).is_some() && numeric_rank(a) == numeric_rank(b)
+}
+
+/// Whether every value of type `from` is representable in type `to`.
+fn widens(from: &str, to: &str) -> bool {
+    match (numeric_rank(from), numeric_rank(to)) {
+        (Some((a, from_bits)), Some((b, to_bits))) => match (a, b) {
+            _ if a == b => from_bits <= to_bits,
+            ('u', 'i') => from_bits < to_bits,
+            // f32 and f64 represent integers of up to 24 and 53 bits exactly
+            ('i' | 'u', 'f') => from_bits <= if to_bits == 64 { 32 } else { 16 },
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v1() -> Schema {
+        Schema::new("user")
+            .field("id", FieldType::new("int"))
+            .field("name", FieldType::new("string"))
+            .field("nick", FieldType::new("string").nullable())
+    }
+
+    #[test]
+    fn test_identical_and_aliases() {
+        let report = v1().compatible_with(&v1());
+        assert!(report.changes.is_empty());
+        assert!(report.is_full());
+
+        let aliased = Schema::new("user")
+            .field("name", FieldType::new("string"))
+            .field("id", FieldType::new("int32"))
+            .field("nick", FieldType::new("string").nullable());
+        assert!(aliased.compatible_with(&v1()).changes.is_empty());
+    }
+
+    #[test]
+    fn test_add_and_remove_fields() {
+        let added = v1().field("email", FieldType::new("string").nullable());
+        assert!(added.compatible_with(&v1()).is_full());
+
+        let added_required = v1().field("age", FieldType::new("int"));
+        let report = added_required.compatible_with(&v1());
+        assert!(!report.is_backward());
+        assert!(report.is_forward());
+
+        let mut removed = v1();
+        removed.fields.retain(|f| f.name != "name");
+        let report = removed.compatible_with(&v1());
+        assert!(report.is_backward());
+        assert!(!report.is_forward());
+        assert_eq!(report.violations(CompatibilityLevel::Full)[0].to_string(), "removed field 'name: string'");
+    }
+
+    #[test]
+    fn test_type_and_nullability_changes() {
+        let mut wider = v1();
+        wider.fields[0].field_type = FieldType::new("int64");
+        let report = wider.compatible_with(&v1());
+        assert!(matches!(report.changes[0], SchemaChange::TypeWidened { .. }));
+        assert!(report.satisfies(CompatibilityLevel::Backward));
+        assert!(!report.satisfies(CompatibilityLevel::Forward));
+        assert!(v1().compatible_with(&wider).satisfies(CompatibilityLevel::Forward));
+
+        let mut other = v1();
+        other.fields[0].field_type = FieldType::new("string");
+        assert!(!other.compatible_with(&v1()).satisfies(CompatibilityLevel::Backward));
+        assert!(other.compatible_with(&v1()).satisfies(CompatibilityLevel::None));
+
+        // Doubles cannot hold every int64, so neither direction is safe
+        let mut float = wider.clone();
+        float.fields[0].field_type = FieldType::new("float");
+        assert!(matches!(float.compatible_with(&wider).changes[0], SchemaChange::TypeChanged { .. }));
+        assert!(!float.compatible_with(&wider).satisfies(CompatibilityLevel::Backward));
+
+        let mut nullable = v1();
+        nullable.fields[1].field_type = FieldType::new("string").nullable();
+        let report = nullable.compatible_with(&v1());
+        assert_eq!(report.changes, vec![SchemaChange::BecameNullable { name: "name".into() }]);
+        assert!(report.is_backward() && !report.is_forward());
+    }
+
+    #[test]
+    fn test_widening_rules() {
+        assert!(widens("int8", "int64"));
+        assert!(widens("uint16", "int"));
+        assert!(!widens("uint64", "int64"));
+        assert!(widens("int", "float"));
+        assert!(widens("uint32", "float64"));
+        assert!(!widens("int64", "float"));
+        assert!(!widens("uint64", "float"));
+        assert!(widens("int16", "float32"));
+        assert!(!widens("int", "float32"));
+        assert!(widens("float32", "float64"));
+        assert!(!widens("int", "uint64"));
+        assert!(!widens("string", "bytes"));
+    }
+
+    #[test]
+    fn test_registry_gates_incompatible_versions() {
+        let mut registry = SchemaRegistry::new();
+        registry.add_schema_checked(v1(), CompatibilityLevel::Full).unwrap();
+
+        let breaking = v1().field("age", FieldType::new("int"));
+        let report = registry.add_schema_checked(breaking, CompatibilityLevel::Backward).unwrap_err();
+        assert_eq!(report.violations(CompatibilityLevel::Backward).len(), 1);
+        assert_eq!(registry.schema("user").unwrap().fields.len(), 3);
+
+        let evolved = v1().field("email", FieldType::new("string").nullable());
+        registry.add_schema_checked(evolved, CompatibilityLevel::Full).unwrap();
+        assert_eq!(registry.schema("user").unwrap().fields.len(), 4);
+    }
+}
@@ -0,0 +1,216 @@
+//! Compressed TeaLeaf text files.
+//!
+//! Text exports are often stored as `.tl.gz` or `.tl.zst`. Loading
+//! recognizes both from their first bytes and decompresses them before
+//! parsing, and [`TeaLeaf::to_tl_file_compressed`] writes them. Each format
+//! needs its feature, `gzip` or `zstd`; without it, a compressed file fails
+//! with an error naming the feature instead of as garbled text.
+
+use std::path::Path;
+
+use crate::{Error, Result, TeaLeaf};
+
+/// A compression format for text files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextCompression {
+    Gzip,
+    Zstd,
+}
+
+impl TextCompression {
+    /// The format of `bytes`, from its magic number, or `None` for
+    /// uncompressed text.
+    pub fn detect(bytes: &[u8]) -> Option<TextCompression> {
+        match bytes {
+            [0x1F, 0x8B, ..] => Some(TextCompression::Gzip),
+            [0x28, 0xB5, 0x2F, 0xFD, ..] => Some(TextCompression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// The format a file name asks for: `.gz` or `.zst`.
+    pub fn from_path(path: &Path) -> Option<TextCompression> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Some(TextCompression::Gzip),
+            Some("zst") => Some(TextCompression::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TextCompression::Gzip => "gzip",
+            TextCompression::Zstd => "zstd",
+        }
+    }
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn invalid(self, e: std::io::Error) -> Error {
+        Error::ParseError(format!("invalid {} data: {}", self.name(), e))
+    }
+
+    fn unsupported(self) -> Error {
+        Error::ParseError(format!(
+            "file is {}-compressed; enable the `{}` feature of tealeaf-core to read and write it",
+            self.name(), self.name(),
+        ))
+    }
+
+    /// `bytes` decompressed.
+    pub fn decompress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        self.decompress_limited(bytes, u64::MAX)
+    }
+
+    /// `bytes` decompressed, failing with [`Error::LimitExceeded`] as soon
+    /// as the output grows past `max_len` bytes, so a small file that
+    /// expands without bound is never held in memory.
+    #[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unused_variables))]
+    pub fn decompress_limited(self, bytes: &[u8], max_len: u64) -> Result<Vec<u8>> {
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        let read_limited = |decoder: &mut dyn std::io::Read| -> Result<Vec<u8>> {
+            use std::io::Read;
+            let mut out = Vec::new();
+            decoder.take(max_len.saturating_add(1)).read_to_end(&mut out).map_err(|e| self.invalid(e))?;
+            if out.len() as u64 > max_len {
+                return Err(Error::LimitExceeded(format!(
+                    "{} data decompresses to more than {} bytes", self.name(), max_len,
+                )));
+            }
+            Ok(out)
+        };
+        match self {
+            #[cfg(feature = "gzip")]
+            TextCompression::Gzip => read_limited(&mut flate2::read::MultiGzDecoder::new(bytes)),
+            #[cfg(feature = "zstd")]
+            TextCompression::Zstd => {
+                let mut decoder = zstd::stream::read::Decoder::new(bytes).map_err(|e| self.invalid(e))?;
+                read_limited(&mut decoder)
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    /// `bytes` compressed at the format's default level.
+    #[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unused_variables))]
+    pub fn compress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            TextCompression::Gzip => {
+                use std::io::Write;
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                Ok(encoder.finish()?)
+            }
+            #[cfg(feature = "zstd")]
+            TextCompression::Zstd => Ok(zstd::encode_all(bytes, 0)?),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unsupported()),
+        }
+    }
+}
+
+/// `bytes` decompressed if they start with a known magic number, else as
+/// they are, failing with [`Error::LimitExceeded`] if the text is longer
+/// than `max_len` bytes.
+pub(crate) fn decompress_text(bytes: Vec<u8>, max_len: u64) -> Result<Vec<u8>> {
+    match TextCompression::detect(&bytes) {
+        Some(compression) => compression.decompress_limited(&bytes, max_len),
+        None if bytes.len() as u64 > max_len => Err(Error::LimitExceeded(format!(
+            "text is {} bytes, limit is {}", bytes.len(), max_len,
+        ))),
+        None => Ok(bytes),
+    }
+}
+
+impl TeaLeaf {
+    /// Write the document as text with schemas, compressed by the format
+    /// the file name asks for: `.gz` for gzip, `.zst` for zstd.
+    ///
+    /// [`load`](Self::load) reads the file back. Fails with
+    /// [`Error::ParseError`] if the name has neither extension or the
+    /// format's feature is not enabled.
+    pub fn to_tl_file_compressed<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let compression = TextCompression::from_path(path).ok_or_else(|| {
+            Error::ParseError(format!("cannot tell the compression of '{}'; use a .gz or .zst name", path.display()))
+        })?;
+        let bytes = compression.compress(self.to_tl_with_schemas().as_bytes())?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(TextCompression::detect(&[0x1F, 0x8B, 8, 0]), Some(TextCompression::Gzip));
+        assert_eq!(TextCompression::detect(&[0x28, 0xB5, 0x2F, 0xFD, 0]), Some(TextCompression::Zstd));
+        assert_eq!(TextCompression::detect(b"a: 1"), None);
+        assert_eq!(TextCompression::from_path(Path::new("data.tl.zst")), Some(TextCompression::Zstd));
+        assert_eq!(TextCompression::from_path(Path::new("data.tl")), None);
+    }
+
+    #[test]
+    fn test_compressed_files_round_trip() {
+        let doc = TeaLeaf::parse("@struct p (x: int, y: int)\npoints: @table p [(1, 2), (3, 4)]\nname: \"Zoë\"").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        for (name, supported) in [("d.tl.gz", cfg!(feature = "gzip")), ("d.tl.zst", cfg!(feature = "zstd"))] {
+            let path = dir.path().join(name);
+            match doc.to_tl_file_compressed(&path) {
+                Ok(()) => {
+                    assert!(supported);
+                    assert!(TextCompression::detect(&std::fs::read(&path).unwrap()).is_some());
+                    let loaded = TeaLeaf::load(&path).unwrap();
+                    assert_eq!(loaded.data, doc.data);
+                    assert_eq!(loaded.binding("points"), Some("p"));
+                }
+                Err(e) => {
+                    assert!(!supported);
+                    assert!(e.to_string().contains("feature"), "{}", e);
+                }
+            }
+        }
+        assert!(doc.to_tl_file_compressed(dir.path().join("d.tl")).is_err());
+
+        // Compressed-looking text that is not valid fails as such
+        let path = dir.path().join("bad.tl");
+        std::fs::write(&path, [0x1F, 0x8B, 0, 0]).unwrap();
+        let err = TeaLeaf::load(&path).err().unwrap().to_string();
+        assert!(err.contains("gzip"), "{}", err);
+    }
+
+    #[test]
+    fn test_decompression_is_bounded() {
+        // A comment of a million spaces compresses to about a kilobyte
+        let text = format!("#{}\nx: 1\n", " ".repeat(1 << 20));
+        let dir = tempfile::tempdir().unwrap();
+        for compression in [TextCompression::Gzip, TextCompression::Zstd] {
+            let Ok(bytes) = compression.compress(text.as_bytes()) else { continue };
+            assert!(bytes.len() < 16 * 1024);
+            assert_eq!(compression.decompress_limited(&bytes, text.len() as u64).unwrap().len(), text.len());
+            let err = compression.decompress_limited(&bytes, 4096).err().unwrap();
+            assert!(matches!(err, Error::LimitExceeded(_)), "{}", err);
+
+            let path = dir.path().join(format!("bomb.tl.{}", compression.name()));
+            std::fs::write(&path, &bytes).unwrap();
+            assert!(TeaLeaf::load_with_max_size(&path, text.len() as u64).is_ok());
+            let err = TeaLeaf::load_with_max_size(&path, 4096).err().unwrap();
+            assert!(matches!(err, Error::LimitExceeded(_)), "{}", err);
+
+            // Includes count at their decompressed size
+            let main = dir.path().join("main.tl");
+            std::fs::write(&main, format!("@include \"bomb.tl.{}\"", compression.name())).unwrap();
+            let policy = crate::IncludePolicy::sandboxed(dir.path());
+            assert!(TeaLeaf::load_with_includes(&main, policy.clone()).is_ok());
+            let err = TeaLeaf::load_with_includes(&main, policy.with_max_bytes(64 * 1024)).err().unwrap();
+            assert_eq!(err.to_string(), format!("Limit exceeded: include of bomb.tl.{} exceeds 65536 bytes of included files", compression.name()));
+        }
+        let plain = dir.path().join("plain.tl");
+        std::fs::write(&plain, "x: 1").unwrap();
+        assert!(matches!(TeaLeaf::load_with_max_size(&plain, 3), Err(Error::LimitExceeded(_))));
+    }
+}
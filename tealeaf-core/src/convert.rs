@@ -3,6 +3,7 @@
 //! This module provides the `ToTeaLeaf` and `FromTeaLeaf` traits for converting
 //! between Rust types and TeaLeaf `Value`s, along with automatic schema collection.
 
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use indexmap::IndexMap;
@@ -98,10 +99,59 @@ pub trait ToTeaLeaf {
     fn tealeaf_field_type() -> FieldType;
 }
 
+thread_local! {
+    /// `(kind, type name)` of the collections in progress on this thread.
+    static COLLECTING: RefCell<Vec<(&'static str, &'static str)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `collect` for `type_name`, or return an empty map if the same `kind`
+/// of collection is already in progress for it further up the stack.
+///
+/// Derived `collect_schemas`/`collect_unions` go through this so recursive
+/// types (`children: Vec<Self>`, `next: Option<Box<Self>>`) stop at the
+/// cycle; the outermost call adds the type's own, self-referencing schema.
+#[doc(hidden)]
+pub fn collect_once<V>(
+    kind: &'static str,
+    type_name: &'static str,
+    collect: impl FnOnce() -> IndexMap<String, V>,
+) -> IndexMap<String, V> {
+    struct Pop;
+    impl Drop for Pop {
+        fn drop(&mut self) {
+            COLLECTING.with(|c| c.borrow_mut().pop());
+        }
+    }
+
+    let entered = COLLECTING.with(|c| {
+        let mut c = c.borrow_mut();
+        if c.contains(&(kind, type_name)) {
+            false
+        } else {
+            c.push((kind, type_name));
+            true
+        }
+    });
+    if !entered {
+        return IndexMap::new();
+    }
+    let _pop = Pop;
+    collect()
+}
+
 /// Convert a TeaLeaf `Value` back into a Rust type.
 pub trait FromTeaLeaf: Sized {
     /// Attempt to reconstruct this type from a TeaLeaf `Value`.
     fn from_tealeaf_value(value: &Value) -> Result<Self, ConvertError>;
+
+    /// The union whose tagged values this type reads, used by
+    /// [`TeaLeaf::to_union_dto`](crate::TeaLeaf::to_union_dto).
+    ///
+    /// Default implementation returns `None`. Derived enums with the default
+    /// (external) tagging return their union name.
+    fn tealeaf_union() -> Option<&'static str> {
+        None
+    }
 }
 
 /// Extension trait providing convenience methods on types implementing `ToTeaLeaf`.
@@ -112,11 +162,13 @@ pub trait ToTeaLeafExt: ToTeaLeaf + Sized {
     }
 
     /// Convert to TeaLeaf text format string.
+    #[cfg(feature = "text")]
     fn to_tl_string(&self, key: &str) -> String {
         self.to_tealeaf_doc(key).to_tl_with_schemas()
     }
 
     /// Compile to binary .tlbx format.
+    #[cfg(feature = "binary")]
     fn to_tlbx(
         &self,
         key: &str,
@@ -127,6 +179,7 @@ pub trait ToTeaLeafExt: ToTeaLeaf + Sized {
     }
 
     /// Convert to JSON string (pretty-printed).
+    #[cfg(feature = "json")]
     fn to_tealeaf_json(&self, key: &str) -> crate::Result<String> {
         self.to_tealeaf_doc(key).to_json()
     }
@@ -135,6 +188,48 @@ pub trait ToTeaLeafExt: ToTeaLeaf + Sized {
 // Blanket implementation: any ToTeaLeaf type gets convenience methods
 impl<T: ToTeaLeaf> ToTeaLeafExt for T {}
 
+/// Extension trait providing parsing entry points on types implementing `FromTeaLeaf`.
+///
+/// These are the inverse of [`ToTeaLeafExt`]: text written by
+/// [`to_tl_string`](ToTeaLeafExt::to_tl_string) reads back with
+/// [`from_tl_str`](Self::from_tl_str).
+pub trait FromTeaLeafExt: FromTeaLeaf {
+    /// Parse TeaLeaf text holding a single top-level entry into this type.
+    #[cfg(feature = "text")]
+    fn from_tl_str(input: &str) -> crate::Result<Self> {
+        single_entry(&crate::TeaLeaf::parse(input)?)
+    }
+
+    /// Load a TeaLeaf text file holding a single top-level entry into this type.
+    #[cfg(feature = "text")]
+    fn from_tl_file(path: impl AsRef<std::path::Path>) -> crate::Result<Self> {
+        single_entry(&crate::TeaLeaf::load(path)?)
+    }
+
+    /// Decode one section of a binary file into this type.
+    #[cfg(feature = "binary")]
+    fn from_tlbx_section(reader: &crate::Reader, key: &str) -> crate::Result<Self> {
+        Ok(Self::from_tealeaf_value(&reader.get(key)?)?)
+    }
+}
+
+// Blanket implementation: any FromTeaLeaf type gets parsing entry points
+impl<T: FromTeaLeaf> FromTeaLeafExt for T {}
+
+/// Convert the only entry of `doc`, failing if it has zero or several.
+#[cfg(feature = "text")]
+fn single_entry<T: FromTeaLeaf>(doc: &crate::TeaLeaf) -> crate::Result<T> {
+    match doc.data.len() {
+        1 => {
+            let (_, value) = doc.data.first().expect("length checked");
+            Ok(T::from_tealeaf_value(value)?)
+        }
+        n => Err(Error::ParseError(format!(
+            "expected exactly one top-level entry, found {}", n
+        ))),
+    }
+}
+
 // =============================================================================
 // Primitive ToTeaLeaf Implementations
 // =============================================================================
@@ -266,6 +361,16 @@ impl ToTeaLeaf for Vec<u8> {
     }
 }
 
+// Fixed-size byte arrays (hashes, keys, ids) are bytes too
+impl<const N: usize> ToTeaLeaf for [u8; N] {
+    fn to_tealeaf_value(&self) -> Value {
+        Value::Bytes(self.to_vec())
+    }
+    fn tealeaf_field_type() -> FieldType {
+        FieldType::new("bytes")
+    }
+}
+
 // =============================================================================
 // Generic ToTeaLeaf Implementations
 // =============================================================================
@@ -303,12 +408,14 @@ impl NotU8 for f32 {}
 impl NotU8 for f64 {}
 impl NotU8 for String {}
 impl<T> NotU8 for Vec<T> {}
+impl<const N: usize> NotU8 for [u8; N] {}
 impl<T> NotU8 for Option<T> {}
 impl<K, V> NotU8 for HashMap<K, V> {}
 impl<K, V> NotU8 for IndexMap<K, V> {}
 impl<T> NotU8 for Box<T> {}
 impl<T> NotU8 for std::sync::Arc<T> {}
 impl<T> NotU8 for std::rc::Rc<T> {}
+impl NotU8 for Value {}
 
 impl<T: ToTeaLeaf + NotU8> ToTeaLeaf for Vec<T> {
     fn to_tealeaf_value(&self) -> Value {
@@ -409,6 +516,16 @@ impl<T: ToTeaLeaf> ToTeaLeaf for std::rc::Rc<T> {
     }
 }
 
+// Untyped sub-documents pass through as-is, typed as `any`
+impl ToTeaLeaf for Value {
+    fn to_tealeaf_value(&self) -> Value {
+        self.clone()
+    }
+    fn tealeaf_field_type() -> FieldType {
+        FieldType::new("any")
+    }
+}
+
 // Tuple implementations (2 through 6 elements)
 macro_rules! impl_to_tealeaf_tuple {
     ($($idx:tt: $T:ident),+) => {
@@ -601,6 +718,36 @@ impl FromTeaLeaf for Vec<u8> {
     }
 }
 
+impl<const N: usize> FromTeaLeaf for [u8; N] {
+    fn from_tealeaf_value(value: &Value) -> Result<Self, ConvertError> {
+        bytes_from_value(value)
+    }
+}
+
+/// The value of a `#[tealeaf(bytes)]` field: `Value::Bytes` of whatever the
+/// field holds, such as `Box<[u8]>` or `bytes::Bytes`.
+#[doc(hidden)]
+pub fn bytes_to_value<T: AsRef<[u8]> + ?Sized>(bytes: &T) -> Value {
+    Value::Bytes(bytes.as_ref().to_vec())
+}
+
+/// Read a `#[tealeaf(bytes)]` field, or a `[u8; N]`, from `Value::Bytes`.
+/// Fails if the value is not bytes or `T` refuses them, as an array of
+/// another length does.
+#[doc(hidden)]
+pub fn bytes_from_value<T: TryFrom<Vec<u8>>>(value: &Value) -> Result<T, ConvertError> {
+    let bytes = value.as_bytes().ok_or_else(|| ConvertError::TypeMismatch {
+        expected: "bytes".into(),
+        got: format!("{:?}", value.tl_type()),
+        path: String::new(),
+    })?;
+    T::try_from(bytes.to_vec()).map_err(|_| ConvertError::TypeMismatch {
+        expected: std::any::type_name::<T>().into(),
+        got: format!("{} bytes", bytes.len()),
+        path: String::new(),
+    })
+}
+
 // =============================================================================
 // Generic FromTeaLeaf Implementations
 // =============================================================================
@@ -694,6 +841,12 @@ impl<T: FromTeaLeaf> FromTeaLeaf for std::rc::Rc<T> {
     }
 }
 
+impl FromTeaLeaf for Value {
+    fn from_tealeaf_value(value: &Value) -> Result<Self, ConvertError> {
+        Ok(value.clone())
+    }
+}
+
 // Tuple FromTeaLeaf implementations
 macro_rules! impl_from_tealeaf_tuple {
     ($($idx:tt: $T:ident),+) => {
@@ -739,27 +892,42 @@ mod tests {
         assert_eq!(42i64.to_tealeaf_value(), Value::Int(42));
         assert_eq!(true.to_tealeaf_value(), Value::Bool(true));
         assert_eq!("hello".to_tealeaf_value(), Value::String("hello".into()));
-        assert_eq!(3.14f64.to_tealeaf_value(), Value::Float(3.14));
+        assert_eq!(2.5f64.to_tealeaf_value(), Value::Float(2.5));
         assert_eq!(42u32.to_tealeaf_value(), Value::UInt(42));
     }
 
     #[test]
     fn test_primitive_from_tealeaf() {
         assert_eq!(i64::from_tealeaf_value(&Value::Int(42)).unwrap(), 42);
-        assert_eq!(
-            bool::from_tealeaf_value(&Value::Bool(true)).unwrap(),
-            true
-        );
+        assert!(bool::from_tealeaf_value(&Value::Bool(true)).unwrap());
         assert_eq!(
             String::from_tealeaf_value(&Value::String("hi".into())).unwrap(),
             "hi"
         );
         assert_eq!(
-            f64::from_tealeaf_value(&Value::Float(3.14)).unwrap(),
-            3.14
+            f64::from_tealeaf_value(&Value::Float(2.5)).unwrap(),
+            2.5
         );
     }
 
+    #[test]
+    fn test_value_passthrough() {
+        let value = Value::Array(vec![Value::Int(1), Value::String("a".into())]);
+        assert_eq!(value.to_tealeaf_value(), value);
+        assert_eq!(Value::from_tealeaf_value(&value).unwrap(), value);
+        assert_eq!(Value::tealeaf_field_type(), FieldType::new("any"));
+
+        // Null is None for Option<Value>, not Some(Null)
+        assert_eq!(Option::<Value>::from_tealeaf_value(&Value::Null).unwrap(), None);
+        assert_eq!(Vec::<Value>::from_tealeaf_value(&value).unwrap(), vec![Value::Int(1), Value::String("a".into())]);
+        assert_eq!(Vec::<Value>::tealeaf_field_type(), FieldType::new("any").array());
+
+        let obj = Value::Object(vec![("k".to_string(), value.clone())].into_iter().collect());
+        let map = IndexMap::<String, Value>::from_tealeaf_value(&obj).unwrap();
+        assert_eq!(map["k"], value);
+        assert_eq!(map.to_tealeaf_value(), obj);
+    }
+
     #[test]
     fn test_option_roundtrip() {
         let some: Option<i64> = Some(42);
@@ -795,6 +963,23 @@ mod tests {
         assert_eq!(Vec::<u8>::from_tealeaf_value(&val).unwrap(), v);
     }
 
+    #[test]
+    fn test_byte_array_as_bytes() {
+        let digest = [0xDEu8, 0xAD, 0xBE, 0xEF];
+        let val = digest.to_tealeaf_value();
+        assert_eq!(val, Value::Bytes(digest.to_vec()));
+        assert_eq!(<[u8; 4]>::tealeaf_field_type(), FieldType::new("bytes"));
+        assert_eq!(<[u8; 4]>::from_tealeaf_value(&val).unwrap(), digest);
+
+        let err = <[u8; 8]>::from_tealeaf_value(&val).unwrap_err().to_string();
+        assert!(err.contains("expected [u8; 8], got 4 bytes"), "{}", err);
+        assert!(<[u8; 4]>::from_tealeaf_value(&Value::Int(1)).is_err());
+        assert_eq!(Vec::<[u8; 2]>::from_tealeaf_value(&Value::Array(vec![Value::Bytes(vec![1, 2])])).unwrap(), vec![[1, 2]]);
+
+        let boxed: Box<[u8]> = bytes_from_value(&val).unwrap();
+        assert_eq!(bytes_to_value(&boxed), val);
+    }
+
     #[test]
     fn test_hashmap_roundtrip() {
         let mut map = HashMap::new();
@@ -907,6 +1092,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "binary")]
     fn test_to_tlbx_convenience() {
         let val = 42i64;
         let dir = std::env::temp_dir();
@@ -917,12 +1103,47 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "json")]
     fn test_to_tealeaf_json_convenience() {
         let val = 42i64;
         let json = val.to_tealeaf_json("num").unwrap();
         assert!(json.contains("42"));
     }
 
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_from_tl_str_roundtrip() {
+        let text = vec![1i64, 2, 3].to_tl_string("nums");
+        assert_eq!(Vec::<i64>::from_tl_str(&text).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_from_tl_str_requires_single_entry() {
+        assert!(i64::from_tl_str("a: 1\nb: 2").is_err());
+        assert!(i64::from_tl_str("").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_from_tl_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("value.tl");
+        std::fs::write(&path, "greeting: hello\n").unwrap();
+        assert_eq!(String::from_tl_file(&path).unwrap(), "hello");
+    }
+
+    #[test]
+    #[cfg(feature = "binary")]
+    fn test_from_tlbx_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("value.tlbx");
+        vec!["a".to_string(), "b".to_string()].to_tlbx("letters", &path, false).unwrap();
+        let reader = crate::Reader::open(&path).unwrap();
+        assert_eq!(Vec::<String>::from_tlbx_section(&reader, "letters").unwrap(), vec!["a", "b"]);
+        assert!(Vec::<String>::from_tlbx_section(&reader, "missing").is_err());
+    }
+
     #[test]
     fn test_tuple_roundtrip() {
         let t = (1i64, "hello".to_string());
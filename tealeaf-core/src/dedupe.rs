@@ -0,0 +1,202 @@
+//! Writing long strings once.
+//!
+//! Documents such as prompt templates can hold the same long string many
+//! times. [`FormatOptions::dedupe_strings`] writes each such string once, as
+//! a top-level reference definition (`!str1: "..."`), and a `!str1`
+//! reference wherever it occurs. [`TeaLeaf::to_tl_file_spilled`] goes
+//! further and moves long strings into files of their own, pulled in by
+//! `@include`, so the main file stays small enough to review.
+//!
+//! Either way the text parses back to references;
+//! [`Pass::ResolveRefs`](crate::Pass::ResolveRefs) puts the strings back in
+//! place.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::{write_data, FormatOptions, IndexMap, Result, TeaLeaf, Value};
+
+/// Replace the strings of at least `min_len` bytes in the values of `data`
+/// with references, naming them `str1`, `str2`, ... in order of first
+/// occurrence, skipping names already in use. With `repeated`, only strings
+/// that occur more than once are replaced. Returns the reference names and
+/// strings replaced.
+pub(crate) fn extract_strings(data: &mut IndexMap<String, Value>, min_len: usize, repeated: bool) -> Vec<(String, String)> {
+    let mut counts: IndexMap<String, usize> = IndexMap::new();
+    let mut taken: HashSet<String> = data.keys().map(|k| k.strip_prefix('!').unwrap_or(k).to_string()).collect();
+    for value in data.values() {
+        visit(value, &mut |v| match v {
+            Value::String(s) if s.len() >= min_len => *counts.entry(s.clone()).or_default() += 1,
+            Value::Ref(name) => { taken.insert(name.clone()); }
+            _ => {}
+        });
+    }
+
+    let mut next = 0;
+    let mut names: IndexMap<String, String> = IndexMap::new();
+    for (s, count) in counts {
+        if repeated && count < 2 {
+            continue;
+        }
+        let name = loop {
+            next += 1;
+            let name = format!("str{}", next);
+            if !taken.contains(&name) {
+                break name;
+            }
+        };
+        names.insert(s, name);
+    }
+    if !names.is_empty() {
+        for value in data.values_mut() {
+            replace(value, &names);
+        }
+    }
+    names.into_iter().map(|(s, name)| (name, s)).collect()
+}
+
+/// Call `f` on `value` and every value inside it. Map keys are skipped:
+/// they cannot be references.
+fn visit(value: &Value, f: &mut impl FnMut(&Value)) {
+    f(value);
+    match value {
+        Value::Array(items) => items.iter().for_each(|v| visit(v, f)),
+        Value::Object(obj) => obj.values().for_each(|v| visit(v, f)),
+        Value::Map(pairs) => pairs.iter().for_each(|(_, v)| visit(v, f)),
+        Value::Tagged(_, inner) => visit(inner, f),
+        _ => {}
+    }
+}
+
+fn replace(value: &mut Value, names: &IndexMap<String, String>) {
+    match value {
+        Value::String(s) => {
+            if let Some(name) = names.get(s.as_str()) {
+                *value = Value::Ref(name.clone());
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| replace(v, names)),
+        Value::Object(obj) => obj.values_mut().for_each(|v| replace(v, names)),
+        Value::Map(pairs) => pairs.iter_mut().for_each(|(_, v)| replace(v, names)),
+        Value::Tagged(_, inner) => replace(inner, names),
+        _ => {}
+    }
+}
+
+/// This document with repeated strings of at least `min_len` bytes moved to
+/// reference definitions ahead of the other keys, or `None` if there are
+/// none.
+pub(crate) fn deduped(doc: &TeaLeaf, min_len: usize) -> Option<TeaLeaf> {
+    let mut data = doc.data.clone();
+    let strings = extract_strings(&mut data, min_len, true);
+    if strings.is_empty() {
+        return None;
+    }
+    let mut doc = doc.clone();
+    doc.data = strings.into_iter().map(|(name, s)| (format!("!{}", name), Value::String(s))).collect();
+    doc.data.extend(data);
+    Some(doc)
+}
+
+impl TeaLeaf {
+    /// Write the document as text to `path`, moving every string of at
+    /// least `min_len` bytes into a file of its own.
+    ///
+    /// Each string is written once, as the reference definition `!strN` in
+    /// `<stem>.strings/strN.tl` next to `path`, and the main file includes
+    /// those files and refers to the strings by reference. [`load`](Self::load)
+    /// reads the files back. Returns the paths of the string files, which
+    /// are overwritten if they exist; the directory is only created when
+    /// there is a string to move. Other options apply to the main file as
+    /// they do for [`to_tl_with_options`](Self::to_tl_with_options).
+    pub fn to_tl_file_spilled<P: AsRef<Path>>(&self, path: P, opts: &FormatOptions, min_len: usize) -> Result<Vec<PathBuf>> {
+        let path = path.as_ref();
+        let mut doc = self.clone();
+        let strings = extract_strings(&mut doc.data, min_len.max(1), false);
+
+        let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let dir_name = format!("{}.strings", stem);
+        let dir = path.with_file_name(&dir_name);
+        let newline = if opts.line_ending.is_crlf(self.crlf) { "\r\n" } else { "\n" };
+        let mut main = String::new();
+        let mut files = Vec::with_capacity(strings.len());
+        if !strings.is_empty() {
+            std::fs::create_dir_all(&dir)?;
+        }
+        for (name, s) in strings {
+            let file = dir.join(format!("{}.tl", name));
+            let mut text = String::new();
+            write_data(&mut text, &[(format!("!{}", name), Value::String(s))].into_iter().collect(), opts);
+            std::fs::write(&file, text.replace('\n', newline))?;
+            main.push_str(&format!("@include \"{}/{}.tl\"{}", dir_name, name, newline));
+            files.push(file);
+        }
+        if !main.is_empty() {
+            main.push_str(newline);
+        }
+        main.push_str(&doc.to_tl_with_options(opts));
+        std::fs::write(path, main)?;
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Pass, Pipeline};
+
+    fn prompt_doc() -> TeaLeaf {
+        let prompt = "You are a helpful assistant. ".repeat(4);
+        TeaLeaf::parse(&format!(
+            "str1: taken\ntemplates: [{{name: a, system: \"{p}\"}}, {{name: b, system: \"{p}\"}}]\nother: \"{p}\"\nshort: [x, x]\nonce: \"{once}\"",
+            p = prompt,
+            once = "y".repeat(200),
+        )).unwrap()
+    }
+
+    #[test]
+    fn test_dedupe_strings() {
+        let doc = prompt_doc();
+        let text = doc.to_tl_with_options(&FormatOptions::default().with_dedupe_strings(64));
+        assert_eq!(text.matches("You are a helpful assistant.").count(), 4, "{}", text);
+        assert!(text.starts_with("!str2: \"You are"), "{}", text);
+        assert!(text.contains("system: !str2"), "{}", text);
+        assert!(text.contains("other: !str2"), "{}", text);
+        assert!(text.contains("short: [x, x]"), "{}", text);
+        assert!(!text.contains("!str3"), "{}", text);
+
+        let mut parsed = TeaLeaf::parse(&text).unwrap();
+        Pipeline::new().pass(Pass::ResolveRefs).run(&mut parsed).unwrap();
+        parsed.data.shift_remove("!str2");
+        assert_eq!(parsed.data, doc.data);
+        assert_eq!(doc.estimated_text_size(&FormatOptions::default().with_dedupe_strings(64)), text.len());
+
+        // Nothing long enough leaves the output unchanged
+        assert_eq!(doc.to_tl_with_options(&FormatOptions::default().with_dedupe_strings(1000)), doc.to_tl_with_schemas());
+    }
+
+    #[test]
+    fn test_spill_strings() {
+        let doc = prompt_doc();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prompts.tl");
+        let files = doc.to_tl_file_spilled(&path, &FormatOptions::default(), 64).unwrap();
+        assert_eq!(files, vec![dir.path().join("prompts.strings/str2.tl"), dir.path().join("prompts.strings/str3.tl")]);
+
+        let main = std::fs::read_to_string(&path).unwrap();
+        assert!(main.starts_with("@include \"prompts.strings/str2.tl\"\n@include \"prompts.strings/str3.tl\"\n\n"), "{}", main);
+        assert!(!main.contains("helpful"), "{}", main);
+        assert!(main.contains("once: !str3"), "{}", main);
+
+        let mut loaded = TeaLeaf::load(&path).unwrap();
+        Pipeline::new().pass(Pass::ResolveRefs).run(&mut loaded).unwrap();
+        loaded.data.retain(|k, _| !k.starts_with('!'));
+        assert_eq!(loaded.data, doc.data);
+
+        // Nothing to move: no directory, and the plain text
+        let path = dir.path().join("plain.tl");
+        assert!(doc.to_tl_file_spilled(&path, &FormatOptions::default(), 10_000).unwrap().is_empty());
+        assert!(!dir.path().join("plain.strings").exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), doc.to_tl_with_schemas());
+    }
+}
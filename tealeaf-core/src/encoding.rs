@@ -0,0 +1,199 @@
+//! Detecting the character encoding of TeaLeaf text files.
+//!
+//! TeaLeaf text is UTF-8, but files exported by Windows tools often start
+//! with a byte order mark or are UTF-16 throughout. [`Encoding::detect`]
+//! recognizes these from the first bytes, so loading can skip the mark, or
+//! name the encoding it cannot read, instead of failing on the first
+//! character.
+
+use crate::{Error, Result};
+
+/// The character encoding of a text file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8 without a byte order mark
+    Utf8,
+    /// UTF-8 starting with the byte order mark `EF BB BF`
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Encoding {
+    /// The encoding of `bytes`, from its byte order mark if it has one.
+    /// Without one, text starting with a zero byte or a zero second byte is
+    /// taken for UTF-16 or UTF-32, since TeaLeaf text never starts with
+    /// NUL; anything else is UTF-8, valid or not.
+    pub fn detect(bytes: &[u8]) -> Encoding {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => Encoding::Utf8Bom,
+            [0x00, 0x00, 0xFE, 0xFF, ..] => Encoding::Utf32Be,
+            [0xFF, 0xFE, 0x00, 0x00, ..] => Encoding::Utf32Le,
+            [0xFE, 0xFF, ..] => Encoding::Utf16Be,
+            [0xFF, 0xFE, ..] => Encoding::Utf16Le,
+            [0x00, 0x00, 0x00, a, ..] if *a != 0 => Encoding::Utf32Be,
+            [a, 0x00, 0x00, 0x00, ..] if *a != 0 => Encoding::Utf32Le,
+            [0x00, a, ..] if *a != 0 => Encoding::Utf16Be,
+            [a, 0x00, ..] if *a != 0 => Encoding::Utf16Le,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+        }
+    }
+
+    /// Whether TeaLeaf reads text in this encoding without transcoding.
+    pub fn is_utf8(self) -> bool {
+        matches!(self, Encoding::Utf8 | Encoding::Utf8Bom)
+    }
+
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => &[],
+            Encoding::Utf8Bom => &[0xEF, 0xBB, 0xBF],
+            Encoding::Utf16Le => &[0xFF, 0xFE],
+            Encoding::Utf16Be => &[0xFE, 0xFF],
+            Encoding::Utf32Le => &[0xFF, 0xFE, 0, 0],
+            Encoding::Utf32Be => &[0, 0, 0xFE, 0xFF],
+        }
+    }
+
+    /// `bytes` in this encoding as a string, without the byte order mark.
+    ///
+    /// Fails with [`Error::ParseError`] giving the line and column of the
+    /// first byte that is not valid in this encoding.
+    pub fn decode(self, bytes: &[u8]) -> Result<String> {
+        let bytes = bytes.strip_prefix(self.bom()).unwrap_or(bytes);
+        let (width, big_endian) = match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => {
+                return match std::str::from_utf8(bytes) {
+                    Ok(text) => Ok(text.to_string()),
+                    Err(e) => {
+                        let valid = std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default();
+                        Err(invalid(self, valid))
+                    }
+                };
+            }
+            Encoding::Utf16Le => (2, false),
+            Encoding::Utf16Be => (2, true),
+            Encoding::Utf32Le => (4, false),
+            Encoding::Utf32Be => (4, true),
+        };
+        let units = bytes.chunks(width).map(|unit| {
+            let mut n = 0u32;
+            for i in 0..unit.len() {
+                let byte = if big_endian { unit[i] } else { unit[unit.len() - 1 - i] };
+                n = n << 8 | byte as u32;
+            }
+            (n, unit.len() == width)
+        });
+        let mut text = String::with_capacity(bytes.len() / width);
+        if width == 2 {
+            let mut complete = true;
+            let units: Vec<u16> = units.map(|(n, whole)| {
+                complete &= whole;
+                n as u16
+            }).collect();
+            for c in char::decode_utf16(units) {
+                match c {
+                    Ok(c) => text.push(c),
+                    Err(_) => return Err(invalid(self, &text)),
+                }
+            }
+            if !complete {
+                return Err(invalid(self, &text));
+            }
+        } else {
+            for (n, whole) in units {
+                match char::from_u32(n) {
+                    Some(c) if whole => text.push(c),
+                    _ => return Err(invalid(self, &text)),
+                }
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// An error for text that stops being valid `encoding` after `valid`.
+fn invalid(encoding: Encoding, valid: &str) -> Error {
+    let line = valid.matches('\n').count() + 1;
+    let col = valid.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    Error::ParseError(format!("Invalid {} at line {}, column {}", encoding.name(), line, col))
+}
+
+/// Decode the contents of a text file: UTF-8 with or without a byte order
+/// mark, and with `transcode` UTF-16 and UTF-32 too. Other encodings fail
+/// with [`Error::UnsupportedEncoding`].
+#[cfg(feature = "text")]
+pub(crate) fn decode_text(bytes: &[u8], transcode: bool) -> Result<(String, Encoding)> {
+    let encoding = Encoding::detect(bytes);
+    if !transcode && !encoding.is_utf8() {
+        return Err(Error::UnsupportedEncoding(encoding));
+    }
+    Ok((encoding.decode(bytes)?, encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|u| if big_endian { u.to_be_bytes() } else { u.to_le_bytes() })
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_and_decode() {
+        let text = "name: \"Zoë 🍵\"\n";
+        let mut bom = vec![0xEF, 0xBB, 0xBF];
+        bom.extend_from_slice(text.as_bytes());
+        let mut le = vec![0xFF, 0xFE];
+        le.extend(utf16(text, false));
+        let mut be32 = vec![0, 0, 0xFE, 0xFF];
+        be32.extend(text.chars().flat_map(|c| (c as u32).to_be_bytes()));
+        let le32: Vec<u8> = text.chars().flat_map(|c| (c as u32).to_le_bytes()).collect();
+
+        for (bytes, encoding) in [
+            (text.as_bytes().to_vec(), Encoding::Utf8),
+            (bom, Encoding::Utf8Bom),
+            (le, Encoding::Utf16Le),
+            (utf16(text, true), Encoding::Utf16Be),
+            (be32, Encoding::Utf32Be),
+            (le32, Encoding::Utf32Le),
+        ] {
+            assert_eq!(Encoding::detect(&bytes), encoding);
+            assert_eq!(encoding.decode(&bytes).unwrap(), text, "{}", encoding.name());
+        }
+        assert_eq!(Encoding::detect(b""), Encoding::Utf8);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let err = Encoding::Utf8.decode(b"a: 1\nb: \"x\xFFy\"\n").unwrap_err();
+        assert_eq!(err.to_string(), "Parse error: Invalid UTF-8 at line 2, column 6");
+        // A lone surrogate, and an odd trailing byte
+        let err = Encoding::Utf16Le.decode(&[b'a', 0, 0x00, 0xD8, b'b', 0]).unwrap_err();
+        assert_eq!(err.to_string(), "Parse error: Invalid UTF-16LE at line 1, column 2");
+        assert!(Encoding::Utf16Be.decode(&[0, b'a', 0]).is_err());
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn test_decode_text() {
+        let err = decode_text(&utf16("a: 1", false), false).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedEncoding(Encoding::Utf16Le)));
+        assert_eq!(decode_text(&utf16("a: 1", false), true).unwrap(), ("a: 1".to_string(), Encoding::Utf16Le));
+    }
+}
@@ -0,0 +1,403 @@
+//! Environment capture for diagnostic bundles.
+//!
+//! `EnvSnapshot` records host and process metadata (hostname, OS, library
+//! version, selected environment variables) in a standard shape so support
+//! bundles built from TeaLeaf documents all describe their environment the
+//! same way:
+//!
+//! ```text
+//! @struct env_var (name: string, value: string?, redacted: bool)
+//! @struct env_snapshot (hostname: string?, os: string, arch: string, family: string,
+//!                       pid: uint, tealeaf_version: string, captured_at: timestamp,
+//!                       vars: []env_var)
+//! ```
+//!
+//! Capture is opt-in: no environment variable is recorded unless it is named
+//! explicitly, and values whose names look sensitive are redacted.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use indexmap::IndexMap;
+
+use crate::convert::{ConvertError, FromTeaLeaf, NotU8, ToTeaLeaf};
+use crate::types::ObjectMap;
+use crate::{FieldType, Schema, Value, VERSION};
+
+/// Schema name of the snapshot struct.
+pub const ENV_SNAPSHOT_SCHEMA: &str = "env_snapshot";
+/// Schema name of a captured environment variable.
+pub const ENV_VAR_SCHEMA: &str = "env_var";
+
+/// Name segments that mark an environment variable as sensitive (case-insensitive).
+const DEFAULT_REDACT_PATTERNS: &[&str] = &[
+    "SECRET", "TOKEN", "PASSWORD", "PASSWD", "CREDENTIAL", "CREDENTIALS", "API_KEY", "PRIVATE_KEY",
+    "AUTH", "AUTHORIZATION",
+];
+
+/// Placeholder written in place of a redacted value.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A single captured environment variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvVar {
+    pub name: String,
+    /// `None` when the variable is not set in the process environment.
+    pub value: Option<String>,
+    pub redacted: bool,
+}
+
+/// Host and process metadata captured at a point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvSnapshot {
+    pub hostname: Option<String>,
+    pub os: String,
+    pub arch: String,
+    pub family: String,
+    pub pid: u32,
+    pub tealeaf_version: String,
+    /// Unix milliseconds (UTC).
+    pub captured_at: i64,
+    pub vars: Vec<EnvVar>,
+}
+
+impl EnvSnapshot {
+    /// Capture a snapshot with the default settings (no environment variables).
+    pub fn capture() -> Self {
+        EnvCapture::new().capture()
+    }
+
+    /// The `env_var` and `env_snapshot` schemas, in dependency order.
+    pub fn schemas() -> IndexMap<String, Schema> {
+        let mut schemas = IndexMap::new();
+        schemas.insert(
+            ENV_VAR_SCHEMA.to_string(),
+            Schema::new(ENV_VAR_SCHEMA)
+                .field("name", FieldType::new("string"))
+                .field("value", FieldType::new("string").nullable())
+                .field("redacted", FieldType::new("bool")),
+        );
+        schemas.insert(
+            ENV_SNAPSHOT_SCHEMA.to_string(),
+            Schema::new(ENV_SNAPSHOT_SCHEMA)
+                .field("hostname", FieldType::new("string").nullable())
+                .field("os", FieldType::new("string"))
+                .field("arch", FieldType::new("string"))
+                .field("family", FieldType::new("string"))
+                .field("pid", FieldType::new("uint"))
+                .field("tealeaf_version", FieldType::new("string"))
+                .field("captured_at", FieldType::new("timestamp"))
+                .field("vars", FieldType::new(ENV_VAR_SCHEMA).array()),
+        );
+        schemas
+    }
+
+    /// Look up a captured variable by name.
+    pub fn var(&self, name: &str) -> Option<&EnvVar> {
+        self.vars.iter().find(|v| v.name == name)
+    }
+}
+
+/// Options controlling what an [`EnvSnapshot`] records.
+///
+/// # Example
+///
+/// ```
+/// use tealeaf::env::EnvCapture;
+///
+/// let snapshot = EnvCapture::new()
+///     .var("RUST_LOG")
+///     .var("DATABASE_PASSWORD") // redacted by default
+///     .capture();
+/// assert_eq!(snapshot.vars.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnvCapture {
+    vars: Vec<String>,
+    redact_patterns: Vec<String>,
+    include_hostname: bool,
+}
+
+impl EnvCapture {
+    /// Create a capture configuration with the default redaction patterns.
+    pub fn new() -> Self {
+        Self {
+            vars: Vec::new(),
+            redact_patterns: DEFAULT_REDACT_PATTERNS.iter().map(|s| s.to_string()).collect(),
+            include_hostname: true,
+        }
+    }
+
+    /// Record the named environment variable.
+    pub fn var(mut self, name: impl Into<String>) -> Self {
+        self.vars.push(name.into());
+        self
+    }
+
+    /// Record each of the named environment variables.
+    pub fn vars<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.vars.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Redact variables whose name contains `pattern` as whole segments,
+    /// ignoring case: `TOKEN` matches `GITHUB_TOKEN` but not `TOKENIZER`.
+    /// Segments are separated by `_` or any other character that is not a
+    /// letter or digit.
+    pub fn redact(mut self, pattern: impl Into<String>) -> Self {
+        self.redact_patterns.push(pattern.into().to_ascii_uppercase());
+        self
+    }
+
+    /// Drop the default redaction patterns, keeping only those added afterwards.
+    pub fn clear_redactions(mut self) -> Self {
+        self.redact_patterns.clear();
+        self
+    }
+
+    /// Omit the hostname from the snapshot.
+    pub fn without_hostname(mut self) -> Self {
+        self.include_hostname = false;
+        self
+    }
+
+    /// Whether a variable with this name would be redacted.
+    pub fn is_redacted(&self, name: &str) -> bool {
+        let name = segments(name);
+        self.redact_patterns.iter().any(|p| name.contains(&segments(p)))
+    }
+
+    /// Capture the current process environment.
+    pub fn capture(&self) -> EnvSnapshot {
+        let captured_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        let vars = self.vars.iter().map(|name| {
+            let redacted = self.is_redacted(name);
+            let value = std::env::var(name).ok()
+                .map(|v| if redacted { REDACTED.to_string() } else { v });
+            EnvVar { name: name.clone(), value, redacted }
+        }).collect();
+
+        EnvSnapshot {
+            hostname: if self.include_hostname { hostname() } else { None },
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            family: std::env::consts::FAMILY.to_string(),
+            pid: std::process::id(),
+            tealeaf_version: VERSION.to_string(),
+            captured_at,
+            vars,
+        }
+    }
+}
+
+impl Default for EnvCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `name` in upper case with every separator as `_` and one more at each
+/// end, so that a pattern matches whole segments by substring search.
+fn segments(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 2);
+    out.push('_');
+    out.extend(name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }));
+    out.push('_');
+    out
+}
+
+/// Best-effort hostname lookup without platform-specific dependencies.
+fn hostname() -> Option<String> {
+    for var in ["HOSTNAME", "COMPUTERNAME"] {
+        if let Ok(h) = std::env::var(var) {
+            if !h.trim().is_empty() {
+                return Some(h.trim().to_string());
+            }
+        }
+    }
+    std::fs::read_to_string("/etc/hostname").ok()
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+}
+
+// =============================================================================
+// Conversions
+// =============================================================================
+
+impl ToTeaLeaf for EnvVar {
+    fn to_tealeaf_value(&self) -> Value {
+        let mut obj = ObjectMap::new();
+        obj.insert("name".to_string(), Value::String(self.name.clone()));
+        obj.insert("value".to_string(), self.value.to_tealeaf_value());
+        obj.insert("redacted".to_string(), Value::Bool(self.redacted));
+        Value::Object(obj)
+    }
+    fn collect_schemas() -> IndexMap<String, Schema> {
+        let mut schemas = EnvSnapshot::schemas();
+        schemas.shift_remove(ENV_SNAPSHOT_SCHEMA);
+        schemas
+    }
+    fn tealeaf_field_type() -> FieldType {
+        FieldType::new(ENV_VAR_SCHEMA)
+    }
+}
+
+impl NotU8 for EnvVar {}
+
+impl ToTeaLeaf for EnvSnapshot {
+    fn to_tealeaf_value(&self) -> Value {
+        let mut obj = ObjectMap::new();
+        obj.insert("hostname".to_string(), self.hostname.to_tealeaf_value());
+        obj.insert("os".to_string(), Value::String(self.os.clone()));
+        obj.insert("arch".to_string(), Value::String(self.arch.clone()));
+        obj.insert("family".to_string(), Value::String(self.family.clone()));
+        obj.insert("pid".to_string(), Value::UInt(self.pid as u64));
+        obj.insert("tealeaf_version".to_string(), Value::String(self.tealeaf_version.clone()));
+        obj.insert("captured_at".to_string(), Value::Timestamp(self.captured_at, 0));
+        obj.insert("vars".to_string(), self.vars.to_tealeaf_value());
+        Value::Object(obj)
+    }
+    fn collect_schemas() -> IndexMap<String, Schema> {
+        Self::schemas()
+    }
+    fn tealeaf_field_type() -> FieldType {
+        FieldType::new(ENV_SNAPSHOT_SCHEMA)
+    }
+}
+
+fn field<'a>(value: &'a Value, struct_name: &str, name: &str) -> Result<&'a Value, ConvertError> {
+    value.get(name).ok_or_else(|| ConvertError::MissingField {
+        struct_name: struct_name.to_string(),
+        field: name.to_string(),
+    })
+}
+
+fn nested<T: FromTeaLeaf>(value: &Value, struct_name: &str, name: &str) -> Result<T, ConvertError> {
+    T::from_tealeaf_value(field(value, struct_name, name)?).map_err(|e| ConvertError::Nested {
+        path: name.to_string(),
+        source: Box::new(e),
+    })
+}
+
+/// Like [`nested`], but treats an absent field as null.
+fn optional<T: FromTeaLeaf>(value: &Value, name: &str) -> Result<Option<T>, ConvertError> {
+    Option::<T>::from_tealeaf_value(value.get(name).unwrap_or(&Value::Null)).map_err(|e| {
+        ConvertError::Nested { path: name.to_string(), source: Box::new(e) }
+    })
+}
+
+impl FromTeaLeaf for EnvVar {
+    fn from_tealeaf_value(value: &Value) -> Result<Self, ConvertError> {
+        Ok(Self {
+            name: nested(value, ENV_VAR_SCHEMA, "name")?,
+            value: optional(value, "value")?,
+            redacted: nested(value, ENV_VAR_SCHEMA, "redacted")?,
+        })
+    }
+}
+
+impl FromTeaLeaf for EnvSnapshot {
+    fn from_tealeaf_value(value: &Value) -> Result<Self, ConvertError> {
+        let captured_at = field(value, ENV_SNAPSHOT_SCHEMA, "captured_at")?;
+        let captured_at = captured_at.as_timestamp_millis().ok_or_else(|| ConvertError::TypeMismatch {
+            expected: "timestamp".into(),
+            got: format!("{:?}", captured_at.tl_type()),
+            path: "captured_at".into(),
+        })?;
+        Ok(Self {
+            hostname: optional(value, "hostname")?,
+            os: nested(value, ENV_SNAPSHOT_SCHEMA, "os")?,
+            arch: nested(value, ENV_SNAPSHOT_SCHEMA, "arch")?,
+            family: nested(value, ENV_SNAPSHOT_SCHEMA, "family")?,
+            pid: nested(value, ENV_SNAPSHOT_SCHEMA, "pid")?,
+            tealeaf_version: nested(value, ENV_SNAPSHOT_SCHEMA, "tealeaf_version")?,
+            captured_at,
+            vars: nested(value, ENV_SNAPSHOT_SCHEMA, "vars")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_basic_fields() {
+        let snap = EnvCapture::new().without_hostname().capture();
+        assert_eq!(snap.os, std::env::consts::OS);
+        assert_eq!(snap.arch, std::env::consts::ARCH);
+        assert_eq!(snap.pid, std::process::id());
+        assert_eq!(snap.tealeaf_version, VERSION);
+        assert!(snap.hostname.is_none());
+        assert!(snap.captured_at > 0);
+        assert!(snap.vars.is_empty());
+    }
+
+    #[test]
+    fn test_redaction() {
+        let capture = EnvCapture::new();
+        assert!(capture.is_redacted("DB_PASSWORD"));
+        assert!(capture.is_redacted("github_token"));
+        assert!(!capture.is_redacted("RUST_LOG"));
+        assert!(capture.is_redacted("HTTP_AUTH"));
+        assert!(capture.is_redacted("Proxy-Authorization"));
+        assert!(capture.is_redacted("AWS_SECRET_ACCESS_KEY"));
+        assert!(!capture.is_redacted("GIT_AUTHOR_NAME"));
+        assert!(!capture.is_redacted("TOKENIZERS_PARALLELISM"));
+
+        let custom = EnvCapture::new().clear_redactions().redact("internal");
+        assert!(custom.is_redacted("MY_INTERNAL_URL"));
+        assert!(!custom.is_redacted("DB_PASSWORD"));
+    }
+
+    #[test]
+    fn test_capture_vars() {
+        // PATH is set in every test environment we run under; a made-up
+        // variable exercises the unset case.
+        let snap = EnvCapture::new()
+            .vars(["PATH", "TEALEAF_TEST_UNSET_VAR"])
+            .redact("PATH")
+            .capture();
+        let path = snap.var("PATH").unwrap();
+        assert!(path.redacted);
+        if path.value.is_some() {
+            assert_eq!(path.value.as_deref(), Some(REDACTED));
+        }
+        let unset = snap.var("TEALEAF_TEST_UNSET_VAR").unwrap();
+        assert_eq!(unset.value, None);
+        assert!(!unset.redacted);
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_snapshot_text_roundtrip() {
+        let snap = EnvSnapshot {
+            hostname: Some("build-01".into()),
+            os: "linux".into(),
+            arch: "x86_64".into(),
+            family: "unix".into(),
+            pid: 4242,
+            tealeaf_version: VERSION.into(),
+            captured_at: 1_700_000_000_000,
+            vars: vec![
+                EnvVar { name: "RUST_LOG".into(), value: Some("debug".into()), redacted: false },
+                EnvVar { name: "API_TOKEN".into(), value: Some(REDACTED.into()), redacted: true },
+                EnvVar { name: "UNSET".into(), value: None, redacted: false },
+            ],
+        };
+        let doc = crate::TeaLeafBuilder::new().add("env", &snap).build();
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("@struct env_var"), "{}", text);
+        assert!(text.contains("@struct env_snapshot"), "{}", text);
+
+        let reparsed = crate::TeaLeaf::parse(&text).unwrap();
+        let back: EnvSnapshot = reparsed.to_dto("env").unwrap();
+        assert_eq!(back, snap);
+    }
+}
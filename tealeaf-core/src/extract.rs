@@ -0,0 +1,232 @@
+//! Pulling one value out of a large text document.
+//!
+//! [`extract`] reads a document line by line and keeps only the text of the
+//! section holding the wanted key, together with the `@struct`, `@union`,
+//! `@alias` and `@include` directives it may depend on. Other sections are
+//! skimmed for brackets, strings and comments, to tell where they end, but
+//! never lexed or built, and reading stops as soon as the wanted section
+//! is complete.
+
+use std::io::{BufRead, BufReader, Read};
+
+use crate::types::split_path_root;
+use crate::{Result, TeaLeaf, Value};
+
+/// Top-level directives kept for the wanted section to use
+const DEFINITIONS: &[&str] = &["struct", "union", "alias", "include"];
+
+/// The value at `path` (as in [`TeaLeaf::get_path`]) of the text document
+/// read from `input`, or `None` if the document has no such value.
+///
+/// Only the first section with the path's top-level key is read; later
+/// sections of the same key are not. References in the value are returned
+/// as [`Value::Ref`], unresolved, and `@include` paths are relative to the
+/// working directory, as with [`TeaLeaf::parse`].
+///
+/// Sections must start on a line of their own, as [`TeaLeaf::to_tl`]
+/// writes them.
+///
+/// ```no_run
+/// let file = std::fs::File::open("export.tl")?;
+/// let email = tealeaf::extract(file, "users[1042].email")?;
+/// # Ok::<(), tealeaf::Error>(())
+/// ```
+pub fn extract(input: impl Read, path: &str) -> Result<Option<Value>> {
+    if path.is_empty() {
+        return Ok(None);
+    }
+    let (key, _) = split_path_root(path);
+    let mut reader = BufReader::new(input);
+    let mut skim = Skim::default();
+    // Definitions, then the wanted section once found
+    let mut kept = String::new();
+    let mut keeping = false;
+    let mut found = false;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        if skim.at_top_level() {
+            match item_start(&line) {
+                Some(Item::Definition) => {
+                    if found {
+                        break;
+                    }
+                    keeping = true;
+                }
+                Some(Item::Key(k)) => {
+                    if found {
+                        break;
+                    }
+                    keeping = k == key;
+                    found = keeping;
+                }
+                None => {}
+            }
+        }
+        if keeping {
+            kept.push_str(&line);
+        }
+        skim.line(&line);
+    }
+    Ok(TeaLeaf::parse(&kept)?.get_path(path).cloned())
+}
+
+enum Item {
+    Definition,
+    Key(String),
+}
+
+/// What starts on `line`, if it begins a top-level item.
+fn item_start(line: &str) -> Option<Item> {
+    let rest = line.trim_start();
+    if let Some(directive) = rest.strip_prefix('@') {
+        return DEFINITIONS.contains(&word(directive)).then_some(Item::Definition);
+    }
+    let (key, after) = if rest.starts_with('"') {
+        let end = string_end(rest.as_bytes(), 1)?;
+        match Value::parse_literal(&rest[..end]) {
+            Ok(Value::String(s)) => (s, &rest[end..]),
+            _ => return None,
+        }
+    } else if let Some(name) = rest.strip_prefix('!') {
+        let name = word(name);
+        (format!("!{}", name), &rest[1 + name.len()..])
+    } else {
+        // Lines starting with a digit are values, such as timestamps
+        if !rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return None;
+        }
+        let name = word(rest);
+        (name.to_string(), &rest[name.len()..])
+    };
+    if key.is_empty() || !after.trim_start().starts_with(':') {
+        return None;
+    }
+    Some(Item::Key(key))
+}
+
+/// The leading word of `s`, as the lexer reads words.
+fn word(s: &str) -> &str {
+    let end = s.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.')).unwrap_or(s.len());
+    &s[..end]
+}
+
+/// Index just past the quote closing the string whose contents start at
+/// `from`, if it closes on this line.
+fn string_end(bytes: &[u8], from: usize) -> Option<usize> {
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Bracket depth and multiline-string state across lines.
+#[derive(Default)]
+struct Skim {
+    depth: usize,
+    in_multiline: bool,
+}
+
+impl Skim {
+    fn at_top_level(&self) -> bool {
+        self.depth == 0 && !self.in_multiline
+    }
+
+    fn line(&mut self, line: &str) {
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if self.in_multiline {
+                match line[i..].find("\"\"\"") {
+                    Some(at) => {
+                        i += at + 3;
+                        self.in_multiline = false;
+                    }
+                    None => return,
+                }
+                continue;
+            }
+            match bytes[i] {
+                b'"' if bytes[i..].starts_with(b"\"\"\"") => {
+                    self.in_multiline = true;
+                    i += 3;
+                }
+                b'"' => match string_end(bytes, i + 1) {
+                    Some(end) => i = end,
+                    None => return,
+                },
+                b'#' => return,
+                b'(' | b'[' | b'{' => {
+                    self.depth += 1;
+                    i += 1;
+                }
+                b')' | b']' | b'}' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    i += 1;
+                }
+                _ => i += 1,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    const DOC: &str = r#"# export
+@struct user (id: int, name: string)
+
+notes: """
+  users: [not, this]
+  """
+"odd key": {a: [1, 2]}  # comment with ( bracket
+users: @table user [
+  (1, alice),
+  (2, "bob # not a comment")
+]
+started:
+  2024-01-15T10:30:00Z
+after: !base
+"#;
+
+    /// Fails any read, to show that extraction stopped before it
+    struct Broken;
+
+    impl Read for Broken {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("read past the wanted section"))
+        }
+    }
+
+    #[test]
+    fn test_extracts_value_at_path() {
+        let get = |path| extract(DOC.as_bytes(), path).unwrap();
+        assert_eq!(get("users[1].name"), Some(Value::String("bob # not a comment".into())));
+        assert_eq!(get("users[0]").unwrap().get("id"), Some(&Value::Int(1)));
+        assert_eq!(get("odd key.a[1]"), Some(Value::Int(2)));
+        assert_eq!(get("notes").unwrap().as_str(), Some("users: [not, this]"));
+        assert!(get("started").unwrap().as_timestamp_millis().is_some());
+        assert_eq!(get("after"), Some(Value::Ref("base".into())));
+        assert_eq!(get("users[5]"), None);
+        assert_eq!(get("missing"), None);
+        assert_eq!(get(""), None);
+    }
+
+    #[test]
+    fn test_stops_after_section() {
+        let input = DOC.as_bytes().chain(Broken);
+        assert_eq!(extract(input, "users[0].name").unwrap(), Some(Value::String("alice".into())));
+        assert!(matches!(extract(DOC.as_bytes().chain(Broken), "missing"), Err(crate::Error::Io(_))));
+        assert!(extract("a: [1,\nb: 2\n".as_bytes(), "a").is_err());
+    }
+}
@@ -0,0 +1,353 @@
+//! Schema-driven fake data.
+//!
+//! [`generate`] fills one table per schema of a [`SchemaRegistry`] with rows
+//! that conform to it, for tests, demos and benchmark inputs:
+//!
+//! ```
+//! use tealeaf::{fake, TeaLeaf};
+//!
+//! let schemas = TeaLeaf::parse("@struct user (id: int, email: string, joined: timestamp?)")?;
+//! let doc = fake::generate(&schemas.schema_registry(), 1000, 42);
+//! assert_eq!(doc.get("users").unwrap().as_array().unwrap().len(), 1000);
+//! # Ok::<(), tealeaf::Error>(())
+//! ```
+//!
+//! The same registry, row count and seed always give the same document.
+//! Values are picked by field type and, for plausibility, by field name: an
+//! `id` field counts up from 1, `email` holds addresses, `price` two-decimal
+//! amounts, and so on. Nullable fields are null about one time in ten, and
+//! arrays hold up to three elements. Fields naming a schema hold a nested
+//! object, and fields naming a union a tagged variant. Past a nesting depth
+//! of [`MAX_DEPTH`], nullable fields of those kinds are null and arrays
+//! empty, so self-referencing schemas stay finite.
+
+use indexmap::IndexMap;
+
+use crate::types::ObjectMap;
+use crate::{FieldType, Schema, SchemaRegistry, TeaLeaf, TimePrecision, Value};
+
+/// Nesting depth beyond which nullable struct and union fields are null.
+pub const MAX_DEPTH: usize = 4;
+
+/// Depth at which even non-nullable nested values are cut off with null,
+/// reached only by schemas that require themselves.
+const CYCLE_LIMIT: usize = 32;
+
+/// Share of nullable field values that are null, out of 100.
+const NULL_PERCENT: u64 = 10;
+
+const FIRST_NAMES: &[&str] = &["Alice", "Bob", "Carmen", "Dmitri", "Emeka", "Fatima", "Grace", "Hiro", "Ines", "Jonas", "Keiko", "Luis", "Mei", "Noah", "Olga", "Priya"];
+const LAST_NAMES: &[&str] = &["Smith", "Garcia", "Okafor", "Ivanova", "Tanaka", "Muller", "Silva", "Khan", "Nguyen", "Rossi", "Kowalski", "Haddad"];
+const CITIES: &[&str] = &["Lisbon", "Nairobi", "Osaka", "Toronto", "Lyon", "Austin", "Pune", "Krakow", "Bogota", "Melbourne"];
+const COUNTRIES: &[&str] = &["PT", "KE", "JP", "CA", "FR", "US", "IN", "PL", "CO", "AU"];
+const STATUSES: &[&str] = &["active", "pending", "suspended", "closed"];
+const WORDS: &[&str] = &["alpha", "river", "quiet", "amber", "signal", "harbor", "copper", "meadow", "vector", "lantern", "orbit", "pebble", "summit", "willow", "ember", "tidal"];
+
+/// 2020-01-01T00:00:00Z; generated timestamps fall in the five years after.
+const EPOCH_2020_MS: i64 = 1_577_836_800_000;
+const FIVE_YEARS_MS: u64 = 5 * 365 * 86_400_000;
+
+/// Generate `rows_per_table` rows for every schema in `registry`, each under
+/// the schema name made plural (`user` → `users`) and bound to its schema.
+pub fn generate(registry: &SchemaRegistry, rows_per_table: usize, seed: u64) -> TeaLeaf {
+    let mut faker = Faker { registry, rng: Rng(seed) };
+    let mut data = IndexMap::new();
+    for schema in registry.schemas.values() {
+        let rows = (0..rows_per_table).map(|row| faker.object(schema, row, 0)).collect();
+        data.insert(plural(&schema.name), Value::Array(rows));
+    }
+    let mut doc = TeaLeaf {
+        schemas: registry.schemas.clone(),
+        unions: registry.unions.clone(),
+        data,
+        is_root_array: false,
+        is_root_primitive: false,
+        crlf: false,
+        bindings: IndexMap::new(),
+    };
+    for schema in registry.schemas.keys() {
+        doc.bind(&plural(schema), schema);
+    }
+    doc
+}
+
+/// Plural of a schema name, the inverse of the singularizing that maps
+/// table keys to schemas.
+fn plural(name: &str) -> String {
+    let vowel_y = ["ay", "ey", "iy", "oy", "uy"].iter().any(|end| name.ends_with(end));
+    if name.ends_with('y') && !vowel_y {
+        format!("{}ies", &name[..name.len() - 1])
+    } else if ["s", "x", "ch", "sh"].iter().any(|end| name.ends_with(end)) {
+        format!("{}es", name)
+    } else {
+        format!("{}s", name)
+    }
+}
+
+/// SplitMix64: small, fast and the same on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`; `n` must be nonzero.
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn between(&mut self, low: i64, high: i64) -> i64 {
+        low + self.below((high - low) as u64 + 1) as i64
+    }
+
+    fn percent(&mut self, p: u64) -> bool {
+        self.below(100) < p
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
+
+struct Faker<'a> {
+    registry: &'a SchemaRegistry,
+    rng: Rng,
+}
+
+impl Faker<'_> {
+    fn object(&mut self, schema: &Schema, row: usize, depth: usize) -> Value {
+        let mut obj = ObjectMap::new();
+        for field in &schema.fields {
+            let value = self.field(&field.name, &field.field_type, row, depth);
+            obj.insert(field.name.clone(), value);
+        }
+        Value::Object(obj)
+    }
+
+    fn field(&mut self, name: &str, field_type: &FieldType, row: usize, depth: usize) -> Value {
+        let nested = self.registry.schema(&field_type.base).is_some() || self.registry.union(&field_type.base).is_some();
+        if field_type.nullable && (nested && depth >= MAX_DEPTH || self.rng.percent(NULL_PERCENT)) {
+            return Value::Null;
+        }
+        if field_type.is_array {
+            let len = if depth < MAX_DEPTH { self.rng.below(4) } else { 0 };
+            return Value::Array((0..len).map(|_| self.scalar(name, &field_type.base, row, depth)).collect());
+        }
+        self.scalar(name, &field_type.base, row, depth)
+    }
+
+    fn scalar(&mut self, name: &str, base: &str, row: usize, depth: usize) -> Value {
+        let lower = name.to_ascii_lowercase();
+        match base {
+            "bool" => Value::Bool(self.rng.percent(50)),
+            "int8" => Value::Int(self.int(&lower, row, i8::MIN as i64, i8::MAX as i64)),
+            "int16" => Value::Int(self.int(&lower, row, i16::MIN as i64, i16::MAX as i64)),
+            "int" | "int32" => Value::Int(self.int(&lower, row, i32::MIN as i64, i32::MAX as i64)),
+            "int64" => Value::Int(self.int(&lower, row, i64::MIN, i64::MAX)),
+            "uint8" => Value::UInt(self.int(&lower, row, 0, u8::MAX as i64) as u64),
+            "uint16" => Value::UInt(self.int(&lower, row, 0, u16::MAX as i64) as u64),
+            "uint" | "uint32" => Value::UInt(self.int(&lower, row, 0, u32::MAX as i64) as u64),
+            "uint64" => Value::UInt(self.int(&lower, row, 0, i64::MAX) as u64),
+            "float" | "float64" | "float32" => Value::Float(self.float(&lower)),
+            "bytesize" => Value::Int(self.rng.between(0, 1 << 30)),
+            "duration" => Value::Int(self.rng.between(0, 3_600_000)),
+            "bytes" => Value::Bytes((0..16).map(|_| self.rng.next() as u8).collect()),
+            "timestamp" => Value::Timestamp(EPOCH_2020_MS + self.rng.below(FIVE_YEARS_MS) as i64, 0),
+            "timestamp_ns" => {
+                let ms = EPOCH_2020_MS + self.rng.below(FIVE_YEARS_MS) as i64;
+                Value::TimestampNanos(ms * 1_000_000 + self.rng.below(1_000_000) as i64, 0, TimePrecision::Nanos)
+            }
+            "object" => {
+                let mut obj = ObjectMap::new();
+                obj.insert(self.rng.pick(WORDS).to_string(), Value::String(self.rng.pick(WORDS).to_string()));
+                Value::Object(obj)
+            }
+            "map" => Value::Map((0..self.rng.below(3))
+                .map(|_| (Value::String(self.rng.pick(WORDS).to_string()), Value::Int(self.rng.between(0, 100))))
+                .collect()),
+            "tuple" => Value::Array(vec![Value::Int(self.rng.between(0, 100)), Value::Int(self.rng.between(0, 100))]),
+            "string" => Value::String(self.string(&lower, row)),
+            "secret" => Value::String(format!("sk_{:016x}", self.rng.next())),
+            other => {
+                let registry = self.registry;
+                if let Some(schema) = registry.schema(other) {
+                    if depth >= CYCLE_LIMIT {
+                        return Value::Null;
+                    }
+                    return self.object(schema, row, depth + 1);
+                }
+                if let Some(union) = registry.union(other).filter(|u| !u.variants.is_empty()) {
+                    if depth >= CYCLE_LIMIT {
+                        return Value::Null;
+                    }
+                    let variant = &union.variants[self.rng.below(union.variants.len() as u64) as usize];
+                    let mut obj = ObjectMap::new();
+                    for field in &variant.fields {
+                        let value = self.field(&field.name, &field.field_type, row, depth + 1);
+                        obj.insert(field.name.clone(), value);
+                    }
+                    return Value::Tagged(variant.name.clone(), Box::new(Value::Object(obj)));
+                }
+                // `any` and names the registry does not define
+                Value::String(self.string(&lower, row))
+            }
+        }
+    }
+
+    fn int(&mut self, name: &str, row: usize, min: i64, max: i64) -> i64 {
+        if name == "id" {
+            return (row as i64 + 1).clamp(min, max);
+        }
+        let (low, high) = if name.ends_with("_id") {
+            (1, 10_000)
+        } else if name.contains("age") {
+            (18, 90)
+        } else if name.contains("year") {
+            (1990, 2025)
+        } else if ["count", "quantity", "qty", "stock"].iter().any(|w| name.contains(w)) {
+            (0, 100)
+        } else {
+            (0, 10_000)
+        };
+        self.rng.between(low.clamp(min, max), high.clamp(min, max))
+    }
+
+    fn float(&mut self, name: &str) -> f64 {
+        let (low, high) = if name.starts_with("lat") {
+            (-90.0, 90.0)
+        } else if name.starts_with("lon") || name.starts_with("lng") {
+            (-180.0, 180.0)
+        } else if ["price", "amount", "cost", "total"].iter().any(|w| name.contains(w)) {
+            (0.99, 999.99)
+        } else if ["rate", "ratio", "score"].iter().any(|w| name.contains(w)) {
+            (0.0, 1.0)
+        } else {
+            (0.0, 1000.0)
+        };
+        let cents = self.rng.between((low * 100.0) as i64, (high * 100.0) as i64);
+        cents as f64 / 100.0
+    }
+
+    fn string(&mut self, name: &str, row: usize) -> String {
+        let first = self.rng.pick(FIRST_NAMES);
+        let last = self.rng.pick(LAST_NAMES);
+        if name.contains("email") {
+            format!("{}.{}{}@example.com", first.to_ascii_lowercase(), last.to_ascii_lowercase(), row)
+        } else if name.contains("first_name") || name.contains("firstname") {
+            first.to_string()
+        } else if name.contains("last_name") || name.contains("lastname") || name.contains("surname") {
+            last.to_string()
+        } else if name.contains("name") || name.contains("author") || name.contains("user") {
+            format!("{} {}", first, last)
+        } else if name.contains("city") {
+            self.rng.pick(CITIES).to_string()
+        } else if name.contains("country") {
+            self.rng.pick(COUNTRIES).to_string()
+        } else if name.contains("status") || name.contains("state") {
+            self.rng.pick(STATUSES).to_string()
+        } else if name.contains("phone") {
+            format!("+1-555-{:04}", self.rng.below(10_000))
+        } else if name.contains("url") || name.contains("website") {
+            format!("https://example.com/{}/{}", self.rng.pick(WORDS), row)
+        } else if name == "id" || name.ends_with("_id") || name.contains("uuid") {
+            format!("{:016x}", self.rng.next())
+        } else if ["description", "text", "comment", "note", "body", "summary"].iter().any(|w| name.contains(w)) {
+            let words: Vec<&str> = (0..8).map(|_| self.rng.pick(WORDS)).collect();
+            words.join(" ")
+        } else {
+            let words: Vec<&str> = (0..1 + self.rng.below(2)).map(|_| self.rng.pick(WORDS)).collect();
+            words.join(" ")
+        }
+    }
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    const SCHEMAS: &str = r#"
+        @struct address (city: string, country: string)
+        @union payment { card (last4: string), cash () }
+        @struct user (id: int, email: string, age: int8, score: float?, address: address, tags: []string, joined: timestamp)
+        @struct node (id: uint, child: node?, payment: payment)
+        @struct category (name: string)
+    "#;
+
+    fn registry() -> SchemaRegistry {
+        TeaLeaf::parse(SCHEMAS).unwrap().schema_registry()
+    }
+
+    #[test]
+    fn test_rows_conform_to_schemas() {
+        let doc = generate(&registry(), 50, 7);
+        let keys: Vec<&str> = doc.data.keys().map(|k| k.as_str()).collect();
+        assert_eq!(keys, ["addresses", "users", "nodes", "categories"]);
+        assert_eq!(doc.binding("users"), Some("user"));
+
+        let users = doc.get("users").unwrap().as_array().unwrap();
+        assert_eq!(users.len(), 50);
+        let mut nulls = 0;
+        for (i, user) in users.iter().enumerate() {
+            let user = user.as_object().unwrap();
+            assert_eq!(user.get("id"), Some(&Value::Int(i as i64 + 1)));
+            assert!(user["email"].as_str().unwrap().ends_with("@example.com"));
+            assert!((18..=90).contains(&user["age"].as_int().unwrap()));
+            assert!(user["address"].as_object().unwrap().contains_key("city"));
+            assert!(user["tags"].as_array().unwrap().len() <= 3);
+            assert!(matches!(user["joined"], Value::Timestamp(..)));
+            nulls += user["score"].is_null() as usize;
+        }
+        assert!(nulls > 0 && nulls < 25, "{} null scores", nulls);
+
+        // Self-references end at the depth limit; union fields hold variants
+        for node in doc.get("nodes").unwrap().as_array().unwrap() {
+            let mut node = node.as_object().unwrap();
+            let mut depth = 0;
+            loop {
+                assert!(matches!(&node["payment"], Value::Tagged(tag, _) if tag == "card" || tag == "cash"));
+                match node["child"].as_object() {
+                    Some(child) => node = child,
+                    None => break,
+                }
+                depth += 1;
+            }
+            assert!(depth <= MAX_DEPTH);
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_data() {
+        let registry = registry();
+        assert_eq!(generate(&registry, 20, 1).data, generate(&registry, 20, 1).data);
+        assert_ne!(generate(&registry, 20, 1).data, generate(&registry, 20, 2).data);
+        assert!(generate(&registry, 0, 1).get("users").unwrap().as_array().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "binary")]
+    #[test]
+    fn test_compiles_and_reads_back() {
+        let doc = generate(&registry(), 200, 3);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fake.tlbx");
+        assert!(doc.compile_with_warnings(&path, true).unwrap().is_empty());
+        let loaded = TeaLeaf::from_reader(&crate::Reader::open(&path).unwrap()).unwrap();
+        assert_eq!(loaded.data, doc.data);
+        // Text reads `uint` fields back as Int, so compare a table without one
+        let reparsed = TeaLeaf::parse(&doc.to_tl_with_schemas()).unwrap();
+        assert_eq!(reparsed.get("users"), doc.get("users"));
+    }
+
+    #[test]
+    fn test_plural() {
+        assert_eq!(plural("user"), "users");
+        assert_eq!(plural("category"), "categories");
+        assert_eq!(plural("day"), "days");
+        assert_eq!(plural("box"), "boxes");
+        assert_eq!(plural("address"), "addresses");
+    }
+}
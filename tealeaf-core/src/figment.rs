@@ -0,0 +1,200 @@
+//! A [figment](https://docs.rs/figment) configuration format for TeaLeaf.
+//!
+//! [`TeaLeafFormat`] implements figment's `Format`, so `.tl` files layer with
+//! TOML, JSON and environment sources like any other format, with the usual
+//! `file`, `file_exact`, `string`, `nested` and `profile` options:
+//!
+//! ```no_run
+//! use figment::{Figment, providers::{Format, Serialized}};
+//! use tealeaf::figment::TeaLeafFormat;
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Config { host: String, port: u16 }
+//!
+//! let config: Config = Figment::from(Serialized::default("port", 8080))
+//!     .merge(TeaLeafFormat::file("app.tl"))
+//!     .merge(TeaLeafFormat::file("local.tl"))
+//!     .extract()?;
+//! # Ok::<(), figment::Error>(())
+//! ```
+//!
+//! Top-level keys become the dictionary's keys. References are resolved
+//! first, and `@include` paths are relative to the including file. Tagged
+//! values become one-entry dictionaries `{tag: value}`, or just `tag` when
+//! the value is null, serde's forms for enum variants. Timestamps become
+//! ISO 8601 strings, bytes arrays of `u8`, and maps dictionaries keyed by
+//! their string or integer keys.
+
+use std::path::Path;
+
+use ::figment::providers::Format;
+use ::figment::value::{Dict, Empty, Tag, Value as FigmentValue};
+use serde::de::DeserializeOwned;
+
+use crate::pipeline::{Pass, Pipeline};
+use crate::{format_timestamp_millis, format_timestamp_nanos, TeaLeaf, Value};
+
+/// The TeaLeaf text format, as a figment `Format`.
+pub struct TeaLeafFormat;
+
+impl Format for TeaLeafFormat {
+    type Error = ::figment::Error;
+
+    const NAME: &'static str = "TeaLeaf";
+
+    fn from_str<T: DeserializeOwned>(string: &str) -> Result<T, Self::Error> {
+        deserialize(TeaLeaf::parse(string).map_err(|e| e.to_string())?)
+    }
+
+    fn from_path<T: DeserializeOwned>(path: &Path) -> Result<T, Self::Error> {
+        deserialize(TeaLeaf::load(path).map_err(|e| format!("{}: {}", path.display(), e))?)
+    }
+}
+
+// figment's own error type, which every provider returns
+#[allow(clippy::result_large_err)]
+fn deserialize<T: DeserializeOwned>(mut doc: TeaLeaf) -> Result<T, ::figment::Error> {
+    Pipeline::new().pass(Pass::ResolveRefs).run(&mut doc).map_err(|e| e.to_string())?;
+    let mut dict = Dict::new();
+    for (key, value) in &doc.data {
+        dict.insert(key.clone(), to_figment(value)?);
+    }
+    T::deserialize(&FigmentValue::from(dict))
+}
+
+fn to_figment(value: &Value) -> Result<FigmentValue, String> {
+    Ok(match value {
+        Value::Null => FigmentValue::from(Empty::None),
+        Value::Bool(b) => FigmentValue::from(*b),
+        Value::Int(i) => FigmentValue::from(*i),
+        Value::UInt(u) => FigmentValue::from(*u),
+        Value::Float(f) => FigmentValue::from(*f),
+        Value::String(s) => FigmentValue::from(s.clone()),
+        Value::Bytes(b) => FigmentValue::from(b.clone()),
+        Value::Array(items) => {
+            let items = items.iter().map(to_figment).collect::<Result<Vec<_>, _>>()?;
+            FigmentValue::Array(Tag::Default, items)
+        }
+        Value::Object(obj) => {
+            let mut dict = Dict::new();
+            for (k, v) in obj {
+                dict.insert(k.clone(), to_figment(v)?);
+            }
+            FigmentValue::from(dict)
+        }
+        Value::Map(pairs) => {
+            let mut dict = Dict::new();
+            for (k, v) in pairs {
+                let key = match k {
+                    Value::String(s) => s.clone(),
+                    Value::Int(i) => i.to_string(),
+                    Value::UInt(u) => u.to_string(),
+                    other => return Err(format!("map key {:?} cannot be a configuration key", other)),
+                };
+                dict.insert(key, to_figment(v)?);
+            }
+            FigmentValue::from(dict)
+        }
+        Value::Ref(name) => return Err(format!("unresolved reference '!{}'", name)),
+        // A unit variant, as serde reads one
+        Value::Tagged(tag, inner) if inner.is_null() => FigmentValue::from(tag.clone()),
+        Value::Tagged(tag, inner) => {
+            let mut dict = Dict::new();
+            dict.insert(tag.clone(), to_figment(inner)?);
+            FigmentValue::from(dict)
+        }
+        Value::Timestamp(ts, tz) => FigmentValue::from(format_timestamp_millis(*ts, *tz)),
+        Value::TimestampNanos(ns, tz, precision) => FigmentValue::from(format_timestamp_nanos(*ns, *tz, *precision)),
+        Value::JsonNumber(s) => {
+            if let Ok(i) = s.parse::<i64>() {
+                FigmentValue::from(i)
+            } else if let Ok(u) = s.parse::<u64>() {
+                FigmentValue::from(u)
+            } else {
+                match s.parse::<f64>() {
+                    Ok(f) if f.is_finite() => FigmentValue::from(f),
+                    _ => FigmentValue::from(s.clone()),
+                }
+            }
+        }
+        // Configuration is read to be used, so secrets are handed over as is
+        Value::Secret(secret) => match secret.expose_str() {
+            Some(s) => FigmentValue::from(s.to_string()),
+            None => FigmentValue::from(secret.expose_bytes().to_vec()),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::figment::providers::Serialized;
+    use ::figment::Figment;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Backend {
+        Memory,
+        Redis { url: String },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        host: String,
+        port: u16,
+        debug: bool,
+        started: String,
+        limits: Vec<u32>,
+        backend: Backend,
+        fallback: Backend,
+    }
+
+    const APP: &str = r#"
+        defaults: {host: localhost, port: 8080}
+        server: !defaults
+        host: example.org
+        port: 9000
+        debug: false
+        started: 2024-01-15T10:30:00Z
+        limits: [10, 20]
+        backend: :Redis {url: "redis://cache"}
+        fallback: :Memory ~
+    "#;
+
+    #[test]
+    fn test_extracts_config() {
+        let figment = Figment::new()
+            .merge(TeaLeafFormat::string(APP))
+            .merge(Serialized::default("debug", true));
+        assert_eq!(figment.extract_inner::<u16>("server.port").unwrap(), 8080);
+        let config: Config = figment.extract().unwrap();
+        assert_eq!(config, Config {
+            host: "example.org".into(),
+            port: 9000,
+            debug: true,
+            started: "2024-01-15T10:30:00Z".into(),
+            limits: vec![10, 20],
+            backend: Backend::Redis { url: "redis://cache".into() },
+            fallback: Backend::Memory,
+        });
+    }
+
+    #[test]
+    fn test_files_profiles_and_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("base.tl"), "port: 1\n").unwrap();
+        let app = dir.path().join("app.tl");
+        std::fs::write(&app, "@include \"base.tl\"\nhost: h\n").unwrap();
+
+        let port = |figment: Figment| figment.extract_inner::<u16>("port").unwrap();
+        assert_eq!(port(Figment::new().merge(TeaLeafFormat::file_exact(&app))), 1);
+        let profiles = "default: {port: 2}\nprod: {port: 3}";
+        assert_eq!(port(Figment::new().merge(TeaLeafFormat::string(profiles).nested())), 2);
+        assert_eq!(port(Figment::new().merge(TeaLeafFormat::string(profiles).nested()).select("prod")), 3);
+
+        let err = Figment::new().merge(TeaLeafFormat::string("a: {")).extract_inner::<u16>("a").unwrap_err();
+        assert!(err.to_string().contains("TeaLeaf"), "{}", err);
+        let err = Figment::new().merge(TeaLeafFormat::string("a: !missing")).extract_inner::<u16>("a").unwrap_err();
+        assert!(err.to_string().contains("unresolved reference '!missing'"), "{}", err);
+    }
+}
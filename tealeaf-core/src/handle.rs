@@ -0,0 +1,207 @@
+//! Shared, swappable documents for long-running services.
+//!
+//! A [`DocumentHandle`] holds the current version of a document, such as a
+//! service's configuration. Readers take a cheap `Arc` of it and keep using
+//! that version for as long as they hold it. Writers build the next version
+//! off to the side and swap it in whole, so no reader ever sees a partly
+//! updated document.
+//!
+//! Every swap bumps a generation counter. State derived from the document
+//! can record the generation it was built from and compare that with
+//! [`DocumentHandle::generation`], which costs one atomic load, instead of
+//! comparing documents.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+use crate::{Result, TeaLeaf};
+
+/// A document shared between threads and replaced atomically.
+///
+/// Clones share the same document, generation and subscribers.
+///
+/// ```
+/// use tealeaf::{DocumentHandle, TeaLeaf};
+///
+/// let handle = DocumentHandle::new(TeaLeaf::parse("port: 8080")?);
+/// let cached = handle.generation();
+///
+/// handle.update(|doc| {
+///     let mut next = doc.clone();
+///     next.data.insert("port".into(), 9090i64.into());
+///     Ok(next)
+/// })?;
+/// assert!(handle.changed_since(cached));
+/// assert_eq!(handle.current().get("port").and_then(|v| v.as_int()), Some(9090));
+/// # Ok::<(), tealeaf::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct DocumentHandle {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    current: RwLock<(Arc<TeaLeaf>, u64)>,
+    /// Mirrors the generation in `current`, for lock-free checks
+    generation: AtomicU64,
+    /// Held while a new version is built and swapped in, so writers take turns
+    writer: Mutex<()>,
+    subscribers: Mutex<Vec<Sender<u64>>>,
+}
+
+impl DocumentHandle {
+    /// Share `doc` as generation 0.
+    pub fn new(doc: TeaLeaf) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                current: RwLock::new((Arc::new(doc), 0)),
+                generation: AtomicU64::new(0),
+                writer: Mutex::new(()),
+                subscribers: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// The current version of the document.
+    pub fn current(&self) -> Arc<TeaLeaf> {
+        self.snapshot().0
+    }
+
+    /// The current version of the document together with its generation,
+    /// read at the same instant.
+    pub fn snapshot(&self) -> (Arc<TeaLeaf>, u64) {
+        let current = self.shared.current.read().unwrap_or_else(|e| e.into_inner());
+        (Arc::clone(&current.0), current.1)
+    }
+
+    /// How many times the document has been replaced.
+    pub fn generation(&self) -> u64 {
+        self.shared.generation.load(Ordering::Acquire)
+    }
+
+    /// Whether the document has been replaced since `generation`.
+    pub fn changed_since(&self, generation: u64) -> bool {
+        self.generation() != generation
+    }
+
+    /// Swap in `doc` as the current version, returning its generation.
+    pub fn replace(&self, doc: TeaLeaf) -> u64 {
+        let _writer = self.writer();
+        self.swap(doc)
+    }
+
+    /// Build the next version from the current one and swap it in,
+    /// returning its generation.
+    ///
+    /// Readers keep the current version while `build` runs; other writers
+    /// wait for it. If `build` fails, nothing changes.
+    pub fn update(&self, build: impl FnOnce(&TeaLeaf) -> Result<TeaLeaf>) -> Result<u64> {
+        let _writer = self.writer();
+        let next = build(&self.current())?;
+        Ok(self.swap(next))
+    }
+
+    /// Parse the text file at `path` and swap it in, returning its
+    /// generation. If the file cannot be read or parsed, the current version
+    /// stays.
+    #[cfg(feature = "text")]
+    pub fn reload<P: AsRef<std::path::Path>>(&self, path: P) -> Result<u64> {
+        self.update(|_| TeaLeaf::load(path))
+    }
+
+    /// Receive the generation of every version swapped in from now on.
+    ///
+    /// A receiver that falls behind gets each generation in order; one that
+    /// only needs the latest can drain with `try_iter().last()`. Dropping
+    /// the receiver unsubscribes it.
+    pub fn subscribe(&self) -> Receiver<u64> {
+        let (tx, rx) = mpsc::channel();
+        self.shared.subscribers.lock().unwrap_or_else(|e| e.into_inner()).push(tx);
+        rx
+    }
+
+    fn writer(&self) -> MutexGuard<'_, ()> {
+        // A writer that panicked while building swapped nothing in
+        self.shared.writer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn swap(&self, doc: TeaLeaf) -> u64 {
+        let generation = {
+            let mut current = self.shared.current.write().unwrap_or_else(|e| e.into_inner());
+            let generation = current.1 + 1;
+            *current = (Arc::new(doc), generation);
+            self.shared.generation.store(generation, Ordering::Release);
+            generation
+        };
+        self.shared.subscribers.lock().unwrap_or_else(|e| e.into_inner())
+            .retain(|tx| tx.send(generation).is_ok());
+        generation
+    }
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+    use crate::{Error, Value};
+
+    fn port(doc: &TeaLeaf) -> Option<i64> {
+        doc.get("port").and_then(Value::as_int)
+    }
+
+    #[test]
+    fn test_replace_bumps_generation() {
+        let handle = DocumentHandle::new(TeaLeaf::parse("port: 1").unwrap());
+        let old = handle.current();
+        assert_eq!(handle.generation(), 0);
+
+        assert_eq!(handle.replace(TeaLeaf::parse("port: 2").unwrap()), 1);
+        assert!(handle.changed_since(0) && !handle.changed_since(1));
+        // Readers holding the old version keep it
+        assert_eq!(port(&old), Some(1));
+        let (doc, generation) = handle.clone().snapshot();
+        assert_eq!((port(&doc), generation), (Some(2), 1));
+    }
+
+    #[test]
+    fn test_failed_update_and_reload_keep_current() {
+        let handle = DocumentHandle::new(TeaLeaf::parse("port: 1").unwrap());
+        let err = handle.update(|_| Err(Error::ParseError("bad".into())));
+        assert!(err.is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.tl");
+        std::fs::write(&path, "port: [").unwrap();
+        assert!(handle.reload(&path).is_err());
+        assert_eq!((port(&handle.current()), handle.generation()), (Some(1), 0));
+
+        std::fs::write(&path, "port: 3").unwrap();
+        assert_eq!(handle.reload(&path).unwrap(), 1);
+        assert_eq!(port(&handle.current()), Some(3));
+    }
+
+    #[test]
+    fn test_concurrent_updates_and_subscribers() {
+        let handle = DocumentHandle::new(TeaLeaf::parse("port: 0").unwrap());
+        let changes = handle.subscribe();
+        drop(handle.subscribe());
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let handle = handle.clone();
+                s.spawn(move || {
+                    for _ in 0..25 {
+                        handle.update(|doc| {
+                            let mut next = doc.clone();
+                            next.data.insert("port".into(), Value::Int(port(doc).unwrap() + 1));
+                            Ok(next)
+                        }).unwrap();
+                    }
+                });
+            }
+        });
+        // No update was lost, and every generation was announced in order
+        assert_eq!(port(&handle.current()), Some(100));
+        assert_eq!(changes.try_iter().collect::<Vec<_>>(), (1..=100).collect::<Vec<_>>());
+        assert_eq!(handle.shared.subscribers.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,444 @@
+//! Localization string tables.
+//!
+//! By convention, UI strings live in a `translations` section typed by the
+//! `translation` schema:
+//!
+//! ```text
+//! @struct translation (key: string, locale: string, text: string)
+//! translations: @table translation [
+//!     (greeting, en, "Hello"),
+//!     (greeting, fr, "Bonjour"),
+//! ]
+//! fallback_locale: en
+//! ```
+//!
+//! A lookup tries the requested locale, then its language (`pt-BR` → `pt`),
+//! then the document's `fallback_locale` and its language.
+//! [`TranslationTable`] indexes such a section once so that lookups are hash
+//! probes rather than scans over every row. Binary files carry an index of
+//! their own: the writer appends the rows' `(key, locale, text)` string
+//! indices, sorted, after the table, and [`Reader::lookup_translation`]
+//! binary-searches it without decoding any row.
+
+use std::collections::HashMap;
+#[cfg(feature = "binary")]
+use std::collections::BTreeMap;
+
+use crate::{Error, FieldType, Result, Schema, TeaLeaf, Value};
+#[cfg(feature = "binary")]
+use crate::Reader;
+
+/// Schema name for translation rows.
+pub const TRANSLATION_SCHEMA: &str = "translation";
+/// Conventional section key holding the translation table.
+pub const TRANSLATIONS_KEY: &str = "translations";
+/// Conventional key holding the locale to fall back to.
+pub const FALLBACK_LOCALE_KEY: &str = "fallback_locale";
+
+/// The `translation` schema: `(key: string, locale: string, text: string)`.
+pub fn translation_schema() -> Schema {
+    Schema::new(TRANSLATION_SCHEMA)
+        .field("key", FieldType::new("string"))
+        .field("locale", FieldType::new("string"))
+        .field("text", FieldType::new("string"))
+}
+
+/// Indexed view of a translation section.
+///
+/// Lookups fall back from a regional locale to its language (`pt-BR` → `pt`),
+/// then to the fallback locale, if one is set, and its language.
+#[derive(Debug, Clone, Default)]
+pub struct TranslationTable {
+    entries: HashMap<String, HashMap<String, String>>,
+    fallback_locale: Option<String>,
+}
+
+impl TranslationTable {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a table from an array of `translation` rows.
+    ///
+    /// Later rows override earlier rows with the same key and locale.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let rows = value.as_array().ok_or_else(|| {
+            Error::ParseError("translation table must be an array".into())
+        })?;
+        let mut table = Self::new();
+        for (i, row) in rows.iter().enumerate() {
+            let field = |name: &str| {
+                row.get(name).and_then(|v| v.as_str()).ok_or_else(|| {
+                    Error::MissingField(format!("{}[{}].{}", TRANSLATIONS_KEY, i, name))
+                })
+            };
+            table.insert(field("key")?, field("locale")?, field("text")?);
+        }
+        Ok(table)
+    }
+
+    /// Set the locale consulted when neither the requested locale nor its
+    /// language has an entry.
+    pub fn with_fallback_locale(mut self, locale: impl Into<String>) -> Self {
+        self.fallback_locale = Some(locale.into());
+        self
+    }
+
+    /// Add or replace a single entry.
+    pub fn insert(&mut self, key: &str, locale: &str, text: &str) {
+        self.entries
+            .entry(key.to_string())
+            .or_default()
+            .insert(locale.to_string(), text.to_string());
+    }
+
+    /// Look up the text for `key` in `locale`, applying locale fallback.
+    pub fn lookup(&self, key: &str, locale: &str) -> Option<&str> {
+        let by_locale = self.entries.get(key)?;
+        fallback_chain(locale, self.fallback_locale.as_deref())
+            .into_iter()
+            .find_map(|loc| by_locale.get(loc))
+            .map(|s| s.as_str())
+    }
+
+    /// Number of distinct keys.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All locales present in the table, sorted.
+    pub fn locales(&self) -> Vec<&str> {
+        let mut locales: Vec<&str> = self.entries.values()
+            .flat_map(|m| m.keys().map(|l| l.as_str()))
+            .collect();
+        locales.sort_unstable();
+        locales.dedup();
+        locales
+    }
+
+    /// Convert back to an array of `translation` rows, sorted by key then locale.
+    pub fn to_value(&self) -> Value {
+        let mut rows: Vec<(&String, &String, &String)> = self.entries.iter()
+            .flat_map(|(k, m)| m.iter().map(move |(l, t)| (k, l, t)))
+            .collect();
+        rows.sort_unstable_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        Value::Array(rows.into_iter().map(|(k, l, t)| {
+            let mut obj = crate::ObjectMap::new();
+            obj.insert("key".to_string(), Value::String(k.clone()));
+            obj.insert("locale".to_string(), Value::String(l.clone()));
+            obj.insert("text".to_string(), Value::String(t.clone()));
+            Value::Object(obj)
+        }).collect())
+    }
+}
+
+/// The language part of a regional locale (`en-US` / `en_US` → `en`).
+fn language_of(locale: &str) -> Option<&str> {
+    locale.find(['-', '_']).map(|i| &locale[..i])
+}
+
+/// The locales a lookup tries, in order: `locale`, its language, then
+/// `fallback` and its language, each once.
+fn fallback_chain<'a>(locale: &'a str, fallback: Option<&'a str>) -> Vec<&'a str> {
+    let mut chain: Vec<&str> = Vec::with_capacity(4);
+    let candidates = [Some(locale), language_of(locale), fallback, fallback.and_then(language_of)];
+    for loc in candidates.into_iter().flatten() {
+        if !chain.contains(&loc) {
+            chain.push(loc);
+        }
+    }
+    chain
+}
+
+/// The translation index of `rows`, with strings numbered by `intern`:
+///
+/// ```text
+/// entry_count: u32
+/// per entry, sorted by key then locale: key: u32, locale: u32, text: u32
+/// ```
+///
+/// Rows missing a field are left out, and of rows with the same key and
+/// locale only the last is kept.
+#[cfg(feature = "binary")]
+pub(crate) fn encode_index(rows: &[Value], mut intern: impl FnMut(&str) -> u32) -> Vec<u8> {
+    let entries = index_entries(rows);
+    let mut data = Vec::with_capacity(4 + entries.len() * 12);
+    data.extend((entries.len() as u32).to_le_bytes());
+    for ((key, locale), text) in entries {
+        for s in [key, locale, text] {
+            data.extend(intern(s).to_le_bytes());
+        }
+    }
+    data
+}
+
+/// The entries of the translation index of `rows`, as `(key, locale)` to
+/// text.
+#[cfg(feature = "binary")]
+pub(crate) fn index_entries(rows: &[Value]) -> BTreeMap<(&str, &str), &str> {
+    let mut entries = BTreeMap::new();
+    for row in rows {
+        let field = |name: &str| row.get(name).and_then(Value::as_str);
+        if let (Some(key), Some(locale), Some(text)) = (field("key"), field("locale"), field("text")) {
+            entries.insert((key, locale), text);
+        }
+    }
+    entries
+}
+
+impl TeaLeaf {
+    /// Build an indexed [`TranslationTable`] from the `translations` section,
+    /// falling back to the document's `fallback_locale` if it has one.
+    ///
+    /// Prefer this over repeated [`lookup_translation`](Self::lookup_translation)
+    /// calls when resolving many strings.
+    pub fn translations(&self) -> Result<TranslationTable> {
+        let value = self.get(TRANSLATIONS_KEY)
+            .ok_or_else(|| Error::MissingField(TRANSLATIONS_KEY.to_string()))?;
+        let table = TranslationTable::from_value(value)?;
+        Ok(match self.get(FALLBACK_LOCALE_KEY).and_then(Value::as_str) {
+            Some(fallback) => table.with_fallback_locale(fallback),
+            None => table,
+        })
+    }
+
+    /// Look up a single string from the `translations` section, applying the
+    /// same fallback as [`TranslationTable::lookup`] with the document's
+    /// `fallback_locale`.
+    ///
+    /// This scans the section once without building an index.
+    pub fn lookup_translation(&self, key: &str, locale: &str) -> Option<&str> {
+        let rows = self.get(TRANSLATIONS_KEY)?.as_array()?;
+        let chain = fallback_chain(locale, self.get(FALLBACK_LOCALE_KEY).and_then(Value::as_str));
+        // The row of the earliest locale in the chain, the last such row winning
+        let mut best: Option<(usize, &str)> = None;
+        for row in rows {
+            let field = |name: &str| row.get(name).and_then(Value::as_str);
+            if field("key") != Some(key) {
+                continue;
+            }
+            let rank = field("locale").and_then(|loc| chain.iter().position(|c| *c == loc));
+            if let (Some(rank), Some(text)) = (rank, field("text")) {
+                if best.is_none_or(|(best_rank, _)| rank <= best_rank) {
+                    best = Some((rank, text));
+                }
+            }
+        }
+        best.map(|(_, text)| text)
+    }
+}
+
+#[cfg(feature = "binary")]
+impl Reader {
+    /// Decode the `translations` section and index it, falling back to the
+    /// file's `fallback_locale` if it has one.
+    pub fn translations(&self) -> Result<TranslationTable> {
+        let table = TranslationTable::from_value(&self.get(TRANSLATIONS_KEY)?)?;
+        Ok(match self.fallback_locale()? {
+            Some(fallback) => table.with_fallback_locale(fallback),
+            None => table,
+        })
+    }
+
+    /// Look up a single string from the `translations` section, applying the
+    /// same fallback as [`TeaLeaf::lookup_translation`]. `None` if there is
+    /// no such string or no such section.
+    ///
+    /// Files written by this library index the section, so each locale
+    /// tried is a binary search over string indices and no row is decoded;
+    /// other files are decoded and scanned.
+    pub fn lookup_translation(&self, key: &str, locale: &str) -> Result<Option<String>> {
+        let fallback = self.fallback_locale()?;
+        let chain = fallback_chain(locale, fallback.as_deref());
+        let Some((data, offset)) = self.lookup_index(TRANSLATIONS_KEY)? else {
+            return match self.keys().contains(&TRANSLATIONS_KEY) {
+                true => Ok(self.translations()?.lookup(key, locale).map(str::to_string)),
+                false => Ok(None),
+            };
+        };
+        let index = data.get(offset..).ok_or_else(|| invalid_index("offset past the section"))?;
+        let count = index.get(..4).ok_or_else(|| invalid_index("entry count past the section"))?;
+        let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+        let entries = index.get(4..).filter(|e| e.len() / 12 >= count).ok_or_else(|| invalid_index("entries past the section"))?;
+        let entry = |i: usize, field: usize| u32::from_le_bytes(entries[i * 12 + field * 4..][..4].try_into().unwrap()) as usize;
+        for loc in chain {
+            let (mut lo, mut hi) = (0, count);
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                let (k, l) = (self.get_string(entry(mid, 0))?, self.get_string(entry(mid, 1))?);
+                match (k.as_str(), l.as_str()).cmp(&(key, loc)) {
+                    std::cmp::Ordering::Less => lo = mid + 1,
+                    std::cmp::Ordering::Greater => hi = mid,
+                    std::cmp::Ordering::Equal => return self.get_string(entry(mid, 2)).map(Some),
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn fallback_locale(&self) -> Result<Option<String>> {
+        if !self.keys().contains(&FALLBACK_LOCALE_KEY) {
+            return Ok(None);
+        }
+        Ok(self.get(FALLBACK_LOCALE_KEY)?.as_str().map(str::to_string))
+    }
+}
+
+#[cfg(feature = "binary")]
+fn invalid_index(what: &str) -> Error {
+    Error::ParseError(format!("translation index of section '{}': {}", TRANSLATIONS_KEY, what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "text")]
+    const DOC: &str = r#"
+        @struct translation (key: string, locale: string, text: string)
+        translations: @table translation [
+            (greeting, en, "Hello"),
+            (greeting, fr, "Bonjour"),
+            (greeting, pt-BR, "Olá"),
+            (farewell, en, "Goodbye"),
+        ]
+    "#;
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_lookup_translation_exact_and_language_fallback() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        assert_eq!(doc.lookup_translation("greeting", "fr"), Some("Bonjour"));
+        assert_eq!(doc.lookup_translation("greeting", "en-GB"), Some("Hello"));
+        assert_eq!(doc.lookup_translation("farewell", "fr"), None);
+        assert_eq!(doc.lookup_translation("missing", "en"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_translation_table_index() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let table = doc.translations().unwrap().with_fallback_locale("en");
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.lookup("greeting", "pt-BR"), Some("Olá"));
+        assert_eq!(table.lookup("greeting", "en_US"), Some("Hello"));
+        assert_eq!(table.lookup("farewell", "fr"), Some("Goodbye"));
+        assert_eq!(table.locales(), vec!["en", "fr", "pt-BR"]);
+    }
+
+    #[test]
+    #[cfg(all(feature = "text", feature = "binary"))]
+    fn test_translation_table_binary_roundtrip() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("strings.tlbx");
+        doc.compile(&path, true).unwrap();
+
+        let reader = Reader::open(&path).unwrap();
+        let table = reader.translations().unwrap();
+        assert_eq!(table.lookup("greeting", "fr"), Some("Bonjour"));
+        assert_eq!(reader.get(TRANSLATIONS_KEY).unwrap(), *doc.get(TRANSLATIONS_KEY).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_document_fallback_locale() {
+        let doc = TeaLeaf::parse(&format!("{}\nfallback_locale: en\n", DOC)).unwrap();
+        assert_eq!(doc.lookup_translation("farewell", "pt-BR"), Some("Goodbye"));
+        assert_eq!(doc.lookup_translation("greeting", "pt-BR"), Some("Olá"));
+        assert_eq!(doc.translations().unwrap().lookup("farewell", "fr"), Some("Goodbye"));
+        assert_eq!(fallback_chain("pt-BR", Some("en-GB")), vec!["pt-BR", "pt", "en-GB", "en"]);
+        assert_eq!(fallback_chain("en", Some("en")), vec!["en"]);
+    }
+
+    #[test]
+    #[cfg(all(feature = "text", feature = "binary"))]
+    fn test_binary_lookup_uses_index() {
+        let text = format!("{}\nfallback_locale: en\n", DOC);
+        let doc = TeaLeaf::parse(&text).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("strings.tlbx");
+        doc.compile(&path, true).unwrap();
+
+        let reader = Reader::open(&path).unwrap();
+        assert!(reader.lookup_index(TRANSLATIONS_KEY).unwrap().is_some());
+        let lookup = |key: &str, locale: &str| reader.lookup_translation(key, locale).unwrap();
+        assert_eq!(lookup("greeting", "fr").as_deref(), Some("Bonjour"));
+        assert_eq!(lookup("greeting", "pt-BR").as_deref(), Some("Olá"));
+        assert_eq!(lookup("greeting", "en-GB").as_deref(), Some("Hello"));
+        assert_eq!(lookup("farewell", "fr").as_deref(), Some("Goodbye"));
+        assert_eq!(lookup("missing", "en"), None);
+        assert_eq!(reader.translations().unwrap().lookup("farewell", "fr"), Some("Goodbye"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "text", feature = "binary"))]
+    fn test_binary_lookup_truncated_index() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("strings.tlbx");
+        doc.compile(&path, false).unwrap();
+
+        // Point the index of the only section two bytes before its end
+        let mut bytes = std::fs::read(&path).unwrap();
+        let entry = u64::from_le_bytes(bytes[32..40].try_into().unwrap()) as usize + 8;
+        let size = u32::from_le_bytes(bytes[entry + 16..entry + 20].try_into().unwrap());
+        bytes[entry + 28..entry + 32].copy_from_slice(&(size - 2).to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+
+        let err = Reader::open(&path).unwrap().lookup_translation("greeting", "en").unwrap_err();
+        assert!(err.to_string().contains("entry count past the section"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(all(feature = "text", feature = "binary"))]
+    fn test_binary_lookup_without_index() {
+        // A table of another schema is not indexed, and lookups decode it
+        let doc = TeaLeaf::parse(r#"
+            @struct translation (key: string, locale: string, text: string, note: string)
+            translations: @table translation [(greeting, en, "Hello", "")]
+            other: 1
+        "#).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("strings.tlbx");
+        doc.compile(&path, false).unwrap();
+
+        let reader = Reader::open(&path).unwrap();
+        assert!(reader.lookup_index(TRANSLATIONS_KEY).unwrap().is_none());
+        assert_eq!(reader.lookup_translation("greeting", "en-US").unwrap().as_deref(), Some("Hello"));
+
+        let path = dir.path().join("none.tlbx");
+        TeaLeaf::parse("other: 1").unwrap().compile(&path, false).unwrap();
+        assert_eq!(Reader::open(&path).unwrap().lookup_translation("greeting", "en").unwrap(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_translation_table_to_value_sorted() {
+        let mut table = TranslationTable::new();
+        table.insert("b", "en", "B");
+        table.insert("a", "fr", "A-fr");
+        table.insert("a", "en", "A");
+        let doc = TeaLeaf::new(
+            [(TRANSLATION_SCHEMA.to_string(), translation_schema())].into_iter().collect(),
+            [(TRANSLATIONS_KEY.to_string(), table.to_value())].into_iter().collect(),
+        );
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("@table translation"), "{}", text);
+        let rows = doc.get(TRANSLATIONS_KEY).unwrap().as_array().unwrap();
+        let keys: Vec<(&str, &str)> = rows.iter()
+            .map(|r| (r.get("key").unwrap().as_str().unwrap(), r.get("locale").unwrap().as_str().unwrap()))
+            .collect();
+        assert_eq!(keys, vec![("a", "en"), ("a", "fr"), ("b", "en")]);
+    }
+
+    #[test]
+    fn test_translation_table_rejects_non_array() {
+        assert!(TranslationTable::from_value(&Value::Int(1)).is_err());
+    }
+}
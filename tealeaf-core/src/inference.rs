@@ -0,0 +1,274 @@
+//! Reports on the decisions made by schema inference.
+//!
+//! [`TeaLeaf::from_json_with_schemas_with_report`](crate::TeaLeaf::from_json_with_schemas_with_report)
+//! returns an [`InferenceReport`] describing every inferred schema field:
+//! the kinds of value seen, how often it was null or missing, roughly how
+//! many distinct values it holds, and how strongly the data supports the
+//! type chosen. Review it before committing inferred schemas anywhere
+//! permanent.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::types::value_kind;
+use crate::{FieldType, IndexMap, InferredType, ObjectMap, Schema, Value, Warning};
+
+/// Values per field whose distinct count is measured.
+pub const CARDINALITY_SAMPLE: usize = 1000;
+
+/// What schema inference decided, and on what evidence.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InferenceReport {
+    /// One report per inferred schema, in inference order.
+    pub schemas: Vec<SchemaReport>,
+    /// Arrays and object fields left without a schema, with the reason.
+    pub skipped: Vec<Warning>,
+}
+
+/// Evidence behind one inferred schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaReport {
+    pub name: String,
+    /// Key or field whose objects the schema was inferred from.
+    pub source: String,
+    /// Number of objects examined.
+    pub objects: usize,
+    pub fields: Vec<FieldReport>,
+}
+
+/// Evidence behind one inferred field type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldReport {
+    pub name: String,
+    /// The type chosen.
+    pub field_type: FieldType,
+    /// Objects that have the field.
+    pub present: usize,
+    /// Objects whose value for the field is null.
+    pub nulls: usize,
+    /// Kind of each non-null value seen (`int`, `string`, `object`, ...),
+    /// with how often it was seen, in order of first appearance.
+    pub observed: IndexMap<&'static str, usize>,
+    /// Distinct non-null values among the first `sampled` ones.
+    pub distinct: usize,
+    /// Non-null values examined for `distinct`, at most [`CARDINALITY_SAMPLE`].
+    pub sampled: usize,
+    /// How strongly the values support the type, from 0 to 1: the lower
+    /// bound of the 95% Wilson score interval of the share of values that
+    /// support it. Few values give low confidence even when all agree.
+    pub confidence: f64,
+    /// Why the type was chosen, e.g. `all values are int` or
+    /// `values mix int and string`.
+    pub reason: String,
+}
+
+impl FieldReport {
+    /// Share of the schema's `objects` where the field is null or missing.
+    pub fn null_rate(&self, objects: usize) -> f64 {
+        if objects == 0 {
+            return 0.0;
+        }
+        (objects - self.present + self.nulls) as f64 / objects as f64
+    }
+
+    /// Whether every sampled value was distinct, as for identifiers.
+    pub fn looks_unique(&self) -> bool {
+        self.sampled > 1 && self.distinct == self.sampled
+    }
+}
+
+impl fmt::Display for InferenceReport {
+    /// One block per schema, one line per field:
+    ///
+    /// ```text
+    /// user (from users, 3 objects)
+    ///   id: int  nulls 0%  distinct 3/3  confidence 0.44  all values are int
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for schema in &self.schemas {
+            writeln!(f, "{} (from {}, {} objects)", schema.name, schema.source, schema.objects)?;
+            for field in &schema.fields {
+                writeln!(
+                    f,
+                    "  {}: {}  nulls {:.0}%  distinct {}/{}  confidence {:.2}  {}",
+                    field.name,
+                    field.field_type,
+                    field.null_rate(schema.objects) * 100.0,
+                    field.distinct,
+                    field.sampled,
+                    field.confidence,
+                    field.reason,
+                )?;
+            }
+        }
+        for warning in &self.skipped {
+            writeln!(f, "{}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+/// Report on `schema`, inferred from `objects` with the merged value type
+/// of each field in `inferred`.
+pub(crate) fn schema_report(
+    schema: &Schema,
+    source: &str,
+    objects: &[&ObjectMap<String, Value>],
+    inferred: &IndexMap<String, InferredType>,
+) -> SchemaReport {
+    let fields = schema.fields.iter()
+        .map(|field| {
+            let values: Vec<&Value> = objects.iter().filter_map(|obj| obj.get(&field.name)).collect();
+            field_report(&field.name, &field.field_type, &values, inferred.get(&field.name))
+        })
+        .collect();
+    SchemaReport {
+        name: schema.name.clone(),
+        source: source.to_string(),
+        objects: objects.len(),
+        fields,
+    }
+}
+
+fn field_report(name: &str, field_type: &FieldType, values: &[&Value], inferred: Option<&InferredType>) -> FieldReport {
+    let mut observed: IndexMap<&'static str, usize> = IndexMap::new();
+    let mut sample = HashSet::new();
+    let mut sampled = 0;
+    let mut nulls = 0;
+    for value in values {
+        if value.is_null() {
+            nulls += 1;
+            continue;
+        }
+        *observed.entry(value_kind(value)).or_insert(0) += 1;
+        if sampled < CARDINALITY_SAMPLE {
+            sampled += 1;
+            sample.insert(format!("{:?}", value));
+        }
+    }
+    let seen = values.len() - nulls;
+    let most_common = observed.values().copied().max().unwrap_or(0);
+    let kinds = || observed.keys().copied().collect::<Vec<_>>().join(" and ");
+
+    let (support, reason) = match inferred {
+        None | Some(InferredType::Null) => (0, "only nulls seen, defaulted to string".to_string()),
+        Some(InferredType::Mixed) if field_type.is_array => (seen, "arrays are empty or their elements mix kinds".to_string()),
+        // The fewer values disagree with the most common kind, the more
+        // likely `any` is hiding dirty data rather than a real mixture
+        Some(InferredType::Mixed) => (seen - most_common, format!("values mix {}", kinds())),
+        Some(InferredType::Object(_)) if field_type.base == "any" => (seen, "objects match no inferred schema".to_string()),
+        Some(InferredType::Object(_)) => (seen, format!("objects match schema '{}'", field_type.base)),
+        Some(InferredType::Array(_)) if field_type.base == "any" => (seen, "array elements mix kinds or match no schema".to_string()),
+        Some(InferredType::Array(_)) => (seen, format!("arrays of {}", field_type.base)),
+        Some(InferredType::Float) if observed.contains_key("int") => (seen, "int and float values widen to float".to_string()),
+        Some(_) => (seen, format!("all values are {}", kinds())),
+    };
+    FieldReport {
+        name: name.to_string(),
+        field_type: field_type.clone(),
+        present: values.len(),
+        nulls,
+        observed,
+        distinct: sample.len(),
+        sampled,
+        confidence: wilson_lower_bound(support, seen),
+        reason,
+    }
+}
+
+/// Lower bound of the 95% Wilson score interval for `successes` out of `n`.
+pub(crate) fn wilson_lower_bound(successes: usize, n: usize) -> f64 {
+    if n == 0 {
+        return 0.0;
+    }
+    const Z: f64 = 1.96;
+    let n = n as f64;
+    let p = successes as f64 / n;
+    let z2 = Z * Z;
+    let center = p + z2 / (2.0 * n);
+    let margin = Z * ((p * (1.0 - p) + z2 / (4.0 * n)) / n).sqrt();
+    ((center - margin) / (1.0 + z2 / n)).max(0.0)
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+    use crate::TeaLeaf;
+
+    fn report(json: &str) -> InferenceReport {
+        TeaLeaf::from_json_with_schemas_with_report(json).unwrap().1
+    }
+
+    fn field<'a>(schema: &'a SchemaReport, name: &str) -> &'a FieldReport {
+        schema.fields.iter().find(|f| f.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_field_evidence() {
+        let rows: Vec<String> = (0..40)
+            .map(|i| {
+                let score = if i % 2 == 0 { "1".to_string() } else { "1.5".to_string() };
+                let code = if i == 7 { "\"n/a\"".to_string() } else { i.to_string() };
+                let note = if i % 4 == 0 { "null".to_string() } else { "\"x\"".to_string() };
+                format!(r#"{{"id": {}, "kind": "{}", "score": {}, "code": {}, "note": {}, "gone": null}}"#,
+                    i, ["a", "b"][i % 2], score, code, note)
+            })
+            .collect();
+        let report = report(&format!(r#"{{"items": [{}]}}"#, rows.join(",")));
+        let items = &report.schemas[0];
+        assert_eq!((items.name.as_str(), items.source.as_str(), items.objects), ("item", "items", 40));
+
+        let id = field(items, "id");
+        assert_eq!(id.reason, "all values are int");
+        assert!(id.looks_unique() && id.confidence > 0.9);
+
+        let kind = field(items, "kind");
+        assert_eq!((kind.distinct, kind.sampled), (2, 40));
+
+        assert_eq!(field(items, "score").reason, "int and float values widen to float");
+
+        // One stray string forces `any`, with little support for a real mixture
+        let code = field(items, "code");
+        assert_eq!(code.field_type.base, "any");
+        assert_eq!(code.reason, "values mix int and string");
+        assert_eq!(code.observed.get("string"), Some(&1));
+        assert!(code.confidence < 0.2);
+
+        let note = field(items, "note");
+        assert_eq!((note.nulls, note.null_rate(items.objects)), (10, 0.25));
+        assert!(note.field_type.nullable);
+
+        let gone = field(items, "gone");
+        assert_eq!((gone.confidence, gone.reason.as_str()), (0.0, "only nulls seen, defaulted to string"));
+    }
+
+    #[test]
+    fn test_nested_schemas_and_skips() {
+        let report = report(r#"{
+            "orders": [
+                {"id": 1, "customer": {"name": "a"}},
+                {"id": 2, "customer": {"name": "b", "vip": true}}
+            ],
+            "mixed": [{"a": 1}, 2]
+        }"#);
+        let names: Vec<&str> = report.schemas.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["customer", "order"]);
+        assert_eq!(field(&report.schemas[1], "customer").reason, "objects match schema 'customer'");
+        let vip = field(&report.schemas[0], "vip");
+        assert_eq!((vip.present, vip.null_rate(2)), (1, 0.5));
+        assert!(matches!(&report.skipped[..], [Warning::InferenceSkipped { name, .. }] if name == "mixed"));
+
+        let text = report.to_string();
+        assert!(text.contains("order (from orders, 2 objects)"), "{}", text);
+        assert!(text.contains("  customer: customer  nulls 0%  distinct 2/2"), "{}", text);
+        assert!(text.ends_with("No schema inferred for 'mixed': array mixes objects and non-objects\n"), "{}", text);
+    }
+
+    #[test]
+    fn test_wilson_lower_bound() {
+        assert_eq!(wilson_lower_bound(0, 0), 0.0);
+        // Agreement counts for more the more values there are
+        assert!(wilson_lower_bound(3, 3) < wilson_lower_bound(300, 300));
+        assert!((wilson_lower_bound(50, 100) - 0.4038).abs() < 1e-3);
+    }
+}
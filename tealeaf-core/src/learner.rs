@@ -0,0 +1,509 @@
+//! Schema learning over a stream of objects.
+//!
+//! [`SchemaInferrer`](crate::SchemaInferrer) sees all of its data at once. A
+//! [`SchemaLearner`] is fed batches of objects over the life of a service
+//! instead, keeping only counts per field, and decides when what it has seen
+//! is enough to rely on. Its state converts to and from a [`Value`], so it
+//! can be saved as a TeaLeaf document between runs:
+//!
+//! ```
+//! use tealeaf::{ObjectMap, SchemaLearner, Value};
+//!
+//! let order = |id: i64| Value::Object(ObjectMap::from_iter([("id".to_string(), Value::Int(id))]));
+//! let mut learner = SchemaLearner::new("order");
+//! learner.observe(&[order(1), order(2)]);
+//! assert!(learner.stable().is_none());
+//!
+//! let saved = learner.to_value();
+//! let mut learner = SchemaLearner::from_value(&saved)?;
+//! learner.observe(&(3..200).map(order).collect::<Vec<_>>());
+//! assert_eq!(learner.stable().unwrap().fields[0].field_type.to_string(), "int");
+//! # Ok::<(), tealeaf::Error>(())
+//! ```
+//!
+//! The schema it has learned is published as the stable schema once enough
+//! objects were seen and every field's type is confident enough.
+//! [`promote`](SchemaLearner::promote) publishes it early, and
+//! [`freeze`](SchemaLearner::freeze) pins the stable schema so that later
+//! data is only checked against it.
+
+use crate::inference::wilson_lower_bound;
+use crate::types::value_kind;
+use crate::{Error, FieldType, IndexMap, ObjectMap, Result, Schema, Value};
+
+/// Version of the saved state written by [`SchemaLearner::to_value`]
+const STATE_VERSION: i64 = 1;
+
+/// Learns one schema from batches of objects. See the [module
+/// documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaLearner {
+    name: String,
+    min_objects: u64,
+    min_confidence: f64,
+    objects: u64,
+    batches: u64,
+    fields: IndexMap<String, FieldCounts>,
+    history: Vec<Evolution>,
+    stable: Option<Schema>,
+    frozen: bool,
+    violations: u64,
+}
+
+/// What has been seen of one field.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct FieldCounts {
+    /// Objects that had the field
+    present: u64,
+    nulls: u64,
+    /// Non-null values by kind (`int`, `string`, `array`, ...)
+    kinds: IndexMap<String, u64>,
+}
+
+/// A change in the learned type of a field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evolution {
+    /// The batch, counting from 1, after which the type changed.
+    pub batch: u64,
+    pub field: String,
+    /// `None` when the field was first seen.
+    pub from: Option<FieldType>,
+    pub to: FieldType,
+}
+
+impl SchemaLearner {
+    /// A learner for the schema `name`, requiring 100 objects and a
+    /// confidence of 0.95 by default.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            min_objects: 100,
+            min_confidence: 0.95,
+            objects: 0,
+            batches: 0,
+            fields: IndexMap::new(),
+            history: Vec::new(),
+            stable: None,
+            frozen: false,
+            violations: 0,
+        }
+    }
+
+    /// Objects to see before the schema can become stable.
+    pub fn with_min_objects(mut self, objects: u64) -> Self {
+        self.min_objects = objects;
+        self
+    }
+
+    /// Confidence every field needs before the schema can become stable
+    /// (see [`confidence`](Self::confidence)).
+    pub fn with_min_confidence(mut self, confidence: f64) -> Self {
+        self.min_confidence = confidence;
+        self
+    }
+
+    /// Learn from one batch. Values other than objects are ignored.
+    ///
+    /// Changes to field types are added to [`history`](Self::history). If
+    /// the learner is not frozen and the thresholds are now met, the learned
+    /// schema becomes the stable one. If it is frozen, objects that do not
+    /// fit the stable schema are counted as [`violations`](Self::violations);
+    /// they are still learned from.
+    pub fn observe(&mut self, batch: &[Value]) {
+        let before = self.field_types();
+        self.batches += 1;
+        for value in batch {
+            let obj = match value {
+                Value::Object(obj) => obj,
+                _ => continue,
+            };
+            if self.frozen && !self.stable.as_ref().is_some_and(|s| fits_schema(obj, s)) {
+                self.violations += 1;
+            }
+            self.objects += 1;
+            for (key, v) in obj {
+                let counts = self.fields.entry(key.clone()).or_default();
+                counts.present += 1;
+                if v.is_null() {
+                    counts.nulls += 1;
+                } else {
+                    *counts.kinds.entry(value_kind(v).to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+        for (field, to) in self.field_types() {
+            let from = before.get(&field).cloned();
+            if from.as_ref() != Some(&to) {
+                self.history.push(Evolution { batch: self.batches, field, from, to });
+            }
+        }
+        if !self.frozen && self.is_ready() {
+            self.stable = Some(self.candidate());
+        }
+    }
+
+    /// The schema learned so far, whether or not it meets the thresholds.
+    /// Fields are in order of first appearance.
+    pub fn candidate(&self) -> Schema {
+        self.field_types().into_iter().fold(Schema::new(&self.name), |schema, (name, field_type)| schema.field(&name, field_type))
+    }
+
+    /// The confidence of the least certain field, from 0 to 1: the lower
+    /// bound of the 95% Wilson score interval of the share of its non-null
+    /// values that fit its type. Fields that were only ever null say
+    /// nothing either way and are left out. 0 before any field has a value.
+    pub fn confidence(&self) -> f64 {
+        self.fields.values()
+            .filter(|counts| counts.present > counts.nulls)
+            .map(|counts| {
+                let (_, support) = counts.field_type(self.objects);
+                wilson_lower_bound(support as usize, (counts.present - counts.nulls) as usize)
+            })
+            .reduce(f64::min)
+            .unwrap_or(0.0)
+    }
+
+    /// Whether enough objects were seen, with enough confidence, for the
+    /// learned schema to become stable.
+    pub fn is_ready(&self) -> bool {
+        self.objects >= self.min_objects && self.confidence() >= self.min_confidence
+    }
+
+    /// The published schema, if the thresholds were met or it was promoted.
+    pub fn stable(&self) -> Option<&Schema> {
+        self.stable.as_ref()
+    }
+
+    /// Publish the learned schema as the stable one now, whatever the
+    /// thresholds. Fails with [`Error::LearnerState`] if the learner is
+    /// frozen or has seen no fields.
+    pub fn promote(&mut self) -> Result<&Schema> {
+        if self.frozen {
+            return Err(self.state_error("is frozen"));
+        }
+        if self.fields.is_empty() {
+            return Err(self.state_error("has seen no fields"));
+        }
+        Ok(self.stable.insert(self.candidate()))
+    }
+
+    /// Keep the stable schema as it is from now on, checking later objects
+    /// against it. Fails with [`Error::LearnerState`] if there is no stable
+    /// schema yet.
+    pub fn freeze(&mut self) -> Result<&Schema> {
+        match &self.stable {
+            Some(schema) => {
+                self.frozen = true;
+                Ok(schema)
+            }
+            None => Err(self.state_error("has no stable schema to freeze")),
+        }
+    }
+
+    fn state_error(&self, reason: &str) -> Error {
+        Error::LearnerState { schema: self.name.clone(), reason: reason.to_string() }
+    }
+
+    /// Let the stable schema follow the data again.
+    pub fn unfreeze(&mut self) {
+        self.frozen = false;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Objects learned from so far.
+    pub fn objects(&self) -> u64 {
+        self.objects
+    }
+
+    /// Every change in the type of a field, oldest first.
+    pub fn history(&self) -> &[Evolution] {
+        &self.history
+    }
+
+    /// Objects seen while frozen that did not fit the stable schema: with a
+    /// field it lacks, a value of the wrong type, or no value for a field
+    /// that is not nullable.
+    pub fn violations(&self) -> u64 {
+        self.violations
+    }
+
+    fn field_types(&self) -> IndexMap<String, FieldType> {
+        self.fields.iter().map(|(name, counts)| (name.clone(), counts.field_type(self.objects).0)).collect()
+    }
+
+    /// The learner's state, to save and restore with [`from_value`](Self::from_value).
+    pub fn to_value(&self) -> Value {
+        let count = |n: u64| Value::UInt(n);
+        let fields = self.fields.iter().map(|(name, counts)| {
+            let kinds = counts.kinds.iter().map(|(kind, n)| (kind.clone(), count(*n))).collect::<ObjectMap<_, _>>();
+            let state = ObjectMap::from_iter([
+                ("present".to_string(), count(counts.present)),
+                ("nulls".to_string(), count(counts.nulls)),
+                ("kinds".to_string(), Value::Object(kinds)),
+            ]);
+            (name.clone(), Value::Object(state))
+        }).collect::<ObjectMap<_, _>>();
+        let field_type = |t: &FieldType| Value::String(t.to_string());
+        let history = self.history.iter().map(|e| Value::Object(ObjectMap::from_iter([
+            ("batch".to_string(), count(e.batch)),
+            ("field".to_string(), Value::String(e.field.clone())),
+            ("from".to_string(), e.from.as_ref().map_or(Value::Null, field_type)),
+            ("to".to_string(), field_type(&e.to)),
+        ]))).collect();
+        let stable = self.stable.as_ref().map_or(Value::Null, |schema| {
+            Value::Object(schema.fields.iter().map(|f| (f.name.clone(), field_type(&f.field_type))).collect())
+        });
+        Value::Object(ObjectMap::from_iter([
+            ("version".to_string(), Value::Int(STATE_VERSION)),
+            ("name".to_string(), Value::String(self.name.clone())),
+            ("min_objects".to_string(), count(self.min_objects)),
+            ("min_confidence".to_string(), Value::Float(self.min_confidence)),
+            ("objects".to_string(), count(self.objects)),
+            ("batches".to_string(), count(self.batches)),
+            ("frozen".to_string(), Value::Bool(self.frozen)),
+            ("violations".to_string(), count(self.violations)),
+            ("fields".to_string(), Value::Object(fields)),
+            ("history".to_string(), Value::Array(history)),
+            ("stable".to_string(), stable),
+        ]))
+    }
+
+    /// Restore a learner saved with [`to_value`](Self::to_value).
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let state = object(value, "learner state")?;
+        let version = get(state, "version")?.as_int();
+        if version != Some(STATE_VERSION) {
+            return Err(Error::ParseError(format!("unsupported learner state version {:?}", version)));
+        }
+        let name = string(get(state, "name")?, "name")?;
+        let mut learner = SchemaLearner::new(&name)
+            .with_min_objects(count(get(state, "min_objects")?, "min_objects")?)
+            .with_min_confidence(get(state, "min_confidence")?.as_float()
+                .ok_or_else(|| Error::ParseError("'min_confidence' must be a number".into()))?);
+        learner.objects = count(get(state, "objects")?, "objects")?;
+        learner.batches = count(get(state, "batches")?, "batches")?;
+        learner.violations = count(get(state, "violations")?, "violations")?;
+        learner.frozen = get(state, "frozen")?.as_bool()
+            .ok_or_else(|| Error::ParseError("'frozen' must be a bool".into()))?;
+
+        for (field, value) in object(get(state, "fields")?, "fields")? {
+            let counts = object(value, field)?;
+            let mut kinds = IndexMap::new();
+            for (kind, n) in object(get(counts, "kinds")?, "kinds")? {
+                kinds.insert(kind.clone(), count(n, kind)?);
+            }
+            learner.fields.insert(field.clone(), FieldCounts {
+                present: count(get(counts, "present")?, "present")?,
+                nulls: count(get(counts, "nulls")?, "nulls")?,
+                kinds,
+            });
+        }
+        let history = get(state, "history")?.as_array()
+            .ok_or_else(|| Error::ParseError("'history' must be an array".into()))?;
+        for entry in history {
+            let entry = object(entry, "history entry")?;
+            let from = get(entry, "from")?;
+            learner.history.push(Evolution {
+                batch: count(get(entry, "batch")?, "batch")?,
+                field: string(get(entry, "field")?, "field")?,
+                from: if from.is_null() { None } else { Some(FieldType::parse(&string(from, "from")?)) },
+                to: FieldType::parse(&string(get(entry, "to")?, "to")?),
+            });
+        }
+        let stable = get(state, "stable")?;
+        if !stable.is_null() {
+            let mut schema = Schema::new(&name);
+            for (field, field_type) in object(stable, "stable")? {
+                schema.add_field(field, FieldType::parse(&string(field_type, field)?));
+            }
+            learner.stable = Some(schema);
+        }
+        Ok(learner)
+    }
+}
+
+impl FieldCounts {
+    /// The field's type given `objects` seen in all, and how many of its
+    /// non-null values fit that type.
+    fn field_type(&self, objects: u64) -> (FieldType, u64) {
+        let seen = self.present - self.nulls;
+        let count = |kinds: &[&str]| kinds.iter().filter_map(|k| self.kinds.get(*k)).sum::<u64>();
+        let only = |kinds: &[&str]| count(kinds) == seen;
+        let (field_type, support) = if seen == 0 {
+            // Nothing to go on yet
+            (FieldType::new("string"), 0)
+        } else if only(&["int", "uint"]) {
+            (FieldType::new(if only(&["uint"]) { "uint" } else { "int" }), seen)
+        } else if only(&["int", "uint", "float", "number"]) {
+            (FieldType::new("float"), seen)
+        } else {
+            let (kind, &most) = self.kinds.iter().max_by_key(|(_, n)| **n).expect("values were seen");
+            if most == seen {
+                (kind_type(kind), seen)
+            } else {
+                // As in the inference report, a mixture is only as certain
+                // as the values outside its most common kind
+                (FieldType::new("any"), seen - most)
+            }
+        };
+        let nullable = self.nulls > 0 || self.present < objects || seen == 0;
+        (if nullable { field_type.nullable() } else { field_type }, support)
+    }
+}
+
+/// The field type for values of one kind.
+fn kind_type(kind: &str) -> FieldType {
+    match kind {
+        "bool" | "string" | "bytes" | "timestamp" | "timestamp_ns" => FieldType::new(kind),
+        "array" => FieldType::new("any").array(),
+        _ => FieldType::new("any"),
+    }
+}
+
+/// Whether `obj` has only fields of `schema`, each with a value of its type.
+fn fits_schema(obj: &ObjectMap<String, Value>, schema: &Schema) -> bool {
+    obj.keys().all(|k| schema.fields.iter().any(|f| f.name == *k))
+        && schema.fields.iter().all(|field| match obj.get(&field.name) {
+            None | Some(Value::Null) => field.field_type.nullable,
+            Some(value) => {
+                let kind = value_kind(value);
+                match field.field_type.base.as_str() {
+                    "any" => !field.field_type.is_array || kind == "array",
+                    _ if field.field_type.is_array => false,
+                    "float" => matches!(kind, "int" | "uint" | "float" | "number"),
+                    "int" => matches!(kind, "int" | "uint"),
+                    base => base == kind,
+                }
+            }
+        })
+}
+
+fn get<'a>(obj: &'a ObjectMap<String, Value>, key: &str) -> Result<&'a Value> {
+    obj.get(key).ok_or_else(|| Error::MissingField(key.to_string()))
+}
+
+fn object<'a>(value: &'a Value, what: &str) -> Result<&'a ObjectMap<String, Value>> {
+    value.as_object().ok_or_else(|| Error::ParseError(format!("'{}' must be an object", what)))
+}
+
+fn string(value: &Value, what: &str) -> Result<String> {
+    value.as_str().map(str::to_string).ok_or_else(|| Error::ParseError(format!("'{}' must be a string", what)))
+}
+
+fn count(value: &Value, what: &str) -> Result<u64> {
+    value.as_uint().ok_or_else(|| Error::ParseError(format!("'{}' must be a count", what)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn obj(entries: &[(&str, Value)]) -> Value {
+        Value::Object(entries.iter().map(|(k, v)| (k.to_string(), v.clone())).collect())
+    }
+
+    fn order(i: i64) -> Value {
+        obj(&[("id", Value::Int(i)), ("total", Value::Int(i * 10)), ("note", if i % 2 == 0 { Value::Null } else { Value::String("x".into()) })])
+    }
+
+    #[test]
+    fn test_learns_and_publishes_when_confident() {
+        let mut learner = SchemaLearner::new("order").with_min_objects(50).with_min_confidence(0.7);
+        learner.observe(&(0..20).map(order).collect::<Vec<_>>());
+        assert_eq!(learner.stable(), None);
+        let candidate = learner.candidate();
+        let types: Vec<String> = candidate.fields.iter().map(|f| format!("{}: {}", f.name, f.field_type)).collect();
+        assert_eq!(types, ["id: int", "total: int", "note: string?"]);
+
+        // Totals start arriving as floats: widened, and recorded
+        let batch: Vec<Value> = (20..60).map(|i| obj(&[("id", Value::Int(i)), ("total", Value::Float(1.5))])).collect();
+        learner.observe(&batch);
+        let changes: Vec<(u64, &str, String)> = learner.history().iter()
+            .map(|e| (e.batch, e.field.as_str(), e.to.to_string()))
+            .collect();
+        assert_eq!(changes[3..], [(2, "total", "float".to_string())]);
+        assert_eq!(learner.objects(), 60);
+        assert!(learner.confidence() > 0.7 && learner.is_ready());
+        assert_eq!(learner.stable().unwrap().get_field("total").unwrap().field_type, FieldType::new("float"));
+    }
+
+    #[test]
+    fn test_mixed_kinds_hold_back_the_schema() {
+        let mut learner = SchemaLearner::new("event").with_min_objects(10);
+        let batch: Vec<Value> = (0..40).map(|i| {
+            obj(&[("code", if i % 4 == 0 { Value::String("E1".into()) } else { Value::Int(i) })])
+        }).collect();
+        learner.observe(&batch);
+        assert_eq!(learner.candidate().fields[0].field_type, FieldType::new("any"));
+        assert!(learner.confidence() < 0.95);
+        assert_eq!(learner.stable(), None);
+
+        assert!(matches!(learner.freeze(), Err(Error::LearnerState { .. })));
+        assert_eq!(learner.promote().unwrap().fields[0].field_type, FieldType::new("any"));
+        let err = SchemaLearner::new("empty").promote().err().unwrap();
+        assert_eq!(err.to_string(), "Schema learner 'empty' has seen no fields");
+    }
+
+    #[test]
+    fn test_null_only_fields_do_not_hold_back_the_schema() {
+        let mut learner = SchemaLearner::new("order").with_min_objects(50);
+        let batch: Vec<Value> = (0..100).map(|i| obj(&[("id", Value::Int(i)), ("deleted_at", Value::Null)])).collect();
+        learner.observe(&batch);
+        assert!(learner.confidence() > 0.95);
+        let stable = learner.stable().unwrap();
+        assert_eq!(stable.get_field("deleted_at").unwrap().field_type, FieldType::new("string").nullable());
+
+        let mut nulls = SchemaLearner::new("nulls");
+        nulls.observe(&(0..10).map(|_| obj(&[("x", Value::Null)])).collect::<Vec<_>>());
+        assert_eq!(nulls.confidence(), 0.0);
+    }
+
+    #[test]
+    fn test_freeze_counts_violations() {
+        let mut learner = SchemaLearner::new("order").with_min_objects(1);
+        learner.observe(&[order(1), order(2), Value::Int(5)]);
+        assert_eq!(learner.objects(), 2);
+        assert!(learner.freeze().is_err());
+        learner.promote().unwrap();
+        let stable = learner.freeze().unwrap().clone();
+        let err = learner.promote().err().unwrap();
+        assert_eq!(err.to_string(), "Schema learner 'order' is frozen");
+
+        learner.observe(&[
+            order(3),
+            obj(&[("id", Value::UInt(4)), ("total", Value::Int(1))]),
+            obj(&[("id", Value::String("5".into())), ("total", Value::Int(1))]),
+            obj(&[("id", Value::Int(6))]),
+            obj(&[("id", Value::Int(7)), ("total", Value::Int(1)), ("extra", Value::Bool(true))]),
+        ]);
+        assert_eq!(learner.violations(), 3);
+        assert_eq!(learner.stable(), Some(&stable));
+        assert_ne!(learner.candidate(), stable);
+
+        learner.unfreeze();
+        assert!(!learner.is_frozen());
+        let candidate = learner.candidate();
+        assert_eq!(learner.promote().unwrap(), &candidate);
+    }
+
+    #[test]
+    fn test_state_round_trips() {
+        let mut learner = SchemaLearner::new("order").with_min_objects(3).with_min_confidence(0.5);
+        learner.observe(&(0..5).map(order).collect::<Vec<_>>());
+        learner.observe(&[obj(&[("id", Value::Float(0.5)), ("when", Value::Timestamp(0, 0))])]);
+        learner.promote().unwrap();
+        learner.freeze().unwrap();
+        let restored = SchemaLearner::from_value(&learner.to_value()).unwrap();
+        assert_eq!(restored, learner);
+
+        let mut state = learner.to_value();
+        if let Value::Object(obj) = &mut state {
+            obj.insert("version".into(), Value::Int(99));
+        }
+        assert!(SchemaLearner::from_value(&state).is_err());
+        assert!(matches!(SchemaLearner::from_value(&obj(&[("version", Value::Int(1))])), Err(Error::MissingField(_))));
+    }
+}
@@ -17,36 +17,154 @@
 //! ```
 
 mod types;
+#[cfg(feature = "text")]
 mod lexer;
+#[cfg(feature = "text")]
 mod parser;
+#[cfg(feature = "text")]
+mod units;
+#[cfg(feature = "text")]
+mod extract;
+#[cfg(feature = "binary")]
 mod writer;
+#[cfg(feature = "binary")]
 mod reader;
+#[cfg(feature = "binary")]
+mod codec;
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "binary")]
+pub mod bundle;
 pub mod convert;
 pub mod builder;
-
-pub use types::{Error, Result, TLType, FieldType, Field, Schema, Union, Variant, Value, ObjectMap, MAGIC, VERSION, VERSION_MAJOR, VERSION_MINOR, HEADER_SIZE, MAX_STRING_LENGTH, MAX_OBJECT_FIELDS, MAX_ARRAY_LENGTH};
+pub mod env;
+pub mod i18n;
+pub mod warning;
+pub mod registry;
+pub mod compat;
+pub mod text;
+pub mod view;
+pub mod alter;
+pub mod pipeline;
+pub mod tags;
+pub mod outline;
+pub mod handle;
+#[cfg(feature = "infer")]
+pub mod inference;
+pub mod progress;
+pub mod fake;
+pub mod typescript;
+pub mod normalize;
+#[cfg(feature = "infer")]
+pub mod learner;
+pub mod encoding;
+pub mod root;
+pub mod semantic;
+pub mod mapping;
+pub mod coerce;
+pub mod transaction;
+pub mod lookup;
+pub mod shard;
+pub mod utf8;
+#[cfg(feature = "text")]
+pub mod compressed;
+#[cfg(feature = "text")]
+pub mod dedupe;
+#[cfg(feature = "text")]
+pub mod upgrade;
+#[cfg(all(feature = "text", feature = "binary"))]
+pub mod roundtrip;
+#[cfg(feature = "figment")]
+pub mod figment;
+#[cfg(feature = "json")]
+pub mod limits;
+pub mod secret;
+
+pub use types::{Error, Unencodable, Result, TLType, FieldType, Field, Schema, Union, Variant, Value, TimePrecision, ObjectMap, MAGIC, VERSION, VERSION_MAJOR, VERSION_MINOR, HEADER_SIZE, MAX_STRING_LENGTH, MAX_OBJECT_FIELDS, MAX_ARRAY_LENGTH, UNION_FLAG_ORDINAL_TAGS, MAX_UNION_VARIANTS, TYPE_CODE_ANY};
 pub use indexmap::IndexMap;
-pub use lexer::{Lexer, Token, TokenKind};
-pub use parser::Parser;
-pub use writer::Writer;
-pub use reader::Reader;
-pub use convert::{ToTeaLeaf, FromTeaLeaf, ConvertError, ToTeaLeafExt};
-pub use builder::TeaLeafBuilder;
+#[cfg(any(feature = "text", feature = "json"))]
+use types::canonical_float;
+#[cfg(feature = "text")]
+pub use lexer::{Lexer, Token, TokenKind, TimestampOptions, ExcessDigits};
+#[cfg(feature = "text")]
+pub use parser::{Parser, DuplicatePolicy, IncludePolicy};
+#[cfg(feature = "text")]
+pub use extract::extract;
+#[cfg(feature = "binary")]
+pub use writer::{Writer, CompileReport};
+pub use shard::{ShardBy, Sharded};
+#[cfg(feature = "binary")]
+pub use reader::{Reader, ReaderOptions, SectionAccess, SectionField, ChunkStats, ColumnStats, ColumnSummary};
+#[cfg(feature = "binary")]
+pub use codec::{rotate_keys, SectionCodec};
+#[cfg(feature = "binary")]
+pub use bundle::Bundle;
+pub use convert::{ToTeaLeaf, FromTeaLeaf, ConvertError, ToTeaLeafExt, FromTeaLeafExt};
+pub use builder::{TeaLeafBuilder, ConcurrentTeaLeafBuilder};
+pub use view::ValueView;
+pub use alter::AlterOp;
+pub use pipeline::{Pipeline, Pass, PassReport, PipelineReport};
+pub use typescript::generate_typescript;
+pub use normalize::{Case, KeyScope};
+#[cfg(feature = "infer")]
+pub use learner::{SchemaLearner, Evolution};
+pub use encoding::Encoding;
+pub use root::Root;
+pub use semantic::FloatTolerance;
+pub use mapping::{Mapping, MapRule, MappingReport};
+pub use coerce::Coercion;
+pub use transaction::Transaction;
+#[cfg(feature = "text")]
+pub use compressed::TextCompression;
+#[cfg(feature = "text")]
+pub use upgrade::{Conversion, UpgradePolicy, UpgradeReport};
+#[cfg(all(feature = "text", feature = "binary"))]
+pub use roundtrip::{roundtrip_check, roundtrip_check_with, Asymmetry};
+pub use tags::{TagUsage, UnknownTag};
+pub use outline::{Outline, OutlineNode};
+pub use handle::DocumentHandle;
+#[cfg(feature = "infer")]
+pub use inference::InferenceReport;
+pub use progress::Progress;
+#[cfg(any(feature = "binary", feature = "json", feature = "infer"))]
+use progress::{Operation, ProgressUpdate};
+pub use env::{EnvCapture, EnvSnapshot};
+pub use i18n::TranslationTable;
+pub use warning::Warning;
+pub use registry::SchemaRegistry;
+pub use compat::{CompatibilityLevel, CompatibilityReport, SchemaChange};
+#[cfg(feature = "json")]
+pub use limits::JsonLimits;
+pub use secret::{Secret, SecretOutput};
 
 // Re-export derive macros when the "derive" feature is enabled
 #[cfg(feature = "derive")]
 pub use tealeaf_derive::{ToTeaLeaf, FromTeaLeaf};
 
 use std::collections::HashSet;
+#[cfg(feature = "text")]
+use text::{escape_string, needs_quoting, quote_key};
+#[cfg(any(feature = "text", feature = "binary"))]
 use std::path::Path;
 
 /// A parsed TeaLeaf document
+#[derive(Clone)]
 pub struct TeaLeaf {
     pub schemas: IndexMap<String, Schema>,
     pub unions: IndexMap<String, Union>,
     pub data: IndexMap<String, Value>,
     /// Tracks if the source JSON was a root-level array (for round-trip fidelity)
     is_root_array: bool,
+    /// Tracks if the source JSON was a root-level scalar (for round-trip fidelity)
+    #[cfg_attr(not(feature = "json"), allow(dead_code))]
+    is_root_primitive: bool,
+    /// The source text mostly ended lines with `\r\n`, for
+    /// [`LineEnding::Preserve`]
+    #[cfg_attr(not(feature = "text"), allow(dead_code))]
+    crlf: bool,
+    /// Schema each top-level key is explicitly bound to (`@table` / `@as`),
+    /// used by the text writer instead of key-name heuristics
+    bindings: IndexMap<String, String>,
 }
 
 impl TeaLeaf {
@@ -60,41 +178,189 @@ impl TeaLeaf {
             unions: IndexMap::new(),
             data,
             is_root_array: false,
+            is_root_primitive: false,
+            crlf: false,
+            bindings: IndexMap::new(),
         }
     }
 
     /// Parse TeaLeaf text format
+    #[cfg(feature = "text")]
     pub fn parse(input: &str) -> Result<Self> {
+        Self::parse_with_timestamps(input, TimestampOptions::default())
+    }
+
+    /// Parse a schema file: `@struct`, `@union` and other directives, such
+    /// as [`schemas_to_tl`](Self::schemas_to_tl) writes. Text holding data
+    /// fails with [`Error::ParseError`] naming the first key.
+    #[cfg(feature = "text")]
+    pub fn parse_schemas_only(input: &str) -> Result<Self> {
+        let doc = Self::parse(input)?;
+        if let Some(key) = doc.data.keys().next() {
+            return Err(Error::ParseError(format!("Schema file contains data: '{}'", key)));
+        }
+        Ok(doc)
+    }
+
+    /// Parse TeaLeaf text format, accepting timestamp literals according to
+    /// `options`.
+    ///
+    /// ```
+    /// use tealeaf::{TeaLeaf, TimestampOptions};
+    ///
+    /// assert!(TeaLeaf::parse("d: 2024-01-15").is_ok());
+    /// assert!(TeaLeaf::parse_with_timestamps("d: 2024-01-15", TimestampOptions::strict()).is_err());
+    /// ```
+    #[cfg(feature = "text")]
+    pub fn parse_with_timestamps(input: &str, options: TimestampOptions) -> Result<Self> {
+        let tokens = Lexer::new(input).with_timestamps(options).tokenize()?;
+        Self::parse_tokens(input, Parser::new(tokens).with_timestamps(options))
+    }
+
+    /// Parse TeaLeaf text format, handling objects that repeat a key
+    /// according to `policy` rather than failing with
+    /// [`Error::DuplicateKey`].
+    #[cfg(feature = "text")]
+    pub fn parse_with_duplicates(input: &str, policy: DuplicatePolicy) -> Result<Self> {
         let tokens = Lexer::new(input).tokenize()?;
-        let mut parser = Parser::new(tokens);
+        Self::parse_tokens(input, Parser::new(tokens).with_duplicates(policy))
+    }
+
+    /// Parse TeaLeaf text format, reading `@include`d files, relative to
+    /// the working directory, only as `policy` allows.
+    #[cfg(feature = "text")]
+    pub fn parse_with_includes(input: &str, policy: IncludePolicy) -> Result<Self> {
+        let tokens = Lexer::new(input).tokenize()?;
+        Self::parse_tokens(input, Parser::new(tokens).with_includes(policy))
+    }
+
+    #[cfg(feature = "text")]
+    fn parse_tokens(input: &str, mut parser: Parser) -> Result<Self> {
         let data = parser.parse()?;
         let is_root_array = parser.is_root_array();
+        let is_root_primitive = parser.is_root_primitive();
+        let bindings = parser.bindings().clone();
         let (schemas, unions) = parser.into_schemas_and_unions();
         Ok(Self {
             schemas,
             unions,
             data,
             is_root_array,
+            is_root_primitive,
+            crlf: mostly_crlf(input),
+            bindings,
         })
     }
 
+    /// Parse TeaLeaf text format, collecting errors instead of stopping at
+    /// the first.
+    ///
+    /// An error abandons its top-level section (key, `@struct`, `@union` or
+    /// other directive); parsing resumes at the next line that starts a
+    /// section no deeper indented. Returns the document made of everything
+    /// that parsed and every error in source order, each an
+    /// [`Error::ParseError`] whose message starts with the line number. With
+    /// no errors the document is the one [`parse`](Self::parse) returns.
+    #[cfg(feature = "text")]
+    pub fn parse_lenient(input: &str) -> (Self, Vec<Error>) {
+        let (tokens, lex_errors) = Lexer::new(input).tokenize_lenient();
+        let gaps: Vec<usize> = lex_errors.iter().map(|&(_, at, _)| at).collect();
+        let mut parser = Parser::new(tokens);
+        let (data, parse_errors) = parser.parse_lenient(&gaps);
+        let mut errors: Vec<(usize, Error)> = lex_errors.into_iter().map(|(line, _, e)| (line, e)).collect();
+        errors.extend(parse_errors);
+        errors.sort_by_key(|&(line, _)| line);
+
+        let is_root_array = parser.is_root_array();
+        let is_root_primitive = parser.is_root_primitive();
+        let bindings = parser.bindings().clone();
+        let (schemas, unions) = parser.into_schemas_and_unions();
+        let doc = Self {
+            schemas,
+            unions,
+            data,
+            is_root_array,
+            is_root_primitive,
+            crlf: mostly_crlf(input),
+            bindings,
+        };
+        let errors = errors.into_iter().map(|(line, e)| match e {
+            Error::ParseError(msg) => Error::ParseError(format!("line {}: {}", line, msg)),
+            e => Error::ParseError(format!("line {}: {}", line, e)),
+        }).collect();
+        (doc, errors)
+    }
+
     /// Load from text file
     ///
     /// Include paths are resolved relative to the loaded file's directory.
+    #[cfg(feature = "text")]
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref();
-        let content = std::fs::read_to_string(path)?;
-        let tokens = Lexer::new(&content).tokenize()?;
-        let mut parser = Parser::new(tokens).with_base_path(path);
+        Self::load_with_timestamps(path, TimestampOptions::default())
+    }
+
+    /// Load from text file, accepting timestamp literals according to
+    /// `options`, in included files too.
+    #[cfg(feature = "text")]
+    pub fn load_with_timestamps<P: AsRef<Path>>(path: P, options: TimestampOptions) -> Result<Self> {
+        Ok(Self::load_file(path.as_ref(), options, IncludePolicy::default(), u64::MAX, false)?.0)
+    }
+
+    /// Load from text file, reading `@include`d files only as `policy`
+    /// allows.
+    ///
+    /// Use [`IncludePolicy::sandboxed`] for files from tenants or users:
+    /// an include outside its directory, or past its limits, fails the
+    /// load with [`Error::ParseError`] or [`Error::LimitExceeded`] naming
+    /// the include.
+    #[cfg(feature = "text")]
+    pub fn load_with_includes<P: AsRef<Path>>(path: P, policy: IncludePolicy) -> Result<Self> {
+        Ok(Self::load_file(path.as_ref(), TimestampOptions::default(), policy, u64::MAX, false)?.0)
+    }
+
+    /// Load from text file, failing with [`Error::LimitExceeded`] if its
+    /// text is longer than `max_bytes`.
+    ///
+    /// A `.tl.gz` or `.tl.zst` file is measured as it is decompressed, and
+    /// decompression stops at the limit. Includes are limited separately,
+    /// by [`load_with_includes`](Self::load_with_includes).
+    #[cfg(feature = "text")]
+    pub fn load_with_max_size<P: AsRef<Path>>(path: P, max_bytes: u64) -> Result<Self> {
+        Ok(Self::load_file(path.as_ref(), TimestampOptions::default(), IncludePolicy::default(), max_bytes, false)?.0)
+    }
+
+    /// Load from a text file in any [`Encoding`], converting UTF-16 and
+    /// UTF-32 to UTF-8, and return the encoding found.
+    ///
+    /// [`load`](Self::load) reads UTF-8 with or without a byte order mark,
+    /// and fails with [`Error::UnsupportedEncoding`] on anything else.
+    /// Included files are always read as UTF-8.
+    #[cfg(feature = "text")]
+    pub fn load_transcoded<P: AsRef<Path>>(path: P) -> Result<(Self, Encoding)> {
+        Self::load_file(path.as_ref(), TimestampOptions::default(), IncludePolicy::default(), u64::MAX, true)
+    }
+
+    #[cfg(feature = "text")]
+    fn load_file(path: &Path, options: TimestampOptions, includes: IncludePolicy, max_bytes: u64, transcode: bool) -> Result<(Self, Encoding)> {
+        let bytes = compressed::decompress_text(std::fs::read(path)?, max_bytes)?;
+        let (content, encoding) = encoding::decode_text(&bytes, transcode)?;
+        let tokens = Lexer::new(&content).with_timestamps(options).tokenize()?;
+        let mut parser = Parser::new(tokens).with_base_path(path).with_timestamps(options).with_includes(includes);
         let data = parser.parse()?;
         let is_root_array = parser.is_root_array();
+        let is_root_primitive = parser.is_root_primitive();
+        let bindings = parser.bindings().clone();
         let (schemas, unions) = parser.into_schemas_and_unions();
-        Ok(Self {
+        let doc = Self {
             schemas,
             unions,
             data,
             is_root_array,
-        })
+            is_root_primitive,
+            crlf: mostly_crlf(&content),
+            bindings,
+        };
+        Ok((doc, encoding))
     }
 
     /// Get a value by key
@@ -112,24 +378,38 @@ impl TeaLeaf {
         if path.is_empty() {
             return None;
         }
-        let bytes = path.as_bytes();
-        // Find end of first segment (document key)
-        let seg_end = bytes
-            .iter()
-            .position(|&b| b == b'.' || b == b'[')
-            .unwrap_or(bytes.len());
-        let first_key = &path[..seg_end];
-        let root = self.data.get(first_key)?;
-
-        if seg_end >= bytes.len() {
+        let (key, rest) = types::split_path_root(path);
+        let root = self.data.get(key)?;
+        if rest.is_empty() {
             return Some(root);
         }
+        root.get_path(rest)
+    }
 
-        let mut remaining = seg_end;
-        if bytes[remaining] == b'.' {
-            remaining += 1;
-        }
-        root.get_path(&path[remaining..])
+    /// Resolve several paths at once, keyed by path in request order.
+    ///
+    /// Paths use the same syntax as [`get_path`](Self::get_path); a path that
+    /// does not resolve maps to `None`.
+    pub fn get_paths(&self, paths: &[&str]) -> IndexMap<String, Option<&Value>> {
+        paths.iter().map(|p| (p.to_string(), self.get_path(p))).collect()
+    }
+
+    /// Rows of the array under `key` whose timestamp `field` lies in `range`
+    /// (Unix milliseconds, end exclusive), in order. Rows without a timestamp
+    /// in `field` are left out.
+    ///
+    /// See [`Reader::filter_table_by_time`] for the variant that skips
+    /// undecoded row chunks of a binary file.
+    pub fn filter_table_by_time(&self, key: &str, field: &str, range: std::ops::Range<i64>) -> Result<Vec<&Value>> {
+        let rows = self
+            .get(key)
+            .ok_or_else(|| Error::MissingField(key.to_string()))?
+            .as_array()
+            .ok_or_else(|| Error::ParseError("Expected array".into()))?;
+        Ok(rows
+            .iter()
+            .filter(|row| row.get(field).and_then(Value::as_timestamp_millis).is_some_and(|ts| range.contains(&ts)))
+            .collect())
     }
 
     /// Get a schema by name
@@ -142,23 +422,202 @@ impl TeaLeaf {
         self.unions.get(name)
     }
 
+    /// Sort schema and union definitions by name.
+    ///
+    /// Definitions keep the order they were declared or added in, and a
+    /// compiled file numbers its schemas and unions in that order (see
+    /// [`Reader::schema_index`]). Sorting first gives every document with
+    /// the same definitions the same numbering. Text output lists the
+    /// definitions in the new order.
+    pub fn sort_definitions(&mut self) {
+        self.schemas.sort_keys();
+        self.unions.sort_keys();
+    }
+
+    /// Rename a top-level key, keeping its position.
+    ///
+    /// Renaming a reference definition (`"!old"` to `"!new"`) also updates every
+    /// `Value::Ref` that uses it. Returns `false`, leaving the document unchanged,
+    /// if `old` is missing, `new` is already taken, or only one of the two names
+    /// is a reference definition.
+    ///
+    /// An explicit schema binding moves with the key. Unbound top-level arrays
+    /// are matched to schemas by their singularized key when written as text,
+    /// so a renamed key may no longer select the same table.
+    pub fn rename_key(&mut self, old: &str, new: &str) -> bool {
+        if old.starts_with('!') != new.starts_with('!') || !rename_entry(&mut self.data, old, new) {
+            return false;
+        }
+        self.bindings.shift_remove(new);
+        rename_entry(&mut self.bindings, old, new);
+        if let (Some(old_ref), Some(new_ref)) = (old.strip_prefix('!'), new.strip_prefix('!')) {
+            for value in self.data.values_mut() {
+                rename_refs(value, old_ref, new_ref);
+            }
+        }
+        true
+    }
+
+    /// Rename a schema, keeping its position, and update every schema and union
+    /// field whose type refers to it.
+    ///
+    /// Returns `false`, leaving the document unchanged, if `old` is missing or
+    /// `new` is already a schema or union name. Explicit bindings follow the
+    /// rename; unbound top-level tables are matched to schemas by key name
+    /// when written as text, so rename the key as well if the binding relied
+    /// on it.
+    pub fn rename_schema(&mut self, old: &str, new: &str) -> bool {
+        if self.unions.contains_key(new) || !rename_entry(&mut self.schemas, old, new) {
+            return false;
+        }
+        self.schemas[new].name = new.to_string();
+        let fields = self.schemas.values_mut().flat_map(|s| s.fields.iter_mut())
+            .chain(self.unions.values_mut().flat_map(|u| u.variants.iter_mut()).flat_map(|v| v.fields.iter_mut()));
+        for field in fields {
+            if field.field_type.base == old {
+                field.field_type.base = new.to_string();
+            }
+        }
+        for bound in self.bindings.values_mut().filter(|b| *b == old) {
+            *bound = new.to_string();
+        }
+        true
+    }
+
+    /// Bind top-level `key` to schema `schema`.
+    ///
+    /// The text writer then writes the value as that type (`@table schema
+    /// [...]` for an array, `@as schema {...}` or, with
+    /// [`FormatOptions::with_row_objects`], `@row schema (...)` for an
+    /// object) instead of choosing a schema from the key name or fields;
+    /// parsed `@table`, `@as` and `@row` values are bound this way. Returns
+    /// `false`, changing nothing, if the key or schema is missing.
+    pub fn bind(&mut self, key: &str, schema: &str) -> bool {
+        if !self.data.contains_key(key) || !self.schemas.contains_key(schema) {
+            return false;
+        }
+        self.bindings.insert(key.to_string(), schema.to_string());
+        true
+    }
+
+    /// The schema top-level `key` is explicitly bound to, if any.
+    pub fn binding(&self, key: &str) -> Option<&str> {
+        self.bindings.get(key).map(String::as_str)
+    }
+
+    #[cfg(feature = "binary")]
+    fn bound_schema(&self, key: &str) -> Option<&Schema> {
+        self.bindings.get(key).and_then(|name| self.schemas.get(name))
+    }
+
+    /// Rename a field of `schema` and the matching key in every data object
+    /// bound to that schema.
+    ///
+    /// Objects are bound the way the text writer binds them: by explicit
+    /// binding or declared field type, by singularized key name, or by
+    /// matching field names.
+    /// Returns `false`, leaving the document unchanged, if the schema or field is
+    /// missing or `new` is already a field of the schema.
+    pub fn rename_field(&mut self, schema: &str, old: &str, new: &str) -> bool {
+        match self.schemas.get(schema) {
+            Some(s) if s.fields.iter().any(|f| f.name == old)
+                && (old == new || !s.fields.iter().any(|f| f.name == new)) => {}
+            _ => return false,
+        }
+        let rename = BoundObjects {
+            schemas: &self.schemas,
+            target: schema,
+            edit: &|obj| { rename_entry(obj, old, new); },
+        };
+        for (key, value) in self.data.iter_mut() {
+            rename.value(value, self.bindings.get(key).map(String::as_str), Some(key));
+        }
+        if let Some(field) = self.schemas[schema].fields.iter_mut().find(|f| f.name == old) {
+            field.name = new.to_string();
+        }
+        true
+    }
+
     /// Compile to binary format
+    #[cfg(feature = "binary")]
     pub fn compile<P: AsRef<Path>>(&self, path: P, compress: bool) -> Result<()> {
+        self.compile_with_progress(path, compress, &mut Progress::new())
+    }
+
+    /// Compile to binary format, reporting each encoded section to
+    /// `progress` and stopping with [`Error::Cancelled`], without writing
+    /// the file, if it asks to.
+    #[cfg(feature = "binary")]
+    pub fn compile_with_progress<P: AsRef<Path>>(&self, path: P, compress: bool, progress: &mut Progress) -> Result<()> {
+        let writer = self.to_writer(progress)?;
+        progress.check()?;
+        writer.write(path, compress)
+    }
+
+    /// Compile to binary format, also reporting values the writer had to
+    /// coerce to fit their schema field types.
+    #[cfg(feature = "binary")]
+    pub fn compile_with_warnings<P: AsRef<Path>>(&self, path: P, compress: bool) -> Result<Vec<Warning>> {
+        let writer = self.to_writer(&mut Progress::new())?;
+        writer.write(path, compress)?;
+        Ok(writer.warnings().to_vec())
+    }
+
+    /// Compile to binary format, leaving out sections that hold values the
+    /// writer cannot encode instead of failing on them.
+    ///
+    /// The report lists each section left out with all of its unencodable
+    /// values, and the coercions [`compile_with_warnings`](Self::compile_with_warnings)
+    /// would report. Other errors, such as I/O failures, still fail.
+    #[cfg(feature = "binary")]
+    pub fn compile_lenient<P: AsRef<Path>>(&self, path: P, compress: bool) -> Result<CompileReport> {
+        let mut skipped = IndexMap::new();
+        let writer = self.to_writer_skipping(&mut Progress::new(), Some(&mut skipped))?;
+        writer.write(path, compress)?;
+        Ok(CompileReport { skipped, warnings: writer.warnings().to_vec() })
+    }
+
+    #[cfg(feature = "binary")]
+    fn to_writer(&self, progress: &mut Progress) -> Result<Writer> {
+        self.to_writer_skipping(progress, None)
+    }
+
+    /// Build a writer for the document. A section that fails to encode fails
+    /// with every unencodable value in it listed, or, with `skipped`, is
+    /// left out and recorded there.
+    #[cfg(feature = "binary")]
+    fn to_writer_skipping(&self, progress: &mut Progress, mut skipped: Option<&mut IndexMap<String, Vec<Unencodable>>>) -> Result<Writer> {
         let mut writer = Writer::new();
         writer.set_root_array(self.is_root_array);
+        writer.set_root_primitive(self.is_root_primitive);
         for (_, schema) in &self.schemas {
             writer.add_schema(schema.clone());
         }
         for (_, union_def) in &self.unions {
             writer.add_union(union_def.clone());
         }
-        for (key, value) in &self.data {
+        for (i, (key, value)) in self.data.iter().enumerate() {
+            progress.check()?;
             let schema = self.find_schema_for_value(value, key);
-            writer.add_section(key, value, schema)?;
+            match (writer.add_section_with_progress(key, value, schema, progress), skipped.as_deref_mut()) {
+                (Err(Error::Unencodable { section, values }), Some(skipped)) => {
+                    skipped.insert(section, values);
+                    continue;
+                }
+                (result, _) => result?,
+            }
+            progress.report(ProgressUpdate {
+                operation: Operation::Compile,
+                section: key,
+                done: i + 1,
+                total: self.data.len(),
+                bytes: writer.encoded_size(),
+            });
         }
-        writer.write(path, compress)
+        Ok(writer)
     }
 
+    #[cfg(feature = "binary")]
     fn find_schema_for_value(&self, value: &Value, key: &str) -> Option<&Schema> {
         // Try to find a matching schema for array values
         if let Value::Array(arr) = value {
@@ -176,7 +635,9 @@ impl TeaLeaf {
                 indices
             };
 
-            for schema in self.schemas.values() {
+            // An explicitly bound schema is tried first
+            let candidates = self.bound_schema(key).into_iter().chain(self.schemas.values());
+            for schema in candidates {
                 let all_match = sample_indices.iter().all(|&i| {
                     if let Some(Value::Object(obj)) = arr.get(i) {
                         // All required (non-nullable) schema fields must be present
@@ -216,36 +677,51 @@ impl TeaLeaf {
     /// - Integers that fit `i64` → `Value::Int`
     /// - Large positive integers that fit `u64` → `Value::UInt`
     /// - Numbers with decimals or scientific notation → `Value::Float`
+    #[cfg(feature = "json")]
     pub fn from_json(json: &str) -> Result<Self> {
+        Self::from_json_with_progress(json, &mut Progress::new())
+    }
+
+    /// Like [`from_json`](Self::from_json), reporting each converted
+    /// top-level key to `progress` and stopping with [`Error::Cancelled`] if
+    /// it asks to. A root array or primitive is one section named `root`.
+    #[cfg(feature = "json")]
+    pub fn from_json_with_progress(json: &str, progress: &mut Progress) -> Result<Self> {
         let json_value: serde_json::Value = serde_json::from_str(json)
             .map_err(|e| Error::ParseError(format!("Invalid JSON: {}", e)))?;
 
-        let (data, is_root_array) = match json_value {
-            serde_json::Value::Object(obj) => {
-                let map = obj.into_iter()
-                    .map(|(k, v)| (k, json_to_tealeaf_value(v)))
-                    .collect();
-                (map, false)
-            }
-            serde_json::Value::Array(_) => {
-                // Root-level array: store under "root" key but track for round-trip
-                let mut map = IndexMap::new();
-                map.insert("root".to_string(), json_to_tealeaf_value(json_value));
-                (map, true)
-            }
-            _ => {
-                // Other primitives (string, number, bool, null) at root
-                let mut map = IndexMap::new();
-                map.insert("root".to_string(), json_to_tealeaf_value(json_value));
-                (map, false)
-            }
+        let (entries, is_root_array, is_root_primitive) = match json_value {
+            serde_json::Value::Object(obj) => (obj.into_iter().collect(), false, false),
+            // Root-level array: store under "root" key but track for round-trip
+            serde_json::Value::Array(_) => (vec![("root".to_string(), json_value)], true, false),
+            // Other primitives (string, number, bool, null) at root:
+            // store under "root" key but track for round-trip
+            _ => (vec![("root".to_string(), json_value)], false, true),
         };
 
+        let total = entries.len();
+        let mut data = IndexMap::with_capacity(total);
+        for (i, (key, value)) in entries.into_iter().enumerate() {
+            progress.check()?;
+            // Arrays are converted element by element, so long ones can be cancelled
+            let value = match value {
+                serde_json::Value::Array(arr) => Value::Array(arr.into_iter()
+                    .map(|v| progress.tick().map(|_| json_to_tealeaf_value(v)))
+                    .collect::<Result<_>>()?),
+                value => json_to_tealeaf_value(value),
+            };
+            data.insert(key.clone(), value);
+            progress.report(ProgressUpdate { operation: Operation::FromJson, section: &key, done: i + 1, total, bytes: 0 });
+        }
+
         Ok(Self {
             schemas: IndexMap::new(),
             unions: IndexMap::new(),
             data,
             is_root_array,
+            is_root_primitive,
+            crlf: false,
+            bindings: IndexMap::new(),
         })
     }
 
@@ -258,19 +734,111 @@ impl TeaLeaf {
     /// - Enables `@table` format output when serialized
     ///
     /// Use `to_tl_with_schemas()` to serialize with the inferred schemas.
+    #[cfg(all(feature = "json", feature = "infer"))]
     pub fn from_json_with_schemas(json: &str) -> Result<Self> {
+        Self::from_json_with_schemas_and_warnings(json).map(|(doc, _)| doc)
+    }
+
+    /// Like [`from_json_with_schemas`](Self::from_json_with_schemas), also
+    /// reporting arrays of objects that were left without a schema.
+    #[cfg(all(feature = "json", feature = "infer"))]
+    pub fn from_json_with_schemas_and_warnings(json: &str) -> Result<(Self, Vec<Warning>)> {
+        Self::from_json(json).map(Self::infer_json_schemas)
+    }
+
+    /// Like [`from_json_with_schemas`](Self::from_json_with_schemas),
+    /// reporting to `progress` as each top-level key is converted and then
+    /// as it is inferred, and stopping with [`Error::Cancelled`] if it asks to.
+    #[cfg(all(feature = "json", feature = "infer"))]
+    pub fn from_json_with_schemas_with_progress(json: &str, progress: &mut Progress) -> Result<Self> {
+        let doc = Self::from_json_with_progress(json, progress)?;
+        Self::infer_json_schemas_with(doc, SchemaInferrer::new(), progress).map(|(doc, _)| doc)
+    }
+
+    /// Like [`from_json`](Self::from_json), rejecting input that exceeds
+    /// `limits` with [`Error::LimitExceeded`] before any value is built.
+    ///
+    /// Use this for untrusted input such as user uploads.
+    #[cfg(feature = "json")]
+    pub fn from_json_with_limits(json: &str, limits: &JsonLimits) -> Result<Self> {
+        limits.check(json)?;
+        Self::from_json(json)
+    }
+
+    /// Like [`from_json_with_schemas_and_warnings`](Self::from_json_with_schemas_and_warnings),
+    /// rejecting input that exceeds `limits` with [`Error::LimitExceeded`].
+    #[cfg(all(feature = "json", feature = "infer"))]
+    pub fn from_json_with_schemas_and_limits(json: &str, limits: &JsonLimits) -> Result<(Self, Vec<Warning>)> {
+        Self::from_json_with_limits(json, limits).map(Self::infer_json_schemas)
+    }
+
+    #[cfg(all(feature = "json", feature = "infer"))]
+    fn infer_json_schemas(doc: Self) -> (Self, Vec<Warning>) {
+        let (doc, report) = Self::infer_json_schemas_with(doc, SchemaInferrer::new(), &mut Progress::new())
+            .expect("inference without a cancellation check cannot be cancelled");
+        (doc, report.skipped)
+    }
+
+    /// Like [`from_json_with_schemas`](Self::from_json_with_schemas), also
+    /// reporting the evidence behind each inferred field: the kinds of value
+    /// seen, null rates, distinct counts, and how confident inference is in
+    /// the type it chose, including why it fell back to `any`.
+    ///
+    /// ```
+    /// use tealeaf::TeaLeaf;
+    ///
+    /// let json = r#"{"users": [{"id": 1, "tag": "a"}, {"id": 2, "tag": 7}]}"#;
+    /// let (_, report) = TeaLeaf::from_json_with_schemas_with_report(json)?;
+    /// let tag = &report.schemas[0].fields[1];
+    /// assert_eq!(tag.field_type.base, "any");
+    /// assert_eq!(tag.reason, "values mix string and int");
+    /// # Ok::<(), tealeaf::Error>(())
+    /// ```
+    #[cfg(all(feature = "json", feature = "infer"))]
+    pub fn from_json_with_schemas_with_report(json: &str) -> Result<(Self, InferenceReport)> {
         let doc = Self::from_json(json)?;
+        Self::infer_json_schemas_with(doc, SchemaInferrer::new().with_report(), &mut Progress::new())
+    }
+
+    /// Like [`from_json_with_schemas`](Self::from_json_with_schemas), also
+    /// turning objects keyed by IDs into maps whose values share a schema,
+    /// written `@map name {id: (...), ...}`. See [`SchemaInferrer::rewrite_maps`]
+    /// for what counts as keyed by IDs.
+    ///
+    /// Maps convert back to JSON as arrays of `[key, value]` pairs, so use
+    /// this where the TeaLeaf output is the end product.
+    ///
+    /// ```
+    /// use tealeaf::TeaLeaf;
+    ///
+    /// let json = r#"{"users": {"17": {"name": "alice"}, "42": {"name": "bob"}}}"#;
+    /// let doc = TeaLeaf::from_json_with_schemas_and_maps(json)?;
+    /// assert!(doc.to_tl_with_schemas().contains("users: @map user {"));
+    /// # Ok::<(), tealeaf::Error>(())
+    /// ```
+    #[cfg(all(feature = "json", feature = "infer"))]
+    pub fn from_json_with_schemas_and_maps(json: &str) -> Result<Self> {
+        let doc = Self::from_json(json)?;
+        Self::infer_json_schemas_with(doc, SchemaInferrer::new().with_maps(), &mut Progress::new()).map(|(doc, _)| doc)
+    }
 
-        let mut inferrer = SchemaInferrer::new();
-        inferrer.infer(&doc.data);
+    #[cfg(all(feature = "json", feature = "infer"))]
+    fn infer_json_schemas_with(mut doc: Self, mut inferrer: SchemaInferrer, progress: &mut Progress) -> Result<(Self, InferenceReport)> {
+        inferrer.rewrite_maps(&mut doc.data);
+        inferrer.infer_with_progress(&doc.data, progress)?;
+        let mut report = inferrer.report().cloned().unwrap_or_default();
+        report.skipped = inferrer.warnings().to_vec();
         let (schemas, _) = inferrer.into_schemas();
 
-        Ok(Self {
+        Ok((Self {
             schemas,
             unions: IndexMap::new(),
             data: doc.data,
             is_root_array: doc.is_root_array,
-        })
+            is_root_primitive: doc.is_root_primitive,
+            crlf: doc.crlf,
+            bindings: doc.bindings,
+        }, report))
     }
 
     /// Serialize to TeaLeaf text format with schemas.
@@ -280,14 +848,40 @@ impl TeaLeaf {
     ///
     /// If this document represents a root-level JSON array (from `from_json`),
     /// the output will include `@root-array` directive for round-trip fidelity.
+    #[cfg(feature = "text")]
     pub fn to_tl_with_schemas(&self) -> String {
         self.to_tl_with_options(&FormatOptions::default())
     }
 
+    /// Serialize only the `@union` and `@struct` definitions, as a schema
+    /// file that can be kept, diffed and shared apart from any data.
+    /// [`parse_schemas_only`](Self::parse_schemas_only) reads it back.
+    ///
+    /// ```
+    /// use tealeaf::TeaLeaf;
+    ///
+    /// let doc = TeaLeaf::parse("@struct user (id: int, name: string)\nusers: @table user [(1, a)]")?;
+    /// assert_eq!(doc.schemas_to_tl(), "@struct user (id: int, name: string)\n");
+    /// # Ok::<(), tealeaf::Error>(())
+    /// ```
+    #[cfg(feature = "text")]
+    pub fn schemas_to_tl(&self) -> String {
+        let mut out = String::new();
+        let schema_order: Vec<String> = self.schemas.keys().cloned().collect();
+        let union_order: Vec<String> = self.unions.keys().cloned().collect();
+        write_definitions(&mut out, &self.schemas, &schema_order, &self.unions, &union_order, &FormatOptions::default());
+        // No data follows, so no blank line to separate it
+        if out.ends_with("\n\n") {
+            out.pop();
+        }
+        out
+    }
+
     /// Serialize to compact TeaLeaf text format with schema definitions.
     /// Removes insignificant whitespace (spaces after `:` and `,`, indentation,
     /// blank lines) while keeping the format parseable. Table rows remain one
     /// per line for readability.
+    #[cfg(feature = "text")]
     pub fn to_tl_with_schemas_compact(&self) -> String {
         self.to_tl_with_options(&FormatOptions::compact())
     }
@@ -296,29 +890,103 @@ impl TeaLeaf {
     ///
     /// Use `FormatOptions::compact().with_compact_floats()` for maximum
     /// token savings (strips whitespace and `.0` from whole-number floats).
+    #[cfg(feature = "text")]
     pub fn to_tl_with_options(&self, opts: &FormatOptions) -> String {
         let mut output = String::new();
+        self.write_tl(&mut output, opts);
+        output
+    }
 
-        if self.is_root_array {
-            if opts.compact {
-                output.push_str("@root-array\n");
-            } else {
-                output.push_str("@root-array\n\n");
+    /// Serialize like [`to_tl_with_options`](Self::to_tl_with_options), also
+    /// reporting floats that `compact_floats` turns into integers on re-parse.
+    #[cfg(feature = "text")]
+    pub fn to_tl_with_warnings(&self, opts: &FormatOptions) -> (String, Vec<Warning>) {
+        let warnings = if opts.compact_floats {
+            warning::float_warnings(&self.data, float_loses_fraction,
+                |path| Warning::FloatBecomesInt { path })
+        } else {
+            Vec::new()
+        };
+        (self.to_tl_with_options(opts), warnings)
+    }
+
+    /// Size in bytes of [`to_tl_with_options`](Self::to_tl_with_options) output,
+    /// computed by running the text writer against a byte counter instead of
+    /// building the string.
+    #[cfg(feature = "text")]
+    pub fn estimated_text_size(&self, opts: &FormatOptions) -> usize {
+        let mut counter = ByteCounter::default();
+        self.write_tl(&mut counter, opts);
+        counter.0
+    }
+
+    /// Size in bytes of the file [`compile`](Self::compile) would write,
+    /// computed by walking the values without encoding them.
+    ///
+    /// Uncompressed, the estimate is exact for any document `compile`
+    /// accepts. With `compress`, sections are only stored compressed when
+    /// that makes them smaller, so the uncompressed size is returned as an
+    /// upper bound.
+    #[cfg(feature = "binary")]
+    pub fn estimated_binary_size(&self, compress: bool) -> usize {
+        // Compression only replaces a section when the result is smaller, so the
+        // uncompressed total bounds the file size either way.
+        let _ = compress;
+        let mut estimator = writer::SizeEstimator::new();
+        for schema in self.schemas.values() {
+            estimator.add_schema(schema);
+        }
+        for union_def in self.unions.values() {
+            estimator.add_union(union_def);
+        }
+        for (key, value) in &self.data {
+            estimator.add_section(key, value, self.find_schema_for_value(value, key));
+        }
+        estimator.total()
+    }
+
+    #[cfg(feature = "text")]
+    fn write_tl<W: TextSink>(&self, out: &mut W, opts: &FormatOptions) {
+        if opts.dedupe_strings > 0 {
+            if let Some(doc) = dedupe::deduped(self, opts.dedupe_strings) {
+                return doc.write_tl(out, &FormatOptions { dedupe_strings: 0, ..*opts });
+            }
+        }
+        if opts.line_ending.is_crlf(self.crlf) {
+            self.write_tl_aliased(&mut CrLfSink { out }, opts);
+        } else {
+            self.write_tl_aliased(out, opts);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    fn write_tl_aliased<W: TextSink>(&self, out: &mut W, opts: &FormatOptions) {
+        let aliases = choose_key_aliases(opts, |counter| self.write_tl_body(counter, opts));
+        if aliases.is_empty() {
+            self.write_tl_body(out, opts);
+        } else {
+            write_alias_directive(out, &aliases, opts);
+            self.write_tl_body(&mut AliasedSink { out, aliases: &aliases }, opts);
+        }
+    }
+
+    #[cfg(feature = "text")]
+    fn write_tl_body<W: TextSink>(&self, out: &mut W, opts: &FormatOptions) {
+        if self.is_root_array || self.is_root_primitive {
+            out.push_str(if self.is_root_array { "@root-array\n" } else { "@root-primitive\n" });
+            if !opts.compact {
+                out.push('\n');
             }
         }
 
         if self.schemas.is_empty() && self.unions.is_empty() {
-            output.push_str(&dumps_with_options(&self.data, opts));
+            write_data(out, &self.data, opts);
         } else {
             let schema_order: Vec<String> = self.schemas.keys().cloned().collect();
             let union_order: Vec<String> = self.unions.keys().cloned().collect();
-            output.push_str(&dumps_with_schemas_with_options(
-                &self.data, &self.schemas, &schema_order,
-                &self.unions, &union_order, opts,
-            ));
+            write_definitions(out, &self.schemas, &schema_order, &self.unions, &union_order, opts);
+            write_bound_data(out, &self.data, &self.schemas, &self.bindings, opts);
         }
-
-        output
     }
 
     /// Convert to JSON string (pretty-printed).
@@ -338,44 +1006,62 @@ impl TeaLeaf {
     /// | Float ±Inf | `null` (JSON has no Infinity)                  |
     ///
     /// These representations are **contractually stable** and will not change.
+    ///
+    /// Secrets are written as `"[REDACTED]"`; see [`to_json_with_options`](Self::to_json_with_options).
+    #[cfg(feature = "json")]
     pub fn to_json(&self) -> Result<String> {
-        // If the source was a root-level array, return it directly (not wrapped in object)
-        if self.is_root_array {
-            if let Some(root_value) = self.data.get("root") {
-                return serde_json::to_string_pretty(&tealeaf_to_json_value(root_value))
-                    .map_err(|e| Error::ParseError(format!("JSON serialization failed: {}", e)));
-            }
-        }
+        self.to_json_with_options(JsonOptions::pretty())
+    }
 
-        let json_obj: serde_json::Map<String, serde_json::Value> = self.data
-            .iter()
-            .map(|(k, v)| (k.clone(), tealeaf_to_json_value(v)))
-            .collect();
+    /// Convert to JSON string, pretty-printed or compact and with secrets
+    /// masked or exposed as `opts` says.
+    #[cfg(feature = "json")]
+    pub fn to_json_with_options(&self, opts: JsonOptions) -> Result<String> {
+        // If the source was a root-level array or scalar, return it directly (not wrapped in object)
+        let json = match self.data.get("root") {
+            Some(root_value) if self.is_root_array || self.is_root_primitive => {
+                tealeaf_to_json_value(root_value, opts.secrets)
+            }
+            _ => serde_json::Value::Object(self.data
+                .iter()
+                .map(|(k, v)| (k.clone(), tealeaf_to_json_value(v, opts.secrets)))
+                .collect()),
+        };
+        let text = if opts.pretty { serde_json::to_string_pretty(&json) } else { serde_json::to_string(&json) };
+        text.map_err(|e| Error::ParseError(format!("JSON serialization failed: {}", e)))
+    }
 
-        serde_json::to_string_pretty(&serde_json::Value::Object(json_obj))
-            .map_err(|e| Error::ParseError(format!("JSON serialization failed: {}", e)))
+    /// Convert to JSON string (pretty-printed), also reporting floats that
+    /// JSON cannot represent and were exported as `null`.
+    #[cfg(feature = "json")]
+    pub fn to_json_with_warnings(&self) -> Result<(String, Vec<Warning>)> {
+        let json = self.to_json()?;
+        let warnings = warning::float_warnings(&self.data, |f| !f.is_finite(),
+            |path| Warning::NonFiniteFloat { path });
+        Ok((json, warnings))
     }
 
     /// Convert to compact JSON string (no pretty printing)
+    #[cfg(feature = "json")]
     pub fn to_json_compact(&self) -> Result<String> {
-        // If the source was a root-level array, return it directly (not wrapped in object)
-        if self.is_root_array {
-            if let Some(root_value) = self.data.get("root") {
-                return serde_json::to_string(&tealeaf_to_json_value(root_value))
-                    .map_err(|e| Error::ParseError(format!("JSON serialization failed: {}", e)));
-            }
-        }
-
-        let json_obj: serde_json::Map<String, serde_json::Value> = self.data
-            .iter()
-            .map(|(k, v)| (k.clone(), tealeaf_to_json_value(v)))
-            .collect();
+        self.to_json_with_options(JsonOptions::compact())
+    }
 
-        serde_json::to_string(&serde_json::Value::Object(json_obj))
-            .map_err(|e| Error::ParseError(format!("JSON serialization failed: {}", e)))
+    /// Release spare capacity left by parsing or editing, throughout the data.
+    ///
+    /// Objects are built by insertion and arrays by pushing, so a long-lived
+    /// document can hold noticeably more memory than its contents need.
+    pub fn shrink_to_fit(&mut self) {
+        self.data.values_mut().for_each(Value::shrink_to_fit);
+        self.data.shrink_to_fit();
     }
 
     /// Set whether the document represents a root-level array.
+    ///
+    /// The array is still expected under the key `root`.
+    /// [`set_root`](Self::set_root) stores the array and sets the flag in
+    /// one step, and [`root`](Self::root) reads it back.
+    #[deprecated(note = "use `TeaLeaf::set_root` and `TeaLeaf::root`")]
     pub fn set_root_array(&mut self, is_root_array: bool) {
         self.is_root_array = is_root_array;
     }
@@ -383,25 +1069,39 @@ impl TeaLeaf {
     /// Create a TeaLeaf document from a binary Reader.
     ///
     /// Reads all sections from the reader and carries schemas and unions through.
+    #[cfg(feature = "binary")]
     pub fn from_reader(reader: &Reader) -> Result<Self> {
+        Self::from_reader_with_progress(reader, &mut Progress::new())
+    }
+
+    /// Like [`from_reader`](Self::from_reader), reporting each decoded
+    /// section to `progress` and stopping with [`Error::Cancelled`] if it
+    /// asks to.
+    #[cfg(feature = "binary")]
+    pub fn from_reader_with_progress(reader: &Reader, progress: &mut Progress) -> Result<Self> {
         let mut data = IndexMap::new();
-        for key in reader.keys() {
-            data.insert(key.to_string(), reader.get(key)?);
-        }
-        let schemas: IndexMap<String, Schema> = reader.schemas.iter()
-            .map(|s| (s.name.clone(), s.clone()))
-            .collect();
+        let keys = reader.keys();
+        let mut bytes = 0;
+        reader.for_each_value(&keys, progress, |key, value, progress| {
+            progress.check()?;
+            data.insert(key.to_string(), value);
+            bytes += reader.section_size(key);
+            progress.report(ProgressUpdate { operation: Operation::Load, section: key, done: data.len(), total: keys.len(), bytes });
+            Ok(())
+        })?;
+        let schemas = reader.schemas_owned();
         let unions: IndexMap<String, Union> = reader.unions.iter()
             .map(|u| (u.name.clone(), u.clone()))
             .collect();
-        let mut doc = Self {
+        Ok(Self {
             schemas,
             unions,
             data,
             is_root_array: reader.is_root_array(),
-        };
-        doc.set_root_array(reader.is_root_array());
-        Ok(doc)
+            is_root_primitive: reader.is_root_primitive(),
+            crlf: false,
+            bindings: IndexMap::new(),
+        })
     }
 
     /// Create a TeaLeaf document from a single DTO.
@@ -415,6 +1115,7 @@ impl TeaLeaf {
         data.insert(key.to_string(), dto.to_tealeaf_value());
         let mut doc = Self::new(schemas, data);
         doc.unions = unions;
+        doc.bind(key, &T::tealeaf_field_type().base);
         doc
     }
 
@@ -430,6 +1131,7 @@ impl TeaLeaf {
         data.insert(key.to_string(), arr);
         let mut doc = Self::new(schemas, data);
         doc.unions = unions;
+        doc.bind(key, &T::tealeaf_field_type().base);
         doc
     }
 
@@ -453,9 +1155,43 @@ impl TeaLeaf {
             .map(|v| T::from_tealeaf_value(v).map_err(|e| e.into()))
             .collect()
     }
+
+    /// Like [`from_dto_array`](Self::from_dto_array), converting items on
+    /// the rayon thread pool. Element order is preserved.
+    #[cfg(feature = "rayon")]
+    pub fn from_dto_array_parallel<T: convert::ToTeaLeaf + Sync>(key: &str, items: &[T]) -> Self {
+        use rayon::prelude::*;
+        let schemas = T::collect_schemas();
+        let unions = T::collect_unions();
+        let mut data = IndexMap::new();
+        let arr = Value::Array(items.par_iter().map(|i| i.to_tealeaf_value()).collect());
+        data.insert(key.to_string(), arr);
+        let mut doc = Self::new(schemas, data);
+        doc.unions = unions;
+        doc.bind(key, &T::tealeaf_field_type().base);
+        doc
+    }
+
+    /// Like [`to_dto_vec`](Self::to_dto_vec), converting elements on the
+    /// rayon thread pool. Element order is preserved; if several elements
+    /// fail, which error is returned is unspecified.
+    #[cfg(feature = "rayon")]
+    pub fn to_dto_vec_parallel<T: convert::FromTeaLeaf + Send>(&self, key: &str) -> Result<Vec<T>> {
+        use rayon::prelude::*;
+        let value = self
+            .get(key)
+            .ok_or_else(|| Error::MissingField(key.to_string()))?;
+        let arr = value
+            .as_array()
+            .ok_or_else(|| Error::ParseError("Expected array".into()))?;
+        arr.par_iter()
+            .map(|v| T::from_tealeaf_value(v).map_err(|e| e.into()))
+            .collect()
+    }
 }
 
 /// Convert JSON value to TeaLeaf value (best-effort)
+#[cfg(feature = "json")]
 fn json_to_tealeaf_value(json: serde_json::Value) -> Value {
     match json {
         serde_json::Value::Null => Value::Null,
@@ -501,17 +1237,22 @@ fn json_to_tealeaf_value(json: serde_json::Value) -> Value {
 ///
 /// Integer types are tried first during JSON import (i64, then u64) so that
 /// values within 64-bit range stay exact. Only true floats fall through to f64.
-fn tealeaf_to_json_value(tl: &Value) -> serde_json::Value {
+#[cfg(feature = "json")]
+fn tealeaf_to_json_value(tl: &Value, secrets: SecretOutput) -> serde_json::Value {
     match tl {
         Value::Null => serde_json::Value::Null,
         Value::Bool(b) => serde_json::Value::Bool(*b),
         Value::Int(i) => serde_json::Value::Number((*i).into()),
         Value::UInt(u) => serde_json::Value::Number((*u).into()),
         Value::Float(f) => {
-            // Always output floats as floats - the type distinction is intentional
-            serde_json::Number::from_f64(*f)
-                .map(serde_json::Value::Number)
-                .unwrap_or(serde_json::Value::Null)
+            // Always output floats as floats - the type distinction is intentional.
+            // The canonical text is kept verbatim (arbitrary_precision), so JSON
+            // and TeaLeaf text write the same digits
+            if f.is_finite() {
+                canonical_float(*f).parse().map(serde_json::Value::Number).unwrap_or(serde_json::Value::Null)
+            } else {
+                serde_json::Value::Null
+            }
         }
         Value::String(s) => serde_json::Value::String(s.clone()),
         Value::Bytes(b) => {
@@ -522,12 +1263,12 @@ fn tealeaf_to_json_value(tl: &Value) -> serde_json::Value {
             serde_json::Value::String(out)
         }
         Value::Array(arr) => {
-            serde_json::Value::Array(arr.iter().map(tealeaf_to_json_value).collect())
+            serde_json::Value::Array(arr.iter().map(|v| tealeaf_to_json_value(v, secrets)).collect())
         }
         Value::Object(obj) => {
             let map: serde_json::Map<String, serde_json::Value> = obj
                 .iter()
-                .map(|(k, v)| (k.clone(), tealeaf_to_json_value(v)))
+                .map(|(k, v)| (k.clone(), tealeaf_to_json_value(v, secrets)))
                 .collect();
             serde_json::Value::Object(map)
         }
@@ -537,8 +1278,8 @@ fn tealeaf_to_json_value(tl: &Value) -> serde_json::Value {
                 .iter()
                 .map(|(k, v)| {
                     serde_json::Value::Array(vec![
-                        tealeaf_to_json_value(k),
-                        tealeaf_to_json_value(v),
+                        tealeaf_to_json_value(k, secrets),
+                        tealeaf_to_json_value(v, secrets),
                     ])
                 })
                 .collect();
@@ -554,84 +1295,46 @@ fn tealeaf_to_json_value(tl: &Value) -> serde_json::Value {
             // Encode tagged value as object
             let mut obj = serde_json::Map::new();
             obj.insert("$tag".to_string(), serde_json::Value::String(tag.clone()));
-            obj.insert("$value".to_string(), tealeaf_to_json_value(inner));
+            obj.insert("$value".to_string(), tealeaf_to_json_value(inner, secrets));
             serde_json::Value::Object(obj)
         }
         Value::Timestamp(ts, tz) => {
             serde_json::Value::String(format_timestamp_millis(*ts, *tz))
         }
+        Value::TimestampNanos(ns, tz, precision) => {
+            serde_json::Value::String(format_timestamp_nanos(*ns, *tz, *precision))
+        }
         Value::JsonNumber(s) => {
             s.parse::<serde_json::Number>()
                 .map(serde_json::Value::Number)
                 .unwrap_or_else(|_| serde_json::Value::String(s.clone()))
         }
+        Value::Secret(secret) => match secrets {
+            SecretOutput::Redact => serde_json::Value::String(secret::REDACTED.to_string()),
+            SecretOutput::Expose => tealeaf_to_json_value(&secret.expose_value(), secrets),
+        },
     }
 }
 
 /// Read a binary TeaLeaf file
+#[cfg(feature = "binary")]
 pub fn open<P: AsRef<Path>>(path: P) -> Result<Reader> {
     Reader::open(path)
 }
 
 /// Parse TeaLeaf text
+#[cfg(feature = "text")]
 pub fn parse(input: &str) -> Result<TeaLeaf> {
     TeaLeaf::parse(input)
 }
 
 /// Convenience: load and get data
+#[cfg(feature = "text")]
 pub fn loads(input: &str) -> Result<IndexMap<String, Value>> {
     Ok(TeaLeaf::parse(input)?.data)
 }
 
-/// Convenience: serialize to TeaLeaf text
-/// Check if a string needs quoting when serialized to TeaLeaf format.
-/// Returns true if the string could be misinterpreted as another type.
-fn needs_quoting(s: &str) -> bool {
-    if s.is_empty() {
-        return true;
-    }
-
-    // Reserved words, null literal, and float literals the lexer would interpret
-    if matches!(s, "true" | "false" | "null" | "~" | "NaN" | "inf" | "Infinity") {
-        return true;
-    }
-
-    // Whitelist approach: only allow [a-zA-Z0-9_-.] unquoted (ASCII only).
-    // Matches spec grammar: name = (letter | "_") { letter | digit | "_" | "-" | "." }
-    // Any other character (Unicode digits, whitespace, punctuation, etc.)
-    // requires quoting to ensure safe round-trip through the parser.
-    // Note: '-' is excluded here because strings starting with '-' are caught
-    // by the sign-character check below, and mid-string '-' in identifiers
-    // like "foo-bar" is safe only when the first char is a letter.
-    if s.contains(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-' && c != '.') {
-        return true;
-    }
-
-    // Must start with letter or underscore per grammar: name = (letter | "_") { ... }
-    let first = s.chars().next().unwrap();
-    if !first.is_ascii_alphabetic() && first != '_' {
-        return true;
-    }
-
-    // Starts with 0x/0b (hex/binary literal prefix)
-    if s.starts_with("0x") || s.starts_with("0X") || s.starts_with("0b") || s.starts_with("0B") {
-        return true;
-    }
-
-    // Starts with sign character — always quote to avoid parser ambiguity
-    // (parser may try to interpret as a signed number).
-    if s.starts_with('-') || s.starts_with('+') {
-        return true;
-    }
-
-    // Starts with a digit — could be parsed as a number
-    if first.is_ascii_digit() {
-        return true;
-    }
-
-    false
-}
-
+#[cfg(any(feature = "text", feature = "json"))]
 #[inline]
 fn push_hex_byte(out: &mut String, byte: u8) {
     const HEX: &[u8; 16] = b"0123456789abcdef";
@@ -639,6 +1342,7 @@ fn push_hex_byte(out: &mut String, byte: u8) {
     out.push(HEX[(byte & 0x0F) as usize] as char);
 }
 
+#[cfg(any(feature = "text", feature = "json"))]
 #[inline]
 fn push_hex_bytes(out: &mut String, bytes: &[u8]) {
     out.reserve(bytes.len().saturating_mul(2));
@@ -648,13 +1352,13 @@ fn push_hex_bytes(out: &mut String, bytes: &[u8]) {
 }
 
 /// Write a key to the output, quoting if necessary for safe round-trip.
-fn write_key(out: &mut String, key: &str) {
-    if needs_quoting(key) {
-        out.push('"');
-        out.push_str(&escape_string(key));
-        out.push('"');
-    } else {
-        out.push_str(key);
+/// Reference definitions (`!name`) keep their native unquoted form and are
+/// never aliased.
+#[cfg(feature = "text")]
+fn write_key<W: TextSink>(out: &mut W, key: &str) {
+    match key.strip_prefix('!') {
+        Some(name) if !needs_quoting(name) => out.push_str(key),
+        _ => out.push_key(key),
     }
 }
 
@@ -662,20 +1366,27 @@ fn write_key(out: &mut String, key: &str) {
 /// Int/UInt are written as-is. String values use `write_key` for quoting.
 /// Other value types (Null, Bool, Float, etc.) are coerced to quoted strings
 /// so that the text format always round-trips through the parser.
-fn write_map_key(out: &mut String, key: &Value) {
+#[cfg(feature = "text")]
+fn write_map_key<W: TextSink>(out: &mut W, key: &Value, opts: &FormatOptions) {
     match key {
         Value::Int(i) => out.push_str(&i.to_string()),
         Value::UInt(u) => out.push_str(&u.to_string()),
-        Value::String(s) => write_key(out, s),
+        // Map keys are values, never aliased
+        Value::String(s) => out.push_str(&quote_key(s)),
+        Value::Secret(secret) => match opts.secrets {
+            SecretOutput::Redact => out.push_str(&quote_key(secret::REDACTED)),
+            SecretOutput::Expose => write_map_key(out, &secret.expose_value(), opts),
+        },
         // Coerce non-spec key types to quoted strings for text format safety
         Value::Null => out.push_str("\"~\""),
         Value::Bool(b) => { out.push('"'); out.push_str(if *b { "true" } else { "false" }); out.push('"'); }
-        Value::Float(f) => { out.push('"'); out.push_str(&f.to_string()); out.push('"'); }
+        Value::Float(f) => { out.push('"'); out.push_str(&canonical_float(*f)); out.push('"'); }
         Value::JsonNumber(s) => { out.push('"'); out.push_str(s); out.push('"'); }
         Value::Timestamp(ts, tz) => { out.push('"'); out.push_str(&format_timestamp_millis(*ts, *tz)); out.push('"'); }
+        Value::TimestampNanos(ns, tz, p) => { out.push('"'); out.push_str(&format_timestamp_nanos(*ns, *tz, *p)); out.push('"'); }
         Value::Bytes(b) => {
             out.push_str("\"0x");
-            push_hex_bytes(out, b);
+            out.push_hex(b);
             out.push('"');
         }
         Value::Ref(r) => { out.push('"'); out.push('!'); out.push_str(r); out.push('"'); }
@@ -685,6 +1396,7 @@ fn write_map_key(out: &mut String, key: &Value) {
 }
 
 /// Options controlling TeaLeaf text output format.
+#[cfg(feature = "text")]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FormatOptions {
     /// Remove insignificant whitespace (spaces after `:` and `,`, indentation, blank lines).
@@ -692,128 +1404,492 @@ pub struct FormatOptions {
     /// Emit whole-number floats without `.0` suffix (e.g., `42.0` → `42`).
     /// Saves characters/tokens but changes float→int type on re-parse.
     pub compact_floats: bool,
+    /// Write schema-bound arrays of at most this many rows on a single line
+    /// (`@table user [(1, a), (2, b)]`). `0` keeps every table multi-line.
+    pub inline_table_rows: usize,
+    /// Write arrays with fewer rows than this as plain objects instead of
+    /// `@table`. `0` uses `@table` whenever a schema matches.
+    pub min_table_rows: usize,
+    /// Replace keys repeated often enough to pay for it with short names
+    /// declared by an `@alias` directive.
+    pub alias_keys: bool,
+    /// Write objects bound to a schema as one tuple (`@row schema (...)`)
+    /// instead of `@as schema {...}`, so field names are not repeated.
+    pub row_objects: bool,
+    /// Write each entry of an object or map, and each item of an array of
+    /// containers, on a line of its own, and every table row on its own
+    /// line, so changes show up line by line in diffs. Ignored when
+    /// `compact` is set.
+    pub line_per_field: bool,
+    /// How lines end.
+    pub line_ending: LineEnding,
+    /// Write a value with a schema only if the schema is its declared
+    /// type: the schema a top-level key is bound to, or the type of the
+    /// schema field holding it. Arrays and objects are not matched to
+    /// schemas by key name or by their fields, so an array with no declared
+    /// type is written as a plain array rather than a `@table`.
+    pub strict_schemas: bool,
+    /// Write each string of at least this many bytes that occurs more than
+    /// once as a top-level reference definition (`!str1: "..."`), with a
+    /// `!str1` reference wherever it occurs. `0` writes every string in
+    /// place.
+    pub dedupe_strings: usize,
+    /// Whether [`Value::Secret`] is written masked (default) or with its
+    /// contents.
+    pub secrets: SecretOutput,
+}
+
+/// Line endings written by the text writer. The parser accepts `\n` and
+/// `\r\n`, mixed freely, whatever this is set to.
+#[cfg(feature = "text")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// `\n` (default)
+    #[default]
+    Lf,
+    /// `\r\n`
+    CrLf,
+    /// Whichever the text a document was parsed from mostly used; `\n` for
+    /// documents not parsed from text.
+    Preserve,
+}
+
+#[cfg(feature = "text")]
+impl LineEnding {
+    fn is_crlf(self, source_crlf: bool) -> bool {
+        match self {
+            LineEnding::Lf => false,
+            LineEnding::CrLf => true,
+            LineEnding::Preserve => source_crlf,
+        }
+    }
+}
+
+/// Whether most line breaks in `input` are `\r\n`.
+#[cfg(feature = "text")]
+fn mostly_crlf(input: &str) -> bool {
+    let lines = input.matches('\n').count();
+    let crlf = input.matches("\r\n").count();
+    crlf * 2 > lines
 }
 
+#[cfg(feature = "text")]
 impl FormatOptions {
     /// Pretty-printed output (default).
     pub fn pretty() -> Self {
-        Self { compact: false, compact_floats: false }
+        Self { compact: false, compact_floats: false, inline_table_rows: 0, min_table_rows: 0, alias_keys: false, row_objects: false, line_per_field: false, line_ending: LineEnding::Lf, strict_schemas: false, dedupe_strings: 0, secrets: SecretOutput::Redact }
     }
 
     /// Compact output (whitespace stripped).
     pub fn compact() -> Self {
-        Self { compact: true, compact_floats: false }
+        Self { compact: true, compact_floats: false, inline_table_rows: 0, min_table_rows: 0, alias_keys: false, row_objects: false, line_per_field: false, line_ending: LineEnding::Lf, strict_schemas: false, dedupe_strings: 0, secrets: SecretOutput::Redact }
+    }
+
+    /// Diff-friendly output: pretty output with one field per line (see
+    /// [`line_per_field`](Self::line_per_field)), the most verbose form.
+    pub fn diff() -> Self {
+        Self::pretty().with_line_per_field()
+    }
+
+    /// Enable compact float formatting (strip `.0` from whole-number floats).
+    pub fn with_compact_floats(mut self) -> Self {
+        self.compact_floats = true;
+        self
+    }
+
+    /// Write tables of at most `max_rows` rows on a single line.
+    pub fn with_inline_tables(mut self, max_rows: usize) -> Self {
+        self.inline_table_rows = max_rows;
+        self
+    }
+
+    /// Use `@table` only for arrays of at least `min_rows` rows.
+    pub fn with_min_table_rows(mut self, min_rows: usize) -> Self {
+        self.min_table_rows = min_rows;
+        self
+    }
+
+    /// Alias frequently repeated keys, most frequent first.
+    pub fn with_key_aliases(mut self) -> Self {
+        self.alias_keys = true;
+        self
+    }
+
+    /// Write schema-bound objects as `@row` tuples.
+    pub fn with_row_objects(mut self) -> Self {
+        self.row_objects = true;
+        self
+    }
+
+    /// Write object and map entries, and items of arrays of containers,
+    /// one per line.
+    pub fn with_line_per_field(mut self) -> Self {
+        self.line_per_field = true;
+        self
+    }
+
+    /// End lines with `line_ending`.
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Use schemas only where they are declared (see
+    /// [`strict_schemas`](Self::strict_schemas)).
+    pub fn with_strict_schemas(mut self) -> Self {
+        self.strict_schemas = true;
+        self
+    }
+
+    /// Write repeated strings of at least `min_len` bytes once, as
+    /// references (see [`dedupe_strings`](Self::dedupe_strings)).
+    pub fn with_dedupe_strings(mut self, min_len: usize) -> Self {
+        self.dedupe_strings = min_len;
+        self
+    }
+
+    /// Write secrets as `secrets` says (see [`secrets`](Self::secrets)).
+    pub fn with_secrets(mut self, secrets: SecretOutput) -> Self {
+        self.secrets = secrets;
+        self
+    }
+}
+
+#[cfg(feature = "text")]
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self::pretty()
+    }
+}
+
+/// Options controlling JSON output of [`Reader::write_json`] and
+/// [`TeaLeaf::to_json_with_options`].
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Indent nested values by two spaces, one per line, as
+    /// [`TeaLeaf::to_json`] does. Otherwise output is compact, as
+    /// [`TeaLeaf::to_json_compact`].
+    pub pretty: bool,
+    /// Whether [`Value::Secret`] is written masked (default) or with its
+    /// contents.
+    pub secrets: SecretOutput,
+}
+
+#[cfg(feature = "json")]
+impl JsonOptions {
+    /// Pretty-printed output (default).
+    pub fn pretty() -> Self {
+        Self { pretty: true, secrets: SecretOutput::Redact }
+    }
+
+    /// Compact output (no whitespace).
+    pub fn compact() -> Self {
+        Self { pretty: false, secrets: SecretOutput::Redact }
+    }
+
+    /// Write secrets as `secrets` says (see [`secrets`](Self::secrets)).
+    pub fn with_secrets(mut self, secrets: SecretOutput) -> Self {
+        self.secrets = secrets;
+        self
+    }
+}
+
+#[cfg(feature = "json")]
+impl Default for JsonOptions {
+    fn default() -> Self {
+        Self::pretty()
     }
+}
+
+#[cfg(feature = "text")]
+pub fn dumps(data: &IndexMap<String, Value>) -> String {
+    dumps_inner(data, &FormatOptions::default())
+}
+
+/// Serialize data to compact TeaLeaf text format (no schemas).
+/// Removes insignificant whitespace for token-efficient output.
+#[cfg(feature = "text")]
+pub fn dumps_compact(data: &IndexMap<String, Value>) -> String {
+    dumps_inner(data, &FormatOptions::compact())
+}
+
+/// Serialize data to TeaLeaf text format with custom options (no schemas).
+#[cfg(feature = "text")]
+pub fn dumps_with_options(data: &IndexMap<String, Value>, opts: &FormatOptions) -> String {
+    dumps_inner(data, opts)
+}
+
+#[cfg(feature = "text")]
+fn dumps_inner(data: &IndexMap<String, Value>, opts: &FormatOptions) -> String {
+    let mut out = String::new();
+    if opts.line_ending.is_crlf(false) {
+        write_data_aliased(&mut CrLfSink { out: &mut out }, data, opts);
+    } else {
+        write_data_aliased(&mut out, data, opts);
+    }
+    out
+}
+
+#[cfg(feature = "text")]
+fn write_data_aliased<W: TextSink>(out: &mut W, data: &IndexMap<String, Value>, opts: &FormatOptions) {
+    let aliases = choose_key_aliases(opts, |counter| write_data(counter, data, opts));
+    if aliases.is_empty() {
+        write_data(out, data, opts);
+    } else {
+        write_alias_directive(out, &aliases, opts);
+        write_data(&mut AliasedSink { out, aliases: &aliases }, data, opts);
+    }
+}
+
+#[cfg(feature = "text")]
+fn write_data<W: TextSink>(out: &mut W, data: &IndexMap<String, Value>, opts: &FormatOptions) {
+    for (key, value) in data {
+        write_key(out, key);
+        out.push_str(kv_sep(opts.compact));
+        write_value(out, value, 0, opts);
+        out.push('\n');
+    }
+}
+
+/// Destination for text serialization. Lets size estimation drive the same
+/// writer functions as `to_tl_*` without materializing the output.
+#[cfg(feature = "text")]
+trait TextSink {
+    fn push(&mut self, c: char);
+    fn push_str(&mut self, s: &str);
+    /// Lowercase hex digits of `bytes`.
+    fn push_hex(&mut self, bytes: &[u8]);
+    /// An object or top-level key, quoted if necessary.
+    #[inline]
+    fn push_key(&mut self, key: &str) {
+        self.push_str(&quote_key(key));
+    }
+}
+
+#[cfg(feature = "text")]
+impl TextSink for String {
+    #[inline]
+    fn push(&mut self, c: char) { String::push(self, c) }
+    #[inline]
+    fn push_str(&mut self, s: &str) { String::push_str(self, s) }
+    #[inline]
+    fn push_hex(&mut self, bytes: &[u8]) { push_hex_bytes(self, bytes) }
+}
+
+/// Counts UTF-8 bytes written instead of storing them.
+#[cfg(feature = "text")]
+#[derive(Default)]
+struct ByteCounter(usize);
+
+#[cfg(feature = "text")]
+impl TextSink for ByteCounter {
+    #[inline]
+    fn push(&mut self, c: char) { self.0 += c.len_utf8() }
+    #[inline]
+    fn push_str(&mut self, s: &str) { self.0 += s.len() }
+    #[inline]
+    fn push_hex(&mut self, bytes: &[u8]) { self.0 += bytes.len().saturating_mul(2) }
+}
+
+/// Writes keys that have an alias as the alias.
+#[cfg(feature = "text")]
+struct AliasedSink<'a, W> {
+    out: &'a mut W,
+    /// Alias of each aliased key
+    aliases: &'a IndexMap<String, String>,
+}
 
-    /// Enable compact float formatting (strip `.0` from whole-number floats).
-    pub fn with_compact_floats(mut self) -> Self {
-        self.compact_floats = true;
-        self
+#[cfg(feature = "text")]
+impl<W: TextSink> TextSink for AliasedSink<'_, W> {
+    #[inline]
+    fn push(&mut self, c: char) { self.out.push(c) }
+    #[inline]
+    fn push_str(&mut self, s: &str) { self.out.push_str(s) }
+    #[inline]
+    fn push_hex(&mut self, bytes: &[u8]) { self.out.push_hex(bytes) }
+    fn push_key(&mut self, key: &str) {
+        match self.aliases.get(key) {
+            Some(alias) => self.out.push_str(alias),
+            None => self.out.push_key(key),
+        }
     }
 }
 
-impl Default for FormatOptions {
-    fn default() -> Self {
-        Self::pretty()
+/// Writes every line break as `\r\n`. Strings and keys are escaped, so the
+/// only raw newlines the writer emits end lines.
+#[cfg(feature = "text")]
+struct CrLfSink<'a, W> {
+    out: &'a mut W,
+}
+
+#[cfg(feature = "text")]
+impl<W: TextSink> TextSink for CrLfSink<'_, W> {
+    fn push(&mut self, c: char) {
+        if c == '\n' {
+            self.out.push_str("\r\n");
+        } else {
+            self.out.push(c);
+        }
     }
+    fn push_str(&mut self, s: &str) {
+        if s.contains('\n') {
+            self.out.push_str(&s.replace('\n', "\r\n"));
+        } else {
+            self.out.push_str(s);
+        }
+    }
+    #[inline]
+    fn push_hex(&mut self, bytes: &[u8]) { self.out.push_hex(bytes) }
+    #[inline]
+    fn push_key(&mut self, key: &str) { self.out.push_key(key) }
 }
 
-pub fn dumps(data: &IndexMap<String, Value>) -> String {
-    dumps_inner(data, &FormatOptions::default())
+/// Counts how often each key is written, discarding everything else.
+#[cfg(feature = "text")]
+#[derive(Default)]
+struct KeyCounter(IndexMap<String, usize>);
+
+#[cfg(feature = "text")]
+impl TextSink for KeyCounter {
+    fn push(&mut self, _: char) {}
+    fn push_str(&mut self, _: &str) {}
+    fn push_hex(&mut self, _: &[u8]) {}
+    fn push_key(&mut self, key: &str) {
+        match self.0.get_mut(key) {
+            Some(count) => *count += 1,
+            None => { self.0.insert(key.to_string(), 1); }
+        }
+    }
 }
 
-/// Serialize data to compact TeaLeaf text format (no schemas).
-/// Removes insignificant whitespace for token-efficient output.
-pub fn dumps_compact(data: &IndexMap<String, Value>) -> String {
-    dumps_inner(data, &FormatOptions::compact())
+/// Pick aliases for the keys `write` writes, if `opts` asks for them: most
+/// frequent key first, each taking the shortest free name (`a`..`z`, then
+/// `aa`..) as long as it saves more than its directive entry costs.
+#[cfg(feature = "text")]
+fn choose_key_aliases(opts: &FormatOptions, write: impl FnOnce(&mut KeyCounter)) -> IndexMap<String, String> {
+    let mut aliases = IndexMap::new();
+    if !opts.alias_keys {
+        return aliases;
+    }
+    let mut counter = KeyCounter::default();
+    write(&mut counter);
+    let mut keys: Vec<(&String, usize)> = counter.0.iter().map(|(k, &n)| (k, n)).collect();
+    // Stable sort: equally frequent keys keep first-use order
+    keys.sort_by_key(|k| std::cmp::Reverse(k.1));
+
+    let mut names = (1..).flat_map(alias_names).filter(|name| !counter.0.contains_key(name));
+    let mut name = names.next().unwrap_or_default();
+    let mut saved = 0;
+    for (key, count) in keys {
+        let written = quote_key(key).len();
+        // `name=key` plus its separator in the directive
+        let cost = name.len() + 1 + written + sep(opts.compact).len();
+        if count * written <= count * name.len() + cost {
+            continue;
+        }
+        saved += count * (written - name.len()) - cost;
+        aliases.insert(key.clone(), std::mem::replace(&mut name, names.next().unwrap_or_default()));
+    }
+    // The directive itself: `@alias ` and its line break
+    if saved <= "@alias \n".len() {
+        aliases.clear();
+    }
+    aliases
 }
 
-/// Serialize data to TeaLeaf text format with custom options (no schemas).
-pub fn dumps_with_options(data: &IndexMap<String, Value>, opts: &FormatOptions) -> String {
-    dumps_inner(data, opts)
+/// Every lowercase name of `len` letters, in order.
+#[cfg(feature = "text")]
+fn alias_names(len: u32) -> impl Iterator<Item = String> {
+    (0..26usize.pow(len)).map(move |mut i| {
+        let mut name = vec![b'a'; len as usize];
+        for c in name.iter_mut().rev() {
+            *c += (i % 26) as u8;
+            i /= 26;
+        }
+        String::from_utf8(name).unwrap()
+    })
 }
 
-fn dumps_inner(data: &IndexMap<String, Value>, opts: &FormatOptions) -> String {
-    let mut out = String::new();
-    for (key, value) in data {
-        write_key(&mut out, key);
-        out.push_str(kv_sep(opts.compact));
-        write_value(&mut out, value, 0, opts);
-        out.push('\n');
+#[cfg(feature = "text")]
+fn write_alias_directive<W: TextSink>(out: &mut W, aliases: &IndexMap<String, String>, opts: &FormatOptions) {
+    out.push_str("@alias ");
+    for (i, (key, alias)) in aliases.iter().enumerate() {
+        if i > 0 { out.push_str(sep(opts.compact)); }
+        out.push_str(alias);
+        out.push('=');
+        out.push_str(&quote_key(key));
     }
-    out
+    out.push_str(if opts.compact { "\n" } else { "\n\n" });
 }
 
 /// Returns ", " in pretty mode, "," in compact mode
+#[cfg(feature = "text")]
 #[inline]
 fn sep(compact: bool) -> &'static str {
     if compact { "," } else { ", " }
 }
 
 /// Returns ": " in pretty mode, ":" in compact mode.
+#[cfg(feature = "text")]
 #[inline]
 fn kv_sep(compact: bool) -> &'static str {
     if compact { ":" } else { ": " }
 }
 
-/// Escape a string for TeaLeaf text output.
-/// Handles: \\ \" \n \t \r \b \f and \uXXXX for other control characters.
-fn escape_string(s: &str) -> String {
-    let mut out = String::with_capacity(s.len());
-    for c in s.chars() {
-        match c {
-            '\\' => out.push_str("\\\\"),
-            '"' => out.push_str("\\\""),
-            '\n' => out.push_str("\\n"),
-            '\t' => out.push_str("\\t"),
-            '\r' => out.push_str("\\r"),
-            '\u{0008}' => out.push_str("\\b"),
-            '\u{000C}' => out.push_str("\\f"),
-            c if c.is_control() => {
-                // Other control characters use \uXXXX
-                for unit in c.encode_utf16(&mut [0u16; 2]) {
-                    out.push_str(&format!("\\u{:04x}", unit));
-                }
-            }
-            _ => out.push(c),
-        }
+/// Format a float as [`Value::float_to_canonical_string`] does, dropping the
+/// `.0` of whole numbers when `compact_floats` is set.
+#[cfg(feature = "text")]
+fn format_float(f: f64, compact_floats: bool) -> String {
+    let s = canonical_float(f);
+    if compact_floats && float_loses_fraction(f) {
+        s[..s.len() - 2].to_string()
+    } else {
+        s
     }
-    out
 }
 
-/// Format a float ensuring it always has a decimal point or uses scientific notation.
-/// Rust's f64::to_string() expands large/small values (e.g., 6.022e23 becomes
-/// "602200000000000000000000"), which would be reparsed as an integer and overflow.
-/// We use scientific notation for values outside a safe range.
-fn format_float(f: f64, compact_floats: bool) -> String {
-    // Handle non-finite values with keywords the lexer recognizes
-    if f.is_nan() {
-        return "NaN".to_string();
+/// True if `format_float` with `compact_floats` writes `f` without a fraction
+/// or exponent, so it re-parses as an integer: a whole number of at most 15
+/// digits, which integers hold exactly. NaN and infinities are never whole.
+#[cfg(feature = "text")]
+fn float_loses_fraction(f: f64) -> bool {
+    f.fract() == 0.0 && f.abs() < 1e15
+}
+
+/// Whether `opts` writes the entries or items of `value` one per line.
+#[cfg(feature = "text")]
+fn expands(value: &Value, opts: &FormatOptions) -> bool {
+    opts.line_per_field && !opts.compact && match value {
+        Value::Object(obj) => !obj.is_empty(),
+        Value::Map(pairs) => !pairs.is_empty(),
+        Value::Array(items) => items.iter().any(|v| matches!(v, Value::Object(_) | Value::Array(_) | Value::Map(_) | Value::Tagged(..))),
+        _ => false,
+    }
+}
+
+/// Start entry `i` of a container: after a separator, and on a new line
+/// indented by `indent` if the container `expanded`.
+#[cfg(feature = "text")]
+fn start_entry<W: TextSink>(out: &mut W, i: usize, expanded: bool, indent: usize, opts: &FormatOptions) {
+    if i > 0 {
+        out.push_str(if expanded { "," } else { sep(opts.compact) });
     }
-    if f.is_infinite() {
-        return if f.is_sign_positive() { "inf".to_string() } else { "-inf".to_string() };
+    if expanded {
+        new_line(out, indent);
     }
+}
 
-    let s = f.to_string();
-    if s.contains('.') || s.contains('e') || s.contains('E') {
-        // Already has decimal point or scientific notation — safe as-is
-        s
-    } else {
-        // to_string() produced an integer-looking string (no '.' or 'e').
-        // For large values, use scientific notation to avoid i64 overflow on re-parse.
-        // For small values, append ".0" unless compact_floats is enabled.
-        let digits = s.trim_start_matches('-').len();
-        if digits > 15 {
-            format!("{:e}", f)
-        } else if compact_floats {
-            s
-        } else {
-            format!("{}.0", s)
-        }
+#[cfg(feature = "text")]
+fn new_line<W: TextSink>(out: &mut W, indent: usize) {
+    out.push('\n');
+    for _ in 0..indent {
+        out.push(' ');
     }
 }
 
-fn write_value(out: &mut String, value: &Value, indent: usize, opts: &FormatOptions) {
+#[cfg(feature = "text")]
+fn write_value<W: TextSink>(out: &mut W, value: &Value, indent: usize, opts: &FormatOptions) {
     match value {
         Value::Null => out.push('~'),
         Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
@@ -832,39 +1908,54 @@ fn write_value(out: &mut String, value: &Value, indent: usize, opts: &FormatOpti
         }
         Value::Bytes(b) => {
             out.push_str("b\"");
-            push_hex_bytes(out, b);
+            out.push_hex(b);
             out.push('"');
         }
+        Value::Secret(secret) => match opts.secrets {
+            SecretOutput::Redact => {
+                out.push('"');
+                out.push_str(secret::REDACTED);
+                out.push('"');
+            }
+            SecretOutput::Expose => write_value(out, &secret.expose_value(), indent, opts),
+        },
         Value::Array(arr) => {
+            let expanded = expands(value, opts);
+            let inner = if expanded { indent + 2 } else { indent };
             out.push('[');
             for (i, v) in arr.iter().enumerate() {
-                if i > 0 { out.push_str(sep(opts.compact)); }
-                write_value(out, v, indent, opts);
+                start_entry(out, i, expanded, inner, opts);
+                write_value(out, v, inner, opts);
             }
+            if expanded { new_line(out, indent); }
             out.push(']');
         }
         Value::Object(obj) => {
+            let expanded = expands(value, opts);
+            let inner = if expanded { indent + 2 } else { indent };
             out.push('{');
             for (i, (k, v)) in obj.iter().enumerate() {
-                if i > 0 { out.push_str(sep(opts.compact)); }
+                start_entry(out, i, expanded, inner, opts);
                 write_key(out, k);
                 out.push_str(kv_sep(opts.compact));
-                write_value(out, v, indent, opts);
+                write_value(out, v, inner, opts);
             }
+            if expanded { new_line(out, indent); }
             out.push('}');
         }
         Value::Map(pairs) => {
+            let expanded = expands(value, opts);
+            let inner = if expanded { indent + 2 } else { indent };
             out.push_str(if opts.compact { "@map{" } else { "@map {" });
-            let mut first = true;
-            for (k, v) in pairs {
-                if !first { out.push_str(sep(opts.compact)); }
-                first = false;
+            for (i, (k, v)) in pairs.iter().enumerate() {
+                start_entry(out, i, expanded, inner, opts);
                 // Map keys are restricted to string | name | integer per spec.
                 // Write Int/UInt directly; convert other types to quoted strings.
-                write_map_key(out, k);
+                write_map_key(out, k, opts);
                 out.push_str(kv_sep(opts.compact));
-                write_value(out, v, indent, opts);
+                write_value(out, v, inner, opts);
             }
+            if expanded { new_line(out, indent); }
             out.push('}');
         }
         Value::Ref(r) => {
@@ -880,6 +1971,9 @@ fn write_value(out: &mut String, value: &Value, indent: usize, opts: &FormatOpti
         Value::Timestamp(ts, tz) => {
             out.push_str(&format_timestamp_millis(*ts, *tz));
         }
+        Value::TimestampNanos(ns, tz, precision) => {
+            out.push_str(&format_timestamp_nanos(*ns, *tz, *precision));
+        }
     }
 }
 
@@ -887,7 +1981,23 @@ fn write_value(out: &mut String, value: &Value, indent: usize, opts: &FormatOpti
 /// Handles negative timestamps (pre-epoch dates) correctly using Euclidean division.
 /// Years outside [0000, 9999] are clamped to the boundary per spec (4-digit years only).
 /// When tz_offset_minutes is 0, emits 'Z' suffix. Otherwise emits +HH:MM or -HH:MM.
+#[cfg(any(feature = "text", feature = "json"))]
 fn format_timestamp_millis(ts: i64, tz_offset_minutes: i16) -> String {
+    format_timestamp(ts, 0, tz_offset_minutes, None)
+}
+
+/// Format a Unix-nanos timestamp with exactly as many fractional digits as
+/// `precision` calls for (truncating, never rounding).
+#[cfg(any(feature = "text", feature = "json"))]
+fn format_timestamp_nanos(ns: i64, tz_offset_minutes: i16, precision: TimePrecision) -> String {
+    let sub_millis = ns.rem_euclid(1_000_000) as u32;
+    format_timestamp(ns.div_euclid(1_000_000), sub_millis, tz_offset_minutes, Some(precision.fraction_digits()))
+}
+
+/// Shared formatter: `sub_millis` nanoseconds are added below `ts` millis.
+/// With `digits` unset, milliseconds are written only when nonzero.
+#[cfg(any(feature = "text", feature = "json"))]
+fn format_timestamp(ts: i64, sub_millis: u32, tz_offset_minutes: i16, digits: Option<u32>) -> String {
     // Clamp to representable ISO 8601 range (years 0000-9999).
     // Year 0000-01-01T00:00:00Z = -62167219200000 ms
     // Year 9999-12-31T23:59:59.999Z = 253402300799999 ms
@@ -916,7 +2026,11 @@ fn format_timestamp_millis(ts: i64, tz_offset_minutes: i16) -> String {
         format!("{}{:02}:{:02}", sign, abs / 60, abs % 60)
     };
 
-    if millis > 0 {
+    if let Some(digits) = digits.filter(|&d| d > 0) {
+        let nanos = format!("{:09}", millis as u32 * 1_000_000 + sub_millis);
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{}{}",
+            year, month, day, hours, mins, secs_rem, &nanos[..digits as usize], tz_suffix)
+    } else if millis > 0 && digits.is_none() {
         format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}{}",
             year, month, day, hours, mins, secs_rem, millis, tz_suffix)
     } else {
@@ -926,6 +2040,7 @@ fn format_timestamp_millis(ts: i64, tz_offset_minutes: i16) -> String {
 }
 
 /// Convert days since Unix epoch to (year, month, day)
+#[cfg(any(feature = "text", feature = "json"))]
 fn days_to_ymd(days: i64) -> (i64, u32, u32) {
     // Algorithm from Howard Hinnant (extended to i64 for extreme timestamps)
     let z = days + 719468;
@@ -946,8 +2061,9 @@ fn days_to_ymd(days: i64) -> (i64, u32, u32) {
 // =============================================================================
 
 /// Inferred type information for a field
+#[cfg(feature = "infer")]
 #[derive(Debug, Clone, PartialEq)]
-enum InferredType {
+pub(crate) enum InferredType {
     Null,
     Bool,
     Int,
@@ -958,6 +2074,7 @@ enum InferredType {
     Mixed,  // Different types seen - fall back to any
 }
 
+#[cfg(feature = "infer")]
 impl InferredType {
     fn merge(&self, other: &InferredType) -> InferredType {
         if self == other {
@@ -1032,6 +2149,7 @@ impl InferredType {
     }
 }
 
+#[cfg(feature = "infer")]
 fn infer_type(value: &Value) -> InferredType {
     match value {
         Value::Null => InferredType::Null,
@@ -1061,6 +2179,131 @@ fn infer_type(value: &Value) -> InferredType {
     }
 }
 
+/// Rename a map entry in place. Fails if `old` is missing or `new` is taken.
+fn rename_entry<V>(map: &mut IndexMap<String, V>, old: &str, new: &str) -> bool {
+    if old == new {
+        return map.contains_key(old);
+    }
+    if map.contains_key(new) {
+        return false;
+    }
+    match map.get_index_of(old) {
+        Some(idx) => {
+            if let Some((_, value)) = map.shift_remove_index(idx) {
+                map.shift_insert(idx, new.to_string(), value);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Point every `Value::Ref(old)` reachable from `value` at `new`.
+fn rename_refs(value: &mut Value, old: &str, new: &str) {
+    match value {
+        Value::Ref(name) if name == old => *name = new.to_string(),
+        Value::Array(items) => items.iter_mut().for_each(|v| rename_refs(v, old, new)),
+        Value::Object(obj) => obj.values_mut().for_each(|v| rename_refs(v, old, new)),
+        Value::Map(pairs) => {
+            for (k, v) in pairs {
+                rename_refs(k, old, new);
+                rename_refs(v, old, new);
+            }
+        }
+        Value::Tagged(_, inner) => rename_refs(inner, old, new),
+        _ => {}
+    }
+}
+
+/// An edit applied to every object the text writer binds to `target`.
+///
+/// The walk mirrors `write_value_with_schemas` and `write_tuple`, so an object
+/// is edited exactly when it would be written as a `target` tuple. Bindings
+/// are decided before the object is edited.
+struct BoundObjects<'a> {
+    schemas: &'a IndexMap<String, Schema>,
+    target: &'a str,
+    edit: &'a dyn Fn(&mut ObjectMap<String, Value>),
+}
+
+impl BoundObjects<'_> {
+    fn value(&self, value: &mut Value, declared_type: Option<&str>, hint_name: Option<&str>) {
+        match value {
+            Value::Array(items) => {
+                let mut schema = resolve_schema(self.schemas, declared_type, hint_name);
+                if schema.is_none() && (hint_name.is_some() || declared_type.is_some()) {
+                    schema = items.first().and_then(|first| self.structural_match(first));
+                }
+                let is_table = match (schema, items.first()) {
+                    (Some(schema), Some(Value::Object(first))) => {
+                        let keys: HashSet<&str> = first.keys().map(|k| k.as_str()).collect();
+                        object_matches_schema(&keys, schema)
+                    }
+                    _ => false,
+                };
+                for item in items {
+                    match schema {
+                        Some(schema) if is_table => self.row(item, schema),
+                        _ => self.value(item, None, None),
+                    }
+                }
+            }
+            Value::Object(obj) => {
+                let obj_schema = resolve_schema(self.schemas, declared_type, hint_name).or_else(|| {
+                    let keys: HashSet<&str> = obj.keys().map(|k| k.as_str()).collect();
+                    self.schemas.values().find(|s| object_matches_schema(&keys, s))
+                });
+                for (k, child) in obj.iter_mut() {
+                    let field_type = obj_schema.and_then(|s| {
+                        s.fields.iter().find(|f| f.name == *k).map(|f| f.field_type.base.as_str())
+                    });
+                    self.value(child, field_type, Some(k));
+                }
+                if obj_schema.is_some_and(|s| s.name == self.target) {
+                    (self.edit)(obj);
+                }
+            }
+            Value::Map(pairs) => pairs.iter_mut().for_each(|(_, v)| self.value(v, None, None)),
+            Value::Tagged(_, inner) => self.value(inner, None, None),
+            _ => {}
+        }
+    }
+
+    fn row(&self, value: &mut Value, schema: &Schema) {
+        let obj = match value {
+            Value::Object(obj) => obj,
+            _ => return self.value(value, None, None),
+        };
+        for field in &schema.fields {
+            let child = match obj.get_mut(&field.name) {
+                Some(child) if !child.is_null() => child,
+                _ => continue,
+            };
+            match resolve_schema(self.schemas, Some(&field.field_type.base), None) {
+                Some(nested) if field.field_type.is_array => match child {
+                    Value::Array(items) => items.iter_mut().for_each(|item| self.row(item, nested)),
+                    _ => self.value(child, None, None),
+                },
+                Some(nested) => self.row(child, nested),
+                None => self.value(child, None, None),
+            }
+        }
+        if schema.name == self.target {
+            (self.edit)(obj);
+        }
+    }
+
+    fn structural_match(&self, first: &Value) -> Option<&Schema> {
+        match first {
+            Value::Object(obj) => {
+                let keys: HashSet<&str> = obj.keys().map(|k| k.as_str()).collect();
+                self.schemas.values().find(|s| object_matches_schema(&keys, s))
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Singularize a plural name (simple heuristic)
 fn singularize(name: &str) -> String {
     let name = name.to_lowercase();
@@ -1075,16 +2318,19 @@ fn singularize(name: &str) -> String {
     }
 }
 
-/// Check if a name is a value-only type keyword that the parser rejects as a schema
-/// field type (see `parse_field_type` §2.1). If we create `@struct object (b: int)` and
-/// a parent references it as `field: object`, the parser will reject the output.
-/// Only these 5 keywords are rejected — primitive type names like `int`, `bool`, etc.
-/// are allowed as schema names (the parser resolves them via LParen-guard context).
+/// Check if a name is a value type keyword that cannot name a schema (see
+/// `parse_field_type` §2.1). If we create `@struct object (b: int)` and a parent
+/// references it as `field: object`, the parser will reject the output; `map` is a
+/// field type of its own, so `field: map` would not refer to the struct. Only these
+/// 5 keywords are excluded — primitive type names like `int`, `bool`, etc. are
+/// allowed as schema names (the parser resolves them via LParen-guard context).
+#[cfg(feature = "infer")]
 fn is_value_only_type_name(name: &str) -> bool {
     matches!(name, "object" | "map" | "tuple" | "ref" | "tagged")
 }
 
 /// Check if array elements are objects that match a schema's structure
+#[cfg(feature = "infer")]
 fn array_matches_schema(arr: &[Value], schema: &Schema) -> bool {
     if arr.is_empty() {
         return false;
@@ -1117,25 +2363,144 @@ fn object_matches_schema(obj_keys: &HashSet<&str>, schema: &Schema) -> bool {
     && obj_keys.iter().all(|k| schema.fields.iter().any(|f| f.name == *k))
 }
 
+/// Replace objects keyed by IDs in `value` and below with maps, returning
+/// how many were replaced. See [`SchemaInferrer::rewrite_maps`].
+#[cfg(feature = "infer")]
+fn rewrite_id_maps(value: &mut Value) -> usize {
+    let mut replaced = match value {
+        Value::Array(items) => items.iter_mut().map(rewrite_id_maps).sum(),
+        Value::Object(obj) => obj.values_mut().map(rewrite_id_maps).sum(),
+        Value::Map(pairs) => pairs.iter_mut().map(|(_, v)| rewrite_id_maps(v)).sum(),
+        _ => 0,
+    };
+    if let Value::Object(obj) = value {
+        if is_keyed_by_ids(obj) {
+            let pairs = std::mem::take(obj).into_iter()
+                .map(|(k, v)| (lookup::map_key(k), v))
+                .collect();
+            *value = Value::Map(pairs);
+            replaced += 1;
+        }
+    }
+    replaced
+}
+
+#[cfg(feature = "infer")]
+fn is_keyed_by_ids(obj: &ObjectMap<String, Value>) -> bool {
+    let id_like = |key: &str| {
+        key.bytes().any(|b| b.is_ascii_digit())
+            && key.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+    };
+    if obj.len() < 2 || !obj.keys().all(|k| id_like(k)) {
+        return false;
+    }
+    let mut values = obj.values();
+    let first = match values.next() {
+        Some(Value::Object(first)) => first,
+        _ => return false,
+    };
+    // Keep the fields every value has; uniform values share at least one
+    let mut shared: HashSet<&str> = first.keys().map(|k| k.as_str()).collect();
+    for value in values {
+        match value {
+            Value::Object(other) => shared.retain(|k| other.contains_key(*k)),
+            _ => return false,
+        }
+    }
+    !shared.is_empty()
+}
+
 /// Schema inferrer that analyzes data and generates schemas
+#[cfg(feature = "infer")]
 pub struct SchemaInferrer {
     schemas: IndexMap<String, Schema>,
     schema_order: Vec<String>,  // Track order for output
+    warnings: Vec<Warning>,
+    report: Option<InferenceReport>,
+    maps: bool,
 }
 
+#[cfg(feature = "infer")]
 impl SchemaInferrer {
     pub fn new() -> Self {
         Self {
             schemas: IndexMap::new(),
             schema_order: Vec::new(),
+            warnings: Vec::new(),
+            report: None,
+            maps: false,
+        }
+    }
+
+    /// Turn objects keyed by IDs into maps in [`rewrite_maps`](Self::rewrite_maps),
+    /// so their values share one schema instead of each ID becoming a field.
+    pub fn with_maps(mut self) -> Self {
+        self.maps = true;
+        self
+    }
+
+    /// If enabled with [`with_maps`](Self::with_maps), replace every object
+    /// in `data` that is keyed by IDs with a [`Value::Map`], returning how
+    /// many were replaced. Call before [`infer`](Self::infer).
+    ///
+    /// An object is keyed by IDs when it has at least two entries, every key
+    /// is an integer or contains a digit and no characters other than ASCII
+    /// letters, digits, `-`, `_` and `.`, and the values are objects sharing
+    /// at least one field. Integer keys become `Value::Int` keys.
+    pub fn rewrite_maps(&self, data: &mut IndexMap<String, Value>) -> usize {
+        if !self.maps {
+            return 0;
+        }
+        data.values_mut().map(rewrite_id_maps).sum()
+    }
+
+    /// Record the evidence behind each inferred schema, for [`report`](Self::report).
+    pub fn with_report(mut self) -> Self {
+        self.report = Some(InferenceReport::default());
+        self
+    }
+
+    /// What inference decided and why, if enabled with
+    /// [`with_report`](Self::with_report). Skipped arrays are listed in
+    /// [`warnings`](Self::warnings).
+    pub fn report(&self) -> Option<&InferenceReport> {
+        self.report.as_ref()
+    }
+
+    fn record(&mut self, schema: &Schema, source: &str, objects: &[&ObjectMap<String, Value>], field_types: &IndexMap<String, InferredType>) {
+        if let Some(report) = &mut self.report {
+            report.schemas.push(inference::schema_report(schema, source, objects, field_types));
         }
     }
 
+    /// Arrays of objects that were left without a schema, with the reason.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    fn skip(&mut self, name: &str, reason: &str) {
+        self.warnings.push(Warning::InferenceSkipped {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
     /// Analyze data and infer schemas from uniform object arrays
     pub fn infer(&mut self, data: &IndexMap<String, Value>) {
-        for (key, value) in data {
+        self.infer_with_progress(data, &mut Progress::new())
+            .expect("inference without a cancellation check cannot be cancelled");
+    }
+
+    /// Like [`infer`](Self::infer), reporting each analyzed top-level key
+    /// to `progress` and stopping with [`Error::Cancelled`] if it asks to.
+    /// Schemas inferred before cancelling are kept.
+    pub fn infer_with_progress(&mut self, data: &IndexMap<String, Value>, progress: &mut Progress) -> Result<()> {
+        for (i, (key, value)) in data.iter().enumerate() {
+            progress.check()?;
             self.analyze_value(key, value);
+            progress.report(ProgressUpdate { operation: Operation::Infer, section: key, done: i + 1, total: data.len(), bytes: 0 });
         }
+        Ok(())
     }
 
     fn analyze_value(&mut self, hint_name: &str, value: &Value) {
@@ -1146,6 +2511,18 @@ impl SchemaInferrer {
             for (k, v) in obj {
                 self.analyze_value(k, v);
             }
+        } else if let Value::Map(pairs) = value {
+            // Map values named after the map, like array elements
+            let objects: Vec<&ObjectMap<String, Value>> = pairs.iter().filter_map(|(_, v)| v.as_object()).collect();
+            if objects.is_empty() || objects.len() < pairs.len() {
+                return;
+            }
+            for obj in &objects {
+                for (k, v) in *obj {
+                    self.analyze_value(k, v);
+                }
+            }
+            self.analyze_nested_objects(hint_name, &objects);
         }
     }
 
@@ -1172,7 +2549,11 @@ impl SchemaInferrer {
                     most_fields_idx = i;
                 }
             } else {
-                return; // Not all objects
+                // Not all objects
+                if arr.iter().any(|v| matches!(v, Value::Object(_))) {
+                    self.skip(hint_name, "array mixes objects and non-objects");
+                }
+                return;
             }
         }
 
@@ -1190,21 +2571,25 @@ impl SchemaInferrer {
             field_count.keys().cloned().collect()
         };
 
-        // Skip schema inference if fields are empty, any field name is empty,
-        // or the schema name itself needs quoting (it appears unquoted in
-        // `@struct name(...)` and `@table name [...]`).
-        // Field names that need quoting are fine — they get quoted in the
-        // @struct definition, e.g. `@struct root("@type":string, name:string)`.
-        if field_names.is_empty()
-            || field_names.iter().any(|n| n.is_empty())
-            || needs_quoting(hint_name)
-        {
+        // Skip schema inference if fields are empty or any field name is
+        // empty. Names that need quoting are fine — they get quoted in the
+        // definition, e.g. `@struct "my-row"("@type":string, name:string)`.
+        if field_names.is_empty() {
+            return;
+        }
+        if field_names.iter().any(|n| n.is_empty()) {
+            self.skip(hint_name, "empty field name");
+            return;
+        }
+        if hint_name.is_empty() {
+            self.skip(hint_name, "empty schema name");
             return;
         }
 
         // Require at least 1 field present in ALL objects (shared structure)
         let common_count = field_count.values().filter(|&&c| c == total_objects).count();
         if common_count == 0 {
+            self.skip(hint_name, "no field is present in every object");
             return;
         }
 
@@ -1242,6 +2627,7 @@ impl SchemaInferrer {
         // "map", "string"). The parser rejects these as schema field types, so
         // emitting `@struct object (...)` would produce un-parseable output.
         if is_value_only_type_name(&schema_name) {
+            self.skip(hint_name, "schema name is a reserved type name");
             return;
         }
 
@@ -1318,6 +2704,7 @@ impl SchemaInferrer {
         // schema was created first (depth-first); preserve it to avoid overwriting
         // with a different structure.
         if self.schemas.contains_key(&schema_name) {
+            self.skip(hint_name, "schema name already taken by a nested schema");
             return;
         }
 
@@ -1364,20 +2751,23 @@ impl SchemaInferrer {
                 // Check if there's a nested schema for object fields
                 // (skip self-references: field singularizing to the schema being built)
                 let nested_schema_name = singularize(field_name);
-                if nested_schema_name != schema_name && self.schemas.contains_key(&nested_schema_name) {
-                    if matches!(inferred, InferredType::Object(_)) {
-                        field_type = FieldType {
-                            base: nested_schema_name,
-                            nullable: field_type.nullable,
-                            is_array: false,
-                        };
-                    }
+                if nested_schema_name != schema_name
+                    && self.schemas.contains_key(&nested_schema_name)
+                    && matches!(inferred, InferredType::Object(_))
+                {
+                    field_type = FieldType {
+                        base: nested_schema_name,
+                        nullable: field_type.nullable,
+                        is_array: false,
+                    };
                 }
 
                 schema.add_field(field_name, field_type);
             }
         }
 
+        let objects: Vec<&ObjectMap<String, Value>> = arr.iter().filter_map(Value::as_object).collect();
+        self.record(&schema, hint_name, &objects, &field_types);
         self.schema_order.push(schema_name.clone());
         self.schemas.insert(schema_name, schema);
     }
@@ -1422,20 +2812,28 @@ impl SchemaInferrer {
         // Compute schema name early so we can check if it needs quoting
         let schema_name = singularize(field_name);
 
-        // Skip empty objects, empty field names, or when the schema name itself
-        // needs quoting (it appears unquoted in `@struct name(...)` and `@table name [...]`).
-        // Field names that need quoting are fine — they get quoted in the definition.
-        if nested_field_names.is_empty()
-            || nested_field_names.iter().any(|n| n.is_empty())
-            || needs_quoting(&schema_name)
-            || is_value_only_type_name(&schema_name)
-        {
+        // Skip empty objects or empty field names. Names that need quoting
+        // are fine — they get quoted in the definition.
+        if nested_field_names.is_empty() {
+            return;
+        }
+        if nested_field_names.iter().any(|n| n.is_empty()) {
+            self.skip(field_name, "empty field name");
+            return;
+        }
+        if schema_name.is_empty() {
+            self.skip(field_name, "empty schema name");
+            return;
+        }
+        if is_value_only_type_name(&schema_name) {
+            self.skip(field_name, "schema name is a reserved type name");
             return;
         }
 
         // Require at least 1 field present in ALL objects (shared structure)
         let common_count = field_count.values().filter(|&&c| c == total_objects).count();
         if common_count == 0 {
+            self.skip(field_name, "no field is present in every object");
             return;
         }
 
@@ -1555,6 +2953,7 @@ impl SchemaInferrer {
             }
         }
 
+        self.record(&schema, field_name, objects, &field_types);
         self.schema_order.push(schema_name.clone());
         self.schemas.insert(schema_name, schema);
     }
@@ -1564,6 +2963,7 @@ impl SchemaInferrer {
     }
 }
 
+#[cfg(feature = "infer")]
 impl Default for SchemaInferrer {
     fn default() -> Self {
         Self::new()
@@ -1571,6 +2971,7 @@ impl Default for SchemaInferrer {
 }
 
 /// Serialize data to TeaLeaf text format with schemas
+#[cfg(feature = "text")]
 pub fn dumps_with_schemas(
     data: &IndexMap<String, Value>,
     schemas: &IndexMap<String, Schema>,
@@ -1583,6 +2984,7 @@ pub fn dumps_with_schemas(
 
 /// Serialize data to compact TeaLeaf text format with schemas.
 /// Removes insignificant whitespace for token-efficient output.
+#[cfg(feature = "text")]
 pub fn dumps_with_schemas_compact(
     data: &IndexMap<String, Value>,
     schemas: &IndexMap<String, Schema>,
@@ -1594,6 +2996,7 @@ pub fn dumps_with_schemas_compact(
 }
 
 /// Serialize data to TeaLeaf text format with schemas and custom options.
+#[cfg(feature = "text")]
 pub fn dumps_with_schemas_with_options(
     data: &IndexMap<String, Value>,
     schemas: &IndexMap<String, Schema>,
@@ -1605,6 +3008,7 @@ pub fn dumps_with_schemas_with_options(
     dumps_with_schemas_inner(data, schemas, schema_order, unions, union_order, opts)
 }
 
+#[cfg(feature = "text")]
 fn dumps_with_schemas_inner(
     data: &IndexMap<String, Value>,
     schemas: &IndexMap<String, Schema>,
@@ -1614,13 +3018,39 @@ fn dumps_with_schemas_inner(
     opts: &FormatOptions,
 ) -> String {
     let mut out = String::new();
+    write_definitions(&mut out, schemas, schema_order, unions, union_order, opts);
+    write_bound_data(&mut out, data, schemas, &IndexMap::new(), opts);
+    out
+}
+
+/// `field_type` as written in a definition, with struct and union names
+/// that are not plain identifiers quoted.
+#[cfg(feature = "text")]
+fn field_type_tl(field_type: &FieldType) -> String {
+    if !needs_quoting(&field_type.base) {
+        return field_type.to_string();
+    }
+    let array = if field_type.is_array { "[]" } else { "" };
+    let nullable = if field_type.nullable { "?" } else { "" };
+    format!("{}{}{}", array, quote_key(&field_type.base), nullable)
+}
+
+#[cfg(feature = "text")]
+fn write_definitions<W: TextSink>(
+    out: &mut W,
+    schemas: &IndexMap<String, Schema>,
+    schema_order: &[String],
+    unions: &IndexMap<String, Union>,
+    union_order: &[String],
+    opts: &FormatOptions,
+) {
     let mut has_definitions = false;
 
     // Write union definitions first (before structs, since structs may reference unions)
     for name in union_order {
         if let Some(union) = unions.get(name) {
             out.push_str("@union ");
-            out.push_str(&union.name);
+            out.push_str(&quote_key(&union.name));
             out.push_str(if opts.compact { "{\n" } else { " {\n" });
             for (vi, variant) in union.variants.iter().enumerate() {
                 if !opts.compact { out.push_str("  "); }
@@ -1630,9 +3060,9 @@ fn dumps_with_schemas_inner(
                     if fi > 0 {
                         out.push_str(sep(opts.compact));
                     }
-                    out.push_str(&field.name);
+                    out.push_str(&quote_key(&field.name));
                     out.push_str(kv_sep(opts.compact));
-                    out.push_str(&field.field_type.to_string());
+                    out.push_str(&field_type_tl(&field.field_type));
                 }
                 out.push(')');
                 if vi < union.variants.len() - 1 {
@@ -1649,15 +3079,15 @@ fn dumps_with_schemas_inner(
     for name in schema_order {
         if let Some(schema) = schemas.get(name) {
             out.push_str("@struct ");
-            out.push_str(&schema.name);
+            out.push_str(&quote_key(&schema.name));
             out.push_str(if opts.compact { "(" } else { " (" });
             for (i, field) in schema.fields.iter().enumerate() {
                 if i > 0 {
                     out.push_str(sep(opts.compact));
                 }
-                write_key(&mut out, &field.name);
+                out.push_str(&quote_key(&field.name));
                 out.push_str(kv_sep(opts.compact));
-                out.push_str(&field.field_type.to_string());
+                out.push_str(&field_type_tl(&field.field_type));
             }
             out.push_str(")\n");
             has_definitions = true;
@@ -1667,16 +3097,42 @@ fn dumps_with_schemas_inner(
     if has_definitions && !opts.compact {
         out.push('\n');
     }
+}
 
+#[cfg(feature = "text")]
+fn write_bound_data<W: TextSink>(
+    out: &mut W,
+    data: &IndexMap<String, Value>,
+    schemas: &IndexMap<String, Schema>,
+    bindings: &IndexMap<String, String>,
+    opts: &FormatOptions,
+) {
     // Write data (preserves insertion order)
     for (key, value) in data {
-        write_key(&mut out, key);
+        write_key(out, key);
         out.push_str(kv_sep(opts.compact));
-        write_value_with_schemas(&mut out, value, schemas, Some(key), 0, None, opts);
+        // An explicit binding is the declared type; objects say so with @as,
+        // or are written as a tuple with @row
+        let bound = bindings.get(key).and_then(|name| schemas.get(name));
+        if let (Some(schema), Value::Object(obj)) = (bound, value) {
+            let keys: HashSet<&str> = obj.keys().map(|k| k.as_str()).collect();
+            if object_matches_schema(&keys, schema) && opts.row_objects {
+                out.push_str("@row ");
+                out.push_str(&quote_key(&schema.name));
+                out.push_str(if opts.compact { "" } else { " " });
+                write_tuple(out, value, schema, schemas, 0, opts);
+                out.push('\n');
+                continue;
+            }
+            if object_matches_schema(&keys, schema) {
+                out.push_str("@as ");
+                out.push_str(&quote_key(&schema.name));
+                out.push_str(if opts.compact { "" } else { " " });
+            }
+        }
+        write_value_with_schemas(out, value, schemas, Some(key), 0, bound.map(|s| s.name.as_str()), opts);
         out.push('\n');
     }
-
-    out
 }
 
 /// Resolve a schema for a value by trying three strategies in order:
@@ -1711,8 +3167,9 @@ fn resolve_schema<'a>(
     None
 }
 
-fn write_value_with_schemas(
-    out: &mut String,
+#[cfg(feature = "text")]
+fn write_value_with_schemas<W: TextSink>(
+    out: &mut W,
     value: &Value,
     schemas: &IndexMap<String, Schema>,
     hint_name: Option<&str>,
@@ -1720,11 +3177,13 @@ fn write_value_with_schemas(
     declared_type: Option<&str>,
     opts: &FormatOptions,
 ) {
+    // Strict output takes no hints from key names
+    let hint_name = if opts.strict_schemas { None } else { hint_name };
     match value {
         Value::Null => out.push('~'),
         Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
-        Value::Int(i) => out.push_str(&i.to_string()),
-        Value::UInt(u) => out.push_str(&u.to_string()),
+        Value::Int(i) => write_integer(out, *i as i128, schemas, declared_type),
+        Value::UInt(u) => write_integer(out, *u as i128, schemas, declared_type),
         Value::JsonNumber(s) => out.push_str(s),
         Value::Float(f) => out.push_str(&format_float(*f, opts.compact_floats)),
         Value::String(s) => {
@@ -1738,9 +3197,19 @@ fn write_value_with_schemas(
         }
         Value::Bytes(b) => {
             out.push_str("b\"");
-            push_hex_bytes(out, b);
+            out.push_hex(b);
             out.push('"');
         }
+        Value::Secret(secret) => match opts.secrets {
+            SecretOutput::Redact => {
+                out.push('"');
+                out.push_str(secret::REDACTED);
+                out.push('"');
+            }
+            SecretOutput::Expose => {
+                write_value_with_schemas(out, &secret.expose_value(), schemas, hint_name, indent, declared_type, opts);
+            }
+        },
         Value::Array(arr) => {
             // Check if this array can use @table format.
             // Try name-based resolution first, then structural matching as fallback.
@@ -1754,7 +3223,7 @@ fn write_value_with_schemas(
             // Only apply when we have context (hint_name or declared_type) — inside
             // tuple values (both None), structural matching can pick the wrong schema
             // and produce @table where the parser doesn't expect it.
-            if schema.is_none() && (hint_name.is_some() || declared_type.is_some()) {
+            if schema.is_none() && (hint_name.is_some() || declared_type.is_some()) && !opts.strict_schemas {
                 if let Some(Value::Object(first_obj)) = arr.first() {
                     let obj_keys: HashSet<&str> = first_obj.keys().map(|k| k.as_str()).collect();
                     for (_, candidate) in schemas {
@@ -1779,42 +3248,25 @@ fn write_value_with_schemas(
                     false
                 };
 
-                if schema_matches {
+                if schema_matches && arr.len() >= opts.min_table_rows {
                     out.push_str("@table ");
-                    out.push_str(&schema.name);
-                    out.push_str(if opts.compact { "[\n" } else { " [\n" });
-
-                    let inner_indent = if opts.compact { 0 } else { indent + 2 };
-                    for (i, item) in arr.iter().enumerate() {
-                        if !opts.compact {
-                            for _ in 0..inner_indent {
-                                out.push(' ');
-                            }
-                        }
-                        write_tuple(out, item, schema, schemas, inner_indent, opts);
-                        if i < arr.len() - 1 {
-                            out.push(',');
-                        }
-                        out.push('\n');
-                    }
-
-                    if !opts.compact {
-                        for _ in 0..indent {
-                            out.push(' ');
-                        }
-                    }
-                    out.push(']');
+                    out.push_str(&quote_key(&schema.name));
+                    out.push_str(if opts.compact { "" } else { " " });
+                    write_tuple_rows(out, arr, schema, schemas, indent, opts);
                     return;
                 }
             }
 
             // Fall back to regular array format
+            let expanded = expands(value, opts);
+            let inner = if expanded { indent + 2 } else { indent };
             out.push('[');
             for (i, v) in arr.iter().enumerate() {
-                if i > 0 {
-                    out.push_str(sep(opts.compact));
-                }
-                write_value_with_schemas(out, v, schemas, None, indent, None, opts);
+                start_entry(out, i, expanded, inner, opts);
+                write_value_with_schemas(out, v, schemas, None, inner, None, opts);
+            }
+            if expanded {
+                new_line(out, indent);
             }
             out.push(']');
         }
@@ -1823,7 +3275,7 @@ fn write_value_with_schemas(
             // Try name-based resolution first, then structural matching as fallback.
             let mut obj_schema = resolve_schema(schemas, declared_type, hint_name);
 
-            if obj_schema.is_none() {
+            if obj_schema.is_none() && !opts.strict_schemas {
                 let obj_keys: HashSet<&str> = obj.keys().map(|k| k.as_str()).collect();
                 for (_, candidate) in schemas {
                     if object_matches_schema(&obj_keys, candidate) {
@@ -1833,11 +3285,11 @@ fn write_value_with_schemas(
                 }
             }
 
+            let expanded = expands(value, opts);
+            let inner = if expanded { indent + 2 } else { indent };
             out.push('{');
             for (i, (k, v)) in obj.iter().enumerate() {
-                if i > 0 {
-                    out.push_str(sep(opts.compact));
-                }
+                start_entry(out, i, expanded, inner, opts);
                 write_key(out, k);
                 out.push_str(kv_sep(opts.compact));
                 // Look up this field's declared type from the parent schema
@@ -1846,21 +3298,41 @@ fn write_value_with_schemas(
                         .find(|f| f.name == *k)
                         .map(|f| f.field_type.base.as_str())
                 });
-                write_value_with_schemas(out, v, schemas, Some(k), indent, field_type, opts);
+                write_value_with_schemas(out, v, schemas, Some(k), inner, field_type, opts);
+            }
+            if expanded {
+                new_line(out, indent);
             }
             out.push('}');
         }
         Value::Map(pairs) => {
+            // Maps whose values all fit the schema named after the key are
+            // written `@map name {k: (...)}`, like `@table` rows
+            let value_schema = resolve_schema(schemas, declared_type, hint_name).filter(|schema| {
+                !pairs.is_empty() && pairs.iter().all(|(_, v)| match v {
+                    Value::Object(obj) => object_matches_schema(&obj.keys().map(|k| k.as_str()).collect(), schema),
+                    _ => false,
+                })
+            });
+            if let Some(schema) = value_schema {
+                out.push_str("@map ");
+                out.push_str(&quote_key(&schema.name));
+                out.push_str(if opts.compact { "" } else { " " });
+                write_map_rows(out, pairs, schema, schemas, indent, opts);
+                return;
+            }
+
+            let expanded = expands(value, opts);
+            let inner = if expanded { indent + 2 } else { indent };
             out.push_str(if opts.compact { "@map{" } else { "@map {" });
-            let mut first = true;
-            for (k, v) in pairs {
-                if !first {
-                    out.push_str(sep(opts.compact));
-                }
-                first = false;
-                write_map_key(out, k);
+            for (i, (k, v)) in pairs.iter().enumerate() {
+                start_entry(out, i, expanded, inner, opts);
+                write_map_key(out, k, opts);
                 out.push_str(kv_sep(opts.compact));
-                write_value_with_schemas(out, v, schemas, None, indent, None, opts);
+                write_value_with_schemas(out, v, schemas, None, inner, None, opts);
+            }
+            if expanded {
+                new_line(out, indent);
             }
             out.push('}');
         }
@@ -1877,17 +3349,36 @@ fn write_value_with_schemas(
         Value::Timestamp(ts, tz) => {
             out.push_str(&format_timestamp_millis(*ts, *tz));
         }
+        Value::TimestampNanos(ns, tz, precision) => {
+            out.push_str(&format_timestamp_nanos(*ns, *tz, *precision));
+        }
+    }
+}
+
+/// Write an integer, with a unit if it is declared `bytesize` or `duration`
+/// (and no schema of that name shadows the type).
+#[cfg(feature = "text")]
+fn write_integer<W: TextSink>(out: &mut W, n: i128, schemas: &IndexMap<String, Schema>, declared_type: Option<&str>) {
+    let quantity = declared_type
+        .filter(|t| !schemas.contains_key(*t))
+        .and_then(units::Quantity::for_field);
+    match quantity {
+        Some(quantity) if i64::try_from(n).is_ok() => out.push_str(&quantity.format(n)),
+        _ => out.push_str(&n.to_string()),
     }
 }
 
-fn write_tuple(
-    out: &mut String,
+#[cfg(feature = "text")]
+fn write_tuple<W: TextSink>(
+    out: &mut W,
     value: &Value,
     schema: &Schema,
     schemas: &IndexMap<String, Schema>,
     indent: usize,
     opts: &FormatOptions,
 ) {
+    // A tuple is one row, so it stays on one line
+    let opts = &FormatOptions { line_per_field: false, ..*opts };
     if let Value::Object(obj) = value {
         out.push('(');
         for (i, field) in schema.fields.iter().enumerate() {
@@ -1902,6 +3393,14 @@ fn write_tuple(
                 } else {
                     let type_base = field.field_type.base.as_str();
                     let nested_schema = resolve_schema(schemas, Some(type_base), None);
+                    // A `map` field is written as `@map` even if the data holds an object
+                    let v = &*match (type_base, v) {
+                        ("map", Value::Array(items)) if field.field_type.is_array => {
+                            std::borrow::Cow::Owned(Value::Array(items.iter().map(|item| lookup::as_map_field(item).into_owned()).collect()))
+                        }
+                        ("map", _) => lookup::as_map_field(v),
+                        _ => std::borrow::Cow::Borrowed(v),
+                    };
                     // For array fields with a known schema type, write tuples directly without @table
                     if field.field_type.is_array {
                         if let Some(item_schema) = nested_schema {
@@ -1914,6 +3413,8 @@ fn write_tuple(
                     } else if let Some(nested_schema) = nested_schema {
                         // Non-array field with schema type - write as nested tuple
                         write_tuple(out, v, nested_schema, schemas, indent, opts);
+                    } else if units::Quantity::for_field(type_base).is_some() {
+                        write_value_with_schemas(out, v, schemas, None, indent, Some(type_base), opts);
                     } else {
                         write_value_with_schemas(out, v, schemas, None, indent, None, opts);
                     }
@@ -1922,15 +3423,102 @@ fn write_tuple(
                 out.push('~');  // Absent field — not in object
             }
         }
-        out.push(')');
-    } else {
-        write_value_with_schemas(out, value, schemas, None, indent, None, opts);
+        out.push(')');
+    } else {
+        write_value_with_schemas(out, value, schemas, None, indent, None, opts);
+    }
+}
+
+/// Write `arr` as a bracketed list of `schema` tuples, one per line unless
+/// the array is short enough for `opts.inline_table_rows`.
+#[cfg(feature = "text")]
+fn write_tuple_rows<W: TextSink>(
+    out: &mut W,
+    arr: &[Value],
+    schema: &Schema,
+    schemas: &IndexMap<String, Schema>,
+    indent: usize,
+    opts: &FormatOptions,
+) {
+    if arr.len() <= opts.inline_table_rows && (!opts.line_per_field || opts.compact) {
+        out.push('[');
+        for (i, item) in arr.iter().enumerate() {
+            if i > 0 {
+                out.push_str(sep(opts.compact));
+            }
+            write_tuple(out, item, schema, schemas, indent, opts);
+        }
+        out.push(']');
+        return;
+    }
+
+    out.push_str("[\n");
+    let inner_indent = if opts.compact { 0 } else { indent + 2 };
+    for (i, item) in arr.iter().enumerate() {
+        if !opts.compact {
+            for _ in 0..inner_indent {
+                out.push(' ');
+            }
+        }
+        write_tuple(out, item, schema, schemas, inner_indent, opts);
+        if i < arr.len() - 1 {
+            out.push(',');
+        }
+        out.push('\n');
+    }
+    if !opts.compact {
+        for _ in 0..indent {
+            out.push(' ');
+        }
+    }
+    out.push(']');
+}
+
+/// Write map entries with `schema` tuple values, one per line unless there
+/// are few enough for `opts.inline_table_rows`.
+#[cfg(feature = "text")]
+fn write_map_rows<W: TextSink>(
+    out: &mut W,
+    pairs: &[(Value, Value)],
+    schema: &Schema,
+    schemas: &IndexMap<String, Schema>,
+    indent: usize,
+    opts: &FormatOptions,
+) {
+    let inline = pairs.len() <= opts.inline_table_rows && (!opts.line_per_field || opts.compact);
+    let inner_indent = if opts.compact || inline { indent } else { indent + 2 };
+    out.push_str(if inline { "{" } else { "{\n" });
+    for (i, (k, v)) in pairs.iter().enumerate() {
+        if inline && i > 0 {
+            out.push_str(sep(opts.compact));
+        }
+        if !inline && !opts.compact {
+            for _ in 0..inner_indent {
+                out.push(' ');
+            }
+        }
+        write_map_key(out, k, opts);
+        out.push_str(kv_sep(opts.compact));
+        write_tuple(out, v, schema, schemas, inner_indent, opts);
+        if !inline {
+            if i < pairs.len() - 1 {
+                out.push(',');
+            }
+            out.push('\n');
+        }
+    }
+    if !inline && !opts.compact {
+        for _ in 0..indent {
+            out.push(' ');
+        }
     }
+    out.push('}');
 }
 
 /// Write an array of schema-typed values as tuples (without @table annotation)
-fn write_schema_array(
-    out: &mut String,
+#[cfg(feature = "text")]
+fn write_schema_array<W: TextSink>(
+    out: &mut W,
     value: &Value,
     schema: &Schema,
     schemas: &IndexMap<String, Schema>,
@@ -1942,40 +3530,21 @@ fn write_schema_array(
             out.push_str("[]");
             return;
         }
-
-        out.push_str("[\n");
-        let inner_indent = if opts.compact { 0 } else { indent + 2 };
-        for (i, item) in arr.iter().enumerate() {
-            if !opts.compact {
-                for _ in 0..inner_indent {
-                    out.push(' ');
-                }
-            }
-            write_tuple(out, item, schema, schemas, inner_indent, opts);
-            if i < arr.len() - 1 {
-                out.push(',');
-            }
-            out.push('\n');
-        }
-        if !opts.compact {
-            for _ in 0..indent {
-                out.push(' ');
-            }
-        }
-        out.push(']');
+        write_tuple_rows(out, arr, schema, schemas, indent, opts);
     } else {
         // Not an array - fall back to regular value writing
         write_value_with_schemas(out, value, schemas, None, indent, None, opts);
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "text", feature = "binary", feature = "json"))]
 mod tests {
     use super::*;
 
     /// Compare two Values treating absent keys as equivalent to null.
     /// With union-based schema inference, optional fields absent in the
     /// original appear as explicit nulls after roundtrip through @table.
+    #[cfg(feature = "infer")]
     fn values_eq_with_optional_nulls(a: &Value, b: &Value) -> bool {
         match (a, b) {
             (Value::Object(oa), Value::Object(ob)) => {
@@ -2028,8 +3597,8 @@ mod tests {
 
         // Test how Rust formats floats
         println!("Rust float formatting:");
-        println!("  42.0f64.to_string() = '{}'", 42.0f64.to_string());
-        println!("  42.5f64.to_string() = '{}'", 42.5f64.to_string());
+        println!("  42.0f64.to_string() = '{}'", 42.0f64);
+        println!("  42.5f64.to_string() = '{}'", 42.5f64);
 
         // This is the problem! Rust's to_string() drops the .0
         // We need to ensure floats always have a decimal point
@@ -2048,6 +3617,24 @@ mod tests {
         assert_eq!(doc.get("active").unwrap().as_bool(), Some(true));
     }
 
+    #[test]
+    fn test_parse_lenient() {
+        let input = "name: \"a\\qb\"\ncount: 3\nitems: [1, 2,\n  nested: x\nok: \"yes\"\n";
+        let (doc, errors) = TeaLeaf::parse_lenient(input);
+        assert_eq!(doc.get("count").unwrap().as_int(), Some(3));
+        assert_eq!(doc.get("ok").unwrap().as_str(), Some("yes"));
+        assert!(doc.get("name").is_none() && doc.get("items").is_none());
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].starts_with("Parse error: line 1: Invalid escape"), "{:?}", messages);
+        // The unclosed array is reported where parsing gave up on it
+        assert!(messages[1].contains("line 5: "), "{:?}", messages);
+
+        let (clean, errors) = TeaLeaf::parse_lenient("a: 1\nb: [true]");
+        assert!(errors.is_empty());
+        assert_eq!(clean.data, TeaLeaf::parse("a: 1\nb: [true]").unwrap().data);
+    }
+
     #[test]
     fn test_parse_struct() {
         let doc = TeaLeaf::parse(r#"
@@ -2166,7 +3753,7 @@ mod tests {
         // Create a document with bytes programmatically
         let mut entries = IndexMap::new();
         entries.insert("data".to_string(), Value::Bytes(vec![0xca, 0xfe, 0xf0, 0x0d]));
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let json = doc.to_json().unwrap();
         assert!(json.contains("0xcafef00d"), "Bytes should export as hex string: {}", json);
@@ -2176,7 +3763,7 @@ mod tests {
     fn test_json_export_ref() {
         let mut entries = IndexMap::new();
         entries.insert("config".to_string(), Value::Ref("base_config".to_string()));
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let json = doc.to_json().unwrap();
         assert!(json.contains("\"$ref\""), "Ref should export with $ref key: {}", json);
@@ -2187,7 +3774,7 @@ mod tests {
     fn test_json_export_tagged() {
         let mut entries = IndexMap::new();
         entries.insert("status".to_string(), Value::Tagged("ok".to_string(), Box::new(Value::Int(200))));
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let json = doc.to_json().unwrap();
         assert!(json.contains("\"$tag\""), "Tagged should export with $tag key: {}", json);
@@ -2202,7 +3789,7 @@ mod tests {
             (Value::Int(1), Value::String("one".to_string())),
             (Value::Int(2), Value::String("two".to_string())),
         ]));
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let json = doc.to_json().unwrap();
         // Map exports as array of [key, value] pairs
@@ -2221,7 +3808,7 @@ mod tests {
         // 2024-01-15T10:30:00Z = 1705315800000 ms, but let's verify with a known value
         // Use 0 = 1970-01-01T00:00:00Z for simplicity
         entries.insert("created".to_string(), Value::Timestamp(0, 0));
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let json = doc.to_json().unwrap();
         assert!(json.contains("1970-01-01"), "Timestamp should export as ISO 8601 date: {}", json);
@@ -2343,7 +3930,7 @@ mod tests {
         let mut entries = IndexMap::new();
         entries.insert("data".to_string(), Value::String("a".repeat(1000)));
         entries.insert("count".to_string(), Value::Int(12345));
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let temp = NamedTempFile::new().unwrap();
         doc.compile(temp.path(), true).unwrap(); // compressed
@@ -2362,7 +3949,7 @@ mod tests {
             ("host".to_string(), Value::String("localhost".to_string())),
         ].into_iter().collect()));
         entries.insert("config".to_string(), Value::Ref("base".to_string()));
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let temp = NamedTempFile::new().unwrap();
         doc.compile(temp.path(), false).unwrap();
@@ -2378,7 +3965,7 @@ mod tests {
 
         let mut entries = IndexMap::new();
         entries.insert("status".to_string(), Value::Tagged("ok".to_string(), Box::new(Value::Int(200))));
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let temp = NamedTempFile::new().unwrap();
         doc.compile(temp.path(), false).unwrap();
@@ -2399,7 +3986,7 @@ mod tests {
             (Value::Int(1), Value::String("one".to_string())),
             (Value::Int(2), Value::String("two".to_string())),
         ]));
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let temp = NamedTempFile::new().unwrap();
         doc.compile(temp.path(), false).unwrap();
@@ -2418,7 +4005,7 @@ mod tests {
 
         let mut entries = IndexMap::new();
         entries.insert("data".to_string(), Value::Bytes(vec![0xca, 0xfe, 0xf0, 0x0d]));
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let temp = NamedTempFile::new().unwrap();
         doc.compile(temp.path(), false).unwrap();
@@ -2434,7 +4021,7 @@ mod tests {
 
         let mut entries = IndexMap::new();
         entries.insert("created".to_string(), Value::Timestamp(1705315800000, 0)); // 2024-01-15T10:30:00Z
-        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data: entries, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let temp = NamedTempFile::new().unwrap();
         doc.compile(temp.path(), false).unwrap();
@@ -2492,7 +4079,7 @@ mod tests {
         data.insert("null_val".to_string(), Value::Null);
         data.insert("bool_true".to_string(), Value::Bool(true));
         data.insert("int_val".to_string(), Value::Int(42));
-        data.insert("float_val".to_string(), Value::Float(3.14159));
+        data.insert("float_val".to_string(), Value::Float(6.02214));
         data.insert("string_val".to_string(), Value::String("hello".to_string()));
         data.insert("bytes_val".to_string(), Value::Bytes(vec![0xca, 0xfe]));
         data.insert("timestamp_val".to_string(), Value::Timestamp(0, 0));
@@ -2506,7 +4093,7 @@ mod tests {
             (Value::Int(1), Value::String("one".to_string())),
         ]));
 
-        let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         // Compile to binary and read back
         let temp = NamedTempFile::new().unwrap();
@@ -2517,7 +4104,7 @@ mod tests {
         assert!(reader.get("null_val").unwrap().is_null());
         assert_eq!(reader.get("bool_true").unwrap().as_bool(), Some(true));
         assert_eq!(reader.get("int_val").unwrap().as_int(), Some(42));
-        assert_eq!(reader.get("float_val").unwrap().as_float(), Some(3.14159));
+        assert_eq!(reader.get("float_val").unwrap().as_float(), Some(6.02214));
         assert_eq!(reader.get("string_val").unwrap().as_str(), Some("hello"));
         assert_eq!(reader.get("bytes_val").unwrap().as_bytes(), Some(&[0xca, 0xfe][..]));
         assert_eq!(reader.get("timestamp_val").unwrap().as_timestamp_millis(), Some(0));
@@ -2602,9 +4189,9 @@ mod tests {
 
         #[test]
         fn contract_float_roundtrip() {
-            let doc = TeaLeaf::from_json(r#"{"pi": 3.14159}"#).unwrap();
-            let pi = doc.get("pi").unwrap().as_float().unwrap();
-            assert!((pi - 3.14159).abs() < 0.00001);
+            let doc = TeaLeaf::from_json(r#"{"avogadro": 6.02214}"#).unwrap();
+            let avogadro = doc.get("avogadro").unwrap().as_float().unwrap();
+            assert!((avogadro - 6.02214).abs() < 0.00001);
         }
 
         #[test]
@@ -2650,7 +4237,7 @@ mod tests {
         fn contract_bytes_to_json_hex() {
             let mut data = IndexMap::new();
             data.insert("b".to_string(), Value::Bytes(vec![0xca, 0xfe, 0xba, 0xbe]));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: Bytes serialize as lowercase hex with 0x prefix
@@ -2661,7 +4248,7 @@ mod tests {
         fn contract_bytes_empty_to_json() {
             let mut data = IndexMap::new();
             data.insert("b".to_string(), Value::Bytes(vec![]));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: Empty bytes serialize as "0x"
@@ -2673,7 +4260,7 @@ mod tests {
             let mut data = IndexMap::new();
             // 2024-01-15T10:50:00.123Z (verified milliseconds since epoch)
             data.insert("ts".to_string(), Value::Timestamp(1705315800123, 0));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: Timestamp serializes as ISO 8601 with milliseconds
@@ -2685,7 +4272,7 @@ mod tests {
         fn contract_timestamp_epoch_to_json() {
             let mut data = IndexMap::new();
             data.insert("ts".to_string(), Value::Timestamp(0, 0));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: Unix epoch is 1970-01-01T00:00:00Z (no ms for whole seconds)
@@ -2697,7 +4284,7 @@ mod tests {
         fn contract_ref_to_json() {
             let mut data = IndexMap::new();
             data.insert("r".to_string(), Value::Ref("target_key".to_string()));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: Ref serializes as {"$ref": "name"}
@@ -2709,7 +4296,7 @@ mod tests {
         fn contract_tagged_to_json() {
             let mut data = IndexMap::new();
             data.insert("t".to_string(), Value::Tagged("ok".to_string(), Box::new(Value::Int(200))));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: Tagged serializes with $tag and $value keys
@@ -2723,7 +4310,7 @@ mod tests {
         fn contract_tagged_null_value_to_json() {
             let mut data = IndexMap::new();
             data.insert("t".to_string(), Value::Tagged("none".to_string(), Box::new(Value::Null)));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: Tagged with null inner still has $value: null
@@ -2738,7 +4325,7 @@ mod tests {
                 (Value::Int(1), Value::String("one".to_string())),
                 (Value::Int(2), Value::String("two".to_string())),
             ]));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: Map serializes as array of [key, value] pairs
@@ -2751,7 +4338,7 @@ mod tests {
         fn contract_map_empty_to_json() {
             let mut data = IndexMap::new();
             data.insert("m".to_string(), Value::Map(vec![]));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: Empty map serializes as empty array
@@ -2836,7 +4423,7 @@ mod tests {
         fn contract_float_nan_to_null() {
             let mut data = IndexMap::new();
             data.insert("f".to_string(), Value::Float(f64::NAN));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: NaN serializes as null (JSON has no NaN)
@@ -2847,7 +4434,7 @@ mod tests {
         fn contract_float_infinity_to_null() {
             let mut data = IndexMap::new();
             data.insert("f".to_string(), Value::Float(f64::INFINITY));
-            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+            let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
             let json = doc.to_json_compact().unwrap();
             // CONTRACT: Infinity serializes as null (JSON has no Infinity)
@@ -2869,6 +4456,7 @@ mod tests {
     // Schema Inference Tests
     // =========================================================================
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_simple_array() {
         let json = r#"{"users": [{"name": "alice", "age": 30}, {"name": "bob", "age": 25}]}"#;
@@ -2891,6 +4479,7 @@ mod tests {
         assert_eq!(users[0].as_object().unwrap().get("name").unwrap().as_str(), Some("alice"));
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_nested_arrays() {
         let json = r#"{
@@ -2916,6 +4505,7 @@ mod tests {
         assert_eq!(items_field.field_type.base, "item");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_to_tl_text() {
         let json = r#"{"products": [{"name": "Widget", "price": 9.99}, {"name": "Gadget", "price": 19.99}]}"#;
@@ -2935,6 +4525,7 @@ mod tests {
         assert!(tl_text.contains("Widget") || tl_text.contains("\"Widget\""), "Data should be present");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_roundtrip() {
         let json = r#"{"items": [{"id": 1, "name": "A"}, {"id": 2, "name": "B"}]}"#;
@@ -2956,6 +4547,7 @@ mod tests {
         assert!(parsed.schema("item").is_some());
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_nullable_fields() {
         let json = r#"{"users": [{"name": "alice", "email": "a@test.com"}, {"name": "bob", "email": null}]}"#;
@@ -2968,6 +4560,7 @@ mod tests {
         assert!(email_field.field_type.nullable, "Field with null values should be nullable");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_nested_tuples_no_redundant_table() {
         let json = r#"{
@@ -2993,6 +4586,7 @@ mod tests {
         assert_eq!(items[0].as_object().unwrap().get("sku").unwrap().as_str(), Some("A"));
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_mismatched_arrays_not_matched() {
         // Test that arrays with different structures don't incorrectly share schemas
@@ -3015,6 +4609,7 @@ mod tests {
         assert!(product_schema.fields.iter().any(|f| f.name == "price"));
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_special_char_quoting() {
         // Test that strings with special characters are properly quoted
@@ -3038,6 +4633,7 @@ mod tests {
         assert_eq!(item.get("email").unwrap().as_str(), Some("test@example.com"));
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_nested_objects() {
         // Test that nested objects within array elements get schemas created
@@ -3114,6 +4710,7 @@ mod tests {
         assert!(tl_text.contains("shipping_address: shipping_address"), "customer should have shipping_address field with shipping_address type");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_nested_objects_with_nulls() {
         // Test that nested objects handle nullable fields correctly
@@ -3144,6 +4741,7 @@ mod tests {
         assert!(phone_field.field_type.nullable, "phone field should be nullable");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_fuzz_crash_reserved_type_name_as_schema() {
         // Reproduces fuzz crash: [{"object":{"b":0}}]
@@ -3180,7 +4778,7 @@ mod tests {
         data.insert("bool_val".to_string(), Value::Bool(true));
         data.insert("int_val".to_string(), Value::Int(42));
         data.insert("uint_val".to_string(), Value::UInt(999));
-        data.insert("float_val".to_string(), Value::Float(3.14));
+        data.insert("float_val".to_string(), Value::Float(2.25));
         data.insert("str_val".to_string(), Value::String("hello".to_string()));
         data.insert("bytes_val".to_string(), Value::Bytes(vec![0xca, 0xfe]));
         data.insert("arr_val".to_string(), Value::Array(vec![Value::Int(1), Value::Int(2)]));
@@ -3201,7 +4799,7 @@ mod tests {
         assert!(output.contains("true"), "Should contain bool");
         assert!(output.contains("42"), "Should contain int");
         assert!(output.contains("999"), "Should contain uint");
-        assert!(output.contains("3.14"), "Should contain float");
+        assert!(output.contains("2.25"), "Should contain float");
         assert!(output.contains("hello"), "Should contain string");
         assert!(output.contains("b\"cafe\""), "Should contain bytes literal");
         assert!(output.contains("[1, 2]"), "Should contain array");
@@ -3291,7 +4889,7 @@ mod tests {
         assert_eq!(format_float(42.0, false), "42.0");
 
         // Float with decimals should stay as-is
-        assert_eq!(format_float(3.14, false), "3.14");
+        assert_eq!(format_float(2.25, false), "2.25");
 
         // Scientific notation stays as-is
         let very_small = format_float(1e-20, false);
@@ -3308,7 +4906,7 @@ mod tests {
         assert_eq!(format_float(-100.0, true), "-100");
 
         // Non-whole floats are unaffected
-        assert_eq!(format_float(3.14, true), "3.14");
+        assert_eq!(format_float(2.25, true), "2.25");
         assert_eq!(format_float(0.5, true), "0.5");
 
         // Special values unaffected
@@ -3319,13 +4917,18 @@ mod tests {
         // Very large floats use scientific notation (digits > 15), unaffected
         let large = format_float(1e20, true);
         assert!(large.contains('e'), "Very large should use scientific: {}", large);
+
+        // The cut-off is 15 digits, exactly
+        assert_eq!(format_float(999_999_999_999_999.0, true), "999999999999999");
+        assert_eq!(format_float(1e15, true), "1000000000000000.0");
+        assert_eq!(format_float(-0.0, true), "-0");
     }
 
     #[test]
     fn test_dumps_with_compact_floats() {
         let mut data = IndexMap::new();
         data.insert("revenue".to_string(), Value::Float(35934000000.0));
-        data.insert("ratio".to_string(), Value::Float(3.14));
+        data.insert("ratio".to_string(), Value::Float(2.25));
         data.insert("count".to_string(), Value::Int(42));
 
         // Default: whole floats keep .0
@@ -3337,10 +4940,135 @@ mod tests {
         let compact = dumps_with_options(&data, &opts);
         assert!(compact.contains("35934000000"), "Should have whole number: {}", compact);
         assert!(!compact.contains("35934000000.0"), "Should NOT have .0: {}", compact);
-        assert!(compact.contains("3.14"), "Non-whole float preserved: {}", compact);
+        assert!(compact.contains("2.25"), "Non-whole float preserved: {}", compact);
         assert!(compact.contains("42"), "Int preserved: {}", compact);
     }
 
+    #[test]
+    fn test_key_aliases() {
+        let row = |i| format!("{{userIdentifier: {i}, createdTimestamp: {i}, ok: true}}");
+        let rows: Vec<String> = (0..4).map(row).collect();
+        let input = format!(
+            "@struct user (userIdentifier: int)\n\
+             users: @table user [(1), (2)]\n\
+             events: [{}]\n\
+             lookup: @map {{userIdentifier: 1}}\n\
+             a: 1", rows.join(", "));
+        let doc = TeaLeaf::parse(&input).unwrap();
+
+        let opts = FormatOptions::default().with_key_aliases();
+        let aliased = doc.to_tl_with_options(&opts);
+        // `a` is taken by a key, short keys are not worth an alias
+        assert!(aliased.starts_with("@alias b=userIdentifier, c=createdTimestamp\n\n"), "{}", aliased);
+        assert!(aliased.contains("{b: 0, c: 0, ok: true}"), "{}", aliased);
+        // Field names and map keys are written in full
+        assert!(aliased.contains("@struct user (userIdentifier: int)"), "{}", aliased);
+        assert!(aliased.contains("@map {userIdentifier: 1}"), "{}", aliased);
+        assert!(aliased.len() < doc.to_tl_with_schemas().len());
+        assert_eq!(doc.estimated_text_size(&opts), aliased.len());
+
+        let compact = dumps_with_options(&doc.data, &FormatOptions::compact().with_key_aliases());
+        assert!(compact.starts_with("@alias b=userIdentifier,c=createdTimestamp\n"), "{}", compact);
+        for text in [aliased, compact] {
+            assert_eq!(TeaLeaf::parse(&text).unwrap().data, doc.data, "{}", text);
+        }
+
+        // Nothing repeats enough to pay for a directive
+        let few = TeaLeaf::parse("config: {userIdentifier: 1}").unwrap();
+        assert!(!few.to_tl_with_options(&opts).contains("@alias"));
+    }
+
+    #[test]
+    fn test_alias_directive_parsing() {
+        let doc = TeaLeaf::parse(
+            "@alias u=user_identifier, ct=\"content-type\"\n\
+             u: 1\n\
+             obj: {ct: json, \"u\": literal, v: 2}").unwrap();
+        assert_eq!(doc.get("user_identifier").unwrap().as_int(), Some(1));
+        let obj = doc.get("obj").unwrap();
+        assert_eq!(obj.get("content-type").unwrap().as_str(), Some("json"));
+        // Quoted keys are never expanded
+        assert_eq!(obj.get("u").unwrap().as_str(), Some("literal"));
+        assert_eq!(obj.get("v").unwrap().as_int(), Some(2));
+
+        assert!(TeaLeaf::parse("@alias u user\nx: 1").is_err());
+        assert!(TeaLeaf::parse("@alias u=[1]\nx: 1").is_err());
+    }
+
+    #[test]
+    fn test_load_detects_encoding() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        let text = "@struct p (name: string)\nrows: @table p [(\"Zoë\")]\n";
+        let write = |bytes: &[u8]| {
+            let mut file = NamedTempFile::new().unwrap();
+            file.write_all(bytes).unwrap();
+            file
+        };
+        let mut bom = b"\xEF\xBB\xBF".to_vec();
+        bom.extend_from_slice(text.as_bytes());
+        let file = write(&bom);
+        let doc = TeaLeaf::load(file.path()).unwrap();
+        assert_eq!(doc.get_path("rows[0].name").and_then(Value::as_str), Some("Zoë"));
+        assert_eq!(TeaLeaf::load_transcoded(file.path()).unwrap().1, Encoding::Utf8Bom);
+        assert_eq!(TeaLeaf::parse(&format!("\u{feff}{}", text)).unwrap().data, doc.data);
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        let file = write(&utf16);
+        let err = TeaLeaf::load(file.path()).err().unwrap();
+        assert!(matches!(err, Error::UnsupportedEncoding(Encoding::Utf16Le)));
+        assert!(err.to_string().starts_with("Text is UTF-16LE, not UTF-8"), "{}", err);
+        let (transcoded, encoding) = TeaLeaf::load_transcoded(file.path()).unwrap();
+        assert_eq!(encoding, Encoding::Utf16Le);
+        assert_eq!(transcoded.data, doc.data);
+    }
+
+    #[test]
+    fn test_line_per_field() {
+        let input = "@struct pt (x: int, y: int)\n\
+                     pts: @table pt [(1, 2), (3, 4)]\n\
+                     server: {host: localhost, tls: {on: true}, empty: {}, ports: [80, 443], routes: [{path: root}], m: @map {1: x}}\n";
+        let doc = TeaLeaf::parse(input).unwrap();
+        let text = doc.to_tl_with_options(&FormatOptions::diff().with_inline_tables(5));
+        assert!(text.ends_with("pts: @table pt [\n  (1, 2),\n  (3, 4)\n]\n\
+                                server: {\n  host: localhost,\n  tls: {\n    on: true\n  },\n  empty: {},\n  ports: [80, 443],\n\
+                                \x20 routes: [\n    {\n      path: root\n    }\n  ],\n  m: @map {\n    1: x\n  }\n}\n"), "{}", text);
+        assert_eq!(TeaLeaf::parse(&text).unwrap().data, doc.data);
+
+        let plain = dumps_with_options(&doc.data, &FormatOptions::diff());
+        assert!(plain.contains("pts: [\n  {\n    x: 1,\n    y: 2\n  },\n"), "{}", plain);
+        assert_eq!(TeaLeaf::parse(&plain).unwrap().data, doc.data);
+        // Compact output is unaffected
+        assert_eq!(doc.to_tl_with_options(&FormatOptions::compact().with_line_per_field()), doc.to_tl_with_options(&FormatOptions::compact()));
+    }
+
+    #[test]
+    fn test_inline_and_min_table_rows() {
+        let input = "@struct pt (x: int, y: int)\n@struct path (name: string, pts: []pt)\n\
+                     pts: @table pt [(1, 2), (3, 4)]\n\
+                     paths: @table path [(a, [(5, 6)]), (b, [(7, 8), (9, 10), (11, 12)])]";
+        let doc = TeaLeaf::parse(input).unwrap();
+
+        let inline = doc.to_tl_with_options(&FormatOptions::default().with_inline_tables(2));
+        assert!(inline.contains("pts: @table pt [(1, 2), (3, 4)]\n"), "{}", inline);
+        assert!(inline.contains("(a, [(5, 6)])"), "{}", inline);
+        assert!(inline.contains("(b, [\n"), "{}", inline);
+        let compact = doc.to_tl_with_options(&FormatOptions::compact().with_inline_tables(2));
+        assert!(compact.contains("pts:@table pt[(1,2),(3,4)]\n"), "{}", compact);
+
+        let plain = doc.to_tl_with_options(&FormatOptions::default().with_min_table_rows(3));
+        assert!(plain.contains("pts: [{x: 1, y: 2}, {x: 3, y: 4}]"), "{}", plain);
+        assert!(plain.contains("{name: a, pts: [{x: 5, y: 6}]}"), "{}", plain);
+        assert!(plain.contains("{name: b, pts: @table pt [\n"), "{}", plain);
+
+        for text in [inline, compact, plain] {
+            let reparsed = TeaLeaf::parse(&text).unwrap();
+            assert_eq!(reparsed.data, doc.data, "{}", text);
+        }
+    }
+
     #[test]
     fn test_needs_quoting_various_patterns() {
         // Should need quoting
@@ -3433,12 +5161,400 @@ mod tests {
         assert!(doc.get("root").unwrap().is_null());
     }
 
+    #[cfg(feature = "infer")]
+    #[test]
+    fn test_root_primitive_json_roundtrip() {
+        for json in [r#""hello""#, "42", "-1.5", "true", "null", "18446744073709551615"] {
+            let doc = TeaLeaf::from_json(json).unwrap();
+            assert!(doc.is_root_primitive);
+            assert_eq!(doc.to_json_compact().unwrap(), json);
+            assert_eq!(doc.to_json().unwrap(), json);
+        }
+
+        // Schema inference path carries the flag through
+        let doc = TeaLeaf::from_json_with_schemas("42").unwrap();
+        assert_eq!(doc.to_json_compact().unwrap(), "42");
+
+        // A real "root" key in an object document stays wrapped
+        let doc = TeaLeaf::from_json(r#"{"root": 42}"#).unwrap();
+        assert!(!doc.is_root_primitive);
+        assert_eq!(doc.to_json_compact().unwrap(), r#"{"root":42}"#);
+    }
+
+    #[test]
+    fn test_root_primitive_through_text_and_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        for json in [r#""hello""#, "42", "true", "null"] {
+            let doc = TeaLeaf::from_json(json).unwrap();
+
+            let text = doc.to_tl_with_schemas();
+            assert!(text.starts_with("@root-primitive\n"), "{}", text);
+            assert_eq!(TeaLeaf::parse(&text).unwrap().to_json_compact().unwrap(), json);
+
+            let path = dir.path().join("scalar.tlbx");
+            doc.compile(&path, false).unwrap();
+            let reader = Reader::open(&path).unwrap();
+            assert!(reader.is_root_primitive());
+            assert_eq!(TeaLeaf::from_reader(&reader).unwrap().to_json_compact().unwrap(), json);
+            let mut out = Vec::new();
+            reader.write_json(&mut out, JsonOptions::default()).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn test_to_json_warns_on_non_finite_floats() {
+        let doc = TeaLeaf::parse("a: NaN\nb: [1.5, inf]\nc: 2.0").unwrap();
+        let (json, warnings) = doc.to_json_with_warnings().unwrap();
+        assert_eq!(json, doc.to_json().unwrap());
+        assert_eq!(warnings, vec![
+            Warning::NonFiniteFloat { path: "a".into() },
+            Warning::NonFiniteFloat { path: "b[1]".into() },
+        ]);
+    }
+
+    #[test]
+    fn test_compact_floats_warns_on_int_reparse() {
+        let doc = TeaLeaf::parse("a: 42.0\nb: 1.5\nc: 1e300").unwrap();
+        let (_, warnings) = doc.to_tl_with_warnings(&FormatOptions::default());
+        assert!(warnings.is_empty());
+
+        let opts = FormatOptions::compact().with_compact_floats();
+        let (text, warnings) = doc.to_tl_with_warnings(&opts);
+        assert_eq!(text, doc.to_tl_with_options(&opts));
+        assert_eq!(warnings, vec![Warning::FloatBecomesInt { path: "a".into() }]);
+        assert_eq!(TeaLeaf::parse(&text).unwrap().get("a"), Some(&Value::Int(42)));
+    }
+
+    #[test]
+    fn test_compile_warns_on_schema_coercion() {
+        let doc = TeaLeaf::parse(r#"
+            @struct user (id: int, name: string)
+            users: @table user [(1, alice), ("x", bob), (2.5, carol)]
+        "#).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let warnings = doc.compile_with_warnings(dir.path().join("u.tlbx"), false).unwrap();
+        assert_eq!(warnings, vec![
+            Warning::SchemaCoercion { section: "users".into(), expected: "int".into(), found: "string".into() },
+            Warning::SchemaCoercion { section: "users".into(), expected: "int".into(), found: "float".into() },
+        ]);
+
+        let clean = TeaLeaf::parse("@struct p (x: int)\nps: @table p [(1), (2)]").unwrap();
+        assert!(clean.compile_with_warnings(dir.path().join("p.tlbx"), false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_compile_lists_unencodable_values() {
+        let mut doc = TeaLeaf::parse(r#"
+            @struct user (id: int, name: string)
+            users: @table user [(1, alice), ("x", bob)]
+        "#).unwrap();
+        let by_id = Value::Map(vec![(Value::Int(1), Value::String("one".into())), (Value::Float(1.5), Value::Null)]);
+        let flags = Value::Map(vec![(Value::Bool(true), Value::Null), (Value::Int(2), Value::String("two".into()))]);
+        let lookup = ObjectMap::from_iter([("by_id".to_string(), by_id), ("flags".to_string(), flags)]);
+        doc.data.insert("lookup".into(), Value::Object(lookup));
+        let dir = tempfile::tempdir().unwrap();
+
+        let err = doc.compile(dir.path().join("strict.tlbx"), false).unwrap_err();
+        match &err {
+            Error::Unencodable { section, values } => {
+                assert_eq!(section, "lookup");
+                let paths: Vec<&str> = values.iter().map(|v| v.path.as_str()).collect();
+                assert_eq!(paths, vec!["lookup.by_id[1]", "lookup.flags[0]"]);
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert_eq!(err.to_string(), "Section 'lookup' has 2 unencodable value(s): \
+            lookup.by_id[1]: map key is float; map keys must be string, int or uint; \
+            lookup.flags[0]: map key is bool; map keys must be string, int or uint");
+        assert!(!dir.path().join("strict.tlbx").exists());
+
+        let path = dir.path().join("lenient.tlbx");
+        let report = doc.compile_lenient(&path, false).unwrap();
+        assert_eq!(report.skipped.keys().collect::<Vec<_>>(), vec!["lookup"]);
+        assert_eq!(report.skipped["lookup"].len(), 2);
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.to_string().starts_with("skipped 'lookup': 2 unencodable value(s)\n  lookup.by_id[1]: "));
+        let reader = Reader::open(&path).unwrap();
+        assert_eq!(reader.keys(), vec!["users"]);
+    }
+
+    #[test]
+    fn test_map_schema_field_writes_objects_as_maps() {
+        let mut doc = TeaLeaf::parse(r#"
+            @struct catalog (name: string, prices: map, history: []map?)
+            shop: @table catalog [(main, @map {1: 9.5, sku: 2}, ~)]
+        "#).unwrap();
+        assert!(doc.get_path("shop[0].prices").unwrap().as_map().is_some());
+
+        // Data from JSON holds objects; the schema keeps them maps in the output
+        let row = doc.get_path("shop[0]").unwrap().clone();
+        let prices = row.get("prices").unwrap().map_to_object().unwrap();
+        let history = Value::Array(vec![prices.clone()]);
+        let mut row = row.as_object().unwrap().clone();
+        row.insert("prices".into(), prices);
+        row.insert("history".into(), history);
+        doc.data.insert("shop".into(), Value::Array(vec![Value::Object(row)]));
+
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("(main, @map {1: 9.5, sku: 2}, [@map {1: 9.5, sku: 2}])"), "{}", text);
+        let parsed = TeaLeaf::parse(&text).unwrap();
+        assert_eq!(parsed.get_path("shop[0].history[0]"), parsed.get_path("shop[0].prices"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.tlbx");
+        doc.compile(&path, false).unwrap();
+        let loaded = TeaLeaf::from_reader(&Reader::open(&path).unwrap()).unwrap();
+        assert_eq!(loaded.get_path("shop[0].prices"), parsed.get_path("shop[0].prices"));
+        assert_eq!(loaded.get_path("shop[0].history[0]"), parsed.get_path("shop[0].prices"));
+    }
+
+    #[cfg(feature = "infer")]
+    #[test]
+    fn test_inference_warns_on_skipped_arrays() {
+        let (doc, warnings) = TeaLeaf::from_json_with_schemas_and_warnings(
+            r#"{"mixed": [{"a": 1}, 2], "disjoint": [{"a": 1}, {"b": 2}], "users": [{"id": 1}], "nums": [1, 2]}"#,
+        ).unwrap();
+        assert!(doc.schema("user").is_some());
+        assert_eq!(warnings, vec![
+            Warning::InferenceSkipped { name: "mixed".into(), reason: "array mixes objects and non-objects".into() },
+            Warning::InferenceSkipped { name: "disjoint".into(), reason: "no field is present in every object".into() },
+        ]);
+    }
+
     #[test]
     fn test_from_json_invalid() {
         let result = TeaLeaf::from_json("not valid json {{{");
         assert!(result.is_err());
     }
 
+    #[cfg(feature = "infer")]
+    #[test]
+    fn test_from_json_with_limits() {
+        let json = r#"{"users": [{"name": "alice", "tags": ["a", "b"]}, {"name": "bob", "tags": []}]}"#;
+        let limits = JsonLimits::untrusted();
+        let (doc, _) = TeaLeaf::from_json_with_schemas_and_limits(json, &limits).unwrap();
+        assert!(doc.schema("user").is_some());
+
+        let deep = "[".repeat(100) + &"]".repeat(100);
+        let err = TeaLeaf::from_json_with_limits(&deep, &limits).err().unwrap();
+        assert!(matches!(err, Error::LimitExceeded(_)));
+
+        let err = TeaLeaf::from_json_with_limits(json, &limits.with_max_nodes(5)).err().unwrap();
+        assert!(err.to_string().contains("more than 5 values"));
+
+        let err = TeaLeaf::from_json_with_limits(json, &limits.with_max_bytes(10)).err().unwrap();
+        assert!(matches!(err, Error::LimitExceeded(_)));
+
+        // Malformed input within limits is still reported by the parser
+        let err = TeaLeaf::from_json_with_limits("{\"a\": }", &limits).err().unwrap();
+        assert!(matches!(err, Error::ParseError(_)));
+    }
+
+    #[test]
+    fn test_get_paths() {
+        let doc = TeaLeaf::parse("user: {name: alice, tags: [a, b]}\ncount: 3").unwrap();
+        let values = doc.get_paths(&["count", "user.tags[1]", "user.missing", "nope", "user"]);
+        assert_eq!(values.keys().collect::<Vec<_>>(), vec!["count", "user.tags[1]", "user.missing", "nope", "user"]);
+        assert_eq!(values["count"], Some(&Value::Int(3)));
+        assert_eq!(values["user.tags[1]"].and_then(|v| v.as_str()), Some("b"));
+        assert_eq!(values["user.missing"], None);
+        assert_eq!(values["nope"], None);
+        assert_eq!(values["user"], doc.get("user"));
+    }
+
+    #[test]
+    fn test_rename_key_updates_refs() {
+        let mut doc = TeaLeaf::parse("!origin: {x: 0, y: 0}\npath: [!origin, {x: 1, y: 2}]\nname: test").unwrap();
+        assert!(doc.rename_key("!origin", "!start"));
+        assert_eq!(doc.data.keys().collect::<Vec<_>>(), vec!["!start", "path", "name"]);
+        assert_eq!(doc.get_path("path[0]"), Some(&Value::Ref("start".into())));
+
+        assert!(doc.rename_key("name", "title"));
+        assert_eq!(doc.get("title").and_then(|v| v.as_str()), Some("test"));
+
+        assert!(!doc.rename_key("missing", "other"));
+        assert!(!doc.rename_key("title", "path"));
+        assert!(!doc.rename_key("title", "!title"));
+    }
+
+    #[test]
+    fn test_ref_definitions_written_unquoted() {
+        let doc = TeaLeaf::parse("!origin: {x: 0}\npoint: {!inner: 1, at: !inner}\nstart: !origin").unwrap();
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("!origin: {"), "{}", text);
+        assert!(text.contains("!inner: 1"), "{}", text);
+        assert!(!text.contains("\"!"), "{}", text);
+        let reparsed = TeaLeaf::parse(&text).unwrap();
+        assert_eq!(reparsed.get("start"), Some(&Value::Ref("origin".into())));
+    }
+
+    #[test]
+    fn test_rename_schema_updates_type_references() {
+        let input = "@struct address (city: string)\n@struct user (name: string, home: address, past: []address)\n\
+                     @union place { Home (at: address), Away () }\n\
+                     users: @table user [(alice, (Paris), [(Rome)])]";
+        let mut doc = TeaLeaf::parse(input).unwrap();
+        assert!(doc.rename_schema("address", "location"));
+        assert!(!doc.rename_schema("address", "other"));
+        assert!(!doc.rename_schema("user", "location"));
+        assert!(!doc.rename_schema("user", "place"));
+
+        assert_eq!(doc.schemas.keys().collect::<Vec<_>>(), vec!["location", "user"]);
+        assert_eq!(doc.schema("location").unwrap().name, "location");
+        let user = doc.schema("user").unwrap();
+        assert_eq!(user.fields[1].field_type.base, "location");
+        assert_eq!(user.fields[2].field_type.base, "location");
+        assert_eq!(doc.union("place").unwrap().variants[0].fields[0].field_type.base, "location");
+
+        let reparsed = TeaLeaf::parse(&doc.to_tl_with_schemas()).unwrap();
+        assert_eq!(reparsed.get_path("users[0].past[0].city").and_then(|v| v.as_str()), Some("Rome"));
+    }
+
+    #[test]
+    fn test_shrink_to_fit_keeps_data() {
+        let mut wide = ObjectMap::with_capacity(256);
+        wide.insert("tags".to_string(), Value::Array(Vec::with_capacity(64)));
+        wide.insert("name".to_string(), Value::String(String::with_capacity(128)));
+        let mut data = IndexMap::with_capacity(32);
+        data.insert("wide".to_string(), Value::Object(wide));
+        data.insert("list".to_string(), Value::Array(vec![Value::Tagged("t".into(), Box::new(Value::Map(Vec::with_capacity(16))))]));
+        let mut doc = TeaLeaf::new(IndexMap::new(), data);
+        let before = doc.data.clone();
+
+        doc.shrink_to_fit();
+        assert_eq!(doc.data, before);
+        assert!(doc.data.capacity() < 32);
+        let wide = doc.get("wide").unwrap().as_object().unwrap();
+        assert!(wide.capacity() < 256);
+        assert!(wide["tags"].as_array().unwrap().is_empty());
+        match &wide["name"] {
+            Value::String(s) => assert_eq!(s.capacity(), 0),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_as_binds_object_to_schema() {
+        let input = "@struct server_config (host: string, ports: []int)\n\
+                     config: @as server_config {host: localhost, ports: [80, 443]}\n\
+                     plain: {host: other, ports: []}";
+        let doc = TeaLeaf::parse(input).unwrap();
+        assert_eq!(doc.binding("config"), Some("server_config"));
+        assert_eq!(doc.binding("plain"), None);
+        assert_eq!(doc.get_path("config.ports[1]"), Some(&Value::Int(443)));
+
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("config: @as server_config {host: localhost"), "{}", text);
+        assert!(text.contains("plain: {host: other"), "{}", text);
+        let reparsed = TeaLeaf::parse(&text).unwrap();
+        assert_eq!(reparsed.data, doc.data);
+        assert_eq!(reparsed.binding("config"), Some("server_config"));
+
+        assert!(matches!(TeaLeaf::parse("config: @as nope {a: 1}"), Err(Error::UnknownStruct(_))));
+        assert!(TeaLeaf::parse("@struct s (a: int)\nconfig: @as s [1]").is_err());
+    }
+
+    #[test]
+    fn test_row_objects() {
+        let input = "@struct server (host: string, port: int, tls: bool, note: string?)\n\
+                     config: @row server (localhost, 8080, true, ~)\n\
+                     other: @row server (\"a b\", 1, false, hi)\n";
+        let doc = TeaLeaf::parse(input).unwrap();
+        assert_eq!(doc.binding("config"), Some("server"));
+        let keys: Vec<&str> = doc.get("config").unwrap().as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(keys, ["host", "port", "tls"]);
+        assert_eq!(doc.get_path("other.note").and_then(Value::as_str), Some("hi"));
+
+        // Opt-in: @as by default, @row when asked
+        assert!(doc.to_tl_with_schemas().contains("config: @as server {"));
+        let text = doc.to_tl_with_options(&FormatOptions::default().with_row_objects());
+        assert!(text.contains("config: @row server (localhost, 8080, true, ~)\n"), "{}", text);
+        let compact = doc.to_tl_with_options(&FormatOptions::compact().with_row_objects());
+        assert!(compact.contains("config:@row server(localhost,8080,true,~)\n"), "{}", compact);
+        for text in [text, compact] {
+            let reparsed = TeaLeaf::parse(&text).unwrap();
+            assert_eq!(reparsed.data, doc.data);
+            assert_eq!(reparsed.binding("other"), Some("server"));
+        }
+
+        assert!(matches!(TeaLeaf::parse("x: @row nope (1)"), Err(Error::UnknownStruct(_))));
+        assert!(matches!(TeaLeaf::parse("@struct s (a: int)\nx: @row s (1, 2)"), Err(Error::TooManyValues { .. })));
+        assert!(TeaLeaf::parse("@struct s (a: int)\nx: @row s {a: 1}").is_err());
+    }
+
+    #[test]
+    fn test_strict_schemas() {
+        let mut doc = TeaLeaf::parse(r#"
+            @struct user (id: int, name: string)
+            @struct team (lead: user, members: []user)
+            staff: @table user [(1, alice)]
+            teams: @table team [((1, alice), [(2, bob)])]
+        "#).unwrap();
+        // Unbound values that only match by key name or by their fields
+        let users = doc.get("staff").unwrap().clone();
+        doc.data.insert("users".into(), users.clone());
+        doc.data.insert("people".into(), users);
+
+        let loose = doc.to_tl_with_schemas();
+        assert!(loose.contains("users: @table user"), "{}", loose);
+        assert!(loose.contains("people: @table user"), "{}", loose);
+
+        let text = doc.to_tl_with_options(&FormatOptions::default().with_strict_schemas());
+        assert!(text.contains("staff: @table user [\n  (1, alice)\n]"), "{}", text);
+        assert!(text.contains("  ((1, alice), [\n    (2, bob)\n  ])"), "{}", text);
+        assert!(text.contains("users: [{id: 1, name: alice}]"), "{}", text);
+        assert!(text.contains("people: [{id: 1, name: alice}]"), "{}", text);
+        assert_eq!(TeaLeaf::parse(&text).unwrap().data, doc.data);
+    }
+
+    #[test]
+    fn test_binding_overrides_key_name() {
+        // Singularizing "items" names the wrong schema; the binding wins
+        let input = "@struct item (sku: string)\n@struct order (id: int, sku: string)\n\
+                     items: @table order [(1, a), (2, b)]";
+        let mut doc = TeaLeaf::parse(input).unwrap();
+        assert_eq!(doc.binding("items"), Some("order"));
+        assert!(doc.to_tl_with_schemas().contains("items: @table order ["));
+
+        assert!(doc.rename_key("items", "lines"));
+        assert_eq!(doc.binding("lines"), Some("order"));
+        assert_eq!(doc.binding("items"), None);
+        assert!(doc.rename_schema("order", "sales_order"));
+        assert_eq!(doc.binding("lines"), Some("sales_order"));
+        assert!(doc.to_tl_with_schemas().contains("lines: @table sales_order ["));
+
+        assert!(!doc.bind("lines", "missing"));
+        assert!(!doc.bind("missing", "item"));
+        assert!(doc.bind("lines", "item"));
+        assert_eq!(doc.binding("lines"), Some("item"));
+    }
+
+    #[test]
+    fn test_rename_field_updates_bound_objects() {
+        let input = "@struct point (x: int, y: int)\n@struct shape (name: string, at: point, path: []point)\n\
+                     shapes: @table shape [(sq, (1, 2), [(3, 4), (5, 6)])]\n\
+                     origin: {x: 0, y: 0}\n\
+                     other: {x: 1, z: 2}";
+        let mut doc = TeaLeaf::parse(input).unwrap();
+        assert!(doc.rename_field("point", "x", "left"));
+        assert!(!doc.rename_field("point", "x", "left"));
+        assert!(!doc.rename_field("point", "y", "left"));
+        assert!(!doc.rename_field("missing", "y", "top"));
+
+        assert_eq!(doc.schema("point").unwrap().fields[0].name, "left");
+        assert_eq!(doc.get_path("shapes[0].at.left"), Some(&Value::Int(1)));
+        assert_eq!(doc.get_path("shapes[0].path[1].left"), Some(&Value::Int(5)));
+        assert_eq!(doc.get_path("origin.left"), Some(&Value::Int(0)));
+        // Not bound to `point`: has a field the schema lacks
+        assert_eq!(doc.get_path("other.x"), Some(&Value::Int(1)));
+
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("@table shape"), "{}", text);
+        let reparsed = TeaLeaf::parse(&text).unwrap();
+        assert_eq!(reparsed.get_path("shapes[0].path[0].left"), Some(&Value::Int(3)));
+    }
+
     #[test]
     fn test_loads_convenience() {
         let data = loads("name: alice\nage: 30").unwrap();
@@ -3450,10 +5566,11 @@ mod tests {
     // Coverage: InferredType::merge() branches
     // =========================================================================
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_inferred_type_merge_int_float() {
         let t = infer_type(&Value::Int(42));
-        let f = infer_type(&Value::Float(3.14));
+        let f = infer_type(&Value::Float(2.25));
         let merged = t.merge(&f);
         assert_eq!(merged, InferredType::Float);
 
@@ -3462,6 +5579,7 @@ mod tests {
         assert_eq!(merged, InferredType::Float);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_inferred_type_merge_null_with_type() {
         let n = InferredType::Null;
@@ -3474,6 +5592,7 @@ mod tests {
         assert_eq!(merged, InferredType::String);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_inferred_type_merge_arrays() {
         let a1 = InferredType::Array(Box::new(InferredType::Int));
@@ -3482,6 +5601,7 @@ mod tests {
         assert_eq!(merged, InferredType::Array(Box::new(InferredType::Float)));
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_inferred_type_merge_objects_same_fields() {
         let o1 = InferredType::Object(vec![
@@ -3502,6 +5622,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_inferred_type_merge_objects_different_fields() {
         let o1 = InferredType::Object(vec![
@@ -3514,6 +5635,7 @@ mod tests {
         assert_eq!(merged, InferredType::Mixed);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_inferred_type_merge_incompatible() {
         let s = InferredType::String;
@@ -3522,6 +5644,7 @@ mod tests {
         assert_eq!(merged, InferredType::Mixed);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_inferred_type_to_field_type() {
         let schemas = IndexMap::new();
@@ -3545,6 +5668,7 @@ mod tests {
         assert_eq!(obj_type.to_field_type(&schemas).base, "any");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_inferred_type_to_field_type_with_matching_schema() {
         let mut schemas = IndexMap::new();
@@ -3561,6 +5685,7 @@ mod tests {
         assert_eq!(ft.base, "point");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_infer_type_special_values() {
         // Bytes, Ref, Tagged, Timestamp, Map all become Mixed
@@ -3581,6 +5706,7 @@ mod tests {
         assert_eq!(infer_type(&Value::UInt(42)), InferredType::Int);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_json_with_schemas_empty_nested_object_roundtrip() {
         // Regression: fuzzer found that [{"n":{}}] crashes because the inferrer
@@ -3600,7 +5726,7 @@ mod tests {
     fn test_to_tl_with_schemas_no_schemas() {
         let mut data = IndexMap::new();
         data.insert("name".to_string(), Value::String("alice".to_string()));
-        let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false };
+        let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: false, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let output = doc.to_tl_with_schemas();
         assert!(output.contains("name: alice"), "Should use dumps() format");
@@ -3611,7 +5737,7 @@ mod tests {
     fn test_to_tl_with_schemas_root_array() {
         let mut data = IndexMap::new();
         data.insert("root".to_string(), Value::Array(vec![Value::Int(1), Value::Int(2)]));
-        let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: true };
+        let doc = TeaLeaf { data, schemas: IndexMap::new(), unions: IndexMap::new(), is_root_array: true, is_root_primitive: false, crlf: false, bindings: IndexMap::new() };
 
         let output = doc.to_tl_with_schemas();
         assert!(output.starts_with("@root-array"), "Should have root-array directive");
@@ -3795,18 +5921,21 @@ mod tests {
     // Coverage: array_matches_schema()
     // =========================================================================
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_array_matches_schema_empty() {
         let schema = Schema::new("test");
         assert!(!array_matches_schema(&[], &schema));
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_array_matches_schema_non_object() {
         let schema = Schema::new("test");
         assert!(!array_matches_schema(&[Value::Int(1)], &schema));
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_array_matches_schema_matching() {
         let mut schema = Schema::new("user");
@@ -3864,6 +5993,23 @@ mod tests {
         assert_eq!(result, vec!["a", "b"]);
     }
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_dto_conversion() {
+        let items: Vec<String> = (0..10_000).map(|i| format!("item{}", i)).collect();
+        let doc = TeaLeaf::from_dto_array_parallel("items", &items);
+        assert_eq!(doc.get("items"), TeaLeaf::from_dto_array("items", &items).get("items"));
+
+        let back: Vec<String> = doc.to_dto_vec_parallel("items").unwrap();
+        assert_eq!(back, items);
+
+        let mut data = IndexMap::new();
+        data.insert("mixed".to_string(), Value::Array(vec![Value::String("a".into()), Value::Int(1)]));
+        let doc = TeaLeaf::new(IndexMap::new(), data);
+        assert!(doc.to_dto_vec_parallel::<String>("mixed").is_err());
+        assert!(doc.to_dto_vec_parallel::<String>("missing").is_err());
+    }
+
     #[test]
     fn test_to_dto_vec_not_array() {
         let mut data = IndexMap::new();
@@ -3885,6 +6031,7 @@ mod tests {
     // =========================================================================
 
     #[test]
+    #[allow(deprecated)]
     fn test_set_root_array() {
         let mut doc = TeaLeaf::new(IndexMap::new(), IndexMap::new());
         assert!(!doc.is_root_array);
@@ -3892,6 +6039,7 @@ mod tests {
         assert!(doc.is_root_array);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inferrer_non_uniform_array() {
         // Array with different object structures should not create a schema
@@ -3900,6 +6048,7 @@ mod tests {
         assert!(doc.schema("item").is_none(), "Non-uniform array should not produce schema");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inferrer_mixed_types_in_array() {
         // Array with non-objects
@@ -3908,6 +6057,7 @@ mod tests {
         assert!(doc.schema("item").is_none(), "Non-object array should not produce schema");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inferrer_empty_array() {
         let json = r#"{"items": []}"#;
@@ -3915,6 +6065,7 @@ mod tests {
         assert!(doc.schema("item").is_none(), "Empty array should not produce schema");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inferrer_duplicate_schema_name() {
         // Two arrays that would produce the same schema name
@@ -3927,6 +6078,7 @@ mod tests {
         assert!(doc.schema("item").is_some());
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inferrer_int_float_merge() {
         // Field that has int in one record and float in another
@@ -3937,6 +6089,7 @@ mod tests {
         assert_eq!(x_field.field_type.base, "float", "Int+Float merge should produce float");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_with_root_array() {
         let json = r#"[{"id": 1, "name": "Alice"}, {"id": 2, "name": "Bob"}]"#;
@@ -4009,6 +6162,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_to_json_root_array() {
         let mut data = IndexMap::new();
         data.insert("root".to_string(), Value::Array(vec![Value::Int(1), Value::Int(2)]));
@@ -4022,6 +6176,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_to_json_compact_root_array() {
         let mut data = IndexMap::new();
         data.insert("root".to_string(), Value::Array(vec![Value::Int(1)]));
@@ -4032,12 +6187,14 @@ mod tests {
         assert_eq!(json, "[1]");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_infer_type_bool_value() {
         let it = infer_type(&Value::Bool(true));
         assert!(matches!(it, InferredType::Bool));
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_nested_object_fields() {
         // JSON with nested objects inside array items
@@ -4050,6 +6207,7 @@ mod tests {
         assert!(doc.schema("record").is_some(), "Should infer record schema");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_not_all_objects_returns_early() {
         // Array where second element is not an object
@@ -4108,6 +6266,9 @@ mod tests {
                 d
             },
             is_root_array: false,
+            is_root_primitive: false,
+            crlf: false,
+            bindings: IndexMap::new(),
         };
         let result = doc.find_schema_for_value(doc.data.get("items").unwrap(), "items");
         assert!(result.is_some(), "Should match schema when nullable field is missing");
@@ -4137,6 +6298,9 @@ mod tests {
                 d
             },
             is_root_array: false,
+            is_root_primitive: false,
+            crlf: false,
+            bindings: IndexMap::new(),
         };
         let result = doc.find_schema_for_value(doc.data.get("points").unwrap(), "points");
         assert!(result.is_none(), "Should NOT match schema when extra keys are present");
@@ -4158,6 +6322,9 @@ mod tests {
                 d
             },
             is_root_array: false,
+            is_root_primitive: false,
+            crlf: false,
+            bindings: IndexMap::new(),
         };
         let result = doc.find_schema_for_value(doc.data.get("empty").unwrap(), "empty");
         assert!(result.is_none(), "Empty array should return None when no schema name matches");
@@ -4179,6 +6346,9 @@ mod tests {
                 d
             },
             is_root_array: false,
+            is_root_primitive: false,
+            crlf: false,
+            bindings: IndexMap::new(),
         };
         let result = doc.find_schema_for_value(doc.data.get("items").unwrap(), "items");
         assert!(result.is_some(), "Empty array should match schema by singularized key name");
@@ -4223,6 +6393,183 @@ mod tests {
         assert_eq!(formatted, "1970-01-01T00:00:01.123Z");
     }
 
+    #[test]
+    fn test_timestamp_nanos_formatting() {
+        let ns = 1_705_314_600_123_456_789;
+        assert_eq!(format_timestamp_nanos(ns, 0, TimePrecision::Nanos), "2024-01-15T10:30:00.123456789Z");
+        assert_eq!(format_timestamp_nanos(ns, 0, TimePrecision::Micros), "2024-01-15T10:30:00.123456Z");
+        assert_eq!(format_timestamp_nanos(ns, 60, TimePrecision::Millis), "2024-01-15T11:30:00.123+01:00");
+        assert_eq!(format_timestamp_nanos(ns, 0, TimePrecision::Seconds), "2024-01-15T10:30:00Z");
+        assert_eq!(format_timestamp_nanos(-1, 0, TimePrecision::Nanos), "1969-12-31T23:59:59.999999999Z");
+    }
+
+    #[test]
+    fn test_timestamp_precision_roundtrip() {
+        let doc = TeaLeaf::parse("@struct span (name: string, start: timestamp_ns)\n\
+            spans: @table span [(a, 2024-01-15T10:30:00.000001Z), (b, 2024-01-15T10:30:00.123456789Z)]\n\
+            loose: 2024-01-15T10:30:00.250000Z").unwrap();
+        let start = doc.get_path("spans[1].start").unwrap();
+        assert_eq!(start, &Value::TimestampNanos(1_705_314_600_123_456_789, 0, TimePrecision::Nanos));
+        assert_eq!(start.as_timestamp(), Some((1_705_314_600_123, 0)));
+
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("2024-01-15T10:30:00.000001Z"), "{}", text);
+        assert!(text.contains("2024-01-15T10:30:00.250000Z"), "{}", text);
+        assert_eq!(TeaLeaf::parse(&text).unwrap().data, doc.data);
+
+        let json = doc.to_json().unwrap();
+        assert!(json.contains("\"2024-01-15T10:30:00.123456789Z\""), "{}", json);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spans.tlbx");
+        doc.compile(&path, false).unwrap();
+        let back = TeaLeaf::from_reader(&Reader::open(&path).unwrap()).unwrap();
+        assert_eq!(back.data, doc.data);
+        assert_eq!(back.schema("span").unwrap().fields[1].field_type.base, "timestamp_ns");
+
+        // 2.x readers do not know the type code, so 2.x files may not use it
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[4..8].copy_from_slice(&[2, 0, 1, 0]);
+        let err = Reader::from_bytes(bytes).err().unwrap();
+        assert!(err.to_string().contains("schema 'span' field 'start' holds nanosecond timestamps, which version 2.1"), "{}", err);
+        TeaLeaf::parse("loose: 2024-01-15T10:30:00.250000Z").unwrap().compile(&path, false).unwrap();
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[4..8].copy_from_slice(&[2, 0, 1, 0]);
+        let err = Reader::from_bytes(bytes).err().unwrap();
+        assert!(err.to_string().contains("section 'loose' holds nanosecond timestamps"), "{}", err);
+    }
+
+    #[test]
+    fn test_mixed_line_endings() {
+        let input = "# config\r\n@struct user (id: int, name: string)\r\nusers: @table user [\r\n  (1, \"a\r\nb\"),\n  (2, bob),\r\n]\r\n\
+            note: \"\"\"\r\n  first\r\n  second\n  \"\"\"\r\nport: 8080 # trailing\r\n";
+        let doc = TeaLeaf::parse(input).unwrap();
+        assert_eq!(doc.data, TeaLeaf::parse(&input.replace("\r\n", "\n")).unwrap().data);
+        assert_eq!(doc.get_path("users[0].name").and_then(Value::as_str), Some("a\nb"));
+        assert_eq!(doc.get("note").and_then(Value::as_str), Some("first\nsecond"));
+        assert_eq!(doc.get("port").and_then(Value::as_int), Some(8080));
+
+        let lf = doc.to_tl_with_schemas();
+        assert!(!lf.contains('\r'), "{:?}", lf);
+        let crlf = doc.to_tl_with_options(&FormatOptions::default().with_line_ending(LineEnding::CrLf));
+        assert_eq!(crlf, lf.replace('\n', "\r\n"));
+        assert_eq!(doc.estimated_text_size(&FormatOptions::default().with_line_ending(LineEnding::CrLf)), crlf.len());
+        assert_eq!(TeaLeaf::parse(&crlf).unwrap().data, doc.data);
+
+        // Preserve follows the line ending most of the source used
+        let preserve = FormatOptions::default().with_line_ending(LineEnding::Preserve);
+        assert_eq!(doc.to_tl_with_options(&preserve), crlf);
+        assert_eq!(TeaLeaf::parse(&lf).unwrap().to_tl_with_options(&preserve), lf);
+        let dumped = dumps_with_options(&doc.data, &FormatOptions::compact().with_line_ending(LineEnding::CrLf));
+        assert!(dumped.ends_with("port:8080\r\n"), "{:?}", dumped);
+    }
+
+    #[test]
+    fn test_size_and_duration_fields() {
+        let doc = TeaLeaf::parse("@struct cache (name: string, max: bytesize, ttl: duration)\n\
+            caches: @table cache [(hot, 64MiB, 5m), (cold, 1.5GB, 1d), (off, 0B, 0)]\n\
+            limit: 512KiB").unwrap();
+        assert_eq!(doc.get_path("caches[0].max"), Some(&Value::Int(64 << 20)));
+        assert_eq!(doc.get_path("caches[1].max"), Some(&Value::Int(1_500_000_000)));
+        assert_eq!(doc.get_path("caches[1].ttl"), Some(&Value::Int(86_400_000)));
+        assert_eq!(doc.get("limit"), Some(&Value::Int(512 << 10)));
+
+        // Declared fields get their unit back; untyped integers stay plain
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("(hot, 64MiB, 5m)"), "{}", text);
+        assert!(text.contains("(cold, 1500MB, 1d)"), "{}", text);
+        assert!(text.contains("(off, 0B, 0ms)"), "{}", text);
+        assert!(text.contains("limit: 524288"), "{}", text);
+        assert_eq!(TeaLeaf::parse(&text).unwrap().data, doc.data);
+
+        let err = TeaLeaf::parse("@struct cache (max: bytesize)\nc: @table cache [(30s)]").err().unwrap();
+        assert!(err.to_string().contains("bytesize field expects a size, got a duration"), "{}", err);
+    }
+
+    #[test]
+    fn test_schemas_to_tl_round_trip() {
+        let input = "@union shape {\n  circle (r: float),\n  dot ()\n}\n\
+                     @struct point (x: int, \"y pos\": int?)\n@struct drawing (at: []point, shape: shape)\n\n\
+                     drawings: @table drawing [([(1, 2)], :dot ())]\n";
+        let doc = TeaLeaf::parse(input).unwrap();
+        let schemas = doc.schemas_to_tl();
+        assert!(input.starts_with(&schemas), "{}", schemas);
+        assert!(!schemas.contains("drawings"));
+
+        let reparsed = TeaLeaf::parse_schemas_only(&schemas).unwrap();
+        assert_eq!(reparsed.schemas, doc.schemas);
+        assert_eq!(reparsed.unions, doc.unions);
+        assert!(reparsed.data.is_empty());
+        assert_eq!(reparsed.schemas_to_tl(), schemas);
+
+        let err = TeaLeaf::parse_schemas_only(input).err().unwrap();
+        assert_eq!(err.to_string(), "Parse error: Schema file contains data: 'drawings'");
+        assert_eq!(TeaLeaf::parse("a: 1").unwrap().schemas_to_tl(), "");
+    }
+
+    #[test]
+    fn test_sorted_definition_indices() {
+        let input = "@union shape {\n  dot ()\n}\n@union color {\n  red ()\n}\n\
+                     @struct point (x: int, c: color)\n@struct drawing (at: point, shape: shape)\n\
+                     d: @table drawing [((1, :red ()), :dot ())]\n";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("d.tlbx");
+        let mut doc = TeaLeaf::parse(input).unwrap();
+
+        let indices = |doc: &TeaLeaf| {
+            doc.compile(&path, false).unwrap();
+            let reader = Reader::open(&path).unwrap();
+            assert_eq!(TeaLeaf::from_reader(&reader).unwrap().data, doc.data);
+            ["point", "drawing", "shape", "color"].map(|n| reader.schema_index(n).or(reader.union_index(n)))
+        };
+        assert_eq!(indices(&doc), [Some(0), Some(1), Some(0), Some(1)]);
+        doc.sort_definitions();
+        assert_eq!(indices(&doc), [Some(1), Some(0), Some(1), Some(0)]);
+        assert!(doc.to_tl_with_schemas().starts_with("@union color"));
+
+        let mut writer = Writer::new();
+        writer.add_schema(doc.schemas["point"].clone());
+        writer.add_schema(doc.schemas["drawing"].clone());
+        writer.sort_definitions();
+        assert_eq!(writer.add_schema(doc.schemas["point"].clone()), 1);
+    }
+
+    #[cfg(feature = "infer")]
+    #[test]
+    fn test_id_keyed_objects_become_maps() {
+        let json = r#"{
+            "users": {
+                "17": {"name": "alice", "roles": [{"id": 1, "label": "admin"}]},
+                "42": {"name": "bob", "age": 30, "roles": []}
+            },
+            "sessions": {"a1f3-09": {"user": 17}, "b7c2-11": {"user": 42}},
+            "settings": {"theme": {"dark": true}, "layout": {"wide": false}}
+        }"#;
+        let doc = TeaLeaf::from_json_with_schemas_and_maps(json).unwrap();
+        let users = doc.get("users").and_then(|v| v.as_map()).unwrap();
+        assert_eq!((&users[0].0, &users[1].0), (&Value::Int(17), &Value::Int(42)));
+        assert_eq!(doc.get("sessions").and_then(|v| v.as_map()).unwrap()[0].0, Value::String("a1f3-09".into()));
+        // Keys without digits are fields, not IDs
+        assert!(doc.get("settings").and_then(|v| v.as_object()).is_some());
+        let fields: Vec<String> = doc.schemas.get("user").unwrap().fields.iter()
+            .map(|f| format!("{}: {}", f.name, f.field_type)).collect();
+        assert_eq!(fields, ["name: string", "age: int?", "roles: []role"]);
+
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("users: @map user {\n  17: (alice, ~, [\n    (1, admin)\n  ]),\n  42: (bob, 30, [])\n}"), "{}", text);
+        assert!(text.contains("sessions: @map session {\n  a1f3-09: (17),"), "{}", text);
+        assert_eq!(TeaLeaf::parse(&text).unwrap().data, doc.data);
+
+        let inline = doc.to_tl_with_options(&FormatOptions::compact().with_inline_tables(2));
+        assert!(inline.contains("users:@map user{17:(alice,~,[(1,admin)]),42:(bob,30,[])}"), "{}", inline);
+        assert_eq!(TeaLeaf::parse(&inline).unwrap().data, doc.data);
+
+        // Off by default
+        let plain = TeaLeaf::from_json_with_schemas(json).unwrap();
+        assert!(plain.get("users").and_then(|v| v.as_object()).is_some());
+        assert_eq!(SchemaInferrer::new().rewrite_maps(&mut plain.data.clone()), 0);
+    }
+
     #[test]
     fn test_negative_timestamp_json_export() {
         let mut data = IndexMap::new();
@@ -4442,6 +6789,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_fuzz_crash_48767e10_json_schemas_bare_dash_roundtrip() {
         // Regression: fuzz_json_schemas crash-48767e10b4ec71542bfbee2bc358b1e21831a259
@@ -4464,6 +6812,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_fuzz_crash_820dac71_empty_key_roundtrip() {
         // Regression: fuzz_json_schemas crash-820dac71c95d324067cd88de5f24897c65ace57a
@@ -4485,6 +6834,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_fuzz_crash_66a8d851_root_array_empty_key() {
         // Regression: fuzz_json_schemas crash-66a8d85176f76ed68ada9f9526abe4efd8352f27
@@ -4497,6 +6847,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_fuzz_crash_847a9194_uint_roundtrip() {
         // Regression: fuzz_json_schemas crash-847a919462bb567fab268023a5a29d04e92db779
@@ -4581,6 +6932,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_json_any_type_compile_roundtrip() {
         // Regression: from_json_with_schemas infers "any" for fields whose nested objects
@@ -4602,6 +6954,27 @@ mod tests {
         assert_eq!(reader.keys().len(), doc.data.len());
     }
 
+    #[cfg(feature = "infer")]
+    #[test]
+    fn test_json_any_field_values_roundtrip() {
+        use tempfile::NamedTempFile;
+
+        let json = r#"[
+            {"name": "alice", "meta": {"x": 1}},
+            {"name": "bob",   "meta": 7},
+            {"name": "carol", "meta": ["a", 2]}
+        ]"#;
+        let doc = TeaLeaf::from_json_with_schemas(json).unwrap();
+        let temp = NamedTempFile::new().unwrap();
+        doc.compile(temp.path(), false).unwrap();
+
+        let reader = Reader::open(temp.path()).unwrap();
+        assert_eq!(reader.any_fields().len(), 1);
+        assert_eq!(reader.any_fields()[0].1, "meta");
+        assert_eq!(&reader.get("root").unwrap(), doc.get("root").unwrap());
+    }
+
+    #[cfg(feature = "infer")]
     #[test]
     fn json_any_array_binary_roundtrip() {
         // Regression: []any fields (from JSON inference of heterogeneous arrays inside
@@ -4654,6 +7027,7 @@ mod tests {
         assert_eq!(data1[2].as_float(), Some(29.99));
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn retail_orders_json_binary_roundtrip() {
         // End-to-end: retail_orders.json → infer schemas → compile → read → JSON
@@ -4695,6 +7069,7 @@ mod tests {
         assert_eq!(items.len(), 3, "first order should have 3 items");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn fuzz_repro_json_schema_bool_field_name() {
         // Fuzz crash: field named "bool" conflicts with type keyword
@@ -4712,6 +7087,7 @@ mod tests {
 
     /// Helper: verify that a JSON field named after a built-in type correctly
     /// round-trips through TL text when schema inference is used.
+    #[cfg(feature = "infer")]
     fn assert_builtin_name_text_roundtrip(type_name: &str, inner_json: &str) {
         let input = format!(r#"[{{"{type_name}":{inner_json}}}]"#);
         let tl = TeaLeaf::from_json_with_schemas(&input)
@@ -4738,77 +7114,92 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_bool() {
         assert_builtin_name_text_roundtrip("bool", r#"{"x":1}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_int() {
         // Inner value is a string so field type "string" doesn't collide with schema "int"
         assert_builtin_name_text_roundtrip("int", r#"{"x":"hello"}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_int8() {
         assert_builtin_name_text_roundtrip("int8", r#"{"x":"hello"}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_int16() {
         assert_builtin_name_text_roundtrip("int16", r#"{"x":"hello"}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_int32() {
         assert_builtin_name_text_roundtrip("int32", r#"{"x":"hello"}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_int64() {
         assert_builtin_name_text_roundtrip("int64", r#"{"x":"hello"}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_uint() {
         assert_builtin_name_text_roundtrip("uint", r#"{"x":"hello"}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_uint8() {
         assert_builtin_name_text_roundtrip("uint8", r#"{"x":"hello"}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_uint16() {
         assert_builtin_name_text_roundtrip("uint16", r#"{"x":"hello"}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_uint32() {
         assert_builtin_name_text_roundtrip("uint32", r#"{"x":"hello"}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_uint64() {
         assert_builtin_name_text_roundtrip("uint64", r#"{"x":"hello"}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_float() {
         assert_builtin_name_text_roundtrip("float", r#"{"x":1}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_float32() {
         assert_builtin_name_text_roundtrip("float32", r#"{"x":1}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_float64() {
         assert_builtin_name_text_roundtrip("float64", r#"{"x":1}"#);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_string() {
         assert_builtin_name_text_roundtrip("string", r#"{"x":1}"#);
@@ -4818,6 +7209,7 @@ mod tests {
     // which is NOT a built-in type. The direct TL-parsing test below covers "bytes" as a
     // schema name.
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_timestamp() {
         assert_builtin_name_text_roundtrip("timestamp", r#"{"x":1}"#);
@@ -4868,6 +7260,7 @@ mod tests {
     /// Self-referencing case: @struct int (x: int) where the inner field type
     /// matches the schema name. The LParen guard ensures `x: int` resolves to
     /// primitive int (next token is a literal, not `(`).
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_builtin_self_referencing() {
         // JSON: [{"int": {"x": 1}}] — creates @struct int (x: int)
@@ -4960,6 +7353,7 @@ root: @table root [
     }
 
     /// Multiple built-in-named schemas in the same document
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_shadows_multiple_builtins() {
         let input = r#"[{"bool":{"a":1},"int":{"b":"hello"},"float":{"c":true}}]"#;
@@ -4983,6 +7377,7 @@ root: @table root [
 
     /// Fuzz crash: singularize("s") → "" (empty string), producing invalid
     /// @struct definitions with missing names.
+    #[cfg(feature = "infer")]
     #[test]
     fn fuzz_repro_singularize_single_char_s() {
         let input = r#"[{"s":{"b":1}}]"#;
@@ -5016,6 +7411,7 @@ root: @table root [
     }
 
     /// Fuzz crash: field name with dots causes value mismatch on roundtrip
+    #[cfg(feature = "infer")]
     #[test]
     fn fuzz_repro_dots_in_field_name() {
         // Fuzz regression: field "root" inside root-array wrapper both singularize to "root",
@@ -5033,6 +7429,7 @@ root: @table root [
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_name_collision_field_matches_parent() {
         // When an array field name singularizes to the same name as its parent schema,
@@ -5049,6 +7446,7 @@ root: @table root [
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn analyze_node_nesting_stress_test() {
         // Stress test: "node" appears at many nesting levels with different shapes.
@@ -5119,6 +7517,7 @@ root: @table root [
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_collision_recursive_arrays() {
         // "nodes" appears as arrays at two levels with different shapes.
@@ -5154,6 +7553,7 @@ root: @table root [
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_collision_recursive_same_shape() {
         // "nodes" appears at two levels but SAME shape [{id, name}].
@@ -5197,6 +7597,7 @@ root: @table root [
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_collision_three_level_nesting() {
         // "nodes" at 3 levels: L1 and L2 have same shape {name, nodes},
@@ -5252,6 +7653,7 @@ root: @table root [
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn schema_collision_three_level_divergent_leaves() {
         // L1: [{name, nodes}], L2: [{name, nodes}] (same shape),
@@ -5307,6 +7709,7 @@ root: @table root [
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn json_inference_nested_array_inside_object() {
         // JSON inference must discover array schemas inside nested objects.
@@ -5419,6 +7822,7 @@ root: @table root [
         assert!(output.contains(":ok 200"), "Space after :tag must be kept (tag/value would merge), got: {output}");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_compact_struct_definition() {
         let json = r#"{"users": [{"id": 1, "name": "alice"}, {"id": 2, "name": "bob"}]}"#;
@@ -5436,6 +7840,7 @@ root: @table root [
         assert!(!compact.contains(")\n\n"), "no blank line after struct def, got: {compact}");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_compact_is_smaller_than_pretty() {
         let json = r#"{"users": [{"id": 1, "name": "alice"}, {"id": 2, "name": "bob"}]}"#;
@@ -5449,6 +7854,7 @@ root: @table root [
         );
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_compact_roundtrip() {
         // Compact output must re-parse to the same data
@@ -5471,6 +7877,7 @@ root: @table root [
         assert_eq!(v1, v2, "Compact round-trip data mismatch");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_compact_preserves_quoted_strings() {
         // Strings with spaces must keep their quotes and content intact
@@ -5505,6 +7912,9 @@ root: @table root [
             unions: IndexMap::new(),
             data,
             is_root_array: false,
+            is_root_primitive: false,
+            crlf: false,
+            bindings: IndexMap::new(),
         };
         let compact = doc.to_tl_with_schemas_compact();
         assert!(compact.contains("point:{x:1,y:2}"), "got: {compact}");
@@ -5535,6 +7945,7 @@ root: @table root [
         }
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_with_at_prefixed_keys() {
         // JSON-LD style @type keys should trigger schema inference with quoted field names
@@ -5551,6 +7962,7 @@ root: @table root [
         assert!(tl_text.contains("@table"), "Should use @table encoding: {}", tl_text);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_quoted_field_roundtrip() {
         // Full JSON -> TL -> JSON roundtrip with @type keys
@@ -5572,17 +7984,57 @@ root: @table root [
     }
 
     #[test]
-    fn test_schema_inference_skips_when_schema_name_needs_quoting() {
-        // When the inferred schema name itself would need quoting, skip inference
-        let json = r#"{"@items": [{"name": "x"}, {"name": "y"}]}"#;
+    fn test_quoted_schema_names() {
+        let input = r#"
+            @union "acme.shape/v1" { circle (r: float), dot () }
+            @struct "acme/point" (x: int, y: int)
+            @struct "acme/path" (name: string, start: "acme/point", pts: []"acme/point"?, shape: "acme.shape/v1")
+            paths: @table "acme/path" [(a, (0, 0), [(1, 2)], :dot ())]
+            origin: @row "acme/point" (0, 0)
+            home: @as "acme/point" {x: 1, y: 2}
+            by_id: @map "acme/point" {1: (3, 4)}
+        "#;
+        let doc = TeaLeaf::parse(input).unwrap();
+        let path = doc.schema("acme/path").unwrap();
+        assert_eq!(path.fields[1].field_type, FieldType::new("acme/point"));
+        assert_eq!(path.fields[2].field_type, FieldType::new("acme/point").array().nullable());
+        assert_eq!(doc.binding("home"), Some("acme/point"));
+        assert_eq!(doc.get_path("paths[0].pts[0].y"), Some(&Value::Int(2)));
+        assert!(TeaLeaf::parse(r#"@struct "" (a: int)"#).is_err());
+
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains(r#"@struct "acme/path" (name: string, start: "acme/point", pts: []"acme/point"?, shape: "acme.shape/v1")"#), "{}", text);
+        assert!(text.contains(r#"paths: @table "acme/path""#), "{}", text);
+        let reparsed = TeaLeaf::parse(&text).unwrap();
+        assert_eq!(reparsed.data, doc.data);
+        assert_eq!(reparsed.schemas, doc.schemas);
+
+        let bytes = doc.to_writer(&mut Progress::new()).unwrap().to_bytes(false).unwrap();
+        let reader = Reader::from_bytes(bytes).unwrap();
+        assert_eq!(reader.get("paths").unwrap(), doc.data["paths"]);
+        let schemas = TeaLeaf::from_reader(&reader).unwrap().schemas;
+        assert_eq!(schemas["acme/path"].fields[1], path.fields[1]);
+        assert_eq!(schemas["acme/path"].fields[3], path.fields[3]);
+    }
+
+    #[cfg(feature = "infer")]
+    #[test]
+    fn test_schema_inference_quotes_schema_names() {
+        // A schema name that is not a plain identifier is inferred and quoted
+        let json = r#"{"@items": [{"name": "x"}, {"name": "y"}], "acme/orders": [{"sku": "a", "meta": {"k": 1}}]}"#;
         let doc = TeaLeaf::from_json_with_schemas(json).unwrap();
         let tl_text = doc.to_tl_with_schemas();
 
-        // Should NOT have inferred a schema because "@items" -> "@item" needs quoting
-        assert!(!tl_text.contains("@struct"), "Should NOT infer schema when name needs quoting: {}", tl_text);
-        assert!(!tl_text.contains("@table"), "Should NOT use @table when name needs quoting: {}", tl_text);
+        assert!(tl_text.contains("@struct \"@item\" (name: string)"), "{}", tl_text);
+        assert!(tl_text.contains("\"@items\": @table \"@item\""), "{}", tl_text);
+        assert!(tl_text.contains("\"acme/orders\": @table \"acme/order\""), "{}", tl_text);
+        let reparsed = TeaLeaf::parse(&tl_text)
+            .unwrap_or_else(|e| panic!("Failed to re-parse: {e}\nTL:\n{tl_text}"));
+        assert_eq!(reparsed.data, doc.data);
+        assert_eq!(reparsed.schemas, doc.schemas);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_root_array_with_at_keys() {
         // Root-level array with @type keys should also get schema inference
@@ -5605,6 +8057,7 @@ root: @table root [
         assert_eq!(v1, v2, "Root array roundtrip failed");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_dollar_prefixed_keys() {
         // JSON Schema / OpenAPI style $ref, $id, $schema keys
@@ -5628,6 +8081,7 @@ root: @table root [
         assert_eq!(v1, v2, "Roundtrip failed for $-prefixed keys");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_hash_prefixed_keys() {
         // XML-to-JSON style #text, #cdata keys
@@ -5651,6 +8105,7 @@ root: @table root [
         assert_eq!(v1, v2, "Roundtrip failed for #-prefixed keys");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_colon_in_keys() {
         // XML namespace style keys like xsi:type, dc:title
@@ -5674,6 +8129,7 @@ root: @table root [
         assert_eq!(v1, v2, "Roundtrip failed for colon keys");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_odata_keys() {
         // OData style @odata.type, @odata.id keys
@@ -5697,6 +8153,7 @@ root: @table root [
         assert_eq!(v1, v2, "Roundtrip failed for OData keys");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_uri_keys() {
         // RDF/JSON style with full URI keys
@@ -5719,6 +8176,7 @@ root: @table root [
         assert_eq!(v1, v2, "Roundtrip failed for URI keys");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_space_in_keys() {
         // Keys with spaces (common in human-friendly exports, spreadsheet-to-JSON)
@@ -5742,6 +8200,7 @@ root: @table root [
         assert_eq!(v1, v2, "Roundtrip failed for space keys");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_mixed_special_keys() {
         // Mix of regular and special-character keys in one schema
@@ -5770,6 +8229,7 @@ root: @table root [
 
     // ---- Optional/nullable field inference tests ----
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_optional_fields() {
         // Objects with different field sets — 'c' only in first object
@@ -5792,6 +8252,7 @@ root: @table root [
         assert!(tl_text.contains("~"), "Missing field should produce ~: {}", tl_text);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_optional_fields_roundtrip() {
         let json = r#"{"items": [
@@ -5827,6 +8288,7 @@ root: @table root [
         assert!(items_out[1].get("c").is_none(), "Missing nullable field should be dropped (absent)");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_no_common_fields_skipped() {
         // No fields in common — should NOT infer a schema
@@ -5841,6 +8303,7 @@ root: @table root [
         assert!(!tl_text.contains("@table"), "Should NOT use @table: {}", tl_text);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_single_common_field() {
         // Only 'id' is shared — should infer schema with optional fields
@@ -5858,6 +8321,7 @@ root: @table root [
         assert!(tl_text.contains("b: string?"), "'b' should be nullable: {}", tl_text);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_optional_nested_array() {
         // Nested array field present in some objects but not others
@@ -5873,6 +8337,7 @@ root: @table root [
         assert!(tl_text.contains("tags: []string?"), "Optional array field should be nullable: {}", tl_text);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_optional_nested_object() {
         // Nested object field present in some objects but not others
@@ -5900,6 +8365,7 @@ root: @table root [
         assert_eq!(people[2]["address"]["city"], "Portland");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_inference_wa_health_data_pattern() {
         // Pattern matching the WA health dataset: many shared fields, some optional
@@ -5920,6 +8386,7 @@ root: @table root [
         assert!(tl_text.contains("temporal: string?"), "temporal should be nullable: {}", tl_text);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_write_schemas_nullable_field_matching() {
         // Verify that write_value_with_schemas correctly applies @table
@@ -5941,6 +8408,7 @@ root: @table root [
         assert!(compact.contains("@table"), "Compact should also use @table: {}", compact);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_field_ordering_uses_most_complete_object() {
         // The most-complete object (most fields) should determine schema field order
@@ -5956,6 +8424,7 @@ root: @table root [
             "Schema should use most-complete object's field order: {}", tl_text);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_field_ordering_appends_extra_fields() {
         // Fields not in the most-complete object are appended at the end
@@ -5973,6 +8442,7 @@ root: @table root [
             "Schema should use first most-complete object's order with extras appended: {}", tl_text);
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_schema_field_ordering_roundtrip_preserves_order() {
         // Roundtrip should preserve the most-complete object's field ordering
@@ -5997,6 +8467,7 @@ root: @table root [
             "Roundtripped second record should drop absent nullable field");
     }
 
+    #[cfg(feature = "infer")]
     #[test]
     fn test_explicit_null_roundtrip_text_and_binary() {
         // Regression: fuzz_json_schemas crashed on single-object array with explicit null.
@@ -6024,4 +8495,74 @@ root: @table root [
         assert_eq!(v2[0]["key"], serde_json::Value::Null,
             "Binary roundtrip must preserve explicit null");
     }
+
+    #[cfg(feature = "infer")]
+    #[test]
+    fn test_size_estimates_for_inferred_schemas() {
+        use tempfile::NamedTempFile;
+
+        let json = r#"{
+            "users": [
+                {"id": 1, "name": "Ana", "tags": ["a", "b"], "address": {"city": "Lisbon", "zip": null}, "score": 9.5},
+                {"id": 70000, "name": "Bo \"B\"", "tags": [], "address": {"city": "Oslo", "zip": "0150"}, "score": 1e300},
+                {"id": 3, "name": "日本", "tags": ["a"], "address": {"city": "Kyoto"}, "extra": [1, "x", null]}
+            ],
+            "meta": {"count": 3, "big": 18446744073709551615, "mixed": [1, 2.5, true]}
+        }"#;
+        let doc = TeaLeaf::from_json_with_schemas(json).unwrap();
+
+        for opts in [FormatOptions::pretty(), FormatOptions::compact().with_compact_floats()] {
+            assert_eq!(doc.estimated_text_size(&opts), doc.to_tl_with_options(&opts).len());
+        }
+
+        let temp = NamedTempFile::new().unwrap();
+        doc.compile(temp.path(), false).unwrap();
+        let actual = std::fs::metadata(temp.path()).unwrap().len() as usize;
+        assert_eq!(doc.estimated_binary_size(false), actual);
+        doc.compile(temp.path(), true).unwrap();
+        let compressed = std::fs::metadata(temp.path()).unwrap().len() as usize;
+        assert!(doc.estimated_binary_size(true) >= compressed);
+    }
+
+    #[test]
+    fn test_size_estimate_for_union_typed_fields() {
+        let doc = TeaLeaf::parse(r#"
+            @union shape { circle (r: float), dot () }
+            @struct drawing (main: shape, all: []shape)
+            drawings: @table drawing [
+                (:circle (1.5), [:dot (), :square (2), :circle (3.0)]),
+            ]
+        "#).unwrap();
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        doc.compile(temp.path(), false).unwrap();
+        let actual = std::fs::metadata(temp.path()).unwrap().len() as usize;
+        assert_eq!(doc.estimated_binary_size(false), actual);
+
+        let back = TeaLeaf::from_reader(&Reader::open(temp.path()).unwrap()).unwrap();
+        assert_eq!(back.get("drawings"), doc.get("drawings"));
+    }
+
+    #[test]
+    fn test_size_estimate_for_typed_fields_and_translations() {
+        let doc = TeaLeaf::parse(r#"
+            @struct point (x: int8, y: int16?)
+            @struct item (tags: map, at: timestamp_ns, raw: bytes, extra: any, path: []point, label: string)
+            @struct translation (key: string, locale: string, text: string)
+            items: @table item [
+                ({"1": a, "01": b, c: [1, 2]}, 2024-01-15T10:30:00.123456789Z, b"cafe", {k: 1}, [(1, 2), (3, ~)], 7),
+                ({}, 2024-01-15T10:30:00Z, b"", 70000, [], "x"),
+            ]
+            translations: @table translation [
+                (greeting, en, "Hello"),
+                (greeting, en, "Hi"),
+                (farewell, fr, "Au revoir"),
+            ]
+            counts: {"1": 1, big: 18446744073709551615, neg: -40000, list: [1, 2147483648]}
+        "#).unwrap();
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        doc.compile(temp.path(), false).unwrap();
+        let actual = std::fs::metadata(temp.path()).unwrap().len() as usize;
+        assert_eq!(doc.estimated_binary_size(false), actual);
+        assert!(Reader::open(temp.path()).unwrap().lookup_index("translations").unwrap().is_some());
+    }
 }
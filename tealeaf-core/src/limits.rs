@@ -0,0 +1,169 @@
+//! Size and complexity limits for untrusted JSON input.
+//!
+//! [`TeaLeaf::from_json_with_limits`](crate::TeaLeaf::from_json_with_limits)
+//! scans the raw input before building any value tree, so an oversized or
+//! deeply nested document is rejected without allocating for it.
+
+use crate::{Error, Result};
+
+/// Limits applied by the `from_json_*_with_limits` entry points.
+///
+/// Every limit defaults to unlimited; [`JsonLimits::untrusted`] is a starting
+/// point for user uploads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonLimits {
+    /// Maximum input length in bytes.
+    pub max_bytes: usize,
+    /// Maximum nesting depth of arrays and objects (the root container is depth 1).
+    pub max_depth: usize,
+    /// Maximum number of values, counting containers and scalars but not object keys.
+    pub max_nodes: usize,
+}
+
+impl JsonLimits {
+    /// No limits beyond those of the JSON parser itself.
+    pub fn unlimited() -> Self {
+        Self { max_bytes: usize::MAX, max_depth: usize::MAX, max_nodes: usize::MAX }
+    }
+
+    /// 16 MiB, depth 64, one million values.
+    pub fn untrusted() -> Self {
+        Self { max_bytes: 16 * 1024 * 1024, max_depth: 64, max_nodes: 1_000_000 }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    /// Check `json` against these limits, stopping at the first one exceeded.
+    ///
+    /// The scan only tracks nesting and value boundaries; malformed input that
+    /// stays within the limits is left for the JSON parser to report.
+    pub(crate) fn check(&self, json: &str) -> Result<()> {
+        if json.len() > self.max_bytes {
+            return Err(Error::LimitExceeded(format!(
+                "input is {} bytes, limit is {}", json.len(), self.max_bytes
+            )));
+        }
+        if self.max_depth == usize::MAX && self.max_nodes == usize::MAX {
+            return Ok(());
+        }
+
+        let bytes = json.as_bytes();
+        // One entry per open container: true for objects.
+        let mut stack: Vec<bool> = Vec::new();
+        let mut expect_key = false;
+        let mut nodes = 0usize;
+        let mut count_node = |offset: usize| -> Result<()> {
+            nodes += 1;
+            if nodes > self.max_nodes {
+                return Err(Error::LimitExceeded(format!(
+                    "more than {} values (at byte {})", self.max_nodes, offset
+                )));
+            }
+            Ok(())
+        };
+
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'"' => {
+                    if !expect_key {
+                        count_node(i)?;
+                    }
+                    expect_key = false;
+                    i += 1;
+                    while i < bytes.len() {
+                        match bytes[i] {
+                            b'\\' => i += 2,
+                            b'"' => break,
+                            _ => i += 1,
+                        }
+                    }
+                }
+                c @ (b'{' | b'[') => {
+                    count_node(i)?;
+                    stack.push(c == b'{');
+                    if stack.len() > self.max_depth {
+                        return Err(Error::LimitExceeded(format!(
+                            "nesting deeper than {} (at byte {})", self.max_depth, i
+                        )));
+                    }
+                    expect_key = c == b'{';
+                }
+                b'}' | b']' => {
+                    stack.pop();
+                    expect_key = false;
+                }
+                b',' => expect_key = stack.last() == Some(&true),
+                b':' => expect_key = false,
+                b' ' | b'\t' | b'\n' | b'\r' => {}
+                _ => {
+                    // Number or literal: count it and skip to the next delimiter
+                    count_node(i)?;
+                    while i < bytes.len()
+                        && !matches!(bytes[i], b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r')
+                    {
+                        i += 1;
+                    }
+                    continue;
+                }
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Default for JsonLimits {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_limit(result: Result<()>) -> bool {
+        matches!(result, Err(Error::LimitExceeded(_)))
+    }
+
+    #[test]
+    fn test_counts_values_not_keys() {
+        let json = r#"{"a": [1, 2.5, "x"], "b": {"c": null, "d": true}}"#;
+        // root, a, 1, 2.5, "x", b, null, true
+        assert!(JsonLimits::unlimited().with_max_nodes(8).check(json).is_ok());
+        assert!(is_limit(JsonLimits::unlimited().with_max_nodes(7).check(json)));
+    }
+
+    #[test]
+    fn test_depth() {
+        let json = r#"{"a": [[{"b": "]]]]"}]]}"#;
+        assert!(JsonLimits::unlimited().with_max_depth(4).check(json).is_ok());
+        assert!(is_limit(JsonLimits::unlimited().with_max_depth(3).check(json)));
+    }
+
+    #[test]
+    fn test_escaped_quotes_in_strings() {
+        let json = r#"["a\"[[[", "b\\", {"k\"": 1}]"#;
+        assert!(JsonLimits::unlimited().with_max_depth(2).with_max_nodes(5).check(json).is_ok());
+    }
+
+    #[test]
+    fn test_bytes() {
+        assert!(JsonLimits::unlimited().with_max_bytes(4).check("1234").is_ok());
+        assert!(is_limit(JsonLimits::unlimited().with_max_bytes(3).check("1234")));
+    }
+}
@@ -0,0 +1,108 @@
+//! Keyed lookups as objects or maps.
+//!
+//! The same lookup table arrives as an object from JSON (`{"17": {...}}`)
+//! and as a map from TeaLeaf text (`@map {17: {...}}`). The methods here
+//! convert between the two, refusing keys the target cannot hold rather
+//! than silently merging or dropping entries. A schema field of type `map`
+//! keeps such a field a map in the output whichever form the data has.
+
+#[cfg(any(feature = "text", feature = "binary"))]
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::types::value_kind;
+use crate::{Error, ObjectMap, Result, Value};
+
+/// The map key for object key `key`: an `Int` if it is an integer written
+/// the canonical way, else a `String`.
+pub(crate) fn map_key(key: String) -> Value {
+    match key.parse::<i64>() {
+        Ok(i) if i.to_string() == key => Value::Int(i),
+        _ => Value::String(key),
+    }
+}
+
+impl Value {
+    /// This object as a map, with integer keys (`"17"`, not `"017"`)
+    /// becoming `Int` keys and the rest `String` keys, in the same order.
+    ///
+    /// Fails with [`Error::ParseError`] if the value is not an object.
+    pub fn object_to_map(&self) -> Result<Value> {
+        match self {
+            Value::Object(obj) => Ok(Value::Map(obj.iter().map(|(k, v)| (map_key(k.clone()), v.clone())).collect())),
+            other => Err(Error::ParseError(format!("expected an object, got {}", value_kind(other)))),
+        }
+    }
+
+    /// This map as an object, with integer keys written in decimal.
+    ///
+    /// Fails with [`Error::ParseError`] if the value is not a map, a key is
+    /// not a string or integer, or two keys give the same object key, such
+    /// as `1` and `"1"`.
+    pub fn map_to_object(&self) -> Result<Value> {
+        let pairs = match self {
+            Value::Map(pairs) => pairs,
+            other => return Err(Error::ParseError(format!("expected a map, got {}", value_kind(other)))),
+        };
+        let mut obj = ObjectMap::with_capacity(pairs.len());
+        let mut seen = HashMap::with_capacity(pairs.len());
+        for (i, (k, v)) in pairs.iter().enumerate() {
+            let key = match k {
+                Value::String(s) => s.clone(),
+                Value::Int(n) => n.to_string(),
+                Value::UInt(n) => n.to_string(),
+                other => {
+                    return Err(Error::ParseError(format!("map key {} is {}; object keys must be strings or integers", i, value_kind(other))));
+                }
+            };
+            if let Some(first) = seen.insert(key.clone(), i) {
+                return Err(Error::ParseError(format!("map keys {} and {} both become object key '{}'", first, i, key)));
+            }
+            obj.insert(key, v.clone());
+        }
+        Ok(Value::Object(obj))
+    }
+}
+
+/// `value` as a schema field of type `map` holds it: objects become maps,
+/// and anything else stays as it is.
+#[cfg(any(feature = "text", feature = "binary"))]
+pub(crate) fn as_map_field(value: &Value) -> Cow<'_, Value> {
+    match value {
+        Value::Object(_) => value.object_to_map().map_or(Cow::Borrowed(value), Cow::Owned),
+        _ => Cow::Borrowed(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_map_conversion() {
+        let obj = Value::Object(ObjectMap::from_iter([
+            ("17".to_string(), Value::Bool(true)),
+            ("017".to_string(), Value::Bool(false)),
+            ("-3".to_string(), Value::Null),
+            ("sku".to_string(), Value::Int(1)),
+        ]));
+        let map = obj.object_to_map().unwrap();
+        assert_eq!(map, Value::Map(vec![
+            (Value::Int(17), Value::Bool(true)),
+            (Value::String("017".into()), Value::Bool(false)),
+            (Value::Int(-3), Value::Null),
+            (Value::String("sku".into()), Value::Int(1)),
+        ]));
+        assert_eq!(map.map_to_object().unwrap(), obj);
+
+        let uint = Value::Map(vec![(Value::UInt(u64::MAX), Value::Null)]);
+        assert!(uint.map_to_object().unwrap().get(&u64::MAX.to_string()).is_some());
+
+        let dup = Value::Map(vec![(Value::Int(1), Value::Null), (Value::String("1".into()), Value::Null)]);
+        assert!(dup.map_to_object().unwrap_err().to_string().contains("map keys 0 and 1 both become object key '1'"));
+        let bad = Value::Map(vec![(Value::Float(1.5), Value::Null)]);
+        assert!(bad.map_to_object().unwrap_err().to_string().contains("is float"));
+        assert!(Value::Int(1).object_to_map().is_err());
+        assert!(obj.map_to_object().is_err());
+    }
+}
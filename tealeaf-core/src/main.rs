@@ -1,11 +1,12 @@
 //! TeaLeaf CLI
 
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process;
 
 use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell};
-use tealeaf::{FormatOptions, Reader, TeaLeaf};
+use tealeaf::{FloatTolerance, FormatOptions, JsonOptions, LineEnding, Pipeline, Reader, TeaLeaf};
 
 #[derive(Parser)]
 #[command(
@@ -43,6 +44,9 @@ enum Commands {
         /// Write whole-number floats as integers (e.g. 42.0 becomes 42)
         #[arg(long)]
         compact_floats: bool,
+        /// Shorten frequently repeated keys with an @alias directive
+        #[arg(long)]
+        alias_keys: bool,
     },
 
     /// Show file info (auto-detects text/binary format)
@@ -51,10 +55,26 @@ enum Commands {
         input: PathBuf,
     },
 
+    /// Summarize a file's keys, types and lengths (auto-detects text/binary format)
+    Outline {
+        /// Input .tl or .tlbx file
+        input: PathBuf,
+        /// Levels of fields to show below the top-level keys
+        #[arg(short, long, default_value_t = 2)]
+        depth: usize,
+    },
+
     /// Validate a text format (.tl) file
     Validate {
         /// Input .tl file
         input: PathBuf,
+        /// Expected .tl file the input must mean the same as
+        #[arg(long)]
+        against: Option<PathBuf>,
+        /// How close floats must be to match the expected file: exact, f32,
+        /// abs:EPSILON, rel:EPSILON or ulps:N
+        #[arg(long, default_value = "exact", requires = "against")]
+        tolerance: FloatTolerance,
     },
 
     /// Convert TeaLeaf text (.tl) to JSON
@@ -79,6 +99,12 @@ enum Commands {
         /// Write whole-number floats as integers (e.g. 42.0 becomes 42)
         #[arg(long)]
         compact_floats: bool,
+        /// Shorten frequently repeated keys with an @alias directive
+        #[arg(long)]
+        alias_keys: bool,
+        /// Write objects keyed by IDs as @map with one schema for their values
+        #[arg(long)]
+        id_maps: bool,
     },
 
     /// Convert TeaLeaf binary (.tlbx) to JSON
@@ -99,6 +125,21 @@ enum Commands {
         output: PathBuf,
     },
 
+    /// Apply a transformation pipeline to a text file (.tl)
+    Transform {
+        /// Input .tl file
+        input: PathBuf,
+        /// Pipeline .tl file listing the passes under a `passes` key
+        #[arg(short, long)]
+        pipeline: PathBuf,
+        /// Output .tl file
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Omit insignificant whitespace for token-efficient output
+        #[arg(long)]
+        compact: bool,
+    },
+
     /// Generate shell completions
     Completions {
         /// Shell to generate completions for
@@ -121,15 +162,18 @@ fn main() {
 
     let result = match cli.command {
         Commands::Compile { ref input, ref output } => cmd_compile(input, output),
-        Commands::Decompile { ref input, ref output, compact, compact_floats } =>
-            cmd_decompile(input, output, compact, compact_floats),
+        Commands::Decompile { ref input, ref output, compact, compact_floats, alias_keys } =>
+            cmd_decompile(input, output, compact, compact_floats, alias_keys),
         Commands::Info { ref input } => cmd_info(input),
-        Commands::Validate { ref input } => cmd_validate(input),
+        Commands::Outline { ref input, depth } => cmd_outline(input, depth),
+        Commands::Validate { ref input, ref against, tolerance } => cmd_validate(input, against.as_deref(), tolerance),
         Commands::ToJson { ref input, ref output } => cmd_to_json(input, output.as_deref()),
-        Commands::FromJson { ref input, ref output, compact, compact_floats } =>
-            cmd_from_json(input, output, compact, compact_floats),
+        Commands::FromJson { ref input, ref output, compact, compact_floats, alias_keys, id_maps } =>
+            cmd_from_json(input, output, compact, compact_floats, alias_keys, id_maps),
         Commands::TlbxToJson { ref input, ref output } => cmd_tlbx_to_json(input, output.as_deref()),
         Commands::JsonToTlbx { ref input, ref output } => cmd_json_to_tlbx(input, output),
+        Commands::Transform { ref input, ref pipeline, ref output, compact } =>
+            cmd_transform(input, pipeline, output, compact),
         Commands::Completions { shell } => {
             generate(shell, &mut Cli::command(), "tealeaf", &mut std::io::stdout());
             Ok(())
@@ -156,10 +200,11 @@ fn cmd_compile(input: &Path, output: &Path) -> Result<(), Box<dyn std::error::Er
     Ok(())
 }
 
-fn cmd_decompile(input: &Path, output: &Path, compact: bool, compact_floats: bool) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_decompile(input: &Path, output: &Path, compact: bool, compact_floats: bool, alias_keys: bool) -> Result<(), Box<dyn std::error::Error>> {
     let mut flags = Vec::new();
     if compact { flags.push("compact"); }
     if compact_floats { flags.push("compact-floats"); }
+    if alias_keys { flags.push("alias-keys"); }
     let flag_str = if flags.is_empty() { String::new() } else { format!(" ({})", flags.join(", ")) };
     println!("Decompiling {} -> {}{}", input.display(), output.display(), flag_str);
 
@@ -167,6 +212,7 @@ fn cmd_decompile(input: &Path, output: &Path, compact: bool, compact_floats: boo
     let doc = TeaLeaf::from_reader(&reader)?;
     let mut opts = if compact { FormatOptions::compact() } else { FormatOptions::default() };
     if compact_floats { opts = opts.with_compact_floats(); }
+    if alias_keys { opts = opts.with_key_aliases(); }
     let tl_text = doc.to_tl_with_options(&opts);
 
     std::fs::write(output, tl_text)?;
@@ -174,20 +220,18 @@ fn cmd_decompile(input: &Path, output: &Path, compact: bool, compact_floats: boo
     Ok(())
 }
 
+/// Auto-detect format: a binary file starts with the `TLBX` magic.
+fn is_binary_file(input: &Path) -> std::io::Result<bool> {
+    let mut file = std::fs::File::open(input)?;
+    let mut magic = [0u8; 4];
+    use std::io::Read;
+    Ok(file.read_exact(&mut magic).is_ok() && &magic == b"TLBX")
+}
+
 fn cmd_info(input: &Path) -> Result<(), Box<dyn std::error::Error>> {
     let file_size = std::fs::metadata(input)?.len();
 
-    // Auto-detect format: try reading first 4 bytes for magic
-    let is_binary = {
-        let mut file = std::fs::File::open(input)?;
-        let mut magic = [0u8; 4];
-        use std::io::Read;
-        if file.read_exact(&mut magic).is_ok() {
-            &magic == b"TLBX"
-        } else {
-            false
-        }
-    };
+    let is_binary = is_binary_file(input)?;
 
     println!("File: {}", input.display());
     println!("Size: {} bytes", file_size);
@@ -202,6 +246,13 @@ fn cmd_info(input: &Path) -> Result<(), Box<dyn std::error::Error>> {
         for schema in &reader.schemas {
             println!("  {} ({} fields)", schema.name, schema.fields.len());
         }
+        let any_fields = reader.any_fields();
+        if !any_fields.is_empty() {
+            println!("Any-typed fields: {}", any_fields.len());
+            for (schema, field) in any_fields {
+                println!("  {}.{}", schema, field);
+            }
+        }
         if !reader.unions.is_empty() {
             println!("Unions: {}", reader.unions.len());
             for union_def in &reader.unions {
@@ -211,7 +262,10 @@ fn cmd_info(input: &Path) -> Result<(), Box<dyn std::error::Error>> {
         println!();
         println!("Sections: {}", reader.keys().len());
         for key in reader.keys() {
-            println!("  {}", key);
+            match reader.section_fields(key)? {
+                Some(fields) => println!("  {} ({} fields)", key, fields.len()),
+                None => println!("  {}", key),
+            }
         }
     } else {
         println!("Format: Text (.tl)");
@@ -245,9 +299,28 @@ fn cmd_info(input: &Path) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn cmd_validate(input: &Path) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_outline(input: &Path, depth: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let doc = if is_binary_file(input)? {
+        TeaLeaf::from_reader(&Reader::open(input)?)?
+    } else {
+        TeaLeaf::load(input)?
+    };
+    print!("{}", doc.outline(depth));
+    Ok(())
+}
+
+fn cmd_validate(input: &Path, against: Option<&Path>, tolerance: FloatTolerance) -> Result<(), Box<dyn std::error::Error>> {
+    let expected = against.map(TeaLeaf::load).transpose()?;
     match TeaLeaf::load(input) {
         Ok(doc) => {
+            let differences = expected.map_or_else(Vec::new, |expected| expected.semantic_differences(&doc, tolerance));
+            if !differences.is_empty() {
+                println!("\u{2717} Differs from the expected file at {} path(s):", differences.len());
+                for path in differences {
+                    println!("  {}", path);
+                }
+                process::exit(1);
+            }
             println!("\u{2713} Valid");
             println!("  Schemas: {}", doc.schemas.len());
             println!("  Keys: {}", doc.data.len());
@@ -278,16 +351,22 @@ fn cmd_to_json(input: &Path, output: Option<&Path>) -> Result<(), Box<dyn std::e
     Ok(())
 }
 
-fn cmd_from_json(input: &Path, output: &Path, compact: bool, compact_floats: bool) -> Result<(), Box<dyn std::error::Error>> {
+fn cmd_from_json(input: &Path, output: &Path, compact: bool, compact_floats: bool, alias_keys: bool, id_maps: bool) -> Result<(), Box<dyn std::error::Error>> {
     let mut flags = Vec::new();
     if compact { flags.push("compact"); }
     if compact_floats { flags.push("compact-floats"); }
+    if alias_keys { flags.push("alias-keys"); }
+    if id_maps { flags.push("id-maps"); }
     let flag_str = if flags.is_empty() { String::new() } else { format!(" ({})", flags.join(", ")) };
     println!("Converting {} -> {}{}", input.display(), output.display(), flag_str);
 
     let json_content = std::fs::read_to_string(input)?;
 
-    let doc = TeaLeaf::from_json_with_schemas(&json_content)?;
+    let doc = if id_maps {
+        TeaLeaf::from_json_with_schemas_and_maps(&json_content)?
+    } else {
+        TeaLeaf::from_json_with_schemas(&json_content)?
+    };
     if !doc.schemas.is_empty() {
         println!("Inferred {} schema(s):", doc.schemas.len());
         for (name, schema) in &doc.schemas {
@@ -296,6 +375,7 @@ fn cmd_from_json(input: &Path, output: &Path, compact: bool, compact_floats: boo
     }
     let mut opts = if compact { FormatOptions::compact() } else { FormatOptions::default() };
     if compact_floats { opts = opts.with_compact_floats(); }
+    if alias_keys { opts = opts.with_key_aliases(); }
     let tl_text = doc.to_tl_with_options(&opts);
 
     std::fs::write(output, tl_text)?;
@@ -305,17 +385,21 @@ fn cmd_from_json(input: &Path, output: &Path, compact: bool, compact_floats: boo
 }
 
 fn cmd_tlbx_to_json(input: &Path, output: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
-    let reader = Reader::open(input)?;
-    let doc = TeaLeaf::from_reader(&reader)?;
-    let json = doc.to_json()?;
+    // Streamed section by section, so the file is never held as one document
+    let reader = Reader::open_mmap(input)?;
 
     match output {
         Some(path) => {
-            std::fs::write(path, &json)?;
+            let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+            reader.write_json(&mut out, JsonOptions::pretty())?;
+            out.flush()?;
             println!("Converted {} -> {}", input.display(), path.display());
         }
         None => {
-            println!("{}", json);
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            reader.write_json(&mut out, JsonOptions::pretty())?;
+            writeln!(out)?;
+            out.flush()?;
         }
     }
 
@@ -336,3 +420,22 @@ fn cmd_json_to_tlbx(input: &Path, output: &Path) -> Result<(), Box<dyn std::erro
     println!("Done: {} bytes -> {} bytes ({:.1}%)", in_size, out_size, ratio);
     Ok(())
 }
+
+fn cmd_transform(input: &Path, pipeline: &Path, output: &Path, compact: bool) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Transforming {} -> {}", input.display(), output.display());
+
+    let spec = TeaLeaf::load(pipeline)?;
+    let passes = spec.get("passes")
+        .ok_or_else(|| format!("{} has no 'passes' key", pipeline.display()))?;
+    let pipeline = Pipeline::from_value(passes)?;
+
+    let mut doc = TeaLeaf::load(input)?;
+    let report = pipeline.run(&mut doc)?;
+    print!("{}", report);
+
+    let opts = if compact { FormatOptions::compact() } else { FormatOptions::default() };
+    let opts = opts.with_line_ending(LineEnding::Preserve);
+    std::fs::write(output, doc.to_tl_with_options(&opts))?;
+    println!("Done");
+    Ok(())
+}
@@ -0,0 +1,450 @@
+//! Declarative reshaping of documents.
+//!
+//! A [`Mapping`] is a list of [`MapRule`]s that move, copy, drop, split and
+//! merge values by path, run by [`TeaLeaf::apply_mapping`]. Mappings can be
+//! built in Rust or read from a TeaLeaf value, so the translation between
+//! two partners' layouts can live in a file next to the data:
+//!
+//! ```text
+//! rules: [
+//!     {move: customer.name, to: buyer.full_name},
+//!     {split: buyer.full_name, on: " ", to: [buyer.first, buyer.last]},
+//!     {default: currency, value: EUR},
+//!     {each: lines, rules: [{move: qty, to: quantity}, {drop: internal_note}]},
+//! ]
+//! ```
+//!
+//! Paths use [`TeaLeaf::get_path`] syntax (`key.field[N].field`).
+
+use std::fmt;
+
+use indexmap::IndexMap;
+
+use crate::types::value_kind;
+use crate::{Error, Result, TeaLeaf, Value};
+
+/// One step of a [`Mapping`].
+///
+/// Rules whose source path is missing do nothing and are listed in the
+/// [`MappingReport`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapRule {
+    /// Move the value at `from` to `to`, replacing any value there.
+    Move { from: String, to: String },
+    /// Copy the value at `from` to `to`.
+    Copy { from: String, to: String },
+    /// Remove the value at `path`.
+    Drop { path: String },
+    /// Set `path` to `value` if it is missing or null.
+    Default { path: String, value: Value },
+    /// Split the string at `from` on `separator` into the paths `to`, in
+    /// order, and remove it. The last path gets the rest of the string;
+    /// paths beyond the parts are not set.
+    Split { from: String, separator: String, to: Vec<String> },
+    /// Join the values at `from`, as text, with `separator` into `to`, and
+    /// remove them. Missing and null values are left out.
+    Merge { from: Vec<String>, separator: String, to: String },
+    /// Apply `rules` to each object in the array at `path`, with paths
+    /// relative to the object.
+    Each { path: String, rules: Vec<MapRule> },
+}
+
+impl MapRule {
+    /// Read a rule from its declarative form: an object whose first key
+    /// names the rule (`move`, `copy`, `drop`, `default`, `split`, `merge`
+    /// or `each`) and holds its source, with the rule's options beside it.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let obj = match value {
+            Value::Object(obj) => obj,
+            other => return Err(Error::ParseError(format!("expected a rule object, got {}", value_kind(other)))),
+        };
+        let (name, source) = match obj.first() {
+            Some((name, source)) => (name.as_str(), source),
+            None => return Err(Error::ParseError("empty rule".to_string())),
+        };
+        let path = |key: &str, value: Option<&Value>| match value {
+            Some(Value::String(s)) => Ok(s.clone()),
+            Some(other) => Err(Error::ParseError(format!("'{}' of {} must be a path, got {}", key, name, value_kind(other)))),
+            None => Err(Error::MissingField(format!("{}.{}", name, key))),
+        };
+        let paths = |key: &str, value: Option<&Value>| match value {
+            Some(Value::Array(items)) => items.iter().map(|item| path(key, Some(item))).collect::<Result<Vec<_>>>(),
+            other => Ok(vec![path(key, other)?]),
+        };
+        let separator = |key: &str| match obj.get(key) {
+            Some(value) => path(key, Some(value)),
+            None => Ok(" ".to_string()),
+        };
+
+        Ok(match name {
+            "move" => MapRule::Move { from: path(name, Some(source))?, to: path("to", obj.get("to"))? },
+            "copy" => MapRule::Copy { from: path(name, Some(source))?, to: path("to", obj.get("to"))? },
+            "drop" => MapRule::Drop { path: path(name, Some(source))? },
+            "default" => MapRule::Default {
+                path: path(name, Some(source))?,
+                value: obj.get("value").cloned().ok_or_else(|| Error::MissingField("default.value".to_string()))?,
+            },
+            "split" => MapRule::Split {
+                from: path(name, Some(source))?,
+                separator: separator("on")?,
+                to: paths("to", obj.get("to"))?,
+            },
+            "merge" => MapRule::Merge {
+                from: paths(name, Some(source))?,
+                separator: separator("with")?,
+                to: path("to", obj.get("to"))?,
+            },
+            "each" => MapRule::Each {
+                path: path(name, Some(source))?,
+                rules: Mapping::from_value(obj.get("rules").ok_or_else(|| Error::MissingField("each.rules".to_string()))?)?.rules,
+            },
+            other => return Err(Error::ParseError(format!("unknown rule '{}'", other))),
+        })
+    }
+
+    fn apply(&self, data: &mut Value, at: &str, report: &mut MappingReport) -> Result<()> {
+        match self {
+            MapRule::Move { from, to } => match take(data, from)? {
+                Some(value) => {
+                    put(data, to, value)?;
+                    report.changed += 1;
+                }
+                None => report.missing.push(format!("{}{}", at, from)),
+            },
+            MapRule::Copy { from, to } => match get(data, from)?.cloned() {
+                Some(value) => {
+                    put(data, to, value)?;
+                    report.changed += 1;
+                }
+                None => report.missing.push(format!("{}{}", at, from)),
+            },
+            MapRule::Drop { path } => match take(data, path)? {
+                Some(_) => report.changed += 1,
+                None => report.missing.push(format!("{}{}", at, path)),
+            },
+            MapRule::Default { path, value } => {
+                if matches!(get(data, path)?, None | Some(Value::Null)) {
+                    put(data, path, value.clone())?;
+                    report.changed += 1;
+                }
+            }
+            MapRule::Split { from, separator, to } => match get(data, from)? {
+                Some(Value::String(s)) => {
+                    let parts: Vec<Value> = s.splitn(to.len(), separator.as_str()).map(|p| Value::String(p.to_string())).collect();
+                    take(data, from)?;
+                    for (path, part) in to.iter().zip(parts) {
+                        put(data, path, part)?;
+                    }
+                    report.changed += 1;
+                }
+                Some(other) => {
+                    return Err(Error::ParseError(format!("cannot split '{}{}': {} is not a string", at, from, value_kind(other))));
+                }
+                None => report.missing.push(format!("{}{}", at, from)),
+            },
+            MapRule::Merge { from, separator, to } => {
+                let mut parts = Vec::new();
+                for path in from {
+                    match take(data, path)? {
+                        None | Some(Value::Null) => report.missing.push(format!("{}{}", at, path)),
+                        Some(Value::String(s)) => parts.push(s),
+                        Some(Value::Int(i)) => parts.push(i.to_string()),
+                        Some(Value::UInt(u)) => parts.push(u.to_string()),
+                        Some(Value::Float(f)) => parts.push(f.to_string()),
+                        Some(Value::JsonNumber(n)) => parts.push(n),
+                        Some(Value::Bool(b)) => parts.push(b.to_string()),
+                        Some(other) => {
+                            return Err(Error::ParseError(format!("cannot merge '{}{}': {} is not a scalar", at, path, value_kind(&other))));
+                        }
+                    }
+                }
+                if !parts.is_empty() {
+                    put(data, to, Value::String(parts.join(separator)))?;
+                    report.changed += 1;
+                }
+            }
+            MapRule::Each { path, rules } => match get(data, path)? {
+                Some(Value::Array(items)) => {
+                    for (i, item) in items.iter_mut().enumerate() {
+                        if let Value::Object(_) = item {
+                            let at = format!("{}{}[{}].", at, path, i);
+                            for rule in rules {
+                                rule.apply(item, &at, report)?;
+                            }
+                        }
+                    }
+                }
+                Some(other) => {
+                    return Err(Error::ParseError(format!("cannot apply rules to each of '{}{}': {} is not an array", at, path, value_kind(other))));
+                }
+                None => report.missing.push(format!("{}{}", at, path)),
+            },
+        }
+        Ok(())
+    }
+}
+
+/// An ordered list of rules reshaping a document.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mapping {
+    pub rules: Vec<MapRule>,
+}
+
+impl Mapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a rule.
+    pub fn rule(mut self, rule: MapRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Read a mapping from an array of rules in declarative form (see
+    /// [`MapRule::from_value`]).
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let items = value.as_array()
+            .ok_or_else(|| Error::ParseError(format!("mapping must be an array of rules, got {}", value_kind(value))))?;
+        let rules = items.iter().enumerate()
+            .map(|(i, item)| MapRule::from_value(item).map_err(|e| Error::ParseError(format!("rule {}: {}", i, e))))
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+}
+
+/// What [`TeaLeaf::apply_mapping`] did.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MappingReport {
+    /// Number of rules that changed a value, counting each object of an
+    /// `each` rule separately
+    pub changed: usize,
+    /// Source paths that were missing, in full
+    pub missing: Vec<String>,
+}
+
+impl fmt::Display for MappingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} changed", self.changed)?;
+        for path in &self.missing {
+            writeln!(f, "  missing {}", path)?;
+        }
+        Ok(())
+    }
+}
+
+impl TeaLeaf {
+    /// Reshape the data by the rules of `mapping`, in order.
+    ///
+    /// The rules work on a copy of the data, so on error the document is
+    /// unchanged. Schemas are left as they are, and bindings of top-level
+    /// keys that no longer exist are dropped.
+    ///
+    /// Fails with [`Error::ParseError`] if a rule writes through a value
+    /// that is not an object, splits a value that is not a string, or
+    /// applies `each` to a value that is not an array.
+    pub fn apply_mapping(&mut self, mapping: &Mapping) -> Result<MappingReport> {
+        let mut data = Value::Object(self.data.clone());
+        let mut report = MappingReport::default();
+        for rule in &mapping.rules {
+            rule.apply(&mut data, "", &mut report)?;
+        }
+        if let Value::Object(data) = data {
+            self.data = data;
+        }
+        let data = &self.data;
+        self.bindings.retain(|key, _| data.contains_key(key));
+        Ok(report)
+    }
+}
+
+// =============================================================================
+// Paths
+// =============================================================================
+
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+fn segments(path: &str) -> Result<Vec<Segment<'_>>> {
+    let invalid = || Error::ParseError(format!("invalid path '{}'", path));
+    let mut out = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(invalid)?;
+            out.push(Segment::Index(after[..end].parse().map_err(|_| invalid())?));
+            rest = &after[end + 1..];
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            out.push(Segment::Key(&rest[..end]));
+            rest = &rest[end..];
+        }
+        if let Some(after) = rest.strip_prefix('.') {
+            if after.is_empty() {
+                return Err(invalid());
+            }
+            rest = after;
+        }
+    }
+    match out.first() {
+        Some(Segment::Key(_)) => Ok(out),
+        _ => Err(invalid()),
+    }
+}
+
+fn child<'a>(value: &'a mut Value, segment: &Segment) -> Option<&'a mut Value> {
+    match (value, segment) {
+        (Value::Object(obj), Segment::Key(key)) => obj.get_mut(*key),
+        (Value::Array(items), Segment::Index(i)) => items.get_mut(*i),
+        _ => None,
+    }
+}
+
+fn get<'a>(root: &'a mut Value, path: &str) -> Result<Option<&'a mut Value>> {
+    let mut current = Some(root);
+    for segment in &segments(path)? {
+        current = current.and_then(|value| child(value, segment));
+    }
+    Ok(current)
+}
+
+/// Remove and return the value at `path`. Array elements are replaced by
+/// null, so the indexes of later elements still hold.
+pub(crate) fn take(root: &mut Value, path: &str) -> Result<Option<Value>> {
+    let mut segments = segments(path)?;
+    let last = segments.pop();
+    let mut current = Some(root);
+    for segment in &segments {
+        current = current.and_then(|value| child(value, segment));
+    }
+    Ok(match (current, last) {
+        (Some(Value::Object(obj)), Some(Segment::Key(key))) => obj.shift_remove(key),
+        (Some(Value::Array(items)), Some(Segment::Index(i))) => items.get_mut(i).map(std::mem::take),
+        _ => None,
+    })
+}
+
+/// Set the value at `path`, creating missing objects on the way.
+pub(crate) fn put(root: &mut Value, path: &str, value: Value) -> Result<()> {
+    let segments = segments(path)?;
+    let mut current = root;
+    for (i, segment) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        current = match (current, segment) {
+            (Value::Object(obj), Segment::Key(key)) => {
+                obj.entry(key.to_string()).or_insert_with(|| Value::Object(IndexMap::new()))
+            }
+            (Value::Array(items), Segment::Index(index)) => {
+                let len = items.len();
+                items.get_mut(*index).ok_or_else(|| {
+                    Error::ParseError(format!("cannot set '{}': index {} is past the end of {} items", path, index, len))
+                })?
+            }
+            (other, _) => {
+                return Err(Error::ParseError(format!("cannot set '{}': segment {} is inside {}", path, i + 1, value_kind(other))));
+            }
+        };
+        if last {
+            *current = value;
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"
+        order: {
+            id: 17,
+            customer: {name: "Ada Lovelace", email: "ada@example.com"},
+            lines: [{sku: A1, qty: 2, note: "gift"}, {sku: B2, qty: 1}],
+        }
+        street: "1 Main St"
+        city: London
+    "#;
+
+    #[test]
+    fn test_apply_mapping() {
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        let report = doc.apply_mapping(&Mapping::new()
+            .rule(MapRule::Move { from: "order.customer".into(), to: "buyer".into() })
+            .rule(MapRule::Split { from: "buyer.name".into(), separator: " ".into(), to: vec!["buyer.first".into(), "buyer.last".into()] })
+            .rule(MapRule::Copy { from: "order.id".into(), to: "meta.source.order".into() })
+            .rule(MapRule::Merge { from: vec!["street".into(), "city".into()], separator: ", ".into(), to: "buyer.address".into() })
+            .rule(MapRule::Default { path: "currency".into(), value: Value::String("EUR".into()) })
+            .rule(MapRule::Each { path: "order.lines".into(), rules: vec![
+                MapRule::Move { from: "qty".into(), to: "quantity".into() },
+                MapRule::Drop { path: "note".into() },
+            ] })
+            .rule(MapRule::Drop { path: "order.lines[1].sku".into() })
+        ).unwrap();
+
+        assert_eq!(doc.get_path("buyer.first").and_then(Value::as_str), Some("Ada"));
+        assert_eq!(doc.get_path("buyer.last").and_then(Value::as_str), Some("Lovelace"));
+        assert!(doc.get_path("buyer.name").is_none());
+        assert_eq!(doc.get_path("buyer.address").and_then(Value::as_str), Some("1 Main St, London"));
+        assert_eq!(doc.get_path("meta.source.order").and_then(Value::as_int), Some(17));
+        assert_eq!(doc.get_path("order.id").and_then(Value::as_int), Some(17));
+        assert_eq!(doc.get_path("currency").and_then(Value::as_str), Some("EUR"));
+        assert_eq!(doc.get_path("order.lines[0].quantity").and_then(Value::as_int), Some(2));
+        assert!(doc.get_path("order.lines[0].note").is_none());
+        assert_eq!(doc.get_path("order.lines[1]").and_then(|line| line.get("sku")), None);
+        assert!(doc.get("street").is_none() && doc.get("order").unwrap().get("customer").is_none());
+
+        assert_eq!(report.changed, 9);
+        assert_eq!(report.missing, vec!["order.lines[1].note"]);
+        assert_eq!(report.to_string(), "9 changed\n  missing order.lines[1].note\n");
+    }
+
+    #[test]
+    fn test_mapping_from_value() {
+        let spec = TeaLeaf::parse(r#"
+            rules: [
+                {move: order.customer.name, to: full_name},
+                {split: full_name, on: " ", to: [first, last]},
+                {merge: [last, first], with: ", ", to: sort_name},
+                {default: currency, value: EUR},
+                {each: order.lines, rules: [{copy: sku, to: code}]},
+                {drop: nothing},
+            ]
+        "#).unwrap();
+        let mapping = Mapping::from_value(spec.get("rules").unwrap()).unwrap();
+        assert_eq!(mapping.rules.len(), 6);
+        assert_eq!(mapping.rules[1], MapRule::Split { from: "full_name".into(), separator: " ".into(), to: vec!["first".into(), "last".into()] });
+
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        let report = doc.apply_mapping(&mapping).unwrap();
+        assert_eq!(doc.get("sort_name").and_then(Value::as_str), Some("Lovelace, Ada"));
+        assert_eq!(doc.get_path("order.lines[1].code").and_then(Value::as_str), Some("B2"));
+        assert_eq!(report.missing, vec!["nothing"]);
+
+        for bad in ["[shuffle]", "[{}]", "[{move: a}]", "[{default: a}]", "[{each: a}]", "[{move: 1, to: b}]", "{drop: a}"] {
+            assert!(Mapping::from_value(&Value::parse_literal(bad).unwrap()).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_mapping_errors_leave_document_unchanged() {
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        let before = doc.data.clone();
+        for rule in [
+            MapRule::Move { from: "city".into(), to: "order.id.value".into() },
+            MapRule::Split { from: "order.id".into(), separator: " ".into(), to: vec!["a".into()] },
+            MapRule::Each { path: "city".into(), rules: vec![] },
+            MapRule::Copy { from: "city".into(), to: "order.lines[5]".into() },
+            MapRule::Merge { from: vec!["city".into(), "order".into()], separator: " ".into(), to: "x".into() },
+            MapRule::Drop { path: "order..id".into() },
+        ] {
+            assert!(doc.apply_mapping(&Mapping::new().rule(MapRule::Drop { path: "street".into() }).rule(rule.clone())).is_err(), "{:?}", rule);
+            assert_eq!(doc.data, before);
+        }
+    }
+}
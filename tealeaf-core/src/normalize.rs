@@ -0,0 +1,274 @@
+//! Rewriting object keys and field names to one naming convention.
+//!
+//! [`TeaLeaf::normalize_keys`] splits each name into words at `_`, `-`,
+//! spaces and case changes (`orderId`, `order_id`, `OrderID` and
+//! `order-id` are all the words `order` and `id`) and joins them again in
+//! the chosen [`Case`], so data merged from several sources agrees on its
+//! field names before schemas are inferred or compared.
+
+use crate::{Error, IndexMap, ObjectMap, Result, Schema, TeaLeaf, Union, Value};
+
+/// A naming convention for [`TeaLeaf::normalize_keys`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// `order_id`
+    Snake,
+    /// `orderId`
+    Camel,
+    /// `order-id`
+    Kebab,
+}
+
+/// Which names [`TeaLeaf::normalize_keys`] rewrites.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyScope {
+    /// Keys of objects and fields of schemas and union variants, but not
+    /// top-level keys.
+    #[default]
+    Fields,
+    /// Top-level keys too, except reference definitions (`!name`).
+    All,
+}
+
+impl Case {
+    /// `name` in this case. Leading underscores are kept, so `_id` does not
+    /// become `id`; names without letters or digits are returned as they are.
+    pub fn apply(self, name: &str) -> String {
+        let trimmed = name.trim_start_matches('_');
+        let prefix = &name[..name.len() - trimmed.len()];
+        let words = split_words(trimmed);
+        if words.is_empty() {
+            return name.to_string();
+        }
+        let mut out = String::from(prefix);
+        for (i, word) in words.iter().enumerate() {
+            match self {
+                Case::Snake | Case::Kebab => {
+                    if i > 0 {
+                        out.push(if self == Case::Snake { '_' } else { '-' });
+                    }
+                    out.push_str(&word.to_lowercase());
+                }
+                Case::Camel if i == 0 => out.push_str(&word.to_lowercase()),
+                Case::Camel => {
+                    let mut chars = word.chars();
+                    if let Some(first) = chars.next() {
+                        out.extend(first.to_uppercase());
+                        out.push_str(&chars.as_str().to_lowercase());
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Words of `name`: runs of letters and digits broken at separators, at a
+/// lower-to-upper case change, and before the last capital of a run of
+/// capitals followed by a lowercase letter (`HTTPServer` is `HTTP`, `Server`).
+fn split_words(name: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = name.char_indices().collect();
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+    for (i, &(at, c)) in chars.iter().enumerate() {
+        if matches!(c, '_' | '-' | ' ' | '.') {
+            if let Some(s) = start.take() {
+                words.push(&name[s..at]);
+            }
+            continue;
+        }
+        if let Some(s) = start {
+            let prev = chars[i - 1].1;
+            let next_lower = chars.get(i + 1).is_some_and(|&(_, n)| n.is_lowercase());
+            let boundary = c.is_uppercase()
+                && (prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower));
+            if boundary {
+                words.push(&name[s..at]);
+                start = Some(at);
+            }
+        } else {
+            start = Some(at);
+        }
+    }
+    if let Some(s) = start {
+        words.push(&name[s..]);
+    }
+    words
+}
+
+impl TeaLeaf {
+    /// Rewrite object keys and schema and union field names in `case`.
+    ///
+    /// Every object is rewritten, whatever its schema, and key order is
+    /// kept. With [`KeyScope::All`], top-level keys are rewritten too and
+    /// their schema bindings follow them. Map keys, tags, schema names and
+    /// reference names are left as they are.
+    ///
+    /// Fails with [`Error::KeyCollision`] naming both keys, leaving the
+    /// document unchanged, if two keys of one object, two fields of one
+    /// schema or variant, or (with `KeyScope::All`) two top-level keys
+    /// would get the same name.
+    pub fn normalize_keys(&mut self, case: Case, scope: KeyScope) -> Result<()> {
+        let schemas = self.schemas.iter()
+            .map(|(name, schema)| Ok((name.clone(), normalize_schema(schema, case)?)))
+            .collect::<Result<IndexMap<String, Schema>>>()?;
+        let unions = self.unions.iter()
+            .map(|(name, union)| Ok((name.clone(), normalize_union(union, case)?)))
+            .collect::<Result<IndexMap<String, Union>>>()?;
+
+        let mut data: IndexMap<String, Value> = IndexMap::with_capacity(self.data.len());
+        let mut renamed: IndexMap<String, String> = IndexMap::new();
+        for (key, value) in &self.data {
+            let new_key = if scope == KeyScope::All && !key.starts_with('!') { case.apply(key) } else { key.clone() };
+            let value = normalize_value(value, case, key)?;
+            if let Some((other, _)) = data.get_key_value(&new_key) {
+                return Err(collision("", other_source(&renamed, other), key, &new_key));
+            }
+            renamed.insert(new_key.clone(), key.clone());
+            data.insert(new_key, value);
+        }
+
+        let bindings = self.bindings.iter()
+            .map(|(key, schema)| {
+                let key = if scope == KeyScope::All && !key.starts_with('!') { case.apply(key) } else { key.clone() };
+                (key, schema.clone())
+            })
+            .collect();
+        self.schemas = schemas;
+        self.unions = unions;
+        self.data = data;
+        self.bindings = bindings;
+        Ok(())
+    }
+}
+
+/// The original key that became `key`.
+fn other_source<'a>(renamed: &'a IndexMap<String, String>, key: &'a str) -> &'a str {
+    renamed.get(key).map(String::as_str).unwrap_or(key)
+}
+
+fn collision(place: &str, first: &str, second: &str, name: &str) -> Error {
+    Error::KeyCollision { first: first.to_string(), second: second.to_string(), normalized: name.to_string(), place: place.to_string() }
+}
+
+fn normalize_fields(fields: &mut [crate::Field], case: Case, place: &str) -> Result<()> {
+    let mut seen: IndexMap<String, String> = IndexMap::with_capacity(fields.len());
+    for field in fields.iter_mut() {
+        let name = case.apply(&field.name);
+        if let Some(first) = seen.get(&name) {
+            return Err(collision(place, first, &field.name, &name));
+        }
+        seen.insert(name.clone(), field.name.clone());
+        field.name = name;
+    }
+    Ok(())
+}
+
+fn normalize_schema(schema: &Schema, case: Case) -> Result<Schema> {
+    let mut schema = schema.clone();
+    normalize_fields(&mut schema.fields, case, &format!("schema '{}'", schema.name))?;
+    Ok(schema)
+}
+
+fn normalize_union(union: &Union, case: Case) -> Result<Union> {
+    let mut union = union.clone();
+    for variant in &mut union.variants {
+        normalize_fields(&mut variant.fields, case, &format!("variant '{}' of union '{}'", variant.name, union.name))?;
+    }
+    Ok(union)
+}
+
+fn normalize_value(value: &Value, case: Case, path: &str) -> Result<Value> {
+    Ok(match value {
+        Value::Object(obj) => {
+            let mut out: ObjectMap<String, Value> = ObjectMap::with_capacity(obj.len());
+            let mut renamed: IndexMap<String, String> = IndexMap::with_capacity(obj.len());
+            for (key, v) in obj {
+                let new_key = case.apply(key);
+                if let Some((other, _)) = out.get_key_value(&new_key) {
+                    return Err(collision(path, other_source(&renamed, other), key, &new_key));
+                }
+                let v = normalize_value(v, case, &format!("{}.{}", path, key))?;
+                renamed.insert(new_key.clone(), key.clone());
+                out.insert(new_key, v);
+            }
+            Value::Object(out)
+        }
+        Value::Array(items) => Value::Array(items.iter().enumerate()
+            .map(|(i, v)| normalize_value(v, case, &format!("{}[{}]", path, i)))
+            .collect::<Result<_>>()?),
+        Value::Map(pairs) => Value::Map(pairs.iter().enumerate()
+            .map(|(i, (k, v))| Ok((k.clone(), normalize_value(v, case, &format!("{}[{}]", path, i))?)))
+            .collect::<Result<_>>()?),
+        Value::Tagged(tag, inner) => Value::Tagged(tag.clone(), Box::new(normalize_value(inner, case, path)?)),
+        other => other.clone(),
+    })
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_apply() {
+        for name in ["orderId", "order_id", "OrderID", "order-id", "Order Id"] {
+            assert_eq!(Case::Snake.apply(name), "order_id", "{}", name);
+            assert_eq!(Case::Camel.apply(name), "orderId", "{}", name);
+            assert_eq!(Case::Kebab.apply(name), "order-id", "{}", name);
+        }
+        assert_eq!(Case::Snake.apply("HTTPServerURL"), "http_server_url");
+        assert_eq!(Case::Camel.apply("address2_line"), "address2Line");
+        assert_eq!(Case::Snake.apply("_id"), "_id");
+        assert_eq!(Case::Snake.apply("$ref"), "$ref");
+        assert_eq!(Case::Snake.apply("---"), "---");
+    }
+
+    #[test]
+    fn test_normalize_keys() {
+        let input = "@struct order (orderId: int, CustomerName: string?)\n\
+                     orders: @table order [(1, ann), (2, ~)]\n\
+                     shipmentInfo: {\"Tracking-No\": x1, items: [{ItemID: 5}], by: @map {KeyA: {innerKey: 1}}}\n\
+                     !baseRef: {someKey: 1}\n";
+        let mut doc = TeaLeaf::parse(input).unwrap();
+        doc.normalize_keys(Case::Snake, KeyScope::Fields).unwrap();
+        let fields: Vec<&str> = doc.schema("order").unwrap().fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(fields, ["order_id", "customer_name"]);
+        assert_eq!(doc.get_path("orders[0].customer_name").and_then(Value::as_str), Some("ann"));
+        assert_eq!(doc.get_path("shipmentInfo.tracking_no").and_then(Value::as_str), Some("x1"));
+        assert_eq!(doc.get_path("shipmentInfo.items[0].item_id"), Some(&Value::Int(5)));
+        let by = doc.get_path("shipmentInfo.by").unwrap().as_map().unwrap();
+        assert_eq!(by[0].0.as_str(), Some("KeyA"));
+        assert!(by[0].1.get("inner_key").is_some());
+        assert!(doc.get("!baseRef").unwrap().get("some_key").is_some());
+
+        doc.normalize_keys(Case::Camel, KeyScope::All).unwrap();
+        assert!(doc.get("shipmentInfo").is_some() && doc.get("!baseRef").is_some());
+        assert_eq!(doc.binding("orders"), Some("order"));
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("@struct order (orderId: int, customerName: string?)"), "{}", text);
+        assert_eq!(TeaLeaf::parse(&text).unwrap().data, doc.data);
+
+        doc.normalize_keys(Case::Kebab, KeyScope::All).unwrap();
+        assert!(doc.get("shipment-info").unwrap().get("tracking-no").is_some());
+    }
+
+    #[test]
+    fn test_collisions_leave_document_unchanged() {
+        let mut doc = TeaLeaf::parse("@struct s (aB: int, a_b: int)\nx: {}\n").unwrap();
+        let err = doc.normalize_keys(Case::Snake, KeyScope::Fields).unwrap_err();
+        assert!(matches!(&err, Error::KeyCollision { normalized, place, .. } if normalized == "a_b" && place == "schema 's'"), "{}", err);
+        assert_eq!(err.to_string(), "Keys 'aB' and 'a_b' in schema 's' both normalize to 'a_b'");
+        assert_eq!(doc.schema("s").unwrap().fields[0].name, "aB");
+
+        let mut doc = TeaLeaf::parse("rows: [{id: 1}, {orderId: 1, OrderID: 2}]\n").unwrap();
+        let before = doc.data.clone();
+        let err = doc.normalize_keys(Case::Snake, KeyScope::Fields).unwrap_err();
+        assert!(err.to_string().contains("'orderId' and 'OrderID' in rows[1]"), "{}", err);
+        assert_eq!(doc.data, before);
+
+        let mut doc = TeaLeaf::parse("userId: 1\nuser_id: 2\n").unwrap();
+        doc.normalize_keys(Case::Snake, KeyScope::Fields).unwrap();
+        let err = doc.normalize_keys(Case::Snake, KeyScope::All).unwrap_err();
+        assert!(err.to_string().contains("'userId' and 'user_id'"), "{}", err);
+    }
+}
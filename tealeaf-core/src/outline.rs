@@ -0,0 +1,278 @@
+//! Structural summaries of documents, for exploring unknown files.
+//!
+//! [`TeaLeaf::outline`] describes what a document holds (keys, field types,
+//! array and table lengths) without printing any of the data. The elements of
+//! an array are summarized together: an array of objects lists every field
+//! seen in any element, marking those missing or null in some as optional.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::types::value_kind;
+use crate::{IndexMap, ObjectMap, Schema, TeaLeaf, Value, object_matches_schema, resolve_schema};
+
+/// Summary of a document, from [`TeaLeaf::outline`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Outline {
+    /// One node per top-level key, in document order.
+    pub nodes: Vec<OutlineNode>,
+}
+
+/// Summary of a key or field and, down to the requested depth, its fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineNode {
+    pub name: String,
+    /// Kind of the value, as in warnings (`int`, `object`, `array`, ...), or
+    /// `mixed` if the values summarized differ.
+    pub kind: &'static str,
+    /// Common kind of an array's elements; `None` if it has none.
+    pub items: Option<&'static str>,
+    /// Schema bound to an object, or to the elements of an array.
+    pub schema: Option<String>,
+    /// Element count of an array or map, field count of an object. Only
+    /// known for a single value, not for fields summarized across elements.
+    pub len: Option<usize>,
+    /// Null, or missing from some of the objects summarized.
+    pub optional: bool,
+    /// Fields of an object, or of the objects in an array.
+    pub children: Vec<OutlineNode>,
+    /// The node has fields below the requested depth.
+    pub truncated: bool,
+}
+
+impl OutlineNode {
+    /// The node's type as written in the text outline, e.g. `[]user`,
+    /// `string?` or `object`.
+    pub fn type_name(&self) -> String {
+        let base = match (self.kind, self.items) {
+            ("array", Some(items)) => format!("[]{}", self.schema.as_deref().unwrap_or(items)),
+            ("object", _) => self.schema.clone().unwrap_or_else(|| "object".to_string()),
+            (kind, _) => kind.to_string(),
+        };
+        if self.optional && self.kind != "null" {
+            format!("{}?", base)
+        } else {
+            base
+        }
+    }
+
+    /// The node as an object with `type`, and `len`, `fields` and
+    /// `truncated` where they apply.
+    pub fn to_value(&self) -> Value {
+        let mut obj = ObjectMap::new();
+        obj.insert("type".to_string(), Value::String(self.type_name()));
+        if let Some(len) = self.len {
+            obj.insert("len".to_string(), Value::UInt(len as u64));
+        }
+        if !self.children.is_empty() {
+            let fields = self.children.iter().map(|c| (c.name.clone(), c.to_value())).collect();
+            obj.insert("fields".to_string(), Value::Object(fields));
+        }
+        if self.truncated {
+            obj.insert("truncated".to_string(), Value::Bool(true));
+        }
+        Value::Object(obj)
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        write!(f, "{:indent$}{}: {}", "", self.name, self.type_name(), indent = indent)?;
+        match (self.kind, self.len) {
+            ("object", Some(len)) => write!(f, " {{{}}}", len)?,
+            (_, Some(len)) => write!(f, " [{}]", len)?,
+            _ => {}
+        }
+        if self.truncated {
+            write!(f, " ...")?;
+        }
+        writeln!(f)?;
+        for child in &self.children {
+            child.write(f, indent + 2)?;
+        }
+        Ok(())
+    }
+}
+
+impl Outline {
+    /// The outline as an object mapping each top-level key to
+    /// [`OutlineNode::to_value`].
+    pub fn to_value(&self) -> Value {
+        Value::Object(self.nodes.iter().map(|n| (n.name.clone(), n.to_value())).collect())
+    }
+}
+
+/// One line per node, fields indented under their parent:
+///
+/// ```text
+/// users: []user [2]
+///   id: int
+///   email: string?
+/// config: object {2} ...
+/// ```
+impl fmt::Display for Outline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.nodes {
+            node.write(f, 0)?;
+        }
+        Ok(())
+    }
+}
+
+impl TeaLeaf {
+    /// Summarize the document's structure down to `depth` levels of fields
+    /// below the top-level keys; `outline(0)` lists only the keys.
+    ///
+    /// Arrays do not count as a level: the fields of a table's rows are one
+    /// level below the table's key. Objects are bound to schemas the way the
+    /// text writer binds them.
+    pub fn outline(&self, depth: usize) -> Outline {
+        let summary = Summary { schemas: &self.schemas };
+        let nodes = self.data.iter()
+            .map(|(key, value)| summary.node(key, &[value], self.binding(key), depth))
+            .collect();
+        Outline { nodes }
+    }
+}
+
+struct Summary<'a> {
+    schemas: &'a IndexMap<String, Schema>,
+}
+
+impl Summary<'_> {
+    /// Summarize `values` together under `name`.
+    fn node(&self, name: &str, values: &[&Value], declared_type: Option<&str>, depth: usize) -> OutlineNode {
+        let present: Vec<&Value> = values.iter().copied().filter(|v| !v.is_null()).collect();
+        let mut node = OutlineNode {
+            name: name.to_string(),
+            kind: common_kind(&present).unwrap_or("null"),
+            items: None,
+            schema: None,
+            len: None,
+            optional: present.len() < values.len(),
+            children: Vec::new(),
+            truncated: false,
+        };
+        if let [value] = values {
+            node.len = match value {
+                Value::Array(items) => Some(items.len()),
+                Value::Object(obj) => Some(obj.len()),
+                Value::Map(pairs) => Some(pairs.len()),
+                _ => None,
+            };
+        }
+        let objects: Vec<&ObjectMap<String, Value>> = match node.kind {
+            "object" => present.iter().filter_map(|v| v.as_object()).collect(),
+            "array" => {
+                let elements: Vec<&Value> = present.iter()
+                    .filter_map(|v| v.as_array())
+                    .flatten()
+                    .filter(|v| !v.is_null())
+                    .collect();
+                node.items = common_kind(&elements);
+                if node.items != Some("object") {
+                    return node;
+                }
+                elements.iter().filter_map(|v| v.as_object()).collect()
+            }
+            _ => return node,
+        };
+        let schema = self.schema_for(objects.first().copied(), declared_type, name);
+        node.schema = schema.map(|s| s.name.clone());
+        if depth == 0 {
+            node.truncated = objects.iter().any(|obj| !obj.is_empty());
+        } else {
+            node.children = self.fields(&objects, schema, depth - 1);
+        }
+        node
+    }
+
+    /// Summarize each field seen in any of `objects`, in order of first use.
+    fn fields(&self, objects: &[&ObjectMap<String, Value>], schema: Option<&Schema>, depth: usize) -> Vec<OutlineNode> {
+        let mut fields: IndexMap<&str, Vec<&Value>> = IndexMap::new();
+        for obj in objects {
+            for (k, v) in obj.iter() {
+                fields.entry(k.as_str()).or_default().push(v);
+            }
+        }
+        fields.into_iter().map(|(name, values)| {
+            let declared = schema
+                .and_then(|s| s.fields.iter().find(|f| f.name == name))
+                .map(|f| f.field_type.base.as_str());
+            let mut node = self.node(name, &values, declared, depth);
+            node.optional |= values.len() < objects.len();
+            node
+        }).collect()
+    }
+
+    fn schema_for(&self, first: Option<&ObjectMap<String, Value>>, declared_type: Option<&str>, name: &str) -> Option<&Schema> {
+        let keys: HashSet<&str> = first?.keys().map(|k| k.as_str()).collect();
+        resolve_schema(self.schemas, declared_type, Some(name))
+            .filter(|s| object_matches_schema(&keys, s))
+            .or_else(|| self.schemas.values().find(|s| object_matches_schema(&keys, s)))
+    }
+}
+
+/// The kind shared by all `values`, `mixed` if they differ.
+fn common_kind(values: &[&Value]) -> Option<&'static str> {
+    let first = value_kind(values.first()?);
+    if values.iter().all(|v| value_kind(v) == first) {
+        Some(first)
+    } else {
+        Some("mixed")
+    }
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"
+        @struct user (id: int, name: string, email: string?)
+        users: @table user [
+            (1, alice, "a@x.io"),
+            (2, bob, ~),
+        ]
+        events: [{at: 1, kind: open}, {at: 2, extra: {note: hi}}, {at: "late"}]
+        config: {host: localhost, ports: [80, 443], tls: {cert: "a.pem"}}
+        lookup: @map {1: one, 2: two}
+        empty: []
+    "#;
+
+    #[test]
+    fn test_outline_text() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        assert_eq!(doc.outline(1).to_string(), "\
+users: []user [2]
+  id: int
+  name: string
+  email: string?
+events: []object [3]
+  at: mixed
+  kind: string?
+  extra: object? {1} ...
+config: object {3}
+  host: string
+  ports: []int [2]
+  tls: object {1} ...
+lookup: map [2]
+empty: array [0]
+");
+        let keys_only = doc.outline(0).to_string();
+        assert!(keys_only.starts_with("users: []user [2] ...\nevents: []object [3] ...\n"), "{}", keys_only);
+        assert!(doc.outline(2).to_string().contains("  tls: object {1}\n    cert: string\n"));
+    }
+
+    #[test]
+    fn test_outline_value() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let outline = doc.outline(1);
+        assert_eq!(outline.nodes[0].schema.as_deref(), Some("user"));
+        assert_eq!(outline.nodes[0].children.len(), 3);
+
+        let value = outline.to_value();
+        assert_eq!(value.get_path("users.type").and_then(Value::as_str), Some("[]user"));
+        assert_eq!(value.get_path("users.len").and_then(Value::as_uint), Some(2));
+        assert_eq!(value.get_path("users.fields.email.type").and_then(Value::as_str), Some("string?"));
+        assert_eq!(value.get_path("config.fields.tls.truncated").and_then(Value::as_bool), Some(true));
+        assert!(value.get_path("lookup.fields").is_none());
+    }
+}
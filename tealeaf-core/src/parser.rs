@@ -1,15 +1,110 @@
 //! Parser for TeaLeaf text format
 
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use indexmap::IndexMap;
 use crate::{Error, Result, Value, Schema, Field, FieldType, Union, Variant};
 use crate::types::ObjectMap;
-use crate::lexer::{Token, TokenKind, Lexer};
+use crate::lexer::{Token, TokenKind, Lexer, TimestampOptions};
+use crate::units::Quantity;
 
 /// Maximum recursion depth for nested parse_value calls (arrays, objects, maps, tuples, tags).
-/// Matches the binary reader's MAX_DECODE_DEPTH to ensure text↔binary parity.
+/// Matches the binary reader's default `ReaderOptions::DEFAULT_MAX_DEPTH` to ensure text↔binary parity.
 const MAX_PARSE_DEPTH: usize = 256;
 
+/// What the parser does when an object gives the same key twice.
+///
+/// A tuple with more values than its schema has fields is always an
+/// [`Error::TooManyValues`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Fail with [`Error::DuplicateKey`].
+    #[default]
+    Error,
+    /// Keep the last value given, in the position of the first.
+    LastWins,
+}
+
+/// Which files `@include` may read, and how much.
+///
+/// The default allows any include, as trusted files expect.
+/// [`sandboxed`](Self::sandboxed) suits text from tenants or users: it
+/// confines includes to one directory and limits how many files and bytes
+/// they read, so a document cannot pull in arbitrary files or balloon
+/// through nested includes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludePolicy {
+    /// Directories included files must lie under once links are resolved;
+    /// empty allows any directory.
+    pub allowed_roots: Vec<PathBuf>,
+    /// Maximum number of files included, counting nested includes.
+    pub max_files: usize,
+    /// Maximum total size of the included files, in bytes, counting a
+    /// compressed file at its decompressed size.
+    pub max_bytes: u64,
+    /// Allow absolute include paths.
+    pub allow_absolute: bool,
+    /// Allow `..` in include paths.
+    pub allow_parent: bool,
+}
+
+impl Default for IncludePolicy {
+    fn default() -> Self {
+        Self {
+            allowed_roots: Vec::new(),
+            max_files: usize::MAX,
+            max_bytes: u64::MAX,
+            allow_absolute: true,
+            allow_parent: true,
+        }
+    }
+}
+
+impl IncludePolicy {
+    /// No `@include` at all.
+    pub fn deny() -> Self {
+        Self { max_files: 0, ..Self::default() }
+    }
+
+    /// Includes under `root` only, by relative paths without `..`, at most
+    /// 64 files and 16 MiB in all.
+    pub fn sandboxed(root: impl Into<PathBuf>) -> Self {
+        Self {
+            allowed_roots: vec![root.into()],
+            max_files: 64,
+            max_bytes: 16 * 1024 * 1024,
+            allow_absolute: false,
+            allow_parent: false,
+        }
+    }
+
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Check that `path`, as written in the `@include`, may be read from
+    /// `resolved`, its canonical location.
+    fn check_path(&self, path: &str, resolved: &Path) -> Result<()> {
+        let written = Path::new(path);
+        if !self.allow_absolute && (written.is_absolute() || written.has_root()) {
+            return Err(Error::ParseError(format!("Include of {} denied: absolute paths are not allowed", path)));
+        }
+        if !self.allow_parent && written.components().any(|c| c == Component::ParentDir) {
+            return Err(Error::ParseError(format!("Include of {} denied: '..' is not allowed", path)));
+        }
+        let inside = |root: &PathBuf| root.canonicalize().is_ok_and(|root| resolved.starts_with(root));
+        if !self.allowed_roots.is_empty() && !self.allowed_roots.iter().any(inside) {
+            return Err(Error::ParseError(format!("Include of {} denied: outside the allowed directories", path)));
+        }
+        Ok(())
+    }
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
@@ -20,6 +115,18 @@ pub struct Parser {
     include_stack: Vec<std::path::PathBuf>,
     /// Indicates the source was a root-level JSON array (set by @root-array directive)
     is_root_array: bool,
+    /// Indicates the source was a root-level JSON scalar (set by @root-primitive directive)
+    is_root_primitive: bool,
+    /// Schema named by `@table` or `@as` for each top-level key that has one
+    bindings: IndexMap<String, String>,
+    /// Key each `@alias` name stands for
+    aliases: IndexMap<String, String>,
+    /// Timestamp forms accepted in included files
+    timestamps: TimestampOptions,
+    duplicates: DuplicatePolicy,
+    includes: IncludePolicy,
+    /// Files and bytes included so far, nested includes too
+    included: (usize, u64),
 }
 
 impl Parser {
@@ -32,9 +139,36 @@ impl Parser {
             base_path: None,
             include_stack: Vec::new(),
             is_root_array: false,
+            is_root_primitive: false,
+            bindings: IndexMap::new(),
+            aliases: IndexMap::new(),
+            timestamps: TimestampOptions::default(),
+            duplicates: DuplicatePolicy::default(),
+            includes: IncludePolicy::default(),
+            included: (0, 0),
         }
     }
 
+    /// Read `@include`d files only as `policy` allows, in included files
+    /// too.
+    pub fn with_includes(mut self, policy: IncludePolicy) -> Self {
+        self.includes = policy;
+        self
+    }
+
+    /// Handle repeated object keys according to `policy`, in included
+    /// files too.
+    pub fn with_duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Lex included files with `options`, as the including file was.
+    pub fn with_timestamps(mut self, options: TimestampOptions) -> Self {
+        self.timestamps = options;
+        self
+    }
+
     pub fn with_base_path(mut self, path: &Path) -> Self {
         self.base_path = path.parent().map(|p| p.to_path_buf());
         self
@@ -44,55 +178,148 @@ impl Parser {
         let mut result = IndexMap::new();
 
         while !self.at_end() {
-            match self.current_kind() {
-                TokenKind::Directive(d) => {
-                    let directive = d.clone();
-                    self.advance();
-                    match directive.as_str() {
-                        "struct" => self.parse_struct_def()?,
-                        "union" => self.parse_union_def()?,
-                        "include" => {
-                            let included = self.parse_include()?;
-                            for (k, v) in included {
-                                result.insert(k, v);
-                            }
-                        }
-                        "root-array" => {
-                            // Marks this document as representing a root-level JSON array
-                            self.is_root_array = true;
+            self.parse_section(&mut result)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Parse a single value making up the whole input, as in
+    /// [`Value::parse_literal`].
+    pub fn parse_literal(&mut self) -> Result<Value> {
+        let value = self.parse_value(0)?;
+        if !self.at_end() {
+            return Err(Error::UnexpectedToken {
+                expected: "end of input".to_string(),
+                got: format!("{:?}", self.current_kind()),
+            });
+        }
+        Ok(value)
+    }
+
+    /// Parse like [`parse`](Self::parse), but after an error skip to the next
+    /// top-level section and carry on. `gaps` are the token positions where
+    /// the lexer dropped input (see `Lexer::tokenize_lenient`); a section
+    /// running into one is dropped without a further error, the lexer having
+    /// reported it, and parsing resumes after the gap. Returns what parsed
+    /// and each error with its line.
+    pub(crate) fn parse_lenient(&mut self, gaps: &[usize]) -> (IndexMap<String, Value>, Vec<(usize, Error)>) {
+        let mut result = IndexMap::new();
+        let mut errors = Vec::new();
+
+        while !self.at_end() {
+            let start = self.pos;
+            let mut section = IndexMap::new();
+            let outcome = self.parse_section(&mut section);
+            // A section that failed right at a gap ran into it too
+            let gap = gaps.iter().copied()
+                .find(|&g| g > start && (g < self.pos || g == self.pos && outcome.is_err()));
+            match (outcome, gap) {
+                (Ok(()), None) => result.extend(section),
+                (Err(err), None) => {
+                    errors.push((self.current().line, err));
+                    self.skip_to_next_section(start + 1, self.tokens[start].col);
+                }
+                (_, Some(gap)) => self.skip_to_next_section(gap, self.tokens[start].col),
+            }
+        }
+
+        (result, errors)
+    }
+
+    /// Parse one top-level item (a key-value pair, reference or directive)
+    /// into `result`.
+    fn parse_section(&mut self, result: &mut IndexMap<String, Value>) -> Result<()> {
+        match self.current_kind() {
+            TokenKind::Directive(d) => {
+                let directive = d.clone();
+                self.advance();
+                match directive.as_str() {
+                    "struct" => self.parse_struct_def()?,
+                    "union" => self.parse_union_def()?,
+                    "alias" => self.parse_alias_def()?,
+                    "include" => {
+                        let included = self.parse_include()?;
+                        for (k, v) in included {
+                            result.insert(k, v);
                         }
-                        _ => {
-                            // Unknown top-level directive: silently ignored (spec §1.18).
-                            // Consume same-line argument for forward compatibility —
-                            // a future directive like @custom foo should not leave
-                            // "foo" to be misparsed as a key.
-                            let directive_line = self.tokens[self.pos - 1].line;
-                            if !self.at_end()
-                                && self.current().line == directive_line
-                                && self.can_start_value()
-                            {
-                                let _ = self.parse_value(0)?;
-                            }
+                    }
+                    "root-array" => {
+                        // Marks this document as representing a root-level JSON array
+                        self.is_root_array = true;
+                    }
+                    "root-primitive" => {
+                        // Marks this document as representing a root-level JSON scalar
+                        self.is_root_primitive = true;
+                    }
+                    _ => {
+                        // Unknown top-level directive: silently ignored (spec §1.18).
+                        // Consume same-line argument for forward compatibility —
+                        // a future directive like @custom foo should not leave
+                        // "foo" to be misparsed as a key.
+                        let directive_line = self.tokens[self.pos - 1].line;
+                        if !self.at_end()
+                            && self.current().line == directive_line
+                            && self.can_start_value()
+                        {
+                            let _ = self.parse_value(0)?;
                         }
                     }
                 }
-                TokenKind::Word(_) | TokenKind::String(_) => {
-                    let (key, value) = self.parse_pair(0)?;
-                    result.insert(key, value);
-                }
-                TokenKind::Ref(r) => {
-                    let ref_name = r.clone();
-                    self.advance();
-                    self.expect(TokenKind::Colon)?;
-                    let value = self.parse_value(0)?;
-                    result.insert(format!("!{}", ref_name), value);
+            }
+            TokenKind::Word(_) | TokenKind::String(_) => {
+                let binding = self.value_binding(self.pos + 2);
+                let (key, value) = self.parse_pair(0)?;
+                match binding {
+                    Some(schema) => { self.bindings.insert(key.clone(), schema); }
+                    None => { self.bindings.shift_remove(&key); }
                 }
-                TokenKind::Eof => break,
-                _ => { self.advance(); }
+                result.insert(key, value);
+            }
+            TokenKind::Ref(r) => {
+                let ref_name = r.clone();
+                self.advance();
+                self.expect(TokenKind::Colon)?;
+                let value = self.parse_value(0)?;
+                result.insert(format!("!{}", ref_name), value);
             }
+            TokenKind::Eof => {}
+            _ => { self.advance(); }
         }
+        Ok(())
+    }
 
-        Ok(result)
+    /// The schema named by a `@table`, `@as` or `@row` value starting at
+    /// token `at`.
+    fn value_binding(&self, at: usize) -> Option<String> {
+        match (self.tokens.get(at).map(|t| &t.kind), self.tokens.get(at + 1).map(|t| &t.kind)) {
+            (Some(TokenKind::Directive(d)), Some(TokenKind::Word(name) | TokenKind::String(name)))
+                if d == "table" || d == "as" || d == "row" => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    /// Move to the first token from `from` on that begins a line, is
+    /// indented no deeper than `col` and starts a section. Scanning from the
+    /// start of the failed section rather than from the error lets recovery
+    /// stop at a section that an unclosed bracket swallowed.
+    fn skip_to_next_section(&mut self, from: usize, col: usize) {
+        self.pos = from;
+        while !self.at_end() {
+            let tok = self.current();
+            let starts_line = self.tokens[self.pos - 1].line < tok.line;
+            let starts_section = match tok.kind {
+                TokenKind::Directive(_) => true,
+                TokenKind::Word(_) | TokenKind::String(_) | TokenKind::Ref(_) => {
+                    matches!(self.tokens.get(self.pos + 1).map(|t| &t.kind), Some(TokenKind::Colon))
+                }
+                _ => false,
+            };
+            if starts_line && tok.col <= col && starts_section {
+                break;
+            }
+            self.advance();
+        }
     }
 
     pub fn into_schemas(self) -> IndexMap<String, Schema> {
@@ -113,35 +340,29 @@ impl Parser {
         self.is_root_array
     }
 
+    /// Check if the @root-primitive directive was present
+    pub fn is_root_primitive(&self) -> bool {
+        self.is_root_primitive
+    }
+
+    /// Top-level keys whose value named its schema with `@table` or `@as`,
+    /// and that schema.
+    pub fn bindings(&self) -> &IndexMap<String, String> {
+        &self.bindings
+    }
+
     // =========================================================================
     // Struct Definition
     // =========================================================================
 
     fn parse_struct_def(&mut self) -> Result<()> {
-        let name = self.expect_word()?;
+        let name = self.expect_type_name()?;
         self.expect(TokenKind::LParen)?;
 
         let mut schema = Schema::new(&name);
 
         while !self.check(TokenKind::RParen) {
-            // Field names can be unquoted words or quoted strings (for names
-            // that contain special characters like @type, $ref, etc.)
-            let field_name = match self.current_kind() {
-                TokenKind::Word(w) => {
-                    let w = w.clone();
-                    self.advance();
-                    w
-                }
-                TokenKind::String(s) => {
-                    let s = s.clone();
-                    self.advance();
-                    s
-                }
-                _ => return Err(Error::UnexpectedToken {
-                    expected: "field name".to_string(),
-                    got: format!("{:?}", self.current_kind()),
-                }),
-            };
+            let field_name = self.expect_field_name(&name, &schema.fields)?;
 
             let field_type = if self.check(TokenKind::Colon) {
                 self.advance();
@@ -162,12 +383,53 @@ impl Parser {
         Ok(())
     }
 
+    /// The name of the next field of `definition`, which has `fields` so
+    /// far. Names can be unquoted words or quoted strings (for names that
+    /// contain special characters like @type, $ref, etc., or that read as
+    /// literals like `true`); they must not be empty or repeat.
+    fn expect_field_name(&mut self, definition: &str, fields: &[Field]) -> Result<String> {
+        let (line, col) = (self.current().line, self.current().col);
+        let invalid = |field: &str, reason: &str| Error::InvalidFieldName {
+            definition: definition.to_string(),
+            field: field.to_string(),
+            reason: reason.to_string(),
+            line,
+            col,
+        };
+        let name = match self.current_kind() {
+            TokenKind::Word(w) | TokenKind::String(w) => w.clone(),
+            kind => {
+                let literal = match kind {
+                    TokenKind::Bool(true) => "true",
+                    TokenKind::Bool(false) => "false",
+                    TokenKind::ExplicitNull => "null",
+                    TokenKind::Null => "~",
+                    TokenKind::Float(f) if f.is_nan() => "NaN",
+                    TokenKind::Float(f) if f.is_infinite() => "inf",
+                    _ => return Err(Error::UnexpectedToken {
+                        expected: "field name".to_string(),
+                        got: format!("{:?}", kind),
+                    }),
+                };
+                return Err(invalid(literal, "reserved literal; quote it to use it as a name"));
+            }
+        };
+        if name.is_empty() {
+            return Err(invalid(&name, "field name is empty"));
+        }
+        if fields.iter().any(|f| f.name == name) {
+            return Err(invalid(&name, "duplicate field name"));
+        }
+        self.advance();
+        Ok(name)
+    }
+
     // =========================================================================
     // Union Definition
     // =========================================================================
 
     fn parse_union_def(&mut self) -> Result<()> {
-        let name = self.expect_word()?;
+        let name = self.expect_type_name()?;
         self.expect(TokenKind::LBrace)?;
 
         let mut union_type = Union::new(&name);
@@ -179,7 +441,7 @@ impl Parser {
             let mut variant = Variant::new(&variant_name);
 
             while !self.check(TokenKind::RParen) {
-                let field_name = self.expect_word()?;
+                let field_name = self.expect_field_name(&format!("{}.{}", name, variant_name), &variant.fields)?;
 
                 let field_type = if self.check(TokenKind::Colon) {
                     self.advance();
@@ -208,6 +470,34 @@ impl Parser {
         Ok(())
     }
 
+    // =========================================================================
+    // Alias Definition
+    // =========================================================================
+
+    /// `@alias u=user_identifier, t="created-at"`: from here on, the unquoted
+    /// key `u` stands for `user_identifier`.
+    fn parse_alias_def(&mut self) -> Result<()> {
+        loop {
+            let alias = self.expect_word()?;
+            self.expect(TokenKind::Eq)?;
+            let key = match self.current_kind() {
+                TokenKind::Word(w) => w.clone(),
+                TokenKind::String(s) => s.clone(),
+                _ => return Err(Error::UnexpectedToken {
+                    expected: "aliased key".to_string(),
+                    got: format!("{:?}", self.current_kind()),
+                }),
+            };
+            self.advance();
+            self.aliases.insert(alias, key);
+
+            if !self.check(TokenKind::Comma) {
+                return Ok(());
+            }
+            self.advance();
+        }
+    }
+
     // =========================================================================
     // Include Directive
     // =========================================================================
@@ -244,12 +534,38 @@ impl Parser {
             ));
         }
 
-        // Read and parse the included file
-        let content = std::fs::read_to_string(&include_path)
+        // Read and parse the included file, within the policy's limits
+        let policy = &self.includes;
+        if self.included.0 >= policy.max_files {
+            return Err(Error::LimitExceeded(format!("include of {} exceeds {} included files", path_str, policy.max_files)));
+        }
+        policy.check_path(&path_str, &canonical)?;
+        let size = std::fs::metadata(&include_path).map(|m| m.len()).unwrap_or(0);
+        if size > policy.max_bytes - self.included.1 {
+            return Err(Error::LimitExceeded(format!("include of {} exceeds {} bytes of included files", path_str, policy.max_bytes)));
+        }
+        let bytes = std::fs::read(&include_path)
+            .map_err(|e| Error::ParseError(format!("Failed to include {}: {}", path_str, e)))?;
+        let size = bytes.len() as u64;
+        if size > policy.max_bytes - self.included.1 {
+            return Err(Error::LimitExceeded(format!("include of {} exceeds {} bytes of included files", path_str, policy.max_bytes)));
+        }
+        // Compressed includes count at their decompressed size, and stop
+        // decompressing once they outgrow what is left of the budget.
+        let bytes = crate::compressed::decompress_text(bytes, policy.max_bytes - self.included.1).map_err(|e| match e {
+            Error::LimitExceeded(_) => Error::LimitExceeded(format!("include of {} exceeds {} bytes of included files", path_str, policy.max_bytes)),
+            e => Error::ParseError(format!("Failed to include {}: {}", path_str, e)),
+        })?;
+        self.included = (self.included.0 + 1, self.included.1 + bytes.len() as u64);
+        let (content, _) = crate::encoding::decode_text(&bytes, false)
             .map_err(|e| Error::ParseError(format!("Failed to include {}: {}", path_str, e)))?;
 
-        let tokens = Lexer::new(&content).tokenize()?;
-        let mut parser = Parser::new(tokens);
+        let tokens = Lexer::new(&content).with_timestamps(self.timestamps).tokenize()?;
+        let mut parser = Parser::new(tokens)
+            .with_timestamps(self.timestamps)
+            .with_duplicates(self.duplicates)
+            .with_includes(self.includes.clone());
+        parser.included = self.included;
         if let Some(parent) = include_path.parent() {
             parser.base_path = Some(parent.to_path_buf());
         }
@@ -261,6 +577,7 @@ impl Parser {
         parser.unions = self.unions.clone();
 
         let data = parser.parse()?;
+        self.included = parser.included;
 
         // Merge schemas and unions
         for (name, schema) in parser.schemas {
@@ -269,6 +586,7 @@ impl Parser {
         for (name, union_type) in parser.unions {
             self.unions.insert(name, union_type);
         }
+        self.bindings.extend(parser.bindings);
 
         Ok(data)
     }
@@ -283,12 +601,22 @@ impl Parser {
             type_str.push_str("[]");
         }
 
-        // Base type
+        // Base type; a quoted name is always a struct or union name
+        if let TokenKind::String(name) = self.current_kind() {
+            let mut field_type = FieldType::new(name.clone());
+            field_type.is_array = !type_str.is_empty();
+            self.advance();
+            if self.check(TokenKind::Question) {
+                self.advance();
+                field_type.nullable = true;
+            }
+            return Ok(field_type);
+        }
         let base = self.expect_word()?;
 
         // Reject value-only types that cannot be schema field types (spec §2.1)
         match base.as_str() {
-            "object" | "map" | "tuple" | "ref" | "tagged" => {
+            "object" | "tuple" | "ref" | "tagged" => {
                 return Err(Error::ParseError(
                     format!("'{}' is a value type and cannot be used as a schema field type", base)
                 ));
@@ -313,7 +641,8 @@ impl Parser {
 
     fn parse_pair(&mut self, depth: usize) -> Result<(String, Value)> {
         let key = match self.current_kind() {
-            TokenKind::Word(w) => w.clone(),
+            // Quoted keys are always literal, so any key can be written
+            TokenKind::Word(w) => self.aliases.get(w).unwrap_or(w).clone(),
             TokenKind::String(s) => s.clone(),
             _ => return Err(Error::UnexpectedToken {
                 expected: "key".to_string(),
@@ -339,6 +668,7 @@ impl Parser {
             TokenKind::Bool(b) => { let b = *b; self.advance(); Ok(Value::Bool(b)) }
             TokenKind::Int(i) => { let i = *i; self.advance(); Ok(Value::Int(i)) }
             TokenKind::UInt(u) => { let u = *u; self.advance(); Ok(Value::UInt(u)) }
+            TokenKind::Size(n) | TokenKind::Duration(n) => { let n = *n; self.advance(); Ok(Value::Int(n)) }
             TokenKind::JsonNumber(s) => { let s = s.clone(); self.advance(); Ok(Value::JsonNumber(s)) }
             TokenKind::Float(f) => { let f = *f; self.advance(); Ok(Value::Float(f)) }
             TokenKind::String(s) => { let s = s.clone(); self.advance(); Ok(Value::String(s)) }
@@ -346,6 +676,7 @@ impl Parser {
             TokenKind::Word(w) => { let w = w.clone(); self.advance(); Ok(Value::String(w)) }
             TokenKind::Ref(r) => { let r = r.clone(); self.advance(); Ok(Value::Ref(r)) }
             TokenKind::Timestamp(ts, tz) => { let ts = *ts; let tz = *tz; self.advance(); Ok(Value::Timestamp(ts, tz)) }
+            TokenKind::TimestampNanos(ns, tz, p) => { let (ns, tz, p) = (*ns, *tz, *p); self.advance(); Ok(Value::TimestampNanos(ns, tz, p)) }
             TokenKind::Colon => {
                 self.advance(); // consume ':'
                 match self.current_kind() {
@@ -379,6 +710,8 @@ impl Parser {
     fn parse_directive_value(&mut self, directive: &str, depth: usize) -> Result<Value> {
         match directive {
             "table" => self.parse_table(depth),
+            "as" => self.parse_as(depth),
+            "row" => self.parse_row(depth),
             "map" => self.parse_map(depth),
             _ => {
                 // Unknown directive in value position: consume argument, return null (spec §1.18)
@@ -398,12 +731,15 @@ impl Parser {
                 | TokenKind::Bool(_)
                 | TokenKind::Int(_)
                 | TokenKind::UInt(_)
+                | TokenKind::Size(_)
+                | TokenKind::Duration(_)
                 | TokenKind::Float(_)
                 | TokenKind::String(_)
                 | TokenKind::Bytes(_)
                 | TokenKind::Word(_)
                 | TokenKind::Ref(_)
                 | TokenKind::Timestamp(_, _)
+                | TokenKind::TimestampNanos(_, _, _)
                 | TokenKind::JsonNumber(_)
                 | TokenKind::Colon
                 | TokenKind::Directive(_)
@@ -413,7 +749,17 @@ impl Parser {
         )
     }
 
+    /// `@map {k: v, ...}`, or `@map name {k: (...), ...}` whose values are
+    /// tuples of struct type `name`.
     fn parse_map(&mut self, depth: usize) -> Result<Value> {
+        let schema = match self.current_kind() {
+            TokenKind::Word(name) | TokenKind::String(name) => {
+                let name = name.clone();
+                self.advance();
+                Some(self.schemas.get(&name).ok_or(Error::UnknownStruct(name))?.clone())
+            }
+            _ => None,
+        };
         self.expect(TokenKind::LBrace)?;
         let mut pairs = Vec::new();
 
@@ -432,7 +778,10 @@ impl Parser {
             };
 
             self.expect(TokenKind::Colon)?;
-            let value = self.parse_value(depth + 1)?;
+            let value = match &schema {
+                Some(schema) => self.parse_tuple_with_schema(schema, depth + 1)?,
+                None => self.parse_value(depth + 1)?,
+            };
             pairs.push((key, value));
 
             if self.check(TokenKind::Comma) {
@@ -445,7 +794,7 @@ impl Parser {
     }
 
     fn parse_table(&mut self, depth: usize) -> Result<Value> {
-        let struct_name = self.expect_word()?;
+        let struct_name = self.expect_type_name()?;
         let schema = self.schemas
             .get(&struct_name)
             .ok_or_else(|| Error::UnknownStruct(struct_name.clone()))?
@@ -466,10 +815,36 @@ impl Parser {
         Ok(Value::Array(rows))
     }
 
+    /// `@as name {...}`: an object explicitly of struct type `name`.
+    fn parse_as(&mut self, depth: usize) -> Result<Value> {
+        let struct_name = self.expect_type_name()?;
+        if !self.schemas.contains_key(&struct_name) {
+            return Err(Error::UnknownStruct(struct_name));
+        }
+        if !self.check(TokenKind::LBrace) {
+            return Err(Error::UnexpectedToken {
+                expected: format!("object after @as {}", struct_name),
+                got: format!("{:?}", self.current_kind()),
+            });
+        }
+        self.parse_object(depth + 1)
+    }
+
+    /// `@row name (...)`: an object of struct type `name`, written as one
+    /// tuple of its fields.
+    fn parse_row(&mut self, depth: usize) -> Result<Value> {
+        let struct_name = self.expect_type_name()?;
+        let schema = self.schemas
+            .get(&struct_name)
+            .ok_or(Error::UnknownStruct(struct_name))?
+            .clone();
+        self.parse_tuple_with_schema(&schema, depth + 1)
+    }
+
     fn parse_tuple_with_schema(&mut self, schema: &Schema, depth: usize) -> Result<Value> {
         self.expect(TokenKind::LParen)?;
 
-        let mut obj = ObjectMap::new();
+        let mut obj = ObjectMap::with_capacity(schema.fields.len());
         for field in &schema.fields {
             let is_explicit_null = self.check(TokenKind::ExplicitNull);
             let value = self.parse_value_for_field(&field.field_type, depth)?;
@@ -488,6 +863,15 @@ impl Parser {
             }
         }
 
+        if !self.check(TokenKind::RParen) && !self.check(TokenKind::Eof) {
+            let tok = self.current();
+            return Err(Error::TooManyValues {
+                schema: schema.name.clone(),
+                fields: schema.fields.len(),
+                line: tok.line,
+                col: tok.col,
+            });
+        }
         self.expect(TokenKind::RParen)?;
         Ok(Value::Object(obj))
     }
@@ -524,6 +908,31 @@ impl Parser {
             return Ok(Value::Array(arr));
         }
 
+        // Secret field — unless a schema of that name shadows the type
+        if field_type.base == "secret" && !self.schemas.contains_key("secret") {
+            return match self.parse_value(depth)? {
+                Value::String(s) => Ok(Value::Secret(s.into())),
+                Value::Bytes(b) => Ok(Value::Secret(b.into())),
+                other => Err(Error::ParseError(format!(
+                    "secret field expects a string or bytes, got {:?}", other.tl_type()
+                ))),
+            };
+        }
+
+        // Size and duration fields reject literals of the other kind
+        let given = match self.current_kind() {
+            TokenKind::Size(_) => Some(Quantity::Size),
+            TokenKind::Duration(_) => Some(Quantity::Duration),
+            _ => None,
+        };
+        if let (Some(expected), Some(given)) = (Quantity::for_field(&field_type.base), given) {
+            if given != expected && !self.schemas.contains_key(&field_type.base) {
+                return Err(Error::ParseError(format!(
+                    "{} field expects a {}, got a {}", field_type.base, expected.name(), given.name()
+                )));
+            }
+        }
+
         // Regular value
         self.parse_value(depth)
     }
@@ -533,16 +942,19 @@ impl Parser {
         let mut obj = ObjectMap::new();
 
         while !self.check(TokenKind::RBrace) {
-            if let TokenKind::Ref(r) = self.current_kind() {
+            let (line, col) = (self.current().line, self.current().col);
+            let (key, value) = if let TokenKind::Ref(r) = self.current_kind() {
                 let key = format!("!{}", r);
                 self.advance();
                 self.expect(TokenKind::Colon)?;
-                let value = self.parse_value(depth)?;
-                obj.insert(key, value);
+                (key, self.parse_value(depth)?)
             } else {
-                let (key, value) = self.parse_pair(depth)?;
-                obj.insert(key, value);
+                self.parse_pair(depth)?
+            };
+            if self.duplicates == DuplicatePolicy::Error && obj.contains_key(&key) {
+                return Err(Error::DuplicateKey { key, line, col });
             }
+            obj.insert(key, value);
             if self.check(TokenKind::Comma) {
                 self.advance();
             }
@@ -634,11 +1046,36 @@ impl Parser {
         }
     }
 
+    /// A struct or union name: a word, or a quoted string for names that
+    /// are not plain identifiers, such as `"my-schema"`.
+    fn expect_type_name(&mut self) -> Result<String> {
+        match self.current_kind() {
+            TokenKind::String(s) if s.is_empty() => Err(Error::ParseError("type name is empty".to_string())),
+            TokenKind::String(s) => {
+                let s = s.clone();
+                self.advance();
+                Ok(s)
+            }
+            _ => self.expect_word(),
+        }
+    }
+
     fn at_end(&self) -> bool {
         matches!(self.current_kind(), TokenKind::Eof)
     }
 }
 
+impl Value {
+    /// Parse one TeaLeaf value, such as `[1, 2]`, `:ok ~` or
+    /// `@map {1: one}`, rather than a whole document. Nothing but
+    /// whitespace and comments may follow it. Schemas are not available,
+    /// so `@table` and schema-typed `@map` values fail; references are
+    /// kept as [`Value::Ref`].
+    pub fn parse_literal(input: &str) -> Result<Value> {
+        Parser::new(Lexer::new(input).tokenize()?).parse_literal()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -722,6 +1159,21 @@ mod tests {
         assert_eq!(shape.variants[2].fields.len(), 0);
     }
 
+    #[test]
+    fn test_parse_literal() {
+        let m = Value::parse_literal("@map{1: one}").unwrap();
+        assert_eq!(m, Value::Map(vec![(Value::Int(1), Value::String("one".into()))]));
+        assert_eq!(Value::parse_literal("  [1, \"a\"]  # trailing comment\n").unwrap(),
+            Value::Array(vec![Value::Int(1), Value::String("a".into())]));
+        assert_eq!(Value::parse_literal(":ok ~").unwrap(), Value::Tagged("ok".into(), Box::new(Value::Null)));
+        assert_eq!(Value::parse_literal("!base").unwrap(), Value::Ref("base".into()));
+
+        assert!(matches!(Value::parse_literal("1 2"), Err(Error::UnexpectedToken { expected, .. }) if expected == "end of input"));
+        assert!(Value::parse_literal("").is_err());
+        assert!(Value::parse_literal("[1,").is_err());
+        assert!(matches!(Value::parse_literal("@map point {1: (2)}"), Err(Error::UnknownStruct(_))));
+    }
+
     // -------------------------------------------------------------------------
     // Map parsing
     // -------------------------------------------------------------------------
@@ -744,6 +1196,80 @@ mod tests {
         assert_eq!(m.len(), 2);
     }
 
+    #[test]
+    fn test_duplicate_keys_and_extra_values() {
+        let input = "@struct p (x: int, meta: any)\nps: @table p [\n  (1, {a: 1}),\n  (2, {a: 1, b: 2, a: 3})\n]";
+        match parse(input) {
+            Err(Error::DuplicateKey { key, line, col }) => assert_eq!((key.as_str(), line, col), ("a", 4, 20)),
+            other => panic!("expected DuplicateKey, got {:?}", other),
+        }
+        let tokens = Lexer::new(input).tokenize().unwrap();
+        let data = Parser::new(tokens).with_duplicates(DuplicatePolicy::LastWins).parse().unwrap();
+        let meta = data["ps"].as_array().unwrap()[1].as_object().unwrap()["meta"].as_object().unwrap();
+        let entries: Vec<_> = meta.iter().map(|(k, v)| (k.as_str(), v.as_int().unwrap())).collect();
+        assert_eq!(entries, [("a", 3), ("b", 2)]);
+
+        assert!(matches!(parse("o: {!r: 1, !r: 2}"), Err(Error::DuplicateKey { key, .. }) if key == "!r"));
+
+        let err = parse("@struct p (x: int, y: int)\nps: @table p [(1, 2), (3, 4, 5)]").unwrap_err();
+        assert!(matches!(err, Error::TooManyValues { ref schema, fields: 2, line: 2, col: 30 } if schema == "p"), "{:?}", err);
+        assert_eq!(err.to_string(), "Too many values for 'p' (2 fields) at line 2, column 30");
+        // Nested struct tuples are checked the same way
+        assert!(matches!(
+            parse("@struct a (v: int)\n@struct b (a: a)\nbs: @table b [((1, 2))]"),
+            Err(Error::TooManyValues { schema, .. }) if schema == "a"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_field_names() {
+        let err = parse("@struct p (x: int,\n  y: int, x: string)\nps: @table p [(1, 2, a)]").unwrap_err();
+        assert!(matches!(err, Error::InvalidFieldName { ref definition, ref field, line: 2, col: 11, .. }
+            if definition == "p" && field == "x"), "{:?}", err);
+        assert_eq!(err.to_string(), "Invalid field name 'x' in 'p' at line 2, column 11: duplicate field name");
+
+        for (input, field, reason) in [
+            ("@struct p (\"\": int)", "", "empty"),
+            ("@struct p (ok, true: bool)", "true", "reserved literal"),
+            ("@struct p (null)", "null", "reserved literal"),
+            ("@struct p (~: int)", "~", "reserved literal"),
+            ("@struct p (inf: float)", "inf", "reserved literal"),
+            ("@union shape {circle (r: float, r: float)}", "r", "duplicate"),
+            ("@union shape {circle (NaN: float)}", "NaN", "reserved literal"),
+        ] {
+            match parse(input) {
+                Err(Error::InvalidFieldName { field: f, reason: r, .. }) => {
+                    assert_eq!(f, field, "{}", input);
+                    assert!(r.contains(reason), "{}: {}", input, r);
+                }
+                other => panic!("{}: expected InvalidFieldName, got {:?}", input, other),
+            }
+        }
+        let err = parse("@union shape {circle (r: float), box (w: int, w: int)}").unwrap_err();
+        assert!(err.to_string().contains("in 'shape.box'"), "{}", err);
+
+        // Quoted, reserved words are plain names, and are written back quoted
+        let doc = crate::TeaLeaf::parse(
+            "@union flag {set (\"true\": bool)}\n@struct p (\"null\": int, \"false\": flag)\nps: @table p [(1, :set (true))]",
+        ).unwrap();
+        assert_eq!(doc.get_path("ps[0].null"), Some(&Value::Int(1)));
+        let text = doc.to_tl_with_schemas();
+        assert!(text.contains("set (\"true\": bool)"), "{}", text);
+        assert_eq!(crate::TeaLeaf::parse(&text).unwrap().data, doc.data);
+    }
+
+    #[test]
+    fn test_map_with_struct_values() {
+        let data = parse("@struct user (name: string, age: int?)\nusers: @map user {17: (alice, 30), 42: (bob, ~)}").unwrap();
+        let pairs = data.get("users").unwrap().as_map().unwrap();
+        assert_eq!(pairs[0].0, Value::Int(17));
+        assert_eq!(pairs[0].1.as_object().unwrap().get("age"), Some(&Value::Int(30)));
+        assert!(!pairs[1].1.as_object().unwrap().contains_key("age"));
+
+        assert!(matches!(parse("m: @map nope {1: (a)}"), Err(Error::UnknownStruct(n)) if n == "nope"));
+        assert!(parse("@struct p (x: int)\nm: @map p {1: {x: 1}}").is_err());
+    }
+
     #[test]
     fn test_map_empty() {
         let data = parse("m: @map {}").unwrap();
@@ -882,6 +1408,16 @@ mod tests {
         assert!(parser.is_root_array());
     }
 
+    #[test]
+    fn test_root_primitive_directive() {
+        let input = "@root-primitive\nroot: 42";
+        let tokens = Lexer::new(input).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.parse().unwrap();
+        assert!(parser.is_root_primitive());
+        assert!(!parser.is_root_array());
+    }
+
     // -------------------------------------------------------------------------
     // Ref key at top level
     // -------------------------------------------------------------------------
@@ -1096,6 +1632,45 @@ mod tests {
         std::fs::remove_file(&file_b).ok();
     }
 
+    #[test]
+    fn test_include_policy() {
+        let dir = std::env::temp_dir().join("tealeaf_include_policy");
+        let tenant = dir.join("tenant");
+        std::fs::create_dir_all(tenant.join("shared")).unwrap();
+        std::fs::write(dir.join("secret.tl"), "secret: 1").unwrap();
+        std::fs::write(tenant.join("shared/base.tl"), "base: 1").unwrap();
+        std::fs::write(tenant.join("shared/nested.tl"), "@include \"base.tl\"\nnested: 2").unwrap();
+
+        let load = |text: &str, policy: IncludePolicy| {
+            let main = tenant.join("main.tl");
+            std::fs::write(&main, text).unwrap();
+            let tokens = Lexer::new(text).tokenize().unwrap();
+            Parser::new(tokens).with_base_path(&main).with_includes(policy).parse()
+        };
+        let sandbox = IncludePolicy::sandboxed(&tenant);
+
+        let data = load("@include \"shared/nested.tl\"\nmain: 3", sandbox.clone()).unwrap();
+        assert_eq!(data.keys().collect::<Vec<_>>(), ["base", "nested", "main"]);
+        assert!(load("@include \"../secret.tl\"", IncludePolicy::default()).is_ok());
+
+        let err = load("@include \"../secret.tl\"", sandbox.clone()).unwrap_err();
+        assert_eq!(err.to_string(), "Parse error: Include of ../secret.tl denied: '..' is not allowed");
+        let absolute = dir.join("secret.tl").display().to_string();
+        let err = load(&format!("@include \"{}\"", absolute.replace('\\', "/")), sandbox.clone()).unwrap_err();
+        assert!(err.to_string().contains("absolute paths are not allowed"), "{}", err);
+        let outside = IncludePolicy { allow_parent: true, ..sandbox.clone() };
+        let err = load("@include \"../secret.tl\"", outside).unwrap_err();
+        assert!(err.to_string().contains("outside the allowed directories"), "{}", err);
+
+        let err = load("@include \"shared/nested.tl\"", sandbox.clone().with_max_files(1)).unwrap_err();
+        assert_eq!(err.to_string(), "Limit exceeded: include of base.tl exceeds 1 included files");
+        let err = load("@include \"shared/nested.tl\"", sandbox.clone().with_max_bytes(30)).unwrap_err();
+        assert!(matches!(err, Error::LimitExceeded(_)), "{}", err);
+        assert!(load("@include \"shared/base.tl\"", IncludePolicy::deny()).is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
     #[test]
     fn test_include_stack_propagated_to_child() {
         // Verify that the include_stack starts empty
@@ -1163,7 +1738,7 @@ mod tests {
         for i in 0..depth {
             input.push_str(&format!("{{k{}: ", i));
         }
-        input.push_str("1");
+        input.push('1');
         for _ in 0..depth {
             input.push('}');
         }
@@ -1213,8 +1788,8 @@ mod tests {
 
     #[test]
     fn test_reject_value_only_schema_field_types() {
-        // Spec §2.1: object, map, tuple, ref, tagged are value types, not schema field types
-        for bad_type in &["object", "map", "tuple", "ref", "tagged"] {
+        // Spec §2.1: object, tuple, ref, tagged are value types, not schema field types
+        for bad_type in &["object", "tuple", "ref", "tagged"] {
             let input = format!("@struct Bad (field: {})\n", bad_type);
             let result = crate::TeaLeaf::parse(&input);
             assert!(result.is_err(), "should reject '{}' as schema field type", bad_type);
@@ -1248,4 +1823,16 @@ mod tests {
         assert_eq!(second.get("@type").unwrap().as_str(), Some("B"));
         assert_eq!(second.get("name").unwrap().as_str(), Some("y"));
     }
+
+    #[test]
+    fn test_parse_lenient_recovers_at_sections() {
+        let input = "a: 1\nb: [1, 2\nc: {x: 1}\n@struct p (x: int, y\nd: @table p [(1, 2)]\ne: true\n";
+        let tokens = Lexer::new(input).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let (data, errors) = parser.parse_lenient(&[]);
+        assert_eq!(data.keys().collect::<Vec<_>>(), vec!["a", "c", "e"]);
+        // The unclosed array and struct, then the table of the undefined struct
+        assert_eq!(errors.iter().map(|(line, _)| *line).collect::<Vec<_>>(), vec![3, 5, 5]);
+        assert!(matches!(errors[2].1, Error::UnknownStruct(_)));
+    }
 }
@@ -0,0 +1,532 @@
+//! Composable document transformations.
+//!
+//! A [`Pipeline`] runs a list of [`Pass`]es over a document in order and
+//! reports what each one changed. Pipelines can be built in Rust or read from
+//! a TeaLeaf value, which is how the CLI `transform` command takes them:
+//!
+//! ```text
+//! passes: [
+//!     resolve_refs,
+//!     coerce,
+//!     {pass: redact, fields: [password, ssn]},
+//!     {pass: sort_table, table: users, by: id, descending: true},
+//!     compact,
+//! ]
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+
+use indexmap::IndexMap;
+
+use crate::types::{canonical_float, value_kind};
+use crate::{BoundObjects, Error, FieldType, ObjectMap, Result, TeaLeaf, Value};
+
+/// One transformation of a document's data.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pass {
+    /// Replace each `!name` reference with a copy of the value defined by
+    /// `!name: value` at the top level, or else of the top-level key `name`.
+    /// References to missing keys are left in place and reported; a
+    /// reference cycle is an error.
+    ResolveRefs,
+    /// Convert field values of schema-bound objects to the declared type
+    /// where no information is lost (`"42"` or `42.0` to an `int` field, `7`
+    /// to a `float` field, ...). Values that cannot be converted are reported.
+    CoerceToSchema,
+    /// Replace the value of every object field with one of these names.
+    Redact { fields: Vec<String>, replacement: Value },
+    /// Stable-sort the rows of the table under `table` by field `by`. Rows
+    /// without the field sort last.
+    SortTable { table: String, by: String, descending: bool },
+    /// Drop null-valued fields from objects.
+    Compact,
+}
+
+impl Pass {
+    /// The name of the pass in declarative descriptions and reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pass::ResolveRefs => "resolve_refs",
+            Pass::CoerceToSchema => "coerce",
+            Pass::Redact { .. } => "redact",
+            Pass::SortTable { .. } => "sort_table",
+            Pass::Compact => "compact",
+        }
+    }
+
+    /// Read a pass from its declarative form: a bare name, or an object with
+    /// a `pass` name and the pass's options.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let (name, options) = match value {
+            Value::String(name) => (name.as_str(), None),
+            Value::Object(obj) => match obj.get("pass").and_then(Value::as_str) {
+                Some(name) => (name, Some(obj)),
+                None => return Err(Error::MissingField("pass".to_string())),
+            },
+            other => return Err(Error::ParseError(format!("expected a pass name or object, got {}", value_kind(other)))),
+        };
+        let option = |key: &str| options.and_then(|obj| obj.get(key));
+        let string = |key: &str| match option(key) {
+            Some(Value::String(s)) => Ok(s.clone()),
+            Some(other) => Err(Error::ParseError(format!("'{}' of {} must be a string, got {}", key, name, value_kind(other)))),
+            None => Err(Error::MissingField(format!("{}.{}", name, key))),
+        };
+
+        Ok(match name {
+            "resolve_refs" => Pass::ResolveRefs,
+            "coerce" => Pass::CoerceToSchema,
+            "compact" => Pass::Compact,
+            "redact" => {
+                let fields = match option("fields") {
+                    Some(Value::Array(items)) => items.iter()
+                        .map(|v| v.as_str().map(str::to_string)
+                            .ok_or_else(|| Error::ParseError("redact fields must be strings".to_string())))
+                        .collect::<Result<Vec<_>>>()?,
+                    _ => return Err(Error::MissingField("redact.fields".to_string())),
+                };
+                let replacement = option("with").cloned()
+                    .unwrap_or_else(|| Value::String(crate::env::REDACTED.to_string()));
+                Pass::Redact { fields, replacement }
+            }
+            "sort_table" => Pass::SortTable {
+                table: string("table")?,
+                by: string("by")?,
+                descending: option("descending").and_then(Value::as_bool).unwrap_or(false),
+            },
+            other => return Err(Error::ParseError(format!("unknown pass '{}'", other))),
+        })
+    }
+
+    fn apply(&self, doc: &TeaLeaf, data: &mut IndexMap<String, Value>, report: &mut PassReport) -> Result<()> {
+        match self {
+            Pass::ResolveRefs => {
+                let snapshot = data.clone();
+                for (key, value) in data.iter_mut() {
+                    let mut stack = vec![key.clone()];
+                    resolve_refs(value, &snapshot, &mut stack, report)?;
+                }
+            }
+            Pass::CoerceToSchema => coerce_to_schemas(doc, data, report),
+            Pass::Redact { fields, replacement } => {
+                let fields: HashSet<&str> = fields.iter().map(|f| f.as_str()).collect();
+                for value in data.values_mut() {
+                    redact(value, &fields, replacement, report);
+                }
+            }
+            Pass::SortTable { table, by, descending } => {
+                let rows = match data.get_mut(table) {
+                    Some(Value::Array(rows)) => rows,
+                    Some(_) => return Err(Error::ParseError(format!("'{}' is not a table", table))),
+                    None => return Err(Error::MissingField(table.clone())),
+                };
+                let before: Vec<Value> = rows.clone();
+                rows.sort_by(|a, b| {
+                    let (a, b) = (a.get(by).filter(|v| !v.is_null()), b.get(by).filter(|v| !v.is_null()));
+                    match (a, b) {
+                        (Some(a), Some(b)) if *descending => compare(b, a),
+                        (Some(a), Some(b)) => compare(a, b),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    }
+                });
+                report.changed += rows.iter().zip(&before).filter(|(a, b)| a != b).count();
+            }
+            Pass::Compact => {
+                for value in data.values_mut() {
+                    compact(value, report);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An ordered list of passes.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pipeline {
+    pub passes: Vec<Pass>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a pass.
+    pub fn pass(mut self, pass: Pass) -> Self {
+        self.passes.push(pass);
+        self
+    }
+
+    /// Read a pipeline from an array of passes in declarative form (see
+    /// [`Pass::from_value`]).
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let items = value.as_array()
+            .ok_or_else(|| Error::ParseError(format!("pipeline must be an array of passes, got {}", value_kind(value))))?;
+        let passes = items.iter().enumerate()
+            .map(|(i, item)| Pass::from_value(item).map_err(|e| Error::ParseError(format!("pass {}: {}", i, e))))
+            .collect::<Result<_>>()?;
+        Ok(Self { passes })
+    }
+
+    /// Run every pass over `doc` in order.
+    ///
+    /// The passes work on a copy of the data, so on error the document is
+    /// unchanged.
+    pub fn run(&self, doc: &mut TeaLeaf) -> Result<PipelineReport> {
+        let mut data = doc.data.clone();
+        let mut report = PipelineReport::default();
+        for pass in &self.passes {
+            let mut pass_report = PassReport { pass: pass.name(), changed: 0, notes: Vec::new() };
+            pass.apply(doc, &mut data, &mut pass_report)
+                .map_err(|e| Error::ParseError(format!("{}: {}", pass.name(), e)))?;
+            report.passes.push(pass_report);
+        }
+        doc.data = data;
+        Ok(report)
+    }
+}
+
+/// What one pass of a pipeline changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassReport {
+    pub pass: &'static str,
+    /// Number of values the pass replaced, moved or removed
+    pub changed: usize,
+    /// Things the pass left alone and why
+    pub notes: Vec<String>,
+}
+
+/// The reports of every pass of a pipeline run, in order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PipelineReport {
+    pub passes: Vec<PassReport>,
+}
+
+impl PipelineReport {
+    /// Total number of values changed by all passes.
+    pub fn changed(&self) -> usize {
+        self.passes.iter().map(|p| p.changed).sum()
+    }
+}
+
+impl fmt::Display for PipelineReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pass in &self.passes {
+            writeln!(f, "{}: {} changed", pass.pass, pass.changed)?;
+            for note in &pass.notes {
+                writeln!(f, "  {}", note)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Passes
+// =============================================================================
+
+fn resolve_refs(value: &mut Value, data: &IndexMap<String, Value>, stack: &mut Vec<String>, report: &mut PassReport) -> Result<()> {
+    match value {
+        Value::Ref(name) => {
+            if stack.contains(name) {
+                return Err(Error::ParseError(format!("reference cycle through '!{}'", name)));
+            }
+            // A `!name: value` definition, or else a plain top-level key
+            match data.get(&format!("!{}", name)).or_else(|| data.get(name.as_str())) {
+                Some(target) => {
+                    let mut target = target.clone();
+                    stack.push(name.clone());
+                    resolve_refs(&mut target, data, stack, report)?;
+                    stack.pop();
+                    *value = target;
+                    report.changed += 1;
+                }
+                None => report.notes.push(format!("unresolved reference '!{}'", name)),
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                resolve_refs(item, data, stack, report)?;
+            }
+        }
+        Value::Object(obj) => {
+            for v in obj.values_mut() {
+                resolve_refs(v, data, stack, report)?;
+            }
+        }
+        Value::Map(pairs) => {
+            for (_, v) in pairs {
+                resolve_refs(v, data, stack, report)?;
+            }
+        }
+        Value::Tagged(_, inner) => resolve_refs(inner, data, stack, report)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Result of coercing one value to a field's base type.
+enum Coerced {
+    Keep,
+    Into(Value),
+    Mismatch,
+}
+
+fn coerce_to_schemas(doc: &TeaLeaf, data: &mut IndexMap<String, Value>, report: &mut PassReport) {
+    let schemas = &doc.schemas;
+    let changed = Cell::new(0);
+    // Mismatches are counted per field and value kind rather than listed per row
+    let mismatches: RefCell<IndexMap<(String, &'static str), usize>> = RefCell::new(IndexMap::new());
+
+    for schema in schemas.values() {
+        let edit = |obj: &mut ObjectMap<String, Value>| {
+            for field in &schema.fields {
+                if let Some(value) = obj.get_mut(&field.name) {
+                    coerce_field(value, &field.field_type, &mut |found| {
+                        *mismatches.borrow_mut().entry((format!("{}.{}", schema.name, field.name), found)).or_insert(0) += 1;
+                    }, &changed);
+                }
+            }
+        };
+        let bound = BoundObjects { schemas, target: &schema.name, edit: &edit };
+        for (key, value) in data.iter_mut() {
+            bound.value(value, doc.binding(key), Some(key));
+        }
+    }
+
+    report.changed += changed.get();
+    for ((field, found), count) in mismatches.into_inner() {
+        report.notes.push(format!("{}: {} {} value(s) left unconverted", field, count, found));
+    }
+}
+
+fn coerce_field(value: &mut Value, field_type: &FieldType, mismatch: &mut dyn FnMut(&'static str), changed: &Cell<usize>) {
+    match value {
+        Value::Array(items) if field_type.is_array => {
+            for item in items {
+                coerce_field(item, &FieldType::new(field_type.base.clone()), mismatch, changed);
+            }
+        }
+        _ => match coerce(value, &field_type.base) {
+            Coerced::Keep => {}
+            Coerced::Into(v) => {
+                *value = v;
+                changed.set(changed.get() + 1);
+            }
+            Coerced::Mismatch => mismatch(value_kind(value)),
+        },
+    }
+}
+
+fn coerce(value: &Value, base: &str) -> Coerced {
+    let family = match base {
+        "int" | "int8" | "int16" | "int32" | "int64" | "bytesize" | "duration" => 'i',
+        "uint" | "uint8" | "uint16" | "uint32" | "uint64" => 'u',
+        "float" | "float32" | "float64" => 'f',
+        "bool" => 'b',
+        "string" => 's',
+        // Structs, unions, timestamps, bytes and `any` are left alone
+        _ => return Coerced::Keep,
+    };
+    let into = |v: Option<Value>| v.map_or(Coerced::Mismatch, Coerced::Into);
+    match (family, value) {
+        // Nullability is not the pass's business
+        (_, Value::Null) => Coerced::Keep,
+        ('i', Value::Int(_)) | ('u', Value::UInt(_)) | ('f', Value::Float(_))
+        | ('b', Value::Bool(_)) | ('s', Value::String(_)) => Coerced::Keep,
+        ('u', Value::Int(i)) => if *i >= 0 { Coerced::Keep } else { Coerced::Mismatch },
+        ('i', Value::UInt(u)) => into(i64::try_from(*u).ok().map(Value::Int)),
+        ('i', Value::Float(f)) => into(whole(*f).filter(|_| f.abs() < 9.2e18).map(|f| Value::Int(f as i64))),
+        ('u', Value::Float(f)) => into(whole(*f).filter(|f| *f >= 0.0 && *f < 1.8e19).map(|f| Value::UInt(f as u64))),
+        ('f', Value::Int(i)) => into(Some(*i as f64).filter(|f| *f as i64 == *i).map(Value::Float)),
+        ('f', Value::UInt(u)) => into(Some(*u as f64).filter(|f| *f as u64 == *u).map(Value::Float)),
+        ('i', Value::String(s) | Value::JsonNumber(s)) => into(s.trim().parse().ok().map(Value::Int)),
+        ('u', Value::String(s) | Value::JsonNumber(s)) => into(s.trim().parse().ok().map(Value::UInt)),
+        ('f', Value::String(s) | Value::JsonNumber(s)) => into(s.trim().parse().ok().map(Value::Float)),
+        ('b', Value::String(s)) => into(match s.as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        }),
+        ('s', Value::Int(i)) => Coerced::Into(Value::String(i.to_string())),
+        ('s', Value::UInt(u)) => Coerced::Into(Value::String(u.to_string())),
+        ('s', Value::Float(f)) => Coerced::Into(Value::String(canonical_float(*f))),
+        ('s', Value::Bool(b)) => Coerced::Into(Value::String(b.to_string())),
+        _ => Coerced::Mismatch,
+    }
+}
+
+fn whole(f: f64) -> Option<f64> {
+    Some(f).filter(|f| f.is_finite() && f.fract() == 0.0)
+}
+
+fn redact(value: &mut Value, fields: &HashSet<&str>, replacement: &Value, report: &mut PassReport) {
+    match value {
+        Value::Object(obj) => {
+            for (k, v) in obj.iter_mut() {
+                if fields.contains(k.as_str()) {
+                    if v != replacement {
+                        *v = replacement.clone();
+                        report.changed += 1;
+                    }
+                } else {
+                    redact(v, fields, replacement, report);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| redact(v, fields, replacement, report)),
+        Value::Map(pairs) => pairs.iter_mut().for_each(|(_, v)| redact(v, fields, replacement, report)),
+        Value::Tagged(_, inner) => redact(inner, fields, replacement, report),
+        _ => {}
+    }
+}
+
+fn compact(value: &mut Value, report: &mut PassReport) {
+    match value {
+        Value::Object(obj) => {
+            let before = obj.len();
+            obj.retain(|_, v| !v.is_null());
+            report.changed += before - obj.len();
+            obj.values_mut().for_each(|v| compact(v, report));
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| compact(v, report)),
+        Value::Map(pairs) => pairs.iter_mut().for_each(|(_, v)| compact(v, report)),
+        Value::Tagged(_, inner) => compact(inner, report),
+        _ => {}
+    }
+}
+
+/// Order for sort keys: numbers by value, strings, bools and timestamps
+/// naturally, and different kinds by a fixed rank.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Bool(_) => 0,
+            Value::Int(_) | Value::UInt(_) | Value::Float(_) | Value::JsonNumber(_) => 1,
+            Value::String(_) => 2,
+            Value::Timestamp(..) | Value::TimestampNanos(..) => 3,
+            _ => 4,
+        }
+    }
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => x.cmp(y),
+        (Value::UInt(x), Value::UInt(y)) => x.cmp(y),
+        (Value::Int(x), Value::UInt(y)) => (*x as i128).cmp(&(*y as i128)),
+        (Value::UInt(x), Value::Int(y)) => (*x as i128).cmp(&(*y as i128)),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        (Value::Timestamp(x, _), Value::Timestamp(y, _)) => x.cmp(y),
+        (Value::TimestampNanos(x, _, _), Value::TimestampNanos(y, _, _)) => x.cmp(y),
+        _ if rank(a) == 1 && rank(b) == 1 => {
+            a.as_float().unwrap_or(f64::NAN).total_cmp(&b.as_float().unwrap_or(f64::NAN))
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"
+        @struct user (id: int, name: string, score: float, password: string?)
+        defaults: {theme: dark}
+        users: @table user [
+            (2, "bob", 7, "hunter2"),
+            (10, "carol", "8.5", ~),
+            (1, "alice", 9.5, "s3cret"),
+        ]
+        settings: {owner: {name: "alice", password: "pw"}, prefs: !defaults}
+    "#;
+
+    #[test]
+    fn test_pipeline_runs_passes_in_order() {
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        let report = Pipeline::new()
+            .pass(Pass::ResolveRefs)
+            .pass(Pass::CoerceToSchema)
+            .pass(Pass::Redact { fields: vec!["password".into()], replacement: Value::Null })
+            .pass(Pass::SortTable { table: "users".into(), by: "id".into(), descending: false })
+            .pass(Pass::Compact)
+            .run(&mut doc)
+            .unwrap();
+
+        assert_eq!(doc.get_path("settings.prefs.theme").and_then(Value::as_str), Some("dark"));
+        let ids: Vec<i64> = doc.get("users").unwrap().as_array().unwrap().iter()
+            .map(|u| u.get("id").unwrap().as_int().unwrap()).collect();
+        assert_eq!(ids, vec![1, 2, 10]);
+        assert_eq!(doc.get_path("users[1].score"), Some(&Value::Float(7.0)));
+        assert_eq!(doc.get_path("users[2].score"), Some(&Value::Float(8.5)));
+        assert!(doc.get_path("users[0].password").is_none());
+        assert!(doc.get_path("settings.owner.password").is_none());
+
+        let changed: Vec<(&str, usize)> = report.passes.iter().map(|p| (p.pass, p.changed)).collect();
+        assert_eq!(changed, vec![("resolve_refs", 1), ("coerce", 2), ("redact", 3), ("sort_table", 3), ("compact", 3)]);
+        assert!(report.to_string().starts_with("resolve_refs: 1 changed\n"));
+
+        // Still a valid document with the same schema
+        let text = doc.to_tl_with_schemas();
+        assert_eq!(TeaLeaf::parse(&text).unwrap().data, doc.data);
+    }
+
+    #[test]
+    fn test_pipeline_from_value() {
+        let spec = TeaLeaf::parse(r#"
+            passes: [
+                resolve_refs,
+                coerce,
+                {pass: redact, fields: [password], with: "***"},
+                {pass: sort_table, table: users, by: score, descending: true},
+            ]
+        "#).unwrap();
+        let pipeline = Pipeline::from_value(spec.get("passes").unwrap()).unwrap();
+        assert_eq!(pipeline.passes.len(), 4);
+        assert_eq!(pipeline.passes[2], Pass::Redact { fields: vec!["password".into()], replacement: Value::String("***".into()) });
+
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        pipeline.run(&mut doc).unwrap();
+        let names: Vec<&str> = doc.get("users").unwrap().as_array().unwrap().iter()
+            .map(|u| u.get("name").unwrap().as_str().unwrap()).collect();
+        assert_eq!(names, vec!["alice", "carol", "bob"]);
+        assert_eq!(doc.get_path("users[2].password").and_then(Value::as_str), Some("***"));
+
+        for bad in ["[shuffle]", "[{fields: [a]}]", "[{pass: sort_table, table: users}]", "{pass: coerce}"] {
+            assert!(Pipeline::from_value(&Value::parse_literal(bad).unwrap()).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_pipeline_reports_and_errors() {
+        let mut doc = TeaLeaf::parse(r#"
+            @struct point (x: int, y: int)
+            points: @table point [(1, 2), ("three", 4), (5.5, 6)]
+            a: !b
+            b: {next: !a}
+            c: !missing
+        "#).unwrap();
+        let before = doc.data.clone();
+
+        let err = Pipeline::new().pass(Pass::Compact).pass(Pass::ResolveRefs).run(&mut doc).unwrap_err();
+        assert!(err.to_string().contains("reference cycle"), "{}", err);
+        assert_eq!(doc.data, before);
+
+        let report = Pipeline::new().pass(Pass::CoerceToSchema).run(&mut doc).unwrap();
+        assert_eq!(report.changed(), 0);
+        assert_eq!(report.passes[0].notes, vec![
+            "point.x: 1 string value(s) left unconverted",
+            "point.x: 1 float value(s) left unconverted",
+        ]);
+
+        doc.data.shift_remove("a");
+        doc.data.shift_remove("b");
+        let report = Pipeline::new().pass(Pass::ResolveRefs).run(&mut doc).unwrap();
+        assert_eq!(report.passes[0].notes, vec!["unresolved reference '!missing'"]);
+        assert!(Pipeline::new().pass(Pass::SortTable { table: "c".into(), by: "x".into(), descending: false })
+            .run(&mut doc).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Progress reporting and cancellation for long operations.
+//!
+//! The `*_with_progress` variants of compiling, loading a binary file,
+//! converting JSON and inferring schemas take a [`Progress`]. It is told
+//! after each top-level section, and asked whether to stop before each one
+//! and, while tables and arrays are compiled, loaded or converted from JSON,
+//! every [`CHECK_ROWS`] rows; a cancelled operation returns
+//! [`Error::Cancelled`] and leaves no output behind.
+//!
+//! ```no_run
+//! use std::sync::atomic::AtomicBool;
+//! use tealeaf::{progress::Progress, TeaLeaf};
+//!
+//! let cancel = AtomicBool::new(false);  // set from another thread to stop
+//! let doc = TeaLeaf::load("big.tl")?;
+//! let mut progress = Progress::new()
+//!     .on_update(|u| println!("{}/{} {}", u.done, u.total, u.section))
+//!     .cancel_flag(&cancel);
+//! doc.compile_with_progress("big.tlbx", true, &mut progress)?;
+//! # Ok::<(), tealeaf::Error>(())
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(any(feature = "binary", feature = "json", feature = "infer"))]
+use crate::{Error, Result};
+
+/// The operation a [`ProgressUpdate`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Encoding sections for a binary file
+    Compile,
+    /// Decoding the sections of a binary file
+    Load,
+    /// Converting parsed JSON to values
+    FromJson,
+    /// Inferring schemas
+    Infer,
+}
+
+/// One finished section.
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressUpdate<'a> {
+    pub operation: Operation,
+    /// Key of the section just finished
+    pub section: &'a str,
+    /// Sections finished so far, this one included
+    pub done: usize,
+    pub total: usize,
+    /// Encoded size of the sections finished so far when compiling or
+    /// loading; 0 for other operations
+    pub bytes: u64,
+}
+
+impl ProgressUpdate<'_> {
+    /// Share of the sections finished, from 0 to 1.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        self.done as f64 / self.total as f64
+    }
+}
+
+/// Rows or array elements processed between cancellation checks within a
+/// section
+pub const CHECK_ROWS: usize = 1024;
+
+type UpdateFn<'a> = Box<dyn FnMut(&ProgressUpdate) + 'a>;
+type CancelFn<'a> = Box<dyn FnMut() -> bool + 'a>;
+
+/// Where progress is reported and cancellation is asked for.
+///
+/// `Progress::new()` does neither, so it can be passed where a
+/// `*_with_progress` method is needed but nobody is watching.
+#[derive(Default)]
+pub struct Progress<'a> {
+    on_update: Option<UpdateFn<'a>>,
+    cancelled: Option<CancelFn<'a>>,
+    /// Rows processed since the last check
+    #[cfg(any(feature = "binary", feature = "json"))]
+    rows: usize,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `f` after each section.
+    pub fn on_update(mut self, f: impl FnMut(&ProgressUpdate) + 'a) -> Self {
+        self.on_update = Some(Box::new(f));
+        self
+    }
+
+    /// Stop before the next section, or within [`CHECK_ROWS`] rows, once
+    /// `flag` is set.
+    pub fn cancel_flag(self, flag: &'a AtomicBool) -> Self {
+        self.cancel_when(move || flag.load(Ordering::Relaxed))
+    }
+
+    /// Stop before the next section, or within [`CHECK_ROWS`] rows, once
+    /// `f` returns `true`.
+    pub fn cancel_when(mut self, f: impl FnMut() -> bool + 'a) -> Self {
+        self.cancelled = Some(Box::new(f));
+        self
+    }
+
+    /// Fail with [`Error::Cancelled`] if cancellation was asked for.
+    #[cfg(any(feature = "binary", feature = "json", feature = "infer"))]
+    pub(crate) fn check(&mut self) -> Result<()> {
+        let cancelled = self.cancelled.as_mut().is_some_and(|cancelled| cancelled());
+        if cancelled {
+            return Err(Error::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Count one row, checking for cancellation every [`CHECK_ROWS`] rows.
+    #[cfg(any(feature = "binary", feature = "json"))]
+    pub(crate) fn tick(&mut self) -> Result<()> {
+        self.rows += 1;
+        if self.rows < CHECK_ROWS {
+            return Ok(());
+        }
+        self.rows = 0;
+        self.check()
+    }
+
+    #[cfg(any(feature = "binary", feature = "json", feature = "infer"))]
+    pub(crate) fn report(&mut self, update: ProgressUpdate) {
+        if let Some(on_update) = &mut self.on_update {
+            on_update(&update);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "text", feature = "binary", feature = "json", feature = "infer"))]
+mod tests {
+    use super::*;
+    use crate::{Reader, SchemaInferrer, TeaLeaf};
+
+    const JSON: &str = r#"{"a": [{"x": 1}, {"x": 2}], "b": {"y": true}, "c": [{"z": "s"}]}"#;
+
+    #[test]
+    fn test_reports_every_section() {
+        let mut seen = Vec::new();
+        let mut progress = Progress::new()
+            .on_update(|u| seen.push((u.operation, u.section.to_string(), u.done, u.total, u.bytes > 0)));
+        let doc = TeaLeaf::from_json_with_schemas_with_progress(JSON, &mut progress).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.tlbx");
+        doc.compile_with_progress(&path, false, &mut progress).unwrap();
+        let loaded = TeaLeaf::from_reader_with_progress(&Reader::open(&path).unwrap(), &mut progress).unwrap();
+        drop(progress);
+        assert_eq!(loaded.data, doc.data);
+
+        let expect = |op, bytes| ["a", "b", "c"].iter().enumerate().map(move |(i, k)| (op, k.to_string(), i + 1, 3, bytes));
+        let expected: Vec<_> = expect(Operation::FromJson, false)
+            .chain(expect(Operation::Infer, false))
+            .chain(expect(Operation::Compile, true))
+            .chain(expect(Operation::Load, true))
+            .collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn test_cancellation_stops_before_next_section() {
+        let cancel = AtomicBool::new(false);
+        let mut progress = Progress::new()
+            .on_update(|u| if u.section == "a" { cancel.store(true, Ordering::Relaxed) })
+            .cancel_flag(&cancel);
+        assert!(matches!(TeaLeaf::from_json_with_progress(JSON, &mut progress), Err(Error::Cancelled)));
+
+        // A cancelled compile leaves no file behind
+        let doc = TeaLeaf::from_json(JSON).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.tlbx");
+        let mut calls = 0;
+        let mut progress = Progress::new().cancel_when(|| { calls += 1; calls > 2 });
+        assert!(matches!(doc.compile_with_progress(&path, true, &mut progress), Err(Error::Cancelled)));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let mut progress = Progress::new().cancel_when(|| true);
+        assert!(matches!(SchemaInferrer::new().infer_with_progress(&doc.data, &mut progress), Err(Error::Cancelled)));
+        assert!(Progress::new().check().is_ok());
+    }
+
+    #[test]
+    fn test_cancellation_within_a_table() {
+        let rows: Vec<String> = (0..3 * CHECK_ROWS).map(|i| format!(r#"{{"x": {}}}"#, i)).collect();
+        let json = format!(r#"{{"rows": [{}]}}"#, rows.join(","));
+        // Only the check before the single section passes
+        let first_only = || {
+            let mut calls = 0;
+            Progress::new().cancel_when(move || { calls += 1; calls > 1 })
+        };
+        assert!(matches!(TeaLeaf::from_json_with_progress(&json, &mut first_only()), Err(Error::Cancelled)));
+
+        let doc = TeaLeaf::from_json_with_schemas(&json).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.tlbx");
+        assert!(matches!(doc.compile_with_progress(&path, false, &mut first_only()), Err(Error::Cancelled)));
+        doc.compile(&path, false).unwrap();
+        let reader = Reader::open(&path).unwrap();
+        assert!(matches!(TeaLeaf::from_reader_with_progress(&reader, &mut first_only()), Err(Error::Cancelled)));
+    }
+}
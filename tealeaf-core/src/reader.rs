@@ -6,17 +6,24 @@
 
 use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Read;
+#[cfg(feature = "json")]
+use std::io::Write;
+use std::ops::{Bound, Range, RangeBounds};
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 use indexmap::IndexMap;
+use crate::codec::SectionCodec;
+use crate::progress::Progress;
+use crate::convert::{FromTeaLeaf, ToTeaLeaf};
 use crate::types::ObjectMap;
 
 use memmap2::Mmap;
 
-use crate::{Error, Result, Value, Schema, Union, Variant, Field, FieldType, TLType, MAGIC, HEADER_SIZE};
+use crate::{Error, Result, Value, TimePrecision, Schema, SchemaRegistry, Union, Variant, Field, FieldType, TLType, MAGIC, HEADER_SIZE, UNION_FLAG_ORDINAL_TAGS, MAX_UNION_VARIANTS, TYPE_CODE_ANY};
 
 /// Maximum allowed decompressed data size (256 MB)
 const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
@@ -24,15 +31,21 @@ const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
 /// Maximum varint encoding length in bytes (ceil(64/7) = 10)
 const MAX_VARINT_BYTES: usize = 10;
 
-/// Maximum recursion depth for nested decode calls (arrays, objects, maps, tagged values).
-/// Set above the text parser's tested 200-level nesting to ensure binary round-trip parity.
-const MAX_DECODE_DEPTH: usize = 256;
-
 /// Maximum number of elements allowed in a single decoded collection (array, map, struct array).
 /// Also used to cap Vec::with_capacity during decode. Prevents OOM from crafted count values
 /// in small files (e.g. a 335-byte file claiming 973M Null elements).
 const MAX_COLLECTION_SIZE: usize = 1024 * 1024;
 
+/// Section flags that version 2 writers never set, with what each marks a
+/// section as. Files of major version 2 holding them are refused.
+const SECTION_FLAGS_SINCE_3: &[(u8, &str)] = &[
+    (0x04, "chunked"),
+    (0x08, "a continuation"),
+    (0x10, "codec-encoded"),
+    (0x20, "followed by column statistics"),
+    (0x80, "described"),
+];
+
 /// Read a u16 from data at the given offset, with bounds checking
 fn read_u16_at(data: &[u8], offset: usize) -> Result<u16> {
     let end = offset.checked_add(2)
@@ -75,6 +88,15 @@ fn read_u64_at(data: &[u8], offset: usize) -> Result<u64> {
     )?))
 }
 
+/// Convert a 64-bit offset or length read from the file to `usize`. On hosts
+/// with a narrower `usize` a value that does not fit is an error rather than
+/// being truncated to a different, in-bounds position.
+fn to_usize(value: u64) -> Result<usize> {
+    usize::try_from(value).map_err(|_| Error::ParseError(format!(
+        "value {} exceeds this platform's address space", value
+    )))
+}
+
 /// Storage backend for reader data
 enum DataSource {
     /// Owned bytes (from file read)
@@ -102,11 +124,41 @@ pub struct Reader {
     schema_map: HashMap<String, usize>,
     pub unions: Vec<Union>,
     union_map: HashMap<String, usize>,
+    /// Per-union flags from the union table, parallel to `unions`
+    pub(crate) union_flags: Vec<u16>,
+    /// Format version from the file header, as `(major, minor)`
+    version: (u16, u16),
     sections: IndexMap<String, SectionInfo>,
+    /// Positions in `sections`, ordered by key
+    sorted_keys: Vec<usize>,
     /// Indicates the source JSON was a root-level array (for round-trip fidelity)
     is_root_array: bool,
+    /// Indicates the source JSON was a root-level scalar (for round-trip fidelity)
+    is_root_primitive: bool,
     /// Cache for decompressed and decoded values
     cache: RefCell<HashMap<String, Value>>,
+    /// Registered codecs, by ID and key ID
+    codecs: HashMap<(u32, Option<String>), Box<dyn SectionCodec>>,
+    options: ReaderOptions,
+    /// Reads per section, when `options.record_access` is set
+    access: RefCell<IndexMap<String, SectionAccess>>,
+    /// Whether each field is encrypted, for schemas with encrypted fields
+    encrypted: HashMap<String, Vec<bool>>,
+    /// The values of the encrypted value table
+    sealed: Vec<SealedValue>,
+    /// Where the encrypted value table lies in the file
+    sealed_table: Range<usize>,
+}
+
+/// A value of the encrypted value table
+struct SealedValue {
+    codec: u32,
+    key_id: String,
+    algorithm: String,
+    /// Where the value, header included, lies in the file
+    range: Range<usize>,
+    /// Length of the header in front of the codec's output
+    header: usize,
 }
 
 #[allow(dead_code)]
@@ -119,6 +171,247 @@ struct SectionInfo {
     compressed: bool,
     is_array: bool,
     item_count: u32,
+    /// Offset of the chunk index following a table's rows in the section data
+    chunk_index: Option<u32>,
+    /// The rows of each chunk are compressed on their own, rather than the
+    /// whole section (see [`inflate_chunks`])
+    chunks_compressed: bool,
+    /// Offset of the translation index following the rows of an unchunked
+    /// `translation` table in the section data
+    lookup_index: Option<u32>,
+    /// Data is stored after the ID of the codec that encoded it
+    codec: bool,
+    /// Data ends with a column statistics block and its offset
+    column_stats: bool,
+    /// Bytes of the structure descriptor, with its length, stored before
+    /// the data; 0 if there is none
+    descriptor: u32,
+    /// Continuation sections holding the rest of a split table, in order
+    /// (see [`Writer::with_max_section_bytes`](crate::Writer::with_max_section_bytes))
+    parts: Vec<SectionInfo>,
+}
+
+impl SectionInfo {
+    /// Bytes held in memory once this part is decompressed or decoded; 0
+    /// if it is used where it lies in the file.
+    fn cost(&self) -> usize {
+        if self.compressed || self.chunks_compressed || self.codec { self.uncompressed_size as usize } else { 0 }
+    }
+}
+
+/// The decompressed data of `section`, the first part of section `key` or
+/// one of its continuations, in the file `bytes`.
+fn load_part<'a>(bytes: &'a [u8], codecs: &HashMap<(u32, Option<String>), Box<dyn SectionCodec>>, key: &str, section: &SectionInfo) -> Result<Cow<'a, [u8]>> {
+    let stored = stored_part(bytes, key, section)?;
+    if section.chunks_compressed {
+        return inflate_chunks(stored, section).map(Cow::Owned);
+    }
+    let data: Cow<'_, [u8]> = if section.compressed {
+        Cow::Owned(decompress_data(stored)?)
+    } else {
+        Cow::Borrowed(stored)
+    };
+    if !section.codec {
+        return Ok(data);
+    }
+    if data.len() < 4 {
+        return Err(Error::ParseError(format!("section '{}' is too short for a codec ID", key)));
+    }
+    let id = read_u32_at(&data, 0)?;
+    let codec = codecs.get(&(id, None))
+        .ok_or_else(|| Error::MissingCodec { section: key.to_string(), codec: id })?;
+    Ok(Cow::Owned(codec.decode(&data[4..])?))
+}
+
+/// The data of `section` in the file `bytes` as stored, after any descriptor.
+fn stored_part<'a>(bytes: &'a [u8], key: &str, section: &SectionInfo) -> Result<&'a [u8]> {
+    let start = to_usize(section.offset)?;
+    let end = start.checked_add(section.size as usize)
+        .ok_or_else(|| Error::ParseError("section offset overflow".into()))?;
+    let start = start + section.descriptor as usize;
+    if end > bytes.len() {
+        return Err(Error::ParseError(format!(
+            "section '{}' data range {}..{} exceeds file size {}",
+            key, start, end, bytes.len()
+        )));
+    }
+    Ok(&bytes[start..end])
+}
+
+/// The table header and rows of a table stored compressed chunk by chunk,
+/// with every chunk decompressed: the same rows an uncompressed section
+/// holds, without its chunk index and statistics.
+fn inflate_chunks(stored: &[u8], section: &SectionInfo) -> Result<Vec<u8>> {
+    let off = section.chunk_index.unwrap_or_default() as usize;
+    let mut data = stored.get(..8)
+        .ok_or_else(|| Error::ParseError("table header out of bounds".into()))?
+        .to_vec();
+    for chunk in parse_chunk_index(stored, off, section.item_count as usize)? {
+        data.extend(decompress_data(&stored[chunk.offset as usize..chunk.end as usize])?);
+        if data.len() > MAX_DECOMPRESSED_SIZE {
+            return Err(Error::ParseError(format!(
+                "Decompressed data exceeds maximum size of {} bytes", MAX_DECOMPRESSED_SIZE
+            )));
+        }
+    }
+    Ok(data)
+}
+
+/// Limits a [`Reader`] applies while decoding, set with
+/// [`Reader::with_options`]. Start from [`ReaderOptions::default`] and
+/// change it with the `with_*` methods; more options may be added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ReaderOptions {
+    /// Deepest nesting of arrays, objects, maps, structs and tagged values
+    /// decoded before failing with [`Error::DepthExceeded`]. Each level
+    /// costs stack, so services reading untrusted files may want it lower.
+    pub max_depth: usize,
+    /// Threads that decompress (and run codecs on) sections ahead of the
+    /// decoder when many sections are read at once, by
+    /// [`TeaLeaf::from_reader`](crate::TeaLeaf::from_reader) or
+    /// [`Reader::write_json`]. `0` (the default) uses one per available
+    /// core; `1` decompresses each section on the calling thread as it is
+    /// decoded.
+    pub decompress_threads: usize,
+    /// Decompressed bytes that may wait to be decoded before the threads
+    /// pause. A section larger than this is still decompressed when the
+    /// decoder needs it.
+    pub decompress_memory: usize,
+    /// Count reads and decode time per section, for
+    /// [`Reader::access_stats`]. Off by default.
+    pub record_access: bool,
+}
+
+impl ReaderOptions {
+    /// Default [`max_depth`](Self::max_depth), the text parser's limit too,
+    /// so any document that parses can be compiled and read back.
+    pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+    /// Default [`decompress_memory`](Self::decompress_memory), 64 MB.
+    pub const DEFAULT_DECOMPRESS_MEMORY: usize = 64 * 1024 * 1024;
+
+    /// Set [`max_depth`](Self::max_depth).
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set [`decompress_threads`](Self::decompress_threads).
+    pub fn with_decompress_threads(mut self, threads: usize) -> Self {
+        self.decompress_threads = threads;
+        self
+    }
+
+    /// Set [`decompress_memory`](Self::decompress_memory).
+    pub fn with_decompress_memory(mut self, bytes: usize) -> Self {
+        self.decompress_memory = bytes;
+        self
+    }
+
+    /// Set [`record_access`](Self::record_access).
+    pub fn with_record_access(mut self, record: bool) -> Self {
+        self.record_access = record;
+        self
+    }
+
+    fn threads(&self) -> usize {
+        match self.decompress_threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+    }
+}
+
+impl Default for ReaderOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            decompress_threads: 0,
+            decompress_memory: Self::DEFAULT_DECOMPRESS_MEMORY,
+            record_access: false,
+        }
+    }
+}
+
+/// How one section was read, recorded when
+/// [`ReaderOptions::record_access`] is set.
+///
+/// Reads by [`Reader::get`], [`Reader::get_as_projected`] and
+/// [`TeaLeaf::from_reader`](crate::TeaLeaf::from_reader) are counted; chunk
+/// reads, JSON export and raw copies are not.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SectionAccess {
+    /// Reads of the section, including those served from the cache
+    pub reads: u64,
+    /// Reads served from the cache without decoding
+    pub cache_hits: u64,
+    /// Decompressed bytes decoded, summed over the reads that decoded
+    pub bytes: u64,
+    /// Time spent decompressing and decoding, summed over the reads that
+    /// decoded. Sections decompressed ahead on other threads count only
+    /// the decoding and any wait for the data.
+    pub decode_time: Duration,
+}
+
+/// A field of a free-form section, from the structure the writer stored
+/// with it (see [`Writer::set_describe_sections`](crate::Writer::set_describe_sections)).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionField {
+    pub name: String,
+    /// Kind of the field's values, named as in error messages (`int`,
+    /// `string`, `object`, ...), or `any` if they differ and `null` if all
+    /// are null. Nullable if the field is missing or null in some object;
+    /// an array if every value is one, with the kind of its elements.
+    pub field_type: FieldType,
+    /// Fields of the field's objects, or of the objects in its arrays
+    pub fields: Vec<SectionField>,
+}
+
+/// Statistics of one column within one chunk of a chunked table section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStats {
+    /// Rows of the chunk where the column is null or absent
+    pub null_count: u32,
+    /// Smallest non-null value, for numeric and timestamp columns with at
+    /// least one value. Timestamps are UTC and rounded down to milliseconds.
+    pub min: Option<Value>,
+    /// Largest non-null value, as for `min`
+    pub max: Option<Value>,
+}
+
+/// Statistics of one column of a whole table section, recorded with
+/// [`Writer::set_column_stats`](crate::Writer::set_column_stats).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSummary {
+    pub name: String,
+    /// Rows where the column is null or absent
+    pub null_count: u32,
+    /// Distinct non-null values, counted by 64-bit hash: exact in practice
+    /// up to 4096, estimated within a few percent above; 0 for encrypted
+    /// columns
+    pub distinct: u32,
+    /// As for [`ColumnStats::min`]
+    pub min: Option<Value>,
+    /// As for [`ColumnStats::max`]
+    pub max: Option<Value>,
+}
+
+/// One chunk of rows of a chunked table section
+/// (see [`Writer::set_chunk_rows`](crate::Writer::set_chunk_rows)).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkStats {
+    /// Position of the chunk's first row in the table
+    pub first_row: usize,
+    pub rows: usize,
+    /// One entry per schema field, in schema order
+    pub columns: Vec<ColumnStats>,
+    /// Offset of the chunk's rows in the data of the section holding it
+    offset: u32,
+    /// Offset just past the chunk's rows
+    end: u32,
+    /// The rows are compressed on their own
+    compressed: bool,
 }
 
 impl Reader {
@@ -155,25 +448,27 @@ impl Reader {
         if bytes.len() < HEADER_SIZE {
             return Err(Error::InvalidMagic);
         }
-        if &bytes[0..4] != MAGIC {
+        if bytes[0..4] != MAGIC {
             return Err(Error::InvalidMagic);
         }
 
-        // Check version - we support major version 2
+        // Check version - we support major versions 2 and 3
         let major = read_u16_at(bytes, 4)?;
         let minor = read_u16_at(bytes, 6)?;
-        if major != 2 {
+        if !(2..=crate::VERSION_MAJOR).contains(&major) {
             return Err(Error::InvalidVersion { major, minor });
         }
 
-        // Read flags: bit 0 = compressed (handled per-section), bit 1 = root_array
+        // Read flags: bit 0 = compressed (handled per-section), bit 1 = root_array,
+        // bit 2 = encrypted values, bit 3 = root_primitive
         let flags = read_u32_at(bytes, 8)?;
         let is_root_array = (flags & 0x02) != 0;
+        let is_root_primitive = (flags & 0x08) != 0;
 
-        let str_off = read_u64_at(bytes, 16)? as usize;
-        let sch_off = read_u64_at(bytes, 24)? as usize;
-        let idx_off = read_u64_at(bytes, 32)? as usize;
-        let dat_off = read_u64_at(bytes, 40)? as usize;
+        let str_off = to_usize(read_u64_at(bytes, 16)?)?;
+        let sch_off = to_usize(read_u64_at(bytes, 24)?)?;
+        let idx_off = to_usize(read_u64_at(bytes, 32)?)?;
+        let dat_off = to_usize(read_u64_at(bytes, 40)?)?;
         let str_cnt = read_u32_at(bytes, 48)? as usize;
         let sch_cnt = read_u32_at(bytes, 52)? as usize;
         let sec_cnt = read_u32_at(bytes, 56)? as usize;
@@ -228,20 +523,53 @@ impl Reader {
             schema_map: HashMap::new(),
             unions: Vec::new(),
             union_map: HashMap::new(),
+            union_flags: Vec::new(),
+            version: (major, minor),
             sections: IndexMap::new(),
+            sorted_keys: Vec::new(),
             is_root_array,
+            is_root_primitive,
             cache: RefCell::new(HashMap::new()),
+            codecs: HashMap::new(),
+            options: ReaderOptions::default(),
+            access: RefCell::new(IndexMap::new()),
+            encrypted: HashMap::new(),
+            sealed: Vec::new(),
+            sealed_table: 0..0,
         };
 
         reader.parse_schemas(sch_off, sch_cnt)?;
         if union_cnt > 0 {
             reader.parse_unions(sch_off, sch_cnt, union_cnt)?;
         }
+        if flags & 0x04 != 0 {
+            if major < 3 {
+                return Err(reader.version_error("the file has encrypted fields"));
+            }
+            let sch_size = read_u32_at(reader.data(), sch_off)? as usize;
+            reader.parse_encrypted_values(sch_off.checked_add(sch_size)
+                .ok_or_else(|| Error::ParseError("encrypted value table offset overflow".into()))?)?;
+        }
         reader.parse_index(idx_off, sec_cnt)?;
 
         Ok(reader)
     }
 
+    /// Decode with `options`, dropping any values decoded under the old ones.
+    pub fn with_options(mut self, options: ReaderOptions) -> Self {
+        self.options = options;
+        self.clear_cache();
+        self
+    }
+
+    /// Fail if decoding has nested past the configured depth.
+    fn check_depth(&self, depth: usize) -> Result<()> {
+        if depth > self.options.max_depth {
+            return Err(Error::DepthExceeded { limit: self.options.max_depth });
+        }
+        Ok(())
+    }
+
     /// Get the underlying data as a byte slice
     fn data(&self) -> &[u8] {
         self.data.as_ref()
@@ -267,11 +595,53 @@ impl Reader {
             .map_err(|_| Error::InvalidUtf8)
     }
 
+    /// Stored size of section `key`, all parts of a split table included.
+    pub(crate) fn section_size(&self, key: &str) -> u64 {
+        self.sections.get(key)
+            .map(|s| std::iter::once(s).chain(&s.parts).map(|p| p.size as u64).sum())
+            .unwrap_or(0)
+    }
+
     /// Get section keys
     pub fn keys(&self) -> Vec<&str> {
         self.sections.keys().map(|s| s.as_str()).collect()
     }
 
+    /// Section keys starting with `prefix`, in sorted order.
+    ///
+    /// With hierarchical keys such as `metrics/2024-05-01`, this lists a
+    /// subtree: `keys_with_prefix("metrics/")`.
+    pub fn keys_with_prefix(&self, prefix: &str) -> Vec<&str> {
+        let start = self.sorted_keys.partition_point(|&i| self.key_at(i) < prefix);
+        self.sorted_keys[start..].iter()
+            .map(|&i| self.key_at(i))
+            .take_while(|k| k.starts_with(prefix))
+            .collect()
+    }
+
+    /// Section keys within `range`, in sorted (byte-wise) order, e.g.
+    /// `keys_in_range("metrics/2024-05-01".."metrics/2024-06-01")`.
+    pub fn keys_in_range<'k, R: RangeBounds<&'k str>>(&self, range: R) -> Vec<&str> {
+        let start = match range.start_bound() {
+            Bound::Included(s) => self.sorted_keys.partition_point(|&i| self.key_at(i) < *s),
+            Bound::Excluded(s) => self.sorted_keys.partition_point(|&i| self.key_at(i) <= *s),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(e) => self.sorted_keys.partition_point(|&i| self.key_at(i) <= *e),
+            Bound::Excluded(e) => self.sorted_keys.partition_point(|&i| self.key_at(i) < *e),
+            Bound::Unbounded => self.sorted_keys.len(),
+        };
+        if start >= end {
+            return Vec::new();
+        }
+        self.sorted_keys[start..end].iter().map(|&i| self.key_at(i)).collect()
+    }
+
+    fn key_at(&self, position: usize) -> &str {
+        self.sections.get_index(position).map_or("", |(k, _)| k.as_str())
+    }
+
     /// Check if the source JSON was a root-level array
     ///
     /// When true, the "root" key contains the array and `to_json` should
@@ -280,50 +650,832 @@ impl Reader {
         self.is_root_array
     }
 
+    /// Check if the source JSON was a root-level scalar, such as `42`
+    ///
+    /// When true, the "root" key contains the value and `to_json` should
+    /// output it directly without wrapping in an object.
+    pub fn is_root_primitive(&self) -> bool {
+        self.is_root_primitive
+    }
+
+    /// Format version from the file header, as `(major, minor)`
+    pub fn version(&self) -> (u16, u16) {
+        self.version
+    }
+
+    /// Clone the schema definitions, keyed by name in file order
+    pub fn schemas_owned(&self) -> IndexMap<String, Schema> {
+        self.schemas.iter().map(|s| (s.name.clone(), s.clone())).collect()
+    }
+
+    /// Position of the schema `name` in the file's schema table. Encoded
+    /// structs and table sections refer to their schema by this index.
+    pub fn schema_index(&self, name: &str) -> Option<usize> {
+        self.schema_map.get(name).copied()
+    }
+
+    /// Position of the union `name` in the file's union table.
+    pub fn union_index(&self, name: &str) -> Option<usize> {
+        self.union_map.get(name).copied()
+    }
+
+    /// Consume the reader, keeping only its schema and union definitions
+    pub fn into_schema_registry(self) -> SchemaRegistry {
+        SchemaRegistry {
+            schemas: self.schemas.into_iter().map(|s| (s.name.clone(), s)).collect(),
+            unions: self.unions.into_iter().map(|u| (u.name.clone(), u)).collect(),
+        }
+    }
+
+    /// Schema fields whose type names neither a struct nor a union, as
+    /// `(schema, field)` pairs. These columns hold heterogeneous values, each
+    /// stored with its own type code.
+    pub fn any_fields(&self) -> Vec<(&str, &str)> {
+        self.schemas.iter()
+            .flat_map(|s| s.fields.iter().map(move |f| (s, f)))
+            .filter(|(_, f)| self.is_any_type(&f.field_type.base))
+            .map(|(s, f)| (s.name.as_str(), f.name.as_str()))
+            .collect()
+    }
+
     /// Get a value by key
     pub fn get(&self, key: &str) -> Result<Value> {
         // Check cache first
         if let Some(cached) = self.cache.borrow().get(key) {
+            self.record_hit(key);
             return Ok(cached.clone());
         }
 
         let section = self.sections.get(key)
             .ok_or_else(|| Error::MissingField(key.to_string()))?;
+        // Decompress the parts of a split table one at a time
+        let start = Instant::now();
+        let mut bytes = 0;
+        let result = self.decode_parts(section, &mut Progress::new(), |part| {
+            let data = self.part_data(key, part)?;
+            bytes += data.len();
+            Ok(data)
+        })?;
+        self.record_decode(key, bytes, start);
 
-        let start = section.offset as usize;
-        let end = start.checked_add(section.size as usize)
-            .ok_or_else(|| Error::ParseError("section offset overflow".into()))?;
-        if end > self.data().len() {
-            return Err(Error::ParseError(format!(
-                "section '{}' data range {}..{} exceeds file size {}",
-                key, start, end, self.data().len()
-            )));
+        self.cache.borrow_mut().insert(key.to_string(), result.clone());
+        Ok(result)
+    }
+
+    /// Reads of each section since the reader was opened or
+    /// [`reset_access_stats`](Self::reset_access_stats) was called, in order
+    /// of first read. Empty unless [`ReaderOptions::record_access`] is set.
+    pub fn access_stats(&self) -> IndexMap<String, SectionAccess> {
+        self.access.borrow().clone()
+    }
+
+    pub fn reset_access_stats(&self) {
+        self.access.borrow_mut().clear();
+    }
+
+    fn record_hit(&self, key: &str) {
+        if self.options.record_access {
+            let mut access = self.access.borrow_mut();
+            let entry = access.entry(key.to_string()).or_default();
+            entry.reads += 1;
+            entry.cache_hits += 1;
         }
+    }
 
-        let data: Cow<'_, [u8]> = if section.compressed {
-            Cow::Owned(decompress_data(&self.data()[start..end])?)
-        } else {
-            Cow::Borrowed(&self.data()[start..end])
+    /// Record a read of section `key` that decoded `bytes` bytes, begun at
+    /// `start`.
+    fn record_decode(&self, key: &str, bytes: usize, start: Instant) {
+        if self.options.record_access {
+            let mut access = self.access.borrow_mut();
+            let entry = access.entry(key.to_string()).or_default();
+            entry.reads += 1;
+            entry.bytes += bytes as u64;
+            entry.decode_time += start.elapsed();
+        }
+    }
+
+    /// Decode every section in `keys`, in order, passing each value to `f`
+    /// together with `progress`, which is asked whether to stop as table
+    /// rows are decoded. Sections are decompressed ahead on other threads as
+    /// [`ReaderOptions`] allows, and values are not cached.
+    pub(crate) fn for_each_value(&self, keys: &[&str], progress: &mut Progress, mut f: impl FnMut(&str, Value, &mut Progress) -> Result<()>) -> Result<()> {
+        let mut start = Instant::now();
+        self.with_section_data(keys, |key, section, data| {
+            let bytes = data.iter().map(|d| d.len()).sum();
+            let mut data = data.into_iter();
+            let value = self.decode_parts(section, progress, |_| {
+                data.next().ok_or_else(|| Error::ParseError(format!("section '{}' is missing a part", key)))
+            })?;
+            self.record_decode(key, bytes, start);
+            f(key, value, progress)?;
+            start = Instant::now();
+            Ok(())
+        })
+    }
+
+    /// Decode a section from the data of its parts, which `part_data`
+    /// supplies in order: the section itself, then its continuations.
+    /// `progress` is asked whether to stop as table rows are decoded.
+    fn decode_parts<'d>(&self, section: &SectionInfo, progress: &mut Progress, mut part_data: impl FnMut(&SectionInfo) -> Result<Cow<'d, [u8]>>) -> Result<Value> {
+        let data = part_data(section)?;
+        let mut cursor = Cursor::new(data.as_ref());
+        if !(section.is_array && section.schema_idx >= 0) {
+            return self.decode_section(&mut cursor, section);
+        }
+
+        let mut rows = Vec::new();
+        self.decode_table_rows(&mut cursor, section.schema_idx as usize, &mut rows, progress)?;
+        for part in &section.parts {
+            let data = part_data(part)?;
+            self.decode_table_rows(&mut Cursor::new(data.as_ref()), part.schema_idx as usize, &mut rows, progress)?;
+        }
+        Ok(Value::Array(rows))
+    }
+
+    /// Decode the rows of a table section onto `rows`, counting each one
+    /// with `progress`.
+    fn decode_table_rows(&self, cursor: &mut Cursor, schema_idx: usize, rows: &mut Vec<Value>, progress: &mut Progress) -> Result<()> {
+        let (count, schema, bitmap_size) = self.struct_array_header(cursor, schema_idx)?;
+        rows.reserve(count.min(cursor.remaining()).min(MAX_COLLECTION_SIZE));
+        for _ in 0..count {
+            progress.tick()?;
+            rows.push(self.decode_struct_row(cursor, schema, bitmap_size, 0)?);
+        }
+        Ok(())
+    }
+
+    /// Call `f` with each section in `keys`, in order, and the data of its
+    /// parts (see [`decode_parts`](Self::decode_parts)).
+    ///
+    /// Parts that need decompressing or decoding are prepared by a pool of
+    /// up to [`ReaderOptions::decompress_threads`] threads, taking parts in
+    /// order, while `f` runs. Threads wait while the prepared parts not yet
+    /// passed to `f` hold more than [`ReaderOptions::decompress_memory`]
+    /// bytes, except for the part `f` is waiting for.
+    fn with_section_data<'r>(&'r self, keys: &[&'r str], mut f: impl FnMut(&'r str, &'r SectionInfo, Vec<Cow<'r, [u8]>>) -> Result<()>) -> Result<()> {
+        let sections = keys.iter()
+            .map(|&key| self.sections.get(key).map(|s| (key, s)).ok_or_else(|| Error::MissingField(key.to_string())))
+            .collect::<Result<Vec<_>>>()?;
+        let jobs: Vec<(&str, &SectionInfo)> = sections.iter()
+            .flat_map(|&(key, section)| std::iter::once(section).chain(&section.parts).map(move |part| (key, part)))
+            .collect();
+        let threads = self.options.threads().min(jobs.iter().filter(|(_, part)| part.cost() > 0).count());
+        if threads <= 1 {
+            for (key, section) in sections {
+                let data = std::iter::once(section).chain(&section.parts)
+                    .map(|part| self.part_data(key, part))
+                    .collect::<Result<Vec<_>>>()?;
+                f(key, section, data)?;
+            }
+            return Ok(());
+        }
+
+        struct Queue<'a> {
+            /// First job no thread has taken
+            next: usize,
+            /// Job `f` is waiting for
+            wanted: usize,
+            /// Cost of jobs taken and not yet released by `f`
+            held: usize,
+            done: Vec<Option<Result<Cow<'a, [u8]>>>>,
+            stop: bool,
+        }
+        let queue = Mutex::new(Queue { next: 0, wanted: 0, held: 0, done: jobs.iter().map(|_| None).collect(), stop: false });
+        let changed = Condvar::new();
+        let (bytes, codecs, budget) = (self.data(), &self.codecs, self.options.decompress_memory);
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let mut q = queue.lock().unwrap();
+                    let job = loop {
+                        if q.stop || q.next == jobs.len() {
+                            return;
+                        }
+                        let cost = jobs[q.next].1.cost();
+                        if q.held == 0 || q.held + cost <= budget || q.next == q.wanted {
+                            break q.next;
+                        }
+                        q = changed.wait(q).unwrap();
+                    };
+                    q.next += 1;
+                    q.held += jobs[job].1.cost();
+                    drop(q);
+                    let (key, part) = jobs[job];
+                    let data = load_part(bytes, codecs, key, part);
+                    queue.lock().unwrap().done[job] = Some(data);
+                    changed.notify_all();
+                });
+            }
+
+            let mut run = || -> Result<()> {
+                let mut job = 0;
+                for &(key, section) in &sections {
+                    let parts = 1 + section.parts.len();
+                    let mut data = Vec::with_capacity(parts);
+                    for _ in 0..parts {
+                        let mut q = queue.lock().unwrap();
+                        q.wanted = job;
+                        changed.notify_all();
+                        while q.done[job].is_none() {
+                            q = changed.wait(q).unwrap();
+                        }
+                        data.push(q.done[job].take().unwrap_or_else(|| unreachable!())?);
+                        job += 1;
+                    }
+                    let result = f(key, section, data);
+                    queue.lock().unwrap().held -= std::iter::once(section).chain(&section.parts).map(SectionInfo::cost).sum::<usize>();
+                    changed.notify_all();
+                    result?;
+                }
+                Ok(())
+            };
+            let result = run();
+            queue.lock().unwrap().stop = true;
+            changed.notify_all();
+            result
+        })
+    }
+
+    /// Decode section `key` as `T`, skipping the table columns `T` does not
+    /// read.
+    ///
+    /// When `T` is a sequence of a struct type (such as `Vec<Row>` with
+    /// `Row` deriving both conversion traits) and the section is a table,
+    /// columns of the file's schema missing from `Row`'s schema are stepped
+    /// over instead of decoded, so no strings or nested values are built for
+    /// them. Any other section is decoded whole, as by [`get`](Self::get).
+    /// Projected tables are not cached.
+    pub fn get_as_projected<T: FromTeaLeaf + ToTeaLeaf>(&self, key: &str) -> Result<T> {
+        let field_type = T::tealeaf_field_type();
+        let schemas = T::collect_schemas();
+        let wanted: Option<HashSet<&str>> = schemas.get(&field_type.base)
+            .filter(|_| field_type.is_array)
+            .map(|schema| schema.fields.iter().map(|f| f.name.as_str()).collect());
+        let start = Instant::now();
+        let (section, data) = self.section_data(key)?;
+        let value = match wanted {
+            Some(wanted) if section.is_array && section.schema_idx >= 0 => {
+                let mut rows = Vec::new();
+                let mut bytes = data.len();
+                self.decode_rows_projected(&data, section, &wanted, &mut rows)?;
+                for part in &section.parts {
+                    let data = self.part_data(key, part)?;
+                    bytes += data.len();
+                    self.decode_rows_projected(&data, part, &wanted, &mut rows)?;
+                }
+                self.record_decode(key, bytes, start);
+                Value::Array(rows)
+            }
+            _ => self.get(key)?,
         };
+        Ok(T::from_tealeaf_value(&value)?)
+    }
 
+    /// Decode the rows of one table section, keeping only `wanted` fields.
+    fn decode_rows_projected(&self, data: &[u8], section: &SectionInfo, wanted: &HashSet<&str>, rows: &mut Vec<Value>) -> Result<()> {
+        let mut cursor = Cursor::new(data);
+        let (count, schema, bitmap_size) = self.struct_array_header(&mut cursor, section.schema_idx as usize)?;
+        let keep: Vec<bool> = schema.fields.iter().map(|f| wanted.contains(f.name.as_str())).collect();
+        rows.reserve(count.min(MAX_COLLECTION_SIZE));
+        for _ in 0..count {
+            rows.push(self.decode_projected_row(&mut cursor, schema, bitmap_size, Some(&keep), 0)?);
+        }
+        Ok(())
+    }
+
+    /// Decode a section that is not a table.
+    fn decode_section(&self, cursor: &mut Cursor, section: &SectionInfo) -> Result<Value> {
+        match section.tl_type {
+            TLType::Array => self.decode_array(cursor, 0),
+            TLType::Object => self.decode_object(cursor, 0),
+            TLType::Struct => self.decode_struct(cursor, 0),
+            TLType::Map => self.decode_map(cursor, 0),
+            _ => self.decode_value(cursor, section.tl_type, 0),
+        }
+    }
+
+    /// Write the file as JSON, the same JSON that
+    /// [`TeaLeaf::from_reader`](crate::TeaLeaf::from_reader) followed by
+    /// [`to_json`](crate::TeaLeaf::to_json) or
+    /// [`to_json_compact`](crate::TeaLeaf::to_json_compact) produces.
+    ///
+    /// No document is built and nothing is cached: tables are decoded and
+    /// written one row at a time, and other sections one at a time. The
+    /// decompressed data of a whole section (every part of a split table) is
+    /// still held while it is written, plus up to
+    /// [`decompress_memory`](ReaderOptions::decompress_memory) of sections
+    /// decompressed ahead, so memory use is bounded by the largest section
+    /// rather than by the file. Writes are small; wrap `w` in a `BufWriter`
+    /// if it is unbuffered.
+    #[cfg(feature = "json")]
+    pub fn write_json<W: Write>(&self, mut w: W, opts: crate::JsonOptions) -> Result<()> {
+        // A root-level array or scalar is written on its own, not wrapped in an object
+        if (self.is_root_array || self.is_root_primitive) && self.sections.contains_key("root") {
+            return self.write_section_json(&mut w, "root", 0, opts);
+        }
+        w.write_all(b"{")?;
+        let keys: Vec<&str> = self.sections.keys().map(String::as_str).collect();
+        let mut first = true;
+        self.with_section_data(&keys, |key, section, data| {
+            if !first {
+                w.write_all(b",")?;
+            }
+            first = false;
+            write_json_break(&mut w, 1, opts)?;
+            serde_json::to_writer(&mut w, key).map_err(json_error)?;
+            w.write_all(if opts.pretty { b": " } else { b":" })?;
+            self.write_parts_json(&mut w, section, data, 1, opts)
+        })?;
+        if !self.sections.is_empty() {
+            write_json_break(&mut w, 0, opts)?;
+        }
+        w.write_all(b"}")?;
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    fn write_section_json<W: Write>(&self, w: &mut W, key: &str, depth: usize, opts: crate::JsonOptions) -> Result<()> {
+        let section = self.sections.get(key)
+            .ok_or_else(|| Error::MissingField(key.to_string()))?;
+        let data = std::iter::once(section).chain(&section.parts)
+            .map(|part| self.part_data(key, part))
+            .collect::<Result<Vec<_>>>()?;
+        self.write_parts_json(w, section, data, depth, opts)
+    }
+
+    /// Write a section as JSON from the data of its parts, the section's
+    /// own then its continuations'.
+    #[cfg(feature = "json")]
+    fn write_parts_json<W: Write>(&self, w: &mut W, section: &SectionInfo, data: Vec<Cow<'_, [u8]>>, depth: usize, opts: crate::JsonOptions) -> Result<()> {
+        let mut parts = std::iter::once(section).chain(&section.parts).zip(data);
+        let (_, data) = match parts.next() {
+            Some(first) => first,
+            None => return Err(Error::ParseError("section has no data".into())),
+        };
         let mut cursor = Cursor::new(data.as_ref());
+        if !(section.is_array && section.schema_idx >= 0) {
+            let value = self.decode_section(&mut cursor, section)?;
+            return write_json_value(w, &value, depth, opts);
+        }
+
+        w.write_all(b"[")?;
+        let mut written = self.write_rows_json(w, &mut cursor, section, 0, depth, opts)?;
+        for (part, data) in parts {
+            written = self.write_rows_json(w, &mut Cursor::new(data.as_ref()), part, written, depth, opts)?;
+        }
+        if written > 0 {
+            write_json_break(w, depth, opts)?;
+        }
+        w.write_all(b"]")?;
+        Ok(())
+    }
+
+    /// Write the rows of one table section as JSON array elements, after
+    /// `written` rows already written. Returns the rows written so far.
+    #[cfg(feature = "json")]
+    fn write_rows_json<W: Write>(&self, w: &mut W, cursor: &mut Cursor, section: &SectionInfo, mut written: usize, depth: usize, opts: crate::JsonOptions) -> Result<usize> {
+        let (count, schema, bitmap_size) = self.struct_array_header(cursor, section.schema_idx as usize)?;
+        for _ in 0..count {
+            if written > 0 {
+                w.write_all(b",")?;
+            }
+            write_json_break(w, depth + 1, opts)?;
+            let row = self.decode_struct_row(cursor, schema, bitmap_size, 0)?;
+            write_json_value(w, &row, depth + 1, opts)?;
+            written += 1;
+        }
+        Ok(written)
+    }
 
-        let result = if section.is_array && section.schema_idx >= 0 {
-            self.decode_struct_array(&mut cursor, section.schema_idx as usize, 0)?
+    /// The encoded bytes of a section, decompressed, together with its
+    /// schema if it is a table (an array of schema-typed structs).
+    ///
+    /// Table sections can be copied into another file with
+    /// [`Writer::add_raw_section`](crate::Writer::add_raw_section) without
+    /// decoding them.
+    pub fn raw_section(&self, key: &str) -> Result<(Vec<u8>, Option<&Schema>)> {
+        let (section, data) = self.section_data(key)?;
+        let schema = if section.is_array && section.schema_idx >= 0 {
+            self.schemas.get(section.schema_idx as usize)
         } else {
-            match section.tl_type {
-                TLType::Array => self.decode_array(&mut cursor, 0)?,
-                TLType::Object => self.decode_object(&mut cursor, 0)?,
-                TLType::Struct => self.decode_struct(&mut cursor, 0)?,
-                TLType::Map => self.decode_map(&mut cursor, 0)?,
-                _ => self.decode_value(&mut cursor, section.tl_type, 0)?,
+            None
+        };
+        // Leave out the chunk index and statistics; the rows alone are the encoded table
+        let rows_end = |section: &SectionInfo, data: &[u8]| -> Result<usize> {
+            if section.chunks_compressed {
+                return Ok(data.len());
             }
+            let len = if section.column_stats { column_stats_offset(data)? } else { data.len() };
+            Ok(section.chunk_index.map_or(len, |off| (off as usize).min(len)))
         };
+        let mut raw = data[..rows_end(section, &data)?].to_vec();
+        if section.parts.is_empty() {
+            return Ok((raw, schema));
+        }
+        // Join a split table: one header counting every row, then each part's rows
+        let mut count = section.item_count;
+        for part in &section.parts {
+            let data = self.part_data(key, part)?;
+            let end = rows_end(part, &data)?;
+            if end < 8 {
+                return Err(Error::ParseError(format!("section '{}' has a truncated part", key)));
+            }
+            raw.extend_from_slice(&data[8..end]);
+            count = count.checked_add(part.item_count)
+                .ok_or_else(|| Error::ParseError(format!("section '{}' has too many rows", key)))?;
+        }
+        if raw.len() < 8 {
+            return Err(Error::ParseError(format!("section '{}' has a truncated part", key)));
+        }
+        raw[..4].copy_from_slice(&count.to_le_bytes());
+        Ok((raw, schema))
+    }
 
-        self.cache.borrow_mut().insert(key.to_string(), result.clone());
+    /// The decompressed data of section `key` and the offset of the
+    /// translation index following its rows, or `None` if it has none.
+    pub(crate) fn lookup_index(&self, key: &str) -> Result<Option<(Cow<'_, [u8]>, usize)>> {
+        match self.sections.get(key) {
+            Some(section @ SectionInfo { lookup_index: Some(offset), .. }) => {
+                Ok(Some((self.part_data(key, section)?, *offset as usize)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Per-chunk statistics of the table section `key`, or `None` if it was
+    /// written unchunked.
+    pub fn chunk_stats(&self, key: &str) -> Result<Option<Vec<ChunkStats>>> {
+        let (section, data, _) = self.table_section(key)?;
+        if section.chunk_index.is_none() {
+            return Ok(None);
+        }
+        let mut chunks = Vec::new();
+        self.for_each_chunk(key, section, data, |_, chunk| {
+            chunks.push(chunk);
+            Ok(())
+        })?;
+        Ok(Some(chunks))
+    }
+
+    /// Statistics of each column of the table section `key`, in schema
+    /// order, or `None` if it was written without them. Only the statistics
+    /// are read; no rows are decoded.
+    pub fn column_stats(&self, key: &str) -> Result<Option<Vec<ColumnSummary>>> {
+        let (section, data, schema) = self.table_section(key)?;
+        if !section.column_stats {
+            return Ok(None);
+        }
+        let off = column_stats_offset(&data)?;
+        parse_column_stats(&data[..data.len() - 4], off, schema).map(Some)
+    }
+
+    /// Decode only chunk `index` of the chunked table section `key`.
+    pub fn read_chunk(&self, key: &str, index: usize) -> Result<Vec<Value>> {
+        let (section, data, schema) = self.table_section(key)?;
+        if section.chunk_index.is_none() {
+            return Err(Error::ParseError(format!("section '{}' is not chunked", key)));
+        }
+        let mut rows = None;
+        let mut seen = 0;
+        self.for_each_chunk(key, section, data, |data, chunk| {
+            if seen == index {
+                rows = Some(self.decode_chunk(data, schema, &chunk)?);
+            }
+            seen += 1;
+            Ok(())
+        })?;
+        rows.ok_or_else(|| Error::ParseError(format!("section '{}' has no chunk {}", key, index)))
+    }
+
+    /// Rows of the table section `key`, decoding only the chunks for which
+    /// `keep` returns true. Use it to skip chunks whose statistics rule out a
+    /// predicate; the rows of kept chunks are returned unfiltered, in table
+    /// order. An unchunked table is decoded whole.
+    pub fn scan_chunks<F: FnMut(&ChunkStats) -> bool>(&self, key: &str, mut keep: F) -> Result<Vec<Value>> {
+        let (section, data, schema) = self.table_section(key)?;
+        if section.chunk_index.is_none() {
+            let mut cursor = Cursor::new(data.as_ref());
+            let mut rows = match self.decode_struct_array(&mut cursor, section.schema_idx as usize, 0)? {
+                Value::Array(rows) => rows,
+                _ => Vec::new(),
+            };
+            for part in &section.parts {
+                let data = self.part_data(key, part)?;
+                if let Value::Array(more) = self.decode_struct_array(&mut Cursor::new(data.as_ref()), part.schema_idx as usize, 0)? {
+                    rows.extend(more);
+                }
+            }
+            return Ok(rows);
+        }
+        let mut result = Vec::new();
+        self.for_each_chunk(key, section, data, |data, chunk| {
+            if keep(&chunk) {
+                result.extend(self.decode_chunk(data, schema, &chunk)?);
+            }
+            Ok(())
+        })?;
         Ok(result)
     }
 
+    /// Call `f` with each chunk of a chunked table, in table order, and the
+    /// data of the section holding it, as [`table_section`](Self::table_section)
+    /// returns it. The parts of a split table are read one at a time; `data`
+    /// is the first part's.
+    fn for_each_chunk<F>(&self, key: &str, section: &SectionInfo, data: Cow<'_, [u8]>, mut f: F) -> Result<()>
+    where
+        F: FnMut(&[u8], ChunkStats) -> Result<()>,
+    {
+        let mut data = Some(data);
+        let mut first_row = 0;
+        for part in std::iter::once(section).chain(&section.parts) {
+            let data = match data.take() {
+                Some(data) => data,
+                None => self.stored_data(key, part)?,
+            };
+            let off = part.chunk_index
+                .ok_or_else(|| Error::ParseError(format!("section '{}' is not chunked", key)))?;
+            for mut chunk in parse_chunk_index(&data, off as usize, part.item_count as usize)? {
+                chunk.first_row += first_row;
+                chunk.compressed = part.chunks_compressed;
+                f(&data, chunk)?;
+            }
+            first_row += part.item_count as usize;
+        }
+        Ok(())
+    }
+
+    /// Rows of the table section `key` whose timestamp `field` lies in
+    /// `range` (Unix milliseconds, end exclusive), in table order.
+    ///
+    /// Chunks of a chunked table whose min/max for `field` fall outside
+    /// `range` are skipped without decoding. Fails if `field` is not a
+    /// timestamp field.
+    pub fn filter_table_by_time(&self, key: &str, field: &str, range: Range<i64>) -> Result<Vec<Value>> {
+        let (_, _, schema) = self.table_section(key)?;
+        let field_idx = schema.fields.iter().position(|f| f.name == field)
+            .ok_or_else(|| Error::MissingField(format!("{}.{}", schema.name, field)))?;
+        let field_type = &schema.fields[field_idx].field_type;
+        if field_type.is_array || !matches!(field_type.to_tl_type(), TLType::Timestamp | TLType::TimestampNanos) {
+            return Err(Error::ParseError(format!(
+                "field '{}' of section '{}' is {}, not a timestamp", field, key, field_type
+            )));
+        }
+        let rows = self.scan_chunks(key, |chunk| {
+            let column = &chunk.columns[field_idx];
+            match (column.min.as_ref().and_then(Value::as_timestamp_millis), column.max.as_ref().and_then(Value::as_timestamp_millis)) {
+                (Some(min), Some(max)) => max >= range.start && min < range.end,
+                _ => false,
+            }
+        })?;
+        Ok(rows.into_iter()
+            .filter(|row| row.get(field)
+                .and_then(Value::as_timestamp_millis)
+                .is_some_and(|ts| range.contains(&ts)))
+            .collect())
+    }
+
+    /// A table section's info, data and schema. The data is decompressed,
+    /// except that the chunks of a table compressed chunk by chunk are left
+    /// for [`decode_chunk`](Self::decode_chunk) to decompress.
+    fn table_section(&self, key: &str) -> Result<(&SectionInfo, Cow<'_, [u8]>, &Schema)> {
+        let section = self.sections.get(key)
+            .ok_or_else(|| Error::MissingField(key.to_string()))?;
+        let data = self.stored_data(key, section)?;
+        match self.schemas.get(section.schema_idx as usize) {
+            Some(schema) if section.is_array && section.schema_idx >= 0 => Ok((section, data, schema)),
+            _ => Err(Error::ParseError(format!("section '{}' is not a table", key))),
+        }
+    }
+
+    /// Decode the rows of one chunk of a chunked table.
+    fn decode_chunk(&self, data: &[u8], schema: &Schema, chunk: &ChunkStats) -> Result<Vec<Value>> {
+        let bitmap_size = read_u16_at(data, 6)? as usize;
+        let rows_data;
+        let mut cursor = if chunk.compressed {
+            rows_data = decompress_data(&data[chunk.offset as usize..chunk.end as usize])?;
+            Cursor::new(&rows_data)
+        } else {
+            let mut cursor = Cursor::new(data);
+            cursor.pos = chunk.offset as usize;
+            cursor
+        };
+        let mut rows = Vec::with_capacity(chunk.rows.min(MAX_COLLECTION_SIZE));
+        for _ in 0..chunk.rows {
+            rows.push(self.decode_struct_row(&mut cursor, schema, bitmap_size, 0)?);
+        }
+        Ok(rows)
+    }
+
+    /// The fields of section `key` as the writer described them, or `None`
+    /// if it was written without a description (tables never have one;
+    /// their schema describes them). Only the description is read; the
+    /// section is not decompressed or decoded.
+    pub fn section_fields(&self, key: &str) -> Result<Option<Vec<SectionField>>> {
+        let section = self.sections.get(key)
+            .ok_or_else(|| Error::MissingField(key.to_string()))?;
+        if section.descriptor == 0 {
+            return Ok(None);
+        }
+        let start = to_usize(section.offset)? + 4;
+        let mut cursor = Cursor::new(&self.data()[start..start - 4 + section.descriptor as usize]);
+        self.read_section_fields(&mut cursor, 0).map(Some)
+    }
+
+    fn read_section_fields(&self, cursor: &mut Cursor, depth: usize) -> Result<Vec<SectionField>> {
+        self.check_depth(depth)?;
+        let count = cursor.read_u32()? as usize;
+        // Each field takes at least 6 bytes
+        if count > cursor.remaining() / 6 {
+            return Err(Error::ParseError(format!("descriptor field count {} exceeds its data", count)));
+        }
+        let mut fields = Vec::with_capacity(count);
+        for _ in 0..count {
+            let len = cursor.read_u32()? as usize;
+            let name = String::from_utf8(cursor.read_bytes(len)?)
+                .map_err(|_| Error::ParseError("descriptor field name is not UTF-8".into()))?;
+            let code = cursor.read_u8()?;
+            let flags = cursor.read_u8()?;
+            let base = match code {
+                TYPE_CODE_ANY => "any",
+                0x00 => "null",
+                0x01 => "bool",
+                0x05 => "int",
+                0x09 => "uint",
+                0x0B => "float",
+                0x10 => "string",
+                0x11 => "bytes",
+                0x12 => "number",
+                0x20 => "array",
+                0x21 => "object",
+                0x23 => "map",
+                0x30 => "ref",
+                0x31 => "tagged",
+                0x32 => "timestamp",
+                0x33 => "timestamp_ns",
+                other => return Err(Error::InvalidType(other)),
+            };
+            let nested = if code == TLType::Object as u8 { self.read_section_fields(cursor, depth + 1)? } else { Vec::new() };
+            let field_type = FieldType { base: base.to_string(), nullable: flags & 0x01 != 0, is_array: flags & 0x02 != 0 };
+            fields.push(SectionField { name, field_type, fields: nested });
+        }
+        Ok(fields)
+    }
+
+    /// Number of entries in the string table.
+    pub(crate) fn string_count(&self) -> usize {
+        self.string_offsets.len()
+    }
+
+    fn section_data(&self, key: &str) -> Result<(&SectionInfo, Cow<'_, [u8]>)> {
+        let section = self.sections.get(key)
+            .ok_or_else(|| Error::MissingField(key.to_string()))?;
+        Ok((section, self.part_data(key, section)?))
+    }
+
+    /// The decompressed data of `section`, the first part of section `key`
+    /// or one of its continuations.
+    fn part_data(&self, key: &str, section: &SectionInfo) -> Result<Cow<'_, [u8]>> {
+        load_part(self.data(), &self.codecs, key, section)
+    }
+
+    /// Like [`part_data`](Self::part_data), but the data of a table
+    /// compressed chunk by chunk as stored, chunk index included.
+    fn stored_data(&self, key: &str, section: &SectionInfo) -> Result<Cow<'_, [u8]>> {
+        if section.chunks_compressed {
+            return stored_part(self.data(), key, section).map(Cow::Borrowed);
+        }
+        self.part_data(key, section)
+    }
+
+    /// Resolve several paths at once, keyed by path in request order.
+    ///
+    /// Paths use the syntax of [`TeaLeaf::get_path`](crate::TeaLeaf::get_path).
+    /// Each section is decoded at most once however many paths it serves; a
+    /// path whose section or nested value is missing maps to `None`.
+    pub fn get_paths(&self, paths: &[&str]) -> Result<IndexMap<String, Option<Value>>> {
+        let mut sections: HashMap<&str, Value> = HashMap::new();
+        let mut result = IndexMap::new();
+        for &path in paths {
+            let (key, rest) = crate::types::split_path_root(path);
+            if !sections.contains_key(key) && !path.is_empty() && self.sections.contains_key(key) {
+                sections.insert(key, self.get(key)?);
+            }
+            let value = sections.get(key).and_then(|root| {
+                if rest.is_empty() { Some(root) } else { root.get_path(rest) }
+            });
+            result.insert(path.to_string(), value.cloned());
+        }
+        Ok(result)
+    }
+
+    /// Decode sections written with `codec`, or field values encrypted with
+    /// it if it has a key ID, replacing any codec registered with the same
+    /// ID and key ID. Sections that need a codec the reader lacks fail to
+    /// read with [`Error::MissingCodec`], and encrypted values with
+    /// [`Error::MissingKey`].
+    pub fn register_codec<C: SectionCodec + 'static>(&mut self, codec: C) {
+        self.codecs.insert((codec.id(), codec.key_id().map(str::to_string)), Box::new(codec));
+    }
+
+    /// The key IDs the encrypted field values of this file need, each once,
+    /// in the order the values appear. Read from the encrypted value table,
+    /// so no codec has to be registered.
+    pub fn key_ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = Vec::new();
+        for sealed in &self.sealed {
+            if !ids.contains(&sealed.key_id.as_str()) {
+                ids.push(&sealed.key_id);
+            }
+        }
+        ids
+    }
+
+    /// The codec ID and algorithm that values encrypted with `key_id` were
+    /// encrypted with, or `None` if no value uses that key.
+    pub fn key_algorithm(&self, key_id: &str) -> Option<(u32, &str)> {
+        self.sealed.iter()
+            .find(|sealed| sealed.key_id == key_id)
+            .map(|sealed| (sealed.codec, sealed.algorithm.as_str()))
+    }
+
+    /// Encrypted fields of registered schemas, as `(schema, field)` pairs.
+    pub fn encrypted_fields(&self) -> Vec<(&str, &str)> {
+        self.schemas.iter()
+            .flat_map(|s| s.fields.iter().enumerate().map(move |(i, f)| (s, i, f)))
+            .filter(|(s, i, _)| self.sealed_fields(s).is_some_and(|sealed| sealed[*i]))
+            .map(|(s, _, f)| (s.name.as_str(), f.name.as_str()))
+            .collect()
+    }
+
+    /// The number of values in the encrypted value table.
+    pub(crate) fn encrypted_value_count(&self) -> usize {
+        self.sealed.len()
+    }
+
+    /// Value `i` of the encrypted value table as stored, header included.
+    pub(crate) fn encrypted_value(&self, i: usize) -> &[u8] {
+        &self.data()[self.sealed[i].range.clone()]
+    }
+
+    /// This file with every field value `old` encrypted re-encrypted with
+    /// `new`, and the number of values changed. See
+    /// [`rotate_keys`](crate::rotate_keys).
+    pub(crate) fn rotate_keys(&self, old: &dyn SectionCodec, new: &dyn SectionCodec) -> Result<(Vec<u8>, usize)> {
+        let old_key = old.key_id()
+            .ok_or_else(|| Error::ParseError(format!("codec {:#x} to rotate from has no key ID", old.id())))?;
+        if new.key_id().is_none() {
+            return Err(Error::ParseError(format!("codec {:#x} to rotate to has no key ID", new.id())));
+        }
+        let bytes = self.data();
+        if self.sealed.is_empty() {
+            return Ok((bytes.to_vec(), 0));
+        }
+
+        // Only the encrypted value table changes; everything after it moves
+        // by the change in its size
+        let mut values = Vec::new();
+        let mut offsets = Vec::with_capacity(self.sealed.len());
+        let mut rotated = 0;
+        for sealed in &self.sealed {
+            offsets.push(values.len());
+            let stored = &bytes[sealed.range.clone()];
+            if sealed.codec == old.id() && sealed.key_id == old_key {
+                values.extend(crate::codec::seal(new, &old.decode(&stored[sealed.header..])?)?);
+                rotated += 1;
+            } else {
+                values.extend_from_slice(stored);
+            }
+        }
+        let Range { start: table_start, end: table_end } = self.sealed_table;
+        let table_size = u32::try_from(8 + 4 * offsets.len() + values.len())
+            .map_err(|_| Error::ValueOutOfRange("encrypted value table exceeds u32::MAX bytes".into()))?;
+        let shift = table_size as i64 - (table_end - table_start) as i64;
+        let moved = |off: u64| (off as i64 + shift) as u64;
+
+        let mut out = Vec::with_capacity((bytes.len() as i64 + shift) as usize);
+        out.extend_from_slice(&bytes[..table_start]);
+        out.extend(table_size.to_le_bytes());
+        out.extend((offsets.len() as u32).to_le_bytes());
+        for offset in offsets {
+            out.extend((offset as u32).to_le_bytes());
+        }
+        out.extend(values);
+        out.extend_from_slice(&bytes[table_end..]);
+
+        // The header offsets of the index and data, and each section's offset
+        let idx_off = moved(read_u64_at(bytes, 32)?);
+        let dat_off = moved(read_u64_at(bytes, 40)?);
+        out[32..40].copy_from_slice(&idx_off.to_le_bytes());
+        out[40..48].copy_from_slice(&dat_off.to_le_bytes());
+        let idx_off = to_usize(idx_off)?;
+        for i in 0..read_u32_at(bytes, 56)? as usize {
+            let entry = idx_off + 8 + i * 32;
+            let offset = moved(read_u64_at(&out, entry + 4)?);
+            out[entry + 4..entry + 12].copy_from_slice(&offset.to_le_bytes());
+        }
+        Ok((out, rotated))
+    }
+
     /// Clear the decompression cache to free memory
     pub fn clear_cache(&self) {
         self.cache.borrow_mut().clear();
@@ -352,12 +1504,12 @@ impl Reader {
             .collect::<Result<Vec<u32>>>()?;
         let start = offsets_end;
 
-        for i in 0..count {
-            let so = start.checked_add(offsets[i] as usize)
+        for (i, &offset) in offsets.iter().enumerate() {
+            let so = start.checked_add(offset as usize)
                 .ok_or_else(|| Error::ParseError("schema entry offset overflow".into()))?;
 
             // Need at least 8 bytes for schema entry header
-            if so.checked_add(8).map_or(true, |end| end > data.len()) {
+            if so.checked_add(8).is_none_or(|end| end > data.len()) {
                 return Err(Error::ParseError(format!("schema entry {} out of bounds", i)));
             }
 
@@ -370,7 +1522,7 @@ impl Reader {
             let mut fo = so + 8;
             for fi in 0..field_count {
                 // Each field entry is 8 bytes
-                if fo.checked_add(8).map_or(true, |end| end > data.len()) {
+                if fo.checked_add(8).is_none_or(|end| end > data.len()) {
                     return Err(Error::ParseError(format!(
                         "schema '{}' field {} out of bounds", name, fi
                     )));
@@ -382,7 +1534,15 @@ impl Reader {
                 let fextra = read_u16_at(data, fo + 6)?;
 
                 let fname = self.get_string(fname_idx as usize)?;
-                let tl_type = TLType::try_from(ftype)?;
+                // Any-typed fields name their declared type like struct fields
+                if ftype == TYPE_CODE_ANY && self.version.0 < 3 {
+                    return Err(self.version_error(&format!("schema '{}' field '{}' is any-typed", name, fname)));
+                }
+                let tl_type = if ftype == TYPE_CODE_ANY {
+                    TLType::Struct
+                } else {
+                    self.declared_type(ftype, || format!("schema '{}' field '{}'", name, fname))?
+                };
 
                 let base = match tl_type {
                     TLType::Bool => "bool".to_string(),
@@ -399,6 +1559,7 @@ impl Reader {
                     TLType::String => "string".to_string(),
                     TLType::Bytes => "bytes".to_string(),
                     TLType::Timestamp => "timestamp".to_string(),
+                    TLType::TimestampNanos => "timestamp_ns".to_string(),
                     TLType::Struct => {
                         // Read struct type name from string table (0xFFFF = no type)
                         if fextra != 0xFFFF {
@@ -428,6 +1589,12 @@ impl Reader {
                 if fflags & 0x02 != 0 {
                     field_type.is_array = true;
                 }
+                if fflags & 0x04 != 0 {
+                    if self.version.0 < 3 {
+                        return Err(self.version_error(&format!("schema '{}' field '{}' is encrypted", name, fname)));
+                    }
+                    self.encrypted.entry(name.clone()).or_insert_with(|| vec![false; field_count])[fi] = true;
+                }
 
                 schema.fields.push(Field::new(fname, field_type));
                 fo += 8;
@@ -436,7 +1603,40 @@ impl Reader {
             self.schema_map.insert(name, self.schemas.len());
             self.schemas.push(schema);
         }
-
+
+        Ok(())
+    }
+
+    /// Read the encrypted value table at `off` (see
+    /// [`Writer::set_field_key`](crate::Writer::set_field_key)): its size,
+    /// the number of values, the offset of each from the end of the
+    /// offsets, then the values, each running to the next.
+    fn parse_encrypted_values(&mut self, off: usize) -> Result<()> {
+        let data = self.data.as_ref();
+        let size = read_u32_at(data, off)? as usize;
+        let count = read_u32_at(data, off + 4)? as usize;
+        let end = off.checked_add(size).filter(|&end| end <= data.len())
+            .ok_or_else(|| Error::ParseError("encrypted value table out of bounds".into()))?;
+        let start = count.checked_mul(4).and_then(|n| (off + 8).checked_add(n)).filter(|&start| start <= end)
+            .ok_or_else(|| Error::ParseError("encrypted value count exceeds the table".into()))?;
+        let offsets = (0..count)
+            .map(|i| Ok(start + read_u32_at(data, off + 8 + i * 4)? as usize))
+            .collect::<Result<Vec<usize>>>()?;
+        for (i, &value_start) in offsets.iter().enumerate() {
+            let value_end = offsets.get(i + 1).copied().unwrap_or(end);
+            if value_start > value_end || value_end > end {
+                return Err(Error::ParseError(format!("encrypted value {} out of bounds", i)));
+            }
+            let header = crate::codec::read_key_header(&data[value_start..value_end])?;
+            self.sealed.push(SealedValue {
+                codec: header.codec,
+                key_id: header.key_id.to_string(),
+                algorithm: header.algorithm.to_string(),
+                range: value_start..value_end,
+                header: header.len,
+            });
+        }
+        self.sealed_table = off..end;
         Ok(())
     }
 
@@ -477,26 +1677,34 @@ impl Reader {
             .collect::<Result<Vec<u32>>>()?;
         let union_data_start = union_offsets_end;
 
-        for i in 0..union_count {
-            let uo = union_data_start.checked_add(union_offsets[i] as usize)
+        for (i, &offset) in union_offsets.iter().enumerate() {
+            let uo = union_data_start.checked_add(offset as usize)
                 .ok_or_else(|| Error::ParseError("union entry offset overflow".into()))?;
 
             // Need at least 8 bytes for union entry header
-            if uo.checked_add(8).map_or(true, |end| end > data.len()) {
+            if uo.checked_add(8).is_none_or(|end| end > data.len()) {
                 return Err(Error::ParseError(format!("union entry {} out of bounds", i)));
             }
 
             let name_idx = read_u32_at(data, uo)?;
             let variant_count = read_u16_at(data, uo + 4)? as usize;
-            // uo + 6..uo + 8 is flags (reserved)
+            let flags = read_u16_at(data, uo + 6)?;
 
             let name = self.get_string(name_idx as usize)?;
+            if flags & !UNION_FLAG_ORDINAL_TAGS != 0 {
+                return Err(Error::ParseError(format!("union '{}' has unknown flags 0x{:04X}", name, flags)));
+            }
+            if flags & UNION_FLAG_ORDINAL_TAGS != 0 && variant_count > MAX_UNION_VARIANTS {
+                return Err(Error::ParseError(format!(
+                    "union '{}' has {} variants; ordinals 0xFFFE and 0xFFFF are reserved", name, variant_count
+                )));
+            }
             let mut union = Union::new(&name);
 
             let mut vo = uo + 8;
             for vi in 0..variant_count {
                 // Need at least 8 bytes for variant header
-                if vo.checked_add(8).map_or(true, |end| end > data.len()) {
+                if vo.checked_add(8).is_none_or(|end| end > data.len()) {
                     return Err(Error::ParseError(format!(
                         "union '{}' variant {} out of bounds", name, vi
                     )));
@@ -512,7 +1720,7 @@ impl Reader {
                 let mut fo = vo + 8;
                 for fi in 0..field_count {
                     // Each field entry is 8 bytes
-                    if fo.checked_add(8).map_or(true, |end| end > data.len()) {
+                    if fo.checked_add(8).is_none_or(|end| end > data.len()) {
                         return Err(Error::ParseError(format!(
                             "union '{}' variant '{}' field {} out of bounds", name, vname, fi
                         )));
@@ -524,7 +1732,7 @@ impl Reader {
                     let fextra = read_u16_at(data, fo + 6)?;
 
                     let fname = self.get_string(fname_idx as usize)?;
-                    let tl_type = TLType::try_from(ftype)?;
+                    let tl_type = self.declared_type(ftype, || format!("union '{}' variant '{}' field '{}'", name, vname, fname))?;
 
                     let base = match tl_type {
                         TLType::Bool => "bool".to_string(),
@@ -541,6 +1749,7 @@ impl Reader {
                         TLType::String => "string".to_string(),
                         TLType::Bytes => "bytes".to_string(),
                         TLType::Timestamp => "timestamp".to_string(),
+                        TLType::TimestampNanos => "timestamp_ns".to_string(),
                         TLType::Struct => {
                             if fextra != 0xFFFF {
                                 self.get_string(fextra as usize)?
@@ -575,6 +1784,7 @@ impl Reader {
 
             self.union_map.insert(name, self.unions.len());
             self.unions.push(union);
+            self.union_flags.push(flags);
         }
 
         Ok(())
@@ -603,11 +1813,17 @@ impl Reader {
             let ptype = data[o + 22];
             let flags = data[o + 23];
             let item_count = read_u32_at(data, o + 24)?;
+            let chunk_index = read_u32_at(data, o + 28)?;
 
             let key = self.get_string(key_idx as usize)?;
+            if self.version.0 < 3 {
+                if let Some((_, feature)) = SECTION_FLAGS_SINCE_3.iter().find(|(flag, _)| flags & flag != 0) {
+                    return Err(self.version_error(&format!("section '{}' is {}", key, feature)));
+                }
+            }
 
             // Validate section data range against file bounds
-            let sec_start = offset as usize;
+            let sec_start = to_usize(offset)?;
             let sec_end = sec_start.checked_add(size as usize)
                 .ok_or_else(|| Error::ParseError(format!(
                     "section '{}' offset overflow", key
@@ -619,26 +1835,87 @@ impl Reader {
                 )));
             }
 
-            self.sections.insert(key, SectionInfo {
+            let descriptor = if flags & 0x80 != 0 {
+                let len = read_u32_at(&data[sec_start..sec_end], 0)?;
+                len.checked_add(4).filter(|&n| n <= size)
+                    .ok_or_else(|| Error::ParseError(format!("section '{}' descriptor exceeds the section", key)))?
+            } else {
+                0
+            };
+            let info = SectionInfo {
                 offset,
                 size,
                 uncompressed_size: uncompressed,
                 schema_idx: if schema_idx == 0xFFFF { -1 } else { schema_idx as i32 },
-                tl_type: TLType::try_from(ptype)?,
-                compressed: flags & 0x01 != 0,
+                tl_type: self.declared_type(ptype, || format!("section '{}'", key))?,
+                // A chunked section without a codec compresses chunk by chunk
+                compressed: flags & 0x01 != 0 && (flags & 0x04 == 0 || flags & 0x10 != 0),
+                chunks_compressed: flags & 0x01 != 0 && flags & 0x04 != 0 && flags & 0x10 == 0,
                 is_array: flags & 0x02 != 0,
                 item_count,
-            });
+                chunk_index: if flags & 0x04 != 0 { Some(chunk_index) } else { None },
+                // Version 2 writers leave the field 0 in unchunked sections
+                lookup_index: if flags & 0x04 == 0 && chunk_index != 0 && self.version.0 >= 3 { Some(chunk_index) } else { None },
+                codec: flags & 0x10 != 0,
+                column_stats: flags & 0x20 != 0,
+                descriptor,
+                parts: Vec::new(),
+            };
             o += 32;
+            if flags & 0x08 == 0 {
+                self.sections.insert(key, info);
+                continue;
+            }
+            // A continuation extends the table last written under its key
+            match self.sections.get_mut(&key) {
+                Some(head) if head.is_array && head.schema_idx >= 0 && info.is_array
+                    && info.schema_idx == head.schema_idx
+                    && info.chunk_index.is_some() == head.chunk_index.is_some() =>
+                {
+                    head.parts.push(info);
+                }
+                _ => return Err(Error::ParseError(format!(
+                    "continuation of section '{}' does not follow a table of the same schema", key
+                ))),
+            }
         }
 
+        self.sorted_keys = (0..self.sections.len()).collect();
+        let sections = &self.sections;
+        self.sorted_keys.sort_by(|&a, &b| sections.get_index(a).unwrap().0.cmp(sections.get_index(b).unwrap().0));
         Ok(())
     }
 
+    /// The type a type code of the schema table or section index stands
+    /// for, refusing nanosecond timestamps in 2.x files.
+    fn declared_type(&self, code: u8, what: impl FnOnce() -> String) -> Result<TLType> {
+        let tl_type = TLType::try_from(code)?;
+        if tl_type == TLType::TimestampNanos && self.version.0 < 3 {
+            return Err(self.version_error(&format!("{} holds nanosecond timestamps", what())));
+        }
+        Ok(tl_type)
+    }
+
+    /// Error for a 3.0 feature found in a 2.x file.
+    fn version_error(&self, feature: &str) -> Error {
+        Error::ParseError(format!("{}, which version {}.{} files cannot hold", feature, self.version.0, self.version.1))
+    }
+
     fn decode_struct_array(&self, cursor: &mut Cursor, schema_idx: usize, depth: usize) -> Result<Value> {
-        if depth > MAX_DECODE_DEPTH {
-            return Err(Error::ParseError("maximum decode nesting depth exceeded".into()));
+        self.check_depth(depth)?;
+        let (count, schema, bitmap_size) = self.struct_array_header(cursor, schema_idx)?;
+        let capacity = count.min(cursor.remaining()).min(MAX_COLLECTION_SIZE);
+        let mut result = Vec::with_capacity(capacity);
+
+        for _ in 0..count {
+            result.push(self.decode_struct_row(cursor, schema, bitmap_size, depth)?);
         }
+
+        Ok(Value::Array(result))
+    }
+
+    /// Read the header of a struct array: its row count, schema and bitmap size.
+    fn struct_array_header(&self, cursor: &mut Cursor, schema_idx: usize) -> Result<(usize, &Schema, usize)> {
         let count = cursor.read_u32()?;
         if count as usize > MAX_COLLECTION_SIZE {
             return Err(Error::ParseError(format!(
@@ -654,69 +1931,141 @@ impl Reader {
                 schema_idx, self.schemas.len()
             )));
         }
-        let schema = &self.schemas[schema_idx];
-        let capacity = (count as usize).min(cursor.remaining()).min(MAX_COLLECTION_SIZE);
-        let mut result = Vec::with_capacity(capacity);
+        Ok((count as usize, &self.schemas[schema_idx], bitmap_size))
+    }
 
+    /// Decode one row of a struct array: its field-state bitmaps, then the
+    /// values of the fields that have one.
+    fn decode_struct_row(&self, cursor: &mut Cursor, schema: &Schema, bitmap_size: usize, depth: usize) -> Result<Value> {
+        self.decode_projected_row(cursor, schema, bitmap_size, None, depth)
+    }
+
+    /// Decode a table row, leaving out fields whose entry in `keep` is false.
+    fn decode_projected_row(&self, cursor: &mut Cursor, schema: &Schema, bitmap_size: usize, keep: Option<&[bool]>, depth: usize) -> Result<Value> {
         // Two-bit field state encoding: bitmap_size = 2 * bms
         let bms = bitmap_size / 2;
-        for _ in 0..count {
-            let mut bitmap = Vec::with_capacity(bitmap_size.min(cursor.remaining()));
-            for _ in 0..bitmap_size {
-                bitmap.push(cursor.read_u8()?);
-            }
-            let lo_bitmap = &bitmap[..bms.min(bitmap.len())];
-            let hi_bitmap = if bitmap.len() > bms { &bitmap[bms..] } else { &[] as &[u8] };
-
-            // Null array element: all fields code=2 (lo=0, hi=1)
-            let all_absent = (0..schema.fields.len()).all(|i| {
-                let lo = i / 8 < lo_bitmap.len() && (lo_bitmap[i / 8] & (1 << (i % 8))) != 0;
-                let hi = i / 8 < hi_bitmap.len() && (hi_bitmap[i / 8] & (1 << (i % 8))) != 0;
-                !lo && hi
-            });
+        let mut bitmap = Vec::with_capacity(bitmap_size.min(cursor.remaining()));
+        for _ in 0..bitmap_size {
+            bitmap.push(cursor.read_u8()?);
+        }
+        let lo_bitmap = &bitmap[..bms.min(bitmap.len())];
+        let hi_bitmap = if bitmap.len() > bms { &bitmap[bms..] } else { &[] as &[u8] };
 
-            if all_absent {
-                result.push(Value::Null);
-            } else {
-                let mut obj = ObjectMap::new();
-                for (i, field) in schema.fields.iter().enumerate() {
-                    let lo = i / 8 < lo_bitmap.len() && (lo_bitmap[i / 8] & (1 << (i % 8))) != 0;
-                    let hi = i / 8 < hi_bitmap.len() && (hi_bitmap[i / 8] & (1 << (i % 8))) != 0;
-                    let code = (lo as u8) | ((hi as u8) << 1);
-                    match code {
-                        0 => {
-                            // Has value — decode inline data
-                            let tl_type = if self.union_map.contains_key(&field.field_type.base) {
-                                TLType::Tagged
-                            } else {
-                                field.field_type.to_tl_type()
-                            };
-                            obj.insert(field.name.clone(), self.decode_value(cursor, tl_type, depth + 1)?);
-                        }
-                        1 => {
-                            // Explicit null — always preserve
-                            obj.insert(field.name.clone(), Value::Null);
-                        }
-                        2 => {
-                            // Absent — drop for nullable fields
-                            if !field.field_type.nullable {
-                                obj.insert(field.name.clone(), Value::Null);
-                            }
-                        }
-                        _ => {} // reserved
-                    }
+        // Null array element: all fields code=2 (lo=0, hi=1)
+        let all_absent = (0..schema.fields.len()).all(|i| {
+            let lo = i / 8 < lo_bitmap.len() && (lo_bitmap[i / 8] & (1 << (i % 8))) != 0;
+            let hi = i / 8 < hi_bitmap.len() && (hi_bitmap[i / 8] & (1 << (i % 8))) != 0;
+            !lo && hi
+        });
+
+        if all_absent {
+            return Ok(Value::Null);
+        }
+
+        let sealed = self.sealed_fields(schema);
+        let mut obj = ObjectMap::with_capacity(schema.fields.len());
+        for (i, field) in schema.fields.iter().enumerate() {
+            let lo = i / 8 < lo_bitmap.len() && (lo_bitmap[i / 8] & (1 << (i % 8))) != 0;
+            let hi = i / 8 < hi_bitmap.len() && (hi_bitmap[i / 8] & (1 << (i % 8))) != 0;
+            let code = (lo as u8) | ((hi as u8) << 1);
+            let sealed = sealed.is_some_and(|sealed| sealed.get(i).copied().unwrap_or(false));
+            if keep.is_some_and(|keep| !keep.get(i).copied().unwrap_or(false)) {
+                match code {
+                    // An encrypted value is an index into the encrypted value table
+                    0 if sealed => drop(cursor.read_u32()?),
+                    0 => self.skip_field(cursor, field, depth + 1)?,
+                    _ => {}
+                }
+                continue;
+            }
+            match code {
+                0 => {
+                    // Has value — decode inline data
+                    let value = if sealed {
+                        self.decode_sealed(cursor, schema, field)?
+                    } else {
+                        self.decode_field(cursor, field, depth + 1)?
+                    };
+                    obj.insert(field.name.clone(), value);
+                }
+                1 => {
+                    // Explicit null — always preserve
+                    obj.insert(field.name.clone(), Value::Null);
+                }
+                2 if !field.field_type.nullable => {
+                    // Absent — drop for nullable fields
+                    obj.insert(field.name.clone(), Value::Null);
                 }
-                result.push(Value::Object(obj));
+                _ => {} // reserved
             }
         }
+        Ok(Value::Object(obj))
+    }
+
+    /// Whether each field of `schema` is encrypted, or `None` if none is.
+    fn sealed_fields(&self, schema: &Schema) -> Option<&[bool]> {
+        if self.encrypted.is_empty() {
+            return None;
+        }
+        self.encrypted.get(&schema.name).map(Vec::as_slice)
+    }
 
-        Ok(Value::Array(result))
+    /// Decrypt and decode the value of the encrypted `field` of `schema`,
+    /// whose index in the encrypted value table is at `cursor`.
+    fn decode_sealed(&self, cursor: &mut Cursor, schema: &Schema, field: &Field) -> Result<Value> {
+        let index = cursor.read_u32()? as usize;
+        let sealed = self.sealed.get(index).ok_or_else(|| Error::ParseError(format!(
+            "encrypted value index {} out of bounds ({} values)", index, self.sealed.len()
+        )))?;
+        let codec = self.codecs.get(&(sealed.codec, Some(sealed.key_id.clone())))
+            .ok_or_else(|| Error::MissingKey {
+                field: format!("{}.{}", schema.name, field.name),
+                codec: sealed.codec,
+                key_id: sealed.key_id.clone(),
+            })?;
+        let plain = codec.decode(&self.data()[sealed.range.start + sealed.header..sealed.range.end])?;
+        // The value was written as the only section of a file of its own
+        let value = Reader::from_bytes(plain)?.with_options(self.options).get("")?;
+        Ok(if field.field_type.base == "secret" { crate::secret::conceal(value) } else { value })
     }
 
-    fn decode_array(&self, cursor: &mut Cursor, depth: usize) -> Result<Value> {
-        if depth > MAX_DECODE_DEPTH {
-            return Err(Error::ParseError("maximum decode nesting depth exceeded".into()));
+    /// Decode the value of a present struct field.
+    fn decode_field(&self, cursor: &mut Cursor, field: &Field, depth: usize) -> Result<Value> {
+        match self.union_map.get(&field.field_type.base) {
+            Some(&union_idx) => self.decode_union_field(cursor, union_idx, field.field_type.is_array, depth),
+            None if self.is_any_type(&field.field_type.base) => {
+                let value = self.decode_any_field(cursor, field.field_type.is_array, depth)?;
+                Ok(if field.field_type.base == "secret" { crate::secret::conceal(value) } else { value })
+            }
+            None => self.decode_value(cursor, field.field_type.to_tl_type(), depth),
+        }
+    }
+
+    /// Step over the value of a present struct field without building it.
+    /// Fixed-size scalars and bytes are skipped by length; other values are
+    /// decoded and dropped, since only walking them gives their size.
+    fn skip_field(&self, cursor: &mut Cursor, field: &Field, depth: usize) -> Result<()> {
+        if self.union_map.contains_key(&field.field_type.base) || self.is_any_type(&field.field_type.base) {
+            return self.decode_field(cursor, field, depth).map(drop);
         }
+        let len = match field.field_type.to_tl_type() {
+            TLType::Null => 0,
+            TLType::Bool | TLType::Int8 | TLType::UInt8 => 1,
+            TLType::Int16 | TLType::UInt16 => 2,
+            TLType::Int32 | TLType::UInt32 | TLType::Float32 | TLType::String | TLType::Ref | TLType::JsonNumber => 4,
+            TLType::Int64 | TLType::UInt64 | TLType::Float64 => 8,
+            TLType::Timestamp => 10,
+            TLType::TimestampNanos => 11,
+            TLType::Bytes => to_usize(cursor.read_varint()?)?,
+            _ => return self.decode_field(cursor, field, depth).map(drop),
+        };
+        cursor.check_bounds(len)?;
+        cursor.pos += len;
+        Ok(())
+    }
+
+    fn decode_array(&self, cursor: &mut Cursor, depth: usize) -> Result<Value> {
+        self.check_depth(depth)?;
         let count = cursor.read_u32()?;
         if count == 0 {
             return Ok(Value::Array(Vec::new()));
@@ -747,11 +2096,9 @@ impl Reader {
     }
 
     fn decode_object(&self, cursor: &mut Cursor, depth: usize) -> Result<Value> {
-        if depth > MAX_DECODE_DEPTH {
-            return Err(Error::ParseError("maximum decode nesting depth exceeded".into()));
-        }
+        self.check_depth(depth)?;
         let count = cursor.read_u16()?;
-        let mut obj = ObjectMap::new();
+        let mut obj = ObjectMap::with_capacity(count as usize);
 
         for _ in 0..count {
             let key_idx = cursor.read_u32()?;
@@ -764,9 +2111,7 @@ impl Reader {
     }
 
     fn decode_struct(&self, cursor: &mut Cursor, depth: usize) -> Result<Value> {
-        if depth > MAX_DECODE_DEPTH {
-            return Err(Error::ParseError("maximum decode nesting depth exceeded".into()));
-        }
+        self.check_depth(depth)?;
         let schema_idx = cursor.read_u16()? as usize;
         if schema_idx >= self.schemas.len() {
             return Err(Error::ParseError(format!(
@@ -775,7 +2120,7 @@ impl Reader {
             )));
         }
         let schema = &self.schemas[schema_idx];
-        let bms = (schema.fields.len() + 7) / 8;
+        let bms = schema.fields.len().div_ceil(8);
         let bitmap_size = 2 * bms;
 
         let mut bitmap = Vec::with_capacity(bitmap_size.min(cursor.remaining()));
@@ -785,7 +2130,8 @@ impl Reader {
         let lo_bitmap = &bitmap[..bms.min(bitmap.len())];
         let hi_bitmap = if bitmap.len() > bms { &bitmap[bms..] } else { &[] as &[u8] };
 
-        let mut obj = ObjectMap::new();
+        let sealed = self.sealed_fields(schema);
+        let mut obj = ObjectMap::with_capacity(schema.fields.len());
         for (i, field) in schema.fields.iter().enumerate() {
             let lo = i / 8 < lo_bitmap.len() && (lo_bitmap[i / 8] & (1 << (i % 8))) != 0;
             let hi = i / 8 < hi_bitmap.len() && (hi_bitmap[i / 8] & (1 << (i % 8))) != 0;
@@ -793,22 +2139,20 @@ impl Reader {
             match code {
                 0 => {
                     // Has value — decode inline data
-                    let tl_type = if self.union_map.contains_key(&field.field_type.base) {
-                        TLType::Tagged
+                    let value = if sealed.is_some_and(|sealed| sealed.get(i).copied().unwrap_or(false)) {
+                        self.decode_sealed(cursor, schema, field)?
                     } else {
-                        field.field_type.to_tl_type()
+                        self.decode_field(cursor, field, depth + 1)?
                     };
-                    obj.insert(field.name.clone(), self.decode_value(cursor, tl_type, depth + 1)?);
+                    obj.insert(field.name.clone(), value);
                 }
                 1 => {
                     // Explicit null — always preserve
                     obj.insert(field.name.clone(), Value::Null);
                 }
-                2 => {
+                2 if !field.field_type.nullable => {
                     // Absent — drop for nullable fields
-                    if !field.field_type.nullable {
-                        obj.insert(field.name.clone(), Value::Null);
-                    }
+                    obj.insert(field.name.clone(), Value::Null);
                 }
                 _ => {} // reserved
             }
@@ -817,10 +2161,83 @@ impl Reader {
         Ok(Value::Object(obj))
     }
 
-    fn decode_map(&self, cursor: &mut Cursor, depth: usize) -> Result<Value> {
-        if depth > MAX_DECODE_DEPTH {
-            return Err(Error::ParseError("maximum decode nesting depth exceeded".into()));
+    /// Decode a value of an any-typed schema field (or an array of them).
+    ///
+    /// Single values are prefixed by their type code; arrays use the
+    /// heterogeneous layout with element type [`TYPE_CODE_ANY`].
+    fn decode_any_field(&self, cursor: &mut Cursor, is_array: bool, depth: usize) -> Result<Value> {
+        if is_array {
+            return self.decode_value(cursor, TLType::Array, depth);
+        }
+        let t = TLType::try_from(cursor.read_u8()?)?;
+        self.decode_value(cursor, t, depth)
+    }
+
+    fn is_any_type(&self, base: &str) -> bool {
+        FieldType::new(base).to_tl_type() == TLType::Struct
+            && !self.schema_map.contains_key(base)
+            && !self.union_map.contains_key(base)
+    }
+
+    /// Decode a value of a union-typed schema field (or an array of them).
+    fn decode_union_field(&self, cursor: &mut Cursor, union_idx: usize, is_array: bool, depth: usize) -> Result<Value> {
+        self.check_depth(depth)?;
+        let ordinal_tags = self.union_flags[union_idx] & UNION_FLAG_ORDINAL_TAGS != 0;
+        if !is_array {
+            return if ordinal_tags {
+                self.decode_union_value(cursor, union_idx, depth)
+            } else {
+                self.decode_value(cursor, TLType::Tagged, depth)
+            };
+        }
+        if !ordinal_tags {
+            return self.decode_array(cursor, depth);
+        }
+
+        let count = cursor.read_u32()?;
+        if count == 0 {
+            return Ok(Value::Array(Vec::new()));
+        }
+        if count as usize > MAX_COLLECTION_SIZE {
+            return Err(Error::ParseError(format!(
+                "array element count {} exceeds limit of {}", count, MAX_COLLECTION_SIZE
+            )));
+        }
+        cursor.read_u8()?; // element type (always tagged)
+        let capacity = (count as usize).min(cursor.remaining()).min(MAX_COLLECTION_SIZE);
+        let mut result = Vec::with_capacity(capacity);
+        for _ in 0..count {
+            result.push(self.decode_union_value(cursor, union_idx, depth + 1)?);
         }
+        Ok(Value::Array(result))
+    }
+
+    /// Decode an ordinal-tagged union value: `[ordinal: u16][type: u8][payload]`.
+    fn decode_union_value(&self, cursor: &mut Cursor, union_idx: usize, depth: usize) -> Result<Value> {
+        let ordinal = cursor.read_u16()?;
+        let tag = match ordinal {
+            // Untagged value
+            0xFFFE => {
+                let t = TLType::try_from(cursor.read_u8()?)?;
+                return self.decode_value(cursor, t, depth + 1);
+            }
+            // Tag outside the union, stored by name
+            0xFFFF => self.get_string(cursor.read_u32()? as usize)?,
+            n => {
+                let union = &self.unions[union_idx];
+                union.variant_at(n as usize).ok_or_else(|| Error::ParseError(format!(
+                    "union '{}' variant ordinal {} out of bounds ({} variants)",
+                    union.name, n, union.variants.len()
+                )))?.name.clone()
+            }
+        };
+        let inner_type = TLType::try_from(cursor.read_u8()?)?;
+        let inner = self.decode_value(cursor, inner_type, depth + 1)?;
+        Ok(Value::Tagged(tag, Box::new(inner)))
+    }
+
+    fn decode_map(&self, cursor: &mut Cursor, depth: usize) -> Result<Value> {
+        self.check_depth(depth)?;
         let count = cursor.read_u32()?;
         if count as usize > MAX_COLLECTION_SIZE {
             return Err(Error::ParseError(format!(
@@ -849,9 +2266,7 @@ impl Reader {
     }
 
     fn decode_value(&self, cursor: &mut Cursor, tl_type: TLType, depth: usize) -> Result<Value> {
-        if depth > MAX_DECODE_DEPTH {
-            return Err(Error::ParseError("maximum decode nesting depth exceeded".into()));
-        }
+        self.check_depth(depth)?;
         Ok(match tl_type {
             TLType::Null => Value::Null,
             TLType::Bool => Value::Bool(cursor.read_u8()? != 0),
@@ -870,7 +2285,7 @@ impl Reader {
                 Value::String(self.get_string(idx as usize)?)
             }
             TLType::Bytes => {
-                let len = cursor.read_varint()? as usize;
+                let len = to_usize(cursor.read_varint()?)?;
                 Value::Bytes(cursor.read_bytes(len)?)
             }
             TLType::Array => self.decode_array(cursor, depth)?,
@@ -893,6 +2308,17 @@ impl Reader {
                 let tz = cursor.read_i16()?;
                 Value::Timestamp(ts, tz)
             }
+            TLType::TimestampNanos => {
+                if self.version.0 < 3 {
+                    return Err(self.version_error("a nanosecond timestamp"));
+                }
+                let ns = cursor.read_i64()?;
+                let tz = cursor.read_i16()?;
+                let code = cursor.read_u8()?;
+                let precision = TimePrecision::from_code(code)
+                    .ok_or_else(|| Error::ParseError(format!("invalid timestamp precision {}", code)))?;
+                Value::TimestampNanos(ns, tz, precision)
+            }
             TLType::JsonNumber => {
                 let idx = cursor.read_u32()?;
                 Value::JsonNumber(self.get_string(idx as usize)?)
@@ -905,6 +2331,141 @@ impl Reader {
     }
 }
 
+/// Start a new line at `depth` levels of indentation, if pretty-printing.
+#[cfg(feature = "json")]
+fn write_json_break<W: Write>(w: &mut W, depth: usize, opts: crate::JsonOptions) -> Result<()> {
+    if opts.pretty {
+        w.write_all(b"\n")?;
+        for _ in 0..depth {
+            w.write_all(b"  ")?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `value` as JSON nested `depth` levels deep.
+#[cfg(feature = "json")]
+fn write_json_value<W: Write>(w: &mut W, value: &Value, depth: usize, opts: crate::JsonOptions) -> Result<()> {
+    let json = crate::tealeaf_to_json_value(value, opts.secrets);
+    if !opts.pretty {
+        return serde_json::to_writer(w, &json).map_err(json_error);
+    }
+    // JSON strings escape newlines, so every newline is an indented line break
+    let text = serde_json::to_string_pretty(&json).map_err(json_error)?;
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            write_json_break(w, depth, opts)?;
+        }
+        w.write_all(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+fn json_error(e: serde_json::Error) -> Error {
+    Error::ParseError(format!("JSON serialization failed: {}", e))
+}
+
+/// Read the chunk index at `off` in `data` (layout in
+/// `Writer::append_chunk_index`) of a table of `row_count` rows.
+fn parse_chunk_index(data: &[u8], off: usize, row_count: usize) -> Result<Vec<ChunkStats>> {
+    use crate::writer::STAT_NONE;
+
+    let mut cursor = Cursor::new(data);
+    cursor.pos = off;
+    let chunk_rows = cursor.read_u32()? as usize;
+    let chunk_count = cursor.read_u32()? as usize;
+    let column_count = cursor.read_u16()? as usize;
+    let kinds = cursor.read_bytes(column_count)?;
+    if chunk_rows == 0 || chunk_count != row_count.div_ceil(chunk_rows) {
+        return Err(Error::ParseError(format!(
+            "chunk index of {} chunks of {} rows does not cover {} rows", chunk_count, chunk_rows, row_count
+        )));
+    }
+    let chunk_size = 4 + kinds.iter().map(|&k| if k == STAT_NONE { 4 } else { 20 }).sum::<usize>();
+    if chunk_count.checked_mul(chunk_size).is_none_or(|len| len > cursor.remaining()) {
+        return Err(Error::ParseError("chunk index out of bounds".into()));
+    }
+
+    let mut chunks: Vec<ChunkStats> = Vec::with_capacity(chunk_count);
+    for i in 0..chunk_count {
+        let offset = cursor.read_u32()?;
+        if offset < 8 || offset as usize > off || chunks.last().is_some_and(|prev| offset < prev.offset) {
+            return Err(Error::ParseError(format!("chunk row offset {} out of bounds", offset)));
+        }
+        if let Some(prev) = chunks.last_mut() {
+            prev.end = offset;
+        }
+        let mut columns = Vec::with_capacity(column_count);
+        for &kind in &kinds {
+            let null_count = cursor.read_u32()?;
+            let (min, max) = read_min_max(&mut cursor, kind)?;
+            columns.push(ColumnStats { null_count, min, max });
+        }
+        let first_row = i * chunk_rows;
+        chunks.push(ChunkStats { first_row, rows: chunk_rows.min(row_count - first_row), columns, offset, end: off as u32, compressed: false });
+    }
+    Ok(chunks)
+}
+
+/// Read the min and max of one column's statistics of `kind`; both `None`
+/// if the column had no values.
+fn read_min_max(cursor: &mut Cursor<'_>, kind: u8) -> Result<(Option<Value>, Option<Value>)> {
+    use crate::writer::{STAT_NONE, STAT_INT, STAT_UINT, STAT_FLOAT, STAT_TIMESTAMP};
+
+    Ok(match kind {
+        STAT_NONE => (None, None),
+        STAT_INT | STAT_TIMESTAMP => {
+            let (min, max) = (cursor.read_i64()?, cursor.read_i64()?);
+            let value = |x| if kind == STAT_INT { Value::Int(x) } else { Value::Timestamp(x, 0) };
+            if min <= max { (Some(value(min)), Some(value(max))) } else { (None, None) }
+        }
+        STAT_UINT => {
+            let (min, max) = (cursor.read_u64()?, cursor.read_u64()?);
+            if min <= max { (Some(Value::UInt(min)), Some(Value::UInt(max))) } else { (None, None) }
+        }
+        STAT_FLOAT => {
+            let (min, max) = (cursor.read_f64()?, cursor.read_f64()?);
+            if min <= max { (Some(Value::Float(min)), Some(Value::Float(max))) } else { (None, None) }
+        }
+        _ => return Err(Error::ParseError(format!("unknown chunk statistics kind {}", kind))),
+    })
+}
+
+/// Offset of the column statistics block stored, with its offset last, at
+/// the end of a table section's `data` (layout in
+/// `writer::encode_column_stats`).
+fn column_stats_offset(data: &[u8]) -> Result<usize> {
+    let end = data.len().checked_sub(4)
+        .ok_or_else(|| Error::ParseError("column statistics out of bounds".into()))?;
+    let off = read_u32_at(data, end)? as usize;
+    if off < 8 || off > end {
+        return Err(Error::ParseError(format!("column statistics offset {} out of bounds", off)));
+    }
+    Ok(off)
+}
+
+/// Read the column statistics block at `off` in `data` for a table of `schema`.
+fn parse_column_stats(data: &[u8], off: usize, schema: &Schema) -> Result<Vec<ColumnSummary>> {
+    let mut cursor = Cursor::new(data);
+    cursor.pos = off;
+    let column_count = cursor.read_u16()? as usize;
+    if column_count != schema.fields.len() {
+        return Err(Error::ParseError(format!(
+            "column statistics of {} columns for the {} fields of '{}'", column_count, schema.fields.len(), schema.name
+        )));
+    }
+    let mut columns = Vec::with_capacity(column_count);
+    for field in &schema.fields {
+        let kind = cursor.read_u8()?;
+        let null_count = cursor.read_u32()?;
+        let distinct = cursor.read_u32()?;
+        let (min, max) = read_min_max(&mut cursor, kind)?;
+        columns.push(ColumnSummary { name: field.name.clone(), null_count, distinct, min, max });
+    }
+    Ok(columns)
+}
+
 // Simple cursor for reading binary data with bounds checking
 struct Cursor<'a> {
     data: &'a [u8],
@@ -1064,6 +2625,543 @@ mod tests {
         std::fs::remove_file(&path).ok();
     }
 
+    #[test]
+    fn test_max_depth() {
+        let nested = |depth| (0..depth).fold(Value::Int(1), |v, _| Value::Array(vec![v]));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deep.tlbx");
+        let mut w = Writer::new();
+        w.add_section("ten", &nested(10), None).unwrap();
+        w.add_section("deep", &nested(40), None).unwrap();
+        w.write(&path, false).unwrap();
+
+        let r = Reader::open(&path).unwrap();
+        assert_eq!(ReaderOptions::default().max_depth, ReaderOptions::DEFAULT_MAX_DEPTH);
+        assert_eq!(r.get("deep").unwrap(), nested(40));
+
+        let r = r.with_options(ReaderOptions::default().with_max_depth(5));
+        assert!(r.cache.borrow().is_empty());
+        let err = r.get("ten").unwrap_err();
+        assert!(matches!(err, Error::DepthExceeded { limit: 5 }));
+        assert_eq!(err.to_string(), "Nesting depth exceeds the limit of 5");
+        let r = r.with_options(ReaderOptions::default().with_max_depth(10));
+        assert_eq!(r.get("ten").unwrap(), nested(10));
+        assert!(matches!(r.get("deep"), Err(Error::DepthExceeded { limit: 10 })));
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_section_fields() {
+        let doc = crate::TeaLeaf::parse(r#"
+            @struct pt (x: int, y: int)
+            settings: {theme: dark, size: 12, tags: [a, b], window: {w: 800, h: ~}}
+            events: [{id: 1, at: 2024-01-15T10:00:00Z, data: {ok: true}}, {id: 2, note: "late", data: {ok: 1}}, 3]
+            ratio: 0.5
+            points: @table pt [(1, 2)]
+        "#).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        for compress in [false, true] {
+            let mut w = Writer::new();
+            w.set_describe_sections(true);
+            for (key, value) in &doc.data {
+                w.add_section(key, value, doc.binding(key).and_then(|name| doc.schema(name))).unwrap();
+            }
+            let path = dir.path().join(format!("described-{}.tlbx", compress));
+            w.write(&path, compress).unwrap();
+
+            let r = Reader::open(&path).unwrap();
+            let shape = |fields: &[SectionField]| -> Vec<String> {
+                fields.iter().map(|f| format!("{}: {}", f.name, f.field_type)).collect()
+            };
+            let settings = r.section_fields("settings").unwrap().unwrap();
+            assert_eq!(shape(&settings), vec!["theme: string", "size: int", "tags: []string", "window: object"]);
+            assert_eq!(shape(&settings[3].fields), vec!["w: int", "h: null?"]);
+            let events = r.section_fields("events").unwrap().unwrap();
+            assert_eq!(shape(&events), vec!["id: int", "at: timestamp?", "data: object", "note: string?"]);
+            assert_eq!(shape(&events[2].fields), vec!["ok: any"]);
+            assert_eq!(r.section_fields("ratio").unwrap(), None);
+            assert_eq!(r.section_fields("points").unwrap(), None);
+            assert!(r.section_fields("none").is_err());
+            assert!(r.cache.borrow().is_empty());
+
+            assert_eq!(crate::TeaLeaf::from_reader(&r).unwrap().data, doc.data);
+        }
+
+        // A 2.x reader would decode the descriptor as data
+        let mut bytes = std::fs::read(dir.path().join("described-false.tlbx")).unwrap();
+        bytes[4..8].copy_from_slice(&[2, 0, 1, 0]);
+        let err = Reader::from_bytes(bytes).err().unwrap();
+        assert!(err.to_string().contains("section 'settings' is described, which version 2.1"), "{}", err);
+    }
+
+    #[test]
+    fn test_access_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.tlbx");
+        let mut w = Writer::new();
+        w.add_section("small", &Value::Int(1), None).unwrap();
+        w.add_section("big", &Value::Array((0..1000).map(Value::Int).collect()), None).unwrap();
+        w.write(&path, false).unwrap();
+
+        let r = Reader::open(&path).unwrap();
+        r.get("big").unwrap();
+        assert!(r.access_stats().is_empty());
+
+        let r = r.with_options(ReaderOptions::default().with_record_access(true));
+        for _ in 0..3 {
+            r.get("big").unwrap();
+        }
+        r.get("small").unwrap();
+        assert!(r.get("none").is_err());
+        let stats = r.access_stats();
+        assert_eq!(stats.keys().collect::<Vec<_>>(), vec!["big", "small"]);
+        assert_eq!((stats["big"].reads, stats["big"].cache_hits), (3, 2));
+        assert!(stats["big"].bytes > stats["small"].bytes && stats["small"].bytes > 0);
+
+        crate::TeaLeaf::from_reader(&r).unwrap();
+        assert_eq!(r.access_stats()["small"].reads, 2);
+        assert_eq!(r.access_stats()["small"].cache_hits, 0);
+        r.reset_access_stats();
+        assert!(r.access_stats().is_empty());
+    }
+
+    #[test]
+    fn test_parallel_decompression() {
+        let schema = Schema::new("row").field("id", FieldType::new("int")).field("name", FieldType::new("string"));
+        let rows = Value::Array((0..300).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("id".to_string(), Value::Int(i));
+            obj.insert("name".to_string(), Value::String(format!("name {}", i % 7)));
+            Value::Object(obj)
+        }).collect());
+        let mut w = Writer::new().with_max_section_bytes(512);
+        w.add_section("rows", &rows, Some(&schema)).unwrap();
+        for i in 0..20 {
+            w.add_section(&format!("text{:02}", i), &Value::String("compressible ".repeat(10 + i)), None).unwrap();
+        }
+        w.add_section("small", &Value::Int(1), None).unwrap();
+        let bytes = w.to_bytes(true).unwrap();
+
+        let serial = Reader::from_bytes(bytes.clone()).unwrap()
+            .with_options(ReaderOptions::default().with_decompress_threads(1));
+        let expected = crate::TeaLeaf::from_reader(&serial).unwrap();
+        assert_eq!(expected.get("rows"), Some(&rows));
+        for options in [
+            ReaderOptions::default(),
+            ReaderOptions::default().with_decompress_threads(4),
+            // Every part waits for the one before it to be decoded
+            ReaderOptions::default().with_decompress_threads(4).with_decompress_memory(1),
+        ] {
+            let r = Reader::from_bytes(bytes.clone()).unwrap().with_options(options);
+            let doc = crate::TeaLeaf::from_reader(&r).unwrap();
+            assert_eq!(doc.data.keys().collect::<Vec<_>>(), expected.data.keys().collect::<Vec<_>>());
+            assert_eq!(doc.data, expected.data);
+            assert!(r.cache.borrow().is_empty());
+            #[cfg(feature = "json")]
+            {
+                let json = |r: &Reader| {
+                    let mut out = Vec::new();
+                    r.write_json(&mut out, crate::JsonOptions::compact()).unwrap();
+                    out
+                };
+                assert_eq!(json(&r), json(&serial));
+            }
+        }
+
+        // An error in one section stops the load
+        struct Flip;
+        impl SectionCodec for Flip {
+            fn id(&self) -> u32 { 1 }
+            fn encode(&self, data: &[u8]) -> Result<Vec<u8>> { Ok(data.iter().map(|b| !b).collect()) }
+            fn decode(&self, data: &[u8]) -> Result<Vec<u8>> { self.encode(data) }
+        }
+        let mut w = Writer::new();
+        w.register_codec(Flip);
+        w.set_section_codec("coded", 1);
+        for i in 0..10 {
+            w.add_section(&format!("s{}", i), &Value::String("x".repeat(200)), None).unwrap();
+        }
+        w.add_section("coded", &Value::Int(1), None).unwrap();
+        let r = Reader::from_bytes(w.to_bytes(true).unwrap()).unwrap()
+            .with_options(ReaderOptions::default().with_decompress_threads(3));
+        assert!(matches!(crate::TeaLeaf::from_reader(&r), Err(Error::MissingCodec { .. })));
+    }
+
+    #[cfg(all(feature = "text", feature = "json"))]
+    #[test]
+    fn test_write_json_matches_document_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases = [
+            ("doc", "@struct user (id: int, name: string, tags: []string, email: string?)\n\
+                     users: @table user [(1, alice, [a, b], \"a@x.io\"), (2, \"bob\\nsmith\", [], ~)]\n\
+                     none: @table user []\n\
+                     config: {debug: true, limits: {rate: 1.5, burst: [1, 2]}, empty: {}}\n\
+                     tags: @map {1: one, two: [2]}\n\
+                     when: 2024-01-15T10:30:00Z\n\
+                     ref: !config\n\
+                     count: 3"),
+            ("root", "@root-array\n\nroot: [{a: 1}, [2, 3], x]"),
+            ("empty", ""),
+        ];
+        for (name, text) in cases {
+            let doc = crate::TeaLeaf::parse(text).unwrap();
+            for compress in [false, true] {
+                let path = dir.path().join(format!("{}-{}.tlbx", name, compress));
+                doc.compile(&path, compress).unwrap();
+                let r = Reader::open(&path).unwrap();
+                let expected = crate::TeaLeaf::from_reader(&r).unwrap();
+                r.clear_cache();
+
+                let mut pretty = Vec::new();
+                r.write_json(&mut pretty, crate::JsonOptions::pretty()).unwrap();
+                assert_eq!(String::from_utf8(pretty).unwrap(), expected.to_json().unwrap(), "{}", name);
+                let mut compact = Vec::new();
+                r.write_json(&mut compact, crate::JsonOptions::compact()).unwrap();
+                assert_eq!(String::from_utf8(compact).unwrap(), expected.to_json_compact().unwrap(), "{}", name);
+                assert!(r.cache.borrow().is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("paths.tlbx");
+        let mut point = ObjectMap::new();
+        point.insert("x".to_string(), Value::Int(1));
+        point.insert("y".to_string(), Value::Int(2));
+        let mut w = Writer::new();
+        w.add_section("points", &Value::Array(vec![Value::Object(point)]), None).unwrap();
+        w.add_section("name", &Value::String("grid".into()), None).unwrap();
+        w.write(&path, true).unwrap();
+
+        let r = Reader::open(&path).unwrap();
+        let values = r.get_paths(&["points[0].y", "name", "points[0].x", "points[5]", "absent.x", ""]).unwrap();
+        assert_eq!(values["points[0].y"], Some(Value::Int(2)));
+        assert_eq!(values["points[0].x"], Some(Value::Int(1)));
+        assert_eq!(values["name"], Some(Value::String("grid".into())));
+        assert_eq!(values["points[5]"], None);
+        assert_eq!(values["absent.x"], None);
+        assert_eq!(values[""], None);
+    }
+
+    #[test]
+    fn test_keys_by_prefix_and_range() {
+        let mut w = Writer::new();
+        for key in ["metrics/2024-05-02", "config", "metrics/2024-05-01", "metrics/2024-06-01", "metricsx", "logs/a"] {
+            w.add_section(key, &Value::String(key.into()), None).unwrap();
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.tlbx");
+        w.write(&path, false).unwrap();
+        let r = Reader::open(&path).unwrap();
+
+        assert_eq!(r.keys_with_prefix("metrics/"), vec!["metrics/2024-05-01", "metrics/2024-05-02", "metrics/2024-06-01"]);
+        assert_eq!(r.keys_with_prefix("metrics").len(), 4);
+        assert!(r.keys_with_prefix("zzz").is_empty());
+        assert_eq!(r.keys_with_prefix("").len(), 6);
+
+        assert_eq!(r.keys_in_range("metrics/2024-05-01".."metrics/2024-06-01"), vec!["metrics/2024-05-01", "metrics/2024-05-02"]);
+        assert_eq!(r.keys_in_range("metrics/2024-05-02"..="metrics/2024-06-01"), vec!["metrics/2024-05-02", "metrics/2024-06-01"]);
+        assert_eq!(r.keys_in_range(.."logs/b"), vec!["config", "logs/a"]);
+        assert_eq!(r.keys_in_range("metricsx"..), vec!["metricsx"]);
+        assert!(r.keys_in_range("z".."a").is_empty());
+        // File order is unchanged
+        assert_eq!(r.keys()[0], "metrics/2024-05-02");
+        assert_eq!(r.get("metrics/2024-05-01").unwrap().as_str(), Some("metrics/2024-05-01"));
+    }
+
+    #[test]
+    fn test_chunk_stats() {
+        let schema = Schema::new("tick")
+            .field("at", FieldType::new("timestamp"))
+            .field("n", FieldType::new("int").nullable())
+            .field("size", FieldType::new("uint"))
+            .field("price", FieldType::new("float"))
+            .field("tag", FieldType::new("string"));
+        let rows: Vec<Value> = (0..5i64).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("at".to_string(), Value::Timestamp(100 - i * 10, 0));
+            if i != 1 {
+                obj.insert("n".to_string(), Value::Int(i - 2));
+            }
+            obj.insert("size".to_string(), Value::UInt(i as u64 * 3));
+            obj.insert("price".to_string(), Value::Float(i as f64 / 2.0));
+            obj.insert("tag".to_string(), Value::String(format!("t{}", i)));
+            Value::Object(obj)
+        }).collect();
+        let mut w = Writer::new();
+        w.set_chunk_rows(2);
+        w.add_section("ticks", &Value::Array(rows.clone()), Some(&schema)).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ticks.tlbx");
+        w.write(&path, false).unwrap();
+        let r = Reader::open(&path).unwrap();
+
+        let chunks = r.chunk_stats("ticks").unwrap().unwrap();
+        assert_eq!(chunks.iter().map(|c| (c.first_row, c.rows)).collect::<Vec<_>>(), vec![(0, 2), (2, 2), (4, 1)]);
+        let first = &chunks[0].columns;
+        assert_eq!((first[0].min.clone(), first[0].max.clone()), (Some(Value::Timestamp(90, 0)), Some(Value::Timestamp(100, 0))));
+        assert_eq!((first[1].null_count, first[1].min.clone(), first[1].max.clone()), (1, Some(Value::Int(-2)), Some(Value::Int(-2))));
+        assert_eq!(chunks[1].columns[2].max, Some(Value::UInt(9)));
+        assert_eq!(chunks[2].columns[3].min, Some(Value::Float(2.0)));
+        assert_eq!(first[4], ColumnStats { null_count: 0, min: None, max: None });
+
+        assert_eq!(r.get("ticks").unwrap(), Value::Array(rows.clone()));
+        assert_eq!(r.read_chunk("ticks", 1).unwrap(), rows[2..4].to_vec());
+        assert!(r.read_chunk("ticks", 3).is_err());
+        let positive = r.scan_chunks("ticks", |c| c.columns[1].max.as_ref().and_then(Value::as_int).is_some_and(|n| n > 0)).unwrap();
+        assert_eq!(positive, rows[2..].to_vec());
+        assert!(r.read_chunk("missing", 0).is_err());
+    }
+
+    #[test]
+    fn test_chunks_decompress_alone() {
+        let schema = Schema::new("tick")
+            .field("n", FieldType::new("int"))
+            .field("tag", FieldType::new("string"));
+        let rows: Vec<Value> = (0..300i64).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("n".to_string(), Value::Int(i % 7));
+            obj.insert("tag".to_string(), Value::String("same".to_string()));
+            Value::Object(obj)
+        }).collect();
+        let table = Value::Array(rows.clone());
+        let mut w = Writer::new();
+        w.set_chunk_rows(100);
+        w.set_column_stats(true);
+        w.add_section("ticks", &table, Some(&schema)).unwrap();
+        let bytes = w.to_bytes(true).unwrap();
+
+        let r = Reader::from_bytes(bytes.clone()).unwrap();
+        let section = &r.sections["ticks"];
+        assert!(section.chunks_compressed && !section.compressed);
+        assert_eq!(r.get("ticks").unwrap(), table);
+        assert_eq!(r.read_chunk("ticks", 1).unwrap(), rows[100..200].to_vec());
+        assert_eq!(r.column_stats("ticks").unwrap().unwrap()[0].distinct, 7);
+        let (raw, _) = r.raw_section("ticks").unwrap();
+        let mut copy = Writer::from_reader(&r).unwrap();
+        copy.add_raw_section("ticks", &schema, &raw).unwrap();
+        assert_eq!(Reader::from_bytes(copy.to_bytes(false).unwrap()).unwrap().get("ticks").unwrap(), table);
+
+        // With the first chunk's compressed rows corrupted, the others still read
+        let stored = r.stored_data("ticks", section).unwrap();
+        let first = &parse_chunk_index(&stored, section.chunk_index.unwrap() as usize, 300).unwrap()[0];
+        let start = section.offset as usize + first.offset as usize;
+        let mut corrupt = bytes;
+        corrupt[start..start + 4].copy_from_slice(b"junk");
+        let r = Reader::from_bytes(corrupt).unwrap();
+        assert!(r.get("ticks").is_err());
+        assert!(r.read_chunk("ticks", 0).is_err());
+        assert_eq!(r.read_chunk("ticks", 2).unwrap(), rows[200..].to_vec());
+        let later = r.scan_chunks("ticks", |c| c.first_row > 0).unwrap();
+        assert_eq!(later, rows[100..].to_vec());
+    }
+
+    #[test]
+    fn test_column_stats() {
+        let schema = Schema::new("order")
+            .field("id", FieldType::new("int"))
+            .field("at", FieldType::new("timestamp").nullable())
+            .field("status", FieldType::new("string"))
+            .field("tags", FieldType::new("string").array());
+        let rows: Vec<Value> = (0..6i64).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("id".to_string(), Value::Int(10 - i));
+            if i % 3 != 0 {
+                obj.insert("at".to_string(), Value::Timestamp(1000 + i, 0));
+            }
+            obj.insert("status".to_string(), Value::String(["new", "paid"][i as usize % 2].to_string()));
+            obj.insert("tags".to_string(), Value::Array(vec![]));
+            Value::Object(obj)
+        }).collect();
+        let table = Value::Array(rows.clone());
+        let mut w = Writer::new().with_max_section_bytes(40);
+        w.add_section("none", &table, Some(&schema)).unwrap();
+        w.set_column_stats(true);
+        w.add_section("split", &table, Some(&schema)).unwrap();
+        w.set_chunk_rows(4);
+        w.add_section("chunked", &table, Some(&schema)).unwrap();
+        let mut w2 = Writer::new();
+        w2.set_column_stats(true);
+        w2.add_section("plain", &table, Some(&schema)).unwrap();
+        w2.add_section("empty", &Value::Array(vec![]), Some(&schema)).unwrap();
+        let r = Reader::from_bytes(w.to_bytes(false).unwrap()).unwrap();
+        let r2 = Reader::from_bytes(w2.to_bytes(true).unwrap()).unwrap();
+        assert!(r.sections["split"].parts.len() > 1);
+
+        let expected = vec![
+            ColumnSummary { name: "id".into(), null_count: 0, distinct: 6, min: Some(Value::Int(5)), max: Some(Value::Int(10)) },
+            ColumnSummary { name: "at".into(), null_count: 2, distinct: 4, min: Some(Value::Timestamp(1001, 0)), max: Some(Value::Timestamp(1005, 0)) },
+            ColumnSummary { name: "status".into(), null_count: 0, distinct: 2, min: None, max: None },
+            ColumnSummary { name: "tags".into(), null_count: 0, distinct: 1, min: None, max: None },
+        ];
+        for (reader, key) in [(&r, "split"), (&r, "chunked"), (&r2, "plain")] {
+            assert_eq!(reader.column_stats(key).unwrap().as_ref(), Some(&expected), "{}", key);
+            assert_eq!(reader.get(key).unwrap(), table, "{}", key);
+            assert_eq!(reader.raw_section(key).unwrap().0, r.raw_section("none").unwrap().0, "{}", key);
+        }
+        assert_eq!(r.chunk_stats("chunked").unwrap().unwrap().len(), 2);
+        assert!(r.column_stats("none").unwrap().is_none());
+        assert!(r2.column_stats("empty").unwrap().is_none());
+        assert!(r.column_stats("missing").is_err());
+
+        let (_, data) = r2.section_data("plain").unwrap();
+        assert!(column_stats_offset(&data[..3]).is_err());
+        let mut bad = data.to_vec();
+        let end = bad.len() - 4;
+        bad[end..].copy_from_slice(&(end as u32 + 1).to_le_bytes());
+        assert!(column_stats_offset(&bad).is_err());
+        let off = column_stats_offset(&data).unwrap();
+        assert!(parse_column_stats(&data[..off + 5], off, &schema).is_err());
+
+        // Column statistics need version 3
+        let mut bytes = w2.to_bytes(false).unwrap();
+        bytes[4..8].copy_from_slice(&[2, 0, 1, 0]);
+        let err = Reader::from_bytes(bytes).err().unwrap();
+        assert!(err.to_string().contains("section 'plain' is followed by column statistics, which version 2.1"), "{}", err);
+    }
+
+    #[test]
+    fn test_split_table_sections() {
+        let schema = Schema::new("row").field("id", FieldType::new("int")).field("name", FieldType::new("string"));
+        let rows: Vec<Value> = (0..50i64).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("id".to_string(), Value::Int(i));
+            obj.insert("name".to_string(), Value::String(format!("n{}", i % 7)));
+            Value::Object(obj)
+        }).collect();
+        let dir = tempfile::tempdir().unwrap();
+        for chunk_rows in [0, 4] {
+            let mut w = Writer::new().with_max_section_bytes(160);
+            w.set_chunk_rows(chunk_rows);
+            w.add_section("rows", &Value::Array(rows.clone()), Some(&schema)).unwrap();
+            w.add_section("after", &Value::Int(1), None).unwrap();
+            let path = dir.path().join(format!("split-{}.tlbx", chunk_rows));
+            w.write(&path, true).unwrap();
+            let r = Reader::open(&path).unwrap();
+
+            assert_eq!(r.keys(), vec!["rows", "after"]);
+            let section = &r.sections["rows"];
+            assert!(section.parts.len() > 1);
+            for part in std::iter::once(section).chain(&section.parts) {
+                assert!(part.uncompressed_size <= 160, "{} bytes", part.uncompressed_size);
+            }
+            assert_eq!(r.get("rows").unwrap(), Value::Array(rows.clone()));
+            assert_eq!(r.scan_chunks("rows", |_| true).unwrap(), rows);
+            #[cfg(feature = "json")]
+            {
+                let mut json = Vec::new();
+                r.write_json(&mut json, crate::JsonOptions::compact()).unwrap();
+                let expected = crate::TeaLeaf::from_reader(&r).unwrap().to_json_compact().unwrap();
+                assert_eq!(String::from_utf8(json).unwrap(), expected);
+            }
+
+            // Copying the raw table joins the parts back into one section
+            let (raw, _) = r.raw_section("rows").unwrap();
+            let mut copy = Writer::from_reader(&r).unwrap();
+            copy.add_raw_section("rows", &schema, &raw).unwrap();
+            let copy_path = dir.path().join("copy.tlbx");
+            copy.write(&copy_path, false).unwrap();
+            let copied = Reader::open(&copy_path).unwrap();
+            assert!(copied.sections["rows"].parts.is_empty());
+            assert_eq!(copied.get("rows").unwrap(), Value::Array(rows.clone()));
+        }
+
+        let r = Reader::open(dir.path().join("split-4.tlbx")).unwrap();
+        let chunks = r.chunk_stats("rows").unwrap().unwrap();
+        assert_eq!(chunks.len(), 13);
+        assert!(chunks.iter().enumerate().all(|(i, c)| c.first_row == i * 4));
+        assert_eq!(chunks[5].columns[0].min, Some(Value::Int(20)));
+        assert_eq!(r.read_chunk("rows", 12).unwrap(), rows[48..].to_vec());
+        assert!(r.read_chunk("rows", 13).is_err());
+        let late = r.scan_chunks("rows", |c| c.columns[0].max.as_ref().and_then(Value::as_int).is_some_and(|n| n >= 45)).unwrap();
+        assert_eq!(late, rows[44..].to_vec());
+
+        // Rows are encoded once, so a coercion is reported once however the table is split
+        let mut coerced = rows.clone();
+        coerced[30] = Value::Object(ObjectMap::from_iter([("id".to_string(), Value::String("x".into()))]));
+        let mut w = Writer::new().with_max_section_bytes(160);
+        w.add_section("rows", &Value::Array(coerced), Some(&schema)).unwrap();
+        assert_eq!(w.warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_continuation_must_follow_table() {
+        let mut w = Writer::new();
+        w.add_section("a", &Value::Int(1), None).unwrap();
+        w.add_section("b", &Value::Int(2), None).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("c.tlbx");
+        w.write(&path, false).unwrap();
+        let mut bytes = std::fs::read(&path).unwrap();
+        assert!(Reader::from_bytes(bytes.clone()).is_ok());
+        // Mark "b" as continuing a table it does not follow
+        let idx_off = u64::from_le_bytes(bytes[32..40].try_into().unwrap()) as usize;
+        bytes[idx_off + 8 + 32 + 23] |= 0x08;
+        assert!(Reader::from_bytes(bytes).is_err());
+    }
+
+    #[test]
+    fn test_version_2_files_predate_continuations() {
+        let schema = Schema::new("p").field("x", FieldType::new("int"));
+        let rows = Value::Array((0..40).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("x".to_string(), Value::Int(i));
+            Value::Object(obj)
+        }).collect());
+        let mut w = Writer::new().with_max_section_bytes(64);
+        w.add_section("p", &rows, Some(&schema)).unwrap();
+        let mut bytes = w.to_bytes(false).unwrap();
+        let r = Reader::from_bytes(bytes.clone()).unwrap();
+        assert_eq!(r.version(), (crate::VERSION_MAJOR, crate::VERSION_MINOR));
+        assert!(!r.sections["p"].parts.is_empty());
+
+        // A 2.x reader would let the last part overwrite the first
+        bytes[4..8].copy_from_slice(&[2, 0, 1, 0]);
+        let err = Reader::from_bytes(bytes.clone()).err().unwrap();
+        assert!(err.to_string().contains("section 'p' is a continuation, which version 2.1 files cannot hold"), "{}", err);
+        bytes[4..6].copy_from_slice(&4u16.to_le_bytes());
+        assert!(matches!(Reader::from_bytes(bytes), Err(Error::InvalidVersion { major: 4, minor: 1 })));
+    }
+
+    #[test]
+    fn test_chunk_index_bounds() {
+        let schema = Schema::new("p").field("x", FieldType::new("int"));
+        let rows = Value::Array((0..3).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("x".to_string(), Value::Int(i));
+            Value::Object(obj)
+        }).collect());
+        let mut w = Writer::new();
+        w.set_chunk_rows(2);
+        w.add_section("p", &rows, Some(&schema)).unwrap();
+        w.set_chunk_rows(0);
+        w.add_section("whole", &rows, Some(&schema)).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("p.tlbx");
+        w.write(&path, false).unwrap();
+        let r = Reader::open(&path).unwrap();
+        assert!(r.chunk_stats("whole").unwrap().is_none());
+        assert!(r.read_chunk("whole", 0).is_err());
+        assert_eq!(r.scan_chunks("whole", |_| false).unwrap(), r.scan_chunks("p", |_| true).unwrap());
+
+        let (section, data) = r.section_data("p").unwrap();
+        let off = section.chunk_index.unwrap() as usize;
+        assert_eq!(parse_chunk_index(&data, off, 3).unwrap().len(), 2);
+        assert!(parse_chunk_index(&data, off, 5).is_err());
+        assert!(parse_chunk_index(&data[..data.len() - 1], off, 3).is_err());
+        let mut bad = data.to_vec();
+        bad[off + 10] = 9;
+        assert!(parse_chunk_index(&bad, off, 3).is_err());
+
+        // Chunked sections need version 3
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[4..8].copy_from_slice(&[2, 0, 0, 0]);
+        let err = Reader::from_bytes(bytes).err().unwrap();
+        assert!(err.to_string().contains("section 'p' is chunked, which version 2.0 files cannot hold"), "{}", err);
+    }
+
     #[test]
     fn test_open_regular() {
         let dir = std::env::temp_dir();
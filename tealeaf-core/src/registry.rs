@@ -0,0 +1,81 @@
+//! Schema and union definitions shared across documents.
+//!
+//! A [`SchemaRegistry`] holds definitions independently of any data, so the
+//! schemas read from one file can be reused to write another:
+//!
+//! ```ignore
+//! let registry = Reader::open("v1.tlbx")?.into_schema_registry();
+//! let mut writer = Writer::new();
+//! writer.use_registry(&registry);
+//! writer.add_section("users", &users, registry.schema("user"))?;
+//! ```
+
+use indexmap::IndexMap;
+
+use crate::{Schema, TeaLeaf, Union};
+
+/// A named collection of schema and union definitions.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaRegistry {
+    pub schemas: IndexMap<String, Schema>,
+    pub unions: IndexMap<String, Union>,
+}
+
+impl SchemaRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a schema, keyed by its name.
+    pub fn add_schema(&mut self, schema: Schema) {
+        self.schemas.insert(schema.name.clone(), schema);
+    }
+
+    /// Add or replace a union, keyed by its name.
+    pub fn add_union(&mut self, union: Union) {
+        self.unions.insert(union.name.clone(), union);
+    }
+
+    pub fn schema(&self, name: &str) -> Option<&Schema> {
+        self.schemas.get(name)
+    }
+
+    pub fn union(&self, name: &str) -> Option<&Union> {
+        self.unions.get(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.schemas.is_empty() && self.unions.is_empty()
+    }
+}
+
+impl TeaLeaf {
+    /// Copy this document's schema and union definitions into a registry.
+    pub fn schema_registry(&self) -> SchemaRegistry {
+        SchemaRegistry {
+            schemas: self.schemas.clone(),
+            unions: self.unions.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FieldType;
+
+    #[test]
+    fn test_registry_add_and_lookup() {
+        let mut registry = SchemaRegistry::new();
+        assert!(registry.is_empty());
+        registry.add_schema(Schema::new("user").field("id", FieldType::new("int")));
+        registry.add_schema(Schema::new("user").field("name", FieldType::new("string")));
+        registry.add_union(Union::new("shape"));
+
+        assert_eq!(registry.schemas.len(), 1);
+        assert_eq!(registry.schema("user").unwrap().fields[0].name, "name");
+        assert!(registry.union("shape").is_some());
+        assert!(registry.schema("missing").is_none());
+    }
+}
@@ -0,0 +1,105 @@
+//! The body of a document, as an explicit value.
+//!
+//! A document converted from a JSON array or scalar keeps that value under
+//! the key `root` and remembers, in a flag, that it is not really a key.
+//! [`TeaLeaf::root`] reads the body as a [`Root`] instead, and
+//! [`TeaLeaf::set_root`] replaces it, so code no longer needs to look up
+//! `"root"` or set the flag with the deprecated
+//! [`TeaLeaf::set_root_array`].
+
+use crate::{IndexMap, TeaLeaf, Value};
+
+/// The body of a document, as [`TeaLeaf::root`] returns it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Root<'a> {
+    /// Top-level keys and values, the body of most documents
+    Object(&'a IndexMap<String, Value>),
+    /// A root-level array: a JSON array, or text under `@root-array`
+    Array(&'a [Value]),
+    /// Any other root-level value, such as a JSON scalar
+    Scalar(&'a Value),
+}
+
+impl Root<'_> {
+    /// The body as one value, as JSON export writes it.
+    pub fn to_value(&self) -> Value {
+        match self {
+            Root::Object(data) => Value::Object((*data).clone()),
+            Root::Array(items) => Value::Array(items.to_vec()),
+            Root::Scalar(value) => (*value).clone(),
+        }
+    }
+}
+
+impl TeaLeaf {
+    /// The body of the document.
+    ///
+    /// A document marked as a root array or scalar gives the value stored
+    /// under `root`; other documents, and marked documents that have no
+    /// `root` key, give their top-level entries.
+    pub fn root(&self) -> Root<'_> {
+        if self.is_root_array || self.is_root_primitive {
+            match self.data.get("root") {
+                Some(Value::Array(items)) if self.is_root_array => return Root::Array(items),
+                Some(value) => return Root::Scalar(value),
+                None => {}
+            }
+        }
+        Root::Object(&self.data)
+    }
+
+    /// Replace the body of the document with `body`, as
+    /// [`from_json`](Self::from_json) would store it: an object's entries
+    /// become the top-level keys, and an array or scalar is kept under
+    /// `root` and exported without it.
+    ///
+    /// Schemas are kept. Bindings of keys the new body does not have are
+    /// dropped.
+    pub fn set_root(&mut self, body: Value) {
+        self.is_root_array = matches!(body, Value::Array(_));
+        self.is_root_primitive = !self.is_root_array && !matches!(body, Value::Object(_));
+        self.data = match body {
+            Value::Object(entries) => entries,
+            body => IndexMap::from([("root".to_string(), body)]),
+        };
+        let data = &self.data;
+        self.bindings.retain(|key, _| data.contains_key(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_and_set_root() {
+        let mut doc = TeaLeaf::new(IndexMap::new(), IndexMap::new());
+        doc.data.insert("root".to_string(), Value::Int(1));
+        assert_eq!(doc.root(), Root::Object(&doc.data));
+
+        doc.set_root(Value::Array(vec![Value::Int(1), Value::Int(2)]));
+        assert_eq!(doc.root(), Root::Array(&[Value::Int(1), Value::Int(2)]));
+        assert_eq!(doc.root().to_value(), doc.data["root"]);
+
+        doc.set_root(Value::String("hi".into()));
+        assert_eq!(doc.root(), Root::Scalar(&Value::String("hi".into())));
+
+        let mut entries = IndexMap::new();
+        entries.insert("a".to_string(), Value::Bool(true));
+        doc.set_root(Value::Object(entries.clone()));
+        assert_eq!(doc.root(), Root::Object(&entries));
+        assert_eq!(doc.root().to_value(), Value::Object(entries));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_root_matches_json() {
+        for json in ["[1,2,3]", "42", "\"x\"", "null", r#"{"root":[1]}"#] {
+            let doc = TeaLeaf::from_json(json).unwrap();
+            let mut rebuilt = TeaLeaf::new(IndexMap::new(), IndexMap::new());
+            rebuilt.set_root(doc.root().to_value());
+            assert_eq!(rebuilt.to_json_compact().unwrap(), json);
+            assert_eq!(rebuilt.root(), doc.root());
+        }
+    }
+}
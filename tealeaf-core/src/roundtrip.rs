@@ -0,0 +1,248 @@
+//! Checking that a document survives its own output formats.
+//!
+//! [`roundtrip_check`] writes a document as pretty text, compact text and
+//! binary, reads each back, and reports every value that came back
+//! different, with its path. Run it over generated documents before
+//! publishing them, to catch values the writers and readers disagree on.
+
+use std::fmt;
+
+use crate::progress::Progress;
+use crate::{FloatTolerance, IndexMap, Reader, TeaLeaf, Value};
+
+/// An output format [`roundtrip_check`] writes and reads back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundTripFormat {
+    /// [`TeaLeaf::to_tl_with_schemas`]
+    Text,
+    /// [`TeaLeaf::to_tl_with_schemas_compact`]
+    CompactText,
+    /// [`TeaLeaf::compile`], uncompressed
+    Binary,
+}
+
+impl RoundTripFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            RoundTripFormat::Text => "text",
+            RoundTripFormat::CompactText => "compact text",
+            RoundTripFormat::Binary => "binary",
+        }
+    }
+}
+
+/// How a value differs after a round trip.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Difference {
+    /// The document could not be written or read back.
+    Failed(String),
+    Changed { before: Value, after: Value },
+    /// The value is missing after the round trip.
+    Missing(Value),
+    /// The value appeared in the round trip.
+    Added(Value),
+}
+
+/// One difference found by [`roundtrip_check`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asymmetry {
+    pub format: RoundTripFormat,
+    /// Path of the value, in [`TeaLeaf::get_path`] syntax; empty for
+    /// [`Difference::Failed`].
+    pub path: String,
+    pub difference: Difference,
+}
+
+impl fmt::Display for Asymmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.difference {
+            Difference::Failed(error) => write!(f, "{}: {}", self.format.name(), error),
+            Difference::Changed { before, after } => write!(f, "{}: {}: {:?} became {:?}", self.format.name(), self.path, before, after),
+            Difference::Missing(before) => write!(f, "{}: {}: {:?} was lost", self.format.name(), self.path, before),
+            Difference::Added(after) => write!(f, "{}: {}: {:?} appeared", self.format.name(), self.path, after),
+        }
+    }
+}
+
+/// Every value of `doc` that reads back differently from each of its
+/// output formats, by format and then in document order. Empty if `doc`
+/// round-trips exactly.
+///
+/// Values compare exactly, variant and all: an unsigned integer read back
+/// as a signed one is reported even though the number is the same. NaN
+/// floats compare equal to each other, and key order is not compared.
+pub fn roundtrip_check(doc: &TeaLeaf) -> Vec<Asymmetry> {
+    roundtrip_check_with(doc, FloatTolerance::Exact)
+}
+
+/// Like [`roundtrip_check`], but floats that read back within `tolerance`
+/// of the original are not reported.
+pub fn roundtrip_check_with(doc: &TeaLeaf, tolerance: FloatTolerance) -> Vec<Asymmetry> {
+    let mut found = Vec::new();
+    for format in [RoundTripFormat::Text, RoundTripFormat::CompactText, RoundTripFormat::Binary] {
+        match read_back(doc, format) {
+            Ok(after) => {
+                let mut path = String::new();
+                compare_entries(&doc.data, &after.data, format, tolerance, &mut path, &mut found);
+            }
+            Err(error) => found.push(Asymmetry { format, path: String::new(), difference: Difference::Failed(error.to_string()) }),
+        }
+    }
+    found
+}
+
+fn read_back(doc: &TeaLeaf, format: RoundTripFormat) -> crate::Result<TeaLeaf> {
+    match format {
+        RoundTripFormat::Text => TeaLeaf::parse(&doc.to_tl_with_schemas()),
+        RoundTripFormat::CompactText => TeaLeaf::parse(&doc.to_tl_with_schemas_compact()),
+        RoundTripFormat::Binary => {
+            let bytes = doc.to_writer(&mut Progress::new())?.to_bytes(false)?;
+            TeaLeaf::from_reader(&Reader::from_bytes(bytes)?)
+        }
+    }
+}
+
+/// Compare the entries of two objects, or of two documents when `path`
+/// is empty.
+fn compare_entries(
+    before: &IndexMap<String, Value>,
+    after: &IndexMap<String, Value>,
+    format: RoundTripFormat,
+    tolerance: FloatTolerance,
+    path: &mut String,
+    found: &mut Vec<Asymmetry>,
+) {
+    let len = path.len();
+    let enter = |path: &mut String, key: &str| {
+        if len > 0 {
+            path.push('.');
+        }
+        path.push_str(key);
+    };
+    for (key, value) in before {
+        enter(path, key);
+        match after.get(key) {
+            Some(other) => compare(value, other, format, tolerance, path, found),
+            None => found.push(Asymmetry { format, path: path.clone(), difference: Difference::Missing(value.clone()) }),
+        }
+        path.truncate(len);
+    }
+    for (key, value) in after.iter().filter(|(key, _)| !before.contains_key(*key)) {
+        enter(path, key);
+        found.push(Asymmetry { format, path: path.clone(), difference: Difference::Added(value.clone()) });
+        path.truncate(len);
+    }
+}
+
+fn compare(
+    before: &Value,
+    after: &Value,
+    format: RoundTripFormat,
+    tolerance: FloatTolerance,
+    path: &mut String,
+    found: &mut Vec<Asymmetry>,
+) {
+    let len = path.len();
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => compare_entries(a, b, format, tolerance, path, found),
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                path.push_str(&format!("[{}]", i));
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => compare(x, y, format, tolerance, path, found),
+                    (Some(x), None) => found.push(Asymmetry { format, path: path.clone(), difference: Difference::Missing(x.clone()) }),
+                    (None, Some(y)) => found.push(Asymmetry { format, path: path.clone(), difference: Difference::Added(y.clone()) }),
+                    (None, None) => {}
+                }
+                path.truncate(len);
+            }
+        }
+        // Entries are compared by position, so a changed key shows as a
+        // changed entry
+        (Value::Map(a), Value::Map(b)) if a.len() == b.len() => {
+            for (i, ((key_a, x), (key_b, y))) in a.iter().zip(b).enumerate() {
+                path.push_str(&format!("[{}]", i));
+                if key_a == key_b {
+                    compare(x, y, format, tolerance, path, found);
+                } else {
+                    found.push(Asymmetry {
+                        format,
+                        path: path.clone(),
+                        difference: Difference::Changed {
+                            before: Value::Array(vec![key_a.clone(), x.clone()]),
+                            after: Value::Array(vec![key_b.clone(), y.clone()]),
+                        },
+                    });
+                }
+                path.truncate(len);
+            }
+        }
+        (Value::Tagged(tag_a, a), Value::Tagged(tag_b, b)) if tag_a == tag_b => compare(a, b, format, tolerance, path, found),
+        (Value::Float(a), Value::Float(b)) if tolerance.matches(*a, *b) => {}
+        (a, b) if a == b => {}
+        (a, b) => found.push(Asymmetry {
+            format,
+            path: path.clone(),
+            difference: Difference::Changed { before: a.clone(), after: b.clone() },
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_document_has_no_asymmetries() {
+        let doc = TeaLeaf::parse(r#"
+            @struct user (id: int, name: string, score: float?, tags: []string)
+            users: @table user [(1, alice, 0.5, [a, b]), (2, "bob smith", ~, [])]
+            nested: {when: 2024-01-15T10:30:00Z, bytes: b"cafe", map: @map {1: one, "k": [1, 2]}, shape: :circle {r: 1.5}}
+            nan: NaN
+            big: 18446744073709551615
+            !base: {a: 1}
+            ref: !base
+        "#).unwrap();
+        assert_eq!(roundtrip_check(&doc), Vec::new());
+    }
+
+    #[test]
+    fn test_reports_differences_with_paths() {
+        let mut doc = TeaLeaf::parse("a: {b: [1, 2]}
+m: @map {1: x}
+").unwrap();
+        // Written as a plain integer, which reads back signed
+        doc.data.insert("u".to_string(), Value::UInt(7));
+        let found = roundtrip_check(&doc);
+        let text: Vec<String> = found.iter()
+            .filter(|a| a.format == RoundTripFormat::Text)
+            .map(|a| a.to_string())
+            .collect();
+        assert!(text.contains(&"text: u: UInt(7) became Int(7)".to_string()), "{:?}", text);
+        assert!(found.iter().all(|a| a.path != "a.b[1]" && a.path != "m[0]"), "{:?}", found);
+
+        let mut before = IndexMap::new();
+        before.insert("k".to_string(), Value::Array(vec![Value::Int(1), Value::Int(2)]));
+        before.insert("gone".to_string(), Value::Null);
+        let mut after = IndexMap::new();
+        after.insert("k".to_string(), Value::Array(vec![Value::Int(1)]));
+        after.insert("new".to_string(), Value::Bool(true));
+        let mut diffs = Vec::new();
+        compare_entries(&before, &after, RoundTripFormat::Binary, FloatTolerance::Exact, &mut String::new(), &mut diffs);
+        let shown: Vec<String> = diffs.iter().map(|a| a.to_string()).collect();
+        assert_eq!(shown, [
+            "binary: k[1]: Int(2) was lost",
+            "binary: gone: Null was lost",
+            "binary: new: Bool(true) appeared",
+        ]);
+
+        let before = IndexMap::from([("f".to_string(), Value::Float(0.1))]);
+        let after = IndexMap::from([("f".to_string(), Value::Float(0.1f32 as f64))]);
+        let mut diffs = Vec::new();
+        compare_entries(&before, &after, RoundTripFormat::Binary, FloatTolerance::Exact, &mut String::new(), &mut diffs);
+        assert_eq!(diffs.len(), 1);
+        diffs.clear();
+        compare_entries(&before, &after, RoundTripFormat::Binary, FloatTolerance::F32, &mut String::new(), &mut diffs);
+        assert_eq!(diffs, []);
+    }
+}
@@ -0,0 +1,253 @@
+//! Secret values: masked when printed, wiped from memory when dropped.
+//!
+//! Tuple and table fields declared with the `secret` type parse into
+//! [`Value::Secret`], as do DTO fields of type [`Secret`]. `Debug` and
+//! `Display` always show [`REDACTED`]. Text and JSON output do too unless
+//! asked to expose secrets with [`SecretOutput::Expose`] (see
+//! [`FormatOptions::secrets`](crate::FormatOptions::secrets) and
+//! [`JsonOptions::secrets`](crate::JsonOptions::secrets)). Binary files and
+//! archives store the contents, so a compiled document reads back with its
+//! secrets intact; a `secret` field is written with a per-value type code
+//! and its values are wrapped back into [`Value::Secret`] when read.
+//!
+//! With the `secret` feature the contents are held by the `secrecy` crate
+//! and zeroized on drop; without it they are ordinary heap memory. Only the
+//! secret itself is wiped: the source text it was parsed from, and copies
+//! the caller makes with the `expose` accessors, are not.
+
+use std::fmt;
+
+use crate::convert::{ConvertError, FromTeaLeaf, NotU8, ToTeaLeaf};
+use crate::{FieldType, Value};
+pub use crate::env::REDACTED;
+
+/// A secret string or byte string, zeroized on drop with the `secret`
+/// feature.
+#[derive(Clone)]
+pub struct Secret(Inner);
+
+#[cfg(feature = "secret")]
+#[derive(Clone)]
+enum Inner {
+    String(secrecy::SecretString),
+    Bytes(secrecy::SecretSlice<u8>),
+}
+
+#[cfg(not(feature = "secret"))]
+#[derive(Clone)]
+enum Inner {
+    String(Box<str>),
+    Bytes(Box<[u8]>),
+}
+
+#[cfg(feature = "secret")]
+fn expose<S: secrecy::zeroize::Zeroize + ?Sized>(held: &secrecy::SecretBox<S>) -> &S {
+    use secrecy::ExposeSecret;
+    held.expose_secret()
+}
+
+#[cfg(not(feature = "secret"))]
+fn expose<S: ?Sized>(held: &S) -> &S {
+    held
+}
+
+/// How text and JSON output write [`Value::Secret`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SecretOutput {
+    /// Write the string [`REDACTED`] in place of the contents (default).
+    #[default]
+    Redact,
+    /// Write the contents, as a string or bytes literal, so the output
+    /// reads back with the secret intact.
+    Expose,
+}
+
+impl Secret {
+    /// The secret text, or `None` for a byte secret.
+    pub fn expose_str(&self) -> Option<&str> {
+        match &self.0 {
+            Inner::String(s) => Some(expose(s)),
+            Inner::Bytes(_) => None,
+        }
+    }
+
+    /// The secret's bytes; the UTF-8 encoding of a string secret.
+    pub fn expose_bytes(&self) -> &[u8] {
+        match &self.0 {
+            Inner::String(s) => expose(s).as_bytes(),
+            Inner::Bytes(b) => expose(b),
+        }
+    }
+
+    /// The contents as a plain string or bytes value, for writers asked to
+    /// expose them.
+    #[cfg(any(feature = "text", feature = "json", feature = "binary"))]
+    pub(crate) fn expose_value(&self) -> Value {
+        match self.expose_str() {
+            Some(s) => Value::String(s.to_string()),
+            None => Value::Bytes(self.expose_bytes().to_vec()),
+        }
+    }
+}
+
+/// Wrap the strings and bytes a `secret` field was stored as back into
+/// secrets, element by element for an array field.
+#[cfg(feature = "binary")]
+pub(crate) fn conceal(value: Value) -> Value {
+    match value {
+        Value::String(s) => Value::Secret(s.into()),
+        Value::Bytes(b) => Value::Secret(b.into()),
+        Value::Array(items) => Value::Array(items.into_iter().map(conceal).collect()),
+        other => other,
+    }
+}
+
+impl From<String> for Secret {
+    fn from(s: String) -> Self {
+        Secret(Inner::String(s.into()))
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(s: &str) -> Self {
+        Secret(Inner::String(s.into()))
+    }
+}
+
+impl From<Vec<u8>> for Secret {
+    fn from(b: Vec<u8>) -> Self {
+        Secret(Inner::Bytes(b.into()))
+    }
+}
+
+impl PartialEq for Secret {
+    fn eq(&self, other: &Self) -> bool {
+        self.expose_str().is_some() == other.expose_str().is_some()
+            && self.expose_bytes() == other.expose_bytes()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl ToTeaLeaf for Secret {
+    fn to_tealeaf_value(&self) -> Value {
+        Value::Secret(self.clone())
+    }
+    fn tealeaf_field_type() -> FieldType {
+        FieldType::new("secret")
+    }
+}
+
+impl FromTeaLeaf for Secret {
+    fn from_tealeaf_value(value: &Value) -> Result<Self, ConvertError> {
+        match value {
+            Value::Secret(s) => Ok(s.clone()),
+            Value::String(s) => Ok(Secret::from(s.as_str())),
+            Value::Bytes(b) => Ok(Secret::from(b.clone())),
+            _ => Err(ConvertError::TypeMismatch {
+                expected: "secret".into(),
+                got: format!("{:?}", value.tl_type()),
+                path: String::new(),
+            }),
+        }
+    }
+}
+
+impl NotU8 for Secret {}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+    use crate::TeaLeaf;
+
+    const DOC: &str = r#"
+        @struct account (user: string, password: secret, key: secret?)
+        accounts: @table account [
+            (alice, "hunter2", b"c0ffee"),
+            (bob, "pa55", ~),
+        ]
+    "#;
+
+    #[test]
+    fn test_secret_fields_parse_and_expose() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let password = match doc.get_path("accounts[0].password") {
+            Some(Value::Secret(s)) => s,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(password.expose_str(), Some("hunter2"));
+        assert_eq!(doc.get_path("accounts[0].password").unwrap().as_str(), None);
+        match doc.get_path("accounts[0].key") {
+            Some(Value::Secret(key)) => {
+                assert_eq!(key.expose_str(), None);
+                assert_eq!(key.expose_bytes(), &[0xc0, 0xff, 0xee]);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(TeaLeaf::parse("@struct a (p: secret)\nx: @table a [(1)]").is_err());
+    }
+
+    #[test]
+    fn test_secret_masked_in_output() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let debug = format!("{:?}", doc.get("accounts").unwrap());
+        assert!(!debug.contains("hunter2") && debug.contains(REDACTED), "{}", debug);
+
+        let text = doc.to_tl_with_schemas();
+        assert!(!text.contains("hunter2") && text.contains("(alice, \"[REDACTED]\", \"[REDACTED]\")"), "{}", text);
+        #[cfg(feature = "json")]
+        {
+            let json = doc.to_json().unwrap();
+            assert!(!json.contains("hunter2") && json.contains(REDACTED), "{}", json);
+        }
+        assert_eq!(Secret::from("hunter2").to_string(), REDACTED);
+    }
+
+    #[test]
+    fn test_secret_exposed_on_request() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let text = doc.to_tl_with_options(&crate::FormatOptions::default().with_secrets(SecretOutput::Expose));
+        assert!(text.contains("(alice, hunter2, b\"c0ffee\")"), "{}", text);
+        assert_eq!(TeaLeaf::parse(&text).unwrap().get_path("accounts[0].password"), doc.get_path("accounts[0].password"));
+        #[cfg(feature = "json")]
+        {
+            let opts = crate::JsonOptions::compact().with_secrets(SecretOutput::Expose);
+            let json = doc.to_json_with_options(opts).unwrap();
+            assert!(json.contains("\"password\":\"hunter2\"") && json.contains("\"key\":\"0xc0ffee\""), "{}", json);
+        }
+    }
+
+    #[cfg(feature = "binary")]
+    #[test]
+    fn test_secret_kept_in_binary() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.tlbx");
+        doc.compile(&path, false).unwrap();
+        let reader = crate::Reader::open(&path).unwrap();
+        let accounts = reader.get("accounts").unwrap();
+        assert_eq!(accounts.get_path("[0].password"), doc.get_path("accounts[0].password"));
+        assert_eq!(accounts.get_path("[0].key"), doc.get_path("accounts[0].key"));
+        assert!(matches!(accounts.get_path("[1].password"), Some(Value::Secret(_))));
+    }
+
+    #[test]
+    fn test_secret_conversions() {
+        let secret = Secret::from("token");
+        assert_eq!(secret.to_tealeaf_value(), Value::Secret(Secret::from("token")));
+        assert_ne!(Secret::from("token"), Secret::from(b"token".to_vec()));
+        assert_eq!(Secret::from_tealeaf_value(&Value::String("token".into())).unwrap(), secret);
+        assert!(Secret::from_tealeaf_value(&Value::Int(1)).is_err());
+        assert_eq!(Secret::tealeaf_field_type(), FieldType::new("secret"));
+    }
+}
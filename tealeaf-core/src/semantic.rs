@@ -0,0 +1,273 @@
+//! Comparing values by meaning rather than by representation.
+//!
+//! `==` on [`Value`] is structural: `Int(7)` differs from `UInt(7)`, key
+//! order matters, and `NaN` never equals itself. [`Value::semantic_eq`]
+//! compares what the values say instead, and
+//! [`Value::semantic_eq_with`] also accepts floats that differ within a
+//! [`FloatTolerance`], for data that went through `f32` or another lossy
+//! system on the way. [`TeaLeaf::semantic_differences`] validates a whole
+//! document against another the same way, listing the paths that differ.
+
+use std::str::FromStr;
+
+use crate::{Error, TeaLeaf, Value};
+
+/// How close two floats must be to compare equal.
+///
+/// Every policy treats NaN as equal to NaN, and an infinity as equal only
+/// to the same infinity.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FloatTolerance {
+    /// The same value
+    #[default]
+    Exact,
+    /// At most this far apart
+    Absolute(f64),
+    /// At most this fraction of the larger magnitude apart
+    Relative(f64),
+    /// At most this many representable `f64` values apart
+    Ulps(u64),
+    /// The same value once both are rounded to `f32`
+    F32,
+}
+
+impl FloatTolerance {
+    /// Whether `a` and `b` are equal under this policy.
+    pub fn matches(self, a: f64, b: f64) -> bool {
+        if a == b || (a.is_nan() && b.is_nan()) {
+            return true;
+        }
+        if !a.is_finite() || !b.is_finite() {
+            return false;
+        }
+        match self {
+            FloatTolerance::Exact => false,
+            FloatTolerance::Absolute(epsilon) => (a - b).abs() <= epsilon,
+            FloatTolerance::Relative(epsilon) => (a - b).abs() <= epsilon * a.abs().max(b.abs()),
+            FloatTolerance::Ulps(ulps) => (ordered(a) - ordered(b)).unsigned_abs() <= ulps as u128,
+            FloatTolerance::F32 => a as f32 == b as f32,
+        }
+    }
+}
+
+/// Parses `exact`, `f32`, `abs:EPSILON`, `rel:EPSILON` or `ulps:N`, as
+/// written on the command line.
+impl FromStr for FloatTolerance {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = || Error::ParseError(format!(
+            "invalid float tolerance '{}'; expected exact, f32, abs:EPSILON, rel:EPSILON or ulps:N", s,
+        ));
+        let epsilon = |x: &str| x.parse::<f64>().ok().filter(|e| *e >= 0.0).ok_or_else(invalid);
+        match s.split_once(':') {
+            None if s == "exact" => Ok(FloatTolerance::Exact),
+            None if s == "f32" => Ok(FloatTolerance::F32),
+            Some(("abs", x)) => epsilon(x).map(FloatTolerance::Absolute),
+            Some(("rel", x)) => epsilon(x).map(FloatTolerance::Relative),
+            Some(("ulps", x)) => x.parse().map(FloatTolerance::Ulps).map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// `x` as an integer that counts representable floats, so neighbours
+/// differ by one, across zero too.
+fn ordered(x: f64) -> i128 {
+    let bits = x.to_bits() as i64;
+    (if bits < 0 { i64::MIN - bits } else { bits }) as i128
+}
+
+impl Value {
+    /// Whether the two values mean the same, with floats compared exactly.
+    /// See [`semantic_eq_with`](Self::semantic_eq_with).
+    pub fn semantic_eq(&self, other: &Value) -> bool {
+        self.semantic_eq_with(other, FloatTolerance::Exact)
+    }
+
+    /// Whether the two values mean the same, with floats compared under
+    /// `tolerance`.
+    ///
+    /// Numbers compare by value whatever their variant: integers against
+    /// integers exactly, and against floats or JSON numbers as floats under
+    /// `tolerance`.
+    /// Objects compare regardless of key order, timestamps by instant
+    /// regardless of offset and precision, and arrays, maps and tagged
+    /// values element by element. Everything else compares as `==` does.
+    pub fn semantic_eq_with(&self, other: &Value, tolerance: FloatTolerance) -> bool {
+        match (self, other) {
+            (Value::Object(a), Value::Object(b)) => {
+                a.len() == b.len()
+                    && a.iter().all(|(key, x)| b.get(key).is_some_and(|y| x.semantic_eq_with(y, tolerance)))
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.semantic_eq_with(y, tolerance))
+            }
+            (Value::Map(a), Value::Map(b)) => {
+                a.len() == b.len()
+                    && a.iter().zip(b).all(|((ka, va), (kb, vb))| {
+                        ka.semantic_eq_with(kb, tolerance) && va.semantic_eq_with(vb, tolerance)
+                    })
+            }
+            (Value::Tagged(tag_a, a), Value::Tagged(tag_b, b)) => tag_a == tag_b && a.semantic_eq_with(b, tolerance),
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::UInt(a), Value::UInt(b)) => a == b,
+            (Value::Int(a), Value::UInt(b)) | (Value::UInt(b), Value::Int(a)) => u64::try_from(*a) == Ok(*b),
+            (Value::JsonNumber(a), Value::JsonNumber(b)) if a == b => true,
+            _ => match (instant(self), instant(other)) {
+                (Some(a), Some(b)) => a == b,
+                _ => match (number(self), number(other)) {
+                    (Some(a), Some(b)) => tolerance.matches(a, b),
+                    _ => self == other,
+                },
+            },
+        }
+    }
+}
+
+impl TeaLeaf {
+    /// The paths, in [`get_path`](Self::get_path) syntax, at which the two
+    /// documents mean different things, in document order. Empty if every
+    /// value is [`semantic_eq_with`](Value::semantic_eq_with) its
+    /// counterpart.
+    ///
+    /// Objects and same-length arrays are compared entry by entry, so only
+    /// the values that differ are listed; a key or element present on one
+    /// side only is listed by its own path.
+    pub fn semantic_differences(&self, other: &TeaLeaf, tolerance: FloatTolerance) -> Vec<String> {
+        let mut found = Vec::new();
+        differences(&self.data, &other.data, tolerance, "", &mut found);
+        found
+    }
+}
+
+fn differences(
+    a: &crate::IndexMap<String, Value>,
+    b: &crate::IndexMap<String, Value>,
+    tolerance: FloatTolerance,
+    path: &str,
+    found: &mut Vec<String>,
+) {
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    for (key, x) in a {
+        match b.get(key) {
+            Some(y) => value_differences(x, y, tolerance, &join(key), found),
+            None => found.push(join(key)),
+        }
+    }
+    found.extend(b.keys().filter(|key| !a.contains_key(*key)).map(|key| join(key)));
+}
+
+fn value_differences(a: &Value, b: &Value, tolerance: FloatTolerance, path: &str, found: &mut Vec<String>) {
+    match (a, b) {
+        (Value::Object(x), Value::Object(y)) => differences(x, y, tolerance, path, found),
+        (Value::Array(x), Value::Array(y)) if x.len() == y.len() => {
+            for (i, (x, y)) in x.iter().zip(y).enumerate() {
+                value_differences(x, y, tolerance, &format!("{}[{}]", path, i), found);
+            }
+        }
+        _ if a.semantic_eq_with(b, tolerance) => {}
+        _ => found.push(path.to_string()),
+    }
+}
+
+/// A timestamp as Unix nanoseconds.
+fn instant(value: &Value) -> Option<i128> {
+    match value {
+        Value::Timestamp(millis, _) => Some(*millis as i128 * 1_000_000),
+        Value::TimestampNanos(nanos, _, _) => Some(*nanos as i128),
+        _ => None,
+    }
+}
+
+/// A number as a float, for comparing numbers of different variants.
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(i) => Some(*i as f64),
+        Value::UInt(u) => Some(*u as f64),
+        Value::Float(f) => Some(*f),
+        Value::JsonNumber(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ObjectMap, TimePrecision};
+
+    #[test]
+    fn test_float_tolerance() {
+        let lossy = 0.1f32 as f64;
+        assert!(!FloatTolerance::Exact.matches(0.1, lossy));
+        assert!(FloatTolerance::F32.matches(0.1, lossy));
+        assert!(FloatTolerance::Absolute(1e-6).matches(0.1, lossy));
+        assert!(FloatTolerance::Relative(1e-7).matches(0.1, lossy));
+        assert!(!FloatTolerance::Relative(1e-9).matches(0.1, lossy));
+        assert!(FloatTolerance::Ulps(1).matches(1.0, 1.0 + f64::EPSILON));
+        assert!(!FloatTolerance::Ulps(1).matches(1.0, 1.0 + 2.0 * f64::EPSILON));
+        assert!(FloatTolerance::Ulps(2).matches(-0.0, f64::from_bits(1)));
+        for tolerance in [FloatTolerance::Exact, FloatTolerance::Absolute(1.0), FloatTolerance::Ulps(u64::MAX)] {
+            assert!(tolerance.matches(f64::NAN, f64::NAN));
+            assert!(tolerance.matches(f64::INFINITY, f64::INFINITY));
+            assert!(!tolerance.matches(f64::INFINITY, f64::MAX));
+            assert!(!tolerance.matches(f64::NAN, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_semantic_eq() {
+        let mut a = ObjectMap::new();
+        a.insert("x".to_string(), Value::Int(7));
+        a.insert("y".to_string(), Value::Float(0.1));
+        let mut b = ObjectMap::new();
+        b.insert("y".to_string(), Value::Float(0.1f32 as f64));
+        b.insert("x".to_string(), Value::UInt(7));
+        let (a, b) = (Value::Object(a), Value::Object(b));
+        assert!(!a.semantic_eq(&b));
+        assert!(a.semantic_eq_with(&b, FloatTolerance::F32));
+        assert!(!a.semantic_eq_with(&Value::Object(ObjectMap::new()), FloatTolerance::F32));
+
+        assert!(Value::Int(3).semantic_eq(&Value::Float(3.0)));
+        assert!(Value::JsonNumber("2.50".into()).semantic_eq(&Value::Float(2.5)));
+        assert!(!Value::Int(-1).semantic_eq(&Value::UInt(u64::MAX)));
+        assert!(Value::Float(f64::NAN).semantic_eq(&Value::Float(f64::NAN)));
+        assert!(Value::Timestamp(1_500, 60).semantic_eq(&Value::TimestampNanos(1_500_000_000, 0, TimePrecision::Nanos)));
+        assert!(!Value::String("1".into()).semantic_eq(&Value::Int(1)));
+        let tagged = |v| Value::Tagged("t".into(), Box::new(v));
+        assert!(tagged(Value::Int(1)).semantic_eq(&tagged(Value::UInt(1))));
+        assert!(!Value::Array(vec![Value::Int(1)]).semantic_eq(&Value::Array(vec![])));
+
+        // Integers past 2^53 compare exactly, not as nearby floats
+        let big = 1i64 << 60;
+        assert!(!Value::Int(big).semantic_eq_with(&Value::Int(big + 1), FloatTolerance::Relative(1e-9)));
+        assert!(!Value::UInt(u64::MAX).semantic_eq_with(&Value::UInt(u64::MAX - 1), FloatTolerance::F32));
+        assert!(!Value::Int(big).semantic_eq_with(&Value::UInt(big as u64 + 1), FloatTolerance::F32));
+        assert!(Value::Int(big).semantic_eq_with(&Value::UInt(big as u64), FloatTolerance::Exact));
+    }
+
+    #[test]
+    fn test_parse_tolerance() {
+        assert_eq!("exact".parse::<FloatTolerance>().unwrap(), FloatTolerance::Exact);
+        assert_eq!("f32".parse::<FloatTolerance>().unwrap(), FloatTolerance::F32);
+        assert_eq!("abs:1e-6".parse::<FloatTolerance>().unwrap(), FloatTolerance::Absolute(1e-6));
+        assert_eq!("rel:0.01".parse::<FloatTolerance>().unwrap(), FloatTolerance::Relative(0.01));
+        assert_eq!("ulps:4".parse::<FloatTolerance>().unwrap(), FloatTolerance::Ulps(4));
+        for bad in ["", "abs", "abs:-1", "rel:x", "ulps:1.5", "f64"] {
+            assert!(bad.parse::<FloatTolerance>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_semantic_differences() {
+        let expected = TeaLeaf::parse("a: {x: 0.1, y: 2}\nrows: [1, 2.5, 3]\nonly_here: 1\nsame: [1, 2]").unwrap();
+        let mut actual = TeaLeaf::parse("a: {y: 2, x: 0.1}\nrows: [1, 2.5, 4]\nextra: 2\nsame: [1]").unwrap();
+        if let Some(Value::Object(a)) = actual.data.get_mut("a") {
+            a.insert("x".to_string(), Value::Float(0.1f32 as f64));
+        }
+        assert_eq!(expected.semantic_differences(&actual, FloatTolerance::Exact), ["a.x", "rows[2]", "only_here", "same", "extra"]);
+        assert_eq!(expected.semantic_differences(&actual, FloatTolerance::F32), ["rows[2]", "only_here", "same", "extra"]);
+        assert!(expected.semantic_differences(&expected.clone(), FloatTolerance::Exact).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+//! Splitting a table into shards.
+//!
+//! [`TeaLeaf::shard_table`] splits the rows under one key into several
+//! documents, each carrying the schemas and unions of the original, so every
+//! shard reads and compiles on its own. A manifest document lists the
+//! shards, and [`Sharded::write`] saves them as binary files next to it for
+//! downstream jobs to process in parallel.
+//!
+//! Hash sharding uses 64-bit FNV-1a over the text of the field, so a row
+//! lands in the same shard on every platform and release.
+
+use crate::types::value_kind;
+use crate::{Error, FieldType, IndexMap, Result, Schema, TeaLeaf, Value};
+
+/// How [`TeaLeaf::shard_table`] assigns rows to shards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShardBy {
+    /// Consecutive runs of at most this many rows, in order
+    Rows(usize),
+    /// This many shards, by a hash of the named field. Rows with equal
+    /// values share a shard; order within a shard is kept.
+    Hash(String, usize),
+}
+
+/// The shards of a table, from [`TeaLeaf::shard_table`].
+#[derive(Clone)]
+pub struct Sharded {
+    /// One document per shard, holding the sharded key only
+    pub shards: Vec<TeaLeaf>,
+    /// `key`, `schema`, `by`, `field` and `rows` of the split, and a
+    /// `shards` table with the file name and row count of each shard
+    pub manifest: TeaLeaf,
+    files: Vec<String>,
+}
+
+impl Sharded {
+    /// The file name [`write`](Self::write) gives each shard:
+    /// `<key>-00000.tlbx`, `<key>-00001.tlbx` and so on.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Compile each shard into `dir` under its file name, and write the
+    /// manifest there as `<key>.manifest.tl`. Creates `dir` if needed.
+    #[cfg(all(feature = "text", feature = "binary"))]
+    pub fn write<P: AsRef<std::path::Path>>(&self, dir: P, compress: bool) -> Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        for (shard, file) in self.shards.iter().zip(&self.files) {
+            shard.compile(dir.join(file), compress)?;
+        }
+        let key = self.manifest.get("key").and_then(Value::as_str).unwrap_or_default();
+        std::fs::write(dir.join(format!("{}.manifest.tl", file_stem(key))), self.manifest.to_tl_with_schemas())?;
+        Ok(())
+    }
+}
+
+impl TeaLeaf {
+    /// Split the array under top-level `key` into shards.
+    ///
+    /// Each shard is a document holding `key` with its share of the rows,
+    /// bound to the same schema, with all of this document's schemas and
+    /// unions. Other keys are left out. `Rows` gives no shards for an empty
+    /// array; `Hash` always gives the number asked for, some possibly empty.
+    ///
+    /// Hashing reads the field of each row: strings as they are, numbers and
+    /// bools as written in text, and null or a missing field as empty text.
+    ///
+    /// Fails with [`Error::MissingField`] if `key` is absent, and
+    /// [`Error::ParseError`] if it is not an array, the shard size or count
+    /// is zero, or a hashed row is not an object or holds a field value of
+    /// another kind.
+    pub fn shard_table(&self, key: &str, by: &ShardBy) -> Result<Sharded> {
+        let rows = match self.get(key) {
+            Some(Value::Array(rows)) => rows,
+            Some(other) => return Err(Error::ParseError(format!("cannot shard '{}': it is {}, not an array", key, value_kind(other)))),
+            None => return Err(Error::MissingField(key.to_string())),
+        };
+        let groups: Vec<Vec<Value>> = match by {
+            ShardBy::Rows(0) | ShardBy::Hash(_, 0) => {
+                return Err(Error::ParseError(format!("cannot shard '{}' into shards of zero", key)));
+            }
+            ShardBy::Rows(n) => rows.chunks(*n).map(<[Value]>::to_vec).collect(),
+            ShardBy::Hash(field, count) => {
+                let mut groups = vec![Vec::new(); *count];
+                for (i, row) in rows.iter().enumerate() {
+                    let text = hash_text(row, field).map_err(|found| {
+                        Error::ParseError(format!("cannot shard '{}[{}]' by '{}': {}", key, i, field, found))
+                    })?;
+                    groups[(fnv1a(text.as_bytes()) % *count as u64) as usize].push(row.clone());
+                }
+                groups
+            }
+        };
+
+        let stem = file_stem(key);
+        let files: Vec<String> = (0..groups.len()).map(|i| format!("{}-{:05}.tlbx", stem, i)).collect();
+        let mut entries = Vec::with_capacity(groups.len());
+        let mut shards = Vec::with_capacity(groups.len());
+        for (rows, file) in groups.into_iter().zip(&files) {
+            entries.push(Value::Object(
+                [("file".to_string(), Value::String(file.clone())), ("rows".to_string(), Value::Int(rows.len() as i64))]
+                    .into_iter().collect(),
+            ));
+            let mut data = IndexMap::new();
+            data.insert(key.to_string(), Value::Array(rows));
+            let mut shard = TeaLeaf::new(self.schemas.clone(), data);
+            shard.unions = self.unions.clone();
+            if let Some(schema) = self.binding(key) {
+                shard.bind(key, schema);
+            }
+            shards.push(shard);
+        }
+
+        let (by_name, field) = match by {
+            ShardBy::Rows(_) => ("rows", Value::Null),
+            ShardBy::Hash(field, _) => ("hash", Value::String(field.clone())),
+        };
+        let mut data = IndexMap::new();
+        data.insert("key".to_string(), Value::String(key.to_string()));
+        data.insert("schema".to_string(), self.binding(key).map_or(Value::Null, |s| Value::String(s.to_string())));
+        data.insert("by".to_string(), Value::String(by_name.to_string()));
+        data.insert("field".to_string(), field);
+        data.insert("rows".to_string(), Value::Int(rows.len() as i64));
+        data.insert("shards".to_string(), Value::Array(entries));
+        let mut schemas = IndexMap::new();
+        schemas.insert("shard".to_string(), Schema::new("shard")
+            .field("file", FieldType::new("string"))
+            .field("rows", FieldType::new("int")));
+        let mut manifest = TeaLeaf::new(schemas, data);
+        manifest.bind("shards", "shard");
+
+        Ok(Sharded { shards, manifest, files })
+    }
+}
+
+/// The text of `row.field` that picks its shard, or the kind of what is
+/// there instead.
+fn hash_text(row: &Value, field: &str) -> std::result::Result<String, String> {
+    let obj = match row {
+        Value::Object(obj) => obj,
+        other => return Err(format!("the row is {}", value_kind(other))),
+    };
+    match obj.get(field) {
+        None | Some(Value::Null) => Ok(String::new()),
+        Some(Value::String(s) | Value::JsonNumber(s)) => Ok(s.clone()),
+        Some(Value::Int(i)) => Ok(i.to_string()),
+        Some(Value::UInt(u)) => Ok(u.to_string()),
+        Some(Value::Bool(b)) => Ok(b.to_string()),
+        Some(other) => Err(format!("the field is {}", value_kind(other))),
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// `key` with characters that do not belong in a file name replaced by `_`.
+fn file_stem(key: &str) -> String {
+    key.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"
+        @struct user (id: int, region: string)
+        users: @table user [
+            (1, eu), (2, us), (3, eu), (4, apac), (5, us),
+        ]
+        other: 1
+    "#;
+
+    #[test]
+    fn test_shard_by_rows() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let sharded = doc.shard_table("users", &ShardBy::Rows(2)).unwrap();
+        let ids: Vec<Vec<i64>> = sharded.shards.iter()
+            .map(|s| s.get("users").unwrap().as_array().unwrap().iter().map(|r| r.get("id").unwrap().as_int().unwrap()).collect())
+            .collect();
+        assert_eq!(ids, vec![vec![1, 2], vec![3, 4], vec![5]]);
+        for shard in &sharded.shards {
+            assert_eq!(shard.binding("users"), Some("user"));
+            assert!(shard.schema("user").is_some());
+            assert!(shard.get("other").is_none());
+        }
+        assert_eq!(sharded.files(), ["users-00000.tlbx", "users-00001.tlbx", "users-00002.tlbx"]);
+
+        let manifest = &sharded.manifest;
+        assert_eq!(manifest.get("by").and_then(Value::as_str), Some("rows"));
+        assert_eq!(manifest.get("rows").and_then(Value::as_int), Some(5));
+        assert_eq!(manifest.get_path("shards[2].rows").and_then(Value::as_int), Some(1));
+        let text = manifest.to_tl_with_schemas();
+        assert!(text.contains("shards: @table shard [\n  (users-00000.tlbx, 2),"), "{}", text);
+    }
+
+    #[test]
+    fn test_shard_by_hash() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let sharded = doc.shard_table("users", &ShardBy::Hash("region".into(), 3)).unwrap();
+        assert_eq!(sharded.shards.len(), 3);
+        // Every row lands in one shard, and equal regions share a shard
+        let mut shard_of = std::collections::HashMap::new();
+        let mut total = 0;
+        for (i, shard) in sharded.shards.iter().enumerate() {
+            for row in shard.get("users").unwrap().as_array().unwrap() {
+                let region = row.get("region").unwrap().as_str().unwrap();
+                assert_eq!(*shard_of.entry(region).or_insert(i), i, "{}", region);
+                total += 1;
+            }
+        }
+        assert_eq!(total, 5);
+        assert_eq!(sharded.manifest.get("field").and_then(Value::as_str), Some("region"));
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        for (key, by, expected) in [
+            ("users", ShardBy::Rows(0), "zero"),
+            ("other", ShardBy::Rows(2), "it is int, not an array"),
+            ("missing", ShardBy::Rows(2), "missing"),
+        ] {
+            let err = doc.shard_table(key, &by).err().unwrap().to_string();
+            assert!(err.contains(expected), "{}", err);
+        }
+        let doc = TeaLeaf::parse("rows: [{tags: [a]}, 3]").unwrap();
+        let err = doc.shard_table("rows", &ShardBy::Hash("tags".into(), 2)).err().unwrap().to_string();
+        assert!(err.contains("'rows[0]' by 'tags': the field is array"), "{}", err);
+    }
+
+    #[cfg(feature = "binary")]
+    #[test]
+    fn test_write_shards() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let sharded = doc.shard_table("users", &ShardBy::Rows(3)).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        sharded.write(dir.path().join("out"), true).unwrap();
+        let manifest = TeaLeaf::load(dir.path().join("out/users.manifest.tl")).unwrap();
+        assert_eq!(manifest.get("shards").unwrap().as_array().unwrap().len(), 2);
+        let reader = crate::Reader::open(dir.path().join("out/users-00001.tlbx")).unwrap();
+        assert_eq!(reader.get("users").unwrap().as_array().unwrap().len(), 2);
+    }
+}
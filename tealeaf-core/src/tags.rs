@@ -0,0 +1,305 @@
+//! Listing, renaming and checking the tags of tagged values.
+
+use std::collections::HashSet;
+
+use crate::convert::FromTeaLeaf;
+use crate::transaction::{mismatch, violation, Conformance};
+use crate::types::value_kind;
+use crate::{Error, IndexMap, ObjectMap, Result, TeaLeaf, Union, Value, object_matches_schema, resolve_schema};
+
+/// Where one tag is used, from [`TeaLeaf::tags`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagUsage {
+    /// Path of each tagged value, in `get_path` syntax and document order.
+    /// Map entries are addressed by position.
+    pub paths: Vec<String>,
+}
+
+impl TagUsage {
+    /// Number of values carrying the tag.
+    pub fn count(&self) -> usize {
+        self.paths.len()
+    }
+}
+
+/// A tagged value in a union-typed field whose tag is not a variant of
+/// that union, from [`TeaLeaf::validate_tags`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownTag {
+    pub path: String,
+    pub tag: String,
+    pub union: String,
+}
+
+impl TeaLeaf {
+    /// Every tag used in the data, in order of first use, with the paths of
+    /// the values carrying it.
+    pub fn tags(&self) -> IndexMap<String, TagUsage> {
+        let mut tags: IndexMap<String, TagUsage> = IndexMap::new();
+        let mut path = String::new();
+        for (key, value) in &self.data {
+            path.clear();
+            path.push_str(key);
+            visit_tags(value, &mut path, &mut |tag, path| {
+                tags.entry(tag.to_string()).or_default().paths.push(path.to_string());
+            });
+        }
+        tags
+    }
+
+    /// Change the tag of every value tagged `old` to `new`, returning how
+    /// many were changed.
+    ///
+    /// Union definitions are left as they are; rename the variant with the
+    /// union itself if `old` is one.
+    pub fn retag(&mut self, old: &str, new: &str) -> usize {
+        let mut count = 0;
+        for value in self.data.values_mut() {
+            retag_value(value, old, new, &mut count);
+        }
+        count
+    }
+
+    /// Tagged values whose tag is not a variant of the union declared by
+    /// the schema field holding them.
+    ///
+    /// Objects are bound to schemas the way the text writer binds them.
+    /// Tags outside union-typed fields, and untagged values in them, are not
+    /// checked. Empty if the document declares no unions.
+    pub fn validate_tags(&self) -> Vec<UnknownTag> {
+        let mut unknown = Vec::new();
+        if self.unions.is_empty() {
+            return unknown;
+        }
+        let mut path = String::new();
+        for (key, value) in &self.data {
+            path.clear();
+            path.push_str(key);
+            self.check_tags(value, self.binding(key), Some(key), &mut path, &mut unknown);
+        }
+        unknown
+    }
+
+    /// Convert the tagged value at top-level `key` into `T`, an enum
+    /// deriving `FromTeaLeaf`, after checking it against the `@union`
+    /// `T` reads.
+    ///
+    /// The tag must name a variant of the union, and the payload must match
+    /// that variant's fields: null for a variant without fields, the value
+    /// itself for a single positional field, an array for several, and an
+    /// object for named fields. Fails with [`Error::MissingField`] if `key`
+    /// is absent, [`Error::ParseError`] if `T` reads no union or the
+    /// document does not declare it, and [`Error::SchemaViolation`] if the
+    /// value does not match it.
+    pub fn to_union_dto<T: FromTeaLeaf>(&self, key: &str) -> Result<T> {
+        let value = self.get(key).ok_or_else(|| Error::MissingField(key.to_string()))?;
+        let name = T::tealeaf_union().ok_or_else(|| {
+            Error::ParseError(format!("{} does not read a union; derive FromTeaLeaf on an enum", std::any::type_name::<T>()))
+        })?;
+        let union = self.unions.get(name).ok_or_else(|| Error::ParseError(format!("the document declares no @union {}", name)))?;
+        self.check_union_value(value, union, key)?;
+        Ok(T::from_tealeaf_value(value)?)
+    }
+
+    fn check_union_value(&self, value: &Value, union: &Union, path: &str) -> Result<()> {
+        let (tag, payload) = match value {
+            Value::Tagged(tag, payload) => (tag, payload.as_ref()),
+            other => return Err(mismatch(path, &format!("a tagged value of union '{}'", union.name), other)),
+        };
+        let variant = union.get_variant(tag).ok_or_else(|| {
+            let names: Vec<&str> = union.variants.iter().map(|v| v.name.as_str()).collect();
+            violation(path, format!(
+                "tag '{}' is not a variant of union '{}' (expected one of: {})",
+                tag, union.name, names.join(", "),
+            ))
+        })?;
+        let check = Conformance { doc: self };
+        let owner = format!("variant '{}' of union '{}'", variant.name, union.name);
+        let positional = variant.fields.iter().enumerate().all(|(i, f)| f.name == i.to_string());
+        match (variant.fields.as_slice(), payload) {
+            ([], Value::Null) => Ok(()),
+            ([], other) => Err(violation(path, format!("{} has no fields, but holds {}", owner, value_kind(other)))),
+            ([field], _) if positional => check.field(payload, &field.field_type, path),
+            (fields, Value::Array(items)) if positional => {
+                if items.len() != fields.len() {
+                    return Err(violation(path, format!("holds {} values, but {} has {} fields", items.len(), owner, fields.len())));
+                }
+                fields.iter().zip(items).enumerate()
+                    .try_for_each(|(i, (f, item))| check.field(item, &f.field_type, &format!("{}[{}]", path, i)))
+            }
+            (fields, Value::Object(obj)) if !positional => check.fields(obj, fields, &owner, path),
+            (_, other) => Err(mismatch(path, if positional { "an array" } else { "an object" }, other)),
+        }
+    }
+
+    fn check_tags(&self, value: &Value, declared_type: Option<&str>, hint_name: Option<&str>, path: &mut String, unknown: &mut Vec<UnknownTag>) {
+        match value {
+            Value::Array(items) => {
+                // Rows of a table are bound to its schema
+                let schema = resolve_schema(&self.schemas, declared_type, hint_name).filter(|schema| {
+                    match items.first() {
+                        Some(Value::Object(first)) => {
+                            let keys: HashSet<&str> = first.keys().map(|k| k.as_str()).collect();
+                            object_matches_schema(&keys, schema)
+                        }
+                        _ => false,
+                    }
+                });
+                for (i, item) in items.iter().enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("[{}]", i));
+                    self.check_tags(item, schema.map(|s| s.name.as_str()), None, path, unknown);
+                    path.truncate(len);
+                }
+            }
+            Value::Object(obj) => self.check_object_tags(obj, declared_type, hint_name, path, unknown),
+            Value::Map(pairs) => {
+                for (i, (_, v)) in pairs.iter().enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("[{}]", i));
+                    self.check_tags(v, None, None, path, unknown);
+                    path.truncate(len);
+                }
+            }
+            Value::Tagged(_, inner) => self.check_tags(inner, None, None, path, unknown),
+            _ => {}
+        }
+    }
+
+    fn check_object_tags(&self, obj: &ObjectMap<String, Value>, declared_type: Option<&str>, hint_name: Option<&str>, path: &mut String, unknown: &mut Vec<UnknownTag>) {
+        let schema = resolve_schema(&self.schemas, declared_type, hint_name).or_else(|| {
+            let keys: HashSet<&str> = obj.keys().map(|k| k.as_str()).collect();
+            self.schemas.values().find(|s| object_matches_schema(&keys, s))
+        });
+        for (k, child) in obj {
+            let len = path.len();
+            path.push('.');
+            path.push_str(k);
+            let field = schema.and_then(|s| s.fields.iter().find(|f| f.name == *k));
+            if let Some(union) = field.and_then(|f| self.unions.get(&f.field_type.base)) {
+                let mut check = |value: &Value, path: &str| {
+                    if let Value::Tagged(tag, _) = value {
+                        if union.variant_index(tag).is_none() {
+                            unknown.push(UnknownTag { path: path.to_string(), tag: tag.clone(), union: union.name.clone() });
+                        }
+                    }
+                };
+                match child {
+                    Value::Array(items) if field.is_some_and(|f| f.field_type.is_array) => {
+                        for (i, item) in items.iter().enumerate() {
+                            check(item, &format!("{}[{}]", path, i));
+                        }
+                    }
+                    _ => check(child, path),
+                }
+            }
+            self.check_tags(child, field.map(|f| f.field_type.base.as_str()), Some(k), path, unknown);
+            path.truncate(len);
+        }
+    }
+}
+
+fn visit_tags(value: &Value, path: &mut String, visit: &mut impl FnMut(&str, &str)) {
+    match value {
+        Value::Tagged(tag, inner) => {
+            visit(tag, path);
+            visit_tags(inner, path, visit);
+        }
+        Value::Array(items) => {
+            for (i, v) in items.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                visit_tags(v, path, visit);
+                path.truncate(len);
+            }
+        }
+        Value::Object(obj) => {
+            for (k, v) in obj {
+                let len = path.len();
+                path.push('.');
+                path.push_str(k);
+                visit_tags(v, path, visit);
+                path.truncate(len);
+            }
+        }
+        Value::Map(pairs) => {
+            for (i, (_, v)) in pairs.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                visit_tags(v, path, visit);
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn retag_value(value: &mut Value, old: &str, new: &str, count: &mut usize) {
+    match value {
+        Value::Tagged(tag, inner) => {
+            if tag == old {
+                *tag = new.to_string();
+                *count += 1;
+            }
+            retag_value(inner, old, new, count);
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| retag_value(v, old, new, count)),
+        Value::Object(obj) => obj.values_mut().for_each(|v| retag_value(v, old, new, count)),
+        Value::Map(pairs) => pairs.iter_mut().for_each(|(_, v)| retag_value(v, old, new, count)),
+        _ => {}
+    }
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"
+        @union shape { circle (radius: float), square (side: float) }
+        @struct drawing (name: string, main: shape, extra: []shape)
+        drawings: @table drawing [
+            (one, :circle (1.0), [:square (2.0), :hexagon (3.0)]),
+            (two, :triangle (1.0), []),
+        ]
+        events: [:created {at: 1}, {inner: :created ~}, :deleted ~]
+        lookup: @map {1: :created ~}
+    "#;
+
+    #[test]
+    fn test_tags_lists_usage() {
+        let doc = TeaLeaf::parse(DOC).unwrap();
+        let tags = doc.tags();
+        assert_eq!(tags.keys().collect::<Vec<_>>(), vec!["circle", "square", "hexagon", "triangle", "created", "deleted"]);
+        assert_eq!(tags["created"].count(), 3);
+        assert_eq!(tags["created"].paths, vec!["events[0]", "events[1].inner", "lookup[0]"]);
+        assert_eq!(tags["hexagon"].paths, vec!["drawings[0].extra[1]"]);
+    }
+
+    #[test]
+    fn test_retag() {
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        assert_eq!(doc.retag("created", "added"), 3);
+        assert_eq!(doc.retag("created", "added"), 0);
+        assert!(!doc.tags().contains_key("created"));
+        assert_eq!(doc.tags()["added"].count(), 3);
+        assert!(doc.to_tl_with_schemas().contains(":added"));
+    }
+
+    #[test]
+    fn test_validate_tags_against_unions() {
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        let unknown = doc.validate_tags();
+        assert_eq!(unknown, vec![
+            UnknownTag { path: "drawings[0].extra[1]".into(), tag: "hexagon".into(), union: "shape".into() },
+            UnknownTag { path: "drawings[1].main".into(), tag: "triangle".into(), union: "shape".into() },
+        ]);
+
+        doc.retag("hexagon", "square");
+        doc.retag("triangle", "circle");
+        assert!(doc.validate_tags().is_empty());
+
+        // Without unions nothing is checked
+        let plain = TeaLeaf::parse("events: [:created ~]").unwrap();
+        assert!(plain.validate_tags().is_empty());
+    }
+}
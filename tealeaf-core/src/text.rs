@@ -0,0 +1,113 @@
+//! Quoting and escaping rules of the TeaLeaf text format.
+//!
+//! These are the rules the text writer itself uses; code that produces `.tl`
+//! text by hand can use them to guarantee its output parses back unchanged.
+
+use std::borrow::Cow;
+
+/// Check if a string needs quoting when serialized to TeaLeaf format.
+/// Returns true if the string could be misinterpreted as another type.
+pub fn needs_quoting(s: &str) -> bool {
+    if s.is_empty() {
+        return true;
+    }
+
+    // Reserved words, null literal, and float literals the lexer would interpret
+    if matches!(s, "true" | "false" | "null" | "~" | "NaN" | "inf" | "Infinity") {
+        return true;
+    }
+
+    // Whitelist approach: only allow [a-zA-Z0-9_-.] unquoted (ASCII only).
+    // Matches spec grammar: name = (letter | "_") { letter | digit | "_" | "-" | "." }
+    // Any other character (Unicode digits, whitespace, punctuation, etc.)
+    // requires quoting to ensure safe round-trip through the parser.
+    // Note: '-' is excluded here because strings starting with '-' are caught
+    // by the sign-character check below, and mid-string '-' in identifiers
+    // like "foo-bar" is safe only when the first char is a letter.
+    if s.contains(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-' && c != '.') {
+        return true;
+    }
+
+    // Must start with letter or underscore per grammar: name = (letter | "_") { ... }
+    let first = s.chars().next().unwrap();
+    if !first.is_ascii_alphabetic() && first != '_' {
+        return true;
+    }
+
+    // Starts with 0x/0b (hex/binary literal prefix)
+    if s.starts_with("0x") || s.starts_with("0X") || s.starts_with("0b") || s.starts_with("0B") {
+        return true;
+    }
+
+    // Starts with sign character — always quote to avoid parser ambiguity
+    // (parser may try to interpret as a signed number).
+    if s.starts_with('-') || s.starts_with('+') {
+        return true;
+    }
+
+    // Starts with a digit — could be parsed as a number
+    if first.is_ascii_digit() {
+        return true;
+    }
+
+    false
+}
+
+/// Escape a string for TeaLeaf text output.
+/// Handles: \\ \" \n \t \r \b \f and \uXXXX for other control characters.
+pub fn escape_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\u{0008}' => out.push_str("\\b"),
+            '\u{000C}' => out.push_str("\\f"),
+            c if c.is_control() => {
+                // Other control characters use \uXXXX
+                for unit in c.encode_utf16(&mut [0u16; 2]) {
+                    out.push_str(&format!("\\u{:04x}", unit));
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Write `key` the way the text writer does: bare when it is a valid name,
+/// otherwise as a quoted, escaped string.
+pub fn quote_key(key: &str) -> Cow<'_, str> {
+    if needs_quoting(key) {
+        Cow::Owned(format!("\"{}\"", escape_string(key)))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_key() {
+        assert_eq!(quote_key("name"), "name");
+        assert_eq!(quote_key("first name"), "\"first name\"");
+        assert_eq!(quote_key("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote_key(""), "\"\"");
+        assert_eq!(quote_key("true"), "\"true\"");
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn test_quoted_keys_parse_back() {
+        for key in ["plain", "with space", "a:b", "tab\there", "42", "-x", "日本"] {
+            let text = format!("{}: 1", quote_key(key));
+            let doc = crate::TeaLeaf::parse(&text).unwrap();
+            assert_eq!(doc.get(key), Some(&crate::Value::Int(1)), "{}", text);
+        }
+    }
+}
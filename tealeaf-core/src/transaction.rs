@@ -0,0 +1,290 @@
+//! All-or-nothing edits of a document.
+//!
+//! [`TeaLeaf::transaction`] runs a closure against a [`Transaction`], a
+//! private copy of the document. The edits replace the document only if the
+//! closure succeeds and every changed key still matches the schema it is
+//! bound to, so a multi-step edit that fails halfway leaves nothing behind.
+
+use crate::mapping::{put, take};
+use crate::types::{split_path_root, value_kind};
+use crate::{Error, Field, FieldType, ObjectMap, Result, Schema, TeaLeaf, Value};
+
+/// A copy of a document being edited by [`TeaLeaf::transaction`].
+pub struct Transaction {
+    doc: TeaLeaf,
+}
+
+impl Transaction {
+    /// The document as edited so far.
+    pub fn document(&self) -> &TeaLeaf {
+        &self.doc
+    }
+
+    /// The document as edited so far, for edits beyond paths, such as
+    /// [`TeaLeaf::rename_key`] or [`TeaLeaf::bind`].
+    pub fn document_mut(&mut self) -> &mut TeaLeaf {
+        &mut self.doc
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.doc.get(key)
+    }
+
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        self.doc.get_path(path)
+    }
+
+    /// Set top-level `key` to `value`, keeping its position if it exists.
+    pub fn set(&mut self, key: &str, value: Value) {
+        self.doc.data.insert(key.to_string(), value);
+    }
+
+    /// Set the value at `path`, creating missing objects on the way.
+    ///
+    /// Fails with [`Error::ParseError`] if the path is malformed, passes
+    /// through a value that is not an object or array, or indexes past the
+    /// end of an array.
+    pub fn set_path(&mut self, path: &str, value: Value) -> Result<()> {
+        let mut root = Value::Object(std::mem::take(&mut self.doc.data));
+        let result = put(&mut root, path, value);
+        if let Value::Object(data) = root {
+            self.doc.data = data;
+        }
+        result
+    }
+
+    /// Remove and return the value at `path`, or `None` if there is none.
+    /// Array elements become null, so later indexes still hold. Removing a
+    /// top-level key drops its binding.
+    ///
+    /// Fails with [`Error::ParseError`] if the path is malformed.
+    pub fn remove(&mut self, path: &str) -> Result<Option<Value>> {
+        let mut root = Value::Object(std::mem::take(&mut self.doc.data));
+        let removed = take(&mut root, path);
+        if let Value::Object(data) = root {
+            self.doc.data = data;
+        }
+        if matches!(removed, Ok(Some(_))) && split_path_root(path).1.is_empty() {
+            self.doc.bindings.shift_remove(path);
+        }
+        removed
+    }
+}
+
+impl TeaLeaf {
+    /// Run `edit` against a copy of the document, and replace the document
+    /// with the copy if it succeeds.
+    ///
+    /// Before replacing, every key bound to a schema whose value, binding or
+    /// schema changed is checked against the schema: each object needs the
+    /// schema's non-nullable fields, no other keys, and values of the
+    /// fields' types, with integers accepted for floats. A value that does
+    /// not match fails with [`Error::SchemaViolation`], or
+    /// [`Error::MissingField`] for a missing field. If `edit` or the check
+    /// fails, the document is unchanged and the error is returned.
+    ///
+    /// ```
+    /// # use tealeaf::{TeaLeaf, Value};
+    /// # let mut doc = TeaLeaf::parse("server: {host: localhost, port: 80}").unwrap();
+    /// let result = doc.transaction(|tx| {
+    ///     tx.set_path("server.port", Value::Int(8080))?;
+    ///     tx.remove("server.host")?;
+    ///     tx.set_path("server.port.number", Value::Int(1)) // fails: port is an int
+    /// });
+    /// assert!(result.is_err());
+    /// assert_eq!(doc.get_path("server.port"), Some(&Value::Int(80)));
+    /// ```
+    pub fn transaction<T>(&mut self, edit: impl FnOnce(&mut Transaction) -> Result<T>) -> Result<T> {
+        let mut tx = Transaction { doc: self.clone() };
+        let out = edit(&mut tx)?;
+        let doc = &tx.doc;
+        for (key, name) in &doc.bindings {
+            let schema = doc.schemas.get(name);
+            let unchanged = self.bindings.get(key) == Some(name)
+                && self.schemas.get(name) == schema
+                && self.data.get(key) == doc.data.get(key);
+            if let (false, Some(schema), Some(value)) = (unchanged, schema, doc.data.get(key)) {
+                let check = Conformance { doc };
+                match value {
+                    Value::Array(items) => {
+                        for (i, item) in items.iter().enumerate() {
+                            check.row(item, schema, &format!("{}[{}]", key, i))?;
+                        }
+                    }
+                    _ => check.row(value, schema, key)?,
+                }
+            }
+        }
+        *self = tx.doc;
+        Ok(out)
+    }
+}
+
+/// Checks values against the schemas of `doc`.
+pub(crate) struct Conformance<'a> {
+    pub(crate) doc: &'a TeaLeaf,
+}
+
+impl Conformance<'_> {
+    fn row(&self, value: &Value, schema: &Schema, path: &str) -> Result<()> {
+        let obj: &ObjectMap<String, Value> = match value {
+            Value::Object(obj) => obj,
+            Value::Ref(_) => return Ok(()),
+            other => return Err(mismatch(path, &schema.name, other)),
+        };
+        self.fields(obj, &schema.fields, &format!("schema '{}'", schema.name), path)
+    }
+
+    /// Check that `obj` has each of `fields` it needs and no other keys.
+    /// `owner` names what declares the fields, for errors.
+    pub(crate) fn fields(&self, obj: &ObjectMap<String, Value>, fields: &[Field], owner: &str, path: &str) -> Result<()> {
+        for field in fields {
+            let path = format!("{}.{}", path, field.name);
+            match obj.get(&field.name) {
+                Some(child) => self.field(child, &field.field_type, &path)?,
+                None if field.field_type.nullable => {}
+                None => return Err(Error::MissingField(path)),
+            }
+        }
+        match obj.keys().find(|key| !fields.iter().any(|f| f.name == **key)) {
+            Some(key) => Err(violation(&format!("{}.{}", path, key), format!("not a field of {}", owner))),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn field(&self, value: &Value, field_type: &FieldType, path: &str) -> Result<()> {
+        if let Value::Null = value {
+            if field_type.nullable {
+                return Ok(());
+            }
+            return Err(violation(path, "null, but its type is not nullable".to_string()));
+        }
+        if field_type.is_array {
+            let item_type = FieldType { base: field_type.base.clone(), nullable: true, is_array: false };
+            return match value {
+                Value::Array(items) => items.iter().enumerate()
+                    .try_for_each(|(i, item)| self.field(item, &item_type, &format!("{}[{}]", path, i))),
+                Value::Ref(_) => Ok(()),
+                other => Err(mismatch(path, &format!("[]{}", field_type.base), other)),
+            };
+        }
+        let base = field_type.base.as_str();
+        if let Some(schema) = self.doc.schemas.get(base) {
+            return self.row(value, schema, path);
+        }
+        let conforms = match base {
+            "int" | "int8" | "int16" | "int32" | "int64" | "bytesize" | "duration" => {
+                matches!(value, Value::Int(_) | Value::UInt(_) | Value::JsonNumber(_))
+            }
+            "uint" | "uint8" | "uint16" | "uint32" | "uint64" => {
+                matches!(value, Value::UInt(_) | Value::JsonNumber(_)) || matches!(value, Value::Int(i) if *i >= 0)
+            }
+            "float" | "float32" | "float64" => {
+                matches!(value, Value::Float(_) | Value::Int(_) | Value::UInt(_) | Value::JsonNumber(_))
+            }
+            "bool" => matches!(value, Value::Bool(_)),
+            "string" => matches!(value, Value::String(_)),
+            "bytes" => matches!(value, Value::Bytes(_)),
+            "timestamp" | "timestamp_ns" => matches!(value, Value::Timestamp(..) | Value::TimestampNanos(..)),
+            "object" => matches!(value, Value::Object(_)),
+            "map" => matches!(value, Value::Map(_) | Value::Object(_)),
+            "tuple" => matches!(value, Value::Array(_)),
+            // Unions, secrets, `any` and unknown names are not checked
+            _ => true,
+        };
+        if conforms || matches!(value, Value::Ref(_)) {
+            Ok(())
+        } else {
+            Err(mismatch(path, base, value))
+        }
+    }
+}
+
+pub(crate) fn mismatch(path: &str, expected: &str, found: &Value) -> Error {
+    violation(path, format!("expected {}, got {}", expected, value_kind(found)))
+}
+
+pub(crate) fn violation(path: &str, reason: String) -> Error {
+    Error::SchemaViolation { path: path.to_string(), reason }
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"
+        @struct address (city: string, zip: string?)
+        @struct user (id: int, name: string, score: float, address: address?, tags: []string)
+        users: @table user [
+            (1, alice, 9.5, (Paris, "75001"), [admin]),
+            (2, bob, 7, ~, []),
+        ]
+        settings: {theme: dark, size: 12}
+    "#;
+
+    #[test]
+    fn test_transaction_commits_all_edits() {
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        let removed = doc.transaction(|tx| {
+            tx.set_path("settings.theme", Value::String("light".into()))?;
+            tx.set_path("settings.font.family", Value::String("mono".into()))?;
+            tx.set_path("users[1].address", Value::Object([("city".to_string(), Value::String("Oslo".into()))].into_iter().collect()))?;
+            tx.set("version", Value::Int(2));
+            tx.remove("settings.size")
+        }).unwrap();
+
+        assert_eq!(removed, Some(Value::Int(12)));
+        assert_eq!(doc.get_path("settings.theme").and_then(Value::as_str), Some("light"));
+        assert_eq!(doc.get_path("settings.font.family").and_then(Value::as_str), Some("mono"));
+        assert_eq!(doc.get_path("users[1].address.city").and_then(Value::as_str), Some("Oslo"));
+        assert!(doc.get_path("settings.size").is_none());
+        assert_eq!(doc.binding("users"), Some("user"));
+        assert!(doc.to_tl_with_schemas().contains("(2, bob, 7, (Oslo, ~), [])"));
+    }
+
+    #[test]
+    fn test_transaction_rolls_back() {
+        let mut doc = TeaLeaf::parse(DOC).unwrap();
+        let before = doc.clone();
+        type Edit = fn(&mut Transaction) -> Result<()>;
+        let fails: [(&str, Edit); 8] = [
+            ("'users[0].name': null", |tx| {
+                tx.remove("settings")?;
+                tx.set_path("users[0].name", Value::Null)
+            }),
+            ("path", |tx| tx.set_path("users[9].name", Value::Null)),
+            ("path", |tx| {
+                tx.set("settings", Value::Null);
+                tx.remove("users[x").map(|_| ())
+            }),
+            ("closure", |tx| {
+                tx.set("settings", Value::Null);
+                Err(Error::ParseError("closure".into()))
+            }),
+            ("users[1].name", |tx| tx.set_path("users[1].name", Value::Int(3))),
+            ("users[0].id", |tx| tx.remove("users[0].id")?.map(|_| ()).ok_or(Error::UnexpectedEof)),
+            ("users[0].address.country", |tx| tx.set_path("users[0].address.country", Value::String("FR".into()))),
+            ("users[0].tags[0]", |tx| tx.set_path("users[0].tags[0]", Value::Bool(true))),
+        ];
+        for (what, edit) in fails {
+            let err = doc.transaction(edit).unwrap_err();
+            assert!(what == "path" || err.to_string().contains(what), "{}: {}", what, err);
+            assert_eq!(doc.data, before.data, "{}", what);
+            assert_eq!(doc.binding("users"), Some("user"));
+        }
+
+        let err = doc.transaction(|tx| tx.set_path("users[1].name", Value::Int(3))).unwrap_err();
+        assert!(matches!(err, Error::SchemaViolation { ref path, .. } if path == "users[1].name"), "{}", err);
+
+        // Unbinding the key, or removing it, skips the check
+        doc.transaction(|tx| {
+            tx.set_path("users[0].name", Value::Int(3))?;
+            tx.document_mut().bindings.clear();
+            Ok(())
+        }).unwrap();
+        assert_eq!(doc.binding("users"), None);
+        let mut doc = before;
+        doc.transaction(|tx| tx.remove("users")?.map(|_| ()).ok_or(Error::UnexpectedEof)).unwrap();
+        assert_eq!(doc.binding("users"), None);
+    }
+}
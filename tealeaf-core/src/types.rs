@@ -6,6 +6,30 @@ use std::io;
 use indexmap::IndexMap;
 
 /// Ordered map type for object fields — preserves insertion order.
+///
+/// This is [`IndexMap`], so its whole API applies: `entry`, `retain`,
+/// `sort_keys`, `get_index` and the rest. Key order is what TeaLeaf writes
+/// in every format, and these methods keep it:
+///
+/// - `insert` of a new key appends it; of an existing key, replaces the
+///   value in place.
+/// - `shift_remove` (and `shift_remove_index`, `shift_insert`) move the
+///   keys after it, keeping their order, in O(n).
+/// - `swap_remove` moves the last key into the hole, in O(1). `remove` is
+///   `swap_remove`; use it only where order no longer matters.
+/// - `retain`, `sort_keys` and `sort_by` keep or define the order as
+///   documented by `IndexMap`.
+///
+/// ```
+/// use tealeaf::ObjectMap;
+///
+/// let mut obj: ObjectMap<&str, i32> = [("a", 1), ("b", 2), ("c", 3), ("d", 4)].into_iter().collect();
+/// obj.insert("b", 20);
+/// obj.shift_remove("a");
+/// assert_eq!(obj.keys().copied().collect::<Vec<_>>(), ["b", "c", "d"]);
+/// obj.swap_remove("b");
+/// assert_eq!(obj.keys().copied().collect::<Vec<_>>(), ["d", "c"]);
+/// ```
 pub type ObjectMap<K, V> = IndexMap<K, V>;
 
 // =============================================================================
@@ -13,8 +37,9 @@ pub type ObjectMap<K, V> = IndexMap<K, V>;
 // =============================================================================
 
 pub const MAGIC: [u8; 4] = *b"TLBX";
-/// Binary format version (major) - for compatibility checks
-pub const VERSION_MAJOR: u16 = 2;
+/// Binary format version (major) - for compatibility checks. Readers also
+/// accept major version 2, refusing the 3.0 features in such files.
+pub const VERSION_MAJOR: u16 = 3;
 /// Binary format version (minor) - for compatibility checks
 pub const VERSION_MINOR: u16 = 0;
 /// Library version string (beta/RFC stage)
@@ -26,12 +51,24 @@ pub const MAX_STRING_LENGTH: usize = u32::MAX as usize;
 pub const MAX_OBJECT_FIELDS: usize = u16::MAX as usize;
 /// Maximum number of elements in an array (u32 encoding limit)
 pub const MAX_ARRAY_LENGTH: usize = u32::MAX as usize;
+/// Union flag: values of union-typed schema fields are tagged by variant
+/// ordinal (u16) instead of by tag string index (u32)
+pub const UNION_FLAG_ORDINAL_TAGS: u16 = 0x0001;
+/// Maximum number of variants in a union; ordinals `0xFFFE` (untagged) and
+/// `0xFFFF` (tag outside the union) are reserved
+pub const MAX_UNION_VARIANTS: usize = 0xFFFE;
+/// Type code of an any-typed schema field (one whose type names neither a
+/// struct nor a union), and element type of a heterogeneous array
+pub const TYPE_CODE_ANY: u8 = 0xFF;
 
 // =============================================================================
 // Error Type
 // =============================================================================
 
+/// Everything that can go wrong reading, writing or converting TeaLeaf data.
+/// More variants may be added, so matches need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     Io(io::Error),
     InvalidMagic,
@@ -44,6 +81,57 @@ pub enum Error {
     MissingField(String),
     ParseError(String),
     ValueOutOfRange(String),
+    LimitExceeded(String),
+    /// A section was written with a codec the reader has not registered
+    MissingCodec { section: String, codec: u32 },
+    /// A field value (`field` as `schema.field`) was encrypted with a key
+    /// the reader or writer has no codec for
+    MissingKey { field: String, codec: u32, key_id: String },
+    /// Stopped by a [`Progress`](crate::progress::Progress) cancellation
+    Cancelled,
+    /// An object gives the same key twice; `line` and `col` locate the second
+    DuplicateKey { key: String, line: usize, col: usize },
+    /// A tuple has more values than schema `schema` has fields; `line` and
+    /// `col` locate the first value too many
+    TooManyValues { schema: String, fields: usize, line: usize, col: usize },
+    /// A `@struct` or union variant `definition` declares a field name that
+    /// is empty, repeated, or an unquoted literal; `line` and `col` locate it
+    InvalidFieldName { definition: String, field: String, reason: String, line: usize, col: usize },
+    /// Binary data nested deeper than the reader's
+    /// [`max_depth`](crate::ReaderOptions::max_depth)
+    DepthExceeded { limit: usize },
+    /// Text is in an encoding other than UTF-8
+    UnsupportedEncoding(crate::encoding::Encoding),
+    /// Section `section` holds values the binary writer cannot encode; all
+    /// of them are listed
+    Unencodable { section: String, values: Vec<Unencodable> },
+    /// A [`SchemaLearner`](crate::SchemaLearner) of schema `schema` cannot
+    /// promote or freeze in its current state, as `reason` says
+    LearnerState { schema: String, reason: String },
+    /// The value at `path` does not match the schema or union it is bound
+    /// to, as `reason` says
+    SchemaViolation { path: String, reason: String },
+    /// Keys `first` and `second` would both become `normalized` under
+    /// [`TeaLeaf::normalize_keys`](crate::TeaLeaf::normalize_keys); `place`
+    /// names the object, schema or variant holding them, empty for
+    /// top-level keys
+    KeyCollision { first: String, second: String, normalized: String, place: String },
+}
+
+/// A value the binary writer cannot encode, as [`Error::Unencodable`] and
+/// [`CompileReport`](crate::CompileReport) list it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unencodable {
+    /// Where the value is, in [`Value::get_path`] syntax; map entries are
+    /// addressed by position
+    pub path: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Unencodable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.reason)
+    }
 }
 
 impl fmt::Display for Error {
@@ -64,6 +152,43 @@ impl fmt::Display for Error {
             Error::MissingField(s) => write!(f, "Missing field: {}", s),
             Error::ParseError(s) => write!(f, "Parse error: {}", s),
             Error::ValueOutOfRange(s) => write!(f, "Value out of range: {}", s),
+            Error::LimitExceeded(s) => write!(f, "Limit exceeded: {}", s),
+            Error::MissingCodec { section, codec } => {
+                write!(f, "Section '{}' needs codec {:#x}, which is not registered", section, codec)
+            }
+            Error::MissingKey { field, codec, key_id } => {
+                write!(f, "Field '{}' needs key '{}' of codec {:#x}, which is not registered", field, key_id, codec)
+            }
+            Error::Cancelled => write!(f, "Operation cancelled"),
+            Error::DuplicateKey { key, line, col } => {
+                write!(f, "Duplicate key '{}' at line {}, column {}", key, line, col)
+            }
+            Error::TooManyValues { schema, fields, line, col } => {
+                write!(f, "Too many values for '{}' ({} fields) at line {}, column {}", schema, fields, line, col)
+            }
+            Error::InvalidFieldName { definition, field, reason, line, col } => {
+                write!(f, "Invalid field name '{}' in '{}' at line {}, column {}: {}", field, definition, line, col, reason)
+            }
+            Error::DepthExceeded { limit } => write!(f, "Nesting depth exceeds the limit of {}", limit),
+            Error::UnsupportedEncoding(encoding) => {
+                write!(f, "Text is {}, not UTF-8; convert it or load it with TeaLeaf::load_transcoded", encoding.name())
+            }
+            Error::Unencodable { section, values } => {
+                write!(f, "Section '{}' has {} unencodable value(s)", section, values.len())?;
+                for (i, value) in values.iter().enumerate() {
+                    write!(f, "{} {}", if i == 0 { ":" } else { ";" }, value)?;
+                }
+                Ok(())
+            }
+            Error::LearnerState { schema, reason } => write!(f, "Schema learner '{}' {}", schema, reason),
+            Error::SchemaViolation { path, reason } => write!(f, "Schema violation at '{}': {}", path, reason),
+            Error::KeyCollision { first, second, normalized, place } => {
+                write!(f, "Keys '{}' and '{}'", first, second)?;
+                if !place.is_empty() {
+                    write!(f, " in {}", place)?;
+                }
+                write!(f, " both normalize to '{}'", normalized)
+            }
         }
     }
 }
@@ -108,6 +233,7 @@ pub enum TLType {
     Ref = 0x30,
     Tagged = 0x31,
     Timestamp = 0x32,
+    TimestampNanos = 0x33,
 }
 
 impl TryFrom<u8> for TLType {
@@ -138,6 +264,7 @@ impl TryFrom<u8> for TLType {
             0x30 => Ok(Self::Ref),
             0x31 => Ok(Self::Tagged),
             0x32 => Ok(Self::Timestamp),
+            0x33 => Ok(Self::TimestampNanos),
             _ => Err(Error::InvalidType(v)),
         }
     }
@@ -206,7 +333,7 @@ impl FieldType {
             "int8" => TLType::Int8,
             "int16" => TLType::Int16,
             "int" | "int32" => TLType::Int32,
-            "int64" => TLType::Int64,
+            "int64" | "bytesize" | "duration" => TLType::Int64,
             "uint8" => TLType::UInt8,
             "uint16" => TLType::UInt16,
             "uint" | "uint32" => TLType::UInt32,
@@ -216,6 +343,7 @@ impl FieldType {
             "string" => TLType::String,
             "bytes" => TLType::Bytes,
             "timestamp" => TLType::Timestamp,
+            "timestamp_ns" => TLType::TimestampNanos,
             "object" => TLType::Object,
             "tuple" => TLType::Tuple,
             "map" => TLType::Map,
@@ -245,7 +373,7 @@ impl fmt::Display for FieldType {
 // Schema
 // =============================================================================
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Field {
     pub name: String,
     pub field_type: FieldType,
@@ -260,7 +388,7 @@ impl Field {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Schema {
     pub name: String,
     pub fields: Vec<Field>,
@@ -297,7 +425,7 @@ impl Schema {
 // =============================================================================
 
 /// A variant in a union type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Variant {
     pub name: String,
     pub fields: Vec<Field>,
@@ -318,7 +446,7 @@ impl Variant {
 }
 
 /// A discriminated union type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Union {
     pub name: String,
     pub variants: Vec<Variant>,
@@ -344,14 +472,84 @@ impl Union {
     pub fn get_variant(&self, name: &str) -> Option<&Variant> {
         self.variants.iter().find(|v| v.name == name)
     }
+
+    /// Ordinal of the named variant: its position in declaration order.
+    ///
+    /// Ordinals are stable across text and binary round-trips, since the
+    /// binary union table preserves variant order.
+    pub fn variant_index(&self, name: &str) -> Option<usize> {
+        self.variants.iter().position(|v| v.name == name)
+    }
+
+    /// The variant with the given ordinal.
+    pub fn variant_at(&self, ordinal: usize) -> Option<&Variant> {
+        self.variants.get(ordinal)
+    }
+}
+
+// =============================================================================
+// Timestamp precision
+// =============================================================================
+
+/// Number of fractional-second digits a [`Value::TimestampNanos`] carries.
+///
+/// Text and JSON output write exactly this many digits, so the precision
+/// survives a round trip; the text parser picks the precision from the digits
+/// it reads (4-6 digits are micros, 7-9 nanos).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimePrecision {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl TimePrecision {
+    /// Fractional-second digits: 0, 3, 6 or 9.
+    pub fn fraction_digits(self) -> u32 {
+        match self {
+            TimePrecision::Seconds => 0,
+            TimePrecision::Millis => 3,
+            TimePrecision::Micros => 6,
+            TimePrecision::Nanos => 9,
+        }
+    }
+
+    /// The narrowest precision that holds `digits` fractional digits.
+    pub fn from_fraction_digits(digits: usize) -> Self {
+        match digits {
+            0 => TimePrecision::Seconds,
+            1..=3 => TimePrecision::Millis,
+            4..=6 => TimePrecision::Micros,
+            _ => TimePrecision::Nanos,
+        }
+    }
+
+    /// Binary encoding of the precision (its fraction digit count).
+    #[cfg(feature = "binary")]
+    pub(crate) fn code(self) -> u8 {
+        self.fraction_digits() as u8
+    }
+
+    #[cfg(feature = "binary")]
+    pub(crate) fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(TimePrecision::Seconds),
+            3 => Some(TimePrecision::Millis),
+            6 => Some(TimePrecision::Micros),
+            9 => Some(TimePrecision::Nanos),
+            _ => None,
+        }
+    }
 }
 
 // =============================================================================
 // Value
 // =============================================================================
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum Value {
+    #[default]
     Null,
     Bool(bool),
     Int(i64),
@@ -366,6 +564,74 @@ pub enum Value {
     Tagged(String, Box<Value>),
     Timestamp(i64, i16),  // Unix milliseconds, timezone offset in minutes
     JsonNumber(String),  // Arbitrary-precision number (raw decimal string)
+    TimestampNanos(i64, i16, TimePrecision),  // Unix nanoseconds, timezone offset in minutes, precision
+    /// Masked when printed and, unless asked otherwise, in text and JSON
+    /// output; see [`crate::secret`]
+    Secret(crate::Secret),
+}
+
+/// Short name of a value's kind, for warnings and reports.
+pub(crate) fn value_kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Int(_) => "int",
+        Value::UInt(_) => "uint",
+        Value::Float(_) => "float",
+        Value::String(_) => "string",
+        Value::Bytes(_) => "bytes",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+        Value::Map(_) => "map",
+        Value::Ref(_) => "ref",
+        Value::Tagged(..) => "tagged",
+        Value::Timestamp(..) => "timestamp",
+        Value::JsonNumber(_) => "number",
+        Value::TimestampNanos(..) => "timestamp_ns",
+        Value::Secret(_) => "secret",
+    }
+}
+
+/// See [`Value::float_to_canonical_string`]. Non-finite values are written
+/// with the `NaN`, `inf` and `-inf` keywords the lexer recognizes.
+pub(crate) fn canonical_float(f: f64) -> String {
+    if f.is_nan() {
+        return "NaN".to_string();
+    }
+    if f.is_infinite() {
+        return if f.is_sign_positive() { "inf".to_string() } else { "-inf".to_string() };
+    }
+
+    // `{:e}` gives the shortest round-trip digits, `d.ddde<exp>`; lay them out
+    // the way ryu does, plain between 1e-5 and 1e16 and in exponent form outside
+    let sci = format!("{:e}", f.abs());
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let digits = mantissa.replace('.', "");
+    let point = exp.parse::<i32>().unwrap_or(0) + 1;
+    let len = digits.len() as i32;
+
+    let mut out = String::with_capacity(digits.len() + 8);
+    if f.is_sign_negative() {
+        out.push('-');
+    }
+    if len <= point && point <= 16 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (point - len) as usize));
+        out.push_str(".0");
+    } else if 0 < point && point <= 16 {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    } else if -5 < point && point <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', (-point) as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(mantissa);
+        out.push('e');
+        out.push_str(&(point - 1).to_string());
+    }
+    out
 }
 
 impl Value {
@@ -424,6 +690,21 @@ impl Value {
         }
     }
 
+    /// The text that TeaLeaf and JSON output write for a float: the shortest
+    /// decimal that parses back to exactly the same `f64`, with a `.0` on
+    /// whole numbers and in exponent form below `1e-5` or from `1e16` up.
+    ///
+    /// JSON output writes the same digits, though serde_json spells a positive
+    /// exponent `e+`. Formatting and parsing use Rust's own float routines,
+    /// which do not depend on the process locale. Returns `None` for
+    /// non-float values.
+    pub fn float_to_canonical_string(&self) -> Option<String> {
+        match self {
+            Value::Float(f) => Some(canonical_float(*f)),
+            _ => None,
+        }
+    }
+
     pub fn as_str(&self) -> Option<&str> {
         match self {
             Value::String(s) => Some(s),
@@ -523,6 +804,32 @@ impl Value {
         Some(current)
     }
 
+    /// Release spare capacity of this value's strings, arrays, objects and
+    /// maps, recursively.
+    pub fn shrink_to_fit(&mut self) {
+        match self {
+            Value::String(s) | Value::Ref(s) | Value::JsonNumber(s) => s.shrink_to_fit(),
+            Value::Bytes(b) => b.shrink_to_fit(),
+            Value::Array(items) => {
+                items.iter_mut().for_each(Value::shrink_to_fit);
+                items.shrink_to_fit();
+            }
+            Value::Object(obj) => {
+                obj.values_mut().for_each(Value::shrink_to_fit);
+                obj.shrink_to_fit();
+            }
+            Value::Map(pairs) => {
+                for (k, v) in pairs.iter_mut() {
+                    k.shrink_to_fit();
+                    v.shrink_to_fit();
+                }
+                pairs.shrink_to_fit();
+            }
+            Value::Tagged(_, inner) => inner.shrink_to_fit(),
+            _ => {}
+        }
+    }
+
     pub fn tl_type(&self) -> TLType {
         match self {
             Value::Null => TLType::Null,
@@ -559,19 +866,33 @@ impl Value {
             Value::Tagged(_, _) => TLType::Tagged,
             Value::Timestamp(_, _) => TLType::Timestamp,
             Value::JsonNumber(_) => TLType::JsonNumber,
+            Value::TimestampNanos(_, _, _) => TLType::TimestampNanos,
+            Value::Secret(s) if s.expose_str().is_none() => TLType::Bytes,
+            Value::Secret(_) => TLType::String,
         }
     }
 
+    /// Unix milliseconds and timezone offset; sub-millisecond digits of a
+    /// [`Value::TimestampNanos`] are truncated.
     pub fn as_timestamp(&self) -> Option<(i64, i16)> {
         match self {
             Value::Timestamp(ts, tz) => Some((*ts, *tz)),
+            Value::TimestampNanos(ns, tz, _) => Some((ns.div_euclid(1_000_000), *tz)),
             _ => None,
         }
     }
 
     pub fn as_timestamp_millis(&self) -> Option<i64> {
+        self.as_timestamp().map(|(ts, _)| ts)
+    }
+
+    /// Unix nanoseconds, timezone offset and precision. A millisecond
+    /// [`Value::Timestamp`] outside the nanosecond range (years 1677-2262)
+    /// returns `None`.
+    pub fn as_timestamp_nanos(&self) -> Option<(i64, i16, TimePrecision)> {
         match self {
-            Value::Timestamp(ts, _) => Some(*ts),
+            Value::Timestamp(ts, tz) => ts.checked_mul(1_000_000).map(|ns| (ns, *tz, TimePrecision::Millis)),
+            Value::TimestampNanos(ns, tz, precision) => Some((*ns, *tz, *precision)),
             _ => None,
         }
     }
@@ -597,6 +918,14 @@ impl Value {
         }
     }
 
+    /// Match a tagged value against `union`, returning the variant named by
+    /// the tag and the payload. Returns `None` for untagged values and for
+    /// tags that are not variants of `union`.
+    pub fn as_union_variant<'u>(&self, union: &'u Union) -> Option<(&'u Variant, &Value)> {
+        let (tag, payload) = self.as_tagged()?;
+        Some((union.get_variant(tag)?, payload))
+    }
+
     pub fn as_json_number(&self) -> Option<&str> {
         match self {
             Value::JsonNumber(s) => Some(s),
@@ -605,10 +934,16 @@ impl Value {
     }
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Value::Null
-    }
+/// Split a document path into its top-level key and the remainder that
+/// [`Value::get_path`] resolves below it (empty for a bare key).
+pub(crate) fn split_path_root(path: &str) -> (&str, &str) {
+    let bytes = path.as_bytes();
+    let seg_end = bytes
+        .iter()
+        .position(|&b| b == b'.' || b == b'[')
+        .unwrap_or(bytes.len());
+    let rest = &path[seg_end..];
+    (&path[..seg_end], rest.strip_prefix('.').unwrap_or(rest))
 }
 
 // Conversions
@@ -670,6 +1005,39 @@ impl From<HashMap<String, Value>> for Value {
 mod tests {
     use super::*;
 
+    // -------------------------------------------------------------------------
+    // Union variant lookup
+    // -------------------------------------------------------------------------
+
+    fn shape_union() -> Union {
+        Union::new("shape")
+            .variant(Variant::new("circle").field("r", FieldType::new("float")))
+            .variant(Variant::new("dot"))
+    }
+
+    #[test]
+    fn test_union_variant_index_and_at() {
+        let shape = shape_union();
+        assert_eq!(shape.variant_index("circle"), Some(0));
+        assert_eq!(shape.variant_index("dot"), Some(1));
+        assert_eq!(shape.variant_index("square"), None);
+        assert_eq!(shape.variant_at(1).map(|v| v.name.as_str()), Some("dot"));
+        assert!(shape.variant_at(2).is_none());
+    }
+
+    #[test]
+    fn test_value_as_union_variant() {
+        let shape = shape_union();
+        let circle = Value::Tagged("circle".into(), Box::new(Value::Float(1.5)));
+        let (variant, payload) = circle.as_union_variant(&shape).unwrap();
+        assert_eq!(variant.name, "circle");
+        assert_eq!(payload, &Value::Float(1.5));
+
+        let square = Value::Tagged("square".into(), Box::new(Value::Null));
+        assert!(square.as_union_variant(&shape).is_none());
+        assert!(Value::Int(1).as_union_variant(&shape).is_none());
+    }
+
     // -------------------------------------------------------------------------
     // TLType::try_from
     // -------------------------------------------------------------------------
@@ -699,6 +1067,7 @@ mod tests {
             (0x30, TLType::Ref),
             (0x31, TLType::Tagged),
             (0x32, TLType::Timestamp),
+            (0x33, TLType::TimestampNanos),
             (0x12, TLType::JsonNumber),
         ];
         for (byte, expected) in cases {
@@ -810,6 +1179,7 @@ mod tests {
             ("string", TLType::String),
             ("bytes", TLType::Bytes),
             ("timestamp", TLType::Timestamp),
+            ("timestamp_ns", TLType::TimestampNanos),
             ("object", TLType::Object),
             ("tuple", TLType::Tuple),
             ("map", TLType::Map),
@@ -1210,7 +1580,7 @@ mod tests {
 
     #[test]
     fn test_value_from_f64() {
-        assert_eq!(Value::from(3.14f64), Value::Float(3.14));
+        assert_eq!(Value::from(2.5f64), Value::Float(2.5));
     }
 
     #[test]
@@ -1294,6 +1664,18 @@ mod tests {
             Error::ValueOutOfRange("too big".into()).to_string(),
             "Value out of range: too big"
         );
+        assert_eq!(
+            Error::LimitExceeded("too deep".into()).to_string(),
+            "Limit exceeded: too deep"
+        );
+        assert_eq!(
+            Error::LearnerState { schema: "order".into(), reason: "is frozen".into() }.to_string(),
+            "Schema learner 'order' is frozen"
+        );
+        assert_eq!(
+            Error::SchemaViolation { path: "users[0].id".into(), reason: "expected int, got string".into() }.to_string(),
+            "Schema violation at 'users[0].id': expected int, got string"
+        );
     }
 
     #[test]
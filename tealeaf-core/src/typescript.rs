@@ -0,0 +1,191 @@
+//! TypeScript type definitions for the JSON export of schema-typed data.
+//!
+//! [`generate_typescript`] writes one `interface` per struct and one
+//! discriminated union type per union, describing values as
+//! [`TeaLeaf::to_json`](crate::TeaLeaf::to_json) writes them:
+//!
+//! - integers and floats are `number`; strings, bytes (`"0x..."`) and
+//!   timestamps (ISO 8601) are `string`;
+//! - nullable fields are optional and may be `null`, since absent fields are
+//!   left out of objects;
+//! - union values are `{$tag, $value}` objects. A variant's `$value` is its
+//!   fields as an object, or as a tuple in field order as the text format
+//!   writes them; a variant without fields has `null` or `[]`;
+//! - fields of any other type are `unknown`.
+//!
+//! References export as `{"$ref": name}`, described by the `TeaLeafRef`
+//! interface; schema-typed fields are not expected to hold them, so resolve
+//! references (see [`Pass::ResolveRefs`](crate::pipeline::Pass::ResolveRefs))
+//! before exporting data checked against these types.
+
+use crate::{Field, FieldType, IndexMap, Schema, Union};
+
+/// TypeScript declarations for `schemas` and `unions`, unions first, each
+/// named after its definition.
+pub fn generate_typescript(schemas: &IndexMap<String, Schema>, unions: &IndexMap<String, Union>) -> String {
+    let names = Names { schemas, unions };
+    let mut out = String::from("// Generated from TeaLeaf schemas.\n\nexport interface TeaLeafRef {\n  $ref: string;\n}\n");
+
+    for union in unions.values() {
+        out.push_str(&format!("\nexport type {} =\n", identifier(&union.name)));
+        if union.variants.is_empty() {
+            out.push_str("  never");
+        }
+        for (i, variant) in union.variants.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            let value = if variant.fields.is_empty() {
+                "null | []".to_string()
+            } else {
+                let object: Vec<String> = variant.fields.iter().map(|f| names.member(f)).collect();
+                let tuple: Vec<String> = variant.fields.iter()
+                    .map(|f| format!("{}: {}", identifier(&f.name), names.field_type(&f.field_type)))
+                    .collect();
+                format!("{{ {} }} | [{}]", object.join("; "), tuple.join(", "))
+            };
+            out.push_str(&format!("  | {{ $tag: {}; $value: {} }}", string_literal(&variant.name), value));
+        }
+        out.push_str(";\n");
+    }
+
+    for schema in schemas.values() {
+        out.push_str(&format!("\nexport interface {} {{\n", identifier(&schema.name)));
+        for field in &schema.fields {
+            out.push_str(&format!("  {};\n", names.member(field)));
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+struct Names<'a> {
+    schemas: &'a IndexMap<String, Schema>,
+    unions: &'a IndexMap<String, Union>,
+}
+
+impl Names<'_> {
+    /// An object member for `field`: optional and nullable if the field is.
+    fn member(&self, field: &Field) -> String {
+        let ty = self.field_type(&field.field_type);
+        if field.field_type.nullable {
+            format!("{}?: {}", property(&field.name), ty)
+        } else {
+            format!("{}: {}", property(&field.name), ty)
+        }
+    }
+
+    fn field_type(&self, field_type: &FieldType) -> String {
+        let base = self.base_type(&field_type.base);
+        let ty = if field_type.is_array { format!("{}[]", base) } else { base };
+        if field_type.nullable {
+            format!("{} | null", ty)
+        } else {
+            ty
+        }
+    }
+
+    fn base_type(&self, base: &str) -> String {
+        match base {
+            "bool" => "boolean".to_string(),
+            "int" | "int8" | "int16" | "int32" | "int64" | "uint" | "uint8" | "uint16" | "uint32" | "uint64"
+            | "float" | "float32" | "float64" | "bytesize" | "duration" => "number".to_string(),
+            "string" | "bytes" | "timestamp" | "timestamp_ns" | "secret" => "string".to_string(),
+            "object" => "Record<string, unknown>".to_string(),
+            "tuple" => "unknown[]".to_string(),
+            "map" => "[unknown, unknown][]".to_string(),
+            name if self.schemas.contains_key(name) || self.unions.contains_key(name) => identifier(name),
+            _ => "unknown".to_string(),
+        }
+    }
+}
+
+/// `name` as a TypeScript identifier, with other characters replaced by `_`.
+fn identifier(name: &str) -> String {
+    let mut id: String = name.chars().map(|c| if c.is_alphanumeric() || c == '_' || c == '$' { c } else { '_' }).collect();
+    if id.is_empty() || id.starts_with(|c: char| c.is_ascii_digit()) {
+        id.insert(0, '_');
+    }
+    id
+}
+
+/// `name` as an object property name, quoted unless it is an identifier.
+fn property(name: &str) -> String {
+    if identifier(name) == name {
+        name.to_string()
+    } else {
+        string_literal(name)
+    }
+}
+
+fn string_literal(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(all(test, feature = "text"))]
+mod tests {
+    use super::*;
+    use crate::TeaLeaf;
+
+    #[test]
+    fn test_generate_typescript() {
+        let doc = TeaLeaf::parse(r#"
+            @union shape {
+              circle (radius: float, label: string?),
+              point (),
+            }
+            @struct point (x: int, "y pos": int?)
+            @struct drawing (name: string, at: []point, shape: shape, alt: shape?, tags: []string?, when: timestamp, data: bytes, extra: any, on: bool)
+        "#).unwrap();
+        let ts = generate_typescript(&doc.schemas, &doc.unions);
+        let expected = r#"// Generated from TeaLeaf schemas.
+
+export interface TeaLeafRef {
+  $ref: string;
+}
+
+export type shape =
+  | { $tag: "circle"; $value: { radius: number; label?: string | null } | [radius: number, label: string | null] }
+  | { $tag: "point"; $value: null | [] };
+
+export interface point {
+  x: number;
+  "y pos"?: number | null;
+}
+
+export interface drawing {
+  name: string;
+  at: point[];
+  shape: shape;
+  alt?: shape | null;
+  tags?: string[] | null;
+  when: string;
+  data: string;
+  extra: unknown;
+  on: boolean;
+}
+"#;
+        assert_eq!(ts, expected);
+    }
+
+    #[test]
+    fn test_names_are_sanitized() {
+        let schema = Schema::new("my-type").field("1st", FieldType::new("int")).field("ok", FieldType::new("string"));
+        let schemas: IndexMap<String, Schema> = [(schema.name.clone(), schema)].into_iter().collect();
+        let ts = generate_typescript(&schemas, &IndexMap::new());
+        assert!(ts.contains("export interface my_type {\n  \"1st\": number;\n  ok: string;\n}"), "{}", ts);
+        assert_eq!(string_literal("a\"b\\"), r#""a\"b\\""#);
+    }
+}
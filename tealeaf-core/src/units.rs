@@ -0,0 +1,127 @@
+//! Size and duration literals such as `10KB`, `4MiB` and `250ms`.
+//!
+//! The lexer reads them as plain integers, counting bytes and milliseconds.
+//! Fields declared `bytesize` or `duration` are written back with the
+//! largest unit that divides the value exactly.
+
+/// What a suffixed literal measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Quantity {
+    /// Bytes
+    Size,
+    /// Milliseconds
+    Duration,
+}
+
+/// Size units by increasing factor; `kB` is accepted but never written.
+const SIZE_UNITS: &[(&str, i64)] = &[
+    ("B", 1),
+    ("KB", 1_000),
+    ("kB", 1_000),
+    ("KiB", 1 << 10),
+    ("MB", 1_000_000),
+    ("MiB", 1 << 20),
+    ("GB", 1_000_000_000),
+    ("GiB", 1 << 30),
+    ("TB", 1_000_000_000_000),
+    ("TiB", 1 << 40),
+    ("PB", 1_000_000_000_000_000),
+    ("PiB", 1 << 50),
+];
+
+/// Duration units by increasing factor.
+const DURATION_UNITS: &[(&str, i64)] = &[
+    ("ms", 1),
+    ("s", 1_000),
+    ("m", 60_000),
+    ("h", 3_600_000),
+    ("d", 86_400_000),
+];
+
+impl Quantity {
+    /// The quantity a field type measures, for `bytesize` and `duration`.
+    pub(crate) fn for_field(base: &str) -> Option<Self> {
+        match base {
+            "bytesize" => Some(Quantity::Size),
+            "duration" => Some(Quantity::Duration),
+            _ => None,
+        }
+    }
+
+    /// The quantity `suffix` measures and how many bytes or milliseconds
+    /// one of it is.
+    pub(crate) fn of_unit(suffix: &str) -> Option<(Self, i64)> {
+        let find = |units: &[(&str, i64)]| units.iter().find(|(u, _)| *u == suffix).map(|(_, f)| *f);
+        find(SIZE_UNITS).map(|f| (Quantity::Size, f))
+            .or_else(|| find(DURATION_UNITS).map(|f| (Quantity::Duration, f)))
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Quantity::Size => "size",
+            Quantity::Duration => "duration",
+        }
+    }
+
+    /// `n` followed by the largest unit that divides it exactly, e.g.
+    /// `4MiB` for 4194304 bytes or `90s` for 90000 milliseconds.
+    pub(crate) fn format(self, n: i128) -> String {
+        let units = match self {
+            Quantity::Size => SIZE_UNITS,
+            Quantity::Duration => DURATION_UNITS,
+        };
+        let (unit, factor) = units.iter().rev()
+            .filter(|(unit, _)| *unit != "kB")
+            .find(|(_, factor)| n != 0 && n % *factor as i128 == 0)
+            .unwrap_or(&units[0]);
+        format!("{}{}", n / *factor as i128, unit)
+    }
+}
+
+/// `number` (a decimal such as `10`, `-2` or `1.5`) times `factor`, if the
+/// result is a whole number that fits an `i64`.
+pub(crate) fn scale(number: &str, factor: i64) -> Option<i64> {
+    let (whole, frac) = number.split_once('.').unwrap_or((number, ""));
+    if whole.trim_start_matches('-').len() + frac.len() > 30 {
+        return None;
+    }
+    let mantissa: i128 = format!("{}{}", whole, frac).parse().ok()?;
+    let divisor = 10i128.pow(frac.len() as u32);
+    let product = mantissa * factor as i128;
+    if product % divisor != 0 {
+        return None;
+    }
+    i64::try_from(product / divisor).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale() {
+        assert_eq!(scale("10", 1_000), Some(10_000));
+        assert_eq!(scale("1.5", 1 << 10), Some(1536));
+        assert_eq!(scale("-2", 1_000), Some(-2_000));
+        assert_eq!(scale("0.25", 1_000), Some(250));
+        // Fractions of the base unit and overflow are rejected
+        assert_eq!(scale("0.5", 1), None);
+        assert_eq!(scale("9000", 1 << 50), None);
+    }
+
+    #[test]
+    fn test_units_and_format() {
+        assert_eq!(Quantity::of_unit("MiB"), Some((Quantity::Size, 1 << 20)));
+        assert_eq!(Quantity::of_unit("kB"), Some((Quantity::Size, 1_000)));
+        assert_eq!(Quantity::of_unit("h"), Some((Quantity::Duration, 3_600_000)));
+        assert_eq!(Quantity::of_unit("mb"), None);
+
+        assert_eq!(Quantity::Size.format(4 << 20), "4MiB");
+        assert_eq!(Quantity::Size.format(10_000), "10KB");
+        assert_eq!(Quantity::Size.format(1_536), "1536B");
+        assert_eq!(Quantity::Size.format(0), "0B");
+        assert_eq!(Quantity::Duration.format(90_000), "90s");
+        assert_eq!(Quantity::Duration.format(-7_200_000), "-2h");
+        assert_eq!(Quantity::Duration.format(250), "250ms");
+    }
+}
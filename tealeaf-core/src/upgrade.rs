@@ -0,0 +1,510 @@
+//! Opt-in conversion of strings that look like other types.
+//!
+//! JSON import keeps strings as strings (see
+//! [`TeaLeaf::from_json`](crate::TeaLeaf::from_json)). [`TeaLeaf::upgrade_types`]
+//! is the deliberate step that turns them into typed values: ISO 8601
+//! strings into timestamps, `0x` hex into bytes, and numeric strings into
+//! numbers, but only at the paths an [`UpgradePolicy`] names. Every
+//! conversion is listed in the returned [`UpgradeReport`], and
+//! [`TeaLeaf::upgrade_types_dry_run`] produces the same report without
+//! changing anything.
+
+use std::fmt;
+
+use crate::{format_timestamp_millis, format_timestamp_nanos, TeaLeaf, Value};
+
+/// A kind of string [`TeaLeaf::upgrade_types`] can convert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conversion {
+    /// ISO 8601 timestamps and dates, as the text format writes them, to
+    /// [`Value::Timestamp`] or [`Value::TimestampNanos`]. The timestamp must
+    /// be the whole string, without surrounding spaces or comments.
+    Timestamp,
+    /// `0x` followed by an even number of hex digits to [`Value::Bytes`].
+    Bytes,
+    /// JSON-style numbers to [`Value::Int`], [`Value::UInt`] or
+    /// [`Value::Float`]. Strings with leading zeros (`"007"`), a leading `+`,
+    /// or a value out of range are kept, since converting them would lose
+    /// what they say. A decimal with more precision than an `f64` holds
+    /// becomes a [`Value::JsonNumber`], which keeps its digits.
+    Number,
+}
+
+impl Conversion {
+    /// The name of the conversion in reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            Conversion::Timestamp => "timestamp",
+            Conversion::Bytes => "bytes",
+            Conversion::Number => "number",
+        }
+    }
+
+    /// `s` converted, if it has this form.
+    pub fn convert(self, s: &str) -> Option<Value> {
+        match self {
+            Conversion::Timestamp => {
+                // A literal with a comment or anything after a space would
+                // parse as its first token alone
+                if !s.starts_with(|c: char| c.is_ascii_digit()) || s.contains(|c: char| c.is_whitespace() || c == '#') {
+                    return None;
+                }
+                match Value::parse_literal(s) {
+                    Ok(v @ (Value::Timestamp(..) | Value::TimestampNanos(..))) => Some(v),
+                    _ => None,
+                }
+            }
+            Conversion::Bytes => {
+                let hex = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))?;
+                if hex.is_empty() || hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return None;
+                }
+                let bytes = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok());
+                bytes.collect::<Option<Vec<u8>>>().map(Value::Bytes)
+            }
+            Conversion::Number => {
+                if !is_json_number(s) {
+                    return None;
+                }
+                if !s.contains(['.', 'e', 'E']) {
+                    if let Ok(i) = s.parse::<i64>() {
+                        return Some(Value::Int(i));
+                    }
+                    return s.parse::<u64>().ok().map(Value::UInt);
+                }
+                let f = s.parse::<f64>().ok().filter(|f| f.is_finite())?;
+                if decimal(s) == decimal(&format!("{:e}", f)) {
+                    Some(Value::Float(f))
+                } else {
+                    Some(Value::JsonNumber(s.to_string()))
+                }
+            }
+        }
+    }
+}
+
+/// A decimal number in a form that compares equal for equal values: its
+/// sign, its significant digits, and the power of ten of the point before
+/// them. `None` if the exponent does not fit.
+fn decimal(s: &str) -> Option<(bool, String, i64)> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (mantissa, exp) = match s.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => (mantissa, exp.parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int, frac);
+    let significant = digits.trim_start_matches('0');
+    let point = exp.checked_add(int.len() as i64)?.checked_sub((digits.len() - significant.len()) as i64)?;
+    let significant = significant.trim_end_matches('0');
+    if significant.is_empty() {
+        return Some((negative, String::new(), 0));
+    }
+    Some((negative, significant.to_string(), point))
+}
+
+/// Whether `s` is a number in JSON's grammar: an optional `-`, an integer
+/// part without leading zeros, then an optional fraction and exponent.
+fn is_json_number(s: &str) -> bool {
+    let b = s.as_bytes();
+    let mut i = usize::from(b.first() == Some(&b'-'));
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while *i < b.len() && b[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i - start
+    };
+    let int_start = i;
+    let int_len = digits(&mut i);
+    if int_len == 0 || (int_len > 1 && b[int_start] == b'0') {
+        return false;
+    }
+    if b.get(i) == Some(&b'.') {
+        i += 1;
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    if matches!(b.get(i), Some(b'e' | b'E')) {
+        i += 1;
+        if matches!(b.get(i), Some(b'+' | b'-')) {
+            i += 1;
+        }
+        if digits(&mut i) == 0 {
+            return false;
+        }
+    }
+    i == b.len()
+}
+
+/// One rule of an [`UpgradePolicy`]: the conversions to try on strings at
+/// paths matching `pattern`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpgradeRule {
+    /// A path in [`TeaLeaf::get_path`] syntax, where `*` matches any one key
+    /// or index, `[*]` any one index, and `**` any number of keys and
+    /// indices, including none.
+    pub pattern: String,
+    /// Conversions tried in order; the first that fits is used. Empty to
+    /// keep the matching strings as they are.
+    pub conversions: Vec<Conversion>,
+}
+
+/// Which strings [`TeaLeaf::upgrade_types`] converts, and to what.
+///
+/// Rules are tried in the order they were added and the first whose
+/// pattern matches a string's path decides its conversions, so exceptions
+/// go before general rules. Strings no rule matches are left alone.
+///
+/// ```
+/// use tealeaf::{Conversion, UpgradePolicy};
+///
+/// let policy = UpgradePolicy::new()
+///     .rule("orders[*].zip", &[])
+///     .rule("**.created_at", &[Conversion::Timestamp])
+///     .rule("orders[*].*", &[Conversion::Number, Conversion::Bytes]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpgradePolicy {
+    pub rules: Vec<UpgradeRule>,
+}
+
+impl UpgradePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A policy converting every string in the document with the first of
+    /// `conversions` that fits.
+    pub fn everywhere(conversions: &[Conversion]) -> Self {
+        Self::new().rule("**", conversions)
+    }
+
+    /// Add a rule after the existing ones.
+    pub fn rule(mut self, pattern: &str, conversions: &[Conversion]) -> Self {
+        self.rules.push(UpgradeRule { pattern: pattern.to_string(), conversions: conversions.to_vec() });
+        self
+    }
+
+    /// The conversions for the string at `path`.
+    fn conversions(&self, path: &[Segment]) -> &[Conversion] {
+        self.rules.iter()
+            .find(|rule| matches(&pattern_segments(&rule.pattern), path))
+            .map_or(&[], |rule| &rule.conversions)
+    }
+}
+
+/// One string converted by [`TeaLeaf::upgrade_types`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Upgrade {
+    /// Path of the value, in `get_path` syntax.
+    pub path: String,
+    pub conversion: Conversion,
+    /// The string before conversion.
+    pub from: String,
+    pub to: Value,
+}
+
+/// Every conversion made, or that would be made, by
+/// [`TeaLeaf::upgrade_types`], in document order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UpgradeReport {
+    pub upgrades: Vec<Upgrade>,
+}
+
+impl UpgradeReport {
+    /// Number of strings converted with `conversion`.
+    pub fn count(&self, conversion: Conversion) -> usize {
+        self.upgrades.iter().filter(|u| u.conversion == conversion).count()
+    }
+}
+
+impl fmt::Display for UpgradeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for upgrade in &self.upgrades {
+            writeln!(f, "{}: {:?} -> {} ({})", upgrade.path, upgrade.from, display(&upgrade.to), upgrade.conversion.name())?;
+        }
+        Ok(())
+    }
+}
+
+/// A converted value as JSON export writes it.
+fn display(value: &Value) -> String {
+    match value {
+        Value::Bytes(bytes) => bytes.iter().fold(String::from("0x"), |mut out, b| {
+            out.push_str(&format!("{:02x}", b));
+            out
+        }),
+        Value::Timestamp(ts, tz) => format_timestamp_millis(*ts, *tz),
+        Value::TimestampNanos(ns, tz, precision) => format_timestamp_nanos(*ns, *tz, *precision),
+        Value::Int(i) => i.to_string(),
+        Value::UInt(u) => u.to_string(),
+        Value::Float(f) => f.to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+impl TeaLeaf {
+    /// Convert strings to the types they look like, as `policy` allows,
+    /// and report each conversion.
+    ///
+    /// Values of every kind of container are visited, including `!`
+    /// definitions, map values and tagged values; map keys are not. Schema
+    /// field types are not changed, so upgrade before inferring schemas, or
+    /// change the types of affected fields with
+    /// [`alter_table`](Self::alter_table).
+    pub fn upgrade_types(&mut self, policy: &UpgradePolicy) -> UpgradeReport {
+        let found = self.find_upgrades(policy);
+        for (segments, upgrade) in &found {
+            if let Some(value) = self.data.get_mut(key_of(segments)).and_then(|v| value_at(v, &segments[1..])) {
+                *value = upgrade.to.clone();
+            }
+        }
+        UpgradeReport { upgrades: found.into_iter().map(|(_, upgrade)| upgrade).collect() }
+    }
+
+    /// The report [`upgrade_types`](Self::upgrade_types) would return,
+    /// without converting anything.
+    pub fn upgrade_types_dry_run(&self, policy: &UpgradePolicy) -> UpgradeReport {
+        UpgradeReport { upgrades: self.find_upgrades(policy).into_iter().map(|(_, upgrade)| upgrade).collect() }
+    }
+
+    fn find_upgrades(&self, policy: &UpgradePolicy) -> Vec<(Vec<Segment>, Upgrade)> {
+        let mut found = Vec::new();
+        let mut path = Vec::new();
+        for (key, value) in &self.data {
+            path.push(Segment::Key(key.clone()));
+            find(value, &mut path, policy, &mut found);
+            path.pop();
+        }
+        found
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn key_of(segments: &[Segment]) -> &str {
+    match &segments[0] {
+        Segment::Key(key) => key,
+        Segment::Index(_) => "",
+    }
+}
+
+fn path_string(segments: &[Segment]) -> String {
+    let mut out = String::new();
+    for (i, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::Key(key) if i == 0 => out.push_str(key),
+            Segment::Key(key) => {
+                out.push('.');
+                out.push_str(key);
+            }
+            Segment::Index(index) => out.push_str(&format!("[{}]", index)),
+        }
+    }
+    out
+}
+
+fn find(value: &Value, path: &mut Vec<Segment>, policy: &UpgradePolicy, found: &mut Vec<(Vec<Segment>, Upgrade)>) {
+    match value {
+        Value::String(s) => {
+            let converted = policy.conversions(path).iter()
+                .find_map(|&conversion| conversion.convert(s).map(|to| (conversion, to)));
+            if let Some((conversion, to)) = converted {
+                let upgrade = Upgrade { path: path_string(path), conversion, from: s.clone(), to };
+                found.push((path.clone(), upgrade));
+            }
+        }
+        Value::Tagged(_, inner) => find(inner, path, policy, found),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                path.push(Segment::Index(i));
+                find(item, path, policy, found);
+                path.pop();
+            }
+        }
+        Value::Object(obj) => {
+            for (key, item) in obj {
+                path.push(Segment::Key(key.clone()));
+                find(item, path, policy, found);
+                path.pop();
+            }
+        }
+        Value::Map(pairs) => {
+            for (i, (_, item)) in pairs.iter().enumerate() {
+                path.push(Segment::Index(i));
+                find(item, path, policy, found);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The string at `path` below `value`, looking through tags.
+fn value_at<'a>(value: &'a mut Value, path: &[Segment]) -> Option<&'a mut Value> {
+    let value = match value {
+        Value::Tagged(_, inner) => return value_at(inner, path),
+        value => value,
+    };
+    let (first, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return Some(value),
+    };
+    let child = match (value, first) {
+        (Value::Array(items), Segment::Index(i)) => items.get_mut(*i)?,
+        (Value::Map(pairs), Segment::Index(i)) => &mut pairs.get_mut(*i)?.1,
+        (Value::Object(obj), Segment::Key(key)) => obj.get_mut(key)?,
+        _ => return None,
+    };
+    value_at(child, rest)
+}
+
+/// The segments of a rule pattern, as they appear in paths, with `[*]`
+/// kept whole.
+fn pattern_segments(pattern: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut rest = pattern;
+    while !rest.is_empty() {
+        if rest.starts_with('[') {
+            let end = rest.find(']').map_or(rest.len(), |i| i + 1);
+            segments.push(&rest[..end]);
+            rest = &rest[end..];
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            segments.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        rest = rest.strip_prefix('.').unwrap_or(rest);
+    }
+    segments
+}
+
+fn matches(pattern: &[&str], path: &[Segment]) -> bool {
+    let (first, rest) = match pattern.split_first() {
+        Some((&first, rest)) => (first, rest),
+        None => return path.is_empty(),
+    };
+    if first == "**" {
+        return (0..=path.len()).any(|skip| matches(rest, &path[skip..]));
+    }
+    let (segment, path_rest) = match path.split_first() {
+        Some(split) => split,
+        None => return false,
+    };
+    let fits = match (first, segment) {
+        ("*", _) | ("[*]", Segment::Index(_)) => true,
+        (p, Segment::Index(i)) => p.strip_prefix('[').and_then(|p| p.strip_suffix(']')) == Some(&i.to_string()),
+        (p, Segment::Key(key)) => p == key,
+    };
+    fits && matches(rest, path_rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        use Conversion::*;
+        assert_eq!(Number.convert("42"), Some(Value::Int(42)));
+        assert_eq!(Number.convert("-1.5e3"), Some(Value::Float(-1500.0)));
+        assert_eq!(Number.convert("18446744073709551615"), Some(Value::UInt(u64::MAX)));
+        assert_eq!(Number.convert("0.1"), Some(Value::Float(0.1)));
+        assert_eq!(Number.convert("-0.0"), Some(Value::Float(-0.0)));
+        assert_eq!(Number.convert("120.50e-2"), Some(Value::Float(1.205)));
+        assert_eq!(Number.convert("1.7976931348623157e308"), Some(Value::Float(f64::MAX)));
+        // More digits than an f64 holds are kept exactly
+        for exact in ["3.14159265358979323846", "0.10000000000000000001", "12345678901234567890.5"] {
+            assert_eq!(Number.convert(exact), Some(Value::JsonNumber(exact.to_string())), "{}", exact);
+        }
+        for kept in ["007", "+1", "1.", ".5", "1e", "0x10", "NaN", "inf", "1_000", " 1", "", "99999999999999999999", "1e999"] {
+            assert_eq!(Number.convert(kept), None, "{}", kept);
+        }
+        assert_eq!(Bytes.convert("0xCAFEf00d"), Some(Value::Bytes(vec![0xca, 0xfe, 0xf0, 0x0d])));
+        for kept in ["0x", "0xabc", "0xzz", "cafe"] {
+            assert_eq!(Bytes.convert(kept), None, "{}", kept);
+        }
+        assert!(matches!(Timestamp.convert("2024-01-15T10:30:00Z"), Some(Value::Timestamp(..))));
+        assert!(matches!(Timestamp.convert("2024-01-15T10:30:00.123456789Z"), Some(Value::TimestampNanos(..))));
+        assert!(matches!(Timestamp.convert("2024-01-15"), Some(Value::Timestamp(..))));
+        for kept in ["2024-13-01", "2024-01-15 extra", "42", "today", "2024-01-15 # note", "2024-01-15T10:30:00Z ", "2024-01-15#x"] {
+            assert_eq!(Timestamp.convert(kept), None, "{}", kept);
+        }
+    }
+
+    #[test]
+    fn test_patterns() {
+        let path = |segments: &[Segment]| segments.to_vec();
+        let p = path(&[Segment::Key("orders".into()), Segment::Index(3), Segment::Key("at".into())]);
+        for pattern in ["orders[*].at", "orders.*.at", "orders[3].at", "**.at", "**", "orders.**", "orders[*].**.at"] {
+            assert!(matches(&pattern_segments(pattern), &p), "{}", pattern);
+        }
+        for pattern in ["orders[*]", "orders[2].at", "*.at", "orders.at", "orders[*].at.**.x"] {
+            assert!(!matches(&pattern_segments(pattern), &p), "{}", pattern);
+        }
+        assert_eq!(path_string(&p), "orders[3].at");
+    }
+
+    #[cfg(feature = "json")]
+    const JSON: &str = r#"{
+        "orders": [
+            {"id": "1001", "zip": "02134", "total": "19.99", "at": "2024-01-15T10:30:00Z", "hash": "0xcafe"},
+            {"id": "1002", "zip": "94105", "total": "free", "at": "soon", "hash": "0xbeef"}
+        ],
+        "note": "42"
+    }"#;
+
+    #[cfg(feature = "json")]
+    fn policy() -> UpgradePolicy {
+        UpgradePolicy::new()
+            .rule("orders[*].zip", &[])
+            .rule("**.at", &[Conversion::Timestamp])
+            .rule("orders[*].*", &[Conversion::Number, Conversion::Bytes])
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_upgrade_types() {
+        let mut doc = TeaLeaf::from_json(JSON).unwrap();
+        let preview = doc.upgrade_types_dry_run(&policy());
+        assert_eq!(doc.get_path("orders[0].id").and_then(Value::as_str), Some("1001"));
+
+        let report = doc.upgrade_types(&policy());
+        assert_eq!(report, preview);
+        let paths: Vec<&str> = report.upgrades.iter().map(|u| u.path.as_str()).collect();
+        assert_eq!(paths, ["orders[0].id", "orders[0].total", "orders[0].at", "orders[0].hash", "orders[1].id", "orders[1].hash"]);
+        assert_eq!((report.count(Conversion::Number), report.count(Conversion::Bytes)), (3, 2));
+        assert_eq!(doc.get_path("orders[0].id"), Some(&Value::Int(1001)));
+        assert_eq!(doc.get_path("orders[0].total"), Some(&Value::Float(19.99)));
+        assert!(matches!(doc.get_path("orders[0].at"), Some(Value::Timestamp(..))));
+        assert_eq!(doc.get_path("orders[1].hash"), Some(&Value::Bytes(vec![0xbe, 0xef])));
+        assert_eq!(doc.get_path("orders[0].zip").and_then(Value::as_str), Some("02134"));
+        assert_eq!(doc.get_path("orders[1].total").and_then(Value::as_str), Some("free"));
+        assert_eq!(doc.get("note").and_then(Value::as_str), Some("42"));
+        assert!(report.to_string().starts_with("orders[0].id: \"1001\" -> 1001 (number)\n"), "{}", report);
+
+        assert!(doc.upgrade_types(&policy()).upgrades.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_everywhere_reaches_nested_values() {
+        let mut doc = TeaLeaf::from_json(r#"{"a": {"b": [["1", "x"]]}, "c": "2"}"#).unwrap();
+        doc.data.insert("t".into(), Value::Tagged("n".into(), Box::new(Value::String("3".into()))));
+        doc.data.insert("m".into(), Value::Map(vec![(Value::String("4".into()), Value::String("5".into()))]));
+        let report = doc.upgrade_types(&UpgradePolicy::everywhere(&[Conversion::Number]));
+        assert_eq!(report.upgrades.len(), 4);
+        assert_eq!(doc.get_path("a.b[0][0]"), Some(&Value::Int(1)));
+        assert_eq!(doc.get("t"), Some(&Value::Tagged("n".into(), Box::new(Value::Int(3)))));
+        assert_eq!(doc.get("m"), Some(&Value::Map(vec![(Value::String("4".into()), Value::Int(5))])));
+    }
+}
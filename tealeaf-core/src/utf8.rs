@@ -0,0 +1,62 @@
+//! Text carried as bytes.
+//!
+//! Binary protocols and some JSON producers deliver text as `bytes`, and
+//! opaque payloads sometimes arrive as strings. [`Value::as_bytes`] and
+//! [`Value::as_str`] only read a value of their own kind; the methods here
+//! convert between the two, checking that bytes read as text are valid
+//! UTF-8 instead of replacing what is not.
+
+use crate::types::value_kind;
+use crate::{Error, Result, Value};
+
+impl Value {
+    /// These bytes as UTF-8 text, without copying.
+    ///
+    /// Fails with [`Error::ParseError`] if the value is not bytes, or the
+    /// bytes are not valid UTF-8; the error gives the offset of the first
+    /// invalid byte.
+    pub fn bytes_as_utf8(&self) -> Result<&str> {
+        match self {
+            Value::Bytes(bytes) => std::str::from_utf8(bytes).map_err(|e| {
+                Error::ParseError(format!("bytes are not valid UTF-8 at offset {}", e.valid_up_to()))
+            }),
+            other => Err(Error::ParseError(format!("expected bytes, got {}", value_kind(other)))),
+        }
+    }
+
+    /// These bytes as a `String` value, checked as in
+    /// [`bytes_as_utf8`](Self::bytes_as_utf8).
+    pub fn bytes_to_string(&self) -> Result<Value> {
+        self.bytes_as_utf8().map(|s| Value::String(s.to_string()))
+    }
+
+    /// This string as a `Bytes` value holding its UTF-8 encoding.
+    ///
+    /// Fails with [`Error::ParseError`] if the value is not a string.
+    pub fn string_to_bytes(&self) -> Result<Value> {
+        match self {
+            Value::String(s) => Ok(Value::Bytes(s.as_bytes().to_vec())),
+            other => Err(Error::ParseError(format!("expected a string, got {}", value_kind(other)))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes_utf8_conversion() {
+        let text = Value::String("héllo".into());
+        let bytes = text.string_to_bytes().unwrap();
+        assert_eq!(bytes, Value::Bytes("héllo".as_bytes().to_vec()));
+        assert_eq!(bytes.bytes_as_utf8().unwrap(), "héllo");
+        assert_eq!(bytes.bytes_to_string().unwrap(), text);
+
+        let bad = Value::Bytes(vec![b'o', b'k', 0xc3, 0x28]);
+        assert!(bad.bytes_as_utf8().unwrap_err().to_string().contains("not valid UTF-8 at offset 2"));
+        assert!(bad.bytes_to_string().is_err());
+        assert!(text.bytes_as_utf8().unwrap_err().to_string().contains("expected bytes, got string"));
+        assert!(bytes.string_to_bytes().unwrap_err().to_string().contains("got bytes"));
+    }
+}
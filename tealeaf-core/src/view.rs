@@ -0,0 +1,273 @@
+//! Null-safe chained access into nested values.
+//!
+//! `doc.at("users").idx(0).at("address").str()` walks one step at a time
+//! without `and_then` chains. A step that does not resolve short-circuits the
+//! rest of the chain; the checked accessors (`value`, `try_str`, ...) report
+//! the path walked and how much of it resolved.
+//!
+//! [`Value::slice`] and [`TeaLeaf::table_page`] borrow a run of array
+//! elements, so paging through a large in-memory table copies nothing.
+
+use std::fmt::Write;
+use std::ops::{Bound, RangeBounds};
+
+use crate::convert::ConvertError;
+use crate::{Error, ObjectMap, Result, TeaLeaf, Value};
+
+/// A position in a value tree reached by [`at`](Self::at) and
+/// [`idx`](Self::idx) steps, which may not exist.
+#[derive(Debug, Clone)]
+pub struct ValueView<'a> {
+    value: Option<&'a Value>,
+    /// Path walked so far, in `get_path` syntax
+    path: String,
+    /// Length of the prefix of `path` that resolved
+    resolved: usize,
+}
+
+impl<'a> ValueView<'a> {
+    /// A view of `value` itself, with an empty path.
+    pub fn new(value: &'a Value) -> Self {
+        Self { value: Some(value), path: String::new(), resolved: 0 }
+    }
+
+    /// Step into object field `key`.
+    pub fn at(mut self, key: &str) -> Self {
+        if !self.path.is_empty() {
+            self.path.push('.');
+        }
+        self.path.push_str(key);
+        self.step(|v| v.get(key))
+    }
+
+    /// Step into array element `index`.
+    pub fn idx(mut self, index: usize) -> Self {
+        let _ = write!(self.path, "[{}]", index);
+        self.step(|v| v.index(index))
+    }
+
+    fn step(mut self, f: impl FnOnce(&'a Value) -> Option<&'a Value>) -> Self {
+        self.value = self.value.and_then(f);
+        if self.value.is_some() {
+            self.resolved = self.path.len();
+        }
+        self
+    }
+
+    /// The path walked so far, e.g. `users[0].address`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get(&self) -> Option<&'a Value> {
+        self.value
+    }
+
+    pub fn exists(&self) -> bool {
+        self.value.is_some()
+    }
+
+    pub fn str(&self) -> Option<&'a str> {
+        self.value?.as_str()
+    }
+
+    pub fn int(&self) -> Option<i64> {
+        self.value?.as_int()
+    }
+
+    pub fn uint(&self) -> Option<u64> {
+        self.value?.as_uint()
+    }
+
+    pub fn float(&self) -> Option<f64> {
+        self.value?.as_float()
+    }
+
+    pub fn bool(&self) -> Option<bool> {
+        self.value?.as_bool()
+    }
+
+    pub fn array(&self) -> Option<&'a [Value]> {
+        self.value?.as_array()
+    }
+
+    pub fn object(&self) -> Option<&'a ObjectMap<String, Value>> {
+        self.value?.as_object()
+    }
+
+    /// The value, or [`Error::MissingField`] naming the path and the part of
+    /// it that resolved.
+    pub fn value(&self) -> Result<&'a Value> {
+        self.value.ok_or_else(|| {
+            if self.resolved == 0 {
+                Error::MissingField(self.path.clone())
+            } else {
+                Error::MissingField(format!("{} (resolved up to '{}')", self.path, &self.path[..self.resolved]))
+            }
+        })
+    }
+
+    pub fn try_str(&self) -> Result<&'a str> {
+        self.convert("string", Value::as_str)
+    }
+
+    pub fn try_int(&self) -> Result<i64> {
+        self.convert("int", Value::as_int)
+    }
+
+    pub fn try_float(&self) -> Result<f64> {
+        self.convert("float", Value::as_float)
+    }
+
+    pub fn try_bool(&self) -> Result<bool> {
+        self.convert("bool", Value::as_bool)
+    }
+
+    fn convert<T>(&self, expected: &str, f: impl FnOnce(&'a Value) -> Option<T>) -> Result<T> {
+        let value = self.value()?;
+        f(value).ok_or_else(|| ConvertError::TypeMismatch {
+            expected: expected.to_string(),
+            got: format!("{:?}", value.tl_type()),
+            path: self.path.clone(),
+        }.into())
+    }
+}
+
+impl Value {
+    /// Start a [`ValueView`] chain at this value.
+    pub fn view(&self) -> ValueView<'_> {
+        ValueView::new(self)
+    }
+
+    /// Start a [`ValueView`] chain at object field `key`.
+    pub fn at(&self, key: &str) -> ValueView<'_> {
+        self.view().at(key)
+    }
+
+    /// Start a [`ValueView`] chain at array element `index`.
+    pub fn idx(&self, index: usize) -> ValueView<'_> {
+        self.view().idx(index)
+    }
+
+    /// Borrow the elements of an array in `range`, cut short at the end of
+    /// the array; `None` if this is not an array.
+    ///
+    /// ```
+    /// use tealeaf::Value;
+    ///
+    /// let arr = Value::Array((1..=5).map(Value::Int).collect());
+    /// assert_eq!(arr.slice(1..3), Some(&[Value::Int(2), Value::Int(3)][..]));
+    /// assert_eq!(arr.slice(4..).map(<[Value]>::len), Some(1));
+    /// assert_eq!(arr.slice(9..12), Some(&[][..]));
+    /// ```
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Option<&[Value]> {
+        let items = self.as_array()?;
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i.saturating_add(1),
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => items.len(),
+        };
+        let end = end.min(items.len());
+        Some(&items[start.min(end)..end])
+    }
+}
+
+impl TeaLeaf {
+    /// Start a [`ValueView`] chain at top-level section `key`.
+    pub fn at(&self, key: &str) -> ValueView<'_> {
+        let value = self.get(key);
+        ValueView {
+            value,
+            path: key.to_string(),
+            resolved: if value.is_some() { key.len() } else { 0 },
+        }
+    }
+
+    /// Borrow up to `limit` rows of the array in section `key`, starting at
+    /// row `offset`; `None` if the section is missing or not an array.
+    /// Pages past the last row are empty.
+    pub fn table_page(&self, key: &str, offset: usize, limit: usize) -> Option<&[Value]> {
+        self.get(key)?.slice(offset..offset.saturating_add(limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users() -> Value {
+        let mut address = ObjectMap::new();
+        address.insert("city".to_string(), Value::String("Oslo".into()));
+        let mut user = ObjectMap::new();
+        user.insert("name".to_string(), Value::String("ada".into()));
+        user.insert("address".to_string(), Value::Object(address));
+        user.insert("age".to_string(), Value::Int(36));
+        let mut root = ObjectMap::new();
+        root.insert("users".to_string(), Value::Array(vec![Value::Object(user)]));
+        Value::Object(root)
+    }
+
+    #[test]
+    fn test_chain_on_value() {
+        let root = users();
+        assert_eq!(root.at("users").idx(0).at("address").at("city").str(), Some("Oslo"));
+        assert_eq!(root.at("users").idx(0).at("age").int(), Some(36));
+        assert_eq!(root.at("users").array().map(<[Value]>::len), Some(1));
+
+        let missing = root.at("users").idx(3).at("address");
+        assert!(!missing.exists());
+        assert_eq!(missing.str(), None);
+        assert_eq!(missing.path(), "users[3].address");
+        assert_eq!(
+            missing.value().unwrap_err().to_string(),
+            "Missing field: users[3].address (resolved up to 'users')"
+        );
+    }
+
+    #[test]
+    fn test_checked_accessors() {
+        let root = users();
+        assert_eq!(root.at("users").idx(0).at("name").try_str().unwrap(), "ada");
+        let err = root.at("users").idx(0).at("name").try_int().unwrap_err();
+        assert_eq!(err.to_string(), "Parse error: Type mismatch at 'users[0].name': expected int, got String");
+        assert!(matches!(root.at("nope").try_str(), Err(Error::MissingField(p)) if p == "nope"));
+    }
+
+    #[test]
+    fn test_chain_on_document() {
+        let doc = TeaLeaf::new(Default::default(), users().as_object().unwrap().clone());
+        assert_eq!(doc.at("users").idx(0).at("address").at("city").str(), Some("Oslo"));
+        let err = doc.at("users").idx(0).at("zip").value().unwrap_err();
+        assert_eq!(err.to_string(), "Missing field: users[0].zip (resolved up to 'users[0]')");
+        assert!(doc.at("absent").idx(0).get().is_none());
+    }
+
+    #[test]
+    fn test_slices_and_pages() {
+        let rows = Value::Array((0..10).map(Value::Int).collect());
+        let ints = |s: &[Value]| s.iter().filter_map(Value::as_int).collect::<Vec<_>>();
+        assert_eq!(rows.slice(2..=4).map(ints), Some(vec![2, 3, 4]));
+        assert_eq!(rows.slice(..2).map(ints), Some(vec![0, 1]));
+        assert_eq!(rows.slice(8..20).map(ints), Some(vec![8, 9]));
+        let (start, end) = (5, 3);
+        assert_eq!(rows.slice(start..end), Some(&[][..]));
+        assert_eq!(Value::Int(1).slice(..), None);
+
+        let mut data = ObjectMap::new();
+        data.insert("rows".to_string(), rows);
+        data.insert("name".to_string(), Value::String("t".into()));
+        let doc = TeaLeaf::new(Default::default(), data);
+        let page = doc.table_page("rows", 3, 4).unwrap();
+        assert_eq!(ints(page), [3, 4, 5, 6]);
+        // The page borrows the document's rows rather than copying them
+        assert!(std::ptr::eq(&page[0], doc.get("rows").unwrap().index(3).unwrap()));
+        assert_eq!(doc.table_page("rows", 9, usize::MAX).map(ints), Some(vec![9]));
+        assert_eq!(doc.table_page("rows", 10, 5).map(<[Value]>::len), Some(0));
+        assert!(doc.table_page("name", 0, 5).is_none() && doc.table_page("absent", 0, 5).is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! Warnings for lossy or best-effort conversions.
+//!
+//! Conversions never fail on these conditions; the `*_with_warnings` entry
+//! points report them so callers can log them or reject the output.
+
+use std::fmt;
+
+#[cfg(any(feature = "text", feature = "json"))]
+use crate::Value;
+
+/// A lossy or best-effort decision made during a conversion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// A NaN or infinite float was exported to JSON as `null`.
+    NonFiniteFloat { path: String },
+    /// A whole-number float was written without `.0` and re-parses as an integer.
+    FloatBecomesInt { path: String },
+    /// The binary writer coerced a value that did not match its schema field type.
+    SchemaCoercion { section: String, expected: String, found: String },
+    /// Schema inference left an array of objects or a nested object field
+    /// without a schema.
+    InferenceSkipped { name: String, reason: String },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::NonFiniteFloat { path } => {
+                write!(f, "Non-finite float at '{}' exported as null", path)
+            }
+            Warning::FloatBecomesInt { path } => {
+                write!(f, "Float at '{}' written without fraction re-parses as an integer", path)
+            }
+            Warning::SchemaCoercion { section, expected, found } => {
+                write!(f, "Section '{}': {} value coerced to {}", section, found, expected)
+            }
+            Warning::InferenceSkipped { name, reason } => {
+                write!(f, "No schema inferred for '{}': {}", name, reason)
+            }
+        }
+    }
+}
+
+/// Call `visit` with the path of every float reachable from `value`.
+///
+/// Paths use the `key.field[N]` syntax accepted by [`Value::get_path`]; map
+/// entries are addressed by position.
+#[cfg(any(feature = "text", feature = "json"))]
+pub(crate) fn visit_floats(value: &Value, path: &mut String, visit: &mut impl FnMut(&str, f64)) {
+    match value {
+        Value::Float(f) => visit(path, *f),
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                visit_floats(v, path, visit);
+                path.truncate(len);
+            }
+        }
+        Value::Object(obj) => {
+            for (k, v) in obj {
+                let len = path.len();
+                path.push('.');
+                path.push_str(k);
+                visit_floats(v, path, visit);
+                path.truncate(len);
+            }
+        }
+        Value::Map(pairs) => {
+            for (i, (_, v)) in pairs.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                visit_floats(v, path, visit);
+                path.truncate(len);
+            }
+        }
+        Value::Tagged(_, inner) => visit_floats(inner, path, visit),
+        _ => {}
+    }
+}
+
+/// Collect one warning per float in `data` matching `pred`.
+#[cfg(any(feature = "text", feature = "json"))]
+pub(crate) fn float_warnings(
+    data: &indexmap::IndexMap<String, Value>,
+    pred: impl Fn(f64) -> bool,
+    make: impl Fn(String) -> Warning,
+) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut path = String::new();
+    for (key, value) in data {
+        path.clear();
+        path.push_str(key);
+        visit_floats(value, &mut path, &mut |p, f| {
+            if pred(f) {
+                warnings.push(make(p.to_string()));
+            }
+        });
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(any(feature = "text", feature = "json"))]
+    fn test_float_paths() {
+        let mut inner = crate::ObjectMap::new();
+        inner.insert("x".to_string(), Value::Float(f64::NAN));
+        inner.insert("y".to_string(), Value::Array(vec![Value::Int(1), Value::Float(f64::INFINITY)]));
+        let mut data = indexmap::IndexMap::new();
+        data.insert("point".to_string(), Value::Object(inner));
+        data.insert("ok".to_string(), Value::Float(1.5));
+
+        let warnings = float_warnings(&data, |f| !f.is_finite(), |path| Warning::NonFiniteFloat { path });
+        assert_eq!(warnings, vec![
+            Warning::NonFiniteFloat { path: "point.x".into() },
+            Warning::NonFiniteFloat { path: "point.y[1]".into() },
+        ]);
+    }
+
+    #[test]
+    fn test_display() {
+        let w = Warning::SchemaCoercion { section: "users".into(), expected: "int".into(), found: "string".into() };
+        assert_eq!(w.to_string(), "Section 'users': string value coerced to int");
+    }
+}
@@ -1,13 +1,17 @@
 //! Binary format writer for TeaLeaf
 
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufWriter, Write, Seek, SeekFrom};
-use std::path::Path;
-use crate::types::ObjectMap;
-
-use crate::{Result, Value, Schema, Union, FieldType, TLType, MAGIC, VERSION_MAJOR, VERSION_MINOR, HEADER_SIZE,
-    MAX_STRING_LENGTH, MAX_OBJECT_FIELDS, MAX_ARRAY_LENGTH};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Cursor, Write, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use crate::codec::SectionCodec;
+use crate::progress::Progress;
+use crate::types::{ObjectMap, Unencodable, value_kind};
+use indexmap::IndexMap;
+
+use crate::{Result, Value, TimePrecision, Schema, SchemaRegistry, Union, FieldType, TLType, Warning, MAGIC, VERSION_MAJOR, VERSION_MINOR, HEADER_SIZE,
+    MAX_STRING_LENGTH, MAX_OBJECT_FIELDS, MAX_ARRAY_LENGTH, UNION_FLAG_ORDINAL_TAGS, MAX_UNION_VARIANTS, TYPE_CODE_ANY};
 
 pub struct Writer {
     strings: Vec<String>,
@@ -16,9 +20,54 @@ pub struct Writer {
     schema_map: HashMap<String, u16>,
     unions: Vec<Union>,
     union_map: HashMap<String, u16>,
+    /// Unions copied from a file that tags their values by name rather than
+    /// by ordinal, so rows using them cannot be copied raw
+    tagged_by_name: HashSet<String>,
     sections: Vec<Section>,
+    /// Total data size of `sections`
+    encoded_size: u64,
     /// Indicates the source JSON was a root-level array (for round-trip fidelity)
     is_root_array: bool,
+    /// Indicates the source JSON was a root-level scalar (for round-trip fidelity)
+    is_root_primitive: bool,
+    /// Values coerced to fit their schema field types
+    warnings: Vec<Warning>,
+    /// Key of the section being encoded, for warnings
+    current_section: String,
+    /// Steps from the root of the section being encoded to the value being
+    /// encoded, for [`Unencodable`] paths
+    path: Vec<PathStep>,
+    /// Values of the section being encoded that the format cannot hold
+    unencodable: Vec<Unencodable>,
+    /// Hold an advisory lock on `<path>.lock` while writing
+    locking: bool,
+    /// Rows per chunk of table sections (0 = unchunked)
+    chunk_rows: usize,
+    /// Encoded size above which table sections are split (0 = never)
+    max_section_bytes: usize,
+    /// Record table-wide column statistics in table sections
+    column_stats: bool,
+    /// Store the inferred structure of sections written without a schema
+    describe_sections: bool,
+    /// Registered codecs, by ID and key ID
+    codecs: HashMap<(u32, Option<String>), Box<dyn SectionCodec>>,
+    /// Codec ID to encode each section key with
+    section_codecs: HashMap<String, u32>,
+    /// Encrypted fields by schema and field name, with the codec ID and key
+    /// ID to encrypt their values with; `None` for fields copied encrypted
+    /// by [`from_reader`](Self::from_reader) that have not been given a key
+    field_keys: HashMap<String, HashMap<String, Option<(u32, String)>>>,
+    /// Values of encrypted fields, by position in the encrypted value table
+    sealed: Vec<Sealed>,
+}
+
+/// A value of the encrypted value table
+enum Sealed {
+    /// The encoding of a value of `field` (`schema.field`), to be encrypted
+    /// with the codec `codec` for `key_id` when the file is written
+    Plain { field: String, codec: u32, key_id: String, data: Vec<u8> },
+    /// A value as stored in the file the writer was created from
+    Stored(Vec<u8>),
 }
 
 struct Section {
@@ -28,6 +77,26 @@ struct Section {
     tl_type: TLType,
     is_array: bool,
     item_count: u32,
+    /// Offset of the chunk index that follows the table rows in `data`
+    chunk_index: Option<u32>,
+    /// Offset of the translation index that follows the rows of an
+    /// unchunked `translation` table in `data`
+    lookup_index: Option<u32>,
+    /// Continues the table of the previous section with the same key
+    continues: bool,
+    /// `data` ends with a column statistics block and its offset
+    column_stats: bool,
+    /// Structure descriptor stored before the section data
+    descriptor: Option<Vec<u8>>,
+}
+
+/// A step of the path to the value being encoded
+#[derive(Clone, Copy)]
+enum PathStep {
+    /// Object key or field name, by string index
+    Key(u32),
+    /// Array element or map entry, by position
+    Index(usize),
 }
 
 impl Writer {
@@ -39,9 +108,105 @@ impl Writer {
             schema_map: HashMap::new(),
             unions: Vec::new(),
             union_map: HashMap::new(),
+            tagged_by_name: HashSet::new(),
             sections: Vec::new(),
+            encoded_size: 0,
             is_root_array: false,
+            is_root_primitive: false,
+            warnings: Vec::new(),
+            current_section: String::new(),
+            path: Vec::new(),
+            unencodable: Vec::new(),
+            locking: false,
+            chunk_rows: 0,
+            max_section_bytes: 0,
+            column_stats: false,
+            describe_sections: false,
+            codecs: HashMap::new(),
+            section_codecs: HashMap::new(),
+            field_keys: HashMap::new(),
+            sealed: Vec::new(),
+        }
+    }
+
+    /// Values that did not match their schema field type and were coerced
+    /// (typically to a zero value) while encoding.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    fn warn_coercion(&mut self, field_type: &FieldType, value: &Value) {
+        self.warnings.push(Warning::SchemaCoercion {
+            section: self.current_section.clone(),
+            expected: field_type.to_string(),
+            found: value_kind(value).to_string(),
+        });
+    }
+
+    /// Record the value at the current path as one the format cannot hold.
+    fn unencodable(&mut self, reason: String) {
+        let path = self.current_path();
+        self.unencodable.push(Unencodable { path, reason });
+    }
+
+    /// The section key and path of the value being encoded.
+    fn current_path(&self) -> String {
+        let mut path = self.current_section.clone();
+        for step in &self.path {
+            match *step {
+                PathStep::Key(idx) => {
+                    path.push('.');
+                    path.push_str(&self.strings[idx as usize]);
+                }
+                PathStep::Index(i) => path.push_str(&format!("[{}]", i)),
+            }
         }
+        path
+    }
+
+    /// Intern a string of the value being encoded, recording it as
+    /// unencodable instead if it is too long for the string table.
+    fn intern_value(&mut self, s: &str) -> u32 {
+        if s.len() > MAX_STRING_LENGTH {
+            self.unencodable(format!("string of {} bytes exceeds maximum {}", s.len(), MAX_STRING_LENGTH));
+            return 0;
+        }
+        self.intern(s)
+    }
+
+    /// Create a writer whose string, schema and union tables start as copies
+    /// of `reader`'s, in the same order.
+    ///
+    /// Encoded section data refers to strings, schemas and unions by table
+    /// index, so this is what allows sections of `reader` to be copied with
+    /// [`add_raw_section`](Self::add_raw_section). Sections from other files
+    /// must still go through [`add_section`](Self::add_section). Encrypted
+    /// field values are copied as stored, and the fields stay encrypted;
+    /// new values for them need a key from [`set_field_key`](Self::set_field_key).
+    pub fn from_reader(reader: &crate::Reader) -> Result<Self> {
+        let mut w = Self::new();
+        for idx in 0..reader.string_count() {
+            let s = reader.get_string(idx)?;
+            // A well-formed string table has no duplicates; keep indices aligned regardless
+            w.string_map.entry(s.clone()).or_insert(idx as u32);
+            w.strings.push(s);
+        }
+        for schema in &reader.schemas {
+            w.add_schema(schema.clone());
+        }
+        for (union, flags) in reader.unions.iter().zip(&reader.union_flags) {
+            if flags & UNION_FLAG_ORDINAL_TAGS == 0 {
+                w.tagged_by_name.insert(union.name.clone());
+            }
+            w.add_union(union.clone());
+        }
+        for (schema, field) in reader.encrypted_fields() {
+            w.field_keys.entry(schema.to_string()).or_default().insert(field.to_string(), None);
+        }
+        for i in 0..reader.encrypted_value_count() {
+            w.sealed.push(Sealed::Stored(reader.encrypted_value(i).to_vec()));
+        }
+        Ok(w)
     }
 
     /// Set whether the source JSON was a root-level array
@@ -49,6 +214,108 @@ impl Writer {
         self.is_root_array = is_root_array;
     }
 
+    /// Set whether the source JSON was a root-level scalar
+    pub fn set_root_primitive(&mut self, is_root_primitive: bool) {
+        self.is_root_primitive = is_root_primitive;
+    }
+
+    /// Hold an exclusive advisory lock on `<path>.lock` while writing, so that
+    /// cooperating writers of the same file take turns. Off by default.
+    pub fn set_locking(&mut self, locking: bool) {
+        self.locking = locking;
+    }
+
+    /// Write table sections in chunks of `rows` rows, each with min/max and
+    /// null-count statistics per column, so that readers can skip chunks
+    /// (see [`Reader::scan_chunks`](crate::Reader::scan_chunks)) or read them
+    /// individually. 0 (the default) writes tables unchunked.
+    pub fn set_chunk_rows(&mut self, rows: usize) {
+        self.chunk_rows = rows;
+    }
+
+    /// Record the null count, distinct count and (for numeric and timestamp
+    /// columns) min/max of every column of each table section, so that
+    /// [`Reader::column_stats`](crate::Reader::column_stats) can report them
+    /// without decoding rows. Off by default.
+    pub fn set_column_stats(&mut self, on: bool) {
+        self.column_stats = on;
+    }
+
+    /// Store the field names and types of each section added without a
+    /// schema whose value is an object or an array of objects, inferred
+    /// from the data, so that [`Reader::section_fields`](crate::Reader::section_fields)
+    /// can list them without decompressing or decoding the section. Sections
+    /// written with a codec are not described, as the description is stored
+    /// unencoded. Off by default.
+    pub fn set_describe_sections(&mut self, on: bool) {
+        self.describe_sections = on;
+    }
+
+    /// Split table sections whose encoded rows would exceed `bytes` into
+    /// continuation sections of at most `bytes` each, so that no single
+    /// section needs a larger decompression buffer. [`Reader::get`](crate::Reader::get)
+    /// joins them back into one table. Splits fall between chunks when
+    /// [`set_chunk_rows`](Self::set_chunk_rows) is used, so a single row or
+    /// chunk larger than `bytes` still gets a section of its own. 0 (the
+    /// default) never splits.
+    pub fn with_max_section_bytes(mut self, bytes: usize) -> Self {
+        self.max_section_bytes = bytes;
+        self
+    }
+
+    /// Make `codec` available to [`set_section_codec`](Self::set_section_codec)
+    /// or, if it has a key ID, to [`set_field_key`](Self::set_field_key),
+    /// replacing any codec registered with the same ID and key ID.
+    pub fn register_codec<C: SectionCodec + 'static>(&mut self, codec: C) {
+        self.codecs.insert((codec.id(), codec.key_id().map(str::to_string)), Box::new(codec));
+    }
+
+    /// Encode the section `key` (every part of it, if split) with the codec
+    /// registered as `codec_id`. The codec must be registered by the time
+    /// the file is written.
+    pub fn set_section_codec(&mut self, key: &str, codec_id: u32) {
+        self.section_codecs.insert(key.to_string(), codec_id);
+    }
+
+    /// Encrypt the values of `field` of `schema`, in every section written
+    /// with that schema after this call, with the codec registered as
+    /// `codec_id` for the key `key_id`. The codec must be registered by the
+    /// time the file is written.
+    ///
+    /// Each value is stored in the encrypted value table with the codec ID,
+    /// key ID and algorithm, where [`Reader::key_ids`](crate::Reader::key_ids)
+    /// finds them and [`rotate_keys`](crate::rotate_keys) replaces them, and
+    /// gets no column statistics. Readers without the key fail on rows
+    /// holding the field with [`Error::MissingKey`](crate::Error::MissingKey),
+    /// and can still read rows projected without it.
+    pub fn set_field_key(&mut self, schema: &str, field: &str, codec_id: u32, key_id: &str) {
+        self.field_keys.entry(schema.to_string()).or_default()
+            .insert(field.to_string(), Some((codec_id, key_id.to_string())));
+    }
+
+    /// Whether each field of `schema` is encrypted.
+    fn sealed_fields(&self, schema: &Schema) -> Vec<bool> {
+        let keys = self.field_keys.get(&schema.name);
+        schema.fields.iter().map(|f| keys.is_some_and(|keys| keys.contains_key(&f.name))).collect()
+    }
+
+    /// Fields of registered schemas whose type names neither a struct nor a
+    /// union, as `(schema, field)` pairs. Their values are written with a
+    /// per-value type code (see [`TYPE_CODE_ANY`]).
+    pub fn any_fields(&self) -> Vec<(&str, &str)> {
+        self.schemas.iter()
+            .flat_map(|s| s.fields.iter().map(move |f| (s, f)))
+            .filter(|(_, f)| self.is_any_type(&f.field_type.base))
+            .map(|(s, f)| (s.name.as_str(), f.name.as_str()))
+            .collect()
+    }
+
+    fn is_any_type(&self, base: &str) -> bool {
+        FieldType::new(base).to_tl_type() == TLType::Struct
+            && !self.schema_map.contains_key(base)
+            && !self.union_map.contains_key(base)
+    }
+
     pub fn intern(&mut self, s: &str) -> u32 {
         if let Some(&idx) = self.string_map.get(s) { return idx; }
         let idx = self.strings.len() as u32;
@@ -59,7 +326,13 @@ impl Writer {
 
     pub fn add_schema(&mut self, schema: Schema) -> u16 {
         if let Some(&idx) = self.schema_map.get(&schema.name) { return idx; }
-        for field in &schema.fields { self.intern(&field.name); }
+        for field in &schema.fields {
+            self.intern(&field.name);
+            // Struct, union and any-typed fields record their type name
+            if FieldType::new(&field.field_type.base).to_tl_type() == TLType::Struct {
+                self.intern(&field.field_type.base);
+            }
+        }
         self.intern(&schema.name);
         let idx = self.schemas.len() as u16;
         self.schema_map.insert(schema.name.clone(), idx);
@@ -82,38 +355,427 @@ impl Writer {
         idx
     }
 
+    /// Renumber the registered schemas and unions in name order.
+    ///
+    /// Schemas and unions are numbered in the order they are added, and
+    /// encoded values refer to them by those numbers. Sorting makes the
+    /// numbering depend only on the set of definitions, so independent
+    /// writers of the same definitions agree on it.
+    ///
+    /// # Panics
+    ///
+    /// If a section has already been added, since its data uses the old
+    /// numbering.
+    pub fn sort_definitions(&mut self) {
+        assert!(self.sections.is_empty(), "sort_definitions called after sections were added");
+        self.schemas.sort_by(|a, b| a.name.cmp(&b.name));
+        self.unions.sort_by(|a, b| a.name.cmp(&b.name));
+        self.schema_map = self.schemas.iter().enumerate().map(|(i, s)| (s.name.clone(), i as u16)).collect();
+        self.union_map = self.unions.iter().enumerate().map(|(i, u)| (u.name.clone(), i as u16)).collect();
+    }
+
+    /// Register every schema and union in `registry`, so sections can be
+    /// written against definitions read from another file.
+    pub fn use_registry(&mut self, registry: &SchemaRegistry) {
+        for schema in registry.schemas.values() {
+            self.add_schema(schema.clone());
+        }
+        for union in registry.unions.values() {
+            self.add_union(union.clone());
+        }
+    }
+
+    /// Encode `value` as the section `key`, as a table of `schema` if given.
+    ///
+    /// Values the format cannot hold, such as map keys that are not strings
+    /// or integers, fail with [`Error::Unencodable`](crate::Error::Unencodable)
+    /// listing every one in the section. A section that fails is not added,
+    /// and the writer is left as it was.
     pub fn add_section(&mut self, key: &str, value: &Value, schema: Option<&Schema>) -> Result<()> {
+        self.add_section_with_progress(key, value, schema, &mut Progress::new())
+    }
+
+    /// Like [`add_section`](Self::add_section), stopping with
+    /// [`Error::Cancelled`](crate::Error::Cancelled) if `progress` asks to
+    /// while the rows of a table or the elements of an array are encoded.
+    pub(crate) fn add_section_with_progress(&mut self, key: &str, value: &Value, schema: Option<&Schema>, progress: &mut Progress) -> Result<()> {
+        let (strings, schemas, sections, warnings, sealed, encoded_size) =
+            (self.strings.len(), self.schemas.len(), self.sections.len(), self.warnings.len(), self.sealed.len(), self.encoded_size);
+        self.current_section = key.to_string();
+        self.path.clear();
+        self.unencodable.clear();
+        let result = self.encode_section(key, value, schema, progress).and_then(|()| {
+            if self.unencodable.is_empty() {
+                Ok(())
+            } else {
+                Err(crate::Error::Unencodable { section: key.to_string(), values: std::mem::take(&mut self.unencodable) })
+            }
+        });
+        if result.is_err() {
+            for s in self.strings.drain(strings..) {
+                self.string_map.remove(&s);
+            }
+            for schema in self.schemas.drain(schemas..) {
+                self.schema_map.remove(&schema.name);
+            }
+            self.sections.truncate(sections);
+            self.warnings.truncate(warnings);
+            self.sealed.truncate(sealed);
+            self.encoded_size = encoded_size;
+        }
+        result
+    }
+
+    fn encode_section(&mut self, key: &str, value: &Value, schema: Option<&Schema>, progress: &mut Progress) -> Result<()> {
         self.intern(key);
-        let (data, tl_type, is_array, item_count) = self.encode_value(value, schema)?;
-        // Compute schema_idx AFTER encoding, since encode_value may register the schema
-        let schema_idx = schema.map(|s| self.schema_map.get(&s.name).copied().unwrap_or(0xFFFF) as i32).unwrap_or(-1);
-        self.sections.push(Section { key: key.to_string(), data, schema_idx, tl_type, is_array, item_count });
+        match (schema, value) {
+            (Some(schema), Value::Array(rows)) if (self.chunk_rows > 0 || self.max_section_bytes > 0 || self.column_stats)
+                && !rows.is_empty()
+                && rows.iter().all(|v| matches!(v, Value::Object(_) | Value::Null)) =>
+            {
+                if rows.len() > MAX_ARRAY_LENGTH {
+                    self.unencodable(format!("array has {} elements, exceeds maximum {}", rows.len(), MAX_ARRAY_LENGTH));
+                }
+                // Rows are encoded once, in units that parts may not cut: a
+                // chunk, a row when splitting unchunked, or else the whole table
+                let unit = if self.chunk_rows > 0 {
+                    self.chunk_rows
+                } else if self.max_section_bytes > 0 {
+                    1
+                } else {
+                    rows.len()
+                };
+                let mut encoded = self.table_header(rows.len(), schema);
+                let ends = self.encode_struct_rows(&mut encoded, rows, 0, schema, unit, progress)?;
+                let starts = self.plan_table_parts(&ends, schema);
+                // Statistics of the whole table go in its first part
+                let mut stats = if self.column_stats { Some(encode_column_stats(rows, schema, &self.sealed_fields(schema))) } else { None };
+                for (i, &start) in starts.iter().enumerate() {
+                    let end = starts.get(i + 1).copied().unwrap_or(ends.len());
+                    let unit_start = |u: usize| if u == 0 { 8 } else { ends[u - 1] };
+                    let rows = &rows[start * unit..(end * unit).min(rows.len())];
+                    let mut data = if starts.len() == 1 {
+                        std::mem::take(&mut encoded)
+                    } else {
+                        let mut data = encoded[..8].to_vec();
+                        data[..4].copy_from_slice(&(rows.len() as u32).to_le_bytes());
+                        data.extend_from_slice(&encoded[unit_start(start)..ends[end - 1]]);
+                        data
+                    };
+                    let chunk_index = if self.chunk_rows > 0 {
+                        let offsets = (start..end).map(|u| 8 + unit_start(u) - unit_start(start));
+                        Some(self.append_chunk_index(&mut data, rows, offsets, schema))
+                    } else {
+                        None
+                    };
+                    let lookup_index = if chunk_index.is_none() && starts.len() == 1 {
+                        self.append_translation_index(&mut data, rows, schema)
+                    } else {
+                        None
+                    };
+                    let column_stats = stats.take().map(|block| {
+                        let offset = data.len() as u32;
+                        data.extend(block);
+                        data.extend(offset.to_le_bytes());
+                    }).is_some();
+                    let schema_idx = self.schema_map[&schema.name] as i32;
+                    self.push_section(Section {
+                        key: key.to_string(), data, schema_idx, tl_type: TLType::Struct, is_array: true,
+                        item_count: rows.len() as u32, chunk_index, lookup_index, continues: i > 0, column_stats, descriptor: None,
+                    });
+                }
+            }
+            _ => {
+                let (mut data, tl_type, is_array, item_count) = match value {
+                    Value::Array(arr) => self.encode_array(arr, schema, progress)?,
+                    _ => self.encode_value(value, schema)?,
+                };
+                // Compute schema_idx AFTER encoding, since encode_value may register the schema
+                let schema_idx = schema.map(|s| self.schema_map.get(&s.name).copied().unwrap_or(0xFFFF) as i32).unwrap_or(-1);
+                let descriptor = if self.describe_sections && schema.is_none() { encode_descriptor(value) } else { None };
+                let lookup_index = match (schema, value) {
+                    (Some(schema), Value::Array(rows)) if tl_type == TLType::Struct => self.append_translation_index(&mut data, rows, schema),
+                    _ => None,
+                };
+                self.push_section(Section {
+                    key: key.to_string(), data, schema_idx, tl_type, is_array, item_count, chunk_index: None, lookup_index,
+                    continues: false, column_stats: false, descriptor,
+                });
+            }
+        }
         Ok(())
     }
 
+    /// Append the translation index of `rows` to their encoded table `data`
+    /// if `schema` is the [`translation`](crate::i18n::translation_schema)
+    /// schema, returning its offset. Tables with encrypted fields get none,
+    /// as it would hold their values in the clear.
+    fn append_translation_index(&mut self, data: &mut Vec<u8>, rows: &[Value], schema: &Schema) -> Option<u32> {
+        if *schema != crate::i18n::translation_schema() || self.field_keys.contains_key(&schema.name) {
+            return None;
+        }
+        let offset = data.len() as u32;
+        data.extend(crate::i18n::encode_index(rows, |s| self.intern(s)));
+        Some(offset)
+    }
+
+    /// Split a table's encoded rows into parts of at most `max_section_bytes`
+    /// each (one part if that is 0), cutting only between the units whose
+    /// end offsets are `ends` (see [`encode_struct_rows`](Self::encode_struct_rows)).
+    /// Returns the first unit of each part.
+    fn plan_table_parts(&self, ends: &[usize], schema: &Schema) -> Vec<usize> {
+        if self.max_section_bytes == 0 {
+            return vec![0];
+        }
+        let sealed_fields = self.sealed_fields(schema);
+        // Table header, plus the chunk index header and per-chunk statistics
+        let (part_overhead, unit_overhead) = if self.chunk_rows > 0 {
+            let kinds = stat_kinds(schema, &sealed_fields);
+            let stats: usize = kinds.iter().map(|&k| if k == STAT_NONE { 4 } else { 20 }).sum();
+            (8 + 10 + kinds.len(), 4 + stats)
+        } else {
+            (8, 0)
+        };
+
+        let mut starts = vec![0];
+        let mut size = part_overhead + if self.column_stats { column_stats_size(schema, &sealed_fields) } else { 0 };
+        let mut unit_start = 8;
+        for (i, &end) in ends.iter().enumerate() {
+            let unit_size = end - unit_start + unit_overhead;
+            unit_start = end;
+            if i > 0 && size + unit_size > self.max_section_bytes {
+                starts.push(i);
+                size = part_overhead;
+            }
+            size += unit_size;
+        }
+        starts
+    }
+
+    /// Append the chunk index of the encoded table `data`, whose rows are
+    /// `rows` and whose chunks start at `offsets`:
+    ///
+    /// ```text
+    /// chunk_rows: u32, chunk_count: u32, column_count: u16, stat_kind: u8 × column_count,
+    /// per chunk: row_offset: u32, per column: null_count: u32, [min, max: 8 bytes each]
+    /// ```
+    ///
+    /// Offsets are from the start of the section data. Columns follow the
+    /// schema; min/max are present for columns whose kind is not
+    /// [`STAT_NONE`] (see [`stat_kinds`]) and are `MAX`/`MIN` (`+inf`/`-inf`
+    /// for floats) when the chunk has no values in that column. Returns the
+    /// offset of the chunk index.
+    fn append_chunk_index(&self, data: &mut Vec<u8>, rows: &[Value], offsets: impl Iterator<Item = usize>, schema: &Schema) -> u32 {
+        let kinds = stat_kinds(schema, &self.sealed_fields(schema));
+        let mut chunks = Vec::new();
+        for (chunk, offset) in rows.chunks(self.chunk_rows).zip(offsets) {
+            let mut stats = Vec::new();
+            for (field, &kind) in schema.fields.iter().zip(&kinds) {
+                let values: Vec<&Value> = chunk.iter()
+                    .filter_map(|row| row.get(&field.name))
+                    .filter(|v| !v.is_null())
+                    .collect();
+                stats.extend(((chunk.len() - values.len()) as u32).to_le_bytes());
+                encode_chunk_min_max(&mut stats, kind, &values);
+            }
+            chunks.push((offset as u32, stats));
+        }
+
+        let chunk_index = data.len() as u32;
+        data.extend((self.chunk_rows as u32).to_le_bytes());
+        data.extend((chunks.len() as u32).to_le_bytes());
+        data.extend((kinds.len() as u16).to_le_bytes());
+        data.extend(&kinds);
+        for (offset, stats) in chunks {
+            data.extend(offset.to_le_bytes());
+            data.extend(stats);
+        }
+        chunk_index
+    }
+
+    /// Add a table section from bytes previously produced by the encoder, as
+    /// returned by [`Reader::raw_section`](crate::Reader::raw_section).
+    ///
+    /// Only the table header (row count, schema index and bitmap size) is
+    /// checked against `schema`; the rows are copied as-is. The strings,
+    /// schemas and unions they reference must be at the same indices in this
+    /// writer as in the file they came from, which holds for a writer created
+    /// with [`from_reader`](Self::from_reader) on that file.
+    ///
+    /// Files older than format 2.1 tag union values by name, while this
+    /// writer declares every union as tagged by ordinal. Tables from such a
+    /// file whose schema reaches a union are refused; decode them and use
+    /// [`add_section`](Self::add_section) instead.
+    pub fn add_raw_section(&mut self, key: &str, schema: &Schema, encoded: &[u8]) -> Result<()> {
+        let invalid = |msg: String| crate::Error::ParseError(format!("raw section '{}': {}", key, msg));
+        if encoded.len() < 8 {
+            return Err(invalid(format!("{} bytes is too short for a table header", encoded.len())));
+        }
+        let count = u32::from_le_bytes(encoded[0..4].try_into().unwrap());
+        let si = u16::from_le_bytes(encoded[4..6].try_into().unwrap());
+        let bitmap_size = u16::from_le_bytes(encoded[6..8].try_into().unwrap()) as usize;
+        if count as usize > MAX_ARRAY_LENGTH {
+            return Err(invalid(format!("{} rows exceeds maximum {}", count, MAX_ARRAY_LENGTH)));
+        }
+        match self.schema_map.get(&schema.name) {
+            Some(&idx) if idx == si => {}
+            Some(&idx) => return Err(invalid(format!(
+                "encoded for schema index {}, but '{}' is index {} here", si, schema.name, idx
+            ))),
+            None => return Err(invalid(format!("schema '{}' is not registered", schema.name))),
+        }
+        let expected = 2 * schema.fields.len().div_ceil(8);
+        if bitmap_size != expected || self.schemas[si as usize].fields.len() != schema.fields.len() {
+            return Err(invalid(format!(
+                "bitmap size {} does not match the {} fields of '{}'", bitmap_size, schema.fields.len(), schema.name
+            )));
+        }
+        if let Some(union) = self.union_tagged_by_name(schema, &mut HashSet::new()) {
+            return Err(invalid(format!(
+                "union '{}' comes from a file that tags its values by name; add the section with add_section instead", union
+            )));
+        }
+        self.intern(key);
+        self.push_section(Section {
+            key: key.to_string(),
+            data: encoded.to_vec(),
+            schema_idx: si as i32,
+            tl_type: TLType::Struct,
+            is_array: true,
+            item_count: count,
+            chunk_index: None,
+            lookup_index: None,
+            continues: false,
+            column_stats: false,
+            descriptor: None,
+        });
+        Ok(())
+    }
+
+    /// A union tagged by name that `schema` uses, directly or through nested
+    /// structs.
+    fn union_tagged_by_name<'a>(&'a self, schema: &'a Schema, seen: &mut HashSet<&'a str>) -> Option<&'a str> {
+        if !seen.insert(&schema.name) {
+            return None;
+        }
+        schema.fields.iter().find_map(|f| {
+            let base = f.field_type.base.as_str();
+            if self.tagged_by_name.contains(base) {
+                return Some(base);
+            }
+            let nested = self.schema_map.get(base).map(|&i| &self.schemas[i as usize])?;
+            self.union_tagged_by_name(nested, seen)
+        })
+    }
+
+    fn push_section(&mut self, section: Section) {
+        self.encoded_size += section.data.len() as u64;
+        self.sections.push(section);
+    }
+
+    /// Encoded size of the sections added so far, before compression.
+    pub(crate) fn encoded_size(&self) -> u64 {
+        self.encoded_size
+    }
+
+    /// Write the binary file to `path`.
+    ///
+    /// The file is written and synced under a temporary name in the same
+    /// directory, then renamed over `path`, so a crash mid-write leaves the
+    /// previous file (or none) rather than a truncated one.
     pub fn write<P: AsRef<Path>>(&self, path: P, compress: bool) -> Result<()> {
-        let file = File::create(path)?;
-        let mut w = BufWriter::new(file);
+        let path = path.as_ref();
+        let _lock = if self.locking { Some(lock_file(path)?) } else { None };
+        replace_file(path, |file| self.write_to(file, compress))
+    }
+
+    /// The bytes [`write`](Self::write) would write to a file, for
+    /// sending or storing a document without going through the file system.
+    pub fn to_bytes(&self, compress: bool) -> Result<Vec<u8>> {
+        Ok(self.write_to(Cursor::new(Vec::new()), compress)?.into_inner())
+    }
+
+    fn write_to<W: Write + Seek>(&self, out: W, compress: bool) -> Result<W> {
+        let mut w = BufWriter::new(out);
         w.write_all(&[0u8; HEADER_SIZE])?;
 
         let str_off = HEADER_SIZE as u64;
         self.write_string_table(&mut w)?;
         let sch_off = str_off + self.string_table_size() as u64;
         self.write_schema_table(&mut w)?;
-        let idx_off = sch_off + self.schema_table_size() as u64;
+        // Encrypted values follow the schema table
+        let encrypted = self.encrypted_value_table()?;
+        w.write_all(&encrypted)?;
+        let idx_off = sch_off + (self.schema_table_size() + encrypted.len()) as u64;
         let index_size = 8 + self.sections.len() * 32;
         w.write_all(&vec![0u8; index_size])?;
         let data_off = idx_off + index_size as u64;
 
         let mut entries = Vec::new();
         let mut cur_off = data_off;
+        let compress_section = |data: &[u8]| -> Result<(Vec<u8>, bool)> {
+            if compress && data.len() > 64 {
+                let c = compress_data(data)?;
+                if c.len() < (data.len() as f64 * 0.9) as usize { return Ok((c, true)); }
+            }
+            Ok((data.to_vec(), false))
+        };
         for sec in &self.sections {
-            let (written, compressed) = if compress && sec.data.len() > 64 {
-                let c = compress_data(&sec.data)?;
-                if c.len() < (sec.data.len() as f64 * 0.9) as usize { (c, true) } else { (sec.data.clone(), false) }
-            } else { (sec.data.clone(), false) };
+            let codec = match self.section_codecs.get(&sec.key) {
+                Some(&id) => Some(self.codecs.get(&(id, None))
+                    .ok_or_else(|| crate::Error::MissingCodec { section: sec.key.clone(), codec: id })?),
+                None => None,
+            };
+            let mut chunk_index = sec.chunk_index;
+            let (mut written, compressed, size) = match codec {
+                // A codec's output is stored after its ID, then compressed like any section data
+                Some(codec) => {
+                    let mut data = codec.id().to_le_bytes().to_vec();
+                    data.extend(codec.encode(&sec.data)?);
+                    let (written, compressed) = compress_section(&data)?;
+                    (written, compressed, data.len())
+                }
+                // A chunked table compresses chunk by chunk, so chunks can be read alone
+                None if compress && sec.chunk_index.is_some() => {
+                    let (data, index) = compress_chunks(&sec.data, sec.chunk_index.unwrap_or_default() as usize, sec.column_stats)?;
+                    if data.len() < (sec.data.len() as f64 * 0.9) as usize {
+                        chunk_index = Some(index);
+                        (data, true, sec.data.len())
+                    } else {
+                        (sec.data.clone(), false, sec.data.len())
+                    }
+                }
+                None => {
+                    let (written, compressed) = compress_section(&sec.data)?;
+                    (written, compressed, sec.data.len())
+                }
+            };
+            // The descriptor goes first, uncompressed, so it can be read on its
+            // own. A codec may be encrypting the section, so its field names
+            // are not stored in the clear next to it
+            let descriptor = sec.descriptor.as_ref().filter(|_| codec.is_none());
+            if let Some(descriptor) = descriptor {
+                let mut prefixed = Vec::with_capacity(4 + descriptor.len() + written.len());
+                prefixed.extend((descriptor.len() as u32).to_le_bytes());
+                prefixed.extend_from_slice(descriptor);
+                prefixed.extend(written);
+                written = prefixed;
+            }
+            // Section sizes are u32 in the index; never let `as u32` wrap them
+            if size > u32::MAX as usize || written.len() > u32::MAX as usize {
+                return Err(crate::Error::ValueOutOfRange(
+                    format!("Section '{}' size {} exceeds u32::MAX", sec.key, size.max(written.len()))));
+            }
             w.write_all(&written)?;
-            entries.push((self.string_map[&sec.key], cur_off, written.len() as u32, sec.data.len() as u32, sec.schema_idx, sec.tl_type, compressed, sec.is_array, sec.item_count));
+            let flags = SectionFlags {
+                compressed,
+                is_array: sec.is_array,
+                continues: sec.continues,
+                codec: codec.is_some(),
+                column_stats: sec.column_stats,
+                described: descriptor.is_some(),
+            };
+            entries.push((self.string_map[&sec.key], cur_off, written.len() as u32, size as u32, sec.schema_idx, sec.tl_type, flags, sec.item_count, chunk_index, sec.lookup_index));
             cur_off += written.len() as u64;
         }
 
@@ -121,10 +783,13 @@ impl Writer {
         w.write_all(&MAGIC)?;
         w.write_all(&VERSION_MAJOR.to_le_bytes())?;
         w.write_all(&VERSION_MINOR.to_le_bytes())?;
-        // Flags: bit 0 = compressed, bit 1 = root_array
+        // Flags: bit 0 = compressed, bit 1 = root_array, bit 2 = encrypted values,
+        // bit 3 = root_primitive
         let mut flags: u32 = 0;
         if compress { flags |= 0x01; }
         if self.is_root_array { flags |= 0x02; }
+        if !encrypted.is_empty() { flags |= 0x04; }
+        if self.is_root_primitive { flags |= 0x08; }
         w.write_all(&flags.to_le_bytes())?;
         w.write_all(&0u32.to_le_bytes())?;
         w.write_all(&str_off.to_le_bytes())?;
@@ -139,19 +804,62 @@ impl Writer {
         w.seek(SeekFrom::Start(idx_off))?;
         w.write_all(&(index_size as u32).to_le_bytes())?;
         w.write_all(&(entries.len() as u32).to_le_bytes())?;
-        for (ki, off, sz, usz, si, pt, comp, arr, cnt) in entries {
+        for (ki, off, sz, usz, si, pt, flags, cnt, chunk_index, lookup_index) in entries {
             w.write_all(&ki.to_le_bytes())?;
             w.write_all(&off.to_le_bytes())?;
             w.write_all(&sz.to_le_bytes())?;
             w.write_all(&usz.to_le_bytes())?;
             w.write_all(&(if si < 0 { 0xFFFFu16 } else { si as u16 }).to_le_bytes())?;
             w.write_all(&[pt as u8])?;
-            w.write_all(&[(if comp { 1 } else { 0 }) | (if arr { 2 } else { 0 })])?;
+            w.write_all(&[(if flags.compressed { 1 } else { 0 }) | (if flags.is_array { 2 } else { 0 }) | (if chunk_index.is_some() { 4 } else { 0 })
+                | (if flags.continues { 8 } else { 0 }) | (if flags.codec { 0x10 } else { 0 }) | (if flags.column_stats { 0x20 } else { 0 })
+                | (if flags.described { 0x80 } else { 0 })])?;
             w.write_all(&cnt.to_le_bytes())?;
-            w.write_all(&[0u8; 4])?;
+            // An unchunked section uses the chunk index offset for its lookup index
+            w.write_all(&chunk_index.or(lookup_index).unwrap_or(0).to_le_bytes())?;
         }
-        w.flush()?;
-        Ok(())
+        w.into_inner().map_err(|e| e.into_error().into())
+    }
+
+    /// The encrypted value table, empty if no field is encrypted:
+    ///
+    /// ```text
+    /// table_size: u32, count: u32, offset: u32 × count, values
+    /// ```
+    ///
+    /// Offsets are from the start of the values; each value runs to the next
+    /// one, or to the end of the table, and is a key header followed by the
+    /// codec's output (see [`seal`](crate::codec::seal)).
+    fn encrypted_value_table(&self) -> Result<Vec<u8>> {
+        if self.sealed.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut offsets = Vec::with_capacity(self.sealed.len());
+        let mut values = Vec::new();
+        for sealed in &self.sealed {
+            offsets.push(values.len());
+            match sealed {
+                Sealed::Plain { field, codec, key_id, data } => {
+                    let codec_impl = self.codecs.get(&(*codec, Some(key_id.clone())))
+                        .ok_or_else(|| crate::Error::MissingKey { field: field.clone(), codec: *codec, key_id: key_id.clone() })?;
+                    values.extend(crate::codec::seal(codec_impl.as_ref(), data)?);
+                }
+                Sealed::Stored(bytes) => values.extend_from_slice(bytes),
+            }
+        }
+        let table_size = 8 + offsets.len() * 4 + values.len();
+        if table_size > u32::MAX as usize {
+            return Err(crate::Error::ValueOutOfRange(
+                format!("Encrypted value table size {} exceeds u32::MAX", table_size)));
+        }
+        let mut table = Vec::with_capacity(table_size);
+        table.extend((table_size as u32).to_le_bytes());
+        table.extend((offsets.len() as u32).to_le_bytes());
+        for offset in offsets {
+            table.extend((offset as u32).to_le_bytes());
+        }
+        table.extend(values);
+        Ok(table)
     }
 
     fn string_table_size(&self) -> usize {
@@ -223,13 +931,15 @@ impl Writer {
                 } else {
                     f.field_type.to_tl_type()
                 };
-                struct_data.push(resolved_tl_type as u8);
+                let is_any = self.is_any_type(&f.field_type.base);
+                struct_data.push(if is_any { TYPE_CODE_ANY } else { resolved_tl_type as u8 });
                 let mut flags: u8 = 0;
                 if f.field_type.nullable { flags |= 0x01; }
                 if f.field_type.is_array { flags |= 0x02; }
+                if self.field_keys.get(&schema.name).is_some_and(|keys| keys.contains_key(&f.name)) { flags |= 0x04; }
                 struct_data.push(flags);
                 // Store struct/union type name string index (0xFFFF = no type)
-                if resolved_tl_type == TLType::Struct {
+                if resolved_tl_type == TLType::Struct || is_any {
                     let type_name_idx = self.string_map.get(&f.field_type.base)
                         .copied()
                         .map(|i| i as u16)
@@ -257,9 +967,13 @@ impl Writer {
             o
         }).collect();
         for union in &self.unions {
+            if union.variants.len() > MAX_UNION_VARIANTS {
+                return Err(crate::Error::ValueOutOfRange(format!(
+                    "Union '{}' has {} variants, exceeds maximum {}", union.name, union.variants.len(), MAX_UNION_VARIANTS)));
+            }
             union_data.extend_from_slice(&self.string_map[&union.name].to_le_bytes());
             union_data.extend_from_slice(&(union.variants.len() as u16).to_le_bytes());
-            union_data.extend_from_slice(&0u16.to_le_bytes()); // flags (reserved)
+            union_data.extend_from_slice(&UNION_FLAG_ORDINAL_TAGS.to_le_bytes());
             for variant in &union.variants {
                 union_data.extend_from_slice(&self.string_map[&variant.name].to_le_bytes());
                 union_data.extend_from_slice(&(variant.fields.len() as u16).to_le_bytes());
@@ -309,14 +1023,16 @@ impl Writer {
             Value::Int(i) => Ok(encode_int(*i)),
             Value::UInt(u) => Ok(encode_uint(*u)),
             Value::Float(f) => Ok((f.to_le_bytes().to_vec(), TLType::Float64, false, 0)),
-            Value::String(s) => { let idx = self.intern(s); Ok((idx.to_le_bytes().to_vec(), TLType::String, false, 0)) }
+            Value::String(s) => { let idx = self.intern_value(s); Ok((idx.to_le_bytes().to_vec(), TLType::String, false, 0)) }
+            // Stored as they are; a `secret` field wraps them again on read
+            Value::Secret(s) => self.encode_value(&s.expose_value(), schema),
             Value::Bytes(b) => { let mut buf = Vec::new(); write_varint(&mut buf, b.len() as u64); buf.extend(b); Ok((buf, TLType::Bytes, false, 0)) }
-            Value::Array(arr) => self.encode_array(arr, schema),
+            Value::Array(arr) => self.encode_array(arr, schema, &mut Progress::new()),
             Value::Object(obj) => self.encode_object(obj),
             Value::Map(pairs) => self.encode_map(pairs),
-            Value::Ref(r) => { let idx = self.intern(r); Ok((idx.to_le_bytes().to_vec(), TLType::Ref, false, 0)) }
+            Value::Ref(r) => { let idx = self.intern_value(r); Ok((idx.to_le_bytes().to_vec(), TLType::Ref, false, 0)) }
             Value::Tagged(tag, inner) => {
-                let ti = self.intern(tag);
+                let ti = self.intern_value(tag);
                 let (d, t, _, _) = self.encode_value(inner, None)?;
                 let mut buf = ti.to_le_bytes().to_vec();
                 buf.push(t as u8);
@@ -328,22 +1044,22 @@ impl Writer {
                 buf.extend(tz.to_le_bytes());
                 Ok((buf, TLType::Timestamp, false, 0))
             }
-            Value::JsonNumber(s) => { let idx = self.intern(s); Ok((idx.to_le_bytes().to_vec(), TLType::JsonNumber, false, 0)) }
+            Value::JsonNumber(s) => { let idx = self.intern_value(s); Ok((idx.to_le_bytes().to_vec(), TLType::JsonNumber, false, 0)) }
+            Value::TimestampNanos(ns, tz, precision) => Ok((encode_timestamp_nanos(*ns, *tz, *precision), TLType::TimestampNanos, false, 0)),
         }
     }
 
     fn encode_map(&mut self, pairs: &[(Value, Value)]) -> Result<(Vec<u8>, TLType, bool, u32)> {
         let mut buf = (pairs.len() as u32).to_le_bytes().to_vec();
-        for (k, v) in pairs {
+        for (i, (k, v)) in pairs.iter().enumerate() {
+            self.path.push(PathStep::Index(i));
             // Validate map keys per spec: map_key = string | name | integer
-            match k {
-                Value::String(_) | Value::Int(_) | Value::UInt(_) => {}
-                _ => return Err(crate::Error::ParseError(
-                    format!("Invalid map key type {:?}: map keys must be string, int, or uint per spec", k.tl_type())
-                )),
+            if !matches!(k, Value::String(_) | Value::Int(_) | Value::UInt(_)) {
+                self.unencodable(format!("map key is {}; map keys must be string, int or uint", value_kind(k)));
             }
             let (kd, kt, _, _) = self.encode_value(k, None)?;
             let (vd, vt, _, _) = self.encode_value(v, None)?;
+            self.path.pop();
             buf.push(kt as u8);
             buf.extend(kd);
             buf.push(vt as u8);
@@ -352,15 +1068,18 @@ impl Writer {
         Ok((buf, TLType::Map, false, pairs.len() as u32))
     }
 
-    fn encode_array(&mut self, arr: &[Value], schema: Option<&Schema>) -> Result<(Vec<u8>, TLType, bool, u32)> {
+    /// Encode an array, asking `progress` whether to stop as elements are
+    /// encoded one by one.
+    fn encode_array(&mut self, arr: &[Value], schema: Option<&Schema>, progress: &mut Progress) -> Result<(Vec<u8>, TLType, bool, u32)> {
         if arr.len() > MAX_ARRAY_LENGTH {
-            return Err(crate::Error::ValueOutOfRange(
-                format!("Array has {} elements, exceeds maximum {}", arr.len(), MAX_ARRAY_LENGTH)));
+            self.unencodable(format!("array has {} elements, exceeds maximum {}", arr.len(), MAX_ARRAY_LENGTH));
         }
         let mut buf = (arr.len() as u32).to_le_bytes().to_vec();
         if arr.is_empty() { return Ok((buf, TLType::Array, true, 0)); }
-        if schema.is_some() && arr.iter().all(|v| matches!(v, Value::Object(_) | Value::Null)) {
-            return self.encode_struct_array(arr, schema.unwrap());
+        if let Some(schema) = schema {
+            if arr.iter().all(|v| matches!(v, Value::Object(_) | Value::Null)) {
+                return self.encode_struct_array(arr, 0, schema, progress);
+            }
         }
         // Spec-conformant homogeneous encoding: only Int32 and String for top-level arrays.
         // All other types (UInt, Bool, Float, Timestamp, Int64) use heterogeneous 0xFF encoding.
@@ -378,23 +1097,53 @@ impl Writer {
         }
         if arr.iter().all(|v| matches!(v, Value::String(_))) {
             buf.push(TLType::String as u8);
-            for v in arr { if let Value::String(s) = v { buf.extend(self.intern(s).to_le_bytes()); } }
+            for (i, v) in arr.iter().enumerate() {
+                if let Value::String(s) = v {
+                    self.path.push(PathStep::Index(i));
+                    buf.extend(self.intern_value(s).to_le_bytes());
+                    self.path.pop();
+                }
+            }
             return Ok((buf, TLType::Array, true, arr.len() as u32));
         }
         buf.push(0xFF);
-        for v in arr { let (d, t, _, _) = self.encode_value(v, None)?; buf.push(t as u8); buf.extend(d); }
+        for (i, v) in arr.iter().enumerate() {
+            progress.tick()?;
+            self.path.push(PathStep::Index(i));
+            let (d, t, _, _) = self.encode_value(v, None)?;
+            self.path.pop();
+            buf.push(t as u8);
+            buf.extend(d);
+        }
         Ok((buf, TLType::Array, true, arr.len() as u32))
     }
 
-    fn encode_struct_array(&mut self, arr: &[Value], schema: &Schema) -> Result<(Vec<u8>, TLType, bool, u32)> {
-        let mut buf = (arr.len() as u32).to_le_bytes().to_vec();
+    /// Encode a table: its header, then its rows; `first` is the position of
+    /// the first row in the whole table.
+    fn encode_struct_array(&mut self, arr: &[Value], first: usize, schema: &Schema, progress: &mut Progress) -> Result<(Vec<u8>, TLType, bool, u32)> {
+        let mut buf = self.table_header(arr.len(), schema);
+        self.encode_struct_rows(&mut buf, arr, first, schema, arr.len().max(1), progress)?;
+        Ok((buf, TLType::Struct, true, arr.len() as u32))
+    }
+
+    /// The header of a table of `rows` rows of `schema` (row count, schema
+    /// index and bitmap size), registering `schema` if needed.
+    fn table_header(&mut self, rows: usize, schema: &Schema) -> Vec<u8> {
+        let mut buf = (rows as u32).to_le_bytes().to_vec();
         let si = match self.schema_map.get(&schema.name) {
             Some(&idx) => idx,
             None => self.add_schema(schema.clone()),
         };
         buf.extend(si.to_le_bytes());
-        let bms = (schema.fields.len() + 7) / 8;
-        buf.extend(((2 * bms) as u16).to_le_bytes());
+        buf.extend(((2 * schema.fields.len().div_ceil(8)) as u16).to_le_bytes());
+        buf
+    }
+
+    /// Append the rows `arr` of a table to `buf`; `first` is the position of
+    /// the first row in the whole table. Returns the end offset in `buf` of
+    /// every run of `unit` rows, the last run possibly shorter.
+    fn encode_struct_rows(&mut self, buf: &mut Vec<u8>, arr: &[Value], first: usize, schema: &Schema, unit: usize, progress: &mut Progress) -> Result<Vec<usize>> {
+        let bms = schema.fields.len().div_ceil(8);
         // Pre-build schema lookup to avoid O(n×m) linear scans per field per row.
         let nested_schemas: Vec<Option<Schema>> = schema.fields.iter()
             .map(|f| {
@@ -404,7 +1153,11 @@ impl Writer {
                     .cloned()
             })
             .collect();
-        for v in arr {
+        let names: Vec<u32> = schema.fields.iter().map(|f| self.intern(&f.name)).collect();
+        let sealed = self.sealed_fields(schema);
+        let mut ends = Vec::with_capacity(arr.len().div_ceil(unit));
+        for (row, v) in arr.iter().enumerate() {
+            progress.tick()?;
             if let Value::Object(obj) = v {
                 // Two-bit field state: 0=has value, 1=explicit null, 2=absent
                 let mut lo_bitmap = vec![0u8; bms];
@@ -427,7 +1180,13 @@ impl Writer {
                                  && (hi_bitmap[i / 8] & (1 << (i % 8))) == 0;
                     if has_value {
                         if let Some(v) = obj.get(&f.name) {
-                            let data = self.encode_typed_value(v, &f.field_type, nested_schemas[i].as_ref())?;
+                            self.path.extend([PathStep::Index(first + row), PathStep::Key(names[i])]);
+                            let data = if sealed[i] {
+                                self.seal_value(&schema.name, &f.name, v)?
+                            } else {
+                                self.encode_typed_value(v, &f.field_type, nested_schemas[i].as_ref())?
+                            };
+                            self.path.truncate(self.path.len() - 2);
                             buf.extend(data);
                         }
                     }
@@ -442,8 +1201,38 @@ impl Writer {
                 buf.extend_from_slice(&lo_bitmap);
                 buf.extend_from_slice(&hi_bitmap);
             }
+            if (row + 1) % unit == 0 || row + 1 == arr.len() {
+                ends.push(buf.len());
+            }
         }
-        Ok((buf, TLType::Struct, true, arr.len() as u32))
+        Ok(ends)
+    }
+
+    /// Add `value` of the encrypted `field` of `schema` to the encrypted
+    /// value table and return its position there, as stored in the row.
+    ///
+    /// The value is encoded without a schema, as the only section of a file
+    /// of its own, so that its strings stay out of this file's string table;
+    /// it is encrypted when the file is written.
+    fn seal_value(&mut self, schema: &str, field: &str, value: &Value) -> Result<Vec<u8>> {
+        let index = (self.sealed.len() as u32).to_le_bytes().to_vec();
+        let Some((codec, key_id)) = self.field_keys.get(schema).and_then(|keys| keys.get(field)).cloned().flatten() else {
+            self.unencodable(format!("field {}.{} is encrypted and has no key; set one with set_field_key", schema, field));
+            return Ok(index);
+        };
+        let mut plain = Writer::new();
+        match plain.add_section("", value, None) {
+            Ok(()) => {}
+            Err(crate::Error::Unencodable { values, .. }) => {
+                let path = self.current_path();
+                self.unencodable.extend(values.into_iter()
+                    .map(|v| Unencodable { path: format!("{}{}", path, v.path), reason: v.reason }));
+                return Ok(index);
+            }
+            Err(e) => return Err(e),
+        }
+        self.sealed.push(Sealed::Plain { field: format!("{}.{}", schema, field), codec, key_id, data: plain.to_bytes(true)? });
+        Ok(index)
     }
 
     /// Encode a value according to a specific field type (schema-aware encoding)
@@ -453,6 +1242,9 @@ impl Writer {
         // Handle arrays
         if field_type.is_array {
             if let Value::Array(arr) = value {
+                if arr.len() > MAX_ARRAY_LENGTH {
+                    self.unencodable(format!("array has {} elements, exceeds maximum {}", arr.len(), MAX_ARRAY_LENGTH));
+                }
                 let mut buf = (arr.len() as u32).to_le_bytes().to_vec();
                 if arr.is_empty() { return Ok(buf); }
 
@@ -470,13 +1262,14 @@ impl Writer {
                     .and_then(|idx| self.schemas.get(*idx as usize))
                     .cloned();
 
-                // If elem type resolves to Struct but no schema exists (e.g., "any"
-                // pseudo-type from JSON inference), use heterogeneous encoding —
-                // the reader can't decode struct format without a schema.
-                if elem_tl_type == TLType::Struct && elem_schema.is_none() {
-                    buf.push(0xFF);
-                    for v in arr {
+                // Any-typed elements (e.g. the "any" pseudo-type from JSON
+                // inference) use heterogeneous encoding with a type code per element.
+                if self.is_any_type(&field_type.base) {
+                    buf.push(TYPE_CODE_ANY);
+                    for (i, v) in arr.iter().enumerate() {
+                        self.path.push(PathStep::Index(i));
                         let (d, t, _, _) = self.encode_value(v, None)?;
+                        self.path.pop();
                         buf.push(t as u8);
                         buf.extend(d);
                     }
@@ -487,17 +1280,31 @@ impl Writer {
                 buf.push(elem_tl_type as u8);
 
                 // Encode each element with proper type
-                for v in arr {
+                for (i, v) in arr.iter().enumerate() {
+                    self.path.push(PathStep::Index(i));
                     buf.extend(self.encode_typed_value(v, &elem_type, elem_schema.as_ref())?);
+                    self.path.pop();
                 }
                 return Ok(buf);
             }
             // Non-array value for array-typed field: encode as zero-length array
             // to maintain stream alignment (empty vec would corrupt subsequent fields)
+            self.warn_coercion(field_type, value);
             return Ok((0u32).to_le_bytes().to_vec());
         }
 
+        // Any-typed field: the value carries its own type code
+        if self.is_any_type(&field_type.base) {
+            let (d, t, _, _) = self.encode_value(value, None)?;
+            let mut buf = vec![t as u8];
+            buf.extend(d);
+            return Ok(buf);
+        }
+
         let tl_type = field_type.to_tl_type();
+        if !fits_field_type(value, tl_type) {
+            self.warn_coercion(field_type, value);
+        }
         match tl_type {
             TLType::Null => Ok(vec![]),
             TLType::Bool => {
@@ -545,8 +1352,12 @@ impl Writer {
                 Ok(f.to_le_bytes().to_vec())
             }
             TLType::String => {
-                if let Value::String(s) = value { Ok(self.intern(s).to_le_bytes().to_vec()) }
-                else { Ok(self.intern("").to_le_bytes().to_vec()) }
+                let s = match value {
+                    Value::String(s) => s.as_str(),
+                    Value::Secret(s) => s.expose_str().unwrap_or(""),
+                    _ => "",
+                };
+                Ok(self.intern_value(s).to_le_bytes().to_vec())
             }
             TLType::Bytes => {
                 if let Value::Bytes(b) = value {
@@ -557,7 +1368,10 @@ impl Writer {
                 } else { Ok(vec![0]) }
             }
             TLType::Timestamp => {
-                if let Value::Timestamp(ts, tz) = value {
+                if let Some((ts, tz)) = value.as_timestamp() {
+                    if matches!(value, Value::TimestampNanos(ns, _, _) if ns.rem_euclid(1_000_000) != 0) {
+                        self.warn_coercion(field_type, value);
+                    }
                     let mut buf = ts.to_le_bytes().to_vec();
                     buf.extend(tz.to_le_bytes());
                     Ok(buf)
@@ -567,11 +1381,23 @@ impl Writer {
                     Ok(buf)
                 }
             }
+            TLType::TimestampNanos => {
+                let (ns, tz, precision) = match value.as_timestamp_nanos() {
+                    Some(nanos) => nanos,
+                    None => {
+                        if matches!(value, Value::Timestamp(..)) {
+                            self.unencodable("timestamp outside the timestamp_ns range of years 1677 to 2262".to_string());
+                        }
+                        // Other values were reported as coerced above
+                        (0, 0, TimePrecision::Nanos)
+                    }
+                };
+                Ok(encode_timestamp_nanos(ns, tz, precision))
+            }
             TLType::Struct => {
                 // Check if this is actually a union type resolved at encoding time
-                if self.union_map.contains_key(&field_type.base) {
-                    let (d, _, _, _) = self.encode_value(value, None)?;
-                    return Ok(d);
+                if let Some(&union_idx) = self.union_map.get(&field_type.base) {
+                    return self.encode_union_value(union_idx, value);
                 }
                 // Nested struct - encode recursively
                 if let (Value::Object(obj), Some(schema)) = (value, nested_schema) {
@@ -581,7 +1407,7 @@ impl Writer {
                     let schema_idx = *self.schema_map.get(&schema.name).unwrap_or(&0);
                     buf.extend(schema_idx.to_le_bytes());
 
-                    let bms = (schema.fields.len() + 7) / 8;
+                    let bms = schema.fields.len().div_ceil(8);
 
                     // Two-bit field state: 0=has value, 1=explicit null, 2=absent
                     let mut lo_bitmap = vec![0u8; bms];
@@ -601,6 +1427,7 @@ impl Writer {
                     buf.extend_from_slice(&hi_bitmap);
 
                     // Fields
+                    let sealed = self.sealed_fields(schema);
                     for (i, f) in schema.fields.iter().enumerate() {
                         let has_value = (lo_bitmap[i / 8] & (1 << (i % 8))) == 0
                                      && (hi_bitmap[i / 8] & (1 << (i % 8))) == 0;
@@ -610,18 +1437,29 @@ impl Writer {
                                     .get(&f.field_type.base)
                                     .and_then(|idx| self.schemas.get(*idx as usize))
                                     .cloned();
-                                buf.extend(self.encode_typed_value(v, &f.field_type, nested.as_ref())?);
+                                let name = self.intern(&f.name);
+                                self.path.push(PathStep::Key(name));
+                                if sealed[i] {
+                                    buf.extend(self.seal_value(&schema.name, &f.name, v)?);
+                                } else {
+                                    buf.extend(self.encode_typed_value(v, &f.field_type, nested.as_ref())?);
+                                }
+                                self.path.pop();
                             }
                         }
                     }
                     Ok(buf)
                 } else {
-                    // No schema found — fall back to generic encoding
-                    // (e.g., 'any' pseudo-type from JSON schema inference)
+                    // Not an object — fall back to generic encoding
                     let (d, _, _, _) = self.encode_value(value, None)?;
                     Ok(d)
                 }
             }
+            TLType::Map => match &*crate::lookup::as_map_field(value) {
+                Value::Map(pairs) => Ok(self.encode_map(pairs)?.0),
+                // Any other value would not decode as a map; write an empty one
+                _ => Ok(0u32.to_le_bytes().to_vec()),
+            },
             _ => {
                 // Fallback to generic encoding
                 let (d, _, _, _) = self.encode_value(value, None)?;
@@ -630,15 +1468,50 @@ impl Writer {
         }
     }
 
+    /// Encode a value of a union-typed schema field, tagged by variant ordinal.
+    ///
+    /// Layout: `[ordinal: u16][type: u8][payload]`. Tags that are not variants
+    /// of the union use ordinal `0xFFFF` followed by the tag's string index;
+    /// untagged values use `0xFFFE` and decode as the bare value.
+    fn encode_union_value(&mut self, union_idx: u16, value: &Value) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let inner = match value {
+            Value::Tagged(tag, inner) => {
+                match self.unions[union_idx as usize].variant_index(tag) {
+                    Some(ordinal) => buf.extend((ordinal as u16).to_le_bytes()),
+                    None => {
+                        buf.extend(0xFFFFu16.to_le_bytes());
+                        buf.extend(self.intern_value(tag).to_le_bytes());
+                    }
+                }
+                inner.as_ref()
+            }
+            _ => {
+                buf.extend(0xFFFEu16.to_le_bytes());
+                value
+            }
+        };
+        let (d, t, _, _) = self.encode_value(inner, None)?;
+        buf.push(t as u8);
+        buf.extend(d);
+        Ok(buf)
+    }
+
     fn encode_object(&mut self, obj: &ObjectMap<String, Value>) -> Result<(Vec<u8>, TLType, bool, u32)> {
         if obj.len() > MAX_OBJECT_FIELDS {
-            return Err(crate::Error::ValueOutOfRange(
-                format!("Object has {} fields, exceeds maximum {}", obj.len(), MAX_OBJECT_FIELDS)));
+            self.unencodable(format!("object has {} fields, exceeds maximum {}", obj.len(), MAX_OBJECT_FIELDS));
         }
         let mut buf = (obj.len() as u16).to_le_bytes().to_vec();
         for (k, v) in obj {
-            buf.extend(self.intern(k).to_le_bytes());
+            if k.len() > MAX_STRING_LENGTH {
+                self.unencodable(format!("key of {} bytes exceeds maximum {}", k.len(), MAX_STRING_LENGTH));
+                continue;
+            }
+            let ki = self.intern(k);
+            buf.extend(ki.to_le_bytes());
+            self.path.push(PathStep::Key(ki));
             let (d, t, _, _) = self.encode_value(v, None)?;
+            self.path.pop();
             buf.push(t as u8);
             buf.extend(d);
         }
@@ -648,6 +1521,265 @@ impl Writer {
 
 impl Default for Writer { fn default() -> Self { Self::new() } }
 
+// =============================================================================
+// Size Estimation
+// =============================================================================
+
+/// Computes the size of the file a [`Writer`] with no options set would write
+/// for a set of sections, by walking the values without encoding them. Each
+/// method mirrors the `encode_*` method of the same name and must be kept in
+/// step with it. Values the writer would reject as unencodable are sized as
+/// if it took them.
+pub(crate) struct SizeEstimator<'a> {
+    strings: HashSet<&'a str>,
+    schemas: HashMap<&'a str, &'a Schema>,
+    unions: HashMap<&'a str, &'a Union>,
+    /// Size of the schema and union entries of the schema table
+    definitions_size: usize,
+    sections: usize,
+    data_size: usize,
+}
+
+impl<'a> SizeEstimator<'a> {
+    pub(crate) fn new() -> Self {
+        Self {
+            strings: HashSet::new(),
+            schemas: HashMap::new(),
+            unions: HashMap::new(),
+            definitions_size: 0,
+            sections: 0,
+            data_size: 0,
+        }
+    }
+
+    pub(crate) fn add_schema(&mut self, schema: &'a Schema) {
+        if self.schemas.contains_key(schema.name.as_str()) { return; }
+        for field in &schema.fields {
+            self.strings.insert(&field.name);
+            if FieldType::new(&field.field_type.base).to_tl_type() == TLType::Struct {
+                self.strings.insert(&field.field_type.base);
+            }
+        }
+        self.strings.insert(&schema.name);
+        self.schemas.insert(&schema.name, schema);
+        self.definitions_size += 4 + 8 + schema.fields.len() * 8;
+    }
+
+    pub(crate) fn add_union(&mut self, union: &'a Union) {
+        if self.unions.contains_key(union.name.as_str()) { return; }
+        self.strings.insert(&union.name);
+        for variant in &union.variants {
+            self.strings.insert(&variant.name);
+            for field in &variant.fields {
+                self.strings.insert(&field.name);
+            }
+        }
+        self.unions.insert(&union.name, union);
+        self.definitions_size += 4 + 8 + union.variants.iter().map(|v| 8 + v.fields.len() * 8).sum::<usize>();
+    }
+
+    pub(crate) fn add_section(&mut self, key: &'a str, value: &'a Value, schema: Option<&'a Schema>) {
+        self.strings.insert(key);
+        self.data_size += self.value_len(value, schema);
+        if let (Some(schema), Value::Array(rows)) = (schema, value) {
+            if is_table(rows) && *schema == crate::i18n::translation_schema() {
+                let entries = crate::i18n::index_entries(rows);
+                self.data_size += 4 + entries.len() * 12;
+                for ((key, locale), text) in entries {
+                    self.strings.extend([key, locale, text]);
+                }
+            }
+        }
+        self.sections += 1;
+    }
+
+    /// Total file size: header, string table, schema table, section index and data.
+    pub(crate) fn total(&self) -> usize {
+        let string_table = 8 + self.strings.len() * 8 + self.strings.iter().map(|s| s.len()).sum::<usize>();
+        let schema_table = if self.schemas.is_empty() && self.unions.is_empty() { 8 } else { 8 + self.definitions_size };
+        let index = 8 + self.sections * 32;
+        HEADER_SIZE + string_table + schema_table + index + self.data_size
+    }
+
+    fn is_any_type(&self, base: &str) -> bool {
+        FieldType::new(base).to_tl_type() == TLType::Struct
+            && !self.schemas.contains_key(base)
+            && !self.unions.contains_key(base)
+    }
+
+    fn string_len(&mut self, s: &'a str) -> usize {
+        self.strings.insert(s);
+        4
+    }
+
+    fn value_len(&mut self, value: &'a Value, schema: Option<&'a Schema>) -> usize {
+        match value {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Int(i) => int_len(*i),
+            Value::UInt(u) => uint_len(*u),
+            Value::Float(_) => 8,
+            Value::String(s) | Value::Ref(s) | Value::JsonNumber(s) => self.string_len(s),
+            Value::Secret(s) => match s.expose_str() {
+                Some(text) => self.string_len(text),
+                None => bytes_len(s.expose_bytes()),
+            },
+            Value::Bytes(b) => bytes_len(b),
+            Value::Array(arr) => self.array_len(arr, schema),
+            Value::Object(obj) => self.object_len(obj),
+            Value::Map(pairs) => self.map_len(pairs),
+            Value::Tagged(tag, inner) => self.string_len(tag) + 1 + self.value_len(inner, None),
+            Value::Timestamp(_, _) => 10,
+            Value::TimestampNanos(_, _, _) => 11,
+        }
+    }
+
+    fn map_len(&mut self, pairs: &'a [(Value, Value)]) -> usize {
+        4 + pairs.iter().map(|(k, v)| 2 + self.value_len(k, None) + self.value_len(v, None)).sum::<usize>()
+    }
+
+    fn array_len(&mut self, arr: &'a [Value], schema: Option<&'a Schema>) -> usize {
+        if arr.is_empty() { return 4; }
+        if let Some(schema) = schema.filter(|_| is_table(arr)) {
+            return self.struct_array_len(arr, schema);
+        }
+        if arr.iter().all(|v| matches!(v, Value::Int(i) if i32::try_from(*i).is_ok())) {
+            return 5 + arr.len() * 4;
+        }
+        if arr.iter().all(|v| matches!(v, Value::String(_))) {
+            return 5 + arr.iter().map(|v| self.value_len(v, None)).sum::<usize>();
+        }
+        5 + arr.iter().map(|v| 1 + self.value_len(v, None)).sum::<usize>()
+    }
+
+    fn struct_array_len(&mut self, arr: &'a [Value], schema: &'a Schema) -> usize {
+        self.add_schema(schema);
+        let mut len = 8 + arr.len() * 2 * schema.fields.len().div_ceil(8);
+        for v in arr {
+            if let Value::Object(obj) = v {
+                len += self.present_fields_len(obj, schema);
+            }
+        }
+        len
+    }
+
+    /// Encoded size of the non-null fields of a struct (bitmaps excluded).
+    fn present_fields_len(&mut self, obj: &'a ObjectMap<String, Value>, schema: &'a Schema) -> usize {
+        let mut len = 0;
+        for f in &schema.fields {
+            if let Some(v) = obj.get(&f.name).filter(|v| !v.is_null()) {
+                let nested = self.schemas.get(f.field_type.base.as_str()).copied();
+                len += self.typed_value_len(v, &f.field_type, nested);
+            }
+        }
+        len
+    }
+
+    fn typed_value_len(&mut self, value: &'a Value, field_type: &FieldType, nested_schema: Option<&'a Schema>) -> usize {
+        if field_type.is_array {
+            let Value::Array(arr) = value else { return 4 };
+            if arr.is_empty() { return 4; }
+            if self.is_any_type(&field_type.base) {
+                return 5 + arr.iter().map(|v| 1 + self.value_len(v, None)).sum::<usize>();
+            }
+            let elem_type = FieldType::new(&field_type.base);
+            let elem_schema = self.schemas.get(field_type.base.as_str()).copied();
+            return 5 + arr.iter().map(|v| self.typed_value_len(v, &elem_type, elem_schema)).sum::<usize>();
+        }
+
+        if self.is_any_type(&field_type.base) {
+            return 1 + self.value_len(value, None);
+        }
+
+        match field_type.to_tl_type() {
+            TLType::Null => 0,
+            TLType::Bool | TLType::Int8 | TLType::UInt8 => 1,
+            TLType::Int16 | TLType::UInt16 => 2,
+            TLType::Int32 | TLType::UInt32 | TLType::Float32 => 4,
+            TLType::Int64 | TLType::UInt64 | TLType::Float64 => 8,
+            TLType::String => self.string_len(match value {
+                Value::String(s) => s,
+                Value::Secret(s) => s.expose_str().unwrap_or(""),
+                _ => "",
+            }),
+            TLType::Bytes => match value {
+                Value::Bytes(b) => bytes_len(b),
+                _ => 1,
+            },
+            TLType::Timestamp => 10,
+            TLType::TimestampNanos => 11,
+            TLType::Struct => {
+                if let Some(union) = self.unions.get(field_type.base.as_str()).copied() {
+                    return self.union_value_len(union, value);
+                }
+                match (value, nested_schema) {
+                    (Value::Object(obj), Some(schema)) => {
+                        2 + 2 * schema.fields.len().div_ceil(8) + self.present_fields_len(obj, schema)
+                    }
+                    _ => self.value_len(value, None),
+                }
+            }
+            // Objects are written as maps, their integer keys as integers
+            TLType::Map => match value {
+                Value::Map(pairs) => self.map_len(pairs),
+                Value::Object(obj) => 4 + obj.iter().map(|(k, v)| {
+                    let key = match crate::lookup::map_key(k.clone()) {
+                        Value::Int(i) => int_len(i),
+                        _ => self.string_len(k),
+                    };
+                    2 + key + self.value_len(v, None)
+                }).sum::<usize>(),
+                _ => 4,
+            },
+            _ => self.value_len(value, None),
+        }
+    }
+
+    fn union_value_len(&mut self, union: &Union, value: &'a Value) -> usize {
+        let (tag_len, inner) = match value {
+            Value::Tagged(tag, inner) if union.variant_index(tag).is_some() => (0, &**inner),
+            Value::Tagged(tag, inner) => (self.string_len(tag), &**inner),
+            _ => (0, value),
+        };
+        3 + tag_len + self.value_len(inner, None)
+    }
+
+    fn object_len(&mut self, obj: &'a ObjectMap<String, Value>) -> usize {
+        2 + obj.iter().map(|(k, v)| self.string_len(k) + 1 + self.value_len(v, None)).sum::<usize>()
+    }
+}
+
+/// Whether `arr` can be written as a table: it is non-empty and holds only
+/// objects and nulls.
+fn is_table(arr: &[Value]) -> bool {
+    !arr.is_empty() && arr.iter().all(|v| matches!(v, Value::Object(_) | Value::Null))
+}
+
+fn int_len(i: i64) -> usize {
+    if i8::try_from(i).is_ok() { 1 } else if i16::try_from(i).is_ok() { 2 } else if i32::try_from(i).is_ok() { 4 } else { 8 }
+}
+
+fn uint_len(u: u64) -> usize {
+    if u8::try_from(u).is_ok() { 1 } else if u16::try_from(u).is_ok() { 2 } else if u32::try_from(u).is_ok() { 4 } else { 8 }
+}
+
+fn bytes_len(b: &[u8]) -> usize {
+    let mut len = 1;
+    let mut v = b.len() as u64;
+    while v >= 0x80 { v >>= 7; len += 1; }
+    len + b.len()
+}
+
+/// Flag bits of a section index entry, besides the chunked bit
+struct SectionFlags {
+    compressed: bool,
+    is_array: bool,
+    continues: bool,
+    codec: bool,
+    column_stats: bool,
+    described: bool,
+}
+
 /// Extract an integer value with best-effort coercion for schema-typed fields.
 /// Out-of-range and non-numeric values default to 0 (spec §2.5).
 fn checked_int_value(value: &Value, min: i64, max: i64, _type_name: &str) -> Result<i64> {
@@ -682,6 +1814,321 @@ fn checked_uint_value(value: &Value, max: u64, _type_name: &str) -> Result<u64>
     if u > max { Ok(0) } else { Ok(u) }
 }
 
+/// Whether `encode_typed_value` stores `value` as `tl_type` without coercion.
+/// Struct, union and other generically encoded types always fit.
+fn fits_field_type(value: &Value, tl_type: TLType) -> bool {
+    let int_in = |min: i128, max: i128| {
+        let i = match value {
+            Value::Int(i) => *i as i128,
+            Value::UInt(u) => *u as i128,
+            Value::Float(f) if f.is_finite() && f.fract() == 0.0 && f.abs() < 1e38 => *f as i128,
+            Value::JsonNumber(s) => match s.parse::<i128>() {
+                Ok(i) => i,
+                Err(_) => return false,
+            },
+            _ => return false,
+        };
+        i >= min && i <= max
+    };
+    match tl_type {
+        TLType::Bool => matches!(value, Value::Bool(_)),
+        TLType::Int8 => int_in(i8::MIN as i128, i8::MAX as i128),
+        TLType::Int16 => int_in(i16::MIN as i128, i16::MAX as i128),
+        TLType::Int32 => int_in(i32::MIN as i128, i32::MAX as i128),
+        TLType::Int64 => int_in(i64::MIN as i128, i64::MAX as i128),
+        TLType::UInt8 => int_in(0, u8::MAX as i128),
+        TLType::UInt16 => int_in(0, u16::MAX as i128),
+        TLType::UInt32 => int_in(0, u32::MAX as i128),
+        TLType::UInt64 => int_in(0, u64::MAX as i128),
+        TLType::Float32 | TLType::Float64 => {
+            matches!(value, Value::Float(_) | Value::Int(_) | Value::UInt(_))
+        }
+        TLType::String => match value {
+            Value::String(_) => true,
+            Value::Secret(s) => s.expose_str().is_some(),
+            _ => false,
+        },
+        TLType::Bytes => matches!(value, Value::Bytes(_)),
+        TLType::Timestamp => matches!(value, Value::Timestamp(..)),
+        // Timestamps outside the nanosecond range are unencodable, not coerced
+        TLType::TimestampNanos => matches!(value, Value::Timestamp(..) | Value::TimestampNanos(..)),
+        TLType::Map => matches!(value, Value::Map(_) | Value::Object(_)),
+        _ => true,
+    }
+}
+
+/// Chunk statistics kinds: null count only, or null count plus min/max
+/// stored as i64, u64, f64 or i64 Unix milliseconds.
+pub(crate) const STAT_NONE: u8 = 0;
+pub(crate) const STAT_INT: u8 = 1;
+pub(crate) const STAT_UINT: u8 = 2;
+pub(crate) const STAT_FLOAT: u8 = 3;
+pub(crate) const STAT_TIMESTAMP: u8 = 4;
+
+/// Which min/max statistics a table column gets in chunk indexes. Arrays and
+/// non-numeric types only get a null count.
+fn chunk_stat_kind(field: &crate::Field) -> u8 {
+    if field.field_type.is_array {
+        return STAT_NONE;
+    }
+    match field.field_type.to_tl_type() {
+        TLType::Int8 | TLType::Int16 | TLType::Int32 | TLType::Int64 => STAT_INT,
+        TLType::UInt8 | TLType::UInt16 | TLType::UInt32 | TLType::UInt64 => STAT_UINT,
+        TLType::Float32 | TLType::Float64 => STAT_FLOAT,
+        TLType::Timestamp | TLType::TimestampNanos => STAT_TIMESTAMP,
+        _ => STAT_NONE,
+    }
+}
+
+/// The [`chunk_stat_kind`] of each field of `schema`, or [`STAT_NONE`] for
+/// fields `sealed` marks as encrypted, whose min/max would give their
+/// values away.
+fn stat_kinds(schema: &Schema, sealed: &[bool]) -> Vec<u8> {
+    schema.fields.iter().zip(sealed)
+        .map(|(f, &sealed)| if sealed { STAT_NONE } else { chunk_stat_kind(f) })
+        .collect()
+}
+
+/// Append the min and max of the non-null `values` of one column of a chunk or table.
+/// Values that don't convert to the column's kind (and NaN) are left out.
+fn encode_chunk_min_max(out: &mut Vec<u8>, kind: u8, values: &[&Value]) {
+    match kind {
+        STAT_INT | STAT_TIMESTAMP => {
+            let (min, max) = values.iter()
+                .filter_map(|v| if kind == STAT_INT { v.as_int() } else { v.as_timestamp_millis() })
+                .fold((i64::MAX, i64::MIN), |(lo, hi), x| (lo.min(x), hi.max(x)));
+            out.extend(min.to_le_bytes());
+            out.extend(max.to_le_bytes());
+        }
+        STAT_UINT => {
+            let (min, max) = values.iter().filter_map(|v| v.as_uint())
+                .fold((u64::MAX, u64::MIN), |(lo, hi), x| (lo.min(x), hi.max(x)));
+            out.extend(min.to_le_bytes());
+            out.extend(max.to_le_bytes());
+        }
+        STAT_FLOAT => {
+            let (min, max) = values.iter().filter_map(|v| v.as_float()).filter(|x| !x.is_nan())
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), x| (lo.min(x), hi.max(x)));
+            out.extend(min.to_le_bytes());
+            out.extend(max.to_le_bytes());
+        }
+        _ => {}
+    }
+}
+
+/// Encode the column statistics of a whole table:
+///
+/// ```text
+/// column_count: u16, per column: stat_kind: u8, null_count: u32, distinct: u32, [min, max: 8 bytes each]
+/// ```
+///
+/// Columns, kinds and min/max are as in chunk indexes (see
+/// [`Writer::encode_chunked_table`]). Distinct values are counted by a
+/// 64-bit hash of each non-null value with a [`DistinctCounter`], except
+/// in columns `sealed` marks as encrypted, which report 0.
+fn encode_column_stats(rows: &[Value], schema: &Schema, sealed: &[bool]) -> Vec<u8> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::fmt::Write as _;
+    use std::hash::Hasher;
+
+    /// Feeds formatted text to a hasher without building a string
+    struct HashWriter(DefaultHasher);
+    impl std::fmt::Write for HashWriter {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            self.0.write(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    let mut out = Vec::with_capacity(column_stats_size(schema, sealed));
+    out.extend((schema.fields.len() as u16).to_le_bytes());
+    for ((field, kind), &sealed) in schema.fields.iter().zip(stat_kinds(schema, sealed)).zip(sealed) {
+        let values: Vec<&Value> = rows.iter()
+            .filter_map(|row| row.get(&field.name))
+            .filter(|v| !v.is_null())
+            .collect();
+        let mut distinct = DistinctCounter::new();
+        for v in values.iter().filter(|_| !sealed) {
+            let mut h = HashWriter(DefaultHasher::new());
+            let _ = write!(h, "{:?}", v);
+            distinct.insert(h.0.finish());
+        }
+        out.push(kind);
+        out.extend(((rows.len() - values.len()) as u32).to_le_bytes());
+        out.extend((distinct.count().min(u32::MAX as u64) as u32).to_le_bytes());
+        encode_chunk_min_max(&mut out, kind, &values);
+    }
+    out
+}
+
+/// Counts distinct 64-bit hashes: exactly up to [`EXACT`](Self::EXACT) of
+/// them, then with a HyperLogLog sketch of 2^[`BITS`](Self::BITS) one-byte
+/// registers (a standard error of about 1.6%), so that memory stays bounded
+/// however many values a column has.
+struct DistinctCounter {
+    exact: Option<HashSet<u64>>,
+    registers: Vec<u8>,
+}
+
+impl DistinctCounter {
+    const EXACT: usize = 4096;
+    const BITS: u32 = 12;
+
+    fn new() -> Self {
+        Self { exact: Some(HashSet::new()), registers: Vec::new() }
+    }
+
+    fn insert(&mut self, hash: u64) {
+        match &mut self.exact {
+            Some(set) if set.len() < Self::EXACT || set.contains(&hash) => {
+                set.insert(hash);
+            }
+            Some(_) => {
+                let set = self.exact.take().unwrap_or_default();
+                self.registers = vec![0; 1 << Self::BITS];
+                for h in set {
+                    self.add(h);
+                }
+                self.add(hash);
+            }
+            None => self.add(hash),
+        }
+    }
+
+    /// Record `hash` in the register its top bits select, as the position
+    /// of the first set bit among the rest.
+    fn add(&mut self, hash: u64) {
+        let register = (hash >> (64 - Self::BITS)) as usize;
+        let rank = ((hash << Self::BITS) | (1 << (Self::BITS - 1))).leading_zeros() + 1;
+        self.registers[register] = self.registers[register].max(rank as u8);
+    }
+
+    fn count(&self) -> u64 {
+        if let Some(set) = &self.exact {
+            return set.len() as u64;
+        }
+        let m = self.registers.len() as f64;
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let estimate = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        // Linear counting is more accurate while registers are still empty
+        let estimate = if estimate <= 2.5 * m && zeros > 0 { m * (m / zeros as f64).ln() } else { estimate };
+        estimate.round() as u64
+    }
+}
+
+/// Nesting of objects a structure descriptor describes; objects deeper
+/// down are listed with no fields.
+const MAX_DESCRIPTOR_DEPTH: usize = 8;
+
+/// Encode the structure descriptor of a free-form section, or `None` if its
+/// value is neither an object nor an array of objects:
+///
+/// ```text
+/// field_count: u32, per field: name_len: u32, name: UTF-8, type: u8, flags: u8, [fields of type OBJECT]
+/// ```
+///
+/// Fields are the keys of the objects in order of first appearance. The
+/// type is the type code every non-null value shares (`INT64`, `UINT64` and
+/// `FLOAT64` for numbers), `ANY` if they differ, or `NULL` if there are
+/// none; for arrays, the code every non-null element shares. Flag bit 0 marks
+/// fields missing or null in some object, bit 1 arrays. An `OBJECT` field is
+/// followed by the descriptor of its objects, or of the objects in its
+/// arrays.
+fn encode_descriptor(value: &Value) -> Option<Vec<u8>> {
+    let objects: Vec<&ObjectMap<String, Value>> = match value {
+        Value::Object(obj) => vec![obj],
+        Value::Array(items) if items.iter().any(|v| matches!(v, Value::Object(_))) => items.iter()
+            .filter_map(|v| match v {
+                Value::Object(obj) => Some(obj),
+                _ => None,
+            })
+            .collect(),
+        _ => return None,
+    };
+    let mut out = Vec::new();
+    describe_objects(&mut out, &objects, 0);
+    Some(out)
+}
+
+fn describe_objects(out: &mut Vec<u8>, objects: &[&ObjectMap<String, Value>], depth: usize) {
+    let mut fields: IndexMap<&str, Vec<&Value>> = IndexMap::new();
+    if depth < MAX_DESCRIPTOR_DEPTH {
+        for obj in objects {
+            for (k, v) in obj.iter() {
+                fields.entry(k.as_str()).or_default().push(v);
+            }
+        }
+    }
+    out.extend((fields.len() as u32).to_le_bytes());
+    for (name, values) in fields {
+        let present: Vec<&Value> = values.iter().copied().filter(|v| !v.is_null()).collect();
+        let is_array = !present.is_empty() && present.iter().all(|v| matches!(v, Value::Array(_)));
+        let items: Vec<&Value> = if is_array {
+            present.iter().flat_map(|v| v.as_array().unwrap_or_default()).filter(|v| !v.is_null()).collect()
+        } else {
+            present.clone()
+        };
+        let mut codes = items.iter().map(|v| descriptor_code(v));
+        let code = match codes.next() {
+            Some(first) if codes.all(|c| c == first) => first,
+            Some(_) => TYPE_CODE_ANY,
+            None => TLType::Null as u8,
+        };
+        out.extend((name.len() as u32).to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.push(code);
+        out.push((if present.len() < objects.len() { 0x01 } else { 0 }) | (if is_array { 0x02 } else { 0 }));
+        if code == TLType::Object as u8 {
+            let nested: Vec<&ObjectMap<String, Value>> = items.iter()
+                .filter_map(|v| match v {
+                    Value::Object(obj) => Some(obj),
+                    _ => None,
+                })
+                .collect();
+            describe_objects(out, &nested, depth + 1);
+        }
+    }
+}
+
+/// Type code of `value` in a structure descriptor.
+fn descriptor_code(value: &Value) -> u8 {
+    let tl_type = match value {
+        Value::Null => TLType::Null,
+        Value::Bool(_) => TLType::Bool,
+        Value::Int(_) => TLType::Int64,
+        Value::UInt(_) => TLType::UInt64,
+        Value::Float(_) => TLType::Float64,
+        Value::String(_) => TLType::String,
+        Value::Bytes(_) => TLType::Bytes,
+        Value::Array(_) => TLType::Array,
+        Value::Object(_) => TLType::Object,
+        Value::Map(_) => TLType::Map,
+        Value::Ref(_) => TLType::Ref,
+        Value::Tagged(..) => TLType::Tagged,
+        Value::Timestamp(..) => TLType::Timestamp,
+        Value::JsonNumber(_) => TLType::JsonNumber,
+        Value::TimestampNanos(..) => TLType::TimestampNanos,
+        Value::Secret(s) if s.expose_str().is_none() => TLType::Bytes,
+        Value::Secret(_) => TLType::String,
+    };
+    tl_type as u8
+}
+
+/// Size of a table's column statistics block, with its trailing offset.
+fn column_stats_size(schema: &Schema, sealed: &[bool]) -> usize {
+    let columns: usize = stat_kinds(schema, sealed).iter().map(|&k| if k == STAT_NONE { 9 } else { 25 }).sum();
+    2 + columns + 4
+}
+
+/// Unix nanoseconds (8 bytes), timezone offset (2 bytes), precision (1 byte).
+fn encode_timestamp_nanos(ns: i64, tz: i16, precision: TimePrecision) -> Vec<u8> {
+    let mut buf = ns.to_le_bytes().to_vec();
+    buf.extend(tz.to_le_bytes());
+    buf.push(precision.code());
+    buf
+}
+
 fn encode_int(i: i64) -> (Vec<u8>, TLType, bool, u32) {
     if i >= i8::MIN as i64 && i <= i8::MAX as i64 { ((i as i8).to_le_bytes().to_vec(), TLType::Int8, false, 0) }
     else if i >= i16::MIN as i64 && i <= i16::MAX as i64 { ((i as i16).to_le_bytes().to_vec(), TLType::Int16, false, 0) }
@@ -696,11 +2143,126 @@ fn encode_uint(u: u64) -> (Vec<u8>, TLType, bool, u32) {
     else { (u.to_le_bytes().to_vec(), TLType::UInt64, false, 0) }
 }
 
+/// A temporary path next to `path`, unique within this process.
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{}.{}-{}.tmp", name, std::process::id(), n))
+}
+
+/// Write `path` through `write` under a temporary name in the same
+/// directory, sync it, rename it over `path` and sync the directory, so a
+/// crash midway leaves the previous file (or none) rather than a truncated
+/// one, and a crash after it returns keeps the new file.
+pub(crate) fn replace_file(path: &Path, write: impl FnOnce(File) -> Result<File>) -> Result<()> {
+    let tmp = temp_path(path);
+    let result = File::create(&tmp)
+        .map_err(Into::into)
+        .and_then(write)
+        .and_then(|file| file.sync_all().map_err(Into::into))
+        .and_then(|()| fs::rename(&tmp, path).map_err(Into::into))
+        .and_then(|()| sync_parent(path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Sync the directory holding `path`, so that a rename into it survives a
+/// crash. Windows cannot open a directory for syncing, so this does nothing
+/// there.
+fn sync_parent(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Open `<path>.lock` and take an exclusive advisory lock on it, released when
+/// the returned file is dropped.
+pub(crate) fn lock_file(path: &Path) -> Result<File> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(lock_path)?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// What [`TeaLeaf::compile_lenient`](crate::TeaLeaf::compile_lenient) left
+/// out and coerced.
+#[derive(Debug, Clone, Default)]
+pub struct CompileReport {
+    /// Sections left out of the file, with every value in each that the
+    /// writer cannot encode
+    pub skipped: IndexMap<String, Vec<Unencodable>>,
+    /// Values in the written sections that were coerced to fit their schema
+    pub warnings: Vec<Warning>,
+}
+
+impl std::fmt::Display for CompileReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (section, values) in &self.skipped {
+            writeln!(f, "skipped '{}': {} unencodable value(s)", section, values.len())?;
+            for value in values {
+                writeln!(f, "  {}", value)?;
+            }
+        }
+        for warning in &self.warnings {
+            writeln!(f, "{}", warning)?;
+        }
+        Ok(())
+    }
+}
+
 fn write_varint(buf: &mut Vec<u8>, mut v: u64) {
     while v >= 0x80 { buf.push(((v & 0x7F) | 0x80) as u8); v >>= 7; }
     buf.push(v as u8);
 }
 
+/// Compress each chunk of a chunked table's `data` on its own, so that a
+/// reader can decompress one chunk without the others. The table header,
+/// chunk index and column statistics stay uncompressed, with the chunk row
+/// offsets and statistics offset moved to match. Returns the data and the
+/// new offset of the chunk index.
+fn compress_chunks(data: &[u8], chunk_index: usize, column_stats: bool) -> Result<(Vec<u8>, u32)> {
+    let u32_at = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+    let chunk_count = u32_at(chunk_index + 4);
+    let column_count = u16::from_le_bytes(data[chunk_index + 8..chunk_index + 10].try_into().unwrap()) as usize;
+    let kinds = &data[chunk_index + 10..chunk_index + 10 + column_count];
+    let entry_size = 4 + kinds.iter().map(|&k| if k == STAT_NONE { 4 } else { 20 }).sum::<usize>();
+    // Offset of the first chunk's entry within the chunk index
+    let entries = 10 + column_count;
+    let starts: Vec<usize> = (0..chunk_count).map(|i| u32_at(chunk_index + entries + i * entry_size)).collect();
+
+    let mut out = data[..8].to_vec();
+    let mut offsets = Vec::with_capacity(chunk_count);
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(chunk_index);
+        offsets.push(out.len() as u32);
+        out.extend(compress_data(&data[start..end])?);
+    }
+    let new_index = out.len();
+    out.extend_from_slice(&data[chunk_index..]);
+    for (i, offset) in offsets.into_iter().enumerate() {
+        let pos = new_index + entries + i * entry_size;
+        out[pos..pos + 4].copy_from_slice(&offset.to_le_bytes());
+    }
+    if column_stats {
+        let end = out.len() - 4;
+        let stats = u32_at(data.len() - 4) - chunk_index + new_index;
+        out[end..].copy_from_slice(&(stats as u32).to_le_bytes());
+    }
+    Ok((out, new_index as u32))
+}
+
 fn compress_data(data: &[u8]) -> Result<Vec<u8>> {
     use flate2::Compression;
     use flate2::write::ZlibEncoder;
@@ -799,8 +2361,8 @@ mod tests {
         obj.insert("med_uint".to_string(), Value::UInt(40000));
         obj.insert("uint32_val".to_string(), Value::UInt(3_000_000));
         obj.insert("uint64_val".to_string(), Value::UInt(9_000_000_000));
-        obj.insert("f32_val".to_string(), Value::Float(3.14));
-        obj.insert("f64_val".to_string(), Value::Float(2.718281828));
+        obj.insert("f32_val".to_string(), Value::Float(1.5));
+        obj.insert("f64_val".to_string(), Value::Float(1.234567891));
         obj.insert("name".to_string(), Value::String("test".into()));
         obj.insert("data".to_string(), Value::Bytes(vec![0xDE, 0xAD]));
 
@@ -1043,6 +2605,14 @@ mod tests {
         let items = events.as_array().unwrap();
         assert_eq!(items.len(), 1);
         std::fs::remove_file(&path).ok();
+
+        // Millisecond timestamps past 2262 do not fit a nanosecond field
+        let schema = Schema::new("trace").field("at", FieldType::new("timestamp_ns"));
+        let mut obj = ObjectMap::new();
+        obj.insert("at".to_string(), Value::Timestamp(10_000_000_000_000, 0));
+        let err = w.add_section("traces", &Value::Array(vec![Value::Object(obj)]), Some(&schema)).err().unwrap();
+        assert!(err.to_string().contains("traces[0].at: timestamp outside the timestamp_ns range"), "{}", err);
+        assert!(w.warnings().is_empty());
     }
 
     #[test]
@@ -1231,6 +2801,395 @@ mod tests {
         std::fs::remove_file(&path).ok();
     }
 
+    #[test]
+    fn test_use_registry_from_reader() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.tlbx");
+        let second = dir.path().join("second.tlbx");
+
+        let mut schema = Schema::new("user");
+        schema.add_field("id", FieldType::new("int"));
+        schema.add_field("name", FieldType::new("string"));
+        let mut w = Writer::new();
+        w.add_schema(schema);
+        w.add_union(crate::Union::new("flag").variant(crate::Variant::new("on")));
+        w.write(&first, false).unwrap();
+
+        let r = Reader::open(&first).unwrap();
+        assert_eq!(r.schemas_owned().keys().collect::<Vec<_>>(), vec!["user"]);
+        let registry = r.into_schema_registry();
+        assert!(registry.union("flag").is_some());
+
+        let mut user = ObjectMap::new();
+        user.insert("id".to_string(), Value::Int(7));
+        user.insert("name".to_string(), Value::String("ana".into()));
+        let users = Value::Array(vec![Value::Object(user)]);
+        let mut w = Writer::new();
+        w.use_registry(&registry);
+        w.add_section("users", &users, registry.schema("user")).unwrap();
+        w.write(&second, false).unwrap();
+
+        let r = Reader::open(&second).unwrap();
+        assert_eq!(r.unions.len(), 1);
+        assert_eq!(r.schemas[0].fields.len(), 2);
+        assert_eq!(r.get("users").unwrap(), users);
+    }
+
+    #[test]
+    fn test_raw_section_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source.tlbx");
+        let copy = dir.path().join("copy.tlbx");
+
+        let schema = Schema::new("point")
+            .field("x", FieldType::new("int"))
+            .field("label", FieldType::new("string").nullable());
+        let row = |x: i64, label: Option<&str>| {
+            let mut obj = ObjectMap::new();
+            obj.insert("x".to_string(), Value::Int(x));
+            obj.insert("label".to_string(), label.map_or(Value::Null, |l| Value::String(l.into())));
+            Value::Object(obj)
+        };
+        let points = Value::Array(vec![row(1, Some("a")), row(2, None), row(3, Some("c"))]);
+        let mut w = Writer::new();
+        w.add_section("points", &points, Some(&schema)).unwrap();
+        w.add_section("name", &Value::String("old".into()), None).unwrap();
+        w.write(&source, true).unwrap();
+
+        let r = Reader::open(&source).unwrap();
+        let (bytes, table_schema) = r.raw_section("points").unwrap();
+        let table_schema = table_schema.unwrap().clone();
+        assert!(r.raw_section("name").unwrap().1.is_none());
+
+        let mut w = Writer::from_reader(&r).unwrap();
+        w.add_raw_section("points", &table_schema, &bytes).unwrap();
+        w.add_section("name", &Value::String("new".into()), None).unwrap();
+        w.write(&copy, false).unwrap();
+
+        let r = Reader::open(&copy).unwrap();
+        assert_eq!(r.get("points").unwrap(), points);
+        assert_eq!(r.get("name").unwrap().as_str(), Some("new"));
+    }
+
+    #[test]
+    fn test_raw_section_copy_from_v2_0_file() {
+        // Written by the 2.0 writer from tests/fixtures/v2_0_unions.tl:
+        // union fields tagged by string index, not by ordinal
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/v2_0_unions.tlbx");
+        let r = Reader::open(&fixture).unwrap();
+        assert_eq!(r.union_flags, [0]);
+        let drawings = r.get("drawings").unwrap();
+        assert_eq!(drawings.get_path("[0].main"), Some(&Value::Tagged("rect".into(), Box::new(Value::Array(vec![Value::Int(2), Value::Int(3)])))));
+
+        let (bytes, schema) = r.raw_section("drawings").unwrap();
+        let schema = schema.unwrap().clone();
+        let mut w = Writer::from_reader(&r).unwrap();
+        let err = w.add_raw_section("drawings", &schema, &bytes).unwrap_err();
+        assert!(err.to_string().contains("union 'shape' comes from a file that tags its values by name"), "{}", err);
+
+        // Re-encoding the rows gives a file of the current format
+        w.add_section("drawings", &drawings, Some(&schema)).unwrap();
+        let copy = Reader::from_bytes(w.to_bytes(false).unwrap()).unwrap();
+        assert_eq!(copy.union_flags, [UNION_FLAG_ORDINAL_TAGS]);
+        assert_eq!(copy.get("drawings").unwrap(), drawings);
+    }
+
+    #[test]
+    fn test_raw_section_header_validation() {
+        let schema = Schema::new("point").field("x", FieldType::new("int"));
+        let other = Schema::new("other").field("a", FieldType::new("int"));
+        let mut w = Writer::new();
+        let (bytes, _, _, _) = w.encode_value(&Value::Array(vec![]), Some(&schema)).unwrap();
+        // An empty array carries no table header
+        assert!(w.add_raw_section("t", &schema, &bytes).is_err());
+
+        let mut obj = ObjectMap::new();
+        obj.insert("x".to_string(), Value::Int(1));
+        let (bytes, _, _, _) = w.encode_value(&Value::Array(vec![Value::Object(obj)]), Some(&schema)).unwrap();
+        assert!(w.add_raw_section("t", &other, &bytes).is_err());
+        w.add_schema(other.clone());
+        assert!(w.add_raw_section("t", &other, &bytes).is_err());
+        w.add_raw_section("t", &schema, &bytes).unwrap();
+        assert_eq!(w.sections.last().unwrap().item_count, 1);
+    }
+
+    #[test]
+    fn test_chunked_table_time_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.tlbx");
+        let schema = Schema::new("event")
+            .field("id", FieldType::new("int"))
+            .field("at", FieldType::new("timestamp").nullable())
+            .field("traced", FieldType::new("timestamp_ns"));
+        // One row per minute; row 5 has no `at`
+        let rows: Vec<Value> = (0..10i64).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("id".to_string(), Value::Int(i));
+            if i != 5 {
+                obj.insert("at".to_string(), Value::Timestamp(i * 60_000, 0));
+            }
+            obj.insert("traced".to_string(), Value::TimestampNanos(i * 60_000_000_000 + 1, 0, crate::TimePrecision::Nanos));
+            Value::Object(obj)
+        }).collect();
+        let table = Value::Array(rows);
+
+        let mut w = Writer::new();
+        w.set_chunk_rows(4);
+        w.add_section("events", &table, Some(&schema)).unwrap();
+        w.write(&path, true).unwrap();
+
+        let r = Reader::open(&path).unwrap();
+        assert_eq!(r.get("events").unwrap(), table);
+        let ids = |rows: Vec<Value>| rows.iter().map(|r| r.get("id").unwrap().as_int().unwrap()).collect::<Vec<_>>();
+        assert_eq!(ids(r.filter_table_by_time("events", "at", 3 * 60_000..7 * 60_000).unwrap()), vec![3, 4, 6]);
+        assert_eq!(ids(r.filter_table_by_time("events", "traced", 60_000..2 * 60_000 + 1).unwrap()), vec![1, 2]);
+        assert!(r.filter_table_by_time("events", "at", 20 * 60_000..30 * 60_000).unwrap().is_empty());
+        assert!(r.filter_table_by_time("events", "missing", 0..1).is_err());
+        let err = r.filter_table_by_time("events", "id", 0..1).err().unwrap();
+        assert!(err.to_string().contains("field 'id' of section 'events' is int, not a timestamp"), "{}", err);
+
+        let doc = crate::TeaLeaf::from_reader(&r).unwrap();
+        let expected: Vec<&Value> = doc.filter_table_by_time("events", "at", 3 * 60_000..7 * 60_000).unwrap();
+        assert_eq!(expected.len(), 3);
+
+        // The chunk index is not part of the raw table
+        let (bytes, _) = r.raw_section("events").unwrap();
+        let mut copy = Writer::from_reader(&r).unwrap();
+        copy.add_raw_section("events", &schema, &bytes).unwrap();
+        assert!(copy.sections[0].chunk_index.is_none());
+    }
+
+    #[test]
+    fn test_failed_section_lists_paths_and_is_not_added() {
+        let schema = Schema::new("entry")
+            .field("name", FieldType::new("string"))
+            .field("tags", FieldType::new("map"));
+        let rows: Vec<Value> = (0..6i64).map(|i| {
+            let mut obj = ObjectMap::new();
+            obj.insert("name".to_string(), Value::String(format!("entry{}", i)));
+            let key = if i == 5 { Value::Bool(true) } else { Value::Int(i) };
+            obj.insert("tags".to_string(), Value::Map(vec![(key, Value::Null)]));
+            Value::Object(obj)
+        }).collect();
+
+        let mut w = Writer::new();
+        w.set_chunk_rows(2);
+        w.add_section("first", &Value::Int(1), None).unwrap();
+        let strings = w.strings.len();
+        match w.add_section("entries", &Value::Array(rows), Some(&schema)) {
+            Err(crate::Error::Unencodable { section, values }) => {
+                assert_eq!(section, "entries");
+                assert_eq!(values, vec![Unencodable {
+                    path: "entries[5].tags[0]".to_string(),
+                    reason: "map key is bool; map keys must be string, int or uint".to_string(),
+                }]);
+            }
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+        assert_eq!(w.sections.len(), 1);
+        assert_eq!(w.strings.len(), strings);
+        assert!(w.schemas.is_empty());
+        assert!(!w.string_map.contains_key("entry0"));
+    }
+
+    #[test]
+    fn test_distinct_counter() {
+        use std::hash::{BuildHasher, RandomState};
+        let hasher = RandomState::new();
+        let mut counter = DistinctCounter::new();
+        for i in 0..DistinctCounter::EXACT as u64 {
+            counter.insert(hasher.hash_one(i));
+            counter.insert(hasher.hash_one(i));
+        }
+        assert_eq!(counter.count(), DistinctCounter::EXACT as u64);
+
+        for i in 0..100_000u64 {
+            counter.insert(hasher.hash_one(i));
+        }
+        assert!(counter.exact.is_none());
+        let error = (counter.count() as f64 - 100_000.0).abs() / 100_000.0;
+        assert!(error < 0.08, "estimate {} is off by {:.1}%", counter.count(), error * 100.0);
+    }
+
+    #[test]
+    fn test_write_replaces_file_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.tlbx");
+
+        let mut w = Writer::new();
+        w.add_section("v", &Value::Int(1), None).unwrap();
+        w.write(&path, false).unwrap();
+        let old = Reader::open_mmap(&path).unwrap();
+
+        let mut w = Writer::new();
+        w.add_section("v", &Value::Int(2), None).unwrap();
+        w.set_locking(true);
+        w.write(&path, false).unwrap();
+
+        // The mapped reader still sees the file it opened
+        #[cfg(unix)]
+        assert_eq!(old.get("v").unwrap(), Value::Int(1));
+        drop(old);
+        assert_eq!(Reader::open(&path).unwrap().get("v").unwrap(), Value::Int(2));
+
+        let mut names: Vec<_> = std::fs::read_dir(dir.path()).unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["data.tlbx", "data.tlbx.lock"]);
+    }
+
+    #[test]
+    fn test_failed_write_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing").join("data.tlbx");
+        let mut w = Writer::new();
+        w.add_section("v", &Value::Int(1), None).unwrap();
+        assert!(w.write(&path, false).is_err());
+        assert!(std::fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_locked_writers_take_turns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shared.tlbx");
+        let handles: Vec<_> = (0..4).map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let mut w = Writer::new();
+                w.set_locking(true);
+                w.add_section("n", &Value::Int(i), None).unwrap();
+                w.write(&path, true).unwrap();
+            })
+        }).collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        let n = Reader::open(&path).unwrap().get("n").unwrap().as_int().unwrap();
+        assert!((0..4).contains(&n));
+    }
+
+    #[test]
+    fn test_any_typed_fields_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("any.tlbx");
+
+        let mut schema = Schema::new("event");
+        schema.add_field("id", FieldType::new("int"));
+        schema.add_field("meta", FieldType::new("any"));
+        schema.add_field("vals", FieldType::new("any").array());
+        let mut nested = ObjectMap::new();
+        nested.insert("k".to_string(), Value::Bool(true));
+        let metas = [Value::Int(5), Value::String("x".into()), Value::Object(nested), Value::Array(vec![Value::Float(1.5)])];
+        let rows: Vec<Value> = metas.iter().enumerate().map(|(i, meta)| {
+            let mut obj = ObjectMap::new();
+            obj.insert("id".to_string(), Value::Int(i as i64));
+            obj.insert("meta".to_string(), meta.clone());
+            obj.insert("vals".to_string(), Value::Array(vec![Value::Int(1), Value::String("two".into())]));
+            Value::Object(obj)
+        }).collect();
+        let events = Value::Array(rows);
+
+        let mut w = Writer::new();
+        w.add_schema(schema.clone());
+        w.add_section("events", &events, Some(&schema)).unwrap();
+        assert!(w.warnings().is_empty());
+        assert_eq!(w.any_fields(), vec![("event", "meta"), ("event", "vals")]);
+        w.write(&path, false).unwrap();
+
+        let r = Reader::open(&path).unwrap();
+        assert_eq!(r.any_fields(), vec![("event", "meta"), ("event", "vals")]);
+        assert_eq!(r.schemas[0].fields[1].field_type.base, "any");
+        assert_eq!(r.get("events").unwrap(), events);
+
+        // A 2.x reader cannot read the type code, so 2.x files may not use it
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[4..8].copy_from_slice(&[2, 0, 1, 0]);
+        let err = Reader::from_bytes(bytes).err().unwrap();
+        assert!(err.to_string().contains("schema 'event' field 'meta' is any-typed, which version 2.1 files cannot hold"), "{}", err);
+    }
+
+    #[test]
+    fn test_union_field_ordinal_tags_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ordinals.tlbx");
+
+        let mut w = Writer::new();
+        let mut union_def = crate::Union::new("Shape");
+        union_def.add_variant(crate::Variant::new("Circle").field("r", FieldType::new("int32")));
+        union_def.add_variant(crate::Variant::new("Dot"));
+        w.add_union(union_def);
+
+        let mut schema = Schema::new("Drawing");
+        schema.add_field("main", FieldType::new("Shape"));
+        schema.add_field("all", FieldType::new("Shape").array());
+
+        let circle = Value::Tagged("Circle".into(), Box::new(Value::Int(3)));
+        let dot = Value::Tagged("Dot".into(), Box::new(Value::Null));
+        let other = Value::Tagged("Square".into(), Box::new(Value::Int(2)));
+        let mut obj = ObjectMap::new();
+        obj.insert("main".to_string(), dot.clone());
+        obj.insert("all".to_string(), Value::Array(vec![circle.clone(), other.clone(), Value::Int(7), dot.clone()]));
+        let arr = Value::Array(vec![Value::Object(obj)]);
+        w.add_section("drawings", &arr, Some(&schema)).unwrap();
+        w.write(&path, false).unwrap();
+
+        let r = Reader::open(&path).unwrap();
+        let drawing = r.get("drawings").unwrap().as_array().unwrap()[0].clone();
+        assert_eq!(drawing.get("main"), Some(&dot));
+        assert_eq!(
+            drawing.get("all"),
+            Some(&Value::Array(vec![circle, other, Value::Int(7), dot])),
+        );
+    }
+
+    #[test]
+    fn test_union_field_ordinal_smaller_than_tag_index() {
+        // u16 ordinal per value instead of a u32 string index
+        let mut w = Writer::new();
+        let mut union_def = crate::Union::new("Flag");
+        union_def.add_variant(crate::Variant::new("On"));
+        w.add_union(union_def);
+        let mut schema = Schema::new("Row");
+        schema.add_field("f", FieldType::new("Flag"));
+        let mut obj = ObjectMap::new();
+        obj.insert("f".to_string(), Value::Tagged("On".into(), Box::new(Value::Null)));
+
+        let data = w.encode_typed_value(obj.get("f").unwrap(), &schema.fields[0].field_type, None).unwrap();
+        assert_eq!(data, vec![0, 0, TLType::Null as u8]);
+        let (generic, _, _, _) = w.encode_value(obj.get("f").unwrap(), None).unwrap();
+        assert_eq!(generic.len(), 5);
+    }
+
+    #[test]
+    fn test_union_ordinals_stay_clear_of_sentinels() {
+        let mut w = Writer::new();
+        let mut union_def = crate::Union::new("Wide");
+        for i in 0..=MAX_UNION_VARIANTS {
+            union_def.add_variant(crate::Variant::new(format!("v{}", i)));
+        }
+        w.add_union(union_def);
+        let err = w.write(tempfile::tempdir().unwrap().path().join("wide.tlbx"), false).unwrap_err();
+        assert!(err.to_string().contains("65535 variants, exceeds maximum 65534"), "{}", err);
+
+        // Readers refuse union flags they do not know
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flags.tlbx");
+        let mut w = Writer::new();
+        let mut union_def = crate::Union::new("Flag");
+        union_def.add_variant(crate::Variant::new("On"));
+        w.add_union(union_def);
+        w.write(&path, false).unwrap();
+        let mut bytes = std::fs::read(&path).unwrap();
+        // Schema region header (8), one union offset (4), then name (4) and variant count (2)
+        let flags_at = u64::from_le_bytes(bytes[24..32].try_into().unwrap()) as usize + 18;
+        assert_eq!(bytes[flags_at..flags_at + 2], UNION_FLAG_ORDINAL_TAGS.to_le_bytes());
+        assert!(Reader::from_bytes(bytes.clone()).is_ok());
+        bytes[flags_at] = 0x03;
+        let err = Reader::from_bytes(bytes).err().unwrap();
+        assert!(err.to_string().contains("union 'Flag' has unknown flags 0x0003"), "{}", err);
+    }
+
     // =========================================================================
     // Issue 7: Deterministic serialization (sorted object keys)
     // =========================================================================
@@ -1251,4 +3210,119 @@ mod tests {
 
         assert_eq!(bytes1, bytes2, "Object encoding should be deterministic");
     }
+
+    // Golden vectors: the exact bytes of small files, written out by hand so
+    // that a host of either byte order or pointer width must produce and read
+    // the same little-endian layout.
+
+    /// Sections `i: -2`, `u: 0x01020304`, `f: 1.5`, `s: "hi"`.
+    const GOLDEN_SCALARS: &[u8] = &[
+        // Header: magic, version 3.0, flags, reserved
+        b'T', b'L', b'B', b'X', 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // String, schema, index and data offsets (u64)
+        0x40, 0, 0, 0, 0, 0, 0, 0, 0x76, 0, 0, 0, 0, 0, 0, 0,
+        0x7e, 0, 0, 0, 0, 0, 0, 0, 0x06, 0x01, 0, 0, 0, 0, 0, 0,
+        // String, schema and section counts, reserved
+        0x05, 0, 0, 0, 0x00, 0, 0, 0, 0x04, 0, 0, 0, 0x00, 0, 0, 0,
+        // String table: size, count, offsets, lengths, "iufshi"
+        0x36, 0, 0, 0, 0x05, 0, 0, 0,
+        0x00, 0, 0, 0, 0x01, 0, 0, 0, 0x02, 0, 0, 0, 0x03, 0, 0, 0, 0x04, 0, 0, 0,
+        0x01, 0, 0, 0, 0x01, 0, 0, 0, 0x01, 0, 0, 0, 0x01, 0, 0, 0, 0x02, 0, 0, 0,
+        b'i', b'u', b'f', b's', b'h', b'i',
+        // Schema table: size, no structs, no unions
+        0x08, 0, 0, 0, 0x00, 0x00, 0x00, 0x00,
+        // Section index: size, count
+        0x88, 0, 0, 0, 0x04, 0, 0, 0,
+        // key, offset (u64), size, uncompressed size, schema, type, flags, item count, chunk index
+        0x00, 0, 0, 0, 0x06, 0x01, 0, 0, 0, 0, 0, 0, 0x01, 0, 0, 0, 0x01, 0, 0, 0, 0xff, 0xff, 0x02, 0x00, 0, 0, 0, 0, 0, 0, 0, 0,
+        0x01, 0, 0, 0, 0x07, 0x01, 0, 0, 0, 0, 0, 0, 0x04, 0, 0, 0, 0x04, 0, 0, 0, 0xff, 0xff, 0x08, 0x00, 0, 0, 0, 0, 0, 0, 0, 0,
+        0x02, 0, 0, 0, 0x0b, 0x01, 0, 0, 0, 0, 0, 0, 0x08, 0, 0, 0, 0x08, 0, 0, 0, 0xff, 0xff, 0x0b, 0x00, 0, 0, 0, 0, 0, 0, 0, 0,
+        0x03, 0, 0, 0, 0x13, 0x01, 0, 0, 0, 0, 0, 0, 0x04, 0, 0, 0, 0x04, 0, 0, 0, 0xff, 0xff, 0x10, 0x00, 0, 0, 0, 0, 0, 0, 0, 0,
+        // Data: int8 -2, uint32, float64 1.5, string index 4
+        0xfe,
+        0x04, 0x03, 0x02, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf8, 0x3f,
+        0x04, 0x00, 0x00, 0x00,
+    ];
+
+    /// `@struct p (x: int16, n: string?)` and `ps: @table p [(-2, a), (515, ~)]`.
+    const GOLDEN_TABLE: &[u8] = &[
+        b'T', b'L', b'B', b'X', 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x40, 0, 0, 0, 0, 0, 0, 0, 0x76, 0, 0, 0, 0, 0, 0, 0,
+        0x9a, 0, 0, 0, 0, 0, 0, 0, 0xc2, 0, 0, 0, 0, 0, 0, 0,
+        0x05, 0, 0, 0, 0x01, 0, 0, 0, 0x01, 0, 0, 0, 0x00, 0, 0, 0,
+        // String table: "x", "n", "p", "ps", "a"
+        0x36, 0, 0, 0, 0x05, 0, 0, 0,
+        0x00, 0, 0, 0, 0x01, 0, 0, 0, 0x02, 0, 0, 0, 0x03, 0, 0, 0, 0x05, 0, 0, 0,
+        0x01, 0, 0, 0, 0x01, 0, 0, 0, 0x01, 0, 0, 0, 0x02, 0, 0, 0, 0x01, 0, 0, 0,
+        b'x', b'n', b'p', b'p', b's', b'a',
+        // Schema table: size, one struct, no unions, struct offsets
+        0x24, 0, 0, 0, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0, 0, 0,
+        // Struct p: name, field count, flags; fields: name, type, flags, extra
+        0x02, 0, 0, 0, 0x02, 0x00, 0x00, 0x00,
+        0x00, 0, 0, 0, 0x03, 0x00, 0xff, 0xff,
+        0x01, 0, 0, 0, 0x10, 0x01, 0xff, 0xff,
+        // Section index
+        0x28, 0, 0, 0, 0x01, 0, 0, 0,
+        0x03, 0, 0, 0, 0xc2, 0, 0, 0, 0, 0, 0, 0, 0x14, 0, 0, 0, 0x14, 0, 0, 0, 0x00, 0x00, 0x22, 0x02, 0x02, 0, 0, 0, 0, 0, 0, 0,
+        // Data: row count, schema, bitmap size, then per row the null bitmap and fields
+        0x02, 0, 0, 0, 0x00, 0x00, 0x02, 0x00,
+        0x00, 0x00, 0xfe, 0xff, 0x04, 0x00, 0x00, 0x00,
+        0x02, 0x00, 0x03, 0x02,
+    ];
+
+    fn written_bytes(w: &Writer) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("golden.tlbx");
+        w.write(&path, false).unwrap();
+        std::fs::read(&path).unwrap()
+    }
+
+    #[test]
+    fn test_golden_scalars() {
+        let mut w = Writer::new();
+        w.add_section("i", &Value::Int(-2), None).unwrap();
+        w.add_section("u", &Value::UInt(0x0102_0304), None).unwrap();
+        w.add_section("f", &Value::Float(1.5), None).unwrap();
+        w.add_section("s", &Value::String("hi".into()), None).unwrap();
+        assert_eq!(written_bytes(&w), GOLDEN_SCALARS);
+
+        let r = Reader::from_bytes(GOLDEN_SCALARS.to_vec()).unwrap();
+        assert_eq!(r.get("i").unwrap(), Value::Int(-2));
+        assert_eq!(r.get("u").unwrap(), Value::UInt(0x0102_0304));
+        assert_eq!(r.get("f").unwrap(), Value::Float(1.5));
+        assert_eq!(r.get("s").unwrap(), Value::String("hi".into()));
+    }
+
+    #[test]
+    fn test_golden_table() {
+        let mut schema = Schema::new("p");
+        schema.add_field("x", FieldType::new("int16"));
+        schema.add_field("n", FieldType::new("string").nullable());
+        let row = |x: i64, n: Value| {
+            let mut obj = ObjectMap::new();
+            obj.insert("x".to_string(), Value::Int(x));
+            obj.insert("n".to_string(), n);
+            Value::Object(obj)
+        };
+        let rows = Value::Array(vec![row(-2, Value::String("a".into())), row(515, Value::Null)]);
+
+        let mut w = Writer::new();
+        w.add_schema(schema.clone());
+        w.add_section("ps", &rows, Some(&schema)).unwrap();
+        assert_eq!(written_bytes(&w), GOLDEN_TABLE);
+
+        let r = Reader::from_bytes(GOLDEN_TABLE.to_vec()).unwrap();
+        assert_eq!(r.schemas, vec![schema]);
+        assert_eq!(r.get("ps").unwrap(), rows);
+    }
+
+    #[test]
+    fn test_offsets_beyond_address_space_are_rejected() {
+        // A section offset that only fits in 64 bits must not wrap on narrower hosts
+        let mut bytes = GOLDEN_SCALARS.to_vec();
+        bytes[0x8a..0x92].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(Reader::from_bytes(bytes).is_err());
+    }
 }
@@ -143,6 +143,20 @@ fn value_to_json(value: &tealeaf::Value) -> serde_json::Value {
             };
             serde_json::Value::String(iso)
         }
+        tealeaf::Value::TimestampNanos(ns, tz, precision) => {
+            // Format the whole seconds as above, then write exactly `precision` digits
+            let secs = value_to_json(&tealeaf::Value::Timestamp(ns.div_euclid(1_000_000_000) * 1000, *tz));
+            let secs = secs.as_str().unwrap();
+            let digits = precision.fraction_digits() as usize;
+            let fraction = format!("{:09}", ns.rem_euclid(1_000_000_000));
+            let iso = if digits == 0 {
+                secs.to_string()
+            } else {
+                format!("{}.{}{}", &secs[..19], &fraction[..digits], &secs[19..])
+            };
+            serde_json::Value::String(iso)
+        }
+        tealeaf::Value::Secret(_) => serde_json::Value::String(tealeaf::secret::REDACTED.into()),
     }
 }
 
@@ -780,7 +794,7 @@ fn json_numeric_equal(a: &serde_json::Value, b: &serde_json::Value) -> bool {
         }
         (serde_json::Value::Object(a), serde_json::Value::Object(b)) => {
             a.len() == b.len() && a.iter().all(|(k, v)| {
-                b.get(k).map_or(false, |bv| json_numeric_equal(v, bv))
+                b.get(k).is_some_and(|bv| json_numeric_equal(v, bv))
             })
         }
         _ => a == b,
@@ -1014,3 +1028,67 @@ fn error_invalid_json() {
     let msg = get_error_message(result);
     assert!(msg.contains("JSON") || msg.contains("parse"), "Error message should indicate JSON parse error: {}", msg);
 }
+
+// =============================================================================
+// Size Estimation
+// =============================================================================
+
+#[test]
+fn canonical_size_estimates_match_output() {
+    use tealeaf::FormatOptions;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut entries: Vec<_> = std::fs::read_dir(samples_dir()).unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "tl"))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        let doc = TeaLeaf::load(&path).unwrap();
+
+        for opts in [FormatOptions::pretty(), FormatOptions::compact(), FormatOptions::compact().with_compact_floats()] {
+            assert_eq!(doc.estimated_text_size(&opts), doc.to_tl_with_options(&opts).len(),
+                "{}: text estimate mismatch for {:?}", name, opts);
+        }
+
+        let raw = dir.path().join(format!("{}.tlbx", name));
+        doc.compile(&raw, false).unwrap();
+        assert_eq!(doc.estimated_binary_size(false), std::fs::metadata(&raw).unwrap().len() as usize,
+            "{}: binary estimate mismatch", name);
+
+        let compressed = dir.path().join(format!("{}.z.tlbx", name));
+        doc.compile(&compressed, true).unwrap();
+        assert!(doc.estimated_binary_size(true) >= std::fs::metadata(&compressed).unwrap().len() as usize,
+            "{}: compressed estimate below output", name);
+    }
+}
+
+// =============================================================================
+// Key Aliases
+// =============================================================================
+
+#[test]
+fn canonical_key_aliases_reparse_unchanged() {
+    use tealeaf::FormatOptions;
+
+    let mut entries: Vec<_> = std::fs::read_dir(samples_dir()).unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "tl"))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        let doc = TeaLeaf::load(&path).unwrap();
+
+        for opts in [FormatOptions::pretty(), FormatOptions::compact()] {
+            let plain = TeaLeaf::parse(&doc.to_tl_with_options(&opts)).unwrap();
+            let text = doc.to_tl_with_options(&opts.with_key_aliases());
+            let aliased = TeaLeaf::parse(&text)
+                .unwrap_or_else(|e| panic!("{}: aliased output does not parse: {}\n{}", name, e, text));
+            assert_eq!(aliased.data, plain.data, "{}: aliased output differs\n{}", name, text);
+        }
+    }
+}
@@ -173,7 +173,7 @@ fn unknown_command_prints_error_and_exits_1() {
 #[test]
 fn each_valid_command_recognized_with_no_args() {
     // Each command with no further args should exit 1 (missing args), proving routing works
-    for cmd in &["compile", "decompile", "info", "validate", "to-json", "from-json", "tlbx-to-json", "json-to-tlbx"] {
+    for cmd in &["compile", "decompile", "info", "outline", "validate", "to-json", "from-json", "tlbx-to-json", "json-to-tlbx", "transform"] {
         let output = run(&[cmd]);
         assert_failure(&output);
         // Should NOT say "unrecognized subcommand"
@@ -404,6 +404,21 @@ fn info_binary_file_succeeds() {
     assert!(out.contains("Format: Binary"), "Expected 'Format: Binary', got: {}", out);
 }
 
+#[test]
+fn outline_text_and_binary_files_agree() {
+    let text = run(&["outline", path_str(&sample_tl("schemas"))]);
+    assert_success(&text);
+    let out = stdout_str(&text);
+    assert!(out.contains("employees: []employee [3]\n"), "got: {}", out);
+    assert!(out.contains("  address: address\n    city: string\n"), "got: {}", out);
+    assert!(!out.contains("Acme"), "outline should not print data, got: {}", out);
+
+    let binary = run(&["outline", path_str(&sample_tlbx("schemas")), "--depth", "0"]);
+    assert_success(&binary);
+    let keys_only = run(&["outline", path_str(&sample_tl("schemas")), "-d", "0"]);
+    assert_eq!(stdout_str(&binary), stdout_str(&keys_only));
+}
+
 #[test]
 fn validate_valid_file_succeeds() {
     let input = sample_tl("primitives");
@@ -427,6 +442,26 @@ fn validate_invalid_file_fails() {
     assert_failure(&output);
 }
 
+#[test]
+fn validate_against_expected_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let expected = dir.path().join("expected.tl");
+    let actual = dir.path().join("actual.tl");
+    std::fs::write(&expected, "ratio: 0.1\ncount: 3").unwrap();
+    std::fs::write(&actual, "count: 3\nratio: 0.10000000149011612").unwrap();
+
+    let output = run(&["validate", path_str(&actual), "--against", path_str(&expected)]);
+    assert_failure(&output);
+    assert!(stdout_str(&output).contains("  ratio"), "{}", stdout_str(&output));
+    for tolerance in ["f32", "abs:1e-6", "rel:1e-7"] {
+        let output = run(&["validate", path_str(&actual), "--against", path_str(&expected), "--tolerance", tolerance]);
+        assert_success(&output);
+    }
+    let output = run(&["validate", path_str(&actual), "--against", path_str(&expected), "--tolerance", "close"]);
+    assert_ne!(output.status.code(), Some(0));
+    assert!(stderr_str(&output).contains("invalid float tolerance"), "{}", stderr_str(&output));
+}
+
 #[test]
 fn to_json_with_output_file_succeeds() {
     let dir = tempfile::tempdir().unwrap();
@@ -451,6 +486,41 @@ fn from_json_succeeds() {
     assert!(!content.is_empty(), "from-json output should be nonempty");
 }
 
+#[test]
+fn from_json_alias_keys_shortens_repeated_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("events.json");
+    // Objects keyed by id are not turned into tables, so their keys repeat
+    let events: Vec<String> = (0..5).map(|i| format!(r#""e{i}": {{"eventIdentifier": {i}, "sessionIdentifier": "s{i}"}}"#)).collect();
+    std::fs::write(&input, format!(r#"{{"events": {{{}}}}}"#, events.join(","))).unwrap();
+
+    let plain = dir.path().join("plain.tl");
+    let aliased = dir.path().join("aliased.tl");
+    assert_success(&run(&["from-json", path_str(&input), "-o", path_str(&plain)]));
+    assert_success(&run(&["from-json", path_str(&input), "-o", path_str(&aliased), "--alias-keys"]));
+
+    let plain = std::fs::read_to_string(&plain).unwrap();
+    let aliased = std::fs::read_to_string(&aliased).unwrap();
+    assert!(aliased.starts_with("@alias "), "got: {}", aliased);
+    assert!(aliased.len() < plain.len());
+    assert_eq!(tealeaf::TeaLeaf::parse(&aliased).unwrap().data, tealeaf::TeaLeaf::parse(&plain).unwrap().data);
+}
+
+#[test]
+fn from_json_id_maps_shares_one_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("events.json");
+    let events: Vec<String> = (0..5).map(|i| format!(r#""{i}": {{"kind": "click", "at": {i}}}"#)).collect();
+    std::fs::write(&input, format!(r#"{{"events": {{{}}}}}"#, events.join(","))).unwrap();
+
+    let out = dir.path().join("events.tl");
+    let output = run(&["from-json", path_str(&input), "-o", path_str(&out), "--id-maps"]);
+    assert_success(&output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("@struct event (2 fields)"));
+    let text = std::fs::read_to_string(&out).unwrap();
+    assert!(text.contains("events: @map event {\n  0: (click, 0),"), "got: {}", text);
+}
+
 #[test]
 fn tlbx_to_json_with_output_file_succeeds() {
     let dir = tempfile::tempdir().unwrap();
@@ -903,7 +973,7 @@ fn text_file_as_binary_input_tlbx_to_json() {
 fn random_bytes_as_tlbx_input() {
     let dir = tempfile::tempdir().unwrap();
     let garbage = dir.path().join("garbage.tlbx");
-    std::fs::write(&garbage, &[0xDE, 0xAD, 0xBE, 0xEF, 0x00, 0x11, 0x22, 0x33]).unwrap();
+    std::fs::write(&garbage, [0xDE, 0xAD, 0xBE, 0xEF, 0x00, 0x11, 0x22, 0x33]).unwrap();
     let out = dir.path().join("out.tl");
     let output = run(&["decompile", path_str(&garbage), "-o", path_str(&out)]);
     assert_failure(&output);
@@ -1060,3 +1130,42 @@ fn validate_all_canonical_samples() {
         assert_success(&output);
     }
 }
+
+#[test]
+fn transform_applies_pipeline_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("in.tl");
+    let pipeline = dir.path().join("pipeline.tl");
+    let out = dir.path().join("out.tl");
+    std::fs::write(&input, "users: [{id: 2, password: a}, {id: 1, password: b}]\n").unwrap();
+    std::fs::write(&pipeline, "passes: [{pass: redact, fields: [password]}, {pass: sort_table, table: users, by: id}]\n").unwrap();
+
+    let output = run(&["transform", path_str(&input), "-p", path_str(&pipeline), "-o", path_str(&out)]);
+    assert_success(&output);
+    let stdout = stdout_str(&output);
+    assert!(stdout.contains("redact: 2 changed"), "{}", stdout);
+    assert!(stdout.contains("sort_table: 2 changed"), "{}", stdout);
+    let text = std::fs::read_to_string(&out).unwrap();
+    assert!(text.find("id: 1").unwrap() < text.find("id: 2").unwrap(), "{}", text);
+    assert!(text.contains("[REDACTED]") && !text.contains("password: a"), "{}", text);
+
+    std::fs::write(&pipeline, "passes: [shuffle]\n").unwrap();
+    let output = run(&["transform", path_str(&input), "-p", path_str(&pipeline), "-o", path_str(&out)]);
+    assert_failure(&output);
+    assert!(stderr_str(&output).contains("unknown pass 'shuffle'"));
+}
+
+#[test]
+fn transform_keeps_crlf_line_endings() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("in.tl");
+    let pipeline = dir.path().join("pipeline.tl");
+    let out = dir.path().join("out.tl");
+    std::fs::write(&input, "server: {host: localhost, proxy: ~}\r\nport: 8080\r\n").unwrap();
+    std::fs::write(&pipeline, "passes: [compact]\n").unwrap();
+
+    let output = run(&["transform", path_str(&input), "-p", path_str(&pipeline), "-o", path_str(&out)]);
+    assert_success(&output);
+    let text = std::fs::read_to_string(&out).unwrap();
+    assert_eq!(text, "server: {host: localhost}\r\nport: 8080\r\n");
+}
@@ -359,6 +359,146 @@ fn test_enum_multi_tuple_variant() {
     assert_eq!(msg, restored);
 }
 
+// =============================================================================
+// Enum layouts: internally, adjacently and untagged
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq, ToTeaLeaf, FromTeaLeaf)]
+struct Square {
+    side: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, ToTeaLeaf, FromTeaLeaf)]
+#[tealeaf(tag = "type")]
+enum InternalShape {
+    #[tealeaf(rename = "circle")]
+    Circle { radius: f64 },
+    #[tealeaf(rename = "square")]
+    Square(Square),
+    #[tealeaf(rename = "point")]
+    Point,
+}
+
+#[test]
+fn test_internally_tagged_enum_layout() {
+    let value = InternalShape::Circle { radius: 1.0 }.to_tealeaf_value();
+    let obj = value.as_object().unwrap();
+    assert_eq!(obj.keys().collect::<Vec<_>>(), vec!["type", "radius"]);
+    assert_eq!(obj.get("type").unwrap().as_str(), Some("circle"));
+
+    let square = InternalShape::Square(Square { side: 2.0 }).to_tealeaf_value();
+    assert_eq!(square.get("type").unwrap().as_str(), Some("square"));
+    assert_eq!(square.get("side").unwrap().as_float(), Some(2.0));
+
+    let point = InternalShape::Point.to_tealeaf_value();
+    assert_eq!(point.as_object().unwrap().len(), 1);
+}
+
+#[test]
+fn test_internally_tagged_enum_from_existing_json() {
+    let doc = TeaLeaf::from_json(
+        r#"{"shapes": [{"type": "circle", "radius": 1.0}, {"type": "square", "side": 3.0}, {"type": "point"}]}"#,
+    )
+    .unwrap();
+    let shapes: Vec<InternalShape> = doc.get("shapes").unwrap().as_array().unwrap().iter()
+        .map(|v| InternalShape::from_tealeaf_value(v).unwrap())
+        .collect();
+    assert_eq!(shapes, vec![
+        InternalShape::Circle { radius: 1.0 },
+        InternalShape::Square(Square { side: 3.0 }),
+        InternalShape::Point,
+    ]);
+
+    assert!(InternalShape::from_tealeaf_value(&Value::Float(1.0)).is_err());
+    let mut missing_tag = ObjectMap::new();
+    missing_tag.insert("radius".to_string(), Value::Float(1.0));
+    assert!(matches!(
+        InternalShape::from_tealeaf_value(&Value::Object(missing_tag)),
+        Err(ConvertError::MissingField { .. })
+    ));
+}
+
+#[test]
+fn test_internally_tagged_enum_schema_roundtrip() {
+    let schemas = InternalShape::collect_schemas();
+    let schema = schemas.get("InternalShape").unwrap();
+    let fields: Vec<(&str, &str, bool)> = schema.fields.iter()
+        .map(|f| (f.name.as_str(), f.field_type.base.as_str(), f.field_type.nullable))
+        .collect();
+    assert_eq!(fields, vec![("type", "string", false), ("radius", "float", true), ("side", "float", true)]);
+    assert!(InternalShape::collect_unions().is_empty());
+
+    let shapes = vec![
+        InternalShape::Circle { radius: 1.5 },
+        InternalShape::Square(Square { side: 2.0 }),
+        InternalShape::Point,
+    ];
+    let doc = TeaLeafBuilder::new().add_vec("shapes", &shapes).build();
+    let tl = doc.to_tl_with_schemas();
+    assert!(tl.contains("@table InternalShape"), "{tl}");
+    let reparsed = TeaLeaf::parse(&tl).unwrap();
+    let restored: Vec<InternalShape> = reparsed.get("shapes").unwrap().as_array().unwrap().iter()
+        .map(|v| InternalShape::from_tealeaf_value(v).unwrap())
+        .collect();
+    assert_eq!(restored, shapes);
+}
+
+#[derive(Debug, Clone, PartialEq, ToTeaLeaf, FromTeaLeaf)]
+#[tealeaf(tag = "t", content = "c")]
+enum AdjacentMessage {
+    Text(String),
+    Move { x: i64, y: i64 },
+    Pair(String, i64),
+    Quit,
+}
+
+#[test]
+fn test_adjacently_tagged_enum_layout() {
+    let value = AdjacentMessage::Text("hi".into()).to_tealeaf_value();
+    assert_eq!(value.get("t").unwrap().as_str(), Some("Text"));
+    assert_eq!(value.get("c").unwrap().as_str(), Some("hi"));
+    assert!(AdjacentMessage::Quit.to_tealeaf_value().get("c").is_none());
+    assert_eq!(AdjacentMessage::tealeaf_field_type().base, "any");
+    assert!(AdjacentMessage::collect_unions().is_empty());
+
+    for msg in [
+        AdjacentMessage::Text("hi".into()),
+        AdjacentMessage::Move { x: 1, y: -2 },
+        AdjacentMessage::Pair("k".into(), 7),
+        AdjacentMessage::Quit,
+    ] {
+        let json = TeaLeafBuilder::new().add_value("m", msg.to_tealeaf_value()).build().to_json().unwrap();
+        let doc = TeaLeaf::from_json(&json).unwrap();
+        assert_eq!(AdjacentMessage::from_tealeaf_value(doc.get("m").unwrap()).unwrap(), msg);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, ToTeaLeaf, FromTeaLeaf)]
+#[tealeaf(untagged)]
+enum UntaggedValue {
+    Point { x: i64, y: i64 },
+    Number(i64),
+    Words(String, String),
+    Nothing,
+}
+
+#[test]
+fn test_untagged_enum_layout() {
+    assert_eq!(UntaggedValue::Number(3).to_tealeaf_value(), Value::Int(3));
+    assert_eq!(UntaggedValue::Nothing.to_tealeaf_value(), Value::Null);
+    assert_eq!(UntaggedValue::tealeaf_field_type().base, "any");
+
+    for v in [
+        UntaggedValue::Point { x: 1, y: 2 },
+        UntaggedValue::Number(3),
+        UntaggedValue::Words("a".into(), "b".into()),
+        UntaggedValue::Nothing,
+    ] {
+        assert_eq!(UntaggedValue::from_tealeaf_value(&v.to_tealeaf_value()).unwrap(), v);
+    }
+    assert!(UntaggedValue::from_tealeaf_value(&Value::Bool(true)).is_err());
+}
+
 // =============================================================================
 // Document creation convenience methods
 // =============================================================================
@@ -551,6 +691,56 @@ fn test_unknown_enum_variant() {
     }
 }
 
+#[test]
+fn test_to_union_dto() {
+    let doc = TeaLeaf::parse(r#"
+        @union Shape {
+            Circle(radius: float),
+            Rectangle(width: float, height: float),
+            Point(),
+        }
+        circle: :Circle {radius: 2}
+        point: :Point ~
+        unknown: :Triangle {side: 1.0}
+        wrong_field: :Circle {diameter: 4.0}
+        wrong_type: :Rectangle {width: 1.0, height: tall}
+        no_fields: :Point {x: 1}
+        untagged: {radius: 2.0}
+    "#).unwrap();
+
+    assert_eq!(doc.to_union_dto::<Shape>("circle").unwrap(), Shape::Circle { radius: 2.0 });
+    assert_eq!(doc.to_union_dto::<Shape>("point").unwrap(), Shape::Point);
+
+    for (key, expected) in [
+        ("unknown", "'unknown': tag 'Triangle' is not a variant of union 'Shape' (expected one of: Circle, Rectangle, Point)"),
+        ("wrong_field", "wrong_field.radius"),
+        ("wrong_type", "'wrong_type.height': expected float, got string"),
+        ("no_fields", "'no_fields': variant 'Point' of union 'Shape' has no fields, but holds object"),
+        ("untagged", "'untagged': expected a tagged value of union 'Shape', got object"),
+    ] {
+        let err = doc.to_union_dto::<Shape>(key).unwrap_err().to_string();
+        assert!(err.contains(expected), "{}: {}", key, err);
+    }
+    // The type must read a union the document declares
+    assert!(doc.to_union_dto::<SimpleUser>("circle").unwrap_err().to_string().contains("does not read a union"));
+    assert!(TeaLeaf::parse("c: :Circle {radius: 1.0}").unwrap().to_union_dto::<Shape>("c").unwrap_err().to_string().contains("no @union Shape"));
+    assert!(doc.to_union_dto::<Shape>("missing").is_err());
+
+    // A document built from the enum declares the union, with positional
+    // fields for tuple variants
+    let doc = TeaLeaf::from_dto("shape", &Shape::Rectangle { width: 1.0, height: 2.0 });
+    assert_eq!(doc.to_union_dto::<Shape>("shape").unwrap(), Shape::Rectangle { width: 1.0, height: 2.0 });
+    let mut doc = TeaLeaf::from_dto("pair", &Message::Pair("key".into(), 42));
+    assert_eq!(doc.to_union_dto::<Message>("pair").unwrap(), Message::Pair("key".into(), 42));
+    doc.data.insert("text".into(), Message::Text("hello".into()).to_tealeaf_value());
+    assert_eq!(doc.to_union_dto::<Message>("text").unwrap(), Message::Text("hello".into()));
+    doc.data.insert("short".into(), Value::Tagged("Pair".into(), Box::new(Value::Array(vec![Value::String("key".into())]))));
+    let err = doc.to_union_dto::<Message>("short").unwrap_err().to_string();
+    assert!(err.contains("'short': holds 1 values, but variant 'Pair' of union 'Message' has 2 fields"), "{}", err);
+    doc.data.insert("text".into(), Value::Tagged("Text".into(), Box::new(Value::Int(1))));
+    assert!(doc.to_union_dto::<Message>("text").unwrap_err().to_string().contains("'text': expected string, got int"));
+}
+
 // =============================================================================
 // Convert coverage: HashMap variants
 // =============================================================================
@@ -1443,6 +1633,62 @@ fn test_flatten_optional_schema() {
     assert!(schemas.get("OptionalAddress").is_some());
 }
 
+// =============================================================================
+// Recursive types: Vec<Self> and Option<Box<Self>>
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq, ToTeaLeaf, FromTeaLeaf)]
+struct TreeNode {
+    name: String,
+    children: Vec<TreeNode>,
+}
+
+impl tealeaf::convert::NotU8 for TreeNode {}
+
+#[derive(Debug, Clone, PartialEq, ToTeaLeaf, FromTeaLeaf)]
+struct ListNode {
+    value: i64,
+    next: Option<Box<ListNode>>,
+}
+
+fn sample_tree() -> TreeNode {
+    let leaf = |name: &str| TreeNode { name: name.into(), children: vec![] };
+    TreeNode {
+        name: "root".into(),
+        children: vec![
+            TreeNode { name: "a".into(), children: vec![leaf("a1"), leaf("a2")] },
+            leaf("b"),
+        ],
+    }
+}
+
+#[test]
+fn test_recursive_schema_references_itself() {
+    let schemas = TreeNode::collect_schemas();
+    assert_eq!(schemas.len(), 1);
+    let children = &schemas["TreeNode"].fields[1];
+    assert_eq!(children.field_type.base, "TreeNode");
+    assert!(children.field_type.is_array);
+
+    let schemas = ListNode::collect_schemas();
+    let next = &schemas["ListNode"].fields[1].field_type;
+    assert_eq!((next.base.as_str(), next.nullable), ("ListNode", true));
+    assert!(TreeNode::collect_unions().is_empty());
+}
+
+#[test]
+fn test_recursive_roundtrip() {
+    let tree = sample_tree();
+    let text = tree.to_tl_string("tree");
+    assert!(text.contains("@struct TreeNode"), "{}", text);
+    let doc = TeaLeaf::parse(&text).unwrap();
+    assert_eq!(TreeNode::from_tealeaf_value(doc.get("tree").unwrap()).unwrap(), tree);
+
+    let list = ListNode { value: 1, next: Some(Box::new(ListNode { value: 2, next: None })) };
+    let doc = TeaLeaf::parse(&list.to_tl_string("list")).unwrap();
+    assert_eq!(ListNode::from_tealeaf_value(doc.get("list").unwrap()).unwrap(), list);
+}
+
 // =============================================================================
 // Builder + schema-aware serialization: @table output with PascalCase schemas
 // =============================================================================
@@ -1575,6 +1821,123 @@ fn test_builder_schema_aware_table_output() {
     assert_eq!(stock[0].as_object().unwrap().get("warehouse").unwrap().as_str(), Some("W1"));
 }
 
+#[test]
+fn test_builder_binds_keys_to_pascal_case_schemas() {
+    let pricing = Pricing { base_price: 5.0, currency: "EUR".into() };
+    let stock = vec![StockEntry { warehouse: "W9".into(), qty: 3, backordered: false }];
+    let doc = TeaLeafBuilder::new()
+        .add("config", &pricing)
+        .add_vec("inventory", &stock)
+        .add_value("note", Value::String("hi".into()))
+        .build();
+    assert_eq!(doc.binding("config"), Some("Pricing"));
+    assert_eq!(doc.binding("inventory"), Some("StockEntry"));
+    assert_eq!(doc.binding("note"), None);
+
+    let tl = doc.to_tl_with_schemas();
+    assert!(tl.contains("config: @as Pricing {"), "{tl}");
+    assert!(tl.contains("inventory: @table StockEntry ["), "{tl}");
+    let reparsed = TeaLeaf::parse(&tl).unwrap();
+    assert_eq!(reparsed.binding("config"), Some("Pricing"));
+    assert_eq!(reparsed.data, doc.data);
+}
+
+#[derive(Debug, Clone, PartialEq, ToTeaLeaf, FromTeaLeaf)]
+struct Envelope {
+    kind: String,
+    payload: Value,
+    meta: tealeaf::IndexMap<String, Value>,
+    trace: Option<Value>,
+}
+
+#[test]
+fn test_raw_value_fields_pass_through() {
+    let doc = TeaLeaf::parse(r#"
+        event: {kind: order, payload: {id: 7, items: [1, 2]}, meta: {source: api, retries: 2}, trace: ~}
+    "#).unwrap();
+    let event = Envelope::from_tealeaf_value(doc.get("event").unwrap()).unwrap();
+    assert_eq!(event.payload.get_path("items").and_then(|v| v.as_array()).map(|a| a.len()), Some(2));
+    assert_eq!(event.meta.get("retries").and_then(|v| v.as_int()), Some(2));
+    assert_eq!(event.trace, None);
+
+    let schema = &Envelope::collect_schemas()["Envelope"];
+    assert_eq!(schema.fields[1].field_type, FieldType::new("any"));
+
+    let back = Envelope::from_tealeaf_value(&event.to_tealeaf_value()).unwrap();
+    assert_eq!(back, event);
+}
+
+#[derive(Debug, Clone, PartialEq, ToTeaLeaf, FromTeaLeaf)]
+struct OrderSummary {
+    id: i64,
+    #[tealeaf(rename = "total")]
+    amount: f64,
+}
+impl NotU8 for OrderSummary {}
+
+#[test]
+fn test_get_as_projected_skips_unread_columns() {
+    let doc = TeaLeaf::parse(r#"
+        @struct line (sku: string, qty: int)
+        @struct order (id: int, customer: string, note: string?, blob: bytes, lines: []line, total: float, at: timestamp)
+        orders: @table order [
+            (1, alice, "rush", b"00ff", [(a, 1), (b, 2)], 9.5, 2024-01-15T10:30:00Z),
+            (2, bob, ~, b"", [], 20.0, 2024-01-16T10:30:00Z),
+        ]
+        config: {id: 3, total: 1.0, extra: yes}
+    "#).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("orders.tlbx");
+    doc.compile(&path, true).unwrap();
+    let reader = tealeaf::Reader::open(&path).unwrap();
+
+    let orders: Vec<OrderSummary> = reader.get_as_projected("orders").unwrap();
+    assert_eq!(orders, vec![OrderSummary { id: 1, amount: 9.5 }, OrderSummary { id: 2, amount: 20.0 }]);
+    let full = Vec::<OrderSummary>::from_tealeaf_value(&reader.get("orders").unwrap()).unwrap();
+    assert_eq!(orders, full);
+
+    // Not a table: decoded whole, then converted
+    let config: OrderSummary = reader.get_as_projected("config").unwrap();
+    assert_eq!(config, OrderSummary { id: 3, amount: 1.0 });
+    assert!(reader.get_as_projected::<Vec<OrderSummary>>("missing").is_err());
+}
+
+#[derive(Debug, Clone, PartialEq, ToTeaLeaf, FromTeaLeaf)]
+struct Blob {
+    digest: [u8; 4],
+    #[tealeaf(bytes)]
+    payload: Box<[u8]>,
+    #[tealeaf(bytes)]
+    preview: Option<Box<[u8]>>,
+}
+
+#[test]
+fn test_byte_fields_become_bytes() {
+    let blob = Blob { digest: [0xde, 0xad, 0xbe, 0xef], payload: Box::new([1, 2, 3]), preview: None };
+    let value = blob.to_tealeaf_value();
+    assert_eq!(value.get("digest"), Some(&Value::Bytes(vec![0xde, 0xad, 0xbe, 0xef])));
+    assert_eq!(value.get("payload"), Some(&Value::Bytes(vec![1, 2, 3])));
+    assert_eq!(value.get("preview"), Some(&Value::Null));
+
+    let schemas = Blob::collect_schemas();
+    let schema = &schemas["Blob"];
+    assert_eq!(schema.get_field("digest").unwrap().field_type, FieldType::new("bytes"));
+    assert_eq!(schema.get_field("preview").unwrap().field_type, FieldType::new("bytes").nullable());
+
+    let doc = TeaLeafBuilder::new().add("blob", &blob).build();
+    let text = doc.to_tl_with_schemas();
+    assert!(text.contains(r#"b"deadbeef""#), "{}", text);
+    let parsed = TeaLeaf::parse(&text).unwrap();
+    assert_eq!(Blob::from_tealeaf_value(parsed.get("blob").unwrap()).unwrap(), blob);
+
+    let mut short = value.clone();
+    if let Value::Object(obj) = &mut short {
+        obj.insert("digest".into(), Value::Bytes(vec![1]));
+    }
+    let err = Blob::from_tealeaf_value(&short).unwrap_err().to_string();
+    assert!(err.contains("got 1 bytes"), "{}", err);
+}
+
 #[path = "fixtures/retail_orders_different_shape.rs"]
 mod retail_data;
 
@@ -20,7 +20,7 @@ fn arb_leaf_value() -> impl Strategy<Value = Value> {
         any::<f64>()
             .prop_filter("finite only", |f| f.is_finite())
             .prop_map(Value::Float),
-        "[a-zA-Z0-9_ ]{0,50}".prop_map(|s| Value::String(s)),
+        "[a-zA-Z0-9_ ]{0,50}".prop_map(Value::String),
         prop::collection::vec(any::<u8>(), 0..20).prop_map(Value::Bytes),
         // Timestamps: reasonable range
         (-1_000_000_000_000i64..1_000_000_000_000i64).prop_map(|ts| Value::Timestamp(ts, 0)),
@@ -60,7 +60,7 @@ fn arb_text_roundtrip_value() -> impl Strategy<Value = Value> {
         any::<bool>().prop_map(Value::Bool),
         // Restrict to i32 range to avoid representation ambiguity
         (-2_000_000_000i64..2_000_000_000i64).prop_map(Value::Int),
-        "[a-zA-Z0-9_ ]{0,30}".prop_map(|s| Value::String(s)),
+        "[a-zA-Z0-9_ ]{0,30}".prop_map(Value::String),
     ]
 }
 
@@ -103,7 +103,7 @@ fn arb_binary_roundtrip_value() -> impl Strategy<Value = Value> {
         any::<f64>()
             .prop_filter("finite only", |f| f.is_finite())
             .prop_map(Value::Float),
-        "[a-zA-Z0-9_ ]{0,30}".prop_map(|s| Value::String(s)),
+        "[a-zA-Z0-9_ ]{0,30}".prop_map(Value::String),
         prop::collection::vec(any::<u8>(), 0..20).prop_map(Value::Bytes),
         (-1_000_000_000_000i64..1_000_000_000_000i64).prop_map(|ts| Value::Timestamp(ts, 0)),
     ]
@@ -201,3 +201,66 @@ proptest! {
         }
     }
 }
+
+// =========================================================================
+// Property: Floats and timestamps round-trip bit-exact through text and JSON
+// =========================================================================
+
+fn single(key: &str, value: Value) -> TeaLeaf {
+    let mut data = IndexMap::new();
+    data.insert(key.to_string(), value);
+    TeaLeaf::new(IndexMap::new(), data)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(500))]
+
+    #[test]
+    fn float_text_roundtrip_is_exact(f in any::<f64>().prop_filter("finite only", |f| f.is_finite())) {
+        let canonical = Value::Float(f).float_to_canonical_string().unwrap();
+        prop_assert_eq!(canonical.parse::<f64>().unwrap().to_bits(), f.to_bits());
+
+        // Repeated cycles must not drift
+        let mut doc = single("f", Value::Float(f));
+        for _ in 0..3 {
+            let text = doc.to_tl_with_schemas();
+            prop_assert!(text.contains(&canonical), "{} not in {}", canonical, text);
+            doc = TeaLeaf::parse(&text).expect("re-parse");
+            match doc.get("f") {
+                Some(Value::Float(g)) => prop_assert_eq!(g.to_bits(), f.to_bits()),
+                other => prop_assert!(false, "expected float, got {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn float_json_roundtrip_is_exact(f in any::<f64>().prop_filter("finite only", |f| f.is_finite())) {
+        // Same digits as text; serde_json spells a positive exponent `e+`
+        let canonical = Value::Float(f).float_to_canonical_string().unwrap();
+        let expected = if canonical.contains("e-") { canonical } else { canonical.replace('e', "e+") };
+        let mut doc = single("f", Value::Float(f));
+        for _ in 0..3 {
+            let json = doc.to_json().expect("to_json");
+            prop_assert!(json.contains(&expected), "{} not in {}", expected, json);
+            doc = TeaLeaf::from_json(&json).expect("from_json");
+            match doc.get("f") {
+                Some(Value::Float(g)) => prop_assert_eq!(g.to_bits(), f.to_bits()),
+                other => prop_assert!(false, "expected float, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn timestamp_text_roundtrip_is_exact(
+        ms in -62_135_596_800_000i64..253_402_300_799_000i64,
+        tz in -1439i16..=1439,
+    ) {
+        let value = Value::Timestamp(ms, tz);
+        let mut doc = single("t", value.clone());
+        for _ in 0..3 {
+            doc = TeaLeaf::parse(&doc.to_tl_with_schemas()).expect("re-parse");
+            prop_assert_eq!(doc.get("t"), Some(&value));
+        }
+    }
+}
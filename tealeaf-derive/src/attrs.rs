@@ -1,6 +1,6 @@
 //! Attribute parsing for `#[tealeaf(...)]` annotations.
 
-use syn::{Attribute, Lit};
+use syn::{Attribute, Data, DeriveInput, Lit};
 
 /// Container-level attributes (on the struct/enum itself)
 #[derive(Debug, Default)]
@@ -11,6 +11,32 @@ pub struct ContainerAttrs {
     pub root_array: bool,
     /// Custom data key: `#[tealeaf(key = "my_key")]`
     pub key: Option<String>,
+    /// Enum tag field name: `#[tealeaf(tag = "type")]`
+    pub tag: Option<String>,
+    /// Enum content field name, with `tag`: `#[tealeaf(content = "data")]`
+    pub content: Option<String>,
+    /// Enum without a tag: `#[tealeaf(untagged)]`
+    pub untagged: bool,
+}
+
+/// Enum variant attributes
+#[derive(Debug, Default)]
+pub struct VariantAttrs {
+    /// Override the variant (tag) name: `#[tealeaf(rename = "circle")]`
+    pub rename: Option<String>,
+}
+
+/// How an enum's variants are represented as values.
+#[derive(Debug)]
+pub enum EnumRepr {
+    /// `:variant payload` tagged values (the default)
+    External,
+    /// `{tag: "variant", ...fields}`
+    Internal { tag: String },
+    /// `{tag: "variant", content: payload}`
+    Adjacent { tag: String, content: String },
+    /// The payload alone; decoding tries each variant in order
+    Untagged,
 }
 
 /// Field-level attributes
@@ -22,7 +48,8 @@ pub struct FieldAttrs {
     pub skip: bool,
     /// Mark as optional/nullable: `#[tealeaf(optional)]`
     pub optional: bool,
-    /// Override the TeaLeaf type: `#[tealeaf(type = "timestamp")]`
+    /// Override the TeaLeaf type: `#[tealeaf(type = "timestamp")]`.
+    /// `#[tealeaf(bytes)]` is short for `type = "bytes"`.
     pub type_override: Option<String>,
     /// Flatten nested struct: `#[tealeaf(flatten)]`
     pub flatten: bool,
@@ -60,11 +87,92 @@ impl ContainerAttrs {
                     }
                     return Ok(());
                 }
+                if meta.path.is_ident("tag") {
+                    let value = meta.value()?;
+                    let lit: Lit = value.parse()?;
+                    if let Lit::Str(s) = lit {
+                        result.tag = Some(s.value());
+                    }
+                    return Ok(());
+                }
+                if meta.path.is_ident("content") {
+                    let value = meta.value()?;
+                    let lit: Lit = value.parse()?;
+                    if let Lit::Str(s) = lit {
+                        result.content = Some(s.value());
+                    }
+                    return Ok(());
+                }
+                if meta.path.is_ident("untagged") {
+                    result.untagged = true;
+                    return Ok(());
+                }
                 Err(meta.error("unknown tealeaf container attribute"))
             })?;
         }
         Ok(result)
     }
+
+    /// The enum representation selected by `tag`, `content` and `untagged`.
+    ///
+    /// Errors on conflicting combinations, and on any of them for a struct.
+    pub fn enum_repr(&self, input: &DeriveInput) -> syn::Result<EnumRepr> {
+        let is_enum = matches!(input.data, Data::Enum(_));
+        let has_repr = self.tag.is_some() || self.content.is_some() || self.untagged;
+        if has_repr && !is_enum {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "tealeaf `tag`, `content` and `untagged` only apply to enums",
+            ));
+        }
+        match (&self.tag, &self.content, self.untagged) {
+            (None, None, false) => Ok(EnumRepr::External),
+            (Some(tag), None, false) => Ok(EnumRepr::Internal { tag: tag.clone() }),
+            (Some(tag), Some(content), false) => Ok(EnumRepr::Adjacent {
+                tag: tag.clone(),
+                content: content.clone(),
+            }),
+            (None, None, true) => Ok(EnumRepr::Untagged),
+            (None, Some(_), _) => Err(syn::Error::new_spanned(
+                &input.ident,
+                "tealeaf `content` requires `tag`",
+            )),
+            (_, _, true) => Err(syn::Error::new_spanned(
+                &input.ident,
+                "tealeaf `untagged` cannot be combined with `tag` or `content`",
+            )),
+        }
+    }
+}
+
+impl VariantAttrs {
+    pub fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut result = Self::default();
+        for attr in attrs {
+            if !attr.path().is_ident("tealeaf") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    let value = meta.value()?;
+                    let lit: Lit = value.parse()?;
+                    if let Lit::Str(s) = lit {
+                        result.rename = Some(s.value());
+                    }
+                    return Ok(());
+                }
+                Err(meta.error("unknown tealeaf variant attribute"))
+            })?;
+        }
+        Ok(result)
+    }
+
+    /// The tag name for `variant`: its `rename`, or the identifier.
+    pub fn variant_name(variant: &syn::Variant) -> syn::Result<String> {
+        Ok(Self::from_attrs(&variant.attrs)?
+            .rename
+            .unwrap_or_else(|| variant.ident.to_string()))
+    }
 }
 
 impl FieldAttrs {
@@ -107,6 +215,10 @@ impl FieldAttrs {
                     result.default = true;
                     return Ok(());
                 }
+                if meta.path.is_ident("bytes") {
+                    result.type_override = Some("bytes".to_string());
+                    return Ok(());
+                }
                 if meta.path.is_ident("type") {
                     let value = meta.value()?;
                     let lit: Lit = value.parse()?;
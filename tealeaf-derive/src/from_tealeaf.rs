@@ -4,7 +4,7 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{Data, DataEnum, DataStruct, DeriveInput, Fields};
 
-use crate::attrs::FieldAttrs;
+use crate::attrs::{ContainerAttrs, EnumRepr, FieldAttrs, VariantAttrs};
 use crate::util;
 
 pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
@@ -13,11 +13,27 @@ pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
 
     let from_value_body = generate_from_value(input)?;
 
+    // Externally tagged enums read the tagged values of the union they derive
+    let container_attrs = ContainerAttrs::from_attrs(&input.attrs)?;
+    let union_fn = match &input.data {
+        Data::Enum(_) if matches!(container_attrs.enum_repr(input)?, EnumRepr::External) => {
+            let union_name = container_attrs.rename.clone().unwrap_or_else(|| name.to_string());
+            quote! {
+                fn tealeaf_union() -> ::std::option::Option<&'static str> {
+                    ::std::option::Option::Some(#union_name)
+                }
+            }
+        }
+        _ => quote! {},
+    };
+
     Ok(quote! {
         impl #impl_generics ::tealeaf::convert::FromTeaLeaf for #name #type_generics #where_clause {
             fn from_tealeaf_value(value: &::tealeaf::Value) -> ::std::result::Result<Self, ::tealeaf::convert::ConvertError> {
                 #from_value_body
             }
+
+            #union_fn
         }
     })
 }
@@ -155,12 +171,22 @@ fn generate_standard_field(
     is_option: bool,
     attrs: &FieldAttrs,
 ) -> syn::Result<TokenStream> {
+    // `bytes` fields read any type built from a Vec<u8>
+    let read = |ty: TokenStream| {
+        if attrs.type_override.as_deref() == Some("bytes") {
+            quote! { ::tealeaf::convert::bytes_from_value::<#ty>(v) }
+        } else {
+            quote! { <#ty as ::tealeaf::convert::FromTeaLeaf>::from_tealeaf_value(v) }
+        }
+    };
+    let read_inner = read(quote! { _ });
+    let read_field = read(quote! { #ty });
     if is_option {
         Ok(quote! {
             #field_ident: {
                 match obj.get(#field_name) {
                     Some(v) if !v.is_null() => {
-                        Some(<_ as ::tealeaf::convert::FromTeaLeaf>::from_tealeaf_value(v)
+                        Some(#read_inner
                             .map_err(|e| ::tealeaf::convert::ConvertError::Nested {
                                 path: format!("{}.{}", #struct_name, #field_name),
                                 source: Box::new(e),
@@ -177,7 +203,7 @@ fn generate_standard_field(
                 #field_ident: {
                     match obj.get(#field_name) {
                         Some(v) if !v.is_null() => {
-                            <#ty as ::tealeaf::convert::FromTeaLeaf>::from_tealeaf_value(v)
+                            #read_field
                                 .map_err(|e| ::tealeaf::convert::ConvertError::Nested {
                                     path: format!("{}.{}", #struct_name, #field_name),
                                     source: Box::new(e),
@@ -192,7 +218,7 @@ fn generate_standard_field(
                 #field_ident: {
                     match obj.get(#field_name) {
                         Some(v) if !v.is_null() => {
-                            <#ty as ::tealeaf::convert::FromTeaLeaf>::from_tealeaf_value(v)
+                            #read_field
                                 .map_err(|e| ::tealeaf::convert::ConvertError::Nested {
                                     path: format!("{}.{}", #struct_name, #field_name),
                                     source: Box::new(e),
@@ -210,7 +236,7 @@ fn generate_standard_field(
                     struct_name: #struct_name.into(),
                     field: #field_name.into(),
                 })?;
-                <#ty as ::tealeaf::convert::FromTeaLeaf>::from_tealeaf_value(v)
+                #read_field
                     .map_err(|e| ::tealeaf::convert::ConvertError::Nested {
                         path: format!("{}.{}", #struct_name, #field_name),
                         source: Box::new(e),
@@ -223,11 +249,14 @@ fn generate_standard_field(
 fn generate_enum_from_value(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream> {
     let name = &input.ident;
     let name_str = name.to_string();
-    let mut match_arms = Vec::new();
+    let repr = ContainerAttrs::from_attrs(&input.attrs)?.enum_repr(input)?;
+    let mut variant_names = Vec::new();
+    let mut variant_bodies = Vec::new();
 
     for variant in &data.variants {
         let variant_ident = &variant.ident;
-        let variant_name = variant_ident.to_string();
+        let variant_name = VariantAttrs::variant_name(variant)?;
+        variant_names.push(variant_name.clone());
 
         match &variant.fields {
             Fields::Named(fields) => {
@@ -243,8 +272,8 @@ fn generate_enum_from_value(input: &DeriveInput, data: &DataEnum) -> syn::Result
                     .collect();
                 let field_types: Vec<_> = fields.named.iter().map(|f| &f.ty).collect();
 
-                match_arms.push(quote! {
-                    #variant_name => {
+                variant_bodies.push(quote! {
+                    {
                         let inner_obj = inner.as_object().ok_or_else(|| ::tealeaf::convert::ConvertError::TypeMismatch {
                             expected: "object".into(),
                             got: format!("{:?}", inner.tl_type()),
@@ -271,8 +300,8 @@ fn generate_enum_from_value(input: &DeriveInput, data: &DataEnum) -> syn::Result
             Fields::Unnamed(fields) => {
                 if fields.unnamed.len() == 1 {
                     let ty = &fields.unnamed[0].ty;
-                    match_arms.push(quote! {
-                        #variant_name => {
+                    variant_bodies.push(quote! {
+                        {
                             let inner_val = <#ty as ::tealeaf::convert::FromTeaLeaf>::from_tealeaf_value(inner)
                                 .map_err(|e| ::tealeaf::convert::ConvertError::Nested {
                                     path: format!("{}::{}", #name_str, #variant_name),
@@ -284,8 +313,8 @@ fn generate_enum_from_value(input: &DeriveInput, data: &DataEnum) -> syn::Result
                 } else {
                     let field_types: Vec<_> = fields.unnamed.iter().map(|f| &f.ty).collect();
                     let indices: Vec<usize> = (0..field_types.len()).collect();
-                    match_arms.push(quote! {
-                        #variant_name => {
+                    variant_bodies.push(quote! {
+                        {
                             let arr = inner.as_array().ok_or_else(|| ::tealeaf::convert::ConvertError::TypeMismatch {
                                 expected: "array".into(),
                                 got: format!("{:?}", inner.tl_type()),
@@ -309,28 +338,107 @@ fn generate_enum_from_value(input: &DeriveInput, data: &DataEnum) -> syn::Result
                 }
             }
             Fields::Unit => {
-                match_arms.push(quote! {
-                    #variant_name => Ok(#name::#variant_ident),
-                });
+                if matches!(repr, EnumRepr::Untagged) {
+                    variant_bodies.push(quote! {
+                        if inner.is_null() {
+                            Ok(#name::#variant_ident)
+                        } else {
+                            Err(::tealeaf::convert::ConvertError::TypeMismatch {
+                                expected: "null".into(),
+                                got: format!("{:?}", inner.tl_type()),
+                                path: format!("{}::{}", #name_str, #variant_name),
+                            })
+                        }
+                    });
+                } else {
+                    variant_bodies.push(quote! { Ok(#name::#variant_ident) });
+                }
             }
         }
     }
 
-    Ok(quote! {
-        match value {
-            ::tealeaf::Value::Tagged(tag, inner) => {
-                match tag.as_str() {
-                    #(#match_arms)*
-                    other => Err(::tealeaf::convert::ConvertError::Custom(
-                        format!("Unknown {} variant: {}", #name_str, other),
-                    )),
+    let unknown_variant = quote! {
+        other => Err(::tealeaf::convert::ConvertError::Custom(
+            format!("Unknown {} variant: {}", #name_str, other),
+        )),
+    };
+    let tag_dispatch = quote! {
+        match tag {
+            #(#variant_names => #variant_bodies,)*
+            #unknown_variant
+        }
+    };
+    let expect_object = quote! {
+        ::tealeaf::convert::ConvertError::TypeMismatch {
+            expected: "object".into(),
+            got: format!("{:?}", value.tl_type()),
+            path: #name_str.into(),
+        }
+    };
+    let read_tag = |tag: &str| quote! {
+        obj.get(#tag).and_then(|v| v.as_str()).ok_or_else(|| ::tealeaf::convert::ConvertError::MissingField {
+            struct_name: #name_str.into(),
+            field: #tag.into(),
+        })?
+    };
+
+    Ok(match &repr {
+        EnumRepr::External => quote! {
+            match value {
+                ::tealeaf::Value::Tagged(tag, inner) => {
+                    let tag = tag.as_str();
+                    #tag_dispatch
+                }
+                _ => Err(::tealeaf::convert::ConvertError::TypeMismatch {
+                    expected: "tagged".into(),
+                    got: format!("{:?}", value.tl_type()),
+                    path: #name_str.into(),
+                }),
+            }
+        },
+        EnumRepr::Internal { tag } => {
+            let read_tag = read_tag(tag);
+            quote! {
+                match value {
+                    // Variant fields sit alongside the tag
+                    ::tealeaf::Value::Object(obj) => {
+                        let tag = #read_tag;
+                        let inner = value;
+                        #tag_dispatch
+                    }
+                    // Non-object newtype payloads keep the tagged-value layout
+                    ::tealeaf::Value::Tagged(tag, inner) => {
+                        let tag = tag.as_str();
+                        #tag_dispatch
+                    }
+                    _ => Err(#expect_object),
                 }
             }
-            _ => Err(::tealeaf::convert::ConvertError::TypeMismatch {
-                expected: "tagged".into(),
-                got: format!("{:?}", value.tl_type()),
-                path: #name_str.into(),
-            }),
         }
+        EnumRepr::Adjacent { tag, content } => {
+            let read_tag = read_tag(tag);
+            quote! {
+                let obj = value.as_object().ok_or_else(|| #expect_object)?;
+                let tag = #read_tag;
+                let null = ::tealeaf::Value::Null;
+                let inner = obj.get(#content).unwrap_or(&null);
+                #tag_dispatch
+            }
+        }
+        EnumRepr::Untagged => quote! {
+            // First variant that accepts the value wins
+            let inner = value;
+            #(
+                let attempt = || -> ::std::result::Result<Self, ::tealeaf::convert::ConvertError> {
+                    #variant_bodies
+                };
+                if let Ok(v) = attempt() {
+                    return Ok(v);
+                }
+            )*
+            Err(::tealeaf::convert::ConvertError::Custom(
+                format!("No {} variant matches value of type {:?}", #name_str, value.tl_type()),
+            ))
+        },
     })
 }
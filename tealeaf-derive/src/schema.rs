@@ -4,12 +4,13 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{DeriveInput, Fields, Data, DataStruct, DataEnum};
 
-use crate::attrs::{ContainerAttrs, FieldAttrs};
+use crate::attrs::{ContainerAttrs, EnumRepr, FieldAttrs, VariantAttrs};
 use crate::util;
 
 /// Generate the `collect_schemas()` method body for a struct.
 pub fn generate_collect_schemas(input: &DeriveInput) -> syn::Result<TokenStream> {
     let container_attrs = ContainerAttrs::from_attrs(&input.attrs)?;
+    let repr = container_attrs.enum_repr(input)?;
     let schema_name = container_attrs
         .rename
         .unwrap_or_else(|| input.ident.to_string());
@@ -90,25 +91,30 @@ pub fn generate_collect_schemas(input: &DeriveInput) -> syn::Result<TokenStream>
 
             Ok(quote! {
                 fn collect_schemas() -> ::tealeaf::IndexMap<String, ::tealeaf::Schema> {
-                    let mut schemas = ::tealeaf::IndexMap::new();
-                    // Collect schemas from nested types
-                    #(#nested_schema_calls)*
-                    // Build own schema
-                    let mut schema = ::tealeaf::Schema::new(#schema_name);
-                    #(#schema_fields)*
-                    schemas.insert(#schema_name.to_string(), schema);
-                    schemas
-                }
-            })
-        }
-        Data::Enum(_) => {
-            // Enums don't produce struct schemas, but nested types in variants might
-            Ok(quote! {
-                fn collect_schemas() -> ::tealeaf::IndexMap<String, ::tealeaf::Schema> {
-                    ::tealeaf::IndexMap::new()
+                    ::tealeaf::convert::collect_once("schemas", ::std::any::type_name::<Self>(), || {
+                        let mut schemas = ::tealeaf::IndexMap::new();
+                        // Collect schemas from nested types
+                        #(#nested_schema_calls)*
+                        // Build own schema
+                        let mut schema = ::tealeaf::Schema::new(#schema_name);
+                        #(#schema_fields)*
+                        schemas.insert(#schema_name.to_string(), schema);
+                        schemas
+                    })
                 }
             })
         }
+        Data::Enum(data) => match &repr {
+            EnumRepr::Internal { tag } => generate_internal_enum_collect_schemas(&schema_name, tag, data),
+            _ => {
+                // Enums don't produce struct schemas, but nested types in variants might
+                Ok(quote! {
+                    fn collect_schemas() -> ::tealeaf::IndexMap<String, ::tealeaf::Schema> {
+                        ::tealeaf::IndexMap::new()
+                    }
+                })
+            }
+        },
         _ => Err(syn::Error::new_spanned(
             &input.ident,
             "ToTeaLeaf can only be derived for structs with named fields or enums",
@@ -116,12 +122,77 @@ pub fn generate_collect_schemas(input: &DeriveInput) -> syn::Result<TokenStream>
     }
 }
 
+/// Build the struct schema for an internally tagged enum: the tag field, then
+/// every variant's fields as nullable, in declaration order. A field name shared
+/// by variants with different types becomes `any`.
+fn generate_internal_enum_collect_schemas(schema_name: &str, tag: &str, data: &DataEnum) -> syn::Result<TokenStream> {
+    let mut field_merges = Vec::new();
+
+    for variant in &data.variants {
+        match &variant.fields {
+            Fields::Named(fields) => {
+                for field in &fields.named {
+                    let fname = field.ident.as_ref().unwrap().to_string();
+                    let ty = &field.ty;
+                    let effective_ty = util::extract_option_inner(ty).unwrap_or(ty);
+                    field_merges.push(quote! {
+                        schemas.extend(<#effective_ty as ::tealeaf::convert::ToTeaLeaf>::collect_schemas());
+                        merge(&mut schema, #fname, <#effective_ty as ::tealeaf::convert::ToTeaLeaf>::tealeaf_field_type());
+                    });
+                }
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                // Newtype variant: its payload's fields sit alongside the tag
+                let ty = &fields.unnamed[0].ty;
+                field_merges.push(quote! {
+                    {
+                        let nested = <#ty as ::tealeaf::convert::ToTeaLeaf>::collect_schemas();
+                        let own = <#ty as ::tealeaf::convert::ToTeaLeaf>::tealeaf_field_type();
+                        if let Some(payload) = nested.get(&own.base) {
+                            for f in &payload.fields {
+                                merge(&mut schema, &f.name, f.field_type.clone());
+                            }
+                        }
+                        schemas.extend(nested);
+                    }
+                });
+            }
+            // Tuple variants are rejected by ToTeaLeaf; unit variants add no fields
+            Fields::Unnamed(_) | Fields::Unit => {}
+        }
+    }
+
+    Ok(quote! {
+        fn collect_schemas() -> ::tealeaf::IndexMap<String, ::tealeaf::Schema> {
+            ::tealeaf::convert::collect_once("schemas", ::std::any::type_name::<Self>(), || {
+                let mut schemas = ::tealeaf::IndexMap::new();
+                let mut schema = ::tealeaf::Schema::new(#schema_name);
+                schema.add_field(#tag, ::tealeaf::FieldType::new("string"));
+                let merge = |schema: &mut ::tealeaf::Schema, name: &str, ft: ::tealeaf::FieldType| {
+                    let ft = ft.nullable();
+                    match schema.fields.iter_mut().find(|f| f.name == name) {
+                        Some(existing) if existing.field_type != ft => {
+                            existing.field_type = ::tealeaf::FieldType::new("any").nullable();
+                        }
+                        Some(_) => {}
+                        None => schema.add_field(name, ft),
+                    }
+                };
+                #(#field_merges)*
+                schemas.insert(#schema_name.to_string(), schema);
+                schemas
+            })
+        }
+    })
+}
+
 /// Generate the `collect_unions()` method body.
 ///
 /// For enums: builds a `Union` with all variants and their fields.
 /// For structs: propagates `collect_unions()` from nested field types.
 pub fn generate_collect_unions(input: &DeriveInput) -> syn::Result<TokenStream> {
     let container_attrs = ContainerAttrs::from_attrs(&input.attrs)?;
+    let repr = container_attrs.enum_repr(input)?;
     let union_name = container_attrs
         .rename
         .unwrap_or_else(|| input.ident.to_string());
@@ -136,7 +207,8 @@ pub fn generate_collect_unions(input: &DeriveInput) -> syn::Result<TokenStream>
 
             for field in &fields.named {
                 let field_attrs = FieldAttrs::from_attrs(&field.attrs)?;
-                if field_attrs.skip {
+                // Bytes hold no unions, and need not implement ToTeaLeaf
+                if field_attrs.skip || field_attrs.type_override.as_deref() == Some("bytes") {
                     continue;
                 }
 
@@ -154,15 +226,25 @@ pub fn generate_collect_unions(input: &DeriveInput) -> syn::Result<TokenStream>
 
             Ok(quote! {
                 fn collect_unions() -> ::tealeaf::IndexMap<String, ::tealeaf::Union> {
-                    let mut unions = ::tealeaf::IndexMap::new();
-                    #(#nested_union_calls)*
-                    unions
+                    ::tealeaf::convert::collect_once("unions", ::std::any::type_name::<Self>(), || {
+                        let mut unions = ::tealeaf::IndexMap::new();
+                        #(#nested_union_calls)*
+                        unions
+                    })
                 }
             })
         }
-        Data::Enum(data) => {
+        Data::Enum(data) if matches!(repr, EnumRepr::External) => {
             generate_enum_collect_unions(&union_name, data)
         }
+        Data::Enum(_) => {
+            // Only tagged values are described by a union
+            Ok(quote! {
+                fn collect_unions() -> ::tealeaf::IndexMap<String, ::tealeaf::Union> {
+                    ::tealeaf::IndexMap::new()
+                }
+            })
+        }
         _ => Err(syn::Error::new_spanned(
             &input.ident,
             "ToTeaLeaf can only be derived for structs with named fields or enums",
@@ -174,7 +256,7 @@ fn generate_enum_collect_unions(union_name: &str, data: &DataEnum) -> syn::Resul
     let mut variant_builds = Vec::new();
 
     for variant in &data.variants {
-        let variant_name = variant.ident.to_string();
+        let variant_name = VariantAttrs::variant_name(variant)?;
 
         match &variant.fields {
             Fields::Named(fields) => {
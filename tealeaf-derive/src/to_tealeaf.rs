@@ -4,7 +4,7 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{Data, DataEnum, DataStruct, DeriveInput, Fields};
 
-use crate::attrs::{ContainerAttrs, FieldAttrs};
+use crate::attrs::{ContainerAttrs, EnumRepr, FieldAttrs, VariantAttrs};
 use crate::schema;
 use crate::util;
 
@@ -17,6 +17,12 @@ pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
         .clone()
         .unwrap_or_else(|| name.to_string());
 
+    let field_type_name = match container_attrs.enum_repr(input)? {
+        // No schema or union describes these layouts
+        EnumRepr::Adjacent { .. } | EnumRepr::Untagged => "any".to_string(),
+        EnumRepr::External | EnumRepr::Internal { .. } => schema_name,
+    };
+
     let to_value_body = generate_to_value(input)?;
     let collect_schemas_body = schema::generate_collect_schemas(input)?;
     let collect_unions_body = schema::generate_collect_unions(input)?;
@@ -32,7 +38,7 @@ pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
             #collect_unions_body
 
             fn tealeaf_field_type() -> ::tealeaf::FieldType {
-                ::tealeaf::FieldType::new(#schema_name)
+                ::tealeaf::FieldType::new(#field_type_name)
             }
         }
     })
@@ -100,6 +106,27 @@ fn generate_to_value(input: &DeriveInput) -> syn::Result<TokenStream> {
                                 });
                             }
                         }
+                        "bytes" => {
+                            // Anything that reads as a byte slice, not an array of ints
+                            if util::is_option_type(ty) {
+                                field_inserts.push(quote! {
+                                    obj.insert(
+                                        #field_name.to_string(),
+                                        match &self.#field_ident {
+                                            Some(v) => ::tealeaf::convert::bytes_to_value(v),
+                                            None => ::tealeaf::Value::Null,
+                                        },
+                                    );
+                                });
+                            } else {
+                                field_inserts.push(quote! {
+                                    obj.insert(
+                                        #field_name.to_string(),
+                                        ::tealeaf::convert::bytes_to_value(&self.#field_ident),
+                                    );
+                                });
+                            }
+                        }
                         _ => {
                             // Generic override: just use the standard conversion
                             field_inserts.push(quote! {
@@ -137,13 +164,15 @@ fn generate_to_value(input: &DeriveInput) -> syn::Result<TokenStream> {
 
 fn generate_enum_to_value(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream> {
     let name = &input.ident;
+    let repr = ContainerAttrs::from_attrs(&input.attrs)?.enum_repr(input)?;
     let mut match_arms = Vec::new();
 
     for variant in &data.variants {
         let variant_ident = &variant.ident;
-        let variant_name = variant_ident.to_string();
+        let variant_name = VariantAttrs::variant_name(variant)?;
 
-        match &variant.fields {
+        // Pattern binding the variant's fields, and the payload value built from them
+        let (pattern, payload) = match &variant.fields {
             Fields::Named(fields) => {
                 let field_idents: Vec<_> = fields
                     .named
@@ -155,9 +184,9 @@ fn generate_enum_to_value(input: &DeriveInput, data: &DataEnum) -> syn::Result<T
                     .iter()
                     .map(|f| f.ident.as_ref().unwrap().to_string())
                     .collect();
-
-                match_arms.push(quote! {
-                    #name::#variant_ident { #(ref #field_idents),* } => {
+                (
+                    quote! { #name::#variant_ident { #(ref #field_idents),* } },
+                    quote! {{
                         let mut obj = ::tealeaf::ObjectMap::new();
                         #(
                             obj.insert(
@@ -165,50 +194,80 @@ fn generate_enum_to_value(input: &DeriveInput, data: &DataEnum) -> syn::Result<T
                                 ::tealeaf::convert::ToTeaLeaf::to_tealeaf_value(#field_idents),
                             );
                         )*
-                        ::tealeaf::Value::Tagged(
-                            #variant_name.to_string(),
-                            Box::new(::tealeaf::Value::Object(obj)),
-                        )
-                    }
-                });
+                        ::tealeaf::Value::Object(obj)
+                    }},
+                )
             }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => (
+                quote! { #name::#variant_ident(ref inner) },
+                quote! { ::tealeaf::convert::ToTeaLeaf::to_tealeaf_value(inner) },
+            ),
             Fields::Unnamed(fields) => {
-                if fields.unnamed.len() == 1 {
-                    match_arms.push(quote! {
-                        #name::#variant_ident(ref inner) => {
-                            ::tealeaf::Value::Tagged(
-                                #variant_name.to_string(),
-                                Box::new(::tealeaf::convert::ToTeaLeaf::to_tealeaf_value(inner)),
-                            )
-                        }
-                    });
-                } else {
-                    let field_bindings: Vec<syn::Ident> = (0..fields.unnamed.len())
-                        .map(|i| syn::Ident::new(&format!("f{}", i), proc_macro2::Span::call_site()))
-                        .collect();
-                    match_arms.push(quote! {
-                        #name::#variant_ident(#(ref #field_bindings),*) => {
-                            ::tealeaf::Value::Tagged(
-                                #variant_name.to_string(),
-                                Box::new(::tealeaf::Value::Array(vec![
-                                    #(::tealeaf::convert::ToTeaLeaf::to_tealeaf_value(#field_bindings)),*
-                                ])),
-                            )
-                        }
-                    });
+                if matches!(repr, EnumRepr::Internal { .. }) {
+                    return Err(syn::Error::new_spanned(
+                        variant,
+                        "internally tagged enums cannot contain tuple variants",
+                    ));
                 }
+                let field_bindings: Vec<syn::Ident> = (0..fields.unnamed.len())
+                    .map(|i| syn::Ident::new(&format!("f{}", i), proc_macro2::Span::call_site()))
+                    .collect();
+                (
+                    quote! { #name::#variant_ident(#(ref #field_bindings),*) },
+                    quote! {
+                        ::tealeaf::Value::Array(vec![
+                            #(::tealeaf::convert::ToTeaLeaf::to_tealeaf_value(#field_bindings)),*
+                        ])
+                    },
+                )
             }
-            Fields::Unit => {
-                match_arms.push(quote! {
-                    #name::#variant_ident => {
-                        ::tealeaf::Value::Tagged(
-                            #variant_name.to_string(),
-                            Box::new(::tealeaf::Value::Null),
-                        )
+            Fields::Unit => (quote! { #name::#variant_ident }, quote! { ::tealeaf::Value::Null }),
+        };
+
+        let is_unit = matches!(variant.fields, Fields::Unit);
+        let body = match &repr {
+            EnumRepr::External => quote! {
+                ::tealeaf::Value::Tagged(#variant_name.to_string(), Box::new(#payload))
+            },
+            EnumRepr::Internal { tag } if is_unit => quote! {{
+                let mut obj = ::tealeaf::ObjectMap::new();
+                obj.insert(#tag.to_string(), ::tealeaf::Value::String(#variant_name.to_string()));
+                ::tealeaf::Value::Object(obj)
+            }},
+            // Payloads that are not objects cannot carry the tag field, so they
+            // keep the default tagged-value layout
+            EnumRepr::Internal { tag } => quote! {
+                match #payload {
+                    ::tealeaf::Value::Object(fields) => {
+                        let mut obj = ::tealeaf::ObjectMap::new();
+                        obj.insert(#tag.to_string(), ::tealeaf::Value::String(#variant_name.to_string()));
+                        for (k, v) in fields {
+                            obj.insert(k, v);
+                        }
+                        ::tealeaf::Value::Object(obj)
                     }
-                });
+                    other => ::tealeaf::Value::Tagged(#variant_name.to_string(), Box::new(other)),
+                }
+            },
+            EnumRepr::Adjacent { tag, content } => {
+                let content_insert = if is_unit {
+                    quote! {}
+                } else {
+                    quote! { obj.insert(#content.to_string(), #payload); }
+                };
+                quote! {{
+                    let mut obj = ::tealeaf::ObjectMap::new();
+                    obj.insert(#tag.to_string(), ::tealeaf::Value::String(#variant_name.to_string()));
+                    #content_insert
+                    ::tealeaf::Value::Object(obj)
+                }}
             }
-        }
+            EnumRepr::Untagged => payload,
+        };
+
+        match_arms.push(quote! {
+            #pattern => #body,
+        });
     }
 
     Ok(quote! {
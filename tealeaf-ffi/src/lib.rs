@@ -562,8 +562,9 @@ pub unsafe extern "C" fn tl_value_type(value: *const TLValue) -> TLValueType {
         Value::Map(_) => TLValueType::Map,
         Value::Ref(_) => TLValueType::Ref,
         Value::Tagged(_, _) => TLValueType::Tagged,
-        Value::Timestamp(_, _) => TLValueType::Timestamp,
+        Value::Timestamp(_, _) | Value::TimestampNanos(_, _, _) => TLValueType::Timestamp,
         Value::JsonNumber(_) => TLValueType::String,
+        Value::Secret(_) => TLValueType::String,
     }
 }
 
@@ -645,6 +646,10 @@ pub unsafe extern "C" fn tl_value_as_string(value: *const TLValue) -> *mut c_cha
     if value.is_null() {
         return ptr::null_mut();
     }
+    // Secrets are never exposed across the FFI, only their mask
+    if let Value::Secret(_) = &(*value).inner {
+        return CString::new(tealeaf::secret::REDACTED).map(|c| c.into_raw()).unwrap_or(ptr::null_mut());
+    }
     match (*value).inner.as_str() {
         Some(s) => CString::new(s).map(|c| c.into_raw()).unwrap_or(ptr::null_mut()),
         None => ptr::null_mut(),
@@ -1321,6 +1326,77 @@ pub unsafe extern "C" fn tl_reader_schema_name(
     }
 }
 
+/// Get the index of the schema `name`, or -1 if the file has no such schema.
+/// Encoded structs and table sections refer to their schema by this index.
+///
+/// # Safety
+///
+/// `reader` must be a valid `TLReader` pointer or null. `name` must be a valid,
+/// null-terminated C string or null.
+#[no_mangle]
+pub unsafe extern "C" fn tl_reader_schema_index(reader: *const TLReader, name: *const c_char) -> i64 {
+    if reader.is_null() || name.is_null() {
+        return -1;
+    }
+    match CStr::from_ptr(name).to_str() {
+        Ok(name) => (*reader).inner.schema_index(name).map_or(-1, |i| i as i64),
+        Err(_) => -1,
+    }
+}
+
+/// Get the number of unions in a binary file.
+///
+/// # Safety
+///
+/// `reader` must be a valid `TLReader` pointer or null.
+#[no_mangle]
+pub unsafe extern "C" fn tl_reader_union_count(reader: *const TLReader) -> usize {
+    if reader.is_null() {
+        return 0;
+    }
+    (*reader).inner.unions.len()
+}
+
+/// Get a union name by index.
+/// Caller must free the returned string with tl_string_free.
+///
+/// # Safety
+///
+/// `reader` must be a valid `TLReader` pointer or null.
+#[no_mangle]
+pub unsafe extern "C" fn tl_reader_union_name(
+    reader: *const TLReader,
+    index: usize,
+) -> *mut c_char {
+    if reader.is_null() {
+        return ptr::null_mut();
+    }
+    let unions = &(*reader).inner.unions;
+    match unions.get(index) {
+        Some(union) => CString::new(union.name.as_str())
+            .map(|s| s.into_raw())
+            .unwrap_or(ptr::null_mut()),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Get the index of the union `name`, or -1 if the file has no such union.
+///
+/// # Safety
+///
+/// `reader` must be a valid `TLReader` pointer or null. `name` must be a valid,
+/// null-terminated C string or null.
+#[no_mangle]
+pub unsafe extern "C" fn tl_reader_union_index(reader: *const TLReader, name: *const c_char) -> i64 {
+    if reader.is_null() || name.is_null() {
+        return -1;
+    }
+    match CStr::from_ptr(name).to_str() {
+        Ok(name) => (*reader).inner.union_index(name).map_or(-1, |i| i as i64),
+        Err(_) => -1,
+    }
+}
+
 /// Get the number of fields in a schema.
 ///
 /// # Safety
@@ -1855,6 +1931,10 @@ headers: @map {"Content-Type": "application/json", "Accept": "*/*"}
         unsafe {
             assert_eq!(tl_reader_schema_count(ptr::null()), 0);
             assert!(tl_reader_schema_name(ptr::null(), 0).is_null());
+            assert_eq!(tl_reader_schema_index(ptr::null(), ptr::null()), -1);
+            assert_eq!(tl_reader_union_count(ptr::null()), 0);
+            assert!(tl_reader_union_name(ptr::null(), 0).is_null());
+            assert_eq!(tl_reader_union_index(ptr::null(), ptr::null()), -1);
             assert_eq!(tl_reader_schema_field_count(ptr::null(), 0), 0);
             assert!(tl_reader_schema_field_name(ptr::null(), 0, 0).is_null());
             assert!(tl_reader_schema_field_type(ptr::null(), 0, 0).is_null());
@@ -2512,6 +2592,15 @@ headers: @map {"Content-Type": "application/json", "Accept": "*/*"}
             assert_eq!(f0_type, "string");
             assert_eq!(f1_type, "int");
 
+            // Indices by name
+            let person = CString::new("Person").unwrap();
+            let missing = CString::new("Missing").unwrap();
+            assert_eq!(tl_reader_schema_index(reader, person.as_ptr()), 0);
+            assert_eq!(tl_reader_schema_index(reader, missing.as_ptr()), -1);
+            assert_eq!(tl_reader_union_index(reader, person.as_ptr()), -1);
+            assert_eq!(tl_reader_union_count(reader), 0);
+            assert!(tl_reader_union_name(reader, 0).is_null());
+
             tl_reader_free(reader);
             tl_document_free(doc);
         }
@@ -642,6 +642,46 @@ uintptr_t tl_reader_schema_count(const struct TLReader *reader);
  */
 char *tl_reader_schema_name(const struct TLReader *reader, uintptr_t index);
 
+/**
+ * Get the index of the schema `name`, or -1 if the file has no such schema.
+ * Encoded structs and table sections refer to their schema by this index.
+ *
+ * # Safety
+ *
+ * `reader` must be a valid `TLReader` pointer or null. `name` must be a valid,
+ * null-terminated C string or null.
+ */
+int64_t tl_reader_schema_index(const struct TLReader *reader, const char *name);
+
+/**
+ * Get the number of unions in a binary file.
+ *
+ * # Safety
+ *
+ * `reader` must be a valid `TLReader` pointer or null.
+ */
+uintptr_t tl_reader_union_count(const struct TLReader *reader);
+
+/**
+ * Get a union name by index.
+ * Caller must free the returned string with tl_string_free.
+ *
+ * # Safety
+ *
+ * `reader` must be a valid `TLReader` pointer or null.
+ */
+char *tl_reader_union_name(const struct TLReader *reader, uintptr_t index);
+
+/**
+ * Get the index of the union `name`, or -1 if the file has no such union.
+ *
+ * # Safety
+ *
+ * `reader` must be a valid `TLReader` pointer or null. `name` must be a valid,
+ * null-terminated C string or null.
+ */
+int64_t tl_reader_union_index(const struct TLReader *reader, const char *name);
+
 /**
  * Get the number of fields in a schema.
  *